@@ -18,6 +18,21 @@ import {
 } from '../assets/generated/wasm/gossip_wasm';
 import { UserProfile } from '../db';
 
+/**
+ * Get the code of an error thrown by the WASM session manager
+ * (e.g. `ANNOUNCEMENT_TOO_OLD` or `SESSION_SATURATED`), if any
+ */
+export function getSessionErrorCode(error: unknown): string | undefined {
+  if (
+    error instanceof Error &&
+    'code' in error &&
+    typeof error.code === 'string'
+  ) {
+    return error.code;
+  }
+  return undefined;
+}
+
 export class SessionModule {
   private sessionManager: SessionManagerWrapper | null = null;
   private onPersist?: () => void; // Callback for automatic persistence
@@ -105,7 +120,7 @@ export class SessionModule {
 
   /**
    * Feed an incoming announcement into the session manager
-   * @returns AnnouncementResult containing the announcer's public keys, timestamp, and user data, or undefined if invalid
+   * @returns AnnouncementResult containing the announcer's public keys, timestamp, and user data, or undefined if rejected
    */
   feedIncomingAnnouncement(
     announcementBytes: Uint8Array,
@@ -116,15 +131,23 @@ export class SessionModule {
       throw new Error('Session manager is not initialized');
     }
 
-    const result = this.sessionManager.feed_incoming_announcement(
-      announcementBytes,
-      ourPk,
-      ourSk
-    );
-
-    if (result) {
-      this.persistIfNeeded();
+    let result: AnnouncementResult;
+    try {
+      result = this.sessionManager.feed_incoming_announcement(
+        announcementBytes,
+        ourPk,
+        ourSk
+      );
+    } catch (error) {
+      // Announcements meant for someone else are expected on a shared board
+      const code = getSessionErrorCode(error);
+      if (code !== 'ANNOUNCEMENT_UNDECRYPTABLE') {
+        console.warn('Announcement rejected:', code ?? error);
+      }
+      return undefined;
     }
+
+    this.persistIfNeeded();
     return result;
   }
 
@@ -141,6 +164,8 @@ export class SessionModule {
 
   /**
    * Process an incoming ciphertext from the message board
+   * @returns The received message, or undefined if no session expects this seeker
+   * @throws An error with a `code` (see getSessionErrorCode) if the message is rejected
    */
   feedIncomingMessageBoardRead(
     seeker: Uint8Array,
@@ -151,18 +176,26 @@ export class SessionModule {
       throw new Error('Session manager is not initialized');
     }
 
-    const result = this.sessionManager.feed_incoming_message_board_read(
-      seeker,
-      ciphertext,
-      ourSk
-    );
-
-    this.persistIfNeeded();
-    return result;
+    try {
+      return this.sessionManager.feed_incoming_message_board_read(
+        seeker,
+        ciphertext,
+        ourSk
+      );
+    } catch (error) {
+      if (getSessionErrorCode(error) === 'UNKNOWN_SEEKER') {
+        return undefined;
+      }
+      throw error;
+    } finally {
+      // a rejected message closes its session, which must be persisted too
+      this.persistIfNeeded();
+    }
   }
 
   /**
    * Send a message to a peer
+   * @throws An error with a `code` (see getSessionErrorCode) if there is no usable session
    */
   sendMessage(
     peerId: Uint8Array,
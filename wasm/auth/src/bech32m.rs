@@ -0,0 +1,288 @@
+//! Bech32m encoding (BIP-350) of byte strings.
+//!
+//! A bech32m string is made of a human-readable prefix, the separator `1`, and the data
+//! encoded in a 32-character alphabet, followed by a 6-character checksum. The checksum
+//! detects any error affecting up to 4 characters, and the alphabet avoids characters that
+//! are easily confused (`1`, `b`, `i`, `o`).
+
+use std::fmt;
+
+/// Alphabet of the data part.
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+/// Constant the checksum of bech32m strings is xored with.
+const BECH32M_CONST: u32 = 0x2bc8_30a3;
+
+/// Number of characters of the checksum.
+const CHECKSUM_SIZE: usize = 6;
+
+/// Maximum length of a bech32m string.
+const MAX_LENGTH: usize = 90;
+
+/// Error decoding a bech32m string.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Bech32mError {
+    /// The string is too long, mixes upper and lower case, has no separator, or contains
+    /// characters outside of the alphabet.
+    Malformed,
+    /// The checksum does not match: the string was mistyped or corrupted.
+    ChecksumMismatch,
+    /// The data part does not encode whole bytes.
+    InvalidPadding,
+}
+
+impl fmt::Display for Bech32mError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Malformed => write!(f, "invalid bech32m format"),
+            Self::ChecksumMismatch => write!(f, "invalid bech32m checksum"),
+            Self::InvalidPadding => write!(f, "invalid bech32m padding"),
+        }
+    }
+}
+
+impl std::error::Error for Bech32mError {}
+
+fn polymod(values: impl IntoIterator<Item = u8>) -> u32 {
+    const GENERATOR: [u32; 5] = [
+        0x3b6a_57b2,
+        0x2650_8e6d,
+        0x1ea1_19fa,
+        0x3d42_33dd,
+        0x2a14_62b3,
+    ];
+    let mut checksum = 1u32;
+    for value in values {
+        let top = checksum >> 25;
+        checksum = ((checksum & 0x01ff_ffff) << 5) ^ u32::from(value);
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                checksum ^= generator;
+            }
+        }
+    }
+    checksum
+}
+
+/// Expands the prefix into the values fed to the checksum.
+fn expand_hrp(hrp: &[u8]) -> impl Iterator<Item = u8> + '_ {
+    hrp.iter()
+        .map(|c| c >> 5)
+        .chain(std::iter::once(0))
+        .chain(hrp.iter().map(|c| c & 0x1f))
+}
+
+/// Regroups bits from `from`-bit groups to `to`-bit groups.
+///
+/// When `pad` is set, the last group is padded with zeros. Otherwise, returns `None` if
+/// the input has more than `from - 1` leftover bits or non-zero leftover bits.
+fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> Option<Vec<u8>> {
+    let mut accumulator = 0u32;
+    let mut bits = 0u32;
+    let max_value = (1u32 << to) - 1;
+    let mut converted = Vec::with_capacity(data.len() * from as usize / to as usize + 1);
+    for value in data {
+        accumulator = (accumulator << from) | u32::from(*value);
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            converted.push(((accumulator >> bits) & max_value) as u8);
+        }
+    }
+    if pad {
+        if bits > 0 {
+            converted.push(((accumulator << (to - bits)) & max_value) as u8);
+        }
+    } else if bits >= from || (accumulator << (to - bits)) & max_value != 0 {
+        return None;
+    }
+    Some(converted)
+}
+
+/// Computes the checksum values of a prefix and 5-bit data values.
+fn create_checksum(hrp: &[u8], values: &[u8]) -> [u8; CHECKSUM_SIZE] {
+    let checksum = polymod(
+        expand_hrp(hrp)
+            .chain(values.iter().copied())
+            .chain([0; CHECKSUM_SIZE]),
+    ) ^ BECH32M_CONST;
+    std::array::from_fn(|i| ((checksum >> (5 * (CHECKSUM_SIZE - 1 - i))) & 0x1f) as u8)
+}
+
+/// Encodes a prefix and 5-bit data values, appending their checksum.
+fn encode_values(hrp: &str, values: &[u8]) -> String {
+    let checksum = create_checksum(hrp.as_bytes(), values);
+    let mut encoded = String::with_capacity(hrp.len() + 1 + values.len() + CHECKSUM_SIZE);
+    encoded.push_str(hrp);
+    encoded.push('1');
+    encoded.extend(
+        values
+            .iter()
+            .chain(checksum.iter())
+            .map(|v| char::from(CHARSET[usize::from(*v)])),
+    );
+    encoded
+}
+
+/// Encodes `data` under the lowercase prefix `hrp`.
+pub(crate) fn encode(hrp: &str, data: &[u8]) -> String {
+    let values = convert_bits(data, 8, 5, true).expect("Padded conversion never fails");
+    encode_values(hrp, &values)
+}
+
+/// Verifies a bech32m string and returns its lowercase prefix and its 5-bit data values,
+/// without the checksum.
+fn decode_values(encoded: &str) -> Result<(String, Vec<u8>), Bech32mError> {
+    // check length, characters and case
+    if encoded.len() > MAX_LENGTH || !encoded.bytes().all(|c| (33..=126).contains(&c)) {
+        return Err(Bech32mError::Malformed);
+    }
+    let has_lower = encoded.bytes().any(|c| c.is_ascii_lowercase());
+    let has_upper = encoded.bytes().any(|c| c.is_ascii_uppercase());
+    if has_lower && has_upper {
+        return Err(Bech32mError::Malformed);
+    }
+    let encoded = encoded.to_ascii_lowercase();
+
+    // split at the last separator
+    let (hrp, data_part) = encoded.rsplit_once('1').ok_or(Bech32mError::Malformed)?;
+    if hrp.is_empty() || data_part.len() < CHECKSUM_SIZE {
+        return Err(Bech32mError::Malformed);
+    }
+    let mut values = data_part
+        .bytes()
+        .map(|c| {
+            CHARSET
+                .iter()
+                .position(|&d| d == c)
+                .map(|v| v as u8)
+                .ok_or(Bech32mError::Malformed)
+        })
+        .collect::<Result<Vec<u8>, _>>()?;
+
+    // verify checksum
+    if polymod(expand_hrp(hrp.as_bytes()).chain(values.iter().copied())) != BECH32M_CONST {
+        return Err(Bech32mError::ChecksumMismatch);
+    }
+    values.truncate(values.len() - CHECKSUM_SIZE);
+    Ok((hrp.to_string(), values))
+}
+
+/// Decodes a bech32m string into its lowercase prefix and its data.
+///
+/// Both all-lowercase and all-uppercase strings are accepted.
+pub(crate) fn decode(encoded: &str) -> Result<(String, Vec<u8>), Bech32mError> {
+    let (hrp, values) = decode_values(encoded)?;
+    let data = convert_bits(&values, 5, 8, false).ok_or(Bech32mError::InvalidPadding)?;
+    Ok((hrp, data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bip350_valid_strings() {
+        for valid in [
+            "A1LQFN3A",
+            "a1lqfn3a",
+            "an83characterlonghumanreadablepartthatcontainsthetheexcludedcharactersbioandnumber11sg7hg6",
+            "abcdef1l7aum6echk45nj3s0wdvt2fg8x9yrzpqzd3ryx",
+            "11llllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllludsr8",
+            "split1checkupstagehandshakeupstreamerranterredcaperredlc445v",
+            "?1v759aa",
+        ] {
+            let (hrp, _) = decode_values(valid).unwrap_or_else(|e| panic!("{valid}: {e}"));
+            assert_eq!(hrp, valid.rsplit_once('1').unwrap().0.to_ascii_lowercase());
+        }
+    }
+
+    #[test]
+    fn test_bip350_invalid_strings() {
+        for (invalid, error) in [
+            // hrp character out of range
+            ("\u{20}1xj0phk", Bech32mError::Malformed),
+            ("\u{7f}1g6xzxy", Bech32mError::Malformed),
+            // overall max length exceeded
+            (
+                "an84characterslonghumanreadablepartthatcontainsthenumber1andtheexcludedcharactersbio1569pvx",
+                Bech32mError::Malformed,
+            ),
+            // no separator
+            ("qyrz8wqd2c9m", Bech32mError::Malformed),
+            // empty hrp
+            ("1qyrz8wqd2c9m", Bech32mError::Malformed),
+            ("16plkw9", Bech32mError::Malformed),
+            ("1p2gdwpf", Bech32mError::Malformed),
+            // invalid data character
+            ("y1b0jsk6g", Bech32mError::Malformed),
+            ("lt1igcx5c0", Bech32mError::Malformed),
+            // too short checksum
+            ("in1muywd", Bech32mError::Malformed),
+            // invalid character in checksum
+            ("mm1crxm3i", Bech32mError::Malformed),
+            ("au1s5cgom", Bech32mError::Malformed),
+            // checksum calculated with uppercase form of hrp
+            ("M1VUXWEZ", Bech32mError::ChecksumMismatch),
+        ] {
+            assert_eq!(decode_values(invalid).err(), Some(error), "{invalid:?}");
+        }
+    }
+
+    #[test]
+    fn test_bech32_checksum_rejected() {
+        // valid bech32 (BIP-173) string, whose checksum differs from bech32m
+        assert_eq!(
+            decode("abcdef1qpzry9x8gf2tvdw0s3jn54khce6mua7lmqqqxw").err(),
+            Some(Bech32mError::ChecksumMismatch)
+        );
+    }
+
+    #[test]
+    fn test_invalid_padding() {
+        // 4 values: 20 bits, of which the 4 leftover bits are not zero
+        let encoded = encode_values("test", &[0, 0, 0, 1]);
+        assert!(decode_values(&encoded).is_ok());
+        assert_eq!(decode(&encoded).err(), Some(Bech32mError::InvalidPadding));
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        for data in [
+            &[][..],
+            &[0],
+            &[0xff; 5],
+            &[1, 2, 3, 4, 5, 6, 7],
+            &[0xab; 32],
+        ] {
+            let encoded = encode("test", data);
+            assert_eq!(
+                decode(&encoded).unwrap(),
+                ("test".to_string(), data.to_vec())
+            );
+            assert_eq!(
+                decode(&encoded.to_ascii_uppercase()).unwrap(),
+                ("test".to_string(), data.to_vec())
+            );
+        }
+    }
+
+    #[test]
+    fn test_single_character_error_detected() {
+        let encoded = encode("test", &[0x42; 32]);
+        for position in 5..encoded.len() {
+            let mut corrupted = encoded.clone().into_bytes();
+            corrupted[position] = if corrupted[position] == b'q' {
+                b'p'
+            } else {
+                b'q'
+            };
+            let corrupted = String::from_utf8(corrupted).unwrap();
+            assert_eq!(
+                decode(&corrupted).err(),
+                Some(Bech32mError::ChecksumMismatch),
+                "{corrupted}"
+            );
+        }
+    }
+}
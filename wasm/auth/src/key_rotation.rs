@@ -0,0 +1,371 @@
+//! Key rotation certificates for long-term user keys.
+//!
+//! A [`UserId`] is derived from the user's public keys, so replacing the keys changes the ID.
+//! To rotate their keys without losing their contacts, a user signs a [`RotationCertificate`]
+//! with their old keys, binding their new [`UserPublicKeys`]. Like [`AuthBlob`](crate::AuthBlob),
+//! the certificate carries both a DSA and a Massa signature. Peers who know the old keys verify
+//! the certificate and follow the user to the new ID.
+//!
+//! Successive rotations form a chain of certificates, each one signed by the keys certified by
+//! the previous one. Peers that missed some rotations check the whole chain with
+//! [`verify_rotation_chain`].
+//!
+//! # Example
+//!
+//! ```ignore
+//! use auth::{RotationCertificate, derive_rotated_keys_from_static_root_secret, verify_rotation_chain};
+//!
+//! // Alice moves to the next generation of keys, recoverable from her passphrase
+//! let (new_pk, new_sk) = derive_rotated_keys_from_static_root_secret(&root_secret, 1);
+//! let certificate = RotationCertificate::new(old_pk.clone(), &old_sk, new_pk.clone());
+//!
+//! // Bob, who knows her old keys, follows her to her new ID
+//! let chain = [certificate];
+//! assert_eq!(
+//!     verify_rotation_chain(&old_pk, &chain).map(|keys| keys.derive_id()),
+//!     Some(new_pk.derive_id())
+//! );
+//! ```
+
+use crate::passphrase_salt::PassphraseSalt;
+use crate::types::{
+    StaticRootSecret, UserId, UserPublicKeys, UserSecretKeys, derive_keys_from_static_root_secret,
+};
+use serde::{Deserialize, Serialize};
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+/// A certificate, signed with a user's old keys, binding their new public keys.
+#[derive(Zeroize, ZeroizeOnDrop, Serialize, Deserialize)]
+pub struct RotationCertificate {
+    /// The public keys being replaced, which signed the certificate.
+    old_public_keys: UserPublicKeys,
+    /// The public keys replacing them.
+    new_public_keys: UserPublicKeys,
+    /// DSA signature of the old keys over the derived signing material.
+    signature_dsa: crypto_dsa::Signature,
+    /// Massa signature of the old keys over the derived signing material.
+    #[zeroize(skip)] // TODO: add zeroization to massa signature
+    signature_massa: massa_signature::Signature,
+}
+
+impl RotationCertificate {
+    /// Derives the DSA and Massa signing messages binding the old ID to the new keys.
+    fn derive_signing_material(
+        old_id: &UserId,
+        new_public_keys: &UserPublicKeys,
+    ) -> (Zeroizing<[u8; 32]>, Zeroizing<[u8; 32]>) {
+        let mut kdf = crypto_kdf::Extract::new(b"auth.key_rotation.kdf.salt------");
+        kdf.input_item(old_id.as_bytes());
+        kdf.input_item(&new_public_keys.to_bytes());
+        let expander = kdf.finalize();
+
+        let mut signature_dsa_message = Zeroizing::new([0u8; 32]);
+        expander.expand(
+            b"auth.key_rotation.kdf.signature_dsa_message",
+            signature_dsa_message.as_mut_slice(),
+        );
+        let mut signature_massa_message = Zeroizing::new([0u8; 32]);
+        expander.expand(
+            b"auth.key_rotation.kdf.signature_massa_message",
+            signature_massa_message.as_mut_slice(),
+        );
+
+        (signature_dsa_message, signature_massa_message)
+    }
+
+    /// Creates a certificate rotating from `old_public_keys` to `new_public_keys`,
+    /// signed with `old_secret_keys`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the Massa signature fails (should never happen in practice).
+    #[must_use]
+    pub fn new(
+        old_public_keys: UserPublicKeys,
+        old_secret_keys: &UserSecretKeys,
+        new_public_keys: UserPublicKeys,
+    ) -> Self {
+        let (signature_dsa_message, signature_massa_message) =
+            Self::derive_signing_material(&old_public_keys.derive_id(), &new_public_keys);
+
+        // Sign with DSA using fresh randomness
+        let mut signature_dsa_randomness = [0u8; crypto_dsa::SIGNING_RANDOMNESS_SIZE];
+        crypto_rng::fill_buffer(&mut signature_dsa_randomness);
+        let signature_dsa = crypto_dsa::sign(
+            &old_secret_keys.dsa_signing_key,
+            signature_dsa_message.as_slice(),
+            b"auth.key_rotation.sign.signature_dsa_message",
+            signature_dsa_randomness,
+        );
+
+        // Sign with Massa
+        let massa_hash = massa_hash::Hash::from_bytes(&signature_massa_message);
+        let signature_massa = old_secret_keys
+            .massa_keypair
+            .sign(&massa_hash)
+            .expect("Failed to sign with Massa");
+
+        Self {
+            old_public_keys,
+            new_public_keys,
+            signature_dsa,
+            signature_massa,
+        }
+    }
+
+    /// Verifies that the certificate is signed by its old keys, and rotates to another ID.
+    #[must_use]
+    pub fn verify(&self) -> bool {
+        let old_id = self.old_id();
+        if self.new_id() == old_id {
+            return false;
+        }
+
+        let (signature_dsa_message, signature_massa_message) =
+            Self::derive_signing_material(&old_id, &self.new_public_keys);
+
+        // Verify DSA signature
+        let is_valid_dsa = crypto_dsa::verify(
+            &self.old_public_keys.dsa_verification_key,
+            signature_dsa_message.as_slice(),
+            b"auth.key_rotation.sign.signature_dsa_message",
+            &self.signature_dsa,
+        );
+        if !is_valid_dsa {
+            return false;
+        }
+
+        // Verify Massa signature
+        let massa_hash = massa_hash::Hash::from_bytes(&signature_massa_message);
+        self.old_public_keys
+            .massa_public_key
+            .verify_signature(&massa_hash, &self.signature_massa)
+            .is_ok()
+    }
+
+    /// Returns the public keys being replaced.
+    #[must_use]
+    pub const fn old_public_keys(&self) -> &UserPublicKeys {
+        &self.old_public_keys
+    }
+
+    /// Returns the public keys replacing them.
+    #[must_use]
+    pub const fn new_public_keys(&self) -> &UserPublicKeys {
+        &self.new_public_keys
+    }
+
+    /// Returns the user ID before the rotation.
+    #[must_use]
+    pub fn old_id(&self) -> UserId {
+        self.old_public_keys.derive_id()
+    }
+
+    /// Returns the user ID after the rotation.
+    #[must_use]
+    pub fn new_id(&self) -> UserId {
+        self.new_public_keys.derive_id()
+    }
+
+    /// Serializes the certificate to bytes using bincode.
+    ///
+    /// # Panics
+    ///
+    /// Panics if serialization fails (should never happen in practice).
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        bincode::serde::encode_to_vec(self, bincode::config::standard())
+            .expect("Failed to serialize RotationCertificate")
+    }
+
+    /// Deserializes a certificate from bytes using bincode. The certificate is not verified.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, bincode::error::DecodeError> {
+        bincode::serde::decode_from_slice(bytes, bincode::config::standard())
+            .map(|(result, _)| result)
+    }
+}
+
+/// Verifies a chain of rotation certificates starting from keys we know.
+///
+/// The first certificate must rotate away from `known_public_keys`, and each following one from
+/// the keys certified by the previous one. Every certificate must be valid.
+///
+/// # Returns
+///
+/// The latest public keys of the user, or `None` if the chain is empty, broken, or contains an
+/// invalid certificate.
+#[must_use]
+pub fn verify_rotation_chain<'a>(
+    known_public_keys: &UserPublicKeys,
+    chain: &'a [RotationCertificate],
+) -> Option<&'a UserPublicKeys> {
+    let mut current_id = known_public_keys.derive_id();
+    for certificate in chain {
+        if certificate.old_id() != current_id || !certificate.verify() {
+            return None;
+        }
+        current_id = certificate.new_id();
+    }
+    chain.last().map(RotationCertificate::new_public_keys)
+}
+
+/// Moves an account created with the fixed passphrase salt to a salted root secret.
+///
+/// Derives the keys of the account from `passphrase` with the fixed salt, and the new keys with
+/// `salt`, and certifies the new keys with the old ones. The certificate is sent to peers like
+/// any rotation, and the old secret keys are kept until they have followed.
+///
+/// # Returns
+///
+/// The new public and secret keys, the old secret keys, and the rotation certificate.
+#[must_use]
+pub fn migrate_to_salted_passphrase(
+    passphrase: &[u8],
+    salt: &PassphraseSalt,
+) -> (
+    UserPublicKeys,
+    UserSecretKeys,
+    UserSecretKeys,
+    RotationCertificate,
+) {
+    let (old_pk, old_sk) =
+        derive_keys_from_static_root_secret(&StaticRootSecret::from_passphrase(passphrase));
+    let (new_pk, new_sk) = derive_keys_from_static_root_secret(
+        &StaticRootSecret::from_salted_passphrase(passphrase, salt),
+    );
+    let certificate = RotationCertificate::new(old_pk, &old_sk, new_pk.clone());
+    (new_pk, new_sk, old_sk, certificate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::derive_rotated_keys_from_static_root_secret;
+
+    fn generation_keys(generation: u32) -> (UserPublicKeys, UserSecretKeys) {
+        let root_secret = StaticRootSecret::from_bytes([7u8; crate::STATIC_ROOT_SECRET_SIZE]);
+        derive_rotated_keys_from_static_root_secret(&root_secret, generation)
+    }
+
+    #[test]
+    fn test_certificate_verification() {
+        let (old_pk, old_sk) = generation_keys(0);
+        let (new_pk, _) = generation_keys(1);
+
+        let certificate = RotationCertificate::new(old_pk.clone(), &old_sk, new_pk.clone());
+        assert!(certificate.verify());
+        assert_eq!(certificate.old_id(), old_pk.derive_id());
+        assert_eq!(certificate.new_id(), new_pk.derive_id());
+    }
+
+    #[test]
+    fn test_certificate_requires_old_keys() {
+        let (old_pk, _) = generation_keys(0);
+        let (new_pk, new_sk) = generation_keys(1);
+
+        // signed with the new keys instead of the old ones
+        let certificate = RotationCertificate::new(old_pk, &new_sk, new_pk);
+        assert!(!certificate.verify());
+    }
+
+    #[test]
+    fn test_certificate_binds_new_keys() {
+        let (old_pk, old_sk) = generation_keys(0);
+        let (new_pk, _) = generation_keys(1);
+        let (other_pk, _) = generation_keys(2);
+
+        let mut certificate = RotationCertificate::new(old_pk, &old_sk, new_pk);
+        certificate.new_public_keys = other_pk;
+        assert!(!certificate.verify());
+    }
+
+    #[test]
+    fn test_certificate_to_same_keys_rejected() {
+        let (old_pk, old_sk) = generation_keys(0);
+
+        let certificate = RotationCertificate::new(old_pk.clone(), &old_sk, old_pk);
+        assert!(!certificate.verify());
+    }
+
+    #[test]
+    fn test_certificate_serialization() {
+        let (old_pk, old_sk) = generation_keys(0);
+        let (new_pk, _) = generation_keys(1);
+
+        let bytes = RotationCertificate::new(old_pk, &old_sk, new_pk.clone()).to_bytes();
+        let certificate = RotationCertificate::from_bytes(&bytes).unwrap();
+        assert!(certificate.verify());
+        assert_eq!(certificate.new_id(), new_pk.derive_id());
+
+        assert!(RotationCertificate::from_bytes(&bytes[..bytes.len() / 2]).is_err());
+    }
+
+    #[test]
+    fn test_rotation_chain() {
+        let (pk0, sk0) = generation_keys(0);
+        let (pk1, sk1) = generation_keys(1);
+        let (pk2, _) = generation_keys(2);
+
+        let chain = [
+            RotationCertificate::new(pk0.clone(), &sk0, pk1.clone()),
+            RotationCertificate::new(pk1.clone(), &sk1, pk2.clone()),
+        ];
+
+        // from the first keys, or from the middle of the chain
+        assert_eq!(
+            verify_rotation_chain(&pk0, &chain).map(UserPublicKeys::derive_id),
+            Some(pk2.derive_id())
+        );
+        assert_eq!(
+            verify_rotation_chain(&pk1, &chain[1..]).map(UserPublicKeys::derive_id),
+            Some(pk2.derive_id())
+        );
+
+        // not starting from the known keys
+        assert!(verify_rotation_chain(&pk1, &chain).is_none());
+
+        // broken chain
+        let reversed = [
+            RotationCertificate::new(pk1.clone(), &sk1, pk2),
+            RotationCertificate::new(pk0.clone(), &sk0, pk1),
+        ];
+        assert!(verify_rotation_chain(&pk0, &reversed).is_none());
+
+        // empty chain
+        assert!(verify_rotation_chain(&pk0, &[]).is_none());
+    }
+
+    #[test]
+    fn test_rotation_chain_rejects_forged_link() {
+        let (pk0, sk0) = generation_keys(0);
+        let (pk1, _) = generation_keys(1);
+        let (pk2, _) = generation_keys(2);
+        let (_, mallory_sk) = generation_keys(3);
+
+        // the second certificate is not signed by the keys certified by the first one
+        let chain = [
+            RotationCertificate::new(pk0.clone(), &sk0, pk1.clone()),
+            RotationCertificate::new(pk1, &mallory_sk, pk2),
+        ];
+        assert!(verify_rotation_chain(&pk0, &chain).is_none());
+    }
+
+    #[test]
+    fn test_migrate_to_salted_passphrase() {
+        let salt = PassphraseSalt::from_bytes([9u8; crate::PASSPHRASE_SALT_SIZE]);
+        let (old_pk, old_sk) =
+            derive_keys_from_static_root_secret(&StaticRootSecret::from_passphrase(b"passphrase"));
+
+        let (new_pk, _, migrated_old_sk, certificate) =
+            migrate_to_salted_passphrase(b"passphrase", &salt);
+        assert_eq!(
+            migrated_old_sk.kem_secret_key.as_bytes(),
+            old_sk.kem_secret_key.as_bytes()
+        );
+        assert_ne!(new_pk.derive_id(), old_pk.derive_id());
+        assert_eq!(
+            verify_rotation_chain(&old_pk, &[certificate]).map(UserPublicKeys::derive_id),
+            Some(new_pk.derive_id())
+        );
+    }
+}
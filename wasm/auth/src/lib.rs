@@ -11,21 +11,68 @@
 //! 2. The `StaticRootSecret` is used to deterministically derive all user keys
 //! 3. A unique `UserId` is derived from the public keys
 //!
+//! Instead of a passphrase of their choice, users can be given a BIP-39 `Mnemonic`, whose
+//! checksum catches typos when it is typed back.
+//!
+//! New accounts should derive their root secret with a random `PassphraseSalt`, kept in the
+//! account backup, so that equal passphrases do not give equal identities. Accounts created with
+//! the fixed salt migrate with `migrate_to_salted_passphrase`, which certifies the new keys
+//! with a key rotation.
+//!
+//! User IDs have a canonical text encoding (bech32m, `gossip1...`) with a checksum that
+//! catches typos; use `Display`/`FromStr` to convert them to and from strings.
+//!
 //! # Authentication Blob
 //!
 //! The `AuthBlob` type provides single-round sender authentication for Agraphon announcements,
 //! allowing a receiver to immediately verify the sender's identity without additional round trips.
 //!
+//! # Key Rotation
+//!
+//! The `RotationCertificate` type lets a user replace their keys, and thus their `UserId`,
+//! without losing their contacts: the certificate is signed with the old keys and binds the
+//! new ones. Rotated keys are derived from the same root secret, one generation at a time.
+//!
+//! # Social Recovery
+//!
+//! The `StaticRootSecret` can be split into recovery shares handed to trusted contacts, any
+//! threshold of which reconstruct it (Shamir secret sharing over GF(256)). A user who forgets
+//! their passphrase recovers their keys from the shares of enough contacts.
+//!
+//! # Safety Numbers
+//!
+//! The `SafetyNumber` type lets two users confirm out-of-band that they hold each other's
+//! genuine public keys, by comparing digits or scanning a QR code.
+//!
 //! # Security
 //!
 //! All secret key material is protected using `zeroize` to ensure sensitive data is
 //! securely erased from memory when no longer needed.
 
 mod auth_blob;
+mod bech32m;
+mod key_rotation;
+mod mnemonic;
+mod passphrase_salt;
+mod recovery;
+mod safety_number;
 mod types;
 
 pub use auth_blob::AuthBlob;
+pub use key_rotation::{RotationCertificate, migrate_to_salted_passphrase, verify_rotation_chain};
+pub use mnemonic::{MNEMONIC_WORD_COUNTS, Mnemonic, MnemonicError};
+pub use passphrase_salt::{
+    PASSPHRASE_SALT_HRP, PASSPHRASE_SALT_SIZE, PassphraseSalt, PassphraseSaltParseError,
+};
+pub use recovery::{
+    RECOVERY_SHARE_SIZE, RecoveryError, RecoveryShare, recover_root_secret, split_root_secret,
+};
+pub use safety_number::{
+    KEY_FINGERPRINT_SIZE, SAFETY_NUMBER_DIGITS, SCANNABLE_SAFETY_NUMBER_SIZE, SafetyNumber,
+    key_fingerprint,
+};
 pub use types::{
-    STATIC_ROOT_SECRET_SIZE, StaticRootSecret, USER_ID_SIZE, UserId, UserPublicKeys,
-    UserSecretKeys, derive_keys_from_static_root_secret,
+    STATIC_ROOT_SECRET_SIZE, StaticRootSecret, USER_ID_HRP, USER_ID_SIZE, UserId, UserIdParseError,
+    UserPublicKeys, UserSecretKeys, derive_keys_from_static_root_secret,
+    derive_rotated_keys_from_static_root_secret,
 };
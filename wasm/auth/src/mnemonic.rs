@@ -0,0 +1,312 @@
+//! BIP-39 mnemonic phrases.
+//!
+//! A mnemonic encodes 128 to 256 bits of entropy as 12 to 24 words of the English BIP-39
+//! wordlist, with a checksum made of the first bits of the SHA-256 of the entropy. Each word
+//! carries 11 bits:
+//!
+//! ```text
+//! bits  = entropy || sha256(entropy)[..entropy_bits / 32]
+//! words = wordlist[bits[0..11]] ... wordlist[bits[11 × (n - 1)..11 × n]]
+//! ```
+//!
+//! Users write down their mnemonic and retype it to recover their account: the checksum catches
+//! most typos before keys are derived from a wrong phrase. The user keys are then derived with
+//! [`StaticRootSecret::from_mnemonic`](crate::StaticRootSecret::from_mnemonic).
+//!
+//! # Example
+//!
+//! ```ignore
+//! use auth::{Mnemonic, StaticRootSecret};
+//!
+//! let mnemonic = Mnemonic::generate(24).unwrap();
+//! println!("write this down: {}", mnemonic.phrase());
+//!
+//! // later, from what the user typed
+//! let typed: Mnemonic = typed_phrase.parse()?;
+//! let root_secret = StaticRootSecret::from_mnemonic(&typed);
+//! ```
+
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::LazyLock;
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+/// Numbers of words a mnemonic can have.
+pub const MNEMONIC_WORD_COUNTS: [usize; 5] = [12, 15, 18, 21, 24];
+
+/// Number of bits encoded by each word.
+const BITS_PER_WORD: usize = 11;
+
+/// The English BIP-39 wordlist, sorted.
+static WORDLIST: LazyLock<Vec<&'static str>> =
+    LazyLock::new(|| include_str!("bip39_english.txt").lines().collect());
+
+/// Error creating or parsing a mnemonic.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MnemonicError {
+    /// The number of words, or the size of the entropy, is not supported.
+    InvalidLength,
+    /// A word is not in the wordlist.
+    UnknownWord,
+    /// The checksum does not match: a word was mistyped or swapped.
+    ChecksumMismatch,
+}
+
+impl fmt::Display for MnemonicError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidLength => write!(f, "invalid mnemonic length"),
+            Self::UnknownWord => write!(f, "unknown mnemonic word"),
+            Self::ChecksumMismatch => write!(f, "invalid mnemonic checksum"),
+        }
+    }
+}
+
+impl std::error::Error for MnemonicError {}
+
+/// A valid BIP-39 mnemonic phrase.
+///
+/// The phrase is kept in its normalized form: lowercase words separated by single spaces.
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
+pub struct Mnemonic {
+    /// The entropy encoded by the phrase.
+    entropy: Vec<u8>,
+    /// The normalized phrase.
+    phrase: String,
+}
+
+/// Returns the checksum bits of some entropy, left-aligned in a byte.
+fn checksum(entropy: &[u8]) -> u8 {
+    let checksum_bits = entropy.len() / 4;
+    let mask = (0xff00u16 >> checksum_bits) as u8;
+    Sha256::digest(entropy)[0] & mask
+}
+
+impl Mnemonic {
+    /// Generates a mnemonic of `word_count` words from fresh randomness.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MnemonicError::InvalidLength`] if `word_count` is not in
+    /// [`MNEMONIC_WORD_COUNTS`].
+    pub fn generate(word_count: usize) -> Result<Self, MnemonicError> {
+        if !MNEMONIC_WORD_COUNTS.contains(&word_count) {
+            return Err(MnemonicError::InvalidLength);
+        }
+        let mut entropy = Zeroizing::new(vec![0u8; word_count * 4 / 3]);
+        crypto_rng::fill_buffer(&mut entropy);
+        Self::from_entropy(&entropy)
+    }
+
+    /// Encodes entropy as a mnemonic.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MnemonicError::InvalidLength`] unless the entropy is 16 to 32 bytes long,
+    /// in steps of 4 bytes.
+    pub fn from_entropy(entropy: &[u8]) -> Result<Self, MnemonicError> {
+        if !(16..=32).contains(&entropy.len()) || !entropy.len().is_multiple_of(4) {
+            return Err(MnemonicError::InvalidLength);
+        }
+        let checksum = checksum(entropy);
+        let bit = |i: usize| {
+            let byte = entropy.get(i / 8).copied().unwrap_or(checksum);
+            usize::from((byte >> (7 - i % 8)) & 1)
+        };
+
+        let word_count = (entropy.len() * 8 + entropy.len() / 4) / BITS_PER_WORD;
+        let mut phrase = String::new();
+        for word in 0..word_count {
+            let index =
+                (0..BITS_PER_WORD).fold(0, |index, i| (index << 1) | bit(word * BITS_PER_WORD + i));
+            if word > 0 {
+                phrase.push(' ');
+            }
+            phrase.push_str(WORDLIST[index]);
+        }
+
+        Ok(Self {
+            entropy: entropy.to_vec(),
+            phrase,
+        })
+    }
+
+    /// Returns the normalized phrase, to show to the user.
+    #[must_use]
+    pub fn phrase(&self) -> &str {
+        &self.phrase
+    }
+
+    /// Returns the entropy encoded by the phrase.
+    #[must_use]
+    pub fn entropy(&self) -> &[u8] {
+        &self.entropy
+    }
+}
+
+impl FromStr for Mnemonic {
+    type Err = MnemonicError;
+
+    /// Parses a mnemonic typed by a user, verifying its checksum.
+    ///
+    /// Words may be separated by any whitespace, and are case-insensitive.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let indices = Zeroizing::new(
+            s.split_whitespace()
+                .map(|word| {
+                    let word = Zeroizing::new(word.to_lowercase());
+                    WORDLIST
+                        .binary_search(&word.as_str())
+                        .map_err(|_| MnemonicError::UnknownWord)
+                })
+                .collect::<Result<Vec<usize>, _>>()?,
+        );
+        if !MNEMONIC_WORD_COUNTS.contains(&indices.len()) {
+            return Err(MnemonicError::InvalidLength);
+        }
+
+        // regroup the 11-bit indices into bytes, the checksum filling the last byte
+        let mut bytes = Zeroizing::new(vec![0u8; indices.len() * BITS_PER_WORD / 8 + 1]);
+        for (word, index) in indices.iter().enumerate() {
+            for i in 0..BITS_PER_WORD {
+                let position = word * BITS_PER_WORD + i;
+                let bit = ((index >> (BITS_PER_WORD - 1 - i)) & 1) as u8;
+                bytes[position / 8] |= bit << (7 - position % 8);
+            }
+        }
+        let entropy_size = indices.len() * 4 / 3;
+        if checksum(&bytes[..entropy_size]) != bytes[entropy_size] {
+            return Err(MnemonicError::ChecksumMismatch);
+        }
+
+        Self::from_entropy(&bytes[..entropy_size])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    /// Vectors of the BIP-39 reference implementation.
+    const VECTORS: [(&str, &str); 7] = [
+        (
+            "00000000000000000000000000000000",
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+        ),
+        (
+            "7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f",
+            "legal winner thank year wave sausage worth useful legal winner thank yellow",
+        ),
+        (
+            "80808080808080808080808080808080",
+            "letter advice cage absurd amount doctor acoustic avoid letter advice cage above",
+        ),
+        (
+            "ffffffffffffffffffffffffffffffff",
+            "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo wrong",
+        ),
+        (
+            "9e885d952ad362caeb4efe34a8e91bd2",
+            "ozone drill grab fiber curtain grace pudding thank cruise elder eight picnic",
+        ),
+        (
+            "ffffffffffffffffffffffffffffffffffffffffffffffff",
+            "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo when",
+        ),
+        (
+            "0000000000000000000000000000000000000000000000000000000000000000",
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon art",
+        ),
+    ];
+
+    #[test]
+    fn test_wordlist() {
+        assert_eq!(WORDLIST.len(), 1 << BITS_PER_WORD);
+        assert!(WORDLIST.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn test_vectors() {
+        for (entropy, phrase) in VECTORS {
+            let mnemonic = Mnemonic::from_entropy(&hex(entropy)).unwrap();
+            assert_eq!(mnemonic.phrase(), phrase);
+
+            let parsed: Mnemonic = phrase.parse().unwrap();
+            assert_eq!(parsed.entropy(), hex(entropy));
+        }
+    }
+
+    #[test]
+    fn test_parse_normalizes() {
+        let typed =
+            "  Legal winner thank year\nwave sausage worth useful LEGAL winner thank   yellow ";
+        let mnemonic: Mnemonic = typed.parse().unwrap();
+        assert_eq!(mnemonic.phrase(), VECTORS[1].1);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        // a word swapped for another valid word
+        assert_eq!(
+            "legal winner thank year wave sausage worth useful legal winner thank zoo"
+                .parse::<Mnemonic>()
+                .err(),
+            Some(MnemonicError::ChecksumMismatch)
+        );
+        // a typo
+        assert_eq!(
+            "legal winner thank year wave sausage worth usefull legal winner thank yellow"
+                .parse::<Mnemonic>()
+                .err(),
+            Some(MnemonicError::UnknownWord)
+        );
+        // a missing word
+        assert_eq!(
+            "legal winner thank year wave sausage worth useful legal winner thank"
+                .parse::<Mnemonic>()
+                .err(),
+            Some(MnemonicError::InvalidLength)
+        );
+        assert_eq!(
+            "".parse::<Mnemonic>().err(),
+            Some(MnemonicError::InvalidLength)
+        );
+    }
+
+    #[test]
+    fn test_generate() {
+        for word_count in MNEMONIC_WORD_COUNTS {
+            let mnemonic = Mnemonic::generate(word_count).unwrap();
+            assert_eq!(mnemonic.phrase().split(' ').count(), word_count);
+
+            let parsed: Mnemonic = mnemonic.phrase().parse().unwrap();
+            assert_eq!(parsed.entropy(), mnemonic.entropy());
+        }
+        assert_ne!(
+            Mnemonic::generate(24).unwrap().entropy(),
+            Mnemonic::generate(24).unwrap().entropy()
+        );
+        assert_eq!(
+            Mnemonic::generate(13).err(),
+            Some(MnemonicError::InvalidLength)
+        );
+    }
+
+    #[test]
+    fn test_invalid_entropy_size() {
+        for size in [0, 12, 17, 36] {
+            assert_eq!(
+                Mnemonic::from_entropy(&vec![0; size]).err(),
+                Some(MnemonicError::InvalidLength)
+            );
+        }
+    }
+}
@@ -0,0 +1,201 @@
+//! Per-user random salts for passphrase-derived root secrets.
+//!
+//! [`StaticRootSecret::from_passphrase`](crate::StaticRootSecret::from_passphrase) uses a fixed
+//! salt, so two users with the same passphrase get the same identity, and a single
+//! precomputation attacks all users at once. A [`PassphraseSalt`] is generated once per account
+//! and makes the derivation unique to it.
+//!
+//! The salt is not secret, but the account cannot be recovered without it: it must be kept in
+//! account backups next to the passphrase or mnemonic. Its text form is checksummed like user
+//! IDs, so that it can be written down and typed back:
+//!
+//! ```text
+//! text = bech32m("gossipsalt", [version (1) || salt (16)])
+//! ```
+//!
+//! The salt given to the password KDF is `"auth.pwd.salt.v1" || salt`.
+//!
+//! Accounts created with the fixed salt move to a salted root secret with a key rotation, see
+//! [`migrate_to_salted_passphrase`](crate::migrate_to_salted_passphrase).
+
+use crate::bech32m::{self, Bech32mError};
+use std::fmt;
+use std::str::FromStr;
+
+/// Size of a passphrase salt in bytes.
+pub const PASSPHRASE_SALT_SIZE: usize = 16;
+
+/// Human-readable prefix of the text encoding of passphrase salts.
+pub const PASSPHRASE_SALT_HRP: &str = "gossipsalt";
+
+/// Version of the salt format.
+const SALT_VERSION: u8 = 1;
+
+/// Size of the salt given to the password KDF in bytes.
+const KDF_SALT_SIZE: usize = 32;
+
+/// Domain separation prefix of the salt given to the password KDF.
+const KDF_SALT_PREFIX: &[u8; KDF_SALT_SIZE - PASSPHRASE_SALT_SIZE] = b"auth.pwd.salt.v1";
+
+/// A random salt, unique per account, for deriving the root secret from a passphrase.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PassphraseSalt([u8; PASSPHRASE_SALT_SIZE]);
+
+impl PassphraseSalt {
+    /// Generates a new salt from fresh randomness.
+    #[must_use]
+    pub fn generate() -> Self {
+        let mut salt = [0u8; PASSPHRASE_SALT_SIZE];
+        crypto_rng::fill_buffer(&mut salt);
+        Self(salt)
+    }
+
+    /// Creates a salt from raw bytes.
+    #[must_use]
+    pub const fn from_bytes(bytes: [u8; PASSPHRASE_SALT_SIZE]) -> Self {
+        Self(bytes)
+    }
+
+    /// Returns the salt as a byte slice.
+    #[must_use]
+    pub const fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Returns the salt given to the password KDF.
+    pub(crate) fn kdf_salt(&self) -> [u8; KDF_SALT_SIZE] {
+        let mut kdf_salt = [0u8; KDF_SALT_SIZE];
+        kdf_salt[..KDF_SALT_PREFIX.len()].copy_from_slice(KDF_SALT_PREFIX);
+        kdf_salt[KDF_SALT_PREFIX.len()..].copy_from_slice(&self.0);
+        kdf_salt
+    }
+}
+
+impl fmt::Display for PassphraseSalt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut data = [0u8; 1 + PASSPHRASE_SALT_SIZE];
+        data[0] = SALT_VERSION;
+        data[1..].copy_from_slice(&self.0);
+        f.write_str(&bech32m::encode(PASSPHRASE_SALT_HRP, &data))
+    }
+}
+
+impl FromStr for PassphraseSalt {
+    type Err = PassphraseSaltParseError;
+
+    /// Parses a salt from its bech32m encoding.
+    ///
+    /// Surrounding whitespace is ignored, and the all-uppercase form is accepted.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (hrp, data) = bech32m::decode(s.trim()).map_err(|e| match e {
+            Bech32mError::Malformed => PassphraseSaltParseError::Malformed,
+            Bech32mError::ChecksumMismatch => PassphraseSaltParseError::ChecksumMismatch,
+            Bech32mError::InvalidPadding => PassphraseSaltParseError::InvalidLength,
+        })?;
+        if hrp != PASSPHRASE_SALT_HRP {
+            return Err(PassphraseSaltParseError::WrongPrefix);
+        }
+        let (&version, salt) = data
+            .split_first()
+            .ok_or(PassphraseSaltParseError::InvalidLength)?;
+        if version != SALT_VERSION {
+            return Err(PassphraseSaltParseError::UnsupportedVersion);
+        }
+        let salt: [u8; PASSPHRASE_SALT_SIZE] = salt
+            .try_into()
+            .map_err(|_| PassphraseSaltParseError::InvalidLength)?;
+        Ok(Self(salt))
+    }
+}
+
+/// Error parsing the text encoding of a [`PassphraseSalt`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PassphraseSaltParseError {
+    /// Not a bech32m string.
+    Malformed,
+    /// The checksum does not match: the salt was mistyped or truncated.
+    ChecksumMismatch,
+    /// The prefix is not [`PASSPHRASE_SALT_HRP`].
+    WrongPrefix,
+    /// The salt was encoded by a newer version.
+    UnsupportedVersion,
+    /// The encoded data is not a version and [`PASSPHRASE_SALT_SIZE`] bytes.
+    InvalidLength,
+}
+
+impl fmt::Display for PassphraseSaltParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Malformed => write!(f, "malformed passphrase salt"),
+            Self::ChecksumMismatch => write!(f, "passphrase salt checksum mismatch"),
+            Self::WrongPrefix => {
+                write!(f, "passphrase salt must start with {PASSPHRASE_SALT_HRP}1")
+            }
+            Self::UnsupportedVersion => write!(f, "unsupported passphrase salt version"),
+            Self::InvalidLength => {
+                write!(
+                    f,
+                    "passphrase salt must encode {PASSPHRASE_SALT_SIZE} bytes"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for PassphraseSaltParseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_salt_string_roundtrip() {
+        let salt = PassphraseSalt::generate();
+        let encoded = salt.to_string();
+        assert!(encoded.starts_with("gossipsalt1"));
+        assert_eq!(encoded.parse::<PassphraseSalt>(), Ok(salt));
+        assert_eq!(
+            format!(" {}\n", encoded.to_ascii_uppercase()).parse::<PassphraseSalt>(),
+            Ok(salt)
+        );
+        assert_ne!(PassphraseSalt::generate(), salt);
+    }
+
+    #[test]
+    fn test_salt_parse_errors() {
+        let encoded = PassphraseSalt::from_bytes([7u8; PASSPHRASE_SALT_SIZE]).to_string();
+
+        // typo
+        let mut typo = encoded.clone().into_bytes();
+        typo[15] = if typo[15] == b'q' { b'p' } else { b'q' };
+        let typo = String::from_utf8(typo).unwrap();
+        assert_eq!(
+            typo.parse::<PassphraseSalt>().err(),
+            Some(PassphraseSaltParseError::ChecksumMismatch)
+        );
+
+        // user ID instead of a salt
+        let user_id = crate::UserId::from_bytes([7u8; crate::USER_ID_SIZE]).to_string();
+        assert_eq!(
+            user_id.parse::<PassphraseSalt>().err(),
+            Some(PassphraseSaltParseError::WrongPrefix)
+        );
+
+        // future version
+        let mut data = [0u8; 1 + PASSPHRASE_SALT_SIZE];
+        data[0] = SALT_VERSION + 1;
+        let future = bech32m::encode(PASSPHRASE_SALT_HRP, &data);
+        assert_eq!(
+            future.parse::<PassphraseSalt>().err(),
+            Some(PassphraseSaltParseError::UnsupportedVersion)
+        );
+
+        // wrong length
+        data[0] = SALT_VERSION;
+        let short = bech32m::encode(PASSPHRASE_SALT_HRP, &data[..PASSPHRASE_SALT_SIZE]);
+        assert_eq!(
+            short.parse::<PassphraseSalt>().err(),
+            Some(PassphraseSaltParseError::InvalidLength)
+        );
+    }
+}
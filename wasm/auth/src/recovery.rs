@@ -0,0 +1,462 @@
+//! Social recovery of the static root secret with Shamir secret sharing.
+//!
+//! A user who loses their passphrase loses their identity. To guard against this, the
+//! [`StaticRootSecret`] can be split into `n` [`RecoveryShare`]s handed to trusted contacts,
+//! any `t` of which reconstruct it, while fewer than `t` reveal nothing about it.
+//!
+//! Each byte of the secret is the constant term of a random polynomial of degree `t - 1` over
+//! GF(256), and share `x` (from 1 to `n`) holds the evaluations of these polynomials at `x`.
+//! The secret is recovered by Lagrange interpolation at 0.
+//!
+//! # Share format
+//!
+//! ```text
+//! share = [version (1) || split_id (16) || threshold (1) || index (1) || value (32)
+//!          || secret_check (8) || checksum (4)]
+//! ```
+//!
+//! - `split_id` is random and shared by all shares of a split, so that shares of different
+//!   splits cannot be mixed up.
+//! - `secret_check` is derived from the secret and the split ID, and verifies the reconstructed
+//!   secret, which catches forged or altered shares.
+//! - `checksum` is derived from the preceding bytes, and catches transmission errors.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use auth::{StaticRootSecret, recover_root_secret, split_root_secret};
+//!
+//! let root_secret = StaticRootSecret::from_passphrase(b"my secure passphrase");
+//!
+//! // 3 of 5 contacts are needed to recover the secret
+//! let shares = split_root_secret(&root_secret, 3, 5).unwrap();
+//!
+//! let recovered = recover_root_secret(&shares[1..4]).unwrap();
+//! assert_eq!(recovered.as_slice(), root_secret.as_slice());
+//! ```
+
+use crate::types::{STATIC_ROOT_SECRET_SIZE, StaticRootSecret};
+use std::fmt;
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+/// Size of a serialized recovery share in bytes.
+pub const RECOVERY_SHARE_SIZE: usize =
+    1 + SPLIT_ID_SIZE + 1 + 1 + STATIC_ROOT_SECRET_SIZE + SECRET_CHECK_SIZE + CHECKSUM_SIZE;
+
+/// Version of the share format.
+const SHARE_VERSION: u8 = 1;
+
+/// Size of the identifier of a split in bytes.
+const SPLIT_ID_SIZE: usize = 16;
+
+/// Size of the secret check in bytes.
+const SECRET_CHECK_SIZE: usize = 8;
+
+/// Size of the share checksum in bytes.
+const CHECKSUM_SIZE: usize = 4;
+
+/// Error returned when splitting or recovering a root secret.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecoveryError {
+    /// The threshold is below 2 or above the number of shares.
+    InvalidThreshold,
+    /// The share does not have the expected size.
+    InvalidLength,
+    /// The share was written by an unsupported version.
+    UnsupportedVersion,
+    /// The share checksum does not match: it was corrupted in transit.
+    ChecksumMismatch,
+    /// The share fields are out of range.
+    Malformed,
+    /// Fewer shares than the threshold were provided.
+    NotEnoughShares,
+    /// The shares come from different splits, or two shares have the same index.
+    MismatchedShares,
+    /// The reconstructed secret does not match its check: a share was forged or altered.
+    InvalidSecret,
+}
+
+impl fmt::Display for RecoveryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidThreshold => write!(f, "invalid recovery threshold"),
+            Self::InvalidLength => write!(f, "invalid recovery share length"),
+            Self::UnsupportedVersion => write!(f, "unsupported recovery share version"),
+            Self::ChecksumMismatch => write!(f, "invalid recovery share checksum"),
+            Self::Malformed => write!(f, "malformed recovery share"),
+            Self::NotEnoughShares => write!(f, "not enough recovery shares"),
+            Self::MismatchedShares => write!(f, "recovery shares do not belong together"),
+            Self::InvalidSecret => write!(f, "recovered secret is invalid"),
+        }
+    }
+}
+
+impl std::error::Error for RecoveryError {}
+
+/// One share of a split static root secret.
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
+pub struct RecoveryShare {
+    /// Identifier shared by all shares of a split.
+    split_id: [u8; SPLIT_ID_SIZE],
+    /// Number of shares needed to recover the secret.
+    threshold: u8,
+    /// Point at which the polynomials are evaluated (never 0).
+    index: u8,
+    /// Evaluations of the polynomials at `index`.
+    value: [u8; STATIC_ROOT_SECRET_SIZE],
+    /// Check of the secret, derived from the secret and the split ID.
+    secret_check: [u8; SECRET_CHECK_SIZE],
+}
+
+impl RecoveryShare {
+    /// Returns the index of the share, from 1 to the number of shares.
+    #[must_use]
+    pub const fn index(&self) -> u8 {
+        self.index
+    }
+
+    /// Returns the number of shares needed to recover the secret.
+    #[must_use]
+    pub const fn threshold(&self) -> u8 {
+        self.threshold
+    }
+
+    /// Serializes the share, appending its checksum.
+    #[must_use]
+    pub fn to_bytes(&self) -> Zeroizing<[u8; RECOVERY_SHARE_SIZE]> {
+        let mut bytes = Zeroizing::new([0u8; RECOVERY_SHARE_SIZE]);
+        let (version, rest) = bytes.split_at_mut(1);
+        version[0] = SHARE_VERSION;
+        let (split_id, rest) = rest.split_at_mut(SPLIT_ID_SIZE);
+        split_id.copy_from_slice(&self.split_id);
+        rest[0] = self.threshold;
+        rest[1] = self.index;
+        let (value, rest) = rest[2..].split_at_mut(STATIC_ROOT_SECRET_SIZE);
+        value.copy_from_slice(&self.value);
+        let (secret_check, _) = rest.split_at_mut(SECRET_CHECK_SIZE);
+        secret_check.copy_from_slice(&self.secret_check);
+
+        let checksum = share_checksum(&bytes[..RECOVERY_SHARE_SIZE - CHECKSUM_SIZE]);
+        bytes[RECOVERY_SHARE_SIZE - CHECKSUM_SIZE..].copy_from_slice(&checksum);
+        bytes
+    }
+
+    /// Deserializes a share, verifying its checksum.
+    ///
+    /// # Errors
+    ///
+    /// - [`RecoveryError::InvalidLength`] if `bytes` is not [`RECOVERY_SHARE_SIZE`] bytes long
+    /// - [`RecoveryError::UnsupportedVersion`] if the share has an unknown version
+    /// - [`RecoveryError::ChecksumMismatch`] if the share was corrupted
+    /// - [`RecoveryError::Malformed`] if the index or threshold is out of range
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, RecoveryError> {
+        let bytes: &[u8; RECOVERY_SHARE_SIZE] =
+            bytes.try_into().map_err(|_| RecoveryError::InvalidLength)?;
+        if bytes[0] != SHARE_VERSION {
+            return Err(RecoveryError::UnsupportedVersion);
+        }
+        let (content, checksum) = bytes.split_at(RECOVERY_SHARE_SIZE - CHECKSUM_SIZE);
+        if share_checksum(content) != checksum {
+            return Err(RecoveryError::ChecksumMismatch);
+        }
+
+        let (split_id, rest) = content[1..].split_at(SPLIT_ID_SIZE);
+        let (threshold, index) = (rest[0], rest[1]);
+        let (value, secret_check) = rest[2..].split_at(STATIC_ROOT_SECRET_SIZE);
+        if index == 0 || threshold < 2 {
+            return Err(RecoveryError::Malformed);
+        }
+
+        Ok(Self {
+            split_id: split_id.try_into().expect("Split ID has a fixed size"),
+            threshold,
+            index,
+            value: value.try_into().expect("Share value has a fixed size"),
+            secret_check: secret_check
+                .try_into()
+                .expect("Secret check has a fixed size"),
+        })
+    }
+}
+
+/// Computes the checksum of the serialized share fields.
+fn share_checksum(content: &[u8]) -> [u8; CHECKSUM_SIZE] {
+    let hash = blake3::derive_key("auth.recovery.share_checksum", content);
+    hash[..CHECKSUM_SIZE]
+        .try_into()
+        .expect("Checksum is shorter than the hash")
+}
+
+/// Computes the check of a secret, bound to the split it belongs to.
+fn secret_check(
+    split_id: &[u8; SPLIT_ID_SIZE],
+    secret: &[u8; STATIC_ROOT_SECRET_SIZE],
+) -> [u8; SECRET_CHECK_SIZE] {
+    let input = Zeroizing::new([split_id.as_slice(), secret.as_slice()].concat());
+    let hash = blake3::derive_key("auth.recovery.secret_check", &input);
+    hash[..SECRET_CHECK_SIZE]
+        .try_into()
+        .expect("Secret check is shorter than the hash")
+}
+
+/// Multiplies two elements of GF(256), with the AES reduction polynomial.
+///
+/// Runs in constant time, as the operands are secret.
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0u8;
+    for _ in 0..8 {
+        product ^= a & 0u8.wrapping_sub(b & 1);
+        let carry = 0u8.wrapping_sub(a >> 7);
+        a = (a << 1) ^ (carry & 0x1b);
+        b >>= 1;
+    }
+    product
+}
+
+/// Inverts a non-zero element of GF(256), as `a^254`.
+fn gf_inv(a: u8) -> u8 {
+    // a^254 = a^2 · a^4 · ... · a^128
+    let mut square = gf_mul(a, a);
+    let mut inverse = square;
+    for _ in 2..8 {
+        square = gf_mul(square, square);
+        inverse = gf_mul(inverse, square);
+    }
+    inverse
+}
+
+/// Splits a root secret into `share_count` shares, any `threshold` of which recover it.
+///
+/// # Errors
+///
+/// Returns [`RecoveryError::InvalidThreshold`] unless `2 <= threshold <= share_count`.
+pub fn split_root_secret(
+    secret: &StaticRootSecret,
+    threshold: u8,
+    share_count: u8,
+) -> Result<Vec<RecoveryShare>, RecoveryError> {
+    if threshold < 2 || threshold > share_count {
+        return Err(RecoveryError::InvalidThreshold);
+    }
+    let secret: &[u8; STATIC_ROOT_SECRET_SIZE] = secret
+        .as_slice()
+        .try_into()
+        .expect("Root secret has a fixed size");
+
+    let mut split_id = [0u8; SPLIT_ID_SIZE];
+    crypto_rng::fill_buffer(&mut split_id);
+    let secret_check = secret_check(&split_id, secret);
+
+    // coefficients of degree 1 to threshold - 1, the constant terms being the secret
+    let mut coefficients = Zeroizing::new(vec![
+        [0u8; STATIC_ROOT_SECRET_SIZE];
+        usize::from(threshold - 1)
+    ]);
+    for coefficient in coefficients.iter_mut() {
+        crypto_rng::fill_buffer(coefficient);
+    }
+
+    Ok((1..=share_count)
+        .map(|index| {
+            // Horner's method, from the highest degree down to the secret
+            let mut value = [0u8; STATIC_ROOT_SECRET_SIZE];
+            for coefficient in coefficients.iter().rev().chain([secret]) {
+                for (v, c) in value.iter_mut().zip(coefficient) {
+                    *v = gf_mul(*v, index) ^ c;
+                }
+            }
+            RecoveryShare {
+                split_id,
+                threshold,
+                index,
+                value,
+                secret_check,
+            }
+        })
+        .collect())
+}
+
+/// Recovers a root secret from at least `threshold` shares of the same split.
+///
+/// # Errors
+///
+/// - [`RecoveryError::NotEnoughShares`] if fewer shares than the threshold are provided
+/// - [`RecoveryError::MismatchedShares`] if the shares come from different splits or repeat an
+///   index
+/// - [`RecoveryError::InvalidSecret`] if the reconstructed secret does not match its check
+pub fn recover_root_secret(shares: &[RecoveryShare]) -> Result<StaticRootSecret, RecoveryError> {
+    let first = shares.first().ok_or(RecoveryError::NotEnoughShares)?;
+    if shares.len() < usize::from(first.threshold) {
+        return Err(RecoveryError::NotEnoughShares);
+    }
+    for (i, share) in shares.iter().enumerate() {
+        if share.split_id != first.split_id
+            || share.threshold != first.threshold
+            || share.secret_check != first.secret_check
+            || shares[..i].iter().any(|other| other.index == share.index)
+        {
+            return Err(RecoveryError::MismatchedShares);
+        }
+    }
+
+    // Lagrange interpolation at 0 over the first threshold shares
+    let shares = &shares[..usize::from(first.threshold)];
+    let mut secret = Zeroizing::new([0u8; STATIC_ROOT_SECRET_SIZE]);
+    for share in shares {
+        // basis polynomial at 0: product of x_m / (x_m - x_j), subtraction being xor
+        let mut basis = 1u8;
+        for other in shares.iter().filter(|other| other.index != share.index) {
+            basis = gf_mul(
+                basis,
+                gf_mul(other.index, gf_inv(other.index ^ share.index)),
+            );
+        }
+        for (s, v) in secret.iter_mut().zip(&share.value) {
+            *s ^= gf_mul(basis, *v);
+        }
+    }
+
+    if secret_check(&first.split_id, &secret) != first.secret_check {
+        return Err(RecoveryError::InvalidSecret);
+    }
+    Ok(StaticRootSecret::from_bytes(*secret))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn root_secret() -> StaticRootSecret {
+        StaticRootSecret::from_bytes(std::array::from_fn(|i| i as u8 * 7))
+    }
+
+    #[test]
+    fn test_gf_arithmetic() {
+        // example from FIPS 197, section 4.2
+        assert_eq!(gf_mul(0x57, 0x83), 0xc1);
+        assert_eq!(gf_mul(0x57, 0x13), 0xfe);
+        for a in 1..=255u8 {
+            assert_eq!(gf_mul(a, gf_inv(a)), 1, "{a}");
+        }
+    }
+
+    #[test]
+    fn test_recover_from_any_threshold_subset() {
+        let secret = root_secret();
+        let shares = split_root_secret(&secret, 3, 5).unwrap();
+        assert_eq!(shares.len(), 5);
+
+        for a in 0..5 {
+            for b in a + 1..5 {
+                for c in b + 1..5 {
+                    let subset = [shares[a].clone(), shares[c].clone(), shares[b].clone()];
+                    let recovered = recover_root_secret(&subset).unwrap();
+                    assert_eq!(recovered.as_slice(), secret.as_slice());
+                }
+            }
+        }
+
+        // more shares than needed
+        let recovered = recover_root_secret(&shares).unwrap();
+        assert_eq!(recovered.as_slice(), secret.as_slice());
+    }
+
+    #[test]
+    fn test_not_enough_shares() {
+        let shares = split_root_secret(&root_secret(), 3, 5).unwrap();
+        assert_eq!(
+            recover_root_secret(&shares[..2]).err(),
+            Some(RecoveryError::NotEnoughShares)
+        );
+        assert_eq!(
+            recover_root_secret(&[]).err(),
+            Some(RecoveryError::NotEnoughShares)
+        );
+    }
+
+    #[test]
+    fn test_invalid_threshold() {
+        let secret = root_secret();
+        assert_eq!(
+            split_root_secret(&secret, 1, 5).err(),
+            Some(RecoveryError::InvalidThreshold)
+        );
+        assert_eq!(
+            split_root_secret(&secret, 4, 3).err(),
+            Some(RecoveryError::InvalidThreshold)
+        );
+        assert_eq!(split_root_secret(&secret, 255, 255).unwrap().len(), 255);
+    }
+
+    #[test]
+    fn test_mismatched_shares() {
+        let secret = root_secret();
+        let shares = split_root_secret(&secret, 2, 3).unwrap();
+        let other_shares = split_root_secret(&secret, 2, 3).unwrap();
+
+        // shares of another split of the same secret
+        assert_eq!(
+            recover_root_secret(&[shares[0].clone(), other_shares[1].clone()]).err(),
+            Some(RecoveryError::MismatchedShares)
+        );
+
+        // the same share twice
+        assert_eq!(
+            recover_root_secret(&[shares[0].clone(), shares[0].clone()]).err(),
+            Some(RecoveryError::MismatchedShares)
+        );
+    }
+
+    #[test]
+    fn test_altered_share_detected() {
+        let secret = root_secret();
+        let mut shares = split_root_secret(&secret, 2, 3).unwrap();
+        shares[1].value[0] ^= 1;
+        assert_eq!(
+            recover_root_secret(&shares[..2]).err(),
+            Some(RecoveryError::InvalidSecret)
+        );
+    }
+
+    #[test]
+    fn test_share_serialization() {
+        let secret = root_secret();
+        let shares = split_root_secret(&secret, 2, 3).unwrap();
+
+        let parsed = shares
+            .iter()
+            .map(|share| RecoveryShare::from_bytes(share.to_bytes().as_slice()).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(parsed[2].index(), 3);
+        assert_eq!(parsed[2].threshold(), 2);
+        let recovered = recover_root_secret(&parsed[1..]).unwrap();
+        assert_eq!(recovered.as_slice(), secret.as_slice());
+    }
+
+    #[test]
+    fn test_corrupted_share_rejected() {
+        let shares = split_root_secret(&root_secret(), 2, 3).unwrap();
+        let bytes = shares[0].to_bytes();
+
+        for position in 1..RECOVERY_SHARE_SIZE {
+            let mut corrupted = *bytes;
+            corrupted[position] ^= 0x10;
+            assert_eq!(
+                RecoveryShare::from_bytes(&corrupted).err(),
+                Some(RecoveryError::ChecksumMismatch),
+                "{position}"
+            );
+        }
+
+        let mut future_version = *bytes;
+        future_version[0] = SHARE_VERSION + 1;
+        assert_eq!(
+            RecoveryShare::from_bytes(&future_version).err(),
+            Some(RecoveryError::UnsupportedVersion)
+        );
+        assert_eq!(
+            RecoveryShare::from_bytes(&bytes[1..]).err(),
+            Some(RecoveryError::InvalidLength)
+        );
+    }
+}
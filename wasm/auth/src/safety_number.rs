@@ -0,0 +1,256 @@
+//! Safety numbers for out-of-band verification of user public keys.
+//!
+//! Two users who want to make sure that the `UserPublicKeys` they hold for each other are
+//! genuine compute the safety number of their pair of keys, and compare it out-of-band:
+//! either by reading the digits aloud, or by scanning a QR code of the scannable bytes.
+//!
+//! The safety number is symmetric: both users get the same one, whichever of the two
+//! computes it. It is made of the fingerprints of both users' public keys, sorted:
+//!
+//! ```text
+//! digits    = digits(fingerprint_low) || digits(fingerprint_high)   (2 × 30 digits)
+//! scannable = [version (1 byte) || fingerprint_low || fingerprint_high]
+//! ```
+//!
+//! The digits of a fingerprint are made of its first 30 bytes, read as six 40-bit big-endian
+//! integers, each reduced modulo 100000 to 5 digits.
+
+use crate::UserPublicKeys;
+use std::fmt;
+use zeroize::Zeroizing;
+
+/// Size of a key fingerprint in bytes.
+pub const KEY_FINGERPRINT_SIZE: usize = 32;
+
+/// Number of digits of a safety number.
+pub const SAFETY_NUMBER_DIGITS: usize = 2 * DIGIT_GROUPS_PER_FINGERPRINT * DIGITS_PER_GROUP;
+
+/// Size of the scannable form of a safety number in bytes.
+pub const SCANNABLE_SAFETY_NUMBER_SIZE: usize = 1 + 2 * KEY_FINGERPRINT_SIZE;
+
+/// Version of the scannable form.
+const SCANNABLE_VERSION: u8 = 1;
+
+/// Number of digit groups encoding each fingerprint.
+const DIGIT_GROUPS_PER_FINGERPRINT: usize = 6;
+
+/// Number of digits per group.
+const DIGITS_PER_GROUP: usize = 5;
+
+/// Number of fingerprint bytes encoded by each group.
+const BYTES_PER_GROUP: usize = 5;
+
+/// Computes the fingerprint of a user's public keys.
+///
+/// Unlike the [`UserId`](crate::UserId), which identifies users, the fingerprint is only meant
+/// to be compared by users, and is domain-separated from the ID.
+#[must_use]
+pub fn key_fingerprint(public_keys: &UserPublicKeys) -> [u8; KEY_FINGERPRINT_SIZE] {
+    let serialized = Zeroizing::new(public_keys.to_bytes());
+    blake3::derive_key("auth.safety_number.key_fingerprint", &serialized)
+}
+
+/// The safety number of a pair of users.
+///
+/// # Example
+///
+/// ```ignore
+/// use auth::SafetyNumber;
+///
+/// let ours = SafetyNumber::new(&alice_pk, &bob_pk);
+/// let theirs = SafetyNumber::new(&bob_pk, &alice_pk);
+/// assert_eq!(ours, theirs);
+///
+/// // display "12345 67890 ..." for comparison, or a QR code of the scannable bytes
+/// println!("{ours}");
+/// let qr_payload = ours.to_scannable_bytes();
+///
+/// // check the QR code scanned from the peer's screen
+/// let scanned = SafetyNumber::from_scannable_bytes(&qr_payload);
+/// assert_eq!(scanned, Some(ours));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SafetyNumber {
+    /// Fingerprints of both users, sorted
+    fingerprints: [[u8; KEY_FINGERPRINT_SIZE]; 2],
+}
+
+impl SafetyNumber {
+    /// Computes the safety number of two users' public keys.
+    ///
+    /// The order of the arguments does not matter.
+    #[must_use]
+    pub fn new(our_public_keys: &UserPublicKeys, peer_public_keys: &UserPublicKeys) -> Self {
+        let mut fingerprints = [
+            key_fingerprint(our_public_keys),
+            key_fingerprint(peer_public_keys),
+        ];
+        fingerprints.sort_unstable();
+        Self { fingerprints }
+    }
+
+    /// Returns the [`SAFETY_NUMBER_DIGITS`] decimal digits of the safety number, without
+    /// separators. Its [`Display`](fmt::Display) implementation groups them by 5.
+    #[must_use]
+    pub fn digits(&self) -> String {
+        let mut digits = String::with_capacity(SAFETY_NUMBER_DIGITS);
+        for fingerprint in &self.fingerprints {
+            for chunk in fingerprint
+                .chunks_exact(BYTES_PER_GROUP)
+                .take(DIGIT_GROUPS_PER_FINGERPRINT)
+            {
+                let value = chunk
+                    .iter()
+                    .fold(0u64, |value, byte| (value << 8) | u64::from(*byte));
+                digits.push_str(&format!(
+                    "{:0width$}",
+                    value % 100_000,
+                    width = DIGITS_PER_GROUP
+                ));
+            }
+        }
+        digits
+    }
+
+    /// Returns the scannable form of the safety number, to be encoded in a QR code.
+    #[must_use]
+    pub fn to_scannable_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(SCANNABLE_SAFETY_NUMBER_SIZE);
+        bytes.push(SCANNABLE_VERSION);
+        bytes.extend_from_slice(&self.fingerprints[0]);
+        bytes.extend_from_slice(&self.fingerprints[1]);
+        bytes
+    }
+
+    /// Parses a safety number scanned from a peer.
+    ///
+    /// Returns `None` if the bytes are not the scannable form of a safety number.
+    /// The result is then compared with the safety number computed locally.
+    #[must_use]
+    pub fn from_scannable_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != SCANNABLE_SAFETY_NUMBER_SIZE || bytes[0] != SCANNABLE_VERSION {
+            return None;
+        }
+        let (low, high) = bytes[1..].split_at(KEY_FINGERPRINT_SIZE);
+        let fingerprints: [[u8; KEY_FINGERPRINT_SIZE]; 2] =
+            [low.try_into().ok()?, high.try_into().ok()?];
+        if fingerprints[0] > fingerprints[1] {
+            return None;
+        }
+        Some(Self { fingerprints })
+    }
+}
+
+impl fmt::Display for SafetyNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, digit) in self.digits().chars().enumerate() {
+            if i > 0 && i % DIGITS_PER_GROUP == 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{digit}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{StaticRootSecret, derive_keys_from_static_root_secret};
+
+    fn public_keys(seed: u8) -> UserPublicKeys {
+        let root_secret = StaticRootSecret::from_bytes([seed; crate::STATIC_ROOT_SECRET_SIZE]);
+        derive_keys_from_static_root_secret(&root_secret).0
+    }
+
+    #[test]
+    fn test_safety_number_is_symmetric() {
+        let alice = public_keys(1);
+        let bob = public_keys(2);
+
+        let ours = SafetyNumber::new(&alice, &bob);
+        let theirs = SafetyNumber::new(&bob, &alice);
+        assert_eq!(ours, theirs);
+        assert_eq!(ours.digits(), theirs.digits());
+        assert_eq!(ours.to_scannable_bytes(), theirs.to_scannable_bytes());
+    }
+
+    #[test]
+    fn test_safety_number_depends_on_both_keys() {
+        let alice = public_keys(1);
+        let bob = public_keys(2);
+        let mallory = public_keys(3);
+
+        let genuine = SafetyNumber::new(&alice, &bob);
+        assert_ne!(genuine, SafetyNumber::new(&alice, &mallory));
+        assert_ne!(genuine.digits(), SafetyNumber::new(&mallory, &bob).digits());
+    }
+
+    #[test]
+    fn test_digits_format() {
+        let safety_number = SafetyNumber::new(&public_keys(1), &public_keys(2));
+
+        let digits = safety_number.digits();
+        assert_eq!(digits.len(), SAFETY_NUMBER_DIGITS);
+        assert!(digits.bytes().all(|b| b.is_ascii_digit()));
+
+        let displayed = safety_number.to_string();
+        let groups: Vec<&str> = displayed.split(' ').collect();
+        assert_eq!(groups.len(), SAFETY_NUMBER_DIGITS / DIGITS_PER_GROUP);
+        assert!(groups.iter().all(|group| group.len() == DIGITS_PER_GROUP));
+        assert_eq!(groups.concat(), digits);
+    }
+
+    #[test]
+    fn test_digits_encoding() {
+        let mut first = [0u8; KEY_FINGERPRINT_SIZE];
+        first[..5].copy_from_slice(&[0, 0, 0, 0x30, 0x39]); // 12345
+        let mut second = [0xFFu8; KEY_FINGERPRINT_SIZE];
+        second[..5].copy_from_slice(&[0, 0, 0, 0, 7]);
+        let safety_number = SafetyNumber {
+            fingerprints: [first, second],
+        };
+
+        // 2^40 - 1 = 1099511627775
+        let digits = safety_number.digits();
+        assert_eq!(&digits[..10], "1234500000");
+        assert_eq!(&digits[30..40], "0000727775");
+    }
+
+    #[test]
+    fn test_scannable_roundtrip() {
+        let safety_number = SafetyNumber::new(&public_keys(1), &public_keys(2));
+        let bytes = safety_number.to_scannable_bytes();
+        assert_eq!(bytes.len(), SCANNABLE_SAFETY_NUMBER_SIZE);
+        assert_eq!(
+            SafetyNumber::from_scannable_bytes(&bytes),
+            Some(safety_number)
+        );
+    }
+
+    #[test]
+    fn test_scannable_rejects_malformed() {
+        let bytes = SafetyNumber::new(&public_keys(1), &public_keys(2)).to_scannable_bytes();
+
+        assert!(SafetyNumber::from_scannable_bytes(&bytes[..bytes.len() - 1]).is_none());
+
+        let mut wrong_version = bytes.clone();
+        wrong_version[0] = SCANNABLE_VERSION + 1;
+        assert!(SafetyNumber::from_scannable_bytes(&wrong_version).is_none());
+
+        let mut unsorted = vec![SCANNABLE_VERSION];
+        unsorted.extend_from_slice(&bytes[1 + KEY_FINGERPRINT_SIZE..]);
+        unsorted.extend_from_slice(&bytes[1..1 + KEY_FINGERPRINT_SIZE]);
+        assert!(SafetyNumber::from_scannable_bytes(&unsorted).is_none());
+    }
+
+    #[test]
+    fn test_key_fingerprint_differs_from_user_id() {
+        let alice = public_keys(1);
+        assert_eq!(key_fingerprint(&alice), key_fingerprint(&alice.clone()));
+        assert_ne!(
+            key_fingerprint(&alice).as_slice(),
+            alice.derive_id().as_bytes()
+        );
+    }
+}
@@ -4,12 +4,20 @@
 //! in a hierarchical key derivation scheme. All user keys are derived from a single
 //! root secret, ensuring deterministic key generation.
 
+use crate::bech32m::{self, Bech32mError};
+use crate::mnemonic::Mnemonic;
+use crate::passphrase_salt::PassphraseSalt;
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
 use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
 
 /// Size of the user ID in bytes.
 pub const USER_ID_SIZE: usize = 32;
 
+/// Human-readable prefix of the text encoding of user IDs.
+pub const USER_ID_HRP: &str = "gossip";
+
 /// Size of the static root secret in bytes.
 pub const STATIC_ROOT_SECRET_SIZE: usize = 32;
 
@@ -17,6 +25,10 @@ pub const STATIC_ROOT_SECRET_SIZE: usize = 32;
 ///
 /// The user ID is deterministically computed from all public keys using a KDF,
 /// ensuring that the same key material always produces the same ID.
+///
+/// Its canonical text encoding is bech32m with the [`USER_ID_HRP`] prefix
+/// (`gossip1...`), produced by [`Display`](fmt::Display) and parsed by [`FromStr`].
+/// The checksum catches typos in IDs typed or pasted by users.
 #[derive(Debug, Clone, Hash, Zeroize, ZeroizeOnDrop, Serialize, Deserialize, PartialEq, Eq)]
 pub struct UserId([u8; USER_ID_SIZE]);
 
@@ -40,6 +52,60 @@ impl AsRef<[u8]> for UserId {
     }
 }
 
+impl fmt::Display for UserId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&bech32m::encode(USER_ID_HRP, &self.0))
+    }
+}
+
+impl FromStr for UserId {
+    type Err = UserIdParseError;
+
+    /// Parses a user ID from its bech32m encoding.
+    ///
+    /// Surrounding whitespace is ignored, and the all-uppercase form is accepted.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (hrp, data) = bech32m::decode(s.trim()).map_err(|e| match e {
+            Bech32mError::Malformed => UserIdParseError::Malformed,
+            Bech32mError::ChecksumMismatch => UserIdParseError::ChecksumMismatch,
+            Bech32mError::InvalidPadding => UserIdParseError::InvalidLength,
+        })?;
+        if hrp != USER_ID_HRP {
+            return Err(UserIdParseError::WrongPrefix);
+        }
+        let bytes: [u8; USER_ID_SIZE] = data
+            .try_into()
+            .map_err(|_| UserIdParseError::InvalidLength)?;
+        Ok(Self(bytes))
+    }
+}
+
+/// Error parsing the text encoding of a [`UserId`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UserIdParseError {
+    /// Not a bech32m string.
+    Malformed,
+    /// The checksum does not match: the ID was mistyped or truncated.
+    ChecksumMismatch,
+    /// The prefix is not [`USER_ID_HRP`].
+    WrongPrefix,
+    /// The encoded data is not [`USER_ID_SIZE`] bytes long.
+    InvalidLength,
+}
+
+impl fmt::Display for UserIdParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Malformed => write!(f, "malformed user ID"),
+            Self::ChecksumMismatch => write!(f, "user ID checksum mismatch"),
+            Self::WrongPrefix => write!(f, "user ID must start with {USER_ID_HRP}1"),
+            Self::InvalidLength => write!(f, "user ID must encode {USER_ID_SIZE} bytes"),
+        }
+    }
+}
+
+impl std::error::Error for UserIdParseError {}
+
 /// A collection of all public keys associated with a user.
 ///
 /// This structure contains three different types of public keys:
@@ -154,6 +220,37 @@ impl StaticRootSecret {
         Self(output)
     }
 
+    /// Derives a static root secret from a passphrase and a per-account salt.
+    ///
+    /// Unlike [`from_passphrase`](Self::from_passphrase), which uses a fixed salt, two
+    /// accounts with the same passphrase get different root secrets, and precomputations
+    /// cannot target all users at once. The salt must be kept with the account backup.
+    #[must_use]
+    pub fn from_salted_passphrase(passphrase: &[u8], salt: &PassphraseSalt) -> Self {
+        let mut output = [0u8; STATIC_ROOT_SECRET_SIZE];
+        crypto_password_kdf::derive(passphrase, &salt.kdf_salt(), &mut output);
+        Self(output)
+    }
+
+    /// Derives a static root secret from a BIP-39 mnemonic.
+    ///
+    /// The normalized phrase goes through the same password-based KDF as
+    /// [`from_passphrase`](Self::from_passphrase), so that accounts created by passing the
+    /// mnemonic as a passphrase keep the same keys. Unlike a passphrase, the mnemonic was
+    /// checked against its checksum, so a mistyped phrase does not silently derive other keys.
+    #[must_use]
+    pub fn from_mnemonic(mnemonic: &Mnemonic) -> Self {
+        Self::from_passphrase(mnemonic.phrase().as_bytes())
+    }
+
+    /// Derives a static root secret from a BIP-39 mnemonic and a per-account salt.
+    ///
+    /// See [`from_salted_passphrase`](Self::from_salted_passphrase).
+    #[must_use]
+    pub fn from_salted_mnemonic(mnemonic: &Mnemonic, salt: &PassphraseSalt) -> Self {
+        Self::from_salted_passphrase(mnemonic.phrase().as_bytes(), salt)
+    }
+
     /// Creates a `StaticRootSecret` from raw bytes.
     ///
     /// # Arguments
@@ -226,9 +323,31 @@ pub struct UserSecretKeys {
 pub fn derive_keys_from_static_root_secret(
     static_root_secret: &StaticRootSecret,
 ) -> (UserPublicKeys, UserSecretKeys) {
-    // Extract entropy from the root secret
+    derive_rotated_keys_from_static_root_secret(static_root_secret, 0)
+}
+
+/// Derives the user keys of a key generation from a static root secret.
+///
+/// Generation 0 is the original set of keys returned by
+/// [`derive_keys_from_static_root_secret`]. Each key rotation moves to the next generation,
+/// so that rotated keys can be recovered from the passphrase, like the original ones.
+/// See [`RotationCertificate`](crate::RotationCertificate).
+///
+/// # Panics
+///
+/// See [`derive_keys_from_static_root_secret`].
+#[must_use]
+pub fn derive_rotated_keys_from_static_root_secret(
+    static_root_secret: &StaticRootSecret,
+    generation: u32,
+) -> (UserPublicKeys, UserSecretKeys) {
+    // Extract entropy from the root secret.
+    // Generation 0 keeps the original derivation, so that existing users keep their keys.
     let mut kdf = crypto_kdf::Extract::new(b"auth.keypairs.kdf.salt----------");
     kdf.input_item(static_root_secret.as_slice());
+    if generation > 0 {
+        kdf.input_item(&generation.to_be_bytes());
+    }
     let expander = kdf.finalize();
 
     // Derive randomness for DSA key generation
@@ -279,6 +398,77 @@ mod tests {
         assert_eq!(user_id.as_bytes(), &bytes);
     }
 
+    #[test]
+    fn test_user_id_string_roundtrip() {
+        let user_id = UserId::from_bytes([42u8; USER_ID_SIZE]);
+        let encoded = user_id.to_string();
+        assert!(encoded.starts_with("gossip1"));
+        assert_eq!(encoded.len(), USER_ID_HRP.len() + 1 + 52 + 6);
+        assert_eq!(encoded.parse::<UserId>(), Ok(user_id.clone()));
+        assert_eq!(
+            format!("  {}\n", encoded.to_ascii_uppercase()).parse::<UserId>(),
+            Ok(user_id)
+        );
+    }
+
+    #[test]
+    fn test_user_id_string_vector() {
+        let user_id = UserId::from_bytes(std::array::from_fn(|i| i as u8));
+        assert_eq!(
+            user_id.to_string(),
+            "gossip1qqqsyqcyq5rqwzqfpg9scrgwpugpzysnzs23v9ccrydpk8qarc0s6gdd90"
+        );
+    }
+
+    #[test]
+    fn test_user_id_parse_errors() {
+        let encoded = UserId::from_bytes([42u8; USER_ID_SIZE]).to_string();
+
+        // typo
+        let mut typo = encoded.clone().into_bytes();
+        typo[10] = if typo[10] == b'q' { b'p' } else { b'q' };
+        let typo = String::from_utf8(typo).unwrap();
+        assert_eq!(
+            typo.parse::<UserId>().err(),
+            Some(UserIdParseError::ChecksumMismatch)
+        );
+
+        // truncation
+        assert_eq!(
+            encoded[..encoded.len() - 1].parse::<UserId>().err(),
+            Some(UserIdParseError::ChecksumMismatch)
+        );
+
+        // mixed case, bad characters, empty string
+        let mixed = format!("GOSSIP{}", &encoded[6..]);
+        assert_eq!(
+            mixed.parse::<UserId>().err(),
+            Some(UserIdParseError::Malformed)
+        );
+        assert_eq!(
+            "gossip1bio".parse::<UserId>().err(),
+            Some(UserIdParseError::Malformed)
+        );
+        assert_eq!(
+            "".parse::<UserId>().err(),
+            Some(UserIdParseError::Malformed)
+        );
+
+        // valid bech32m with another prefix or length
+        assert_eq!(
+            bech32m::encode("other", &[42u8; USER_ID_SIZE])
+                .parse::<UserId>()
+                .err(),
+            Some(UserIdParseError::WrongPrefix)
+        );
+        assert_eq!(
+            bech32m::encode(USER_ID_HRP, &[42u8; USER_ID_SIZE - 1])
+                .parse::<UserId>()
+                .err(),
+            Some(UserIdParseError::InvalidLength)
+        );
+    }
+
     #[test]
     fn test_user_id_as_ref() {
         let bytes = [42u8; USER_ID_SIZE];
@@ -326,6 +516,35 @@ mod tests {
         assert_eq!(root_secret.as_slice(), &bytes);
     }
 
+    #[test]
+    fn test_static_root_secret_from_salted_passphrase() {
+        let salt1 = PassphraseSalt::from_bytes([1u8; crate::PASSPHRASE_SALT_SIZE]);
+        let salt2 = PassphraseSalt::from_bytes([2u8; crate::PASSPHRASE_SALT_SIZE]);
+
+        let salted1 = StaticRootSecret::from_salted_passphrase(b"passphrase", &salt1);
+        let salted2 = StaticRootSecret::from_salted_passphrase(b"passphrase", &salt2);
+        let unsalted = StaticRootSecret::from_passphrase(b"passphrase");
+        assert_ne!(salted1.as_slice(), salted2.as_slice());
+        assert_ne!(salted1.as_slice(), unsalted.as_slice());
+
+        let again = StaticRootSecret::from_salted_passphrase(b"passphrase", &salt1);
+        assert_eq!(salted1.as_slice(), again.as_slice());
+    }
+
+    #[test]
+    fn test_static_root_secret_from_mnemonic() {
+        let phrase = "legal winner thank year wave sausage worth useful legal winner thank yellow";
+        let typed: Mnemonic =
+            " LEGAL winner thank year wave sausage worth useful legal winner  thank yellow"
+                .parse()
+                .unwrap();
+
+        // same secret as the phrase used as a passphrase
+        let root_secret = StaticRootSecret::from_mnemonic(&typed);
+        let expected = StaticRootSecret::from_passphrase(phrase.as_bytes());
+        assert_eq!(root_secret.as_slice(), expected.as_slice());
+    }
+
     #[test]
     fn test_derive_keys_deterministic() {
         let passphrase = b"my secure passphrase";
@@ -364,6 +583,28 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_derive_rotated_keys() {
+        let root_secret = StaticRootSecret::from_passphrase(b"rotating passphrase");
+
+        // generation 0 is the original derivation
+        let (original, _) = derive_keys_from_static_root_secret(&root_secret);
+        let (generation0, _) = derive_rotated_keys_from_static_root_secret(&root_secret, 0);
+        assert_eq!(original.derive_id(), generation0.derive_id());
+
+        // other generations have distinct, reproducible keys
+        let (generation1, _) = derive_rotated_keys_from_static_root_secret(&root_secret, 1);
+        let (generation1_again, _) = derive_rotated_keys_from_static_root_secret(&root_secret, 1);
+        let (generation2, _) = derive_rotated_keys_from_static_root_secret(&root_secret, 2);
+        assert_eq!(generation1.derive_id(), generation1_again.derive_id());
+        assert_ne!(generation1.derive_id(), original.derive_id());
+        assert_ne!(generation1.derive_id(), generation2.derive_id());
+        assert_ne!(
+            generation1.kem_public_key.as_bytes(),
+            original.kem_public_key.as_bytes()
+        );
+    }
+
     #[test]
     fn test_user_id_derivation_deterministic() {
         let passphrase = b"test user id derivation";
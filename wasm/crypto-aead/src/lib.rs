@@ -51,6 +51,19 @@
 //! - **AAD**: Additional authenticated data is NOT encrypted but IS authenticated
 //! - AAD is not included in the ciphertext, so it must be transmitted separately
 //! - The same AAD must be provided during decryption for authentication to succeed
+//!
+//! ## Streaming
+//!
+//! Data too large to be encrypted in one go can be encrypted chunk by chunk with
+//! [`StreamEncryptor`] and decrypted with [`StreamDecryptor`]. Chunks are bound to their
+//! position in the stream and the last chunk is marked, so that reordering, dropping or
+//! truncating chunks is detected. See the [`stream`] module.
+
+pub mod stream;
+
+pub use stream::{
+    STREAM_CHUNK_OVERHEAD, STREAM_NONCE_PREFIX_SIZE, StreamDecryptor, StreamEncryptor,
+};
 
 use aes_siv::{
     Aes256SivAead,
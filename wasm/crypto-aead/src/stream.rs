@@ -0,0 +1,278 @@
+//! Chunked streaming encryption (STREAM construction).
+//!
+//! Large plaintexts are split into chunks that are encrypted one at a time with AES-256-SIV, so
+//! that they never need to be held in memory as a whole. Each chunk is encrypted under the same
+//! key with its own nonce:
+//!
+//! ```text
+//! nonce = [nonce prefix (11 bytes) || chunk counter (u32 BE) || last chunk flag (1 byte)]
+//! ```
+//!
+//! The counter binds each chunk to its position, so chunks cannot be reordered, dropped or
+//! duplicated, and the last chunk flag is only set on the final chunk, so the stream cannot be
+//! truncated at a chunk boundary without the decryptor noticing.
+//!
+//! The nonce prefix must be unique per stream encrypted under a given key. Since AES-SIV is
+//! nonce-misuse resistant, reusing it only reveals which chunks of two streams are equal.
+//!
+//! # Example
+//!
+//! ```rust
+//! use crypto_aead::{Key, KEY_SIZE, STREAM_NONCE_PREFIX_SIZE, StreamDecryptor, StreamEncryptor};
+//!
+//! let key = Key::from([42u8; KEY_SIZE]);
+//! let nonce_prefix = [7u8; STREAM_NONCE_PREFIX_SIZE];
+//!
+//! let mut encryptor = StreamEncryptor::new(&key, nonce_prefix);
+//! let chunk1 = encryptor.encrypt_next(b"Hello, ", b"");
+//! let chunk2 = encryptor.encrypt_last(b"world!", b"");
+//!
+//! let mut decryptor = StreamDecryptor::new(&key, nonce_prefix);
+//! let mut plaintext = decryptor.decrypt_next(&chunk1, b"").unwrap();
+//! plaintext.extend(decryptor.decrypt_last(&chunk2, b"").unwrap());
+//! assert_eq!(plaintext, b"Hello, world!");
+//! ```
+
+use crate::{Key, NONCE_SIZE, Nonce, decrypt, encrypt};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+/// Size of the per-stream nonce prefix in bytes.
+pub const STREAM_NONCE_PREFIX_SIZE: usize = NONCE_SIZE - 5;
+
+/// Size added by encryption to each chunk, in bytes (the AES-SIV tag).
+pub const STREAM_CHUNK_OVERHEAD: usize = 16;
+
+/// Builds the nonce of a chunk.
+fn chunk_nonce(
+    nonce_prefix: &[u8; STREAM_NONCE_PREFIX_SIZE],
+    counter: u32,
+    last_chunk: bool,
+) -> Nonce {
+    let mut nonce = [0u8; NONCE_SIZE];
+    nonce[..STREAM_NONCE_PREFIX_SIZE].copy_from_slice(nonce_prefix);
+    nonce[STREAM_NONCE_PREFIX_SIZE..NONCE_SIZE - 1].copy_from_slice(&counter.to_be_bytes());
+    nonce[NONCE_SIZE - 1] = u8::from(last_chunk);
+    Nonce::from(nonce)
+}
+
+/// Encrypts a stream chunk by chunk.
+///
+/// Chunks are encrypted with [`encrypt_next`](Self::encrypt_next), and the stream is
+/// terminated by [`encrypt_last`](Self::encrypt_last), which consumes the encryptor.
+#[derive(Zeroize, ZeroizeOnDrop)]
+pub struct StreamEncryptor {
+    key: Key,
+    nonce_prefix: [u8; STREAM_NONCE_PREFIX_SIZE],
+    counter: u32,
+}
+
+impl StreamEncryptor {
+    /// Creates an encryptor for a stream identified by `nonce_prefix`.
+    ///
+    /// The nonce prefix should be random and unique per stream encrypted under `key`.
+    pub fn new(key: &Key, nonce_prefix: [u8; STREAM_NONCE_PREFIX_SIZE]) -> Self {
+        Self {
+            key: Key::from(*key.as_bytes()),
+            nonce_prefix,
+            counter: 0,
+        }
+    }
+
+    /// Encrypts the next chunk of the stream, which is not the last one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the stream already has 2^32 - 1 chunks.
+    pub fn encrypt_next(&mut self, chunk: &[u8], aad: &[u8]) -> Vec<u8> {
+        let nonce = chunk_nonce(&self.nonce_prefix, self.counter, false);
+        self.counter = self
+            .counter
+            .checked_add(1)
+            .expect("Too many chunks in stream");
+        encrypt(&self.key, &nonce, chunk, aad)
+    }
+
+    /// Encrypts the last chunk of the stream, which may be empty.
+    pub fn encrypt_last(self, chunk: &[u8], aad: &[u8]) -> Vec<u8> {
+        let nonce = chunk_nonce(&self.nonce_prefix, self.counter, true);
+        encrypt(&self.key, &nonce, chunk, aad)
+    }
+}
+
+/// Decrypts a stream chunk by chunk.
+///
+/// Chunks must be fed in order. The stream is only complete once
+/// [`decrypt_last`](Self::decrypt_last) succeeds: a stream that ends without a last chunk
+/// was truncated.
+#[derive(Zeroize, ZeroizeOnDrop)]
+pub struct StreamDecryptor {
+    key: Key,
+    nonce_prefix: [u8; STREAM_NONCE_PREFIX_SIZE],
+    counter: u32,
+}
+
+impl StreamDecryptor {
+    /// Creates a decryptor for a stream identified by `nonce_prefix`.
+    pub fn new(key: &Key, nonce_prefix: [u8; STREAM_NONCE_PREFIX_SIZE]) -> Self {
+        Self {
+            key: Key::from(*key.as_bytes()),
+            nonce_prefix,
+            counter: 0,
+        }
+    }
+
+    /// Decrypts the next chunk of the stream, which is not the last one.
+    ///
+    /// Returns `None` if the chunk is not the expected next chunk of the stream
+    /// (tampered, reordered, or actually the last chunk). The decryptor is left unchanged.
+    pub fn decrypt_next(&mut self, chunk: &[u8], aad: &[u8]) -> Option<Vec<u8>> {
+        let nonce = chunk_nonce(&self.nonce_prefix, self.counter, false);
+        let next_counter = self.counter.checked_add(1)?;
+        let plaintext = decrypt(&self.key, &nonce, chunk, aad)?;
+        self.counter = next_counter;
+        Some(plaintext)
+    }
+
+    /// Decrypts the last chunk of the stream.
+    ///
+    /// Returns `None` if the chunk is not the last chunk of the stream
+    /// (tampered, reordered, or followed by more chunks).
+    pub fn decrypt_last(self, chunk: &[u8], aad: &[u8]) -> Option<Vec<u8>> {
+        let nonce = chunk_nonce(&self.nonce_prefix, self.counter, true);
+        decrypt(&self.key, &nonce, chunk, aad)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::KEY_SIZE;
+
+    const PREFIX: [u8; STREAM_NONCE_PREFIX_SIZE] = [9u8; STREAM_NONCE_PREFIX_SIZE];
+
+    fn encrypt_chunks(key: &Key, chunks: &[&[u8]]) -> Vec<Vec<u8>> {
+        let mut encryptor = StreamEncryptor::new(key, PREFIX);
+        let (last, others) = chunks.split_last().unwrap();
+        let mut encrypted: Vec<Vec<u8>> = others
+            .iter()
+            .map(|chunk| encryptor.encrypt_next(chunk, b"aad"))
+            .collect();
+        encrypted.push(encryptor.encrypt_last(last, b"aad"));
+        encrypted
+    }
+
+    fn decrypt_chunks(key: &Key, chunks: &[Vec<u8>]) -> Option<Vec<u8>> {
+        let mut decryptor = StreamDecryptor::new(key, PREFIX);
+        let (last, others) = chunks.split_last()?;
+        let mut plaintext = Vec::new();
+        for chunk in others {
+            plaintext.extend(decryptor.decrypt_next(chunk, b"aad")?);
+        }
+        plaintext.extend(decryptor.decrypt_last(last, b"aad")?);
+        Some(plaintext)
+    }
+
+    #[test]
+    fn test_stream_roundtrip() {
+        let key = Key::from([1u8; KEY_SIZE]);
+        let chunks: [&[u8]; 3] = [b"first chunk", b"second chunk", b"last"];
+
+        let encrypted = encrypt_chunks(&key, &chunks);
+        for (chunk, encrypted_chunk) in chunks.iter().zip(&encrypted) {
+            assert_eq!(encrypted_chunk.len(), chunk.len() + STREAM_CHUNK_OVERHEAD);
+        }
+        assert_eq!(
+            decrypt_chunks(&key, &encrypted).unwrap(),
+            b"first chunksecond chunklast"
+        );
+    }
+
+    #[test]
+    fn test_single_empty_chunk() {
+        let key = Key::from([1u8; KEY_SIZE]);
+        let encrypted = encrypt_chunks(&key, &[b""]);
+        assert_eq!(decrypt_chunks(&key, &encrypted).unwrap(), b"");
+    }
+
+    #[test]
+    fn test_identical_chunks_encrypt_differently() {
+        let key = Key::from([1u8; KEY_SIZE]);
+        let encrypted = encrypt_chunks(&key, &[b"same", b"same", b"same"]);
+        assert_ne!(encrypted[0], encrypted[1]);
+        assert_ne!(encrypted[1], encrypted[2]);
+    }
+
+    #[test]
+    fn test_reordered_chunks_rejected() {
+        let key = Key::from([1u8; KEY_SIZE]);
+        let mut encrypted = encrypt_chunks(&key, &[b"one", b"two", b"three"]);
+        encrypted.swap(0, 1);
+        assert!(decrypt_chunks(&key, &encrypted).is_none());
+    }
+
+    #[test]
+    fn test_truncated_stream_rejected() {
+        let key = Key::from([1u8; KEY_SIZE]);
+        let mut encrypted = encrypt_chunks(&key, &[b"one", b"two", b"three"]);
+
+        // dropping the last chunk leaves a stream without a last chunk
+        encrypted.pop();
+        assert!(decrypt_chunks(&key, &encrypted).is_none());
+
+        // dropping a middle chunk shifts the counters
+        let mut encrypted = encrypt_chunks(&key, &[b"one", b"two", b"three"]);
+        encrypted.remove(1);
+        assert!(decrypt_chunks(&key, &encrypted).is_none());
+    }
+
+    #[test]
+    fn test_extended_stream_rejected() {
+        let key = Key::from([1u8; KEY_SIZE]);
+        let mut encrypted = encrypt_chunks(&key, &[b"one", b"two"]);
+        let duplicate = encrypted[1].clone();
+        encrypted.push(duplicate);
+        assert!(decrypt_chunks(&key, &encrypted).is_none());
+    }
+
+    #[test]
+    fn test_failed_chunk_leaves_decryptor_unchanged() {
+        let key = Key::from([1u8; KEY_SIZE]);
+        let encrypted = encrypt_chunks(&key, &[b"one", b"two"]);
+
+        let mut decryptor = StreamDecryptor::new(&key, PREFIX);
+        assert!(decryptor.decrypt_next(&encrypted[1], b"aad").is_none());
+        assert_eq!(
+            decryptor.decrypt_next(&encrypted[0], b"aad").unwrap(),
+            b"one"
+        );
+        assert_eq!(
+            decryptor.decrypt_last(&encrypted[1], b"aad").unwrap(),
+            b"two"
+        );
+    }
+
+    #[test]
+    fn test_wrong_key_prefix_or_aad_rejected() {
+        let key = Key::from([1u8; KEY_SIZE]);
+        let encrypted = encrypt_chunks(&key, &[b"one"]);
+
+        let other_key = Key::from([2u8; KEY_SIZE]);
+        assert!(decrypt_chunks(&other_key, &encrypted).is_none());
+
+        let decryptor = StreamDecryptor::new(&key, [0u8; STREAM_NONCE_PREFIX_SIZE]);
+        assert!(decryptor.decrypt_last(&encrypted[0], b"aad").is_none());
+
+        let decryptor = StreamDecryptor::new(&key, PREFIX);
+        assert!(decryptor.decrypt_last(&encrypted[0], b"other").is_none());
+    }
+
+    #[test]
+    fn test_chunk_nonce_layout() {
+        let nonce = chunk_nonce(&PREFIX, 0x0102_0304, true);
+        assert_eq!(&nonce.as_bytes()[..STREAM_NONCE_PREFIX_SIZE], &PREFIX);
+        assert_eq!(
+            &nonce.as_bytes()[STREAM_NONCE_PREFIX_SIZE..],
+            &[1, 2, 3, 4, 1]
+        );
+    }
+}
@@ -1,13 +1,22 @@
 //! Main session state machine for secure asynchronous messaging.
 
 use crate::announcement::{IncomingAnnouncement, OutgoingAnnouncement};
-use crate::history::{HistoryItemPeer, HistoryItemSelf};
+use crate::error::AgraphonError;
+use crate::history::{
+    HistoryItemPeer, HistoryItemPeerV0, HistoryItemPeerV1, HistoryItemSelf, HistoryItemSelfV0,
+    HistoryItemSelfV1, SkippedChainKeyPeer, SkippedItemPeer, SkippedItemPeerV1,
+};
+use crate::message_chain_kdf::MessageChainKdf;
 use crate::message_root_kdf::MessageRootKdf;
+use crate::protocol_version::{
+    Encapsulation, KemCiphertext, KemPublicKey, KemSecretKey, ProtocolVersion,
+};
 use crypto_aead as aead;
 use crypto_kem as kem;
 use crypto_rng as rng;
 use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
+use subtle::ConstantTimeEq;
 use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
 
 #[derive(Zeroize, ZeroizeOnDrop)]
@@ -16,6 +25,141 @@ pub struct FeedIncomingMessageResult {
     pub newly_acknowledged_self_seekers: Vec<Vec<u8>>,
 }
 
+/// Limits on the state kept to decrypt peer messages delivered out of order.
+///
+/// When a peer message arrives before some of the messages the peer sent earlier,
+/// the session remembers the missing messages, and keeps the chain keys they may have been
+/// encrypted with, so that they can still be decrypted when they arrive.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_agraphon::SkippedMessageLimits;
+///
+/// let limits = SkippedMessageLimits {
+///     max_skip: 8,
+///     max_stored: 64,
+/// };
+/// assert!(limits.max_skip <= SkippedMessageLimits::default().max_skip);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SkippedMessageLimits {
+    /// Maximum number of consecutive peer messages that can be missing
+    /// when decrypting a message ahead of them.
+    /// Setting it to 0 disables out-of-order delivery.
+    pub max_skip: u64,
+    /// Maximum number of skipped peer messages remembered for later decryption, and of chain
+    /// keys kept for them.
+    /// When exceeded, the oldest skipped messages are forgotten and can no longer be decrypted.
+    ///
+    /// It also bounds how long a skipped message is waited for: once the peer has acknowledged
+    /// more than `max_stored` of our messages past the ones it could respond to, it is deemed
+    /// lost and forgotten, so that it does not keep our older messages and their keys alive.
+    pub max_stored: u64,
+}
+
+impl Default for SkippedMessageLimits {
+    fn default() -> Self {
+        Self {
+            max_skip: 16,
+            max_stored: 128,
+        }
+    }
+}
+
+/// Incoming message split into its components.
+struct IncomingMessage<'a> {
+    randomness: [u8; 32],
+    ct: KemCiphertext,
+    ct_static: KemCiphertext,
+    ss_static: kem::SharedSecret,
+    ciphertext: &'a [u8],
+}
+
+impl<'a> IncomingMessage<'a> {
+    /// Splits a message of the given protocol version and decapsulates its static ciphertext.
+    fn parse(
+        version: ProtocolVersion,
+        self_static_sk: &KemSecretKey,
+        message: &'a [u8],
+    ) -> Result<Self, AgraphonError> {
+        let ct_size = version.kem_ciphertext_size();
+
+        // read randomness
+        let randomness: [u8; 32] = message
+            .get(..32)
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or(AgraphonError::Malformed)?;
+
+        // read ct
+        let ct = message
+            .get(32..32 + ct_size)
+            .and_then(|bytes| KemCiphertext::from_bytes(version, bytes))
+            .ok_or(AgraphonError::Malformed)?;
+
+        // read ct_static
+        let ct_static = message
+            .get(32 + ct_size..32 + 2 * ct_size)
+            .and_then(|bytes| KemCiphertext::from_bytes(version, bytes))
+            .ok_or(AgraphonError::Malformed)?;
+
+        // decapsulate ct_static
+        let ss_static = self_static_sk
+            .decapsulate(&ct_static)
+            .ok_or(AgraphonError::VersionMismatch)?;
+
+        // read ciphertext
+        let ciphertext = message
+            .get(32 + 2 * ct_size..)
+            .ok_or(AgraphonError::Malformed)?;
+
+        Ok(Self {
+            randomness,
+            ct,
+            ct_static,
+            ss_static,
+            ciphertext,
+        })
+    }
+}
+
+/// Size of the height of a message in its plaintext, after the next public key.
+const HEIGHT_SIZE: usize = 8;
+
+/// Decrypted incoming message along with the peer state it carries.
+#[derive(Zeroize, ZeroizeOnDrop)]
+struct DecryptedMessage {
+    height: u64,
+    our_parent_height: u64,
+    pk_next: KemPublicKey,
+    k_next: [u8; 32],
+    payload: Vec<u8>,
+}
+
+/// Incoming message decrypted by [`Agraphon::try_decrypt_incoming_message`].
+///
+/// The ratchet step it implies is only applied to the session once the message is accepted
+/// with [`Agraphon::accept_incoming_message`], so that the caller can check its contents first.
+#[derive(Zeroize, ZeroizeOnDrop)]
+pub struct DecryptedIncomingMessage {
+    decrypted: DecryptedMessage,
+    /// Chain key the message was decrypted with
+    k_chain: [u8; 32],
+    /// Whether `k_chain` is the one of a response that was previously stepped over,
+    /// rather than a key of the chain of responses to the parent
+    stepped_over: bool,
+    /// Chain keys of the responses to the parent between the next expected one and this one
+    stepped_over_k_chains: Vec<[u8; 32]>,
+}
+
+impl DecryptedIncomingMessage {
+    /// Returns the decrypted payload of the message.
+    #[must_use]
+    pub fn message_bytes(&self) -> &[u8] {
+        &self.decrypted.payload
+    }
+}
+
 /// The main session state machine for secure asynchronous messaging.
 ///
 /// `Agraphon` implements a Double Ratchet-like protocol that provides:
@@ -28,18 +172,30 @@ pub struct FeedIncomingMessageResult {
 ///
 /// Each `Agraphon` session is created from either an incoming or outgoing announcement.
 /// The session maintains:
-/// - A history of our recently sent messages (for processing out-of-order responses)
-/// - The peer's most recent message state
-/// - Our role (Initiator or Responder) for key derivation
+/// - A history of our recently sent messages (for processing out-of-order responses), along
+///   with the chain key of the next peer response to each of them
+/// - The peer's most recent message state, along with the chain key of our next response
+/// - The peer messages skipped over and the chain keys they may have been encrypted with,
+///   bounded by [`SkippedMessageLimits`]
+///
+/// Every message is encrypted under a chain key. The responses of a party to a same peer
+/// message are linked by a one-way chain, which starts from the root key of that message:
+/// each peer message re-seeds the chain, so that a compromised chain key does not outlive
+/// the next round trip. The messages also carry their height in the sender's sequence,
+/// which tells the receiver which messages are missing.
 ///
 /// # Creating a Session
 ///
 /// By pairing an outgoing and incoming announcement:
 /// ```no_run
-/// # use crypto_agraphon::{Agraphon, OutgoingAnnouncement, IncomingAnnouncement};
+/// # use crypto_agraphon::{Agraphon, OutgoingAnnouncement, IncomingAnnouncement, SkippedMessageLimits};
 /// # let self_outgoing: OutgoingAnnouncement = todo!();
 /// # let peer_incoming: IncomingAnnouncement = todo!();
-/// let session = Agraphon::from_announcement_pair(&self_outgoing, &peer_incoming);
+/// let session = Agraphon::from_announcement_pair(
+///     &self_outgoing,
+///     &peer_incoming,
+///     SkippedMessageLimits::default(),
+/// );
 /// ```
 ///
 /// # Sending Messages
@@ -73,18 +229,32 @@ pub struct FeedIncomingMessageResult {
 /// ).expect("Failed to decrypt message");
 /// // Access decrypted message with result
 /// ```
+///
+/// To check the decrypted message before the session state changes, decrypt it with
+/// [`try_decrypt_incoming_message`](Self::try_decrypt_incoming_message) and then accept it
+/// with [`accept_incoming_message`](Self::accept_incoming_message).
 #[derive(Serialize, Deserialize, Zeroize)]
 pub struct Agraphon {
     // Boxed to avoid vecdeque realloc non-zeroed memory
     #[zeroize(skip)]
     self_msg_history: VecDeque<Box<HistoryItemSelf>>,
     latest_peer_msg: HistoryItemPeer,
+    // Boxed to avoid vecdeque realloc non-zeroed memory
+    #[zeroize(skip)]
+    skipped_peer_msgs: VecDeque<Box<SkippedItemPeer>>,
+    // Boxed to avoid vecdeque realloc non-zeroed memory
+    #[zeroize(skip)]
+    skipped_chain_keys: VecDeque<Box<SkippedChainKeyPeer>>,
+    #[zeroize(skip)]
+    limits: SkippedMessageLimits,
 }
 
 impl Drop for Agraphon {
     fn drop(&mut self) {
         // Each  dropped item will zeroize itself.
         self.self_msg_history.clear();
+        self.skipped_peer_msgs.clear();
+        self.skipped_chain_keys.clear();
 
         // Wipe the rest via the derived impl (deque is skipped).
         Zeroize::zeroize(self);
@@ -93,6 +263,70 @@ impl Drop for Agraphon {
 
 impl ZeroizeOnDrop for Agraphon {}
 
+/// [`Agraphon`] state as serialized before out-of-order delivery was introduced.
+///
+/// It can only be deserialized, to be upgraded with [`AgraphonV1::from_v0`].
+#[derive(Deserialize)]
+pub struct AgraphonV0 {
+    self_msg_history: VecDeque<Box<HistoryItemSelfV0>>,
+    latest_peer_msg: HistoryItemPeerV0,
+}
+
+/// [`Agraphon`] state of schema version 1, in which all the messages of a party were linked
+/// by a single chain that was never re-seeded.
+///
+/// Its sessions cannot be carried over to the current chains: it is only kept so that older
+/// states can be upgraded through it.
+#[derive(Serialize, Deserialize)]
+pub struct AgraphonV1 {
+    self_msg_history: VecDeque<Box<HistoryItemSelfV1>>,
+    latest_peer_msg: HistoryItemPeerV1,
+    skipped_peer_msgs: VecDeque<Box<SkippedItemPeerV1>>,
+    limits: SkippedMessageLimits,
+}
+
+impl AgraphonV1 {
+    /// Upgrades a session saved before out-of-order delivery was introduced.
+    ///
+    /// Each side used to encrypt its next message under the root key of its previous one,
+    /// which becomes the chain key of that side.
+    ///
+    /// # Arguments
+    ///
+    /// * `legacy` - The deserialized session
+    /// * `limits` - Limits on the state kept for out-of-order peer messages
+    #[must_use]
+    pub fn from_v0(legacy: &AgraphonV0, limits: SkippedMessageLimits) -> Self {
+        let self_msg_history = legacy
+            .self_msg_history
+            .iter()
+            .map(|msg| {
+                Box::new(HistoryItemSelfV1 {
+                    seeker: msg.seeker.clone(),
+                    height: msg.height,
+                    sk_next: KemSecretKey::from(&msg.sk_next),
+                    k_next: msg.k_next,
+                    k_chain: msg.k_next,
+                })
+            })
+            .collect();
+
+        let latest_peer_msg = HistoryItemPeerV1 {
+            our_parent_height: legacy.latest_peer_msg.our_parent_height,
+            pk_next: KemPublicKey::from(&legacy.latest_peer_msg.pk_next),
+            k_next: legacy.latest_peer_msg.k_next,
+            k_chain: legacy.latest_peer_msg.k_next,
+        };
+
+        Self {
+            self_msg_history,
+            latest_peer_msg,
+            skipped_peer_msgs: VecDeque::new(),
+            limits,
+        }
+    }
+}
+
 impl Agraphon {
     /// Creates a session by joining an outgoing and incoming announcement.
     ///
@@ -104,48 +338,77 @@ impl Agraphon {
     ///
     /// * `self_outgoing_announcement` - Your finalized outgoing announcement
     /// * `peer_incoming_announcement` - The incoming announcement from your peer
+    /// * `limits` - Limits on the state kept for out-of-order peer messages
     ///
     /// # Returns
     ///
     /// `Some(Agraphon)` session, or `None` if creation fails.
     ///
+    /// # Panics
+    ///
+    /// Panics if the announcements are of different protocol versions, which cannot happen
+    /// when both parties have static keys of the same version.
+    ///
     /// # Examples
     ///
     /// ```no_run
-    /// # use crypto_agraphon::{Agraphon, OutgoingAnnouncement, IncomingAnnouncement};
+    /// # use crypto_agraphon::{Agraphon, OutgoingAnnouncement, IncomingAnnouncement, SkippedMessageLimits};
     /// # use crypto_kem as kem;
     /// # let self_announcement: OutgoingAnnouncement = todo!();
     /// # let peer_announcement: IncomingAnnouncement = todo!();
     /// let session = Agraphon::from_announcement_pair(
     ///     &self_announcement,
-    ///     &peer_announcement
+    ///     &peer_announcement,
+    ///     SkippedMessageLimits::default(),
     /// );
     /// ```
     pub fn from_announcement_pair(
         self_outgoing_announcement: &OutgoingAnnouncement,
         peer_incoming_announcement: &IncomingAnnouncement,
+        limits: SkippedMessageLimits,
     ) -> Self {
+        assert_eq!(
+            self_outgoing_announcement.sk_next.version(),
+            peer_incoming_announcement.pk_next.version(),
+            "Announcements of different protocol versions"
+        );
+
         // Create history
         let mut self_msg_history = VecDeque::new();
         self_msg_history.push_back(Box::new(HistoryItemSelf {
             height: 1,
             sk_next: self_outgoing_announcement.sk_next.clone(),
             k_next: self_outgoing_announcement.k_next,
+            k_chain: MessageChainKdf::from_root_key(&self_outgoing_announcement.k_next)
+                .k_chain_next,
             seeker: Vec::new(),
         }));
 
         let latest_peer_msg = HistoryItemPeer {
+            height: 1,
             our_parent_height: 0,
             pk_next: peer_incoming_announcement.pk_next.clone(),
             k_next: peer_incoming_announcement.k_next,
+            k_chain: MessageChainKdf::from_root_key(&peer_incoming_announcement.k_next)
+                .k_chain_next,
         };
 
         Self {
             self_msg_history,
             latest_peer_msg,
+            skipped_peer_msgs: VecDeque::new(),
+            skipped_chain_keys: VecDeque::new(),
+            limits,
         }
     }
 
+    /// Returns the protocol version of the session, which is the one of the static keys
+    /// of both parties.
+    #[must_use]
+    pub const fn version(&self) -> ProtocolVersion {
+        self.latest_peer_msg.pk_next.version()
+    }
+
     /// Internal helper to retrieve a sent message by its local end height.
     fn get_self_message_by_height(&self, height: u64) -> Option<&HistoryItemSelf> {
         let first_height = self.self_msg_history.front()?.height;
@@ -155,10 +418,19 @@ impl Agraphon {
             .map(|b| &**b)
     }
 
+    /// Internal helper to retrieve a sent message by its local end height, mutably.
+    fn get_self_message_by_height_mut(&mut self, height: u64) -> Option<&mut HistoryItemSelf> {
+        let first_height = self.self_msg_history.front()?.height;
+        let index = height.checked_sub(first_height)?;
+        self.self_msg_history
+            .get_mut(index.try_into().ok()?)
+            .map(|b| &mut **b)
+    }
+
     /// Attempts to decrypt and process an incoming message.
     ///
-    /// This method automatically tries all possible parent messages in the history
-    /// to find the one that can successfully decrypt the message.
+    /// This is [`try_decrypt_incoming_message`](Self::try_decrypt_incoming_message)
+    /// immediately followed by [`accept_incoming_message`](Self::accept_incoming_message).
     ///
     /// # Arguments
     ///
@@ -167,14 +439,16 @@ impl Agraphon {
     ///
     /// # Returns
     ///
-    /// `Some(FeedIncomingMessageResult)` if decryption succeeds and the integrity check passes,
-    /// `None` if the message is malformed, cannot be decrypted, or fails integrity check.
+    /// `Ok(FeedIncomingMessageResult)` if decryption succeeds and the integrity check passes.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`try_decrypt_incoming_message`](Self::try_decrypt_incoming_message).
     ///
     /// # Side Effects
     ///
-    /// On success:
-    /// - Updates `latest_peer_msg` with the peer's new state
-    /// - Prunes old messages from our history (messages older than the found parent)
+    /// Same as [`accept_incoming_message`](Self::accept_incoming_message).
+    /// On failure, the session state is left untouched.
     ///
     /// # Examples
     ///
@@ -191,114 +465,370 @@ impl Agraphon {
     ///
     /// println!("Received: {}", String::from_utf8_lossy(&result.message_bytes));
     /// ```
-    #[allow(clippy::similar_names)] // msg_sk and msg_ss are standard naming
     pub fn try_feed_incoming_message(
         &mut self,
-        self_static_sk: &kem::SecretKey,
+        self_static_sk: impl Into<KemSecretKey>,
         message: &[u8],
-    ) -> Option<FeedIncomingMessageResult> {
-        // Try to decode the incoming message assuming various possible choices of parent on the self side.
-        let scan_ids = self
-            .self_msg_history
-            .iter()
-            .rev()
-            .map(|msg| msg.height)
-            .collect::<Vec<u64>>();
-        for p_self_id in scan_ids {
-            if let Some(res) =
-                self.try_incoming_message_with_self_parent(p_self_id, self_static_sk, message)
-            {
-                return Some(res);
-            }
-        }
-        None
+    ) -> Result<FeedIncomingMessageResult, AgraphonError> {
+        let decrypted = self.try_decrypt_incoming_message(self_static_sk, message)?;
+        self.accept_incoming_message(decrypted)
     }
 
-    fn try_incoming_message_with_self_parent(
-        &mut self,
-        our_parent_height: u64,
-        self_static_sk: &kem::SecretKey,
+    /// Attempts to decrypt an incoming message without modifying the session.
+    ///
+    /// This method automatically tries all the parent messages the peer may have responded
+    /// to, from the newest one down. Messages may be fed in any order, within the configured
+    /// [`SkippedMessageLimits`]. For each parent, the message is tried:
+    ///
+    /// 1. As the next response to that parent
+    /// 2. As a response to that parent that was previously stepped over
+    /// 3. As a response coming after up to `max_skip` responses to that parent that
+    ///    have not been received yet
+    ///
+    /// The decrypted message must then be passed to
+    /// [`accept_incoming_message`](Self::accept_incoming_message) to apply it to the session.
+    ///
+    /// # Arguments
+    ///
+    /// * `self_static_sk` - Our static secret key (for decrypting KEM ciphertext)
+    /// * `message` - The encrypted message bytes
+    ///
+    /// # Errors
+    ///
+    /// - [`AgraphonError::Malformed`] if the message is truncated
+    /// - [`AgraphonError::DecryptionFailed`] if the message cannot be decrypted, fails the
+    ///   integrity check, was already received, or is beyond the [`SkippedMessageLimits`].
+    ///   These causes are indistinguishable on purpose.
+    /// - [`AgraphonError::VersionMismatch`] if `self_static_sk` is not of the
+    ///   [`version`](Self::version) of the session
+    pub fn try_decrypt_incoming_message(
+        &self,
+        self_static_sk: impl Into<KemSecretKey>,
         message: &[u8],
-    ) -> Option<FeedIncomingMessageResult> {
-        let self_msg = &self.latest_peer_msg;
-        let peer_msg = self.get_self_message_by_height(our_parent_height)?;
-        let peer_msg_seeker = peer_msg.seeker.clone();
+    ) -> Result<DecryptedIncomingMessage, AgraphonError> {
+        let incoming = IncomingMessage::parse(self.version(), &self_static_sk.into(), message)?;
 
-        // read randomness
-        let msg_randomness: [u8; 32] = message.get(..32)?.try_into().ok()?;
+        // The peer always responds to the latest of our messages it has seen, so a message
+        // cannot respond to a parent older than the one of the last peer message before it
+        let min_parent_height = self
+            .skipped_peer_msgs
+            .iter()
+            .map(|skipped| skipped.min_parent_height)
+            .fold(self.latest_peer_msg.our_parent_height, u64::min);
 
-        // read ct
-        let msg_ct: [u8; kem::CIPHERTEXT_SIZE] = message
-            .get(32..32 + kem::CIPHERTEXT_SIZE)?
-            .try_into()
-            .ok()?;
-        let msg_ct: kem::Ciphertext = msg_ct.into();
+        for parent in self.self_msg_history.iter().rev() {
+            if parent.height < min_parent_height {
+                break;
+            }
 
-        // read ct_static
-        let msg_ct_static: [u8; kem::CIPHERTEXT_SIZE] = message
-            .get(32 + kem::CIPHERTEXT_SIZE..32 + 2 * kem::CIPHERTEXT_SIZE)?
-            .try_into()
-            .ok()?;
-        let msg_ct_static: kem::Ciphertext = msg_ct_static.into();
+            // decapsulate ct
+            let Some(msg_ss) = parent.sk_next.decapsulate(&incoming.ct) else {
+                continue;
+            };
+
+            // try the next response to this parent
+            if let Some(decrypted) =
+                self.try_decrypt_message(&incoming, parent, &msg_ss, &parent.k_chain)
+            {
+                return self.checked_incoming_message(DecryptedIncomingMessage {
+                    decrypted,
+                    k_chain: parent.k_chain,
+                    stepped_over: false,
+                    stepped_over_k_chains: Vec::new(),
+                });
+            }
 
-        // decapsulate ct
-        let msg_ss = kem::decapsulate(&peer_msg.sk_next, &msg_ct);
+            // try the responses to this parent that were stepped over
+            for skipped in self
+                .skipped_chain_keys
+                .iter()
+                .filter(|skipped| skipped.our_parent_height == parent.height)
+            {
+                if let Some(decrypted) =
+                    self.try_decrypt_message(&incoming, parent, &msg_ss, &skipped.k_chain)
+                {
+                    return self.checked_incoming_message(DecryptedIncomingMessage {
+                        decrypted,
+                        k_chain: skipped.k_chain,
+                        stepped_over: true,
+                        stepped_over_k_chains: Vec::new(),
+                    });
+                }
+            }
 
-        // decapsulate ct_static
-        let msg_ss_static = kem::decapsulate(self_static_sk, &msg_ct_static);
+            // try the responses coming after responses we have not received yet
+            let mut stepped_over_k_chains = Vec::new();
+            let mut k_chain = parent.k_chain;
+            for _ in 0..self.limits.max_skip {
+                let next_k_chain = MessageChainKdf::new(&k_chain).k_chain_next;
+                stepped_over_k_chains.push(std::mem::replace(&mut k_chain, next_k_chain));
+                if let Some(decrypted) =
+                    self.try_decrypt_message(&incoming, parent, &msg_ss, &k_chain)
+                {
+                    return self.checked_incoming_message(DecryptedIncomingMessage {
+                        decrypted,
+                        k_chain,
+                        stepped_over: false,
+                        stepped_over_k_chains,
+                    });
+                }
+            }
+            k_chain.zeroize();
+            stepped_over_k_chains.zeroize();
+        }
 
+        Err(AgraphonError::DecryptionFailed)
+    }
+
+    /// Tries to decrypt a message responding to a given parent under a given chain key.
+    fn try_decrypt_message(
+        &self,
+        incoming: &IncomingMessage<'_>,
+        parent: &HistoryItemSelf,
+        msg_ss: &kem::SharedSecret,
+        k_chain: &[u8; 32],
+    ) -> Option<DecryptedMessage> {
         // root KDF
         let msg_root_kdf = MessageRootKdf::new(
-            &msg_randomness,
-            &self_msg.k_next,
-            &peer_msg.k_next,
-            &msg_ct,
-            &msg_ss,
-            &msg_ct_static,
-            &msg_ss_static,
+            self.version(),
+            &incoming.randomness,
+            k_chain,
+            &parent.k_next,
+            &Encapsulation::new(&incoming.ct, msg_ss),
+            &Encapsulation::new(&incoming.ct_static, &incoming.ss_static),
         );
 
         // decrypt ciphertext with authentication and padding
-        let ciphertext = message.get(32 + 2 * kem::CIPHERTEXT_SIZE..)?;
         let content = Zeroizing::new(aead::decrypt(
             &msg_root_kdf.cipher_key,
             &msg_root_kdf.cipher_nonce,
-            ciphertext,
+            incoming.ciphertext,
             b"",
         )?);
 
-        // parse pk_next
-        let pk_next: [u8; kem::PUBLIC_KEY_SIZE] =
-            content.get(..kem::PUBLIC_KEY_SIZE)?.try_into().ok()?;
-        let pk_next: kem::PublicKey = pk_next.into();
-
-        // parse payload
-        let payload = content.get(kem::PUBLIC_KEY_SIZE..)?.to_vec();
-
-        // update last history item
-        self.latest_peer_msg = HistoryItemPeer {
-            our_parent_height,
+        // parse pk_next, height and payload
+        let pk_size = self.version().kem_public_key_size();
+        let pk_next = content
+            .get(..pk_size)
+            .and_then(|bytes| KemPublicKey::from_bytes(self.version(), bytes))?;
+        let height = content
+            .get(pk_size..pk_size + HEIGHT_SIZE)
+            .and_then(|bytes| bytes.try_into().ok())
+            .map(u64::from_be_bytes)?;
+        let payload = content.get(pk_size + HEIGHT_SIZE..)?.to_vec();
+
+        Some(DecryptedMessage {
+            height,
+            our_parent_height: parent.height,
             pk_next,
             k_next: msg_root_kdf.k_next,
+            payload,
+        })
+    }
+
+    /// Returns the decrypted message if the session can accept it.
+    fn checked_incoming_message(
+        &self,
+        incoming: DecryptedIncomingMessage,
+    ) -> Result<DecryptedIncomingMessage, AgraphonError> {
+        if self.is_acceptable(&incoming.decrypted) {
+            Ok(incoming)
+        } else {
+            Err(AgraphonError::DecryptionFailed)
+        }
+    }
+
+    /// Checks that a decrypted message is either newer than the latest peer message,
+    /// within `max_skip`, or one of the peer messages that are still missing.
+    ///
+    /// Any other message was already received, or was forgotten as lost.
+    fn is_acceptable(&self, decrypted: &DecryptedMessage) -> bool {
+        let latest = &self.latest_peer_msg;
+        if decrypted.height > latest.height {
+            return decrypted.height - latest.height - 1 <= self.limits.max_skip
+                && decrypted.our_parent_height >= latest.our_parent_height;
+        }
+        self.skipped_peer_msgs.iter().any(|skipped| {
+            skipped.height == decrypted.height
+                && decrypted.our_parent_height >= skipped.min_parent_height
+        })
+    }
+
+    /// Applies a message decrypted by
+    /// [`try_decrypt_incoming_message`](Self::try_decrypt_incoming_message) to the session.
+    ///
+    /// # Errors
+    ///
+    /// [`AgraphonError::DecryptionFailed`] if the session changed since the message was
+    /// decrypted, so that the message can no longer be accepted.
+    ///
+    /// # Side Effects
+    ///
+    /// On success:
+    /// - Updates `latest_peer_msg` with the peer's new state and starts the chain of our
+    ///   responses to it, unless the message is a late one
+    /// - Remembers the peer messages skipped over, or forgets the one of a late message
+    /// - Steps the chain of responses to the parent forward, storing the chain keys stepped
+    ///   over, or forgets the stored chain key of a late message
+    /// - Forgets the skipped messages beyond the [`SkippedMessageLimits`], which are deemed lost
+    /// - Prunes old messages from our history (messages older than the found parent,
+    ///   and older than any parent a still-missing peer message could respond to)
+    ///
+    /// On failure, the session state is left untouched.
+    pub fn accept_incoming_message(
+        &mut self,
+        incoming: DecryptedIncomingMessage,
+    ) -> Result<FeedIncomingMessageResult, AgraphonError> {
+        let decrypted = &incoming.decrypted;
+        let parent_height = decrypted.our_parent_height;
+
+        // check that the session did not change in a way that forbids the message
+        if !self.is_acceptable(decrypted) {
+            return Err(AgraphonError::DecryptionFailed);
+        }
+        let skipped_index = if incoming.stepped_over {
+            let index = self.skipped_chain_keys.iter().position(|skipped| {
+                skipped.our_parent_height == parent_height
+                    && bool::from(skipped.k_chain.ct_eq(&incoming.k_chain))
+            });
+            Some(index.ok_or(AgraphonError::DecryptionFailed)?)
+        } else {
+            let parent = self
+                .get_self_message_by_height(parent_height)
+                .ok_or(AgraphonError::DecryptionFailed)?;
+            let expected_k_chain = incoming
+                .stepped_over_k_chains
+                .first()
+                .unwrap_or(&incoming.k_chain);
+            if !bool::from(parent.k_chain.ct_eq(expected_k_chain)) {
+                return Err(AgraphonError::DecryptionFailed);
+            }
+            None
         };
 
-        // prune old messages that cannot be parents anymore
+        // consume the chain key of the message
+        if let Some(index) = skipped_index {
+            self.skipped_chain_keys.remove(index);
+        } else {
+            for k_chain in &incoming.stepped_over_k_chains {
+                self.skipped_chain_keys
+                    .push_back(Box::new(SkippedChainKeyPeer {
+                        our_parent_height: parent_height,
+                        k_chain: *k_chain,
+                    }));
+            }
+            if let Some(parent) = self.get_self_message_by_height_mut(parent_height) {
+                parent.k_chain = MessageChainKdf::new(&incoming.k_chain).k_chain_next;
+            }
+        }
+
+        if decrypted.height > self.latest_peer_msg.height {
+            // remember the skipped messages, they cannot respond to parents older than
+            // the one of the latest message
+            let min_parent_height = self.latest_peer_msg.our_parent_height;
+            for height in self.latest_peer_msg.height + 1..decrypted.height {
+                self.skipped_peer_msgs.push_back(Box::new(SkippedItemPeer {
+                    height,
+                    min_parent_height,
+                }));
+            }
+
+            // update last history item and start the chain of our responses to it
+            self.latest_peer_msg = HistoryItemPeer {
+                height: decrypted.height,
+                our_parent_height: parent_height,
+                pk_next: decrypted.pk_next.clone(),
+                k_next: decrypted.k_next,
+                k_chain: MessageChainKdf::from_root_key(&decrypted.k_next).k_chain_next,
+            };
+        } else {
+            // each skipped message can only be received once
+            self.skipped_peer_msgs
+                .retain(|skipped| skipped.height != decrypted.height);
+        }
+
+        self.forget_stale_skipped_messages();
+        Ok(self.finalize_incoming_message(decrypted))
+    }
+
+    /// Forgets the skipped peer messages beyond the [`SkippedMessageLimits`].
+    ///
+    /// Skipped messages are stored oldest first, with non-decreasing `min_parent_height`,
+    /// so they are forgotten from the front: first the ones beyond `max_stored`, then the ones
+    /// that the peer has moved past by more than `max_stored` of our messages. Our history is
+    /// pruned accordingly when the message being fed is finalized.
+    fn forget_stale_skipped_messages(&mut self) {
+        let max_stored = self.limits.max_stored;
+        let latest_parent_height = self.latest_peer_msg.our_parent_height;
+        while self.skipped_peer_msgs.front().is_some_and(|skipped| {
+            self.skipped_peer_msgs.len() as u64 > max_stored
+                || skipped.min_parent_height.saturating_add(max_stored) < latest_parent_height
+        }) {
+            self.skipped_peer_msgs.pop_front();
+        }
+    }
+
+    /// Prunes our history and the stored chain keys, and assembles the result of a
+    /// successfully decrypted message.
+    fn finalize_incoming_message(
+        &mut self,
+        decrypted: &DecryptedMessage,
+    ) -> FeedIncomingMessageResult {
+        let parent_seeker = self
+            .get_self_message_by_height(decrypted.our_parent_height)
+            .map(|msg| msg.seeker.clone())
+            .unwrap_or_default();
+
+        // prune old messages that cannot be parents anymore,
+        // keeping the ones that still-missing peer messages could respond to
+        let prune_height = self
+            .skipped_peer_msgs
+            .iter()
+            .map(|skipped| skipped.min_parent_height)
+            .fold(self.latest_peer_msg.our_parent_height, u64::min);
         let mut newly_acknowledged_seekers = Vec::new();
         while self
             .self_msg_history
             .front()
-            .is_some_and(|msg| msg.height < our_parent_height)
+            .is_some_and(|msg| msg.height < prune_height)
         {
             if let Some(popped_msg) = self.self_msg_history.pop_front() {
                 newly_acknowledged_seekers.push(popped_msg.seeker.clone());
             }
         }
-        newly_acknowledged_seekers.push(peer_msg_seeker);
+        newly_acknowledged_seekers.push(parent_seeker);
+
+        // stepped over chain keys are only useful to still-missing peer messages
+        // responding to parents that are still in our history
+        if self.skipped_peer_msgs.is_empty() {
+            self.skipped_chain_keys.clear();
+        } else {
+            self.skipped_chain_keys
+                .retain(|skipped| skipped.our_parent_height >= prune_height);
+            while self.skipped_chain_keys.len() as u64 > self.limits.max_stored {
+                self.skipped_chain_keys.pop_front();
+            }
+        }
 
-        Some(FeedIncomingMessageResult {
-            message_bytes: payload,
+        FeedIncomingMessageResult {
+            message_bytes: decrypted.payload.clone(),
             newly_acknowledged_self_seekers: newly_acknowledged_seekers,
-        })
+        }
+    }
+
+    /// Returns the number of skipped peer messages currently stored
+    /// in case they are received later.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use crypto_agraphon::Agraphon;
+    /// # let session: Agraphon = todo!();
+    /// println!("{} peer messages are still missing", session.skipped_message_count());
+    /// ```
+    #[must_use]
+    pub fn skipped_message_count(&self) -> u64 {
+        self.skipped_peer_msgs.len() as u64
     }
 
     /// Encrypts and sends an outgoing message.
@@ -325,6 +855,7 @@ impl Agraphon {
     /// - Generates a new ephemeral key pair
     /// - Adds a new history item to `self_msg_history`
     /// - Increments the local message ID
+    /// - Steps the chain of our responses to the latest peer message forward
     ///
     /// # Examples
     ///
@@ -344,12 +875,22 @@ impl Agraphon {
     ///
     /// Panics if the internal message history is empty. This should never happen in normal
     /// operation as the history is initialized during session creation.
+    ///
+    /// Panics if `peer_static_pk` is not of the [`version`](Self::version) of the session.
     pub fn send_outgoing_message(
         &mut self,
         seeker: &[u8],
         payload: &[u8],
-        peer_static_pk: &kem::PublicKey,
+        peer_static_pk: impl Into<KemPublicKey>,
     ) -> Vec<u8> {
+        let version = self.version();
+        let peer_static_pk = peer_static_pk.into();
+        assert_eq!(
+            peer_static_pk.version(),
+            version,
+            "Static public key of another protocol version than the session"
+        );
+
         // choose parent messages
         let p_self = self
             .self_msg_history
@@ -362,39 +903,28 @@ impl Agraphon {
         rng::fill_buffer(msg_randomness.as_mut_slice());
 
         // encapsulate peer parent's pk_next
-        let (msg_ct, msg_ss) = {
-            let mut kem_randomness = [0u8; kem::ENCAPSULATION_RANDOMNESS_SIZE];
-            rng::fill_buffer(&mut kem_randomness);
-            kem::encapsulate(&p_peer.pk_next, kem_randomness)
-        };
+        let (msg_ct, msg_ss) = p_peer.pk_next.encapsulate();
 
         // encapsulate the peer's static key
-        let (msg_ct_static, msg_ss_static) = {
-            let mut kem_randomness = [0u8; kem::ENCAPSULATION_RANDOMNESS_SIZE];
-            rng::fill_buffer(&mut kem_randomness);
-            kem::encapsulate(peer_static_pk, kem_randomness)
-        };
+        let (msg_ct_static, msg_ss_static) = peer_static_pk.encapsulate();
 
         // root KDF
         let msg_root_kdf = MessageRootKdf::new(
+            version,
             &msg_randomness,
-            &p_self.k_next,
+            &p_peer.k_chain,
             &p_peer.k_next,
-            &msg_ct,
-            &msg_ss,
-            &msg_ct_static,
-            &msg_ss_static,
+            &Encapsulation::new(&msg_ct, &msg_ss),
+            &Encapsulation::new(&msg_ct_static, &msg_ss_static),
         );
 
         // generate pk_next
-        let (sk_next, pk_next) = {
-            let mut pk_randomness = [0u8; kem::KEY_GENERATION_RANDOMNESS_SIZE];
-            rng::fill_buffer(&mut pk_randomness);
-            kem::generate_key_pair(pk_randomness)
-        };
+        let (sk_next, pk_next) = version.generate_key_pair();
 
         // generate plaintext
-        let plaintext = Zeroizing::new([pk_next.as_bytes(), payload].concat());
+        let height = p_self.height + 1;
+        let plaintext =
+            Zeroizing::new([pk_next.as_bytes(), height.to_be_bytes().as_slice(), payload].concat());
 
         // encrypt
         let ciphertext = Zeroizing::new(aead::encrypt(
@@ -404,20 +934,23 @@ impl Agraphon {
             b"",
         ));
 
-        // push self message to history
-        let height = p_self.height + 1;
+        // step the chain of our responses to the peer message forward
+        self.latest_peer_msg.k_chain = MessageChainKdf::new(&p_peer.k_chain).k_chain_next;
+
+        // push self message to history, starting the chain of peer responses to it
         self.self_msg_history.push_back(Box::new(HistoryItemSelf {
             height,
             sk_next,
             k_next: msg_root_kdf.k_next,
+            k_chain: MessageChainKdf::from_root_key(&msg_root_kdf.k_next).k_chain_next,
             seeker: seeker.to_vec(),
         }));
 
         // assemble full message
         [
             msg_randomness.as_slice(),
-            msg_ct.as_bytes().as_slice(),
-            msg_ct_static.as_bytes().as_slice(),
+            msg_ct.as_bytes(),
+            msg_ct_static.as_bytes(),
             &ciphertext,
         ]
         .concat()
@@ -484,6 +1017,19 @@ mod tests {
         kem::PublicKey,
         kem::SecretKey,
         kem::PublicKey,
+    ) {
+        setup_sessions_with_limits(SkippedMessageLimits::default())
+    }
+
+    fn setup_sessions_with_limits(
+        limits: SkippedMessageLimits,
+    ) -> (
+        Agraphon,
+        Agraphon,
+        kem::SecretKey,
+        kem::PublicKey,
+        kem::SecretKey,
+        kem::PublicKey,
     ) {
         // Generate key pairs
         let mut alice_rand = [0u8; kem::KEY_GENERATION_RANDOMNESS_SIZE];
@@ -527,8 +1073,10 @@ mod tests {
             .expect("Integrity check failed");
 
         // Both create sessions
-        let alice_session = Agraphon::from_announcement_pair(&alice_announcement, &bob_incoming);
-        let bob_session = Agraphon::from_announcement_pair(&bob_announcement, &alice_incoming);
+        let alice_session =
+            Agraphon::from_announcement_pair(&alice_announcement, &bob_incoming, limits);
+        let bob_session =
+            Agraphon::from_announcement_pair(&bob_announcement, &alice_incoming, limits);
 
         (
             alice_session,
@@ -620,10 +1168,465 @@ mod tests {
         assert_eq!(&decrypted4.message_bytes, msg4);
     }
 
-    // Note: The protocol does NOT support out-of-order message delivery.
-    // Messages must be processed in the order they were sent due to the ratcheting
-    // of k_next values. However, it does support either party sending multiple
-    // messages in succession without waiting for a reply from the other party.
+    #[test]
+    fn test_out_of_order_delivery() {
+        // Alice sends three messages, Bob receives them in the order 3, 1, 2
+        let (mut alice_session, mut bob_session, _alice_sk, _alice_pk, bob_sk, bob_pk) =
+            setup_sessions();
+
+        let result1 = alice_session.send_outgoing_message(b"seeker", b"Message 1", &bob_pk);
+        let result2 = alice_session.send_outgoing_message(b"seeker", b"Message 2", &bob_pk);
+        let result3 = alice_session.send_outgoing_message(b"seeker", b"Message 3", &bob_pk);
+
+        let decrypted3 = bob_session
+            .try_feed_incoming_message(&bob_sk, &result3)
+            .expect("Failed to decrypt message 3 ahead of the others");
+        assert_eq!(&decrypted3.message_bytes, b"Message 3");
+        assert_eq!(bob_session.skipped_message_count(), 2);
+
+        let decrypted1 = bob_session
+            .try_feed_incoming_message(&bob_sk, &result1)
+            .expect("Failed to decrypt late message 1");
+        assert_eq!(&decrypted1.message_bytes, b"Message 1");
+        assert_eq!(bob_session.skipped_message_count(), 1);
+
+        let decrypted2 = bob_session
+            .try_feed_incoming_message(&bob_sk, &result2)
+            .expect("Failed to decrypt late message 2");
+        assert_eq!(&decrypted2.message_bytes, b"Message 2");
+        assert_eq!(bob_session.skipped_message_count(), 0);
+    }
+
+    #[test]
+    fn test_gap_then_conversation_continues() {
+        // Alice's message 2 is missing for a while: the conversation continues around it
+        let (mut alice_session, mut bob_session, alice_sk, alice_pk, bob_sk, bob_pk) =
+            setup_sessions();
+
+        let result1 = alice_session.send_outgoing_message(b"seeker", b"Message 1", &bob_pk);
+        let result2 = alice_session.send_outgoing_message(b"seeker", b"Message 2", &bob_pk);
+        let result3 = alice_session.send_outgoing_message(b"seeker", b"Message 3", &bob_pk);
+
+        bob_session
+            .try_feed_incoming_message(&bob_sk, &result1)
+            .expect("Failed to decrypt message 1");
+        bob_session
+            .try_feed_incoming_message(&bob_sk, &result3)
+            .expect("Failed to decrypt message 3 over the gap");
+
+        // Bob replies, Alice receives the reply and sends again
+        let bob_result = bob_session.send_outgoing_message(b"seeker", b"Bob reply", &alice_pk);
+        let decrypted_bob = alice_session
+            .try_feed_incoming_message(&alice_sk, &bob_result)
+            .expect("Failed to decrypt Bob's reply");
+        assert_eq!(&decrypted_bob.message_bytes, b"Bob reply");
+
+        let result4 = alice_session.send_outgoing_message(b"seeker", b"Message 4", &bob_pk);
+        let decrypted4 = bob_session
+            .try_feed_incoming_message(&bob_sk, &result4)
+            .expect("Failed to decrypt message 4");
+        assert_eq!(&decrypted4.message_bytes, b"Message 4");
+
+        // The missing message finally shows up
+        let decrypted2 = bob_session
+            .try_feed_incoming_message(&bob_sk, &result2)
+            .expect("Failed to decrypt late message 2");
+        assert_eq!(&decrypted2.message_bytes, b"Message 2");
+        assert_eq!(bob_session.skipped_message_count(), 0);
+    }
+
+    #[test]
+    fn test_late_message_responding_to_older_parent() {
+        // Alice's late message responds to one of Bob's messages that is older than
+        // the parent of Alice's latest message: Bob must not have pruned it
+        let (mut alice_session, mut bob_session, alice_sk, alice_pk, bob_sk, bob_pk) =
+            setup_sessions();
+
+        let bob_result1 = bob_session.send_outgoing_message(b"bob1", b"Bob 1", &alice_pk);
+        alice_session
+            .try_feed_incoming_message(&alice_sk, &bob_result1)
+            .expect("Failed to decrypt Bob 1");
+        let alice_result1 = alice_session.send_outgoing_message(b"alice1", b"Alice 1", &bob_pk);
+
+        let bob_result2 = bob_session.send_outgoing_message(b"bob2", b"Bob 2", &alice_pk);
+        alice_session
+            .try_feed_incoming_message(&alice_sk, &bob_result2)
+            .expect("Failed to decrypt Bob 2");
+        let alice_result2 = alice_session.send_outgoing_message(b"alice2", b"Alice 2", &bob_pk);
+
+        // Bob receives Alice 2 (responding to Bob 2) before Alice 1 (responding to Bob 1)
+        let decrypted2 = bob_session
+            .try_feed_incoming_message(&bob_sk, &alice_result2)
+            .expect("Failed to decrypt Alice 2");
+        assert_eq!(&decrypted2.message_bytes, b"Alice 2");
+        assert!(
+            !decrypted2
+                .newly_acknowledged_self_seekers
+                .contains(&b"bob1".to_vec()),
+            "Bob 1 must be kept while Alice 1 is missing"
+        );
+
+        let decrypted1 = bob_session
+            .try_feed_incoming_message(&bob_sk, &alice_result1)
+            .expect("Failed to decrypt late Alice 1");
+        assert_eq!(&decrypted1.message_bytes, b"Alice 1");
+        assert!(
+            decrypted1
+                .newly_acknowledged_self_seekers
+                .contains(&b"bob1".to_vec())
+        );
+    }
+
+    #[test]
+    fn test_duplicate_messages_rejected() {
+        let (mut alice_session, mut bob_session, _alice_sk, _alice_pk, bob_sk, bob_pk) =
+            setup_sessions();
+
+        let result1 = alice_session.send_outgoing_message(b"seeker", b"Message 1", &bob_pk);
+        let result2 = alice_session.send_outgoing_message(b"seeker", b"Message 2", &bob_pk);
+        let result3 = alice_session.send_outgoing_message(b"seeker", b"Message 3", &bob_pk);
+
+        // duplicate of an in-order message
+        bob_session
+            .try_feed_incoming_message(&bob_sk, &result1)
+            .expect("Failed to decrypt message 1");
+        assert_eq!(
+            bob_session
+                .try_feed_incoming_message(&bob_sk, &result1)
+                .err(),
+            Some(AgraphonError::DecryptionFailed)
+        );
+
+        // duplicate of a late message
+        bob_session
+            .try_feed_incoming_message(&bob_sk, &result3)
+            .expect("Failed to decrypt message 3");
+        bob_session
+            .try_feed_incoming_message(&bob_sk, &result2)
+            .expect("Failed to decrypt late message 2");
+        assert_eq!(
+            bob_session
+                .try_feed_incoming_message(&bob_sk, &result2)
+                .err(),
+            Some(AgraphonError::DecryptionFailed)
+        );
+        assert_eq!(
+            bob_session
+                .try_feed_incoming_message(&bob_sk, &result3)
+                .err(),
+            Some(AgraphonError::DecryptionFailed)
+        );
+
+        // rejected duplicates leave the session usable
+        let result4 = alice_session.send_outgoing_message(b"seeker", b"Message 4", &bob_pk);
+        let decrypted4 = bob_session
+            .try_feed_incoming_message(&bob_sk, &result4)
+            .expect("Failed to decrypt message 4");
+        assert_eq!(&decrypted4.message_bytes, b"Message 4");
+    }
+
+    #[test]
+    fn test_decrypt_then_accept() {
+        let (mut alice_session, mut bob_session, _alice_sk, _alice_pk, bob_sk, bob_pk) =
+            setup_sessions();
+
+        let result1 = alice_session.send_outgoing_message(b"seeker", b"Message 1", &bob_pk);
+
+        // decrypting leaves the session untouched, so the message can be decrypted again
+        let first = bob_session
+            .try_decrypt_incoming_message(&bob_sk, &result1)
+            .expect("Failed to decrypt message 1");
+        assert_eq!(first.message_bytes(), b"Message 1");
+        let second = bob_session
+            .try_decrypt_incoming_message(&bob_sk, &result1)
+            .expect("Failed to decrypt message 1 again");
+
+        // only one of them can be accepted
+        let result = bob_session
+            .accept_incoming_message(first)
+            .expect("Failed to accept message 1");
+        assert_eq!(result.message_bytes, b"Message 1");
+        assert_eq!(
+            bob_session.accept_incoming_message(second).err(),
+            Some(AgraphonError::DecryptionFailed)
+        );
+
+        // a message that is never accepted does not consume its chain key
+        let result2 = alice_session.send_outgoing_message(b"seeker", b"Message 2", &bob_pk);
+        drop(
+            bob_session
+                .try_decrypt_incoming_message(&bob_sk, &result2)
+                .expect("Failed to decrypt message 2"),
+        );
+        let result = bob_session
+            .try_feed_incoming_message(&bob_sk, &result2)
+            .expect("Failed to feed message 2");
+        assert_eq!(result.message_bytes, b"Message 2");
+    }
+
+    #[test]
+    fn test_skip_beyond_limit_fails() {
+        let limits = SkippedMessageLimits {
+            max_skip: 2,
+            max_stored: 16,
+        };
+        let (mut alice_session, mut bob_session, _alice_sk, _alice_pk, bob_sk, bob_pk) =
+            setup_sessions_with_limits(limits);
+
+        let results: Vec<Vec<u8>> = (0..4)
+            .map(|i| alice_session.send_outgoing_message(b"seeker", &[i], &bob_pk))
+            .collect();
+
+        // three missing messages exceed max_skip
+        assert_eq!(
+            bob_session
+                .try_feed_incoming_message(&bob_sk, &results[3])
+                .err(),
+            Some(AgraphonError::DecryptionFailed)
+        );
+        assert_eq!(bob_session.skipped_message_count(), 0);
+
+        // the failure left the session untouched
+        for (i, result) in results.iter().enumerate() {
+            let decrypted = bob_session
+                .try_feed_incoming_message(&bob_sk, result)
+                .expect("Failed to decrypt in-order message");
+            assert_eq!(decrypted.message_bytes, vec![i as u8]);
+        }
+    }
+
+    #[test]
+    fn test_skipped_store_evicts_oldest() {
+        let limits = SkippedMessageLimits {
+            max_skip: 8,
+            max_stored: 1,
+        };
+        let (mut alice_session, mut bob_session, _alice_sk, _alice_pk, bob_sk, bob_pk) =
+            setup_sessions_with_limits(limits);
+
+        let result1 = alice_session.send_outgoing_message(b"seeker", b"Message 1", &bob_pk);
+        let result2 = alice_session.send_outgoing_message(b"seeker", b"Message 2", &bob_pk);
+        let result3 = alice_session.send_outgoing_message(b"seeker", b"Message 3", &bob_pk);
+
+        bob_session
+            .try_feed_incoming_message(&bob_sk, &result3)
+            .expect("Failed to decrypt message 3");
+        assert_eq!(bob_session.skipped_message_count(), 1);
+
+        // message 1 was forgotten, message 2 was kept
+        assert_eq!(
+            bob_session
+                .try_feed_incoming_message(&bob_sk, &result1)
+                .err(),
+            Some(AgraphonError::DecryptionFailed)
+        );
+        let decrypted2 = bob_session
+            .try_feed_incoming_message(&bob_sk, &result2)
+            .expect("Failed to decrypt late message 2");
+        assert_eq!(&decrypted2.message_bytes, b"Message 2");
+    }
+
+    #[test]
+    fn test_lost_message_does_not_pin_history() {
+        let limits = SkippedMessageLimits {
+            max_skip: 4,
+            max_stored: 4,
+        };
+        let (mut alice_session, mut bob_session, alice_sk, alice_pk, bob_sk, bob_pk) =
+            setup_sessions_with_limits(limits);
+
+        // Alice's first message is lost for good
+        let lost = alice_session.send_outgoing_message(b"seeker", b"Lost", &bob_pk);
+        let result = alice_session.send_outgoing_message(b"seeker", b"Message 2", &bob_pk);
+        bob_session
+            .try_feed_incoming_message(&bob_sk, &result)
+            .expect("Failed to decrypt message 2 over the gap");
+        assert_eq!(bob_session.skipped_message_count(), 1);
+
+        // the conversation goes on long past the lost message
+        for i in 0..20u8 {
+            let bob_result = bob_session.send_outgoing_message(b"seeker", &[i], &alice_pk);
+            alice_session
+                .try_feed_incoming_message(&alice_sk, &bob_result)
+                .expect("Failed to decrypt Bob's message");
+            let alice_result = alice_session.send_outgoing_message(b"seeker", &[i], &bob_pk);
+            bob_session
+                .try_feed_incoming_message(&bob_sk, &alice_result)
+                .expect("Failed to decrypt Alice's message");
+        }
+
+        // the lost message was given up, and Bob's history was pruned behind it
+        assert_eq!(bob_session.skipped_message_count(), 0);
+        assert_eq!(bob_session.self_msg_history.len(), 1);
+        assert_eq!(
+            bob_session.try_feed_incoming_message(&bob_sk, &lost).err(),
+            Some(AgraphonError::DecryptionFailed)
+        );
+    }
+
+    /// Establishes sessions between Alice and Bob with static keys of the given version.
+    fn setup_sessions_with_version(
+        version: ProtocolVersion,
+    ) -> (
+        Agraphon,
+        Agraphon,
+        KemSecretKey,
+        KemPublicKey,
+        KemSecretKey,
+        KemPublicKey,
+    ) {
+        let (alice_sk, alice_pk) = version.generate_key_pair();
+        let (bob_sk, bob_pk) = version.generate_key_pair();
+
+        let (alice_announcement_bytes, alice_announcement) =
+            OutgoingAnnouncementPrecursor::new(&bob_pk).finalize(b"Alice");
+        let alice_incoming = IncomingAnnouncementPrecursor::try_from_incoming_announcement_bytes(
+            &alice_announcement_bytes,
+            &bob_pk,
+            &bob_sk,
+        )
+        .expect("Failed to parse announcement")
+        .finalize(&alice_pk)
+        .expect("Integrity check failed");
+
+        let (bob_announcement_bytes, bob_announcement) =
+            OutgoingAnnouncementPrecursor::new(&alice_pk).finalize(b"Bob");
+        let bob_incoming = IncomingAnnouncementPrecursor::try_from_incoming_announcement_bytes(
+            &bob_announcement_bytes,
+            &alice_pk,
+            &alice_sk,
+        )
+        .expect("Failed to parse announcement")
+        .finalize(&bob_pk)
+        .expect("Integrity check failed");
+
+        let limits = SkippedMessageLimits::default();
+        (
+            Agraphon::from_announcement_pair(&alice_announcement, &bob_incoming, limits),
+            Agraphon::from_announcement_pair(&bob_announcement, &alice_incoming, limits),
+            alice_sk,
+            alice_pk,
+            bob_sk,
+            bob_pk,
+        )
+    }
+
+    #[test]
+    fn test_hybrid_session_roundtrip() {
+        let (mut alice_session, mut bob_session, alice_sk, alice_pk, bob_sk, bob_pk) =
+            setup_sessions_with_version(ProtocolVersion::V2);
+        assert_eq!(alice_session.version(), ProtocolVersion::V2);
+        assert_eq!(bob_session.version(), ProtocolVersion::V2);
+
+        // Alice to Bob, with hybrid ciphertexts
+        let msg = alice_session.send_outgoing_message(b"a1", b"hello bob", &bob_pk);
+        assert!(msg.len() > 32 + 2 * kem::hybrid::CIPHERTEXT_SIZE);
+        let result = bob_session
+            .try_feed_incoming_message(&bob_sk, &msg)
+            .expect("Failed to decrypt hybrid message");
+        assert_eq!(result.message_bytes, b"hello bob");
+
+        // Bob answers twice, delivered out of order
+        let msg1 = bob_session.send_outgoing_message(b"b1", b"first", &alice_pk);
+        let msg2 = bob_session.send_outgoing_message(b"b2", b"second", &alice_pk);
+        let result = alice_session
+            .try_feed_incoming_message(&alice_sk, &msg2)
+            .expect("Failed to decrypt hybrid message");
+        assert_eq!(result.message_bytes, b"second");
+        let result = alice_session
+            .try_feed_incoming_message(&alice_sk, &msg1)
+            .expect("Failed to decrypt skipped hybrid message");
+        assert_eq!(result.message_bytes, b"first");
+
+        // The session survives serialization with its version
+        let serialized = serde_json::to_vec(&alice_session).unwrap();
+        let mut alice_session: Agraphon = serde_json::from_slice(&serialized).unwrap();
+        assert_eq!(alice_session.version(), ProtocolVersion::V2);
+
+        let msg = alice_session.send_outgoing_message(b"a2", b"got both", &bob_pk);
+        let result = bob_session
+            .try_feed_incoming_message(&bob_sk, &msg)
+            .expect("Failed to decrypt hybrid message");
+        assert_eq!(result.message_bytes, b"got both");
+        assert!(
+            result
+                .newly_acknowledged_self_seekers
+                .ends_with(&[b"b1".to_vec(), b"b2".to_vec()])
+        );
+    }
+
+    #[test]
+    fn test_static_key_of_another_version_rejected() {
+        let (mut alice_session, mut bob_session, _, _, bob_sk, bob_pk) =
+            setup_sessions_with_version(ProtocolVersion::V2);
+        let (ml_kem_sk, _) = ProtocolVersion::V1.generate_key_pair();
+
+        let msg = alice_session.send_outgoing_message(b"a1", b"hello bob", &bob_pk);
+        assert_eq!(
+            bob_session.try_feed_incoming_message(ml_kem_sk, &msg).err(),
+            Some(AgraphonError::VersionMismatch)
+        );
+
+        // the session is left untouched
+        let result = bob_session
+            .try_feed_incoming_message(&bob_sk, &msg)
+            .expect("Failed to decrypt hybrid message");
+        assert_eq!(result.message_bytes, b"hello bob");
+    }
+
+    #[test]
+    fn test_upgrade_from_v0() {
+        let random_key_pair = || {
+            let mut rand = [0u8; kem::KEY_GENERATION_RANDOMNESS_SIZE];
+            rng::fill_buffer(&mut rand);
+            kem::generate_key_pair(rand)
+        };
+        let random_key = || {
+            let mut key = [0u8; 32];
+            rng::fill_buffer(&mut key);
+            key
+        };
+
+        // Bob's message 2 answered Alice's message 2
+        let (alice_sk_2, _) = random_key_pair();
+        let (alice_sk_3, _) = random_key_pair();
+        let (_, bob_pk_2) = random_key_pair();
+        let (alice_k_2, alice_k_3, bob_k_2) = (random_key(), random_key(), random_key());
+        let alice_legacy = AgraphonV0 {
+            self_msg_history: [(2, alice_sk_2, alice_k_2), (3, alice_sk_3, alice_k_3)]
+                .into_iter()
+                .map(|(height, sk_next, k_next)| {
+                    Box::new(HistoryItemSelfV0 {
+                        seeker: vec![height as u8],
+                        height,
+                        sk_next,
+                        k_next,
+                    })
+                })
+                .collect(),
+            latest_peer_msg: HistoryItemPeerV0 {
+                our_parent_height: 2,
+                pk_next: bob_pk_2,
+                k_next: bob_k_2,
+            },
+        };
+
+        // each side encrypted its next message under the root key of its previous one
+        let limits = SkippedMessageLimits::default();
+        let upgraded = AgraphonV1::from_v0(&alice_legacy, limits);
+        let heights: Vec<u64> = upgraded
+            .self_msg_history
+            .iter()
+            .map(|msg| msg.height)
+            .collect();
+        assert_eq!(heights, vec![2, 3]);
+        assert_eq!(upgraded.self_msg_history[0].k_chain, alice_k_2);
+        assert_eq!(upgraded.self_msg_history[1].k_chain, alice_k_3);
+        assert_eq!(upgraded.latest_peer_msg.our_parent_height, 2);
+        assert_eq!(upgraded.latest_peer_msg.k_chain, bob_k_2);
+        assert!(upgraded.skipped_peer_msgs.is_empty());
+        assert_eq!(upgraded.limits, limits);
+    }
 
     #[test]
     fn test_reply_to_older_parent() {
@@ -743,7 +1746,19 @@ mod tests {
 
         // Decryption should fail
         let decrypt_result = bob_session.try_feed_incoming_message(&bob_sk, &result);
-        assert!(decrypt_result.is_none());
+        assert_eq!(decrypt_result.err(), Some(AgraphonError::DecryptionFailed));
+    }
+
+    #[test]
+    fn test_truncated_message_fails() {
+        let (mut alice_session, mut bob_session, _alice_sk, _alice_pk, bob_sk, bob_pk) =
+            setup_sessions();
+
+        let result = alice_session.send_outgoing_message(b"seeker", b"Test message", &bob_pk);
+
+        // Cut the message before the end of the KEM ciphertexts
+        let decrypt_result = bob_session.try_feed_incoming_message(&bob_sk, &result[..100]);
+        assert_eq!(decrypt_result.err(), Some(AgraphonError::Malformed));
     }
 
     #[test]
@@ -6,8 +6,11 @@
 
 use crate::announcement_auth_kdf::AnnouncementAuthKdf;
 use crate::announcement_root_kdf::AnnouncementRootKdf;
+use crate::error::AgraphonError;
+use crate::protocol_version::{
+    Encapsulation, KemCiphertext, KemPublicKey, KemSecretKey, ProtocolVersion,
+};
 use crypto_aead as cipher;
-use crypto_kem as kem;
 use crypto_rng as rng;
 use serde::{Deserialize, Serialize};
 use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
@@ -31,7 +34,9 @@ use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
 /// payload and repackage it in a new announcement.
 #[derive(Zeroize, ZeroizeOnDrop)]
 pub struct IncomingAnnouncementPrecursor {
-    pk_next: kem::PublicKey,
+    #[zeroize(skip)]
+    version: ProtocolVersion,
+    pk_next: KemPublicKey,
     auth_payload: Vec<u8>,
     k_next: [u8; 32],
     auth_key: [u8; 32],
@@ -44,6 +49,8 @@ impl IncomingAnnouncementPrecursor {
     /// the announcement using our static key pair and extracts the authentication
     /// payload and key.
     ///
+    /// The announcement is read with the protocol version of our static keys.
+    ///
     /// # Arguments
     ///
     /// * `announcement_bytes` - The raw announcement bytes received from the initiator
@@ -52,14 +59,19 @@ impl IncomingAnnouncementPrecursor {
     ///
     /// # Returns
     ///
-    /// `Some(IncomingAnnouncementPrecursor)` if decryption succeeds, `None` if the
-    /// announcement is malformed or cannot be decrypted.
+    /// `Ok(IncomingAnnouncementPrecursor)` if decryption succeeds.
+    ///
+    /// # Errors
+    ///
+    /// - [`AgraphonError::Malformed`] if the announcement is truncated or its contents are invalid
+    /// - [`AgraphonError::DecryptionFailed`] if the announcement cannot be decrypted,
+    ///   for instance because it is addressed to someone else or was tampered with
     ///
     /// # Format
     ///
     /// Announcement bytes contain:
     /// - 32 bytes: randomness
-    /// - `kem::CIPHERTEXT_SIZE` bytes: KEM ciphertext
+    /// - [`ProtocolVersion::kem_ciphertext_size`] bytes: KEM ciphertext
     /// - Remaining bytes: encrypted (`auth_payload` || `integrity_key`)
     ///
     /// # Examples
@@ -91,45 +103,70 @@ impl IncomingAnnouncementPrecursor {
     ///
     /// assert_eq!(incoming_pre.auth_payload(), auth_payload);
     /// ```
-    #[must_use]
     #[allow(clippy::similar_names)] // pk/sk naming is standard in cryptography
     pub fn try_from_incoming_announcement_bytes(
         announcement_bytes: &[u8],
-        our_pk: &kem::PublicKey,
-        our_sk: &kem::SecretKey,
-    ) -> Option<Self> {
-        let randomness: [u8; 32] = announcement_bytes.get(..32)?.try_into().ok()?;
-
-        let ct_end_index = 32 + kem::CIPHERTEXT_SIZE;
-        let ct_bytes: [u8; kem::CIPHERTEXT_SIZE] =
-            announcement_bytes.get(32..ct_end_index)?.try_into().ok()?;
-        let ct = kem::Ciphertext::from(ct_bytes);
-
-        let encrypted_message = announcement_bytes.get(ct_end_index..)?;
-
-        let ss = kem::decapsulate(our_sk, &ct);
-
-        let root_kdf = AnnouncementRootKdf::new(&randomness, &ss, &ct, our_pk);
+        our_pk: impl Into<KemPublicKey>,
+        our_sk: impl Into<KemSecretKey>,
+    ) -> Result<Self, AgraphonError> {
+        let our_pk = our_pk.into();
+        let our_sk = our_sk.into();
+        let version = our_sk.version();
+
+        let randomness: [u8; 32] = announcement_bytes
+            .get(..32)
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or(AgraphonError::Malformed)?;
+
+        let ct_end_index = 32 + version.kem_ciphertext_size();
+        let ct = announcement_bytes
+            .get(32..ct_end_index)
+            .and_then(|bytes| KemCiphertext::from_bytes(version, bytes))
+            .ok_or(AgraphonError::Malformed)?;
+
+        let encrypted_message = announcement_bytes
+            .get(ct_end_index..)
+            .ok_or(AgraphonError::Malformed)?;
+
+        let ss = our_sk
+            .decapsulate(&ct)
+            .ok_or(AgraphonError::DecryptionFailed)?;
+
+        let root_kdf = AnnouncementRootKdf::new(
+            version,
+            &randomness,
+            &Encapsulation::new(&ct, &ss),
+            our_pk.as_bytes(),
+        );
 
-        let plaintext = Zeroizing::new(cipher::decrypt(
-            &root_kdf.cipher_key,
-            &root_kdf.cipher_nonce,
-            encrypted_message,
-            b"",
-        )?);
+        let plaintext = Zeroizing::new(
+            cipher::decrypt(
+                &root_kdf.cipher_key,
+                &root_kdf.cipher_nonce,
+                encrypted_message,
+                b"",
+            )
+            .ok_or(AgraphonError::DecryptionFailed)?,
+        );
 
         // Extract pk_next from plaintext
-        let pk_next_bytes: [u8; kem::PUBLIC_KEY_SIZE] =
-            plaintext.get(..kem::PUBLIC_KEY_SIZE)?.try_into().ok()?;
-        let pk_next = kem::PublicKey::from(pk_next_bytes);
+        let pk_next = plaintext
+            .get(..version.kem_public_key_size())
+            .and_then(|bytes| KemPublicKey::from_bytes(version, bytes))
+            .ok_or(AgraphonError::Malformed)?;
 
         // Extract auth payload (remaining part of plaintext)
-        let auth_payload = plaintext.get(kem::PUBLIC_KEY_SIZE..)?.to_vec();
+        let auth_payload = plaintext
+            .get(version.kem_public_key_size()..)
+            .ok_or(AgraphonError::Malformed)?
+            .to_vec();
 
         // Generate auth key using AnnouncementAuthKdf
-        let auth_kdf = AnnouncementAuthKdf::new(&root_kdf.auth_pre_key, &pk_next);
+        let auth_kdf =
+            AnnouncementAuthKdf::new(version, &root_kdf.auth_pre_key, pk_next.as_bytes());
 
-        Some(Self {
+        Ok(Self {
+            version,
             pk_next,
             k_next: root_kdf.k_next,
             auth_payload,
@@ -167,6 +204,12 @@ impl IncomingAnnouncementPrecursor {
         &self.auth_payload
     }
 
+    /// Returns the protocol version of the announcement, which is the one of our static keys.
+    #[must_use]
+    pub const fn version(&self) -> ProtocolVersion {
+        self.version
+    }
+
     /// Returns the authentication key for message binding verification.
     ///
     /// Both parties derive the same `auth_key` from the announcement. This key
@@ -218,7 +261,8 @@ impl IncomingAnnouncementPrecursor {
     /// # Returns
     ///
     /// `Some(IncomingAnnouncement)` if the integrity check passes, `None` if
-    /// the integrity key doesn't match (indicating tampering or corruption).
+    /// the integrity key doesn't match (indicating tampering or corruption), or if
+    /// `pk_peer` is of another protocol version than the announcement.
     ///
     /// # Examples
     ///
@@ -242,7 +286,11 @@ impl IncomingAnnouncementPrecursor {
     ///     .expect("Integrity check failed");
     /// ```
     #[must_use]
-    pub fn finalize(self, pk_peer: kem::PublicKey) -> Option<IncomingAnnouncement> {
+    pub fn finalize(self, pk_peer: impl Into<KemPublicKey>) -> Option<IncomingAnnouncement> {
+        let pk_peer = pk_peer.into();
+        if pk_peer.version() != self.version {
+            return None;
+        }
         Some(IncomingAnnouncement {
             pk_peer,
             pk_next: self.pk_next.clone(),
@@ -260,8 +308,8 @@ impl IncomingAnnouncementPrecursor {
 /// Pass this to `Agraphon::try_from_incoming_announcement()` to create the session.
 #[derive(Clone, Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
 pub struct IncomingAnnouncement {
-    pub(crate) pk_peer: kem::PublicKey,
-    pub(crate) pk_next: kem::PublicKey,
+    pub(crate) pk_peer: KemPublicKey,
+    pub(crate) pk_next: KemPublicKey,
     pub(crate) k_next: [u8; 32],
 }
 
@@ -278,13 +326,13 @@ pub struct IncomingAnnouncement {
 #[derive(Zeroize, ZeroizeOnDrop)]
 pub struct OutgoingAnnouncementPrecursor {
     randomness: [u8; 32],
-    kem_ct: kem::Ciphertext,
+    kem_ct: KemCiphertext,
     cipher_key: cipher::Key,
     cipher_nonce: cipher::Nonce,
     auth_key: [u8; 32],
     k_next: [u8; 32],
-    pk_next: kem::PublicKey,
-    sk_next: kem::SecretKey,
+    pk_next: KemPublicKey,
+    sk_next: KemSecretKey,
 }
 
 impl OutgoingAnnouncementPrecursor {
@@ -294,6 +342,9 @@ impl OutgoingAnnouncementPrecursor {
     /// static public key. The resulting `auth_key` can be inspected before
     /// finalizing the announcement.
     ///
+    /// The announcement uses the protocol version of `pk_peer`, which must be the one
+    /// of our own static keys for the peer to accept it.
+    ///
     /// # Arguments
     ///
     /// * `pk_peer` - The responder's static public key
@@ -319,30 +370,31 @@ impl OutgoingAnnouncementPrecursor {
     /// // precursor.auth_key() can now be displayed for verification
     /// ```
     #[must_use]
-    pub fn new(pk_peer: &kem::PublicKey) -> Self {
+    pub fn new(pk_peer: impl Into<KemPublicKey>) -> Self {
+        let pk_peer = pk_peer.into();
+        let version = pk_peer.version();
+
         // announcement root KDF randomness
         let mut randomness = Zeroizing::new([0u8; 32]);
         rng::fill_buffer(randomness.as_mut());
 
         // peer KEM encapsulation
-        let (kem_ct, kem_ss) = {
-            let mut kem_randomness = [0u8; kem::ENCAPSULATION_RANDOMNESS_SIZE];
-            rng::fill_buffer(&mut kem_randomness);
-            kem::encapsulate(pk_peer, kem_randomness)
-        };
+        let (kem_ct, kem_ss) = pk_peer.encapsulate();
 
         // announcement root KDF
-        let root_kdf = AnnouncementRootKdf::new(&randomness, &kem_ss, &kem_ct, pk_peer);
+        let root_kdf = AnnouncementRootKdf::new(
+            version,
+            &randomness,
+            &Encapsulation::new(&kem_ct, &kem_ss),
+            pk_peer.as_bytes(),
+        );
 
         // sk_next/pk_next KEM keypair generation
-        let (sk_next, pk_next) = {
-            let mut kem_randomness = [0u8; kem::KEY_GENERATION_RANDOMNESS_SIZE];
-            rng::fill_buffer(&mut kem_randomness);
-            kem::generate_key_pair(kem_randomness)
-        };
+        let (sk_next, pk_next) = version.generate_key_pair();
 
         // announcement auth KDF
-        let announcement_auth_kdf = AnnouncementAuthKdf::new(&root_kdf.auth_pre_key, &pk_next);
+        let announcement_auth_kdf =
+            AnnouncementAuthKdf::new(version, &root_kdf.auth_pre_key, pk_next.as_bytes());
 
         // Create new Nonce and Key objects using the raw bytes from root_kdf
         let cipher_nonce = cipher::Nonce::from(*root_kdf.cipher_nonce.as_bytes());
@@ -461,12 +513,13 @@ impl OutgoingAnnouncementPrecursor {
 #[derive(Clone, Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
 pub struct OutgoingAnnouncement {
     pub(crate) k_next: [u8; 32],
-    pub(crate) sk_next: kem::SecretKey,
+    pub(crate) sk_next: KemSecretKey,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crypto_kem as kem;
     use crypto_rng as rng;
 
     #[test]
@@ -535,7 +588,7 @@ mod tests {
             &eve_sk,
         );
 
-        assert!(result.is_none());
+        assert_eq!(result.err(), Some(AgraphonError::DecryptionFailed));
     }
 
     #[test]
@@ -561,7 +614,27 @@ mod tests {
             &bob_sk,
         );
 
-        assert!(result.is_none());
+        assert_eq!(result.err(), Some(AgraphonError::DecryptionFailed));
+    }
+
+    #[test]
+    fn test_announcement_truncated_bytes() {
+        // Generate keys
+        let mut bob_rand = [0u8; kem::KEY_GENERATION_RANDOMNESS_SIZE];
+        rng::fill_buffer(&mut bob_rand);
+        let (bob_sk, bob_pk) = kem::generate_key_pair(bob_rand);
+
+        // Create announcement and cut it in the middle of the KEM ciphertext
+        let precursor = OutgoingAnnouncementPrecursor::new(&bob_pk);
+        let (announcement_bytes, _) = precursor.finalize(b"test");
+        let truncated = &announcement_bytes[..100];
+
+        // Parsing should fail without attempting decryption
+        let result = IncomingAnnouncementPrecursor::try_from_incoming_announcement_bytes(
+            truncated, &bob_pk, &bob_sk,
+        );
+
+        assert_eq!(result.err(), Some(AgraphonError::Malformed));
     }
 
     #[test]
@@ -635,4 +708,41 @@ mod tests {
 
         assert_eq!(auth_key1, auth_key2);
     }
+
+    #[test]
+    fn test_announcement_protocol_versions() {
+        let mut bob_rand = [0u8; kem::hybrid::KEY_GENERATION_RANDOMNESS_SIZE];
+        rng::fill_buffer(&mut bob_rand);
+        let (bob_sk, bob_pk) = kem::hybrid::generate_key_pair(bob_rand);
+
+        // announcements to hybrid keys are hybrid
+        let precursor = OutgoingAnnouncementPrecursor::new(&bob_pk);
+        let auth_key = *precursor.auth_key();
+        let (announcement_bytes, _) = precursor.finalize(b"Alice");
+        let incoming = IncomingAnnouncementPrecursor::try_from_incoming_announcement_bytes(
+            &announcement_bytes,
+            &bob_pk,
+            &bob_sk,
+        )
+        .expect("Failed to parse hybrid announcement");
+        assert_eq!(incoming.version(), ProtocolVersion::V2);
+        assert_eq!(incoming.auth_key(), &auth_key);
+        assert_eq!(incoming.auth_payload(), b"Alice");
+
+        // the ML-KEM part of the keys alone cannot read them
+        assert!(
+            IncomingAnnouncementPrecursor::try_from_incoming_announcement_bytes(
+                &announcement_bytes,
+                bob_pk.ml_kem(),
+                bob_sk.ml_kem(),
+            )
+            .is_err()
+        );
+
+        // the announcer must have static keys of the same version
+        let mut alice_rand = [0u8; kem::KEY_GENERATION_RANDOMNESS_SIZE];
+        rng::fill_buffer(&mut alice_rand);
+        let (_, alice_pk) = kem::generate_key_pair(alice_rand);
+        assert!(incoming.finalize(alice_pk).is_none());
+    }
 }
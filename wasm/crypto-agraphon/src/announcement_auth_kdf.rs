@@ -3,8 +3,8 @@
 //! This module handles the derivation of the authentication key
 //! used to bind authentication data to specific announcements.
 
+use crate::protocol_version::ProtocolVersion;
 use crypto_kdf as kdf;
-use crypto_kem as kem;
 use zeroize::{Zeroize, ZeroizeOnDrop};
 
 /// Key derivation function for announcement authentication.
@@ -21,7 +21,8 @@ use zeroize::{Zeroize, ZeroizeOnDrop};
 /// # Cryptographic Details
 ///
 /// Uses HKDF with:
-/// - Salt: `"agraphon.auth_kdf.salt.V1----------"`
+/// - Salt: `"agraphon.auth_kdf.salt.V1-------"`, or `"agraphon.auth_kdf.salt.V2-------"`
+///   for [`ProtocolVersion::V2`]
 /// - Inputs: `auth_pre_key`, `pk_next`
 /// - Info string: `"agraphon.auth_kdf.auth_key"`
 #[derive(Zeroize, ZeroizeOnDrop)]
@@ -35,6 +36,7 @@ impl AnnouncementAuthKdf {
     ///
     /// # Arguments
     ///
+    /// * `version` - Protocol version, selecting the KEM of `pk_next`
     /// * `auth_pre_key` - Pre-key derived from the announcement root KDF
     /// * `pk_next` - The initiator's next public key
     ///
@@ -50,15 +52,20 @@ impl AnnouncementAuthKdf {
     /// rng::fill_buffer(&mut pk_randomness);
     /// let (_, pk_next) = kem::generate_key_pair(pk_randomness);
     ///
-    /// let kdf = AnnouncementAuthKdf::new(&auth_pre_key, &pk_next);
+    /// let kdf = AnnouncementAuthKdf::new(ProtocolVersion::V1, &auth_pre_key, pk_next.as_bytes());
     /// kdf.auth_key binds the authentication to this specific announcement
     /// ```
-    pub(crate) fn new(auth_pre_key: &[u8; 32], pk_next: &kem::PublicKey) -> Self {
+    pub(crate) fn new(version: ProtocolVersion, auth_pre_key: &[u8; 32], pk_next: &[u8]) -> Self {
+        debug_assert_eq!(pk_next.len(), version.kem_public_key_size());
         let mut auth_key = [0u8; 32];
 
-        let mut auth_kdf = kdf::Extract::new(b"agraphon.auth_kdf.salt.V1-------");
+        let salt: &[u8] = match version {
+            ProtocolVersion::V1 => b"agraphon.auth_kdf.salt.V1-------",
+            ProtocolVersion::V2 => b"agraphon.auth_kdf.salt.V2-------",
+        };
+        let mut auth_kdf = kdf::Extract::new(salt);
         auth_kdf.input_item(auth_pre_key.as_slice());
-        auth_kdf.input_item(pk_next.as_bytes());
+        auth_kdf.input_item(pk_next);
         let auth_kdf = auth_kdf.finalize();
 
         auth_kdf.expand(b"agraphon.auth_kdf.auth_key", &mut auth_key);
@@ -70,6 +77,7 @@ impl AnnouncementAuthKdf {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crypto_kem as kem;
     use crypto_rng as rng;
 
     #[test]
@@ -81,8 +89,8 @@ mod tests {
         rng::fill_buffer(&mut pk_rand);
         let (_, pk_next) = kem::generate_key_pair(pk_rand);
 
-        let kdf1 = AnnouncementAuthKdf::new(&auth_pre_key, &pk_next);
-        let kdf2 = AnnouncementAuthKdf::new(&auth_pre_key, &pk_next);
+        let kdf1 = AnnouncementAuthKdf::new(ProtocolVersion::V1, &auth_pre_key, pk_next.as_bytes());
+        let kdf2 = AnnouncementAuthKdf::new(ProtocolVersion::V1, &auth_pre_key, pk_next.as_bytes());
 
         assert_eq!(kdf1.auth_key, kdf2.auth_key);
     }
@@ -97,8 +105,10 @@ mod tests {
         rng::fill_buffer(&mut pk_rand);
         let (_, pk_next) = kem::generate_key_pair(pk_rand);
 
-        let kdf1 = AnnouncementAuthKdf::new(&auth_pre_key1, &pk_next);
-        let kdf2 = AnnouncementAuthKdf::new(&auth_pre_key2, &pk_next);
+        let kdf1 =
+            AnnouncementAuthKdf::new(ProtocolVersion::V1, &auth_pre_key1, pk_next.as_bytes());
+        let kdf2 =
+            AnnouncementAuthKdf::new(ProtocolVersion::V1, &auth_pre_key2, pk_next.as_bytes());
 
         assert_ne!(kdf1.auth_key, kdf2.auth_key);
     }
@@ -116,9 +126,25 @@ mod tests {
         rng::fill_buffer(&mut pk_rand2);
         let (_, pk_next2) = kem::generate_key_pair(pk_rand2);
 
-        let kdf1 = AnnouncementAuthKdf::new(&auth_pre_key, &pk_next1);
-        let kdf2 = AnnouncementAuthKdf::new(&auth_pre_key, &pk_next2);
+        let kdf1 =
+            AnnouncementAuthKdf::new(ProtocolVersion::V1, &auth_pre_key, pk_next1.as_bytes());
+        let kdf2 =
+            AnnouncementAuthKdf::new(ProtocolVersion::V1, &auth_pre_key, pk_next2.as_bytes());
 
         assert_ne!(kdf1.auth_key, kdf2.auth_key);
     }
+
+    #[test]
+    fn test_auth_kdf_hybrid() {
+        // Hybrid next public keys should produce deterministic auth keys
+        let auth_pre_key = [42u8; 32];
+
+        let mut pk_rand = [0u8; kem::hybrid::KEY_GENERATION_RANDOMNESS_SIZE];
+        rng::fill_buffer(&mut pk_rand);
+        let (_, pk_next) = kem::hybrid::generate_key_pair(pk_rand);
+
+        let kdf1 = AnnouncementAuthKdf::new(ProtocolVersion::V2, &auth_pre_key, pk_next.as_bytes());
+        let kdf2 = AnnouncementAuthKdf::new(ProtocolVersion::V2, &auth_pre_key, pk_next.as_bytes());
+        assert_eq!(kdf1.auth_key, kdf2.auth_key);
+    }
 }
@@ -4,9 +4,9 @@
 //! Unlike regular messages, announcements include an authentication key that
 //! can be used for authentication.
 
+use crate::protocol_version::{Encapsulation, ProtocolVersion};
 use crypto_aead as cipher;
 use crypto_kdf as kdf;
-use crypto_kem as kem;
 use zeroize::{Zeroize, ZeroizeOnDrop};
 
 /// Root key derivation function for announcement messages.
@@ -34,8 +34,9 @@ use zeroize::{Zeroize, ZeroizeOnDrop};
 /// # Cryptographic Details
 ///
 /// Uses HKDF with:
-/// - Salt: `"agraphon.announcement_root_kdf.V1---"`
-/// - Inputs: randomness, `shared_secret`, ciphertext, `peer_public_key`, `Role::Initiator`
+/// - Salt: `"agraphon.announcement_root_kdf.V1"`, or `"agraphon.announcement_root_kdf.V2"`
+///   for [`ProtocolVersion::V2`]
+/// - Inputs: randomness, ciphertext, `shared_secret`, `peer_public_key`
 /// - Info strings: `"agraphon.announcement_root_kdf.cipher_key"`
 ///   `"agraphon.announcement_root_kdf.cipher_nonce"`,
 ///   `"agraphon.announcement_root_kdf.k_next"`,
@@ -58,9 +59,9 @@ impl AnnouncementRootKdf {
     ///
     /// # Arguments
     ///
+    /// * `version` - Protocol version, selecting the KEM of `encapsulation` and `pk`
     /// * `randomness` - Fresh randomness generated by the initiator
-    /// * `ss` - Shared secret from KEM encapsulation to peer's static key
-    /// * `ct` - KEM ciphertext (for domain separation)
+    /// * `encapsulation` - KEM encapsulation to peer's static key
     /// * `pk` - Peer's static public key
     ///
     /// # Returns
@@ -83,26 +84,37 @@ impl AnnouncementRootKdf {
     /// rng::fill_buffer(&mut enc_rand);
     /// let (ct, ss) = kem::encapsulate(&bob_pk, enc_rand);
     ///
-    /// let kdf = AnnouncementRootKdf::new(&randomness, &ss, &ct, &bob_pk);
+    /// let kdf = AnnouncementRootKdf::new(
+    ///     ProtocolVersion::V1,
+    ///     &randomness,
+    ///     &Encapsulation::ml_kem(&ct, &ss),
+    ///     bob_pk.as_bytes(),
+    /// );
     /// // kdf.auth_pre_key is used to derive the auth_key
     /// // kdf.cipher_key encrypts the announcement payload
     /// ```
     pub(crate) fn new(
+        version: ProtocolVersion,
         randomness: &[u8; 32],
-        ss: &kem::SharedSecret,
-        ct: &kem::Ciphertext,
-        pk: &kem::PublicKey,
+        encapsulation: &Encapsulation<'_>,
+        pk: &[u8],
     ) -> Self {
+        debug_assert_eq!(encapsulation.ct.len(), version.kem_ciphertext_size());
+        debug_assert_eq!(pk.len(), version.kem_public_key_size());
         let mut cipher_nonce = [0u8; cipher::NONCE_SIZE];
         let mut cipher_key = [0u8; cipher::KEY_SIZE];
         let mut k_next = [0u8; 32];
         let mut auth_pre_key = [0u8; 32];
 
-        let mut root_kdf = kdf::Extract::new("agraphon.announcement_root_kdf.V1".as_bytes());
+        let salt = match version {
+            ProtocolVersion::V1 => "agraphon.announcement_root_kdf.V1",
+            ProtocolVersion::V2 => "agraphon.announcement_root_kdf.V2",
+        };
+        let mut root_kdf = kdf::Extract::new(salt.as_bytes());
         root_kdf.input_item(randomness.as_slice());
-        root_kdf.input_item(ct.as_bytes());
-        root_kdf.input_item(ss.as_bytes());
-        root_kdf.input_item(pk.as_bytes());
+        root_kdf.input_item(encapsulation.ct);
+        root_kdf.input_item(encapsulation.ss.as_bytes());
+        root_kdf.input_item(pk);
         let root_kdf = root_kdf.finalize();
         root_kdf.expand(
             "agraphon.announcement_root_kdf.cipher_nonce".as_bytes(),
@@ -134,6 +146,7 @@ impl AnnouncementRootKdf {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crypto_kem as kem;
     use crypto_rng as rng;
 
     #[test]
@@ -149,8 +162,18 @@ mod tests {
         rng::fill_buffer(&mut enc_rand);
         let (ct, ss) = kem::encapsulate(&pk, enc_rand);
 
-        let kdf1 = AnnouncementRootKdf::new(&randomness, &ss, &ct, &pk);
-        let kdf2 = AnnouncementRootKdf::new(&randomness, &ss, &ct, &pk);
+        let kdf1 = AnnouncementRootKdf::new(
+            ProtocolVersion::V1,
+            &randomness,
+            &Encapsulation::ml_kem(&ct, &ss),
+            pk.as_bytes(),
+        );
+        let kdf2 = AnnouncementRootKdf::new(
+            ProtocolVersion::V1,
+            &randomness,
+            &Encapsulation::ml_kem(&ct, &ss),
+            pk.as_bytes(),
+        );
 
         assert_eq!(kdf1.cipher_key.as_bytes(), kdf2.cipher_key.as_bytes());
         assert_eq!(kdf1.cipher_nonce.as_bytes(), kdf2.cipher_nonce.as_bytes());
@@ -172,8 +195,18 @@ mod tests {
         rng::fill_buffer(&mut enc_rand);
         let (ct, ss) = kem::encapsulate(&pk, enc_rand);
 
-        let kdf1 = AnnouncementRootKdf::new(&randomness1, &ss, &ct, &pk);
-        let kdf2 = AnnouncementRootKdf::new(&randomness2, &ss, &ct, &pk);
+        let kdf1 = AnnouncementRootKdf::new(
+            ProtocolVersion::V1,
+            &randomness1,
+            &Encapsulation::ml_kem(&ct, &ss),
+            pk.as_bytes(),
+        );
+        let kdf2 = AnnouncementRootKdf::new(
+            ProtocolVersion::V1,
+            &randomness2,
+            &Encapsulation::ml_kem(&ct, &ss),
+            pk.as_bytes(),
+        );
 
         assert_ne!(kdf1.cipher_key.as_bytes(), kdf2.cipher_key.as_bytes());
         assert_ne!(kdf1.k_next, kdf2.k_next);
@@ -196,8 +229,18 @@ mod tests {
         rng::fill_buffer(&mut enc_rand2);
         let (ct2, ss2) = kem::encapsulate(&pk, enc_rand2);
 
-        let kdf1 = AnnouncementRootKdf::new(&randomness, &ss1, &ct1, &pk);
-        let kdf2 = AnnouncementRootKdf::new(&randomness, &ss2, &ct2, &pk);
+        let kdf1 = AnnouncementRootKdf::new(
+            ProtocolVersion::V1,
+            &randomness,
+            &Encapsulation::ml_kem(&ct1, &ss1),
+            pk.as_bytes(),
+        );
+        let kdf2 = AnnouncementRootKdf::new(
+            ProtocolVersion::V1,
+            &randomness,
+            &Encapsulation::ml_kem(&ct2, &ss2),
+            pk.as_bytes(),
+        );
 
         assert_ne!(kdf1.cipher_key.as_bytes(), kdf2.cipher_key.as_bytes());
         assert_ne!(kdf1.k_next, kdf2.k_next);
@@ -216,11 +259,55 @@ mod tests {
         rng::fill_buffer(&mut enc_rand);
         let (ct, ss) = kem::encapsulate(&pk, enc_rand);
 
-        let kdf = AnnouncementRootKdf::new(&randomness, &ss, &ct, &pk);
+        let kdf = AnnouncementRootKdf::new(
+            ProtocolVersion::V1,
+            &randomness,
+            &Encapsulation::ml_kem(&ct, &ss),
+            pk.as_bytes(),
+        );
 
         assert_eq!(kdf.cipher_key.as_bytes().len(), cipher::KEY_SIZE);
         assert_eq!(kdf.cipher_nonce.as_bytes().len(), cipher::NONCE_SIZE);
         assert_eq!(kdf.k_next.len(), 32);
         assert_eq!(kdf.auth_pre_key.len(), 32);
     }
+
+    #[test]
+    fn test_announcement_root_kdf_hybrid() {
+        // Both sides of a hybrid encapsulation derive the same keys
+        let randomness = [42u8; 32];
+
+        let mut pk_rand = [0u8; kem::hybrid::KEY_GENERATION_RANDOMNESS_SIZE];
+        rng::fill_buffer(&mut pk_rand);
+        let (sk, pk) = kem::hybrid::generate_key_pair(pk_rand);
+
+        let mut enc_rand = [0u8; kem::hybrid::ENCAPSULATION_RANDOMNESS_SIZE];
+        rng::fill_buffer(&mut enc_rand);
+        let (ct, ss) = kem::hybrid::encapsulate(&pk, enc_rand);
+        let decapsulated_ss = kem::hybrid::decapsulate(&sk, &ct);
+
+        let kdf1 = AnnouncementRootKdf::new(
+            ProtocolVersion::V2,
+            &randomness,
+            &Encapsulation {
+                ct: ct.as_bytes(),
+                ss: &ss,
+            },
+            pk.as_bytes(),
+        );
+        let kdf2 = AnnouncementRootKdf::new(
+            ProtocolVersion::V2,
+            &randomness,
+            &Encapsulation {
+                ct: ct.as_bytes(),
+                ss: &decapsulated_ss,
+            },
+            pk.as_bytes(),
+        );
+
+        assert_eq!(kdf1.cipher_key.as_bytes(), kdf2.cipher_key.as_bytes());
+        assert_eq!(kdf1.cipher_nonce.as_bytes(), kdf2.cipher_nonce.as_bytes());
+        assert_eq!(kdf1.k_next, kdf2.k_next);
+        assert_eq!(kdf1.auth_pre_key, kdf2.auth_pre_key);
+    }
 }
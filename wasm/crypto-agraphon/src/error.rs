@@ -0,0 +1,33 @@
+//! Error types returned when processing incoming protocol data.
+
+use std::fmt;
+
+/// Error returned when an incoming announcement or message cannot be processed.
+///
+/// Decryption failures are deliberately opaque: a wrong recipient, a tampered
+/// ciphertext, a replayed message or a message beyond the [`SkippedMessageLimits`]
+/// all result in [`AgraphonError::DecryptionFailed`], so that callers cannot be
+/// turned into an oracle revealing which check failed.
+///
+/// [`SkippedMessageLimits`]: crate::SkippedMessageLimits
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AgraphonError {
+    /// The input does not have the expected structure (e.g. it is truncated).
+    Malformed,
+    /// The input could not be decrypted and authenticated with any of our keys.
+    DecryptionFailed,
+    /// A key given to process the input is of another protocol version than the session.
+    VersionMismatch,
+}
+
+impl fmt::Display for AgraphonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Malformed => write!(f, "malformed input"),
+            Self::DecryptionFailed => write!(f, "decryption failed"),
+            Self::VersionMismatch => write!(f, "key of another protocol version than the session"),
+        }
+    }
+}
+
+impl std::error::Error for AgraphonError {}
@@ -1,8 +1,11 @@
 //! Message history tracking for asynchronous communication.
 //!
 //! This module maintains the state needed to support out-of-order message delivery.
-//! Each party tracks their own sent messages and the peer's most recent message.
+//! Each party tracks their own sent messages, the peer's most recent message,
+//! the peer messages that were skipped over and are still expected, and the chain keys
+//! they may have been encrypted with.
 
+use crate::protocol_version::{KemPublicKey, KemSecretKey};
 use crypto_kem as kem;
 use serde::{Deserialize, Serialize};
 use zeroize::{Zeroize, ZeroizeOnDrop};
@@ -17,23 +20,27 @@ use zeroize::{Zeroize, ZeroizeOnDrop};
 ///
 /// - `sk_next`: The secret key to use for decrypting responses to this message
 /// - `k_next`: Root key derived after sending this message
+/// - `k_chain`: Chain key of the next peer response to this message
 /// - `seeker_next`: Seeker seed for identifying responses to this message
 ///
 /// # Protocol Context
 ///
 /// We maintain a queue of recent sent messages. When receiving a message from
 /// the peer, we compute seekers for each item in this queue to determine which
-/// of our messages they're responding to.
-#[derive(Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
+/// of our messages they're responding to. The peer responses to each of our messages
+/// form a chain that starts from the root key of the message.
+#[derive(Clone, Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
 pub struct HistoryItemSelf {
     /// Seeker for this message
     pub(crate) seeker: Vec<u8>,
     /// Unique identifier for this message
     pub(crate) height: u64,
     /// Secret key for decrypting responses (Static or Ephemeral)
-    pub(crate) sk_next: kem::SecretKey,
+    pub(crate) sk_next: KemSecretKey,
     /// Root key for children messages
     pub(crate) k_next: [u8; 32],
+    /// Chain key of the next peer response to this message
+    pub(crate) k_chain: [u8; 32],
 }
 
 /// History item representing the peer's most recent message.
@@ -43,26 +50,118 @@ pub struct HistoryItemSelf {
 ///
 /// # Fields
 ///
+/// - `height`: Position of this message in the peer's sequence
 /// - `our_parent_height`: Which of our messages they were responding to
 /// - `pk_next`: Their next public key (for us to encapsulate to)
 /// - `k_next`: Their root key after this message
+/// - `k_chain`: Chain key of our next response to this message
 /// - `seeker_next`: Their seeker seed for future message identification
 ///
 /// # Protocol Context
 ///
 /// When the peer sends a message:
 /// 1. They include which of our messages they're responding to (`our_parent_height`)
-/// 2. They include their next public key (`pk_next`)
-/// 3. We update this structure with their new state
+/// 2. They include their next public key (`pk_next`) and the `height` of the message
+/// 3. We update this structure with their new state, and start a new chain of responses
+///    from their root key
 /// 4. We can delete our history items older than `our_parent_height` (they've been acknowledged)
-#[derive(Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
+#[derive(Clone, Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
 pub struct HistoryItemPeer {
+    /// Position of this message in the peer's sequence
+    pub(crate) height: u64,
     /// Which of our messages they were responding to
     pub(crate) our_parent_height: u64,
     /// Their next public key for us to encapsulate to
-    pub(crate) pk_next: kem::PublicKey,
+    pub(crate) pk_next: KemPublicKey,
     /// Their master key after this message
     pub(crate) k_next: [u8; 32],
+    /// Chain key of our next response to this message
+    pub(crate) k_chain: [u8; 32],
+}
+
+/// Item representing a peer message that was skipped over and not received yet.
+///
+/// When a peer message arrives ahead of some of the messages the peer sent before it,
+/// the missing messages are remembered so that they can still be accepted if they
+/// arrive later.
+///
+/// # Fields
+///
+/// - `height`: Position of the skipped message in the peer's sequence
+/// - `min_parent_height`: Lowest of our messages the skipped message can respond to
+///
+/// # Protocol Context
+///
+/// The peer always responds to the most recent of our messages it has seen, so a skipped
+/// message cannot respond to one of our messages older than the parent of the last peer
+/// message received before it. Our history is never pruned below `min_parent_height`
+/// while the item is stored, so that the skipped message can still be decrypted.
+#[derive(Clone, Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
+pub struct SkippedItemPeer {
+    /// Position of the skipped message in the peer's sequence
+    pub(crate) height: u64,
+    /// Lowest of our messages the skipped message can respond to
+    pub(crate) min_parent_height: u64,
+}
+
+/// Chain key of a peer response to one of our messages that was stepped over.
+///
+/// When a peer response is decrypted after stepping the chain of responses to its parent
+/// forward, the chain keys of the responses in between are kept so that they can be
+/// decrypted if they arrive later.
+#[derive(Clone, Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
+pub struct SkippedChainKeyPeer {
+    /// Which of our messages the response responds to
+    pub(crate) our_parent_height: u64,
+    /// Chain key the response was encrypted with
+    pub(crate) k_chain: [u8; 32],
+}
+
+/// [`HistoryItemSelf`] as serialized before out-of-order delivery, without a chain key.
+///
+/// Messages were then encrypted under the root key of the sender's previous message,
+/// which is the chain key of the message that follows it.
+#[derive(Deserialize, Zeroize, ZeroizeOnDrop)]
+pub(crate) struct HistoryItemSelfV0 {
+    pub(crate) seeker: Vec<u8>,
+    pub(crate) height: u64,
+    pub(crate) sk_next: kem::SecretKey,
+    pub(crate) k_next: [u8; 32],
+}
+
+/// [`HistoryItemPeer`] as serialized before out-of-order delivery, without a chain key.
+#[derive(Deserialize, Zeroize, ZeroizeOnDrop)]
+pub(crate) struct HistoryItemPeerV0 {
+    pub(crate) our_parent_height: u64,
+    pub(crate) pk_next: kem::PublicKey,
+    pub(crate) k_next: [u8; 32],
+}
+
+/// [`HistoryItemSelf`] of schema version 1, whose chain key was the one of our next message.
+#[derive(Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
+pub(crate) struct HistoryItemSelfV1 {
+    pub(crate) seeker: Vec<u8>,
+    pub(crate) height: u64,
+    pub(crate) sk_next: KemSecretKey,
+    pub(crate) k_next: [u8; 32],
+    pub(crate) k_chain: [u8; 32],
+}
+
+/// [`HistoryItemPeer`] of schema version 1, whose chain key was the one of the next peer
+/// message.
+#[derive(Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
+pub(crate) struct HistoryItemPeerV1 {
+    pub(crate) our_parent_height: u64,
+    pub(crate) pk_next: KemPublicKey,
+    pub(crate) k_next: [u8; 32],
+    pub(crate) k_chain: [u8; 32],
+}
+
+/// [`SkippedItemPeer`] of schema version 1, which held the chain key of the skipped message.
+#[derive(Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
+pub(crate) struct SkippedItemPeerV1 {
+    pub(crate) k_chain: [u8; 32],
+    pub(crate) min_parent_height: u64,
 }
 
 #[cfg(test)]
@@ -83,9 +182,9 @@ mod tests {
 
         let history_item = HistoryItemSelf {
             height: 1,
-            sk_next,
+            sk_next: sk_next.into(),
             k_next,
-
+            k_chain: k_next,
             seeker: seeker.to_vec(),
         };
 
@@ -103,12 +202,38 @@ mod tests {
         let k_next = [42u8; 32];
 
         let history_item = HistoryItemPeer {
+            height: 3,
             our_parent_height: 5,
-            pk_next,
+            pk_next: pk_next.into(),
             k_next,
+            k_chain: k_next,
         };
 
         assert_eq!(history_item.our_parent_height, 5);
         assert_eq!(history_item.k_next, k_next);
     }
+
+    #[test]
+    fn test_skipped_item_peer_creation() {
+        let skipped_item = SkippedItemPeer {
+            height: 4,
+            min_parent_height: 3,
+        };
+
+        assert_eq!(skipped_item.height, 4);
+        assert_eq!(skipped_item.min_parent_height, 3);
+    }
+
+    #[test]
+    fn test_skipped_chain_key_peer_creation() {
+        let k_chain = [42u8; 32];
+
+        let skipped_chain_key = SkippedChainKeyPeer {
+            our_parent_height: 3,
+            k_chain,
+        };
+
+        assert_eq!(skipped_chain_key.our_parent_height, 3);
+        assert_eq!(skipped_chain_key.k_chain, k_chain);
+    }
 }
@@ -9,9 +9,14 @@
 //! - **Forward Secrecy**: Past messages remain secure even if current keys are compromised
 //! - **Post-Compromise Security**: Future messages are secure after key material is refreshed
 //! - **Asynchronous Communication**: Either party can send multiple messages without waiting for responses
+//! - **Out-of-Order Delivery**: Messages can be processed in any order, within configurable limits
 //!
-//! **Note**: Messages must be processed in the order they were sent. Out-of-order delivery is not supported
-//! due to the ratcheting mechanism that chains cryptographic keys between successive messages
+//! **Note**: The messages a party sends in response to a same peer message are linked by a one-way
+//! chain key, re-seeded from the root key of each new peer message. When a message arrives before
+//! earlier ones, the receiver steps the chain forward and keeps the chain keys of the missing
+//! messages so they can be decrypted later. The number of messages that can be skipped over and
+//! the number of chain keys kept are bounded by [`SkippedMessageLimits`]. Each message can only be
+//! decrypted once.
 //!
 //! ## ⚠️ Security Warnings
 //!
@@ -104,10 +109,36 @@
 //!    - Session state ratchets forward with each message
 //!    - Messages include a "seeker" to identify which message they're responding to
 //!
+//! ## Protocol Versions
+//!
+//! The announcement and message KDFs are domain-separated by a [`ProtocolVersion`], which also
+//! selects the key encapsulation mechanism whose outputs they consume:
+//!
+//! - [`ProtocolVersion::V1`]: ML-KEM-768 only.
+//! - [`ProtocolVersion::V2`]: hybrid X25519 + ML-KEM-768 from `crypto_kem::hybrid`, which keeps
+//!   classical security should ML-KEM-768 turn out to be weak.
+//!
+//! The version of an announcement or a session is the one of the static keys of the parties,
+//! given as [`KemPublicKey`] and [`KemSecretKey`] or as keys of either `crypto_kem` API. Both
+//! parties must have static keys of the same version. Keys derived under one version can never
+//! match keys derived under another one.
+//!
+//! Only this crate supports [`ProtocolVersion::V2`] so far. The static keys of
+//! `auth::UserPublicKeys` are ML-KEM-768 keys, so sessions established through the `sessions`
+//! crate always run [`ProtocolVersion::V1`].
+//!
+//! ## Test Vectors
+//!
+//! `test_vectors/agraphon.json` holds known-answer vectors for the KDFs and for the bytes of a
+//! full conversation, generated with a seeded random source, so that other implementations can
+//! check their interoperability. The tests of this crate check that it still produces them.
+//!
 //! ## Example Usage
 //!
 //! ```rust
-//! use crypto_agraphon::{OutgoingAnnouncementPrecursor, IncomingAnnouncementPrecursor, Agraphon};
+//! use crypto_agraphon::{
+//!     Agraphon, IncomingAnnouncementPrecursor, OutgoingAnnouncementPrecursor, SkippedMessageLimits,
+//! };
 //! use crypto_kem as kem;
 //! use crypto_rng as rng;
 //!
@@ -147,8 +178,9 @@
 //! let bob_incoming = bob_incoming_pre.finalize(bob_pk.clone()).expect("Integrity check failed");
 //!
 //! // Both create sessions by pairing their outgoing with peer's incoming
-//! let mut alice_session = Agraphon::from_announcement_pair(&alice_announcement, &bob_incoming);
-//! let mut bob_session = Agraphon::from_announcement_pair(&bob_announcement, &alice_incoming);
+//! let limits = SkippedMessageLimits::default();
+//! let mut alice_session = Agraphon::from_announcement_pair(&alice_announcement, &bob_incoming, limits);
+//! let mut bob_session = Agraphon::from_announcement_pair(&bob_announcement, &alice_incoming, limits);
 //!
 //! // Now they can exchange messages
 //! let result = alice_session.send_outgoing_message(b"seeker_alice", b"Hello, Bob!", &bob_pk);
@@ -161,11 +193,20 @@ mod agraphon;
 mod announcement;
 mod announcement_auth_kdf;
 mod announcement_root_kdf;
+mod error;
 mod history;
+mod message_chain_kdf;
 mod message_root_kdf;
+mod protocol_version;
+#[cfg(test)]
+mod test_vectors;
 
-pub use agraphon::Agraphon;
+pub use agraphon::{
+    Agraphon, AgraphonV0, AgraphonV1, DecryptedIncomingMessage, SkippedMessageLimits,
+};
 pub use announcement::{
     IncomingAnnouncement, IncomingAnnouncementPrecursor, OutgoingAnnouncement,
     OutgoingAnnouncementPrecursor,
 };
+pub use error::AgraphonError;
+pub use protocol_version::{KemPublicKey, KemSecretKey, ProtocolVersion};
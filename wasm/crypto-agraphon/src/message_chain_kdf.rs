@@ -0,0 +1,127 @@
+//! Symmetric chain key derivation between successive messages of a same sender.
+//!
+//! The messages a party sends in response to a same peer message form a chain: the first
+//! chain key is derived from the root key of that peer message, and each following one from
+//! the previous chain key. Because a step only depends on the previous chain key, a receiver
+//! can step the chain forward over messages it has not received yet.
+
+use crypto_kdf as kdf;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+/// Chain key derivation function for successive messages of a same sender.
+///
+/// Each message is encrypted under keys derived (among other inputs) from a chain key.
+/// A new chain starts from the root key of each peer message, as in the Double Ratchet:
+/// the chain key of the first response to a message is derived from its root key, and the
+/// chain key used by the next response from the same sender is derived from the current one
+/// through this one-way KDF.
+///
+/// # Security Properties
+///
+/// - **One-way**: Past chain keys cannot be recovered from the current one
+/// - **Skippable**: The receiver can derive chain keys of messages it has not received yet,
+///   which allows decrypting messages delivered out of order
+/// - **Re-seeded**: Each peer message starts a new chain from a root key that mixes fresh
+///   KEM shared secrets, so a compromised chain key only exposes the chain it belongs to
+///
+/// # Cryptographic Details
+///
+/// Uses HKDF with:
+/// - Salt: `"agraphon.message_chain_kdf.salt.V1"`
+/// - Inputs: `k_chain`, or the root key `k_next` for the first chain key
+/// - Info string: `"agraphon.message_chain_kdf.k_chain_next"`, or
+///   `"agraphon.message_chain_kdf.k_chain_first"` for the first chain key
+#[derive(Zeroize, ZeroizeOnDrop)]
+pub struct MessageChainKdf {
+    /// Chain key for the next message of the same sender
+    pub(crate) k_chain_next: [u8; 32],
+}
+
+impl MessageChainKdf {
+    /// Derives the chain key of the next message from the current chain key.
+    ///
+    /// # Arguments
+    ///
+    /// * `k_chain` - Chain key used by the current message
+    ///
+    /// # Returns
+    ///
+    /// A `MessageChainKdf` containing the chain key for the next message.
+    ///
+    /// # Example Usage (internal)
+    ///
+    /// ```text
+    /// let k_chain = [0u8; 32];
+    /// let kdf = MessageChainKdf::new(&k_chain);
+    /// // kdf.k_chain_next is the chain key of the following message
+    /// ```
+    pub(crate) fn new(k_chain: &[u8; 32]) -> Self {
+        Self::derive(k_chain, b"agraphon.message_chain_kdf.k_chain_next")
+    }
+
+    /// Derives the chain key of the first response to a message from the root key of that
+    /// message.
+    ///
+    /// # Arguments
+    ///
+    /// * `k_next` - Root key derived by the message being responded to
+    ///
+    /// # Returns
+    ///
+    /// A `MessageChainKdf` containing the chain key for the first response.
+    pub(crate) fn from_root_key(k_next: &[u8; 32]) -> Self {
+        Self::derive(k_next, b"agraphon.message_chain_kdf.k_chain_first")
+    }
+
+    fn derive(input: &[u8; 32], info: &[u8]) -> Self {
+        let mut k_chain_next = [0u8; 32];
+
+        let mut chain_kdf = kdf::Extract::new(b"agraphon.message_chain_kdf.salt.V1");
+        chain_kdf.input_item(input.as_slice());
+        let chain_kdf = chain_kdf.finalize();
+
+        chain_kdf.expand(info, &mut k_chain_next);
+
+        Self { k_chain_next }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message_chain_kdf_deterministic() {
+        // Same input should produce same output
+        let k_chain = [42u8; 32];
+
+        let kdf1 = MessageChainKdf::new(&k_chain);
+        let kdf2 = MessageChainKdf::new(&k_chain);
+
+        assert_eq!(kdf1.k_chain_next, kdf2.k_chain_next);
+    }
+
+    #[test]
+    fn test_message_chain_kdf_advances() {
+        // The derived key should differ from the input and keep changing along the chain
+        let k_chain = [42u8; 32];
+
+        let step1 = MessageChainKdf::new(&k_chain);
+        let step2 = MessageChainKdf::new(&step1.k_chain_next);
+
+        assert_ne!(step1.k_chain_next, k_chain);
+        assert_ne!(step2.k_chain_next, step1.k_chain_next);
+    }
+
+    #[test]
+    fn test_message_chain_kdf_first_key_separated() {
+        // Starting a chain from a root key differs from stepping a chain key
+        let k_next = [42u8; 32];
+
+        let first = MessageChainKdf::from_root_key(&k_next);
+        let step = MessageChainKdf::new(&k_next);
+
+        assert_ne!(first.k_chain_next, k_next);
+        assert_ne!(first.k_chain_next, step.k_chain_next);
+    }
+}
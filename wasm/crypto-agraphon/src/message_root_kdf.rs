@@ -4,16 +4,16 @@
 //! and authentication. It combines both parties' master keys with the ephemeral
 //! shared secret from key encapsulation.
 
+use crate::protocol_version::{Encapsulation, ProtocolVersion};
 use crypto_aead as cipher;
 use crypto_kdf as kdf;
-use crypto_kem as kem;
 use zeroize::{Zeroize, ZeroizeOnDrop};
 
 /// Root key derivation function for regular messages.
 ///
 /// This KDF combines multiple sources of entropy to derive per-message keys:
 /// - Fresh randomness
-/// - Both parties' master keys (from previous messages)
+/// - The sender's chain key and the peer's master key (from previous messages)
 /// - A fresh shared secret from KEM encapsulation
 /// - The KEM ciphertext (for domain separation)
 /// - The sender's role (for bidirectional security)
@@ -34,8 +34,10 @@ use zeroize::{Zeroize, ZeroizeOnDrop};
 /// # Cryptographic Details
 ///
 /// Uses HKDF with:
-/// - Salt: `"agraphon.message_root_kdf.salt.V1----"`
-/// - Inputs: `randomness`, `p_self_mk_next`, `p_peer_mk_next`, `shared_secret`, ciphertext, role
+/// - Salt: `"agraphon.message_root_kdf.salt.V1"`, or `"agraphon.message_root_kdf.salt.V2"`
+///   for [`ProtocolVersion::V2`]
+/// - Inputs: `randomness`, `k_chain`, `p_peer_k_next`, then the ciphertext and shared secret
+///   of the encapsulations to the peer's next and static keys
 /// - Info strings: `"agraphon.message_root_kdf.cipher_key"`
 ///   `"agraphon.message_root_kdf.cipher_nonce"`, and
 ///   `"agraphon.message_root_kdf.integrity_seed"`
@@ -54,12 +56,12 @@ impl MessageRootKdf {
     ///
     /// # Arguments
     ///
+    /// * `version` - Protocol version, selecting the KEM of the encapsulations
     /// * `randomness` - Fresh 32-byte randomness for this message
-    /// * `p_self_mk_next` - Our master key from our most recent message
-    /// * `p_peer_mk_next` - Peer's master key from their most recent message
-    /// * `ss` - Shared secret from KEM encapsulation
-    /// * `ct` - KEM ciphertext (for domain separation)
-    /// * `role` - The sender's role (Initiator or Responder)
+    /// * `k_chain` - The sender's chain key for this message
+    /// * `p_peer_k_next` - Peer's next key from their most recent message
+    /// * `msg` - KEM encapsulation to the peer's next public key
+    /// * `msg_static` - KEM encapsulation to the peer's static public key
     ///
     /// # Returns
     ///
@@ -83,31 +85,44 @@ impl MessageRootKdf {
     /// let mut enc_rand = [0u8; kem::ENCAPSULATION_RANDOMNESS_SIZE];
     /// rng::fill_buffer(&mut enc_rand);
     /// let (ct, ss) = kem::encapsulate(&pk, enc_rand);
+    /// let (ct_static, ss_static) = kem::encapsulate(&peer_static_pk, enc_rand_static);
     ///
-    /// let kdf = MessageRootKdf::new(&randomness, &self_mk, &peer_mk, &ss, &ct, &Role::Initiator);
+    /// let kdf = MessageRootKdf::new(
+    ///     ProtocolVersion::V1,
+    ///     &randomness,
+    ///     &self_mk,
+    ///     &peer_mk,
+    ///     &Encapsulation::ml_kem(&ct, &ss),
+    ///     &Encapsulation::ml_kem(&ct_static, &ss_static),
+    /// );
     /// // kdf.cipher_key and kdf.cipher_nonce can now be used to encrypt the message
     /// ```
     pub(crate) fn new(
+        version: ProtocolVersion,
         randomness: &[u8; 32],
-        p_self_k_next: &[u8],
+        k_chain: &[u8],
         p_peer_k_next: &[u8],
-        ct: &kem::Ciphertext,
-        ss: &kem::SharedSecret,
-        ct_static: &kem::Ciphertext,
-        ss_static: &kem::SharedSecret,
+        msg: &Encapsulation<'_>,
+        msg_static: &Encapsulation<'_>,
     ) -> Self {
+        debug_assert_eq!(msg.ct.len(), version.kem_ciphertext_size());
+        debug_assert_eq!(msg_static.ct.len(), version.kem_ciphertext_size());
         let mut cipher_key = [0u8; cipher::KEY_SIZE];
         let mut cipher_nonce = [0u8; cipher::NONCE_SIZE];
         let mut k_next = [0u8; 32];
 
-        let mut root_kdf = kdf::Extract::new(b"agraphon.message_root_kdf.salt.V1");
+        let salt: &[u8] = match version {
+            ProtocolVersion::V1 => b"agraphon.message_root_kdf.salt.V1",
+            ProtocolVersion::V2 => b"agraphon.message_root_kdf.salt.V2",
+        };
+        let mut root_kdf = kdf::Extract::new(salt);
         root_kdf.input_item(randomness.as_slice());
-        root_kdf.input_item(p_self_k_next);
+        root_kdf.input_item(k_chain);
         root_kdf.input_item(p_peer_k_next);
-        root_kdf.input_item(ct.as_bytes());
-        root_kdf.input_item(ss.as_bytes());
-        root_kdf.input_item(ct_static.as_bytes());
-        root_kdf.input_item(ss_static.as_bytes());
+        root_kdf.input_item(msg.ct);
+        root_kdf.input_item(msg.ss.as_bytes());
+        root_kdf.input_item(msg_static.ct);
+        root_kdf.input_item(msg_static.ss.as_bytes());
         let root_kdf = root_kdf.finalize();
         root_kdf.expand(
             "agraphon.message_root_kdf.cipher_nonce".as_bytes(),
@@ -129,6 +144,7 @@ impl MessageRootKdf {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crypto_kem as kem;
     use crypto_rng as rng;
 
     #[test]
@@ -151,22 +167,20 @@ mod tests {
         let (ct_static, ss_static) = kem::encapsulate(&pk, enc_rand2);
 
         let kdf1 = MessageRootKdf::new(
+            ProtocolVersion::V1,
             &randomness,
             &p_self_k_next,
             &p_peer_k_next,
-            &ct,
-            &ss,
-            &ct_static,
-            &ss_static,
+            &Encapsulation::ml_kem(&ct, &ss),
+            &Encapsulation::ml_kem(&ct_static, &ss_static),
         );
         let kdf2 = MessageRootKdf::new(
+            ProtocolVersion::V1,
             &randomness,
             &p_self_k_next,
             &p_peer_k_next,
-            &ct,
-            &ss,
-            &ct_static,
-            &ss_static,
+            &Encapsulation::ml_kem(&ct, &ss),
+            &Encapsulation::ml_kem(&ct_static, &ss_static),
         );
 
         assert_eq!(kdf1.cipher_key.as_bytes(), kdf2.cipher_key.as_bytes());
@@ -195,22 +209,20 @@ mod tests {
         let (ct_static, ss_static) = kem::encapsulate(&pk, enc_rand2);
 
         let kdf1 = MessageRootKdf::new(
+            ProtocolVersion::V1,
             &randomness1,
             &p_self_k_next,
             &p_peer_k_next,
-            &ct,
-            &ss,
-            &ct_static,
-            &ss_static,
+            &Encapsulation::ml_kem(&ct, &ss),
+            &Encapsulation::ml_kem(&ct_static, &ss_static),
         );
         let kdf2 = MessageRootKdf::new(
+            ProtocolVersion::V1,
             &randomness2,
             &p_self_k_next,
             &p_peer_k_next,
-            &ct,
-            &ss,
-            &ct_static,
-            &ss_static,
+            &Encapsulation::ml_kem(&ct, &ss),
+            &Encapsulation::ml_kem(&ct_static, &ss_static),
         );
 
         assert_ne!(kdf1.cipher_key.as_bytes(), kdf2.cipher_key.as_bytes());
@@ -238,22 +250,20 @@ mod tests {
         let (ct_static, ss_static) = kem::encapsulate(&pk, enc_rand2);
 
         let kdf1 = MessageRootKdf::new(
+            ProtocolVersion::V1,
             &randomness,
             &p_self_k_next1,
             &p_peer_k_next,
-            &ct,
-            &ss,
-            &ct_static,
-            &ss_static,
+            &Encapsulation::ml_kem(&ct, &ss),
+            &Encapsulation::ml_kem(&ct_static, &ss_static),
         );
         let kdf2 = MessageRootKdf::new(
+            ProtocolVersion::V1,
             &randomness,
             &p_self_k_next2,
             &p_peer_k_next,
-            &ct,
-            &ss,
-            &ct_static,
-            &ss_static,
+            &Encapsulation::ml_kem(&ct, &ss),
+            &Encapsulation::ml_kem(&ct_static, &ss_static),
         );
 
         assert_ne!(kdf1.cipher_key.as_bytes(), kdf2.cipher_key.as_bytes());
@@ -280,17 +290,69 @@ mod tests {
         let (ct_static, ss_static) = kem::encapsulate(&pk, enc_rand2);
 
         let kdf = MessageRootKdf::new(
+            ProtocolVersion::V1,
             &randomness,
             &p_self_k_next,
             &p_peer_k_next,
-            &ct,
-            &ss,
-            &ct_static,
-            &ss_static,
+            &Encapsulation::ml_kem(&ct, &ss),
+            &Encapsulation::ml_kem(&ct_static, &ss_static),
         );
 
         assert_eq!(kdf.cipher_key.as_bytes().len(), cipher::KEY_SIZE);
         assert_eq!(kdf.cipher_nonce.as_bytes().len(), cipher::NONCE_SIZE);
         assert_eq!(kdf.k_next.len(), 32);
     }
+
+    #[test]
+    fn test_message_root_kdf_hybrid() {
+        // Both sides of hybrid encapsulations should derive the same keys
+        let randomness = [42u8; 32];
+        let p_self_k_next = [1u8; 32];
+        let p_peer_k_next = [2u8; 32];
+
+        let mut pk_rand = [0u8; kem::hybrid::KEY_GENERATION_RANDOMNESS_SIZE];
+        rng::fill_buffer(&mut pk_rand);
+        let (sk, pk) = kem::hybrid::generate_key_pair(pk_rand);
+
+        let mut enc_rand = [0u8; kem::hybrid::ENCAPSULATION_RANDOMNESS_SIZE];
+        rng::fill_buffer(&mut enc_rand);
+        let (ct, ss) = kem::hybrid::encapsulate(&pk, enc_rand);
+
+        let mut enc_rand2 = [0u8; kem::hybrid::ENCAPSULATION_RANDOMNESS_SIZE];
+        rng::fill_buffer(&mut enc_rand2);
+        let (ct_static, ss_static) = kem::hybrid::encapsulate(&pk, enc_rand2);
+
+        let kdf1 = MessageRootKdf::new(
+            ProtocolVersion::V2,
+            &randomness,
+            &p_self_k_next,
+            &p_peer_k_next,
+            &Encapsulation {
+                ct: ct.as_bytes(),
+                ss: &ss,
+            },
+            &Encapsulation {
+                ct: ct_static.as_bytes(),
+                ss: &ss_static,
+            },
+        );
+        let kdf2 = MessageRootKdf::new(
+            ProtocolVersion::V2,
+            &randomness,
+            &p_self_k_next,
+            &p_peer_k_next,
+            &Encapsulation {
+                ct: ct.as_bytes(),
+                ss: &kem::hybrid::decapsulate(&sk, &ct),
+            },
+            &Encapsulation {
+                ct: ct_static.as_bytes(),
+                ss: &kem::hybrid::decapsulate(&sk, &ct_static),
+            },
+        );
+
+        assert_eq!(kdf1.cipher_key.as_bytes(), kdf2.cipher_key.as_bytes());
+        assert_eq!(kdf1.cipher_nonce.as_bytes(), kdf2.cipher_nonce.as_bytes());
+        assert_eq!(kdf1.k_next, kdf2.k_next);
+    }
 }
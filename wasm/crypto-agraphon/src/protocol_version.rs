@@ -0,0 +1,362 @@
+//! Protocol versions and the key encapsulation mechanism each of them uses.
+
+use crypto_kem as kem;
+use crypto_rng as rng;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+/// Version of the agraphon protocol.
+///
+/// The version selects the key encapsulation mechanism whose outputs are fed to the
+/// announcement and message KDFs, and domain-separates these KDFs so that keys derived
+/// under different versions never collide.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_agraphon::ProtocolVersion;
+/// use crypto_kem as kem;
+///
+/// assert_eq!(ProtocolVersion::default(), ProtocolVersion::V1);
+/// assert_eq!(
+///     ProtocolVersion::V2.kem_ciphertext_size(),
+///     kem::hybrid::CIPHERTEXT_SIZE
+/// );
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ProtocolVersion {
+    /// ML-KEM-768 only.
+    #[default]
+    V1,
+    /// Hybrid X25519 + ML-KEM-768 (see [`crypto_kem::hybrid`]), which stays secure
+    /// as long as either scheme is unbroken.
+    V2,
+}
+
+impl ProtocolVersion {
+    /// Returns the size of the KEM ciphertexts of this version in bytes.
+    #[must_use]
+    pub const fn kem_ciphertext_size(self) -> usize {
+        match self {
+            Self::V1 => kem::CIPHERTEXT_SIZE,
+            Self::V2 => kem::hybrid::CIPHERTEXT_SIZE,
+        }
+    }
+
+    /// Returns the size of the KEM public keys of this version in bytes.
+    #[must_use]
+    pub const fn kem_public_key_size(self) -> usize {
+        match self {
+            Self::V1 => kem::PUBLIC_KEY_SIZE,
+            Self::V2 => kem::hybrid::PUBLIC_KEY_SIZE,
+        }
+    }
+
+    /// Generates a KEM key pair of this version from fresh randomness.
+    pub(crate) fn generate_key_pair(self) -> (KemSecretKey, KemPublicKey) {
+        match self {
+            Self::V1 => {
+                let mut randomness = Zeroizing::new([0u8; kem::KEY_GENERATION_RANDOMNESS_SIZE]);
+                rng::fill_buffer(randomness.as_mut_slice());
+                let (sk, pk) = kem::generate_key_pair(*randomness);
+                (KemSecretKey::MlKem(sk), KemPublicKey::MlKem(pk))
+            }
+            Self::V2 => {
+                let mut randomness =
+                    Zeroizing::new([0u8; kem::hybrid::KEY_GENERATION_RANDOMNESS_SIZE]);
+                rng::fill_buffer(randomness.as_mut_slice());
+                let (sk, pk) = kem::hybrid::generate_key_pair(*randomness);
+                (KemSecretKey::Hybrid(sk), KemPublicKey::Hybrid(pk))
+            }
+        }
+    }
+}
+
+/// A KEM public key of the mechanism of one of the protocol versions.
+///
+/// The version of a session is the one of the static keys of its parties: announcements and
+/// messages are only exchanged between keys of the same version.
+///
+/// Keys are serialized as their bytes and told apart by their size, so that ML-KEM-768 keys
+/// serialize exactly as [`kem::PublicKey`] does.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_agraphon::{KemPublicKey, ProtocolVersion};
+/// use crypto_kem as kem;
+///
+/// let (_, pk) = kem::hybrid::generate_key_pair([1u8; kem::hybrid::KEY_GENERATION_RANDOMNESS_SIZE]);
+/// assert_eq!(KemPublicKey::from(&pk).version(), ProtocolVersion::V2);
+/// ```
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
+pub enum KemPublicKey {
+    /// An ML-KEM-768 key, for [`ProtocolVersion::V1`]
+    MlKem(kem::PublicKey),
+    /// A hybrid X25519 + ML-KEM-768 key, for [`ProtocolVersion::V2`]
+    Hybrid(kem::hybrid::PublicKey),
+}
+
+impl KemPublicKey {
+    /// Returns the protocol version this key is used with.
+    #[must_use]
+    pub const fn version(&self) -> ProtocolVersion {
+        match self {
+            Self::MlKem(_) => ProtocolVersion::V1,
+            Self::Hybrid(_) => ProtocolVersion::V2,
+        }
+    }
+
+    /// Returns the raw bytes of the key.
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            Self::MlKem(pk) => pk.as_bytes(),
+            Self::Hybrid(pk) => pk.as_bytes(),
+        }
+    }
+
+    /// Parses a key of the given version, returning `None` if its size does not match.
+    pub(crate) fn from_bytes(version: ProtocolVersion, bytes: &[u8]) -> Option<Self> {
+        match version {
+            ProtocolVersion::V1 => <[u8; kem::PUBLIC_KEY_SIZE]>::try_from(bytes)
+                .ok()
+                .map(|bytes| Self::MlKem(bytes.into())),
+            ProtocolVersion::V2 => <[u8; kem::hybrid::PUBLIC_KEY_SIZE]>::try_from(bytes)
+                .ok()
+                .map(|bytes| Self::Hybrid(bytes.into())),
+        }
+    }
+
+    /// Encapsulates a shared secret to this key from fresh randomness.
+    pub(crate) fn encapsulate(&self) -> (KemCiphertext, kem::SharedSecret) {
+        match self {
+            Self::MlKem(pk) => {
+                let mut randomness = Zeroizing::new([0u8; kem::ENCAPSULATION_RANDOMNESS_SIZE]);
+                rng::fill_buffer(randomness.as_mut_slice());
+                let (ct, ss) = kem::encapsulate(pk, *randomness);
+                (KemCiphertext::MlKem(ct), ss)
+            }
+            Self::Hybrid(pk) => {
+                let mut randomness =
+                    Zeroizing::new([0u8; kem::hybrid::ENCAPSULATION_RANDOMNESS_SIZE]);
+                rng::fill_buffer(randomness.as_mut_slice());
+                let (ct, ss) = kem::hybrid::encapsulate(pk, *randomness);
+                (KemCiphertext::Hybrid(ct), ss)
+            }
+        }
+    }
+}
+
+impl From<kem::PublicKey> for KemPublicKey {
+    fn from(pk: kem::PublicKey) -> Self {
+        Self::MlKem(pk)
+    }
+}
+
+impl From<&kem::PublicKey> for KemPublicKey {
+    fn from(pk: &kem::PublicKey) -> Self {
+        Self::MlKem(pk.clone())
+    }
+}
+
+impl From<kem::hybrid::PublicKey> for KemPublicKey {
+    fn from(pk: kem::hybrid::PublicKey) -> Self {
+        Self::Hybrid(pk)
+    }
+}
+
+impl From<&kem::hybrid::PublicKey> for KemPublicKey {
+    fn from(pk: &kem::hybrid::PublicKey) -> Self {
+        Self::Hybrid(pk.clone())
+    }
+}
+
+impl From<&Self> for KemPublicKey {
+    fn from(pk: &Self) -> Self {
+        pk.clone()
+    }
+}
+
+impl Serialize for KemPublicKey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(self.as_bytes())
+    }
+}
+
+impl<'de> Deserialize<'de> for KemPublicKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes: Zeroizing<Vec<u8>> = Zeroizing::new(Deserialize::deserialize(deserializer)?);
+        [ProtocolVersion::V1, ProtocolVersion::V2]
+            .into_iter()
+            .find_map(|version| Self::from_bytes(version, &bytes))
+            .ok_or_else(|| {
+                serde::de::Error::custom(format!("no KEM public key has {} bytes", bytes.len()))
+            })
+    }
+}
+
+/// A KEM secret key of the mechanism of one of the protocol versions.
+///
+/// See [`KemPublicKey`], which this mirrors.
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
+pub enum KemSecretKey {
+    /// An ML-KEM-768 key, for [`ProtocolVersion::V1`]
+    MlKem(kem::SecretKey),
+    /// A hybrid X25519 + ML-KEM-768 key, for [`ProtocolVersion::V2`]
+    Hybrid(kem::hybrid::SecretKey),
+}
+
+impl KemSecretKey {
+    /// Returns the protocol version this key is used with.
+    #[must_use]
+    pub const fn version(&self) -> ProtocolVersion {
+        match self {
+            Self::MlKem(_) => ProtocolVersion::V1,
+            Self::Hybrid(_) => ProtocolVersion::V2,
+        }
+    }
+
+    /// Returns the raw bytes of the key.
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            Self::MlKem(sk) => sk.as_bytes(),
+            Self::Hybrid(sk) => sk.as_bytes(),
+        }
+    }
+
+    /// Parses a key of the given version, returning `None` if its size does not match.
+    fn from_bytes(version: ProtocolVersion, bytes: &[u8]) -> Option<Self> {
+        match version {
+            ProtocolVersion::V1 => <[u8; kem::PRIVATE_KEY_SIZE]>::try_from(bytes)
+                .ok()
+                .map(|bytes| Self::MlKem(bytes.into())),
+            ProtocolVersion::V2 => <[u8; kem::hybrid::PRIVATE_KEY_SIZE]>::try_from(bytes)
+                .ok()
+                .map(|bytes| Self::Hybrid(bytes.into())),
+        }
+    }
+
+    /// Decapsulates a shared secret, returning `None` if the ciphertext is of another version.
+    pub(crate) fn decapsulate(&self, ct: &KemCiphertext) -> Option<kem::SharedSecret> {
+        match (self, ct) {
+            (Self::MlKem(sk), KemCiphertext::MlKem(ct)) => Some(kem::decapsulate(sk, ct)),
+            (Self::Hybrid(sk), KemCiphertext::Hybrid(ct)) => Some(kem::hybrid::decapsulate(sk, ct)),
+            _ => None,
+        }
+    }
+}
+
+impl From<kem::SecretKey> for KemSecretKey {
+    fn from(sk: kem::SecretKey) -> Self {
+        Self::MlKem(sk)
+    }
+}
+
+impl From<&kem::SecretKey> for KemSecretKey {
+    fn from(sk: &kem::SecretKey) -> Self {
+        Self::MlKem(sk.clone())
+    }
+}
+
+impl From<kem::hybrid::SecretKey> for KemSecretKey {
+    fn from(sk: kem::hybrid::SecretKey) -> Self {
+        Self::Hybrid(sk)
+    }
+}
+
+impl From<&kem::hybrid::SecretKey> for KemSecretKey {
+    fn from(sk: &kem::hybrid::SecretKey) -> Self {
+        Self::Hybrid(sk.clone())
+    }
+}
+
+impl From<&Self> for KemSecretKey {
+    fn from(sk: &Self) -> Self {
+        sk.clone()
+    }
+}
+
+impl Serialize for KemSecretKey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(self.as_bytes())
+    }
+}
+
+impl<'de> Deserialize<'de> for KemSecretKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes: Zeroizing<Vec<u8>> = Zeroizing::new(Deserialize::deserialize(deserializer)?);
+        [ProtocolVersion::V1, ProtocolVersion::V2]
+            .into_iter()
+            .find_map(|version| Self::from_bytes(version, &bytes))
+            .ok_or_else(|| {
+                serde::de::Error::custom(format!("no KEM secret key has {} bytes", bytes.len()))
+            })
+    }
+}
+
+/// A KEM ciphertext of the mechanism of one of the protocol versions.
+#[derive(Zeroize)]
+pub(crate) enum KemCiphertext {
+    /// An ML-KEM-768 ciphertext, for [`ProtocolVersion::V1`]
+    MlKem(kem::Ciphertext),
+    /// A hybrid X25519 + ML-KEM-768 ciphertext, for [`ProtocolVersion::V2`]
+    Hybrid(kem::hybrid::Ciphertext),
+}
+
+impl KemCiphertext {
+    /// Parses a ciphertext of the given version, returning `None` if its size does not match.
+    pub(crate) fn from_bytes(version: ProtocolVersion, bytes: &[u8]) -> Option<Self> {
+        match version {
+            ProtocolVersion::V1 => <[u8; kem::CIPHERTEXT_SIZE]>::try_from(bytes)
+                .ok()
+                .map(|bytes| Self::MlKem(bytes.into())),
+            ProtocolVersion::V2 => <[u8; kem::hybrid::CIPHERTEXT_SIZE]>::try_from(bytes)
+                .ok()
+                .map(|bytes| Self::Hybrid(bytes.into())),
+        }
+    }
+
+    /// Returns the raw bytes of the ciphertext.
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        match self {
+            Self::MlKem(ct) => ct.as_bytes(),
+            Self::Hybrid(ct) => ct.as_bytes(),
+        }
+    }
+}
+
+/// The output of a key encapsulation, as fed to the KDFs.
+pub(crate) struct Encapsulation<'a> {
+    /// The KEM ciphertext
+    pub(crate) ct: &'a [u8],
+    /// The encapsulated shared secret
+    pub(crate) ss: &'a kem::SharedSecret,
+}
+
+impl<'a> Encapsulation<'a> {
+    /// An encapsulation of any version.
+    pub(crate) fn new(ct: &'a KemCiphertext, ss: &'a kem::SharedSecret) -> Self {
+        match ct {
+            KemCiphertext::MlKem(ct) => Self::ml_kem(ct, ss),
+            KemCiphertext::Hybrid(ct) => Self::hybrid(ct, ss),
+        }
+    }
+
+    /// An ML-KEM-768 encapsulation, for [`ProtocolVersion::V1`].
+    pub(crate) fn ml_kem(ct: &'a kem::Ciphertext, ss: &'a kem::SharedSecret) -> Self {
+        Self {
+            ct: ct.as_bytes(),
+            ss,
+        }
+    }
+
+    /// A hybrid X25519 + ML-KEM-768 encapsulation, for [`ProtocolVersion::V2`].
+    pub(crate) fn hybrid(ct: &'a kem::hybrid::Ciphertext, ss: &'a kem::SharedSecret) -> Self {
+        Self {
+            ct: ct.as_bytes(),
+            ss,
+        }
+    }
+}
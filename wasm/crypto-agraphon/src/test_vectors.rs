@@ -0,0 +1,491 @@
+//! Known-answer test vectors for the agraphon KDFs and wire formats.
+//!
+//! `test_vectors/agraphon.json` holds:
+//!
+//! - the outputs of each KDF for given inputs, under both protocol versions for the root KDFs,
+//! - a conversation between Alice and Bob: both announcements, then messages in both
+//!   directions, including round trips and several messages from the same sender in a row.
+//!
+//! The conversation runs with the random source seeded with `rng_seed` (see
+//! `crypto_rng::seed`): each random value is the next slice of the ChaCha20 keystream keyed
+//! with the seed, with a zero nonce and counter. Values are drawn in this order:
+//!
+//! 1. the 64-byte ML-KEM key generation randomness of Alice's static keys, then of Bob's,
+//! 2. for each announcement: the 32-byte KDF randomness, the 32-byte randomness of the
+//!    encapsulation to the peer's static key, and the 64-byte key generation randomness of the
+//!    next key pair,
+//! 3. for each message: the 32-byte KDF randomness, the 32-byte randomness of the encapsulation
+//!    to the peer's next key, then to the peer's static key, and the 64-byte key generation
+//!    randomness of the next key pair.
+//!
+//! An independent implementation replaying the conversation must produce the same bytes.
+//! After an intentional change of the protocol, regenerate the file with:
+//!
+//! ```text
+//! cargo test -p crypto-agraphon --lib regenerate_test_vectors -- --ignored
+//! ```
+
+use crate::announcement_auth_kdf::AnnouncementAuthKdf;
+use crate::announcement_root_kdf::AnnouncementRootKdf;
+use crate::message_chain_kdf::MessageChainKdf;
+use crate::message_root_kdf::MessageRootKdf;
+use crate::protocol_version::{Encapsulation, ProtocolVersion};
+use crate::{
+    Agraphon, IncomingAnnouncement, IncomingAnnouncementPrecursor, OutgoingAnnouncement,
+    OutgoingAnnouncementPrecursor, SkippedMessageLimits,
+};
+use crypto_kem as kem;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+/// Path of the vector file, relative to the crate root.
+const VECTORS_PATH: &str = "test_vectors/agraphon.json";
+
+/// Seed of the random source drawing the KDF inputs.
+const KDF_INPUTS_SEED: [u8; 32] = *b"agraphon test vectors: kdf input";
+
+/// Seed of the random source of the conversation.
+const CONVERSATION_SEED: [u8; 32] = *b"agraphon test vectors: dialogue.";
+
+/// Authentication payloads of the announcements of Alice and Bob.
+const AUTH_PAYLOADS: [&str; 2] = ["alice auth payload", "bob auth payload"];
+
+/// Senders and payloads of the messages of the conversation, in order.
+const MESSAGES: [(Party, &str); 9] = [
+    (Party::Alice, "hello bob"),
+    (Party::Bob, "hello alice"),
+    (Party::Alice, "how are you?"),
+    (Party::Bob, "fine, and you?"),
+    (Party::Alice, "fine"),
+    (Party::Bob, "good to hear"),
+    (Party::Alice, "one more thing"),
+    (Party::Alice, "and another one"),
+    (Party::Bob, "noted"),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Party {
+    Alice,
+    Bob,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct TestVectors {
+    announcement_root_kdf: Vec<AnnouncementRootKdfVector>,
+    announcement_auth_kdf: Vec<AnnouncementAuthKdfVector>,
+    message_root_kdf: Vec<MessageRootKdfVector>,
+    message_chain_kdf: Vec<MessageChainKdfVector>,
+    conversation: ConversationVector,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct AnnouncementRootKdfVector {
+    version: ProtocolVersion,
+    randomness: String,
+    kem_ciphertext: String,
+    kem_shared_secret: String,
+    public_key: String,
+    cipher_nonce: String,
+    cipher_key: String,
+    k_next: String,
+    auth_pre_key: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct AnnouncementAuthKdfVector {
+    version: ProtocolVersion,
+    auth_pre_key: String,
+    pk_next: String,
+    auth_key: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct MessageRootKdfVector {
+    version: ProtocolVersion,
+    randomness: String,
+    k_chain: String,
+    peer_k_next: String,
+    kem_ciphertext: String,
+    kem_shared_secret: String,
+    static_kem_ciphertext: String,
+    static_kem_shared_secret: String,
+    cipher_nonce: String,
+    cipher_key: String,
+    k_next: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct MessageChainKdfVector {
+    k_next: String,
+    k_chain_first: String,
+    k_chain_next: String,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct ConversationVector {
+    rng_seed: String,
+    alice_public_key: String,
+    bob_public_key: String,
+    announcements: Vec<AnnouncementVector>,
+    messages: Vec<MessageVector>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct AnnouncementVector {
+    sender: Party,
+    auth_payload: String,
+    auth_key: String,
+    bytes: String,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct MessageVector {
+    sender: Party,
+    payload: String,
+    bytes: String,
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut hex, byte| {
+        write!(hex, "{byte:02x}").expect("writing to a string never fails");
+        hex
+    })
+}
+
+fn from_hex(hex: &str) -> Vec<u8> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).expect("invalid hex in test vectors"))
+        .collect()
+}
+
+fn from_hex_array<const N: usize>(hex: &str) -> [u8; N] {
+    from_hex(hex)
+        .try_into()
+        .expect("wrong length in test vectors")
+}
+
+fn shared_secret(hex: &str) -> kem::SharedSecret {
+    kem::SharedSecret::from(from_hex_array::<{ kem::SHARED_SECRET_SIZE }>(hex))
+}
+
+fn random_bytes(size: usize) -> Vec<u8> {
+    let mut bytes = vec![0u8; size];
+    crypto_rng::fill_buffer(&mut bytes);
+    bytes
+}
+
+fn random_hex(size: usize) -> String {
+    to_hex(&random_bytes(size))
+}
+
+impl AnnouncementRootKdfVector {
+    fn random(version: ProtocolVersion) -> Self {
+        Self {
+            version,
+            randomness: random_hex(32),
+            kem_ciphertext: random_hex(version.kem_ciphertext_size()),
+            kem_shared_secret: random_hex(kem::SHARED_SECRET_SIZE),
+            public_key: random_hex(version.kem_public_key_size()),
+            cipher_nonce: String::new(),
+            cipher_key: String::new(),
+            k_next: String::new(),
+            auth_pre_key: String::new(),
+        }
+        .with_outputs()
+    }
+
+    fn with_outputs(self) -> Self {
+        let kdf = AnnouncementRootKdf::new(
+            self.version,
+            &from_hex_array(&self.randomness),
+            &Encapsulation {
+                ct: &from_hex(&self.kem_ciphertext),
+                ss: &shared_secret(&self.kem_shared_secret),
+            },
+            &from_hex(&self.public_key),
+        );
+        Self {
+            cipher_nonce: to_hex(kdf.cipher_nonce.as_bytes()),
+            cipher_key: to_hex(kdf.cipher_key.as_bytes()),
+            k_next: to_hex(&kdf.k_next),
+            auth_pre_key: to_hex(&kdf.auth_pre_key),
+            ..self
+        }
+    }
+}
+
+impl AnnouncementAuthKdfVector {
+    fn random(version: ProtocolVersion) -> Self {
+        Self {
+            version,
+            auth_pre_key: random_hex(32),
+            pk_next: random_hex(version.kem_public_key_size()),
+            auth_key: String::new(),
+        }
+        .with_outputs()
+    }
+
+    fn with_outputs(self) -> Self {
+        let kdf = AnnouncementAuthKdf::new(
+            self.version,
+            &from_hex_array(&self.auth_pre_key),
+            &from_hex(&self.pk_next),
+        );
+        Self {
+            auth_key: to_hex(&kdf.auth_key),
+            ..self
+        }
+    }
+}
+
+impl MessageRootKdfVector {
+    fn random(version: ProtocolVersion) -> Self {
+        Self {
+            version,
+            randomness: random_hex(32),
+            k_chain: random_hex(32),
+            peer_k_next: random_hex(32),
+            kem_ciphertext: random_hex(version.kem_ciphertext_size()),
+            kem_shared_secret: random_hex(kem::SHARED_SECRET_SIZE),
+            static_kem_ciphertext: random_hex(version.kem_ciphertext_size()),
+            static_kem_shared_secret: random_hex(kem::SHARED_SECRET_SIZE),
+            cipher_nonce: String::new(),
+            cipher_key: String::new(),
+            k_next: String::new(),
+        }
+        .with_outputs()
+    }
+
+    fn with_outputs(self) -> Self {
+        let kdf = MessageRootKdf::new(
+            self.version,
+            &from_hex_array(&self.randomness),
+            &from_hex(&self.k_chain),
+            &from_hex(&self.peer_k_next),
+            &Encapsulation {
+                ct: &from_hex(&self.kem_ciphertext),
+                ss: &shared_secret(&self.kem_shared_secret),
+            },
+            &Encapsulation {
+                ct: &from_hex(&self.static_kem_ciphertext),
+                ss: &shared_secret(&self.static_kem_shared_secret),
+            },
+        );
+        Self {
+            cipher_nonce: to_hex(kdf.cipher_nonce.as_bytes()),
+            cipher_key: to_hex(kdf.cipher_key.as_bytes()),
+            k_next: to_hex(&kdf.k_next),
+            ..self
+        }
+    }
+}
+
+impl MessageChainKdfVector {
+    fn random() -> Self {
+        Self {
+            k_next: random_hex(32),
+            k_chain_first: String::new(),
+            k_chain_next: String::new(),
+        }
+        .with_outputs()
+    }
+
+    fn with_outputs(self) -> Self {
+        let first = MessageChainKdf::from_root_key(&from_hex_array(&self.k_next));
+        let next = MessageChainKdf::new(&first.k_chain_next);
+        Self {
+            k_chain_first: to_hex(&first.k_chain_next),
+            k_chain_next: to_hex(&next.k_chain_next),
+            ..self
+        }
+    }
+}
+
+/// The state of one side of the conversation.
+struct Side {
+    sk: kem::SecretKey,
+    pk: kem::PublicKey,
+    outgoing: Option<OutgoingAnnouncement>,
+    incoming: Option<IncomingAnnouncement>,
+    session: Option<Agraphon>,
+}
+
+impl Side {
+    fn new() -> Self {
+        let (sk, pk) = kem::generate_key_pair(
+            random_bytes(kem::KEY_GENERATION_RANDOMNESS_SIZE)
+                .try_into()
+                .expect("randomness has the key generation size"),
+        );
+        Self {
+            sk,
+            pk,
+            outgoing: None,
+            incoming: None,
+            session: None,
+        }
+    }
+}
+
+/// Announces `from` to `to`, and has `to` receive the announcement.
+fn announce(
+    from: &mut Side,
+    to: &mut Side,
+    sender: Party,
+    auth_payload: &str,
+) -> AnnouncementVector {
+    let precursor = OutgoingAnnouncementPrecursor::new(&to.pk);
+    let auth_key = *precursor.auth_key();
+    let (bytes, outgoing) = precursor.finalize(auth_payload.as_bytes());
+    from.outgoing = Some(outgoing);
+
+    let incoming =
+        IncomingAnnouncementPrecursor::try_from_incoming_announcement_bytes(&bytes, &to.pk, &to.sk)
+            .expect("announcement is received");
+    assert_eq!(incoming.auth_payload(), auth_payload.as_bytes());
+    assert_eq!(incoming.auth_key(), &auth_key);
+    to.incoming = incoming.finalize(from.pk.clone());
+
+    AnnouncementVector {
+        sender,
+        auth_payload: to_hex(auth_payload.as_bytes()),
+        auth_key: to_hex(&auth_key),
+        bytes: to_hex(&bytes),
+    }
+}
+
+/// Sends a message from `from` to `to`, and has `to` receive it.
+fn send(from: &mut Side, to: &mut Side, sender: Party, payload: &str) -> MessageVector {
+    let bytes = from
+        .session
+        .as_mut()
+        .expect("session is established")
+        .send_outgoing_message(b"seeker", payload.as_bytes(), &to.pk);
+
+    let received = to
+        .session
+        .as_mut()
+        .expect("session is established")
+        .try_feed_incoming_message(&to.sk, &bytes)
+        .expect("message is received");
+    assert_eq!(received.message_bytes, payload.as_bytes());
+
+    MessageVector {
+        sender,
+        payload: to_hex(payload.as_bytes()),
+        bytes: to_hex(&bytes),
+    }
+}
+
+fn conversation() -> ConversationVector {
+    crypto_rng::with_seed(CONVERSATION_SEED, || {
+        let mut alice = Side::new();
+        let mut bob = Side::new();
+
+        let announcements = vec![
+            announce(&mut alice, &mut bob, Party::Alice, AUTH_PAYLOADS[0]),
+            announce(&mut bob, &mut alice, Party::Bob, AUTH_PAYLOADS[1]),
+        ];
+        for side in [&mut alice, &mut bob] {
+            side.session = Some(Agraphon::from_announcement_pair(
+                side.outgoing.as_ref().expect("announcement is sent"),
+                side.incoming.as_ref().expect("announcement is received"),
+                SkippedMessageLimits::default(),
+            ));
+        }
+
+        let messages = MESSAGES
+            .iter()
+            .map(|&(sender, payload)| match sender {
+                Party::Alice => send(&mut alice, &mut bob, sender, payload),
+                Party::Bob => send(&mut bob, &mut alice, sender, payload),
+            })
+            .collect();
+
+        ConversationVector {
+            rng_seed: to_hex(&CONVERSATION_SEED),
+            alice_public_key: to_hex(alice.pk.as_bytes()),
+            bob_public_key: to_hex(bob.pk.as_bytes()),
+            announcements,
+            messages,
+        }
+    })
+}
+
+fn generate() -> TestVectors {
+    let versions = [ProtocolVersion::V1, ProtocolVersion::V2];
+    let (announcement_root_kdf, announcement_auth_kdf, message_root_kdf, message_chain_kdf) =
+        crypto_rng::with_seed(KDF_INPUTS_SEED, || {
+            (
+                versions.map(AnnouncementRootKdfVector::random).to_vec(),
+                versions.map(AnnouncementAuthKdfVector::random).to_vec(),
+                versions.map(MessageRootKdfVector::random).to_vec(),
+                vec![
+                    MessageChainKdfVector::random(),
+                    MessageChainKdfVector::random(),
+                ],
+            )
+        });
+    TestVectors {
+        announcement_root_kdf,
+        announcement_auth_kdf,
+        message_root_kdf,
+        message_chain_kdf,
+        conversation: conversation(),
+    }
+}
+
+fn stored() -> TestVectors {
+    serde_json::from_str(include_str!("../test_vectors/agraphon.json"))
+        .expect("test vectors are valid JSON")
+}
+
+#[test]
+fn test_kdf_vectors() {
+    let vectors = stored();
+    for vector in vectors.announcement_root_kdf {
+        assert_eq!(vector.clone().with_outputs(), vector);
+    }
+    for vector in vectors.announcement_auth_kdf {
+        assert_eq!(vector.clone().with_outputs(), vector);
+    }
+    for vector in vectors.message_root_kdf {
+        assert_eq!(vector.clone().with_outputs(), vector);
+    }
+    for vector in vectors.message_chain_kdf {
+        assert_eq!(vector.clone().with_outputs(), vector);
+    }
+}
+
+#[test]
+fn test_conversation_vectors() {
+    let expected = stored().conversation;
+    let actual = conversation();
+
+    assert_eq!(actual.rng_seed, expected.rng_seed);
+    assert_eq!(actual.alice_public_key, expected.alice_public_key);
+    assert_eq!(actual.bob_public_key, expected.bob_public_key);
+    assert_eq!(actual.announcements.len(), expected.announcements.len());
+    for (index, (actual, expected)) in actual
+        .announcements
+        .iter()
+        .zip(&expected.announcements)
+        .enumerate()
+    {
+        assert_eq!(actual, expected, "announcement {index}");
+    }
+    assert_eq!(actual.messages.len(), expected.messages.len());
+    for (index, (actual, expected)) in actual.messages.iter().zip(&expected.messages).enumerate() {
+        assert_eq!(actual, expected, "message {index}");
+    }
+}
+
+#[test]
+#[ignore = "rewrites the test vector file"]
+fn regenerate_test_vectors() {
+    let json = serde_json::to_string_pretty(&generate()).expect("vectors serialize");
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join(VECTORS_PATH);
+    std::fs::write(path, json + "\n").expect("vector file is writable");
+}
@@ -34,11 +34,21 @@
 //! assert!(is_valid);
 //! ```
 //!
+//! ## Parameter Sets
+//!
+//! The root API is fixed to ML-DSA 65. The [`tagged`] module also provides ML-DSA 44 and
+//! ML-DSA 87, with keys and signatures tagged with their [`ParameterSet`], to trade
+//! bandwidth for security level.
+//!
 //! ## Security Notes
 //!
 //! - Always use cryptographically secure random number generation for key generation and signing
 //! - This library is suitable for production use as it wraps the formally verified libcrux implementation
 
+pub mod tagged;
+
+pub use tagged::ParameterSet;
+
 use libcrux_ml_dsa::{MLDSASignature, MLDSASigningKey, MLDSAVerificationKey, ml_dsa_65};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
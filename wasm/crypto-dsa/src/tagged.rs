@@ -0,0 +1,514 @@
+//! ML-DSA with a selectable parameter set.
+//!
+//! The root API of this crate is fixed to ML-DSA 65. This module exposes all three ML-DSA
+//! parameter sets through keys and signatures tagged with the [`ParameterSet`] they belong to,
+//! so that deployments can trade bandwidth for security level:
+//!
+//! | Parameter set | Verification key | Signing key | Signature | NIST category |
+//! |---------------|-----------------:|------------:|----------:|--------------:|
+//! | ML-DSA 44     |             1312 |        2560 |      2420 |             2 |
+//! | ML-DSA 65     |             1952 |        4032 |      3309 |             3 |
+//! | ML-DSA 87     |             2592 |        4896 |      4627 |             5 |
+//!
+//! Key generation and signing randomness have the same size for every parameter set, so the
+//! root constants apply. ML-DSA 65 keys convert to and from the root types.
+//!
+//! User keys (`auth::UserPublicKeys`) do not use this module yet: they are ML-DSA 65 keys.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use crypto_dsa::tagged::{self, ParameterSet};
+//!
+//! let (signing_key, verification_key) = tagged::generate_key_pair(ParameterSet::MlDsa44, [1u8; 32]);
+//! let signature = tagged::sign(&signing_key, b"Hello, world!", b"", [2u8; 32]);
+//! assert_eq!(signature.as_bytes().len(), ParameterSet::MlDsa44.signature_size());
+//! assert!(tagged::verify(&verification_key, b"Hello, world!", b"", &signature));
+//! ```
+
+use crate::{KEY_GENERATION_RANDOMNESS_SIZE, SIGNING_RANDOMNESS_SIZE};
+use libcrux_ml_dsa::{
+    MLDSASignature, MLDSASigningKey, MLDSAVerificationKey, ml_dsa_44, ml_dsa_65, ml_dsa_87,
+};
+use serde::{Deserialize, Deserializer, Serialize};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+/// An ML-DSA parameter set.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ParameterSet {
+    /// ML-DSA 44 (NIST security category 2).
+    MlDsa44,
+    /// ML-DSA 65 (NIST security category 3), used by the root API of this crate.
+    #[default]
+    MlDsa65,
+    /// ML-DSA 87 (NIST security category 5).
+    MlDsa87,
+}
+
+impl ParameterSet {
+    /// Returns the verification key size of this parameter set in bytes.
+    #[must_use]
+    pub const fn verification_key_size(self) -> usize {
+        match self {
+            Self::MlDsa44 => 1312,
+            Self::MlDsa65 => crate::VERIFICATION_KEY_SIZE,
+            Self::MlDsa87 => 2592,
+        }
+    }
+
+    /// Returns the signing key size of this parameter set in bytes.
+    #[must_use]
+    pub const fn signing_key_size(self) -> usize {
+        match self {
+            Self::MlDsa44 => 2560,
+            Self::MlDsa65 => crate::SIGNING_KEY_SIZE,
+            Self::MlDsa87 => 4896,
+        }
+    }
+
+    /// Returns the signature size of this parameter set in bytes.
+    #[must_use]
+    pub const fn signature_size(self) -> usize {
+        match self {
+            Self::MlDsa44 => 2420,
+            Self::MlDsa65 => crate::SIGNATURE_SIZE,
+            Self::MlDsa87 => 4627,
+        }
+    }
+}
+
+/// Serialized form of the tagged types, checked on deserialization.
+#[derive(Deserialize, Zeroize, ZeroizeOnDrop)]
+struct TaggedBytes {
+    #[zeroize(skip)]
+    parameter_set: ParameterSet,
+    bytes: Vec<u8>,
+}
+
+impl TaggedBytes {
+    /// Deserializes tagged bytes and checks their length against the parameter set.
+    fn deserialize_checked<'de, D: Deserializer<'de>>(
+        deserializer: D,
+        expected_size: fn(ParameterSet) -> usize,
+    ) -> Result<(ParameterSet, Vec<u8>), D::Error> {
+        let mut tagged = <Self as Deserialize>::deserialize(deserializer)?;
+        let expected = expected_size(tagged.parameter_set);
+        if tagged.bytes.len() != expected {
+            return Err(serde::de::Error::custom(format!(
+                "expected {} bytes, got {}",
+                expected,
+                tagged.bytes.len()
+            )));
+        }
+        Ok((tagged.parameter_set, std::mem::take(&mut tagged.bytes)))
+    }
+}
+
+/// Converts a slice whose length was checked on construction into an array.
+fn to_array<const N: usize>(bytes: &[u8]) -> [u8; N] {
+    bytes
+        .try_into()
+        .expect("Key length checked on construction")
+}
+
+/// An ML-DSA verification key tagged with its parameter set.
+///
+/// # Examples
+///
+/// ```rust
+/// use crypto_dsa::tagged::{ParameterSet, VerificationKey};
+///
+/// let key = VerificationKey::from_bytes(ParameterSet::MlDsa87, &[42u8; 2592]).unwrap();
+/// assert_eq!(key.parameter_set(), ParameterSet::MlDsa87);
+///
+/// // the length must match the parameter set
+/// assert!(VerificationKey::from_bytes(ParameterSet::MlDsa65, &[42u8; 2592]).is_none());
+/// ```
+#[derive(Clone, Zeroize, ZeroizeOnDrop, Serialize)]
+pub struct VerificationKey {
+    #[zeroize(skip)]
+    parameter_set: ParameterSet,
+    bytes: Vec<u8>,
+}
+
+impl VerificationKey {
+    /// Creates a verification key from its bytes.
+    ///
+    /// Returns `None` if `bytes` does not have the verification key size of `parameter_set`.
+    #[must_use]
+    pub fn from_bytes(parameter_set: ParameterSet, bytes: &[u8]) -> Option<Self> {
+        (bytes.len() == parameter_set.verification_key_size()).then(|| Self {
+            parameter_set,
+            bytes: bytes.to_vec(),
+        })
+    }
+
+    /// Returns the parameter set of the key.
+    #[must_use]
+    pub const fn parameter_set(&self) -> ParameterSet {
+        self.parameter_set
+    }
+
+    /// Returns the raw bytes of the key.
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl From<&crate::VerificationKey> for VerificationKey {
+    fn from(verification_key: &crate::VerificationKey) -> Self {
+        Self {
+            parameter_set: ParameterSet::MlDsa65,
+            bytes: verification_key.as_bytes().to_vec(),
+        }
+    }
+}
+
+impl TryFrom<&VerificationKey> for crate::VerificationKey {
+    type Error = ParameterSet;
+
+    /// Converts an ML-DSA 65 key to the root type, or returns the parameter set of the key.
+    fn try_from(verification_key: &VerificationKey) -> Result<Self, Self::Error> {
+        match verification_key.parameter_set {
+            ParameterSet::MlDsa65 => Ok(Self::from(to_array(&verification_key.bytes))),
+            other => Err(other),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for VerificationKey {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let (parameter_set, bytes) =
+            TaggedBytes::deserialize_checked(deserializer, ParameterSet::verification_key_size)?;
+        Ok(Self {
+            parameter_set,
+            bytes,
+        })
+    }
+}
+
+/// An ML-DSA signing key tagged with its parameter set.
+///
+/// **The signing key bytes are automatically and securely zeroed when dropped.**
+#[derive(Clone, Zeroize, ZeroizeOnDrop, Serialize)]
+pub struct SigningKey {
+    #[zeroize(skip)]
+    parameter_set: ParameterSet,
+    bytes: Vec<u8>,
+}
+
+impl SigningKey {
+    /// Creates a signing key from its bytes.
+    ///
+    /// Returns `None` if `bytes` does not have the signing key size of `parameter_set`.
+    #[must_use]
+    pub fn from_bytes(parameter_set: ParameterSet, bytes: &[u8]) -> Option<Self> {
+        (bytes.len() == parameter_set.signing_key_size()).then(|| Self {
+            parameter_set,
+            bytes: bytes.to_vec(),
+        })
+    }
+
+    /// Returns the parameter set of the key.
+    #[must_use]
+    pub const fn parameter_set(&self) -> ParameterSet {
+        self.parameter_set
+    }
+
+    /// Returns the raw bytes of the key.
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl From<&crate::SigningKey> for SigningKey {
+    fn from(signing_key: &crate::SigningKey) -> Self {
+        Self {
+            parameter_set: ParameterSet::MlDsa65,
+            bytes: signing_key.as_bytes().to_vec(),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for SigningKey {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let (parameter_set, bytes) =
+            TaggedBytes::deserialize_checked(deserializer, ParameterSet::signing_key_size)?;
+        Ok(Self {
+            parameter_set,
+            bytes,
+        })
+    }
+}
+
+/// An ML-DSA signature tagged with its parameter set.
+#[derive(Clone, Zeroize, ZeroizeOnDrop, Serialize)]
+pub struct Signature {
+    #[zeroize(skip)]
+    parameter_set: ParameterSet,
+    bytes: Vec<u8>,
+}
+
+impl Signature {
+    /// Creates a signature from its bytes.
+    ///
+    /// Returns `None` if `bytes` does not have the signature size of `parameter_set`.
+    #[must_use]
+    pub fn from_bytes(parameter_set: ParameterSet, bytes: &[u8]) -> Option<Self> {
+        (bytes.len() == parameter_set.signature_size()).then(|| Self {
+            parameter_set,
+            bytes: bytes.to_vec(),
+        })
+    }
+
+    /// Returns the parameter set of the signature.
+    #[must_use]
+    pub const fn parameter_set(&self) -> ParameterSet {
+        self.parameter_set
+    }
+
+    /// Returns the raw bytes of the signature.
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl<'de> Deserialize<'de> for Signature {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let (parameter_set, bytes) =
+            TaggedBytes::deserialize_checked(deserializer, ParameterSet::signature_size)?;
+        Ok(Self {
+            parameter_set,
+            bytes,
+        })
+    }
+}
+
+/// Tags a libcrux key pair, zeroizing the libcrux signing key.
+fn tag_key_pair<const SIGNING: usize, const VERIFICATION: usize>(
+    parameter_set: ParameterSet,
+    mut signing_key: MLDSASigningKey<SIGNING>,
+    verification_key: &MLDSAVerificationKey<VERIFICATION>,
+) -> (SigningKey, VerificationKey) {
+    let tagged_signing_key = SigningKey {
+        parameter_set,
+        bytes: signing_key.as_ref().to_vec(),
+    };
+    signing_key.as_ref_mut().zeroize();
+    let tagged_verification_key = VerificationKey {
+        parameter_set,
+        bytes: verification_key.as_ref().to_vec(),
+    };
+    (tagged_signing_key, tagged_verification_key)
+}
+
+/// Generates a key pair of the given parameter set from the given randomness.
+///
+/// # Security Note
+///
+/// The `randomness` parameter must be generated using a cryptographically secure
+/// random number generator.
+#[must_use]
+pub fn generate_key_pair(
+    parameter_set: ParameterSet,
+    randomness: [u8; KEY_GENERATION_RANDOMNESS_SIZE],
+) -> (SigningKey, VerificationKey) {
+    match parameter_set {
+        ParameterSet::MlDsa44 => {
+            let kp = ml_dsa_44::generate_key_pair(randomness);
+            tag_key_pair(parameter_set, kp.signing_key, &kp.verification_key)
+        }
+        ParameterSet::MlDsa65 => {
+            let kp = ml_dsa_65::generate_key_pair(randomness);
+            tag_key_pair(parameter_set, kp.signing_key, &kp.verification_key)
+        }
+        ParameterSet::MlDsa87 => {
+            let kp = ml_dsa_87::generate_key_pair(randomness);
+            tag_key_pair(parameter_set, kp.signing_key, &kp.verification_key)
+        }
+    }
+}
+
+/// Signs a message with the given signing key.
+///
+/// The signature has the parameter set of the signing key. See [`crate::sign`] for the
+/// meaning of `context` and the requirements on `randomness`.
+///
+/// # Panics
+///
+/// Panics if the ML-DSA signing operation fails. In practice, this should never happen
+/// with valid inputs.
+#[must_use]
+pub fn sign(
+    signing_key: &SigningKey,
+    message: &[u8],
+    context: &[u8],
+    randomness: [u8; SIGNING_RANDOMNESS_SIZE],
+) -> Signature {
+    let parameter_set = signing_key.parameter_set;
+    let bytes = match parameter_set {
+        ParameterSet::MlDsa44 => {
+            let mut sk = MLDSASigningKey::new(to_array(&signing_key.bytes));
+            let sig = ml_dsa_44::sign(&sk, message, context, randomness);
+            sk.as_ref_mut().zeroize();
+            sig.expect("Signing failed").as_ref().to_vec()
+        }
+        ParameterSet::MlDsa65 => {
+            let mut sk = MLDSASigningKey::new(to_array(&signing_key.bytes));
+            let sig = ml_dsa_65::sign(&sk, message, context, randomness);
+            sk.as_ref_mut().zeroize();
+            sig.expect("Signing failed").as_ref().to_vec()
+        }
+        ParameterSet::MlDsa87 => {
+            let mut sk = MLDSASigningKey::new(to_array(&signing_key.bytes));
+            let sig = ml_dsa_87::sign(&sk, message, context, randomness);
+            sk.as_ref_mut().zeroize();
+            sig.expect("Signing failed").as_ref().to_vec()
+        }
+    };
+    Signature {
+        parameter_set,
+        bytes,
+    }
+}
+
+/// Verifies a signature on a message with the given verification key.
+///
+/// Returns `false` if the signature is invalid, or if the signature and the key have
+/// different parameter sets.
+#[must_use]
+pub fn verify(
+    verification_key: &VerificationKey,
+    message: &[u8],
+    context: &[u8],
+    signature: &Signature,
+) -> bool {
+    if verification_key.parameter_set != signature.parameter_set {
+        return false;
+    }
+    match verification_key.parameter_set {
+        ParameterSet::MlDsa44 => ml_dsa_44::verify(
+            &MLDSAVerificationKey::new(to_array(&verification_key.bytes)),
+            message,
+            context,
+            &MLDSASignature::new(to_array(&signature.bytes)),
+        )
+        .is_ok(),
+        ParameterSet::MlDsa65 => ml_dsa_65::verify(
+            &MLDSAVerificationKey::new(to_array(&verification_key.bytes)),
+            message,
+            context,
+            &MLDSASignature::new(to_array(&signature.bytes)),
+        )
+        .is_ok(),
+        ParameterSet::MlDsa87 => ml_dsa_87::verify(
+            &MLDSAVerificationKey::new(to_array(&verification_key.bytes)),
+            message,
+            context,
+            &MLDSASignature::new(to_array(&signature.bytes)),
+        )
+        .is_ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [ParameterSet; 3] = [
+        ParameterSet::MlDsa44,
+        ParameterSet::MlDsa65,
+        ParameterSet::MlDsa87,
+    ];
+
+    #[test]
+    fn test_sign_verify_all_parameter_sets() {
+        for parameter_set in ALL {
+            let (signing_key, verification_key) = generate_key_pair(parameter_set, [1u8; 32]);
+            assert_eq!(verification_key.parameter_set(), parameter_set);
+            assert_eq!(
+                verification_key.as_bytes().len(),
+                parameter_set.verification_key_size()
+            );
+            assert_eq!(
+                signing_key.as_bytes().len(),
+                parameter_set.signing_key_size()
+            );
+
+            let signature = sign(&signing_key, b"message", b"ctx", [2u8; 32]);
+            assert_eq!(signature.parameter_set(), parameter_set);
+            assert_eq!(signature.as_bytes().len(), parameter_set.signature_size());
+
+            assert!(verify(&verification_key, b"message", b"ctx", &signature));
+            assert!(!verify(&verification_key, b"other", b"ctx", &signature));
+            assert!(!verify(&verification_key, b"message", b"", &signature));
+        }
+    }
+
+    #[test]
+    fn test_parameter_set_mismatch_rejected() {
+        let (signing_key, _) = generate_key_pair(ParameterSet::MlDsa44, [1u8; 32]);
+        let (_, verification_key) = generate_key_pair(ParameterSet::MlDsa87, [1u8; 32]);
+        let signature = sign(&signing_key, b"message", b"", [2u8; 32]);
+        assert!(!verify(&verification_key, b"message", b"", &signature));
+    }
+
+    #[test]
+    fn test_ml_dsa_65_matches_root_api() {
+        let (root_signing_key, root_verification_key) = crate::generate_key_pair([3u8; 32]);
+        let (signing_key, verification_key) = generate_key_pair(ParameterSet::MlDsa65, [3u8; 32]);
+        assert_eq!(
+            verification_key.as_bytes(),
+            root_verification_key.as_bytes()
+        );
+        assert_eq!(
+            SigningKey::from(&root_signing_key).as_bytes(),
+            signing_key.as_bytes()
+        );
+
+        let root_signature = crate::sign(&root_signing_key, b"message", b"", [4u8; 32]);
+        let signature = sign(&signing_key, b"message", b"", [4u8; 32]);
+        assert_eq!(signature.as_bytes(), root_signature.as_bytes());
+
+        let converted = crate::VerificationKey::try_from(&verification_key).unwrap();
+        assert!(crate::verify(&converted, b"message", b"", &root_signature));
+    }
+
+    #[test]
+    fn test_root_conversion_rejects_other_parameter_sets() {
+        let (_, verification_key) = generate_key_pair(ParameterSet::MlDsa87, [1u8; 32]);
+        assert_eq!(
+            crate::VerificationKey::try_from(&verification_key).err(),
+            Some(ParameterSet::MlDsa87)
+        );
+    }
+
+    #[test]
+    fn test_from_bytes_checks_length() {
+        for parameter_set in ALL {
+            let size = parameter_set.verification_key_size();
+            assert!(VerificationKey::from_bytes(parameter_set, &vec![0u8; size]).is_some());
+            assert!(VerificationKey::from_bytes(parameter_set, &vec![0u8; size - 1]).is_none());
+
+            let size = parameter_set.signing_key_size();
+            assert!(SigningKey::from_bytes(parameter_set, &vec![0u8; size]).is_some());
+            assert!(SigningKey::from_bytes(parameter_set, &vec![0u8; size + 1]).is_none());
+
+            let size = parameter_set.signature_size();
+            assert!(Signature::from_bytes(parameter_set, &vec![0u8; size]).is_some());
+            assert!(Signature::from_bytes(parameter_set, &[]).is_none());
+        }
+    }
+}
@@ -0,0 +1,461 @@
+//! Hybrid X25519 + ML-KEM-768 key encapsulation.
+//!
+//! The hybrid KEM runs ML-KEM-768 and an ephemeral-static X25519 exchange side by side, and
+//! combines both shared secrets with the X-Wing combiner:
+//!
+//! ```text
+//! shared_secret = SHA3-256(ss_mlkem || ss_x25519 || ct_x25519 || pk_x25519 || XWING_LABEL)
+//! ```
+//!
+//! The combined secret stays secure as long as either ML-KEM-768 or X25519 is unbroken.
+//! The ML-KEM ciphertext is not hashed: ML-KEM is ciphertext-binding by itself, while the
+//! X25519 ciphertext and public key are included because X25519 is not.
+//!
+//! The API has the same shape as the ML-KEM-768 one of this crate. Keys and ciphertexts are
+//! the concatenation of their ML-KEM and X25519 parts:
+//!
+//! ```text
+//! public key  = pk_mlkem (1184 bytes) || pk_x25519 (32 bytes)
+//! secret key  = sk_mlkem (2400 bytes) || sk_x25519 (32 bytes) || pk_x25519 (32 bytes)
+//! ciphertext  = ct_mlkem (1088 bytes) || ct_x25519 (32 bytes)
+//! ```
+//!
+//! Keys are kept expanded rather than derived from a 32-byte seed as in X-Wing, so that the
+//! ML-KEM part remains usable with the non-hybrid functions of this crate.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use crypto_kem::hybrid;
+//!
+//! let (secret_key, public_key) =
+//!     hybrid::generate_key_pair([1u8; hybrid::KEY_GENERATION_RANDOMNESS_SIZE]);
+//! let (ciphertext, shared_secret1) =
+//!     hybrid::encapsulate(&public_key, [2u8; hybrid::ENCAPSULATION_RANDOMNESS_SIZE]);
+//! let shared_secret2 = hybrid::decapsulate(&secret_key, &ciphertext);
+//! assert_eq!(shared_secret1.as_bytes(), shared_secret2.as_bytes());
+//! ```
+
+use crate::SharedSecret;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sha3::{Digest, Sha3_256};
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+/// X25519 key, public key and shared secret size in bytes
+const X25519_SIZE: usize = 32;
+
+/// Domain separation label of the X-Wing combiner
+const XWING_LABEL: &[u8; 6] = b"\\.//^\\";
+
+/// Hybrid public key size in bytes
+pub const PUBLIC_KEY_SIZE: usize = crate::PUBLIC_KEY_SIZE + X25519_SIZE;
+
+/// Hybrid private key size in bytes
+pub const PRIVATE_KEY_SIZE: usize = crate::PRIVATE_KEY_SIZE + 2 * X25519_SIZE;
+
+/// Hybrid ciphertext size in bytes
+pub const CIPHERTEXT_SIZE: usize = crate::CIPHERTEXT_SIZE + X25519_SIZE;
+
+/// Hybrid shared secret size in bytes
+pub const SHARED_SECRET_SIZE: usize = crate::SHARED_SECRET_SIZE;
+
+/// Hybrid key generation randomness size in bytes
+pub const KEY_GENERATION_RANDOMNESS_SIZE: usize =
+    crate::KEY_GENERATION_RANDOMNESS_SIZE + X25519_SIZE;
+
+/// Hybrid encapsulation randomness size in bytes
+pub const ENCAPSULATION_RANDOMNESS_SIZE: usize = crate::ENCAPSULATION_RANDOMNESS_SIZE + X25519_SIZE;
+
+/// Serializes a fixed-size byte array as bytes.
+fn serialize_array<S: Serializer, const N: usize>(
+    bytes: &[u8; N],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_bytes(bytes)
+}
+
+/// Deserializes a fixed-size byte array serialized as bytes.
+fn deserialize_array<'de, D: Deserializer<'de>, const N: usize>(
+    deserializer: D,
+) -> Result<[u8; N], D::Error> {
+    let bytes: Zeroizing<Vec<u8>> = Zeroizing::new(Deserialize::deserialize(deserializer)?);
+    if bytes.len() != N {
+        return Err(serde::de::Error::custom(format!(
+            "expected {} bytes, got {}",
+            N,
+            bytes.len()
+        )));
+    }
+    let mut array = [0u8; N];
+    array.copy_from_slice(&bytes);
+    Ok(array)
+}
+
+/// A public key for the hybrid key encapsulation mechanism.
+///
+/// # Examples
+///
+/// ```rust
+/// use crypto_kem::hybrid::{PublicKey, PUBLIC_KEY_SIZE};
+///
+/// let key_bytes = [42u8; PUBLIC_KEY_SIZE];
+/// let public_key = PublicKey::from(key_bytes);
+/// assert_eq!(public_key.as_bytes(), &key_bytes);
+/// ```
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
+pub struct PublicKey([u8; PUBLIC_KEY_SIZE]);
+
+impl From<[u8; PUBLIC_KEY_SIZE]> for PublicKey {
+    fn from(bytes: [u8; PUBLIC_KEY_SIZE]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl PublicKey {
+    /// Get the raw bytes of the public key
+    #[must_use]
+    pub const fn as_bytes(&self) -> &[u8; PUBLIC_KEY_SIZE] {
+        &self.0
+    }
+
+    /// Returns the ML-KEM-768 part of the public key.
+    #[must_use]
+    pub fn ml_kem(&self) -> crate::PublicKey {
+        let mut bytes = [0u8; crate::PUBLIC_KEY_SIZE];
+        bytes.copy_from_slice(&self.0[..crate::PUBLIC_KEY_SIZE]);
+        crate::PublicKey::from(bytes)
+    }
+
+    fn x25519(&self) -> [u8; X25519_SIZE] {
+        let mut bytes = [0u8; X25519_SIZE];
+        bytes.copy_from_slice(&self.0[crate::PUBLIC_KEY_SIZE..]);
+        bytes
+    }
+}
+
+impl Serialize for PublicKey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_array(&self.0, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for PublicKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_array(deserializer).map(Self)
+    }
+}
+
+/// A secret key for the hybrid key encapsulation mechanism.
+///
+/// **The secret key bytes are automatically and securely zeroed when dropped.**
+///
+/// # Examples
+///
+/// ```rust
+/// use crypto_kem::hybrid::{SecretKey, PRIVATE_KEY_SIZE};
+///
+/// let key_bytes = [42u8; PRIVATE_KEY_SIZE];
+/// let secret_key = SecretKey::from(key_bytes);
+/// assert_eq!(secret_key.as_bytes(), &key_bytes);
+/// ```
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
+pub struct SecretKey([u8; PRIVATE_KEY_SIZE]);
+
+impl From<[u8; PRIVATE_KEY_SIZE]> for SecretKey {
+    fn from(bytes: [u8; PRIVATE_KEY_SIZE]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl SecretKey {
+    /// Get the raw bytes of the secret key
+    #[must_use]
+    pub const fn as_bytes(&self) -> &[u8; PRIVATE_KEY_SIZE] {
+        &self.0
+    }
+
+    /// Returns the ML-KEM-768 part of the secret key.
+    #[must_use]
+    pub fn ml_kem(&self) -> crate::SecretKey {
+        let mut bytes = Zeroizing::new([0u8; crate::PRIVATE_KEY_SIZE]);
+        bytes.copy_from_slice(&self.0[..crate::PRIVATE_KEY_SIZE]);
+        crate::SecretKey::from(*bytes)
+    }
+
+    fn x25519(&self) -> Zeroizing<[u8; X25519_SIZE]> {
+        let mut bytes = Zeroizing::new([0u8; X25519_SIZE]);
+        bytes.copy_from_slice(
+            &self.0[crate::PRIVATE_KEY_SIZE..crate::PRIVATE_KEY_SIZE + X25519_SIZE],
+        );
+        bytes
+    }
+
+    fn x25519_public(&self) -> [u8; X25519_SIZE] {
+        let mut bytes = [0u8; X25519_SIZE];
+        bytes.copy_from_slice(&self.0[crate::PRIVATE_KEY_SIZE + X25519_SIZE..]);
+        bytes
+    }
+}
+
+impl Serialize for SecretKey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_array(&self.0, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for SecretKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_array(deserializer).map(Self)
+    }
+}
+
+/// A ciphertext produced by the hybrid encapsulation operation.
+///
+/// # Examples
+///
+/// ```rust
+/// use crypto_kem::hybrid::{Ciphertext, CIPHERTEXT_SIZE};
+///
+/// let ct_bytes = [42u8; CIPHERTEXT_SIZE];
+/// let ciphertext = Ciphertext::from(ct_bytes);
+/// assert_eq!(ciphertext.as_bytes(), &ct_bytes);
+/// ```
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
+pub struct Ciphertext([u8; CIPHERTEXT_SIZE]);
+
+impl From<[u8; CIPHERTEXT_SIZE]> for Ciphertext {
+    fn from(bytes: [u8; CIPHERTEXT_SIZE]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl Ciphertext {
+    /// Get the raw bytes of the ciphertext
+    #[must_use]
+    pub const fn as_bytes(&self) -> &[u8; CIPHERTEXT_SIZE] {
+        &self.0
+    }
+
+    fn ml_kem(&self) -> crate::Ciphertext {
+        let mut bytes = [0u8; crate::CIPHERTEXT_SIZE];
+        bytes.copy_from_slice(&self.0[..crate::CIPHERTEXT_SIZE]);
+        crate::Ciphertext::from(bytes)
+    }
+
+    fn x25519(&self) -> [u8; X25519_SIZE] {
+        let mut bytes = [0u8; X25519_SIZE];
+        bytes.copy_from_slice(&self.0[crate::CIPHERTEXT_SIZE..]);
+        bytes
+    }
+}
+
+impl Serialize for Ciphertext {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_array(&self.0, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Ciphertext {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_array(deserializer).map(Self)
+    }
+}
+
+/// Splits hybrid randomness into its ML-KEM and X25519 parts.
+fn split_randomness<const N: usize, const M: usize>(
+    randomness: &[u8],
+) -> (Zeroizing<[u8; N]>, Zeroizing<[u8; M]>) {
+    let mut ml_kem = Zeroizing::new([0u8; N]);
+    let mut x25519 = Zeroizing::new([0u8; M]);
+    ml_kem.copy_from_slice(&randomness[..N]);
+    x25519.copy_from_slice(&randomness[N..]);
+    (ml_kem, x25519)
+}
+
+/// Combines the ML-KEM and X25519 shared secrets (X-Wing combiner).
+fn combine(
+    ss_ml_kem: &SharedSecret,
+    ss_x25519: &[u8; X25519_SIZE],
+    ct_x25519: &[u8; X25519_SIZE],
+    pk_x25519: &[u8; X25519_SIZE],
+) -> SharedSecret {
+    let mut hasher = Sha3_256::new();
+    hasher.update(ss_ml_kem.as_bytes());
+    hasher.update(ss_x25519);
+    hasher.update(ct_x25519);
+    hasher.update(pk_x25519);
+    hasher.update(XWING_LABEL);
+    let shared_secret: Zeroizing<[u8; SHARED_SECRET_SIZE]> =
+        Zeroizing::new(hasher.finalize().into());
+    SharedSecret::from(*shared_secret)
+}
+
+/// Generate a hybrid key pair from the given randomness.
+///
+/// # Arguments
+///
+/// * `randomness` - 96 bytes of cryptographically secure random data
+///   (64 for ML-KEM-768 followed by 32 for X25519)
+///
+/// # Security Note
+///
+/// The `randomness` parameter must be generated using a cryptographically secure
+/// random number generator.
+#[must_use]
+pub fn generate_key_pair(
+    randomness: [u8; KEY_GENERATION_RANDOMNESS_SIZE],
+) -> (SecretKey, PublicKey) {
+    let randomness = Zeroizing::new(randomness);
+    let (ml_kem_randomness, sk_x25519) = split_randomness(randomness.as_slice());
+
+    let (sk_ml_kem, pk_ml_kem) = crate::generate_key_pair(*ml_kem_randomness);
+    let pk_x25519 = x25519_dalek::x25519(*sk_x25519, x25519_dalek::X25519_BASEPOINT_BYTES);
+
+    let mut sk = [0u8; PRIVATE_KEY_SIZE];
+    sk[..crate::PRIVATE_KEY_SIZE].copy_from_slice(sk_ml_kem.as_bytes());
+    sk[crate::PRIVATE_KEY_SIZE..crate::PRIVATE_KEY_SIZE + X25519_SIZE]
+        .copy_from_slice(sk_x25519.as_slice());
+    sk[crate::PRIVATE_KEY_SIZE + X25519_SIZE..].copy_from_slice(&pk_x25519);
+    let sk = SecretKey(sk);
+
+    let mut pk = [0u8; PUBLIC_KEY_SIZE];
+    pk[..crate::PUBLIC_KEY_SIZE].copy_from_slice(pk_ml_kem.as_bytes());
+    pk[crate::PUBLIC_KEY_SIZE..].copy_from_slice(&pk_x25519);
+
+    (sk, PublicKey(pk))
+}
+
+/// Encapsulate a shared secret using the given hybrid public key.
+///
+/// # Arguments
+///
+/// * `public_key` - The recipient's hybrid public key
+/// * `randomness` - 64 bytes of cryptographically secure random data
+///   (32 for ML-KEM-768 followed by 32 for the ephemeral X25519 key)
+///
+/// # Security Note
+///
+/// The `randomness` parameter must be generated using a cryptographically secure
+/// random number generator. Reusing randomness will compromise security.
+#[must_use]
+pub fn encapsulate(
+    public_key: &PublicKey,
+    randomness: [u8; ENCAPSULATION_RANDOMNESS_SIZE],
+) -> (Ciphertext, SharedSecret) {
+    let randomness = Zeroizing::new(randomness);
+    let (ml_kem_randomness, ek_x25519) = split_randomness(randomness.as_slice());
+
+    let (ct_ml_kem, ss_ml_kem) = crate::encapsulate(&public_key.ml_kem(), *ml_kem_randomness);
+
+    let pk_x25519 = public_key.x25519();
+    let ct_x25519 = x25519_dalek::x25519(*ek_x25519, x25519_dalek::X25519_BASEPOINT_BYTES);
+    let ss_x25519 = Zeroizing::new(x25519_dalek::x25519(*ek_x25519, pk_x25519));
+
+    let mut ct = [0u8; CIPHERTEXT_SIZE];
+    ct[..crate::CIPHERTEXT_SIZE].copy_from_slice(ct_ml_kem.as_bytes());
+    ct[crate::CIPHERTEXT_SIZE..].copy_from_slice(&ct_x25519);
+
+    let ss = combine(&ss_ml_kem, &ss_x25519, &ct_x25519, &pk_x25519);
+    (Ciphertext(ct), ss)
+}
+
+/// Decapsulate a shared secret from the given hybrid ciphertext using the secret key.
+///
+/// Like ML-KEM, decapsulation never fails: a tampered ciphertext yields an unrelated
+/// shared secret.
+#[must_use]
+pub fn decapsulate(secret_key: &SecretKey, ciphertext: &Ciphertext) -> SharedSecret {
+    let ss_ml_kem = crate::decapsulate(&secret_key.ml_kem(), &ciphertext.ml_kem());
+
+    let ct_x25519 = ciphertext.x25519();
+    let ss_x25519 = Zeroizing::new(x25519_dalek::x25519(*secret_key.x25519(), ct_x25519));
+
+    combine(
+        &ss_ml_kem,
+        &ss_x25519,
+        &ct_x25519,
+        &secret_key.x25519_public(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sizes() {
+        assert_eq!(PUBLIC_KEY_SIZE, 1216);
+        assert_eq!(PRIVATE_KEY_SIZE, 2464);
+        assert_eq!(CIPHERTEXT_SIZE, 1120);
+        assert_eq!(KEY_GENERATION_RANDOMNESS_SIZE, 96);
+        assert_eq!(ENCAPSULATION_RANDOMNESS_SIZE, 64);
+    }
+
+    #[test]
+    fn test_encapsulation_decapsulation_roundtrip() {
+        let (secret_key, public_key) = generate_key_pair([1u8; KEY_GENERATION_RANDOMNESS_SIZE]);
+        let (ciphertext, shared_secret1) =
+            encapsulate(&public_key, [2u8; ENCAPSULATION_RANDOMNESS_SIZE]);
+        let shared_secret2 = decapsulate(&secret_key, &ciphertext);
+        assert_eq!(shared_secret1.as_bytes(), shared_secret2.as_bytes());
+    }
+
+    #[test]
+    fn test_deterministic() {
+        let (_, public_key1) = generate_key_pair([1u8; KEY_GENERATION_RANDOMNESS_SIZE]);
+        let (_, public_key2) = generate_key_pair([1u8; KEY_GENERATION_RANDOMNESS_SIZE]);
+        assert_eq!(public_key1.as_bytes(), public_key2.as_bytes());
+
+        let (ct1, ss1) = encapsulate(&public_key1, [2u8; ENCAPSULATION_RANDOMNESS_SIZE]);
+        let (ct2, ss2) = encapsulate(&public_key2, [2u8; ENCAPSULATION_RANDOMNESS_SIZE]);
+        assert_eq!(ct1.as_bytes(), ct2.as_bytes());
+        assert_eq!(ss1.as_bytes(), ss2.as_bytes());
+    }
+
+    #[test]
+    fn test_parts_match_components() {
+        let randomness = [3u8; KEY_GENERATION_RANDOMNESS_SIZE];
+        let (secret_key, public_key) = generate_key_pair(randomness);
+
+        // the ML-KEM part is a regular ML-KEM-768 key pair
+        let mut ml_kem_randomness = [0u8; crate::KEY_GENERATION_RANDOMNESS_SIZE];
+        ml_kem_randomness.copy_from_slice(&randomness[..crate::KEY_GENERATION_RANDOMNESS_SIZE]);
+        let (sk_ml_kem, pk_ml_kem) = crate::generate_key_pair(ml_kem_randomness);
+        assert_eq!(public_key.ml_kem().as_bytes(), pk_ml_kem.as_bytes());
+        assert_eq!(secret_key.ml_kem().as_bytes(), sk_ml_kem.as_bytes());
+
+        // the X25519 public key is stored in both keys
+        assert_eq!(public_key.x25519(), secret_key.x25519_public());
+    }
+
+    #[test]
+    fn test_shared_secret_depends_on_both_components() {
+        let (secret_key, public_key) = generate_key_pair([4u8; KEY_GENERATION_RANDOMNESS_SIZE]);
+        let (ciphertext, shared_secret) =
+            encapsulate(&public_key, [5u8; ENCAPSULATION_RANDOMNESS_SIZE]);
+
+        // tampering with either ciphertext changes the shared secret
+        for index in [0, crate::CIPHERTEXT_SIZE] {
+            let mut tampered = *ciphertext.as_bytes();
+            tampered[index] ^= 1;
+            let tampered_secret = decapsulate(&secret_key, &Ciphertext::from(tampered));
+            assert_ne!(tampered_secret.as_bytes(), shared_secret.as_bytes());
+        }
+
+        // the result differs from the plain ML-KEM shared secret
+        let ml_kem_secret = crate::decapsulate(&secret_key.ml_kem(), &ciphertext.ml_kem());
+        assert_ne!(ml_kem_secret.as_bytes(), shared_secret.as_bytes());
+    }
+
+    #[test]
+    fn test_wrong_key_gives_different_secret() {
+        let (_, public_key) = generate_key_pair([6u8; KEY_GENERATION_RANDOMNESS_SIZE]);
+        let (other_secret_key, _) = generate_key_pair([7u8; KEY_GENERATION_RANDOMNESS_SIZE]);
+        let (ciphertext, shared_secret) =
+            encapsulate(&public_key, [8u8; ENCAPSULATION_RANDOMNESS_SIZE]);
+        assert_ne!(
+            decapsulate(&other_secret_key, &ciphertext).as_bytes(),
+            shared_secret.as_bytes()
+        );
+    }
+}
@@ -35,11 +35,28 @@
 //! assert_eq!(shared_secret1.as_bytes(), shared_secret2.as_bytes());
 //! ```
 //!
+//! ## Hybrid KEM
+//!
+//! The [`hybrid`] module combines ML-KEM 768 with X25519, so that the shared secret stays
+//! secure as long as either scheme is unbroken. It exposes the same
+//! `generate_key_pair`/`encapsulate`/`decapsulate` functions, with its own key and ciphertext types.
+//!
+//! ## Parameter Sets
+//!
+//! The root API is fixed to ML-KEM 768. The [`tagged`] module also provides ML-KEM 512 and
+//! ML-KEM 1024, with keys and ciphertexts tagged with their [`ParameterSet`], to trade
+//! bandwidth for security level.
+//!
 //! ## Security Notes
 //!
 //! - Always use cryptographically secure random number generation for key generation and encapsulation
 //! - This library is suitable for production use as it wraps the formally verified libcrux implementation
 
+pub mod hybrid;
+pub mod tagged;
+
+pub use tagged::ParameterSet;
+
 use libcrux_ml_kem::{
     MlKemCiphertext, MlKemPrivateKey, MlKemPublicKey, MlKemSharedSecret, mlkem768,
 };
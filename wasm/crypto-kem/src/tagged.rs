@@ -0,0 +1,500 @@
+//! ML-KEM with a selectable parameter set.
+//!
+//! The root API of this crate is fixed to ML-KEM-768. This module exposes all three ML-KEM
+//! parameter sets through keys and ciphertexts tagged with the [`ParameterSet`] they belong to,
+//! so that deployments can trade bandwidth for security level:
+//!
+//! | Parameter set | Public key | Secret key | Ciphertext | NIST category |
+//! |---------------|-----------:|-----------:|-----------:|--------------:|
+//! | ML-KEM-512    |        800 |       1632 |        768 |             1 |
+//! | ML-KEM-768    |       1184 |       2400 |       1088 |             3 |
+//! | ML-KEM-1024   |       1568 |       3168 |       1568 |             5 |
+//!
+//! Key generation and encapsulation randomness, and shared secrets, have the same size for
+//! every parameter set, so the root constants apply. ML-KEM-768 keys convert to and from the
+//! root types, and produce the same ciphertexts and shared secrets.
+//!
+//! User keys (`auth::UserPublicKeys`) do not use this module yet: they are ML-KEM-768 keys.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use crypto_kem::tagged::{self, ParameterSet};
+//!
+//! let (secret_key, public_key) = tagged::generate_key_pair(ParameterSet::MlKem1024, [1u8; 64]);
+//! assert_eq!(public_key.as_bytes().len(), ParameterSet::MlKem1024.public_key_size());
+//!
+//! let (ciphertext, shared_secret1) = tagged::encapsulate(&public_key, [2u8; 32]);
+//! let shared_secret2 = tagged::decapsulate(&secret_key, &ciphertext).unwrap();
+//! assert_eq!(shared_secret1.as_bytes(), shared_secret2.as_bytes());
+//! ```
+
+use crate::{ENCAPSULATION_RANDOMNESS_SIZE, KEY_GENERATION_RANDOMNESS_SIZE, SharedSecret};
+use libcrux_ml_kem::{
+    MlKemCiphertext, MlKemKeyPair, MlKemPrivateKey, MlKemPublicKey, mlkem512, mlkem768, mlkem1024,
+};
+use serde::{Deserialize, Deserializer, Serialize};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+/// An ML-KEM parameter set.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ParameterSet {
+    /// ML-KEM-512 (NIST security category 1).
+    MlKem512,
+    /// ML-KEM-768 (NIST security category 3), used by the root API of this crate.
+    #[default]
+    MlKem768,
+    /// ML-KEM-1024 (NIST security category 5).
+    MlKem1024,
+}
+
+impl ParameterSet {
+    /// Returns the public key size of this parameter set in bytes.
+    #[must_use]
+    pub const fn public_key_size(self) -> usize {
+        match self {
+            Self::MlKem512 => 800,
+            Self::MlKem768 => crate::PUBLIC_KEY_SIZE,
+            Self::MlKem1024 => 1568,
+        }
+    }
+
+    /// Returns the private key size of this parameter set in bytes.
+    #[must_use]
+    pub const fn private_key_size(self) -> usize {
+        match self {
+            Self::MlKem512 => 1632,
+            Self::MlKem768 => crate::PRIVATE_KEY_SIZE,
+            Self::MlKem1024 => 3168,
+        }
+    }
+
+    /// Returns the ciphertext size of this parameter set in bytes.
+    #[must_use]
+    pub const fn ciphertext_size(self) -> usize {
+        match self {
+            Self::MlKem512 => 768,
+            Self::MlKem768 => crate::CIPHERTEXT_SIZE,
+            Self::MlKem1024 => 1568,
+        }
+    }
+}
+
+/// Serialized form of the tagged types, checked on deserialization.
+#[derive(Deserialize, Zeroize, ZeroizeOnDrop)]
+struct TaggedBytes {
+    #[zeroize(skip)]
+    parameter_set: ParameterSet,
+    bytes: Vec<u8>,
+}
+
+impl TaggedBytes {
+    /// Deserializes tagged bytes and checks their length against the parameter set.
+    fn deserialize_checked<'de, D: Deserializer<'de>>(
+        deserializer: D,
+        expected_size: fn(ParameterSet) -> usize,
+    ) -> Result<(ParameterSet, Vec<u8>), D::Error> {
+        let mut tagged = <Self as Deserialize>::deserialize(deserializer)?;
+        let expected = expected_size(tagged.parameter_set);
+        if tagged.bytes.len() != expected {
+            return Err(serde::de::Error::custom(format!(
+                "expected {} bytes, got {}",
+                expected,
+                tagged.bytes.len()
+            )));
+        }
+        Ok((tagged.parameter_set, std::mem::take(&mut tagged.bytes)))
+    }
+}
+
+/// Converts a slice whose length was checked on construction into an array.
+fn to_array<const N: usize>(bytes: &[u8]) -> [u8; N] {
+    bytes
+        .try_into()
+        .expect("Key length checked on construction")
+}
+
+/// An ML-KEM public key tagged with its parameter set.
+///
+/// # Examples
+///
+/// ```rust
+/// use crypto_kem::tagged::{ParameterSet, PublicKey};
+///
+/// let public_key = PublicKey::from_bytes(ParameterSet::MlKem512, &[42u8; 800]).unwrap();
+/// assert_eq!(public_key.parameter_set(), ParameterSet::MlKem512);
+///
+/// // the length must match the parameter set
+/// assert!(PublicKey::from_bytes(ParameterSet::MlKem768, &[42u8; 800]).is_none());
+/// ```
+#[derive(Clone, Zeroize, ZeroizeOnDrop, Serialize)]
+pub struct PublicKey {
+    #[zeroize(skip)]
+    parameter_set: ParameterSet,
+    bytes: Vec<u8>,
+}
+
+impl PublicKey {
+    /// Creates a public key from its bytes.
+    ///
+    /// Returns `None` if `bytes` does not have the public key size of `parameter_set`.
+    #[must_use]
+    pub fn from_bytes(parameter_set: ParameterSet, bytes: &[u8]) -> Option<Self> {
+        (bytes.len() == parameter_set.public_key_size()).then(|| Self {
+            parameter_set,
+            bytes: bytes.to_vec(),
+        })
+    }
+
+    /// Returns the parameter set of the key.
+    #[must_use]
+    pub const fn parameter_set(&self) -> ParameterSet {
+        self.parameter_set
+    }
+
+    /// Returns the raw bytes of the key.
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl From<&crate::PublicKey> for PublicKey {
+    fn from(public_key: &crate::PublicKey) -> Self {
+        Self {
+            parameter_set: ParameterSet::MlKem768,
+            bytes: public_key.as_bytes().to_vec(),
+        }
+    }
+}
+
+impl TryFrom<&PublicKey> for crate::PublicKey {
+    type Error = ParameterSet;
+
+    /// Converts an ML-KEM-768 key to the root type, or returns the parameter set of the key.
+    fn try_from(public_key: &PublicKey) -> Result<Self, Self::Error> {
+        match public_key.parameter_set {
+            ParameterSet::MlKem768 => Ok(Self::from(to_array(&public_key.bytes))),
+            other => Err(other),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for PublicKey {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let (parameter_set, bytes) =
+            TaggedBytes::deserialize_checked(deserializer, ParameterSet::public_key_size)?;
+        Ok(Self {
+            parameter_set,
+            bytes,
+        })
+    }
+}
+
+/// An ML-KEM secret key tagged with its parameter set.
+///
+/// **The secret key bytes are automatically and securely zeroed when dropped.**
+#[derive(Clone, Zeroize, ZeroizeOnDrop, Serialize)]
+pub struct SecretKey {
+    #[zeroize(skip)]
+    parameter_set: ParameterSet,
+    bytes: Vec<u8>,
+}
+
+impl SecretKey {
+    /// Creates a secret key from its bytes.
+    ///
+    /// Returns `None` if `bytes` does not have the private key size of `parameter_set`.
+    #[must_use]
+    pub fn from_bytes(parameter_set: ParameterSet, bytes: &[u8]) -> Option<Self> {
+        (bytes.len() == parameter_set.private_key_size()).then(|| Self {
+            parameter_set,
+            bytes: bytes.to_vec(),
+        })
+    }
+
+    /// Returns the parameter set of the key.
+    #[must_use]
+    pub const fn parameter_set(&self) -> ParameterSet {
+        self.parameter_set
+    }
+
+    /// Returns the raw bytes of the key.
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl From<&crate::SecretKey> for SecretKey {
+    fn from(secret_key: &crate::SecretKey) -> Self {
+        Self {
+            parameter_set: ParameterSet::MlKem768,
+            bytes: secret_key.as_bytes().to_vec(),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for SecretKey {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let (parameter_set, bytes) =
+            TaggedBytes::deserialize_checked(deserializer, ParameterSet::private_key_size)?;
+        Ok(Self {
+            parameter_set,
+            bytes,
+        })
+    }
+}
+
+/// An ML-KEM ciphertext tagged with its parameter set.
+#[derive(Clone, Zeroize, ZeroizeOnDrop, Serialize)]
+pub struct Ciphertext {
+    #[zeroize(skip)]
+    parameter_set: ParameterSet,
+    bytes: Vec<u8>,
+}
+
+impl Ciphertext {
+    /// Creates a ciphertext from its bytes.
+    ///
+    /// Returns `None` if `bytes` does not have the ciphertext size of `parameter_set`.
+    #[must_use]
+    pub fn from_bytes(parameter_set: ParameterSet, bytes: &[u8]) -> Option<Self> {
+        (bytes.len() == parameter_set.ciphertext_size()).then(|| Self {
+            parameter_set,
+            bytes: bytes.to_vec(),
+        })
+    }
+
+    /// Returns the parameter set of the ciphertext.
+    #[must_use]
+    pub const fn parameter_set(&self) -> ParameterSet {
+        self.parameter_set
+    }
+
+    /// Returns the raw bytes of the ciphertext.
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl<'de> Deserialize<'de> for Ciphertext {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let (parameter_set, bytes) =
+            TaggedBytes::deserialize_checked(deserializer, ParameterSet::ciphertext_size)?;
+        Ok(Self {
+            parameter_set,
+            bytes,
+        })
+    }
+}
+
+/// Splits a libcrux key pair into tagged keys, zeroizing the libcrux private key.
+fn tag_key_pair<const PRIVATE: usize, const PUBLIC: usize>(
+    parameter_set: ParameterSet,
+    key_pair: MlKemKeyPair<PRIVATE, PUBLIC>,
+) -> (SecretKey, PublicKey) {
+    let (mut sk, pk) = key_pair.into_parts();
+    let secret_key = SecretKey {
+        parameter_set,
+        bytes: sk.as_slice().to_vec(),
+    };
+    sk[0..PRIVATE].zeroize();
+    let public_key = PublicKey {
+        parameter_set,
+        bytes: pk.as_slice().to_vec(),
+    };
+    (secret_key, public_key)
+}
+
+/// Tags a libcrux ciphertext.
+fn tag_ciphertext<const SIZE: usize>(
+    parameter_set: ParameterSet,
+    ciphertext: &MlKemCiphertext<SIZE>,
+) -> Ciphertext {
+    Ciphertext {
+        parameter_set,
+        bytes: ciphertext.as_slice().to_vec(),
+    }
+}
+
+/// Decapsulates with a libcrux private key built from `secret_key`, zeroizing it afterwards.
+fn decapsulate_with<const PRIVATE: usize, const CT: usize>(
+    secret_key: &SecretKey,
+    ciphertext: &Ciphertext,
+    decapsulate: fn(&MlKemPrivateKey<PRIVATE>, &MlKemCiphertext<CT>) -> [u8; 32],
+) -> SharedSecret {
+    let mut sk = MlKemPrivateKey::from(to_array::<PRIVATE>(&secret_key.bytes));
+    let ct = MlKemCiphertext::from(to_array::<CT>(&ciphertext.bytes));
+    let ss = SharedSecret::from(decapsulate(&sk, &ct));
+    sk[0..PRIVATE].zeroize();
+    ss
+}
+
+/// Generates a key pair of the given parameter set from the given randomness.
+///
+/// # Security Note
+///
+/// The `randomness` parameter must be generated using a cryptographically secure
+/// random number generator.
+#[must_use]
+pub fn generate_key_pair(
+    parameter_set: ParameterSet,
+    randomness: [u8; KEY_GENERATION_RANDOMNESS_SIZE],
+) -> (SecretKey, PublicKey) {
+    match parameter_set {
+        ParameterSet::MlKem512 => {
+            tag_key_pair(parameter_set, mlkem512::generate_key_pair(randomness))
+        }
+        ParameterSet::MlKem768 => {
+            tag_key_pair(parameter_set, mlkem768::generate_key_pair(randomness))
+        }
+        ParameterSet::MlKem1024 => {
+            tag_key_pair(parameter_set, mlkem1024::generate_key_pair(randomness))
+        }
+    }
+}
+
+/// Encapsulates a shared secret to the given public key.
+///
+/// The ciphertext has the parameter set of the public key.
+///
+/// # Security Note
+///
+/// The `randomness` parameter must be generated using a cryptographically secure
+/// random number generator. Reusing randomness will compromise security.
+#[must_use]
+pub fn encapsulate(
+    public_key: &PublicKey,
+    randomness: [u8; ENCAPSULATION_RANDOMNESS_SIZE],
+) -> (Ciphertext, SharedSecret) {
+    let parameter_set = public_key.parameter_set;
+    let (ct, ss) = match parameter_set {
+        ParameterSet::MlKem512 => {
+            let pk = MlKemPublicKey::from(to_array(&public_key.bytes));
+            let (ct, ss) = mlkem512::encapsulate(&pk, randomness);
+            (tag_ciphertext(parameter_set, &ct), ss)
+        }
+        ParameterSet::MlKem768 => {
+            let pk = MlKemPublicKey::from(to_array(&public_key.bytes));
+            let (ct, ss) = mlkem768::encapsulate(&pk, randomness);
+            (tag_ciphertext(parameter_set, &ct), ss)
+        }
+        ParameterSet::MlKem1024 => {
+            let pk = MlKemPublicKey::from(to_array(&public_key.bytes));
+            let (ct, ss) = mlkem1024::encapsulate(&pk, randomness);
+            (tag_ciphertext(parameter_set, &ct), ss)
+        }
+    };
+    (ct, SharedSecret::from(ss))
+}
+
+/// Decapsulates a shared secret from the given ciphertext using the secret key.
+///
+/// Returns `None` if the ciphertext and the secret key have different parameter sets.
+#[must_use]
+pub fn decapsulate(secret_key: &SecretKey, ciphertext: &Ciphertext) -> Option<SharedSecret> {
+    if secret_key.parameter_set != ciphertext.parameter_set {
+        return None;
+    }
+    Some(match secret_key.parameter_set {
+        ParameterSet::MlKem512 => decapsulate_with(secret_key, ciphertext, mlkem512::decapsulate),
+        ParameterSet::MlKem768 => decapsulate_with(secret_key, ciphertext, mlkem768::decapsulate),
+        ParameterSet::MlKem1024 => decapsulate_with(secret_key, ciphertext, mlkem1024::decapsulate),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [ParameterSet; 3] = [
+        ParameterSet::MlKem512,
+        ParameterSet::MlKem768,
+        ParameterSet::MlKem1024,
+    ];
+
+    #[test]
+    fn test_roundtrip_all_parameter_sets() {
+        for parameter_set in ALL {
+            let (secret_key, public_key) = generate_key_pair(parameter_set, [1u8; 64]);
+            assert_eq!(public_key.parameter_set(), parameter_set);
+            assert_eq!(public_key.as_bytes().len(), parameter_set.public_key_size());
+            assert_eq!(
+                secret_key.as_bytes().len(),
+                parameter_set.private_key_size()
+            );
+
+            let (ciphertext, shared_secret1) = encapsulate(&public_key, [2u8; 32]);
+            assert_eq!(ciphertext.parameter_set(), parameter_set);
+            assert_eq!(ciphertext.as_bytes().len(), parameter_set.ciphertext_size());
+
+            let shared_secret2 = decapsulate(&secret_key, &ciphertext).unwrap();
+            assert_eq!(shared_secret1.as_bytes(), shared_secret2.as_bytes());
+        }
+    }
+
+    #[test]
+    fn test_parameter_set_mismatch_rejected() {
+        let (secret_key, _) = generate_key_pair(ParameterSet::MlKem512, [1u8; 64]);
+        let (_, public_key) = generate_key_pair(ParameterSet::MlKem1024, [1u8; 64]);
+        let (ciphertext, _) = encapsulate(&public_key, [2u8; 32]);
+        assert!(decapsulate(&secret_key, &ciphertext).is_none());
+    }
+
+    #[test]
+    fn test_ml_kem_768_matches_root_api() {
+        let (root_secret_key, root_public_key) = crate::generate_key_pair([3u8; 64]);
+        let (secret_key, public_key) = generate_key_pair(ParameterSet::MlKem768, [3u8; 64]);
+        assert_eq!(public_key.as_bytes(), root_public_key.as_bytes());
+        assert_eq!(secret_key.as_bytes(), root_secret_key.as_bytes());
+
+        let (ciphertext, shared_secret1) = encapsulate(&public_key, [4u8; 32]);
+        let (root_ciphertext, root_shared_secret) = crate::encapsulate(&root_public_key, [4u8; 32]);
+        assert_eq!(ciphertext.as_bytes(), root_ciphertext.as_bytes());
+        assert_eq!(shared_secret1.as_bytes(), root_shared_secret.as_bytes());
+
+        let converted = crate::PublicKey::try_from(&PublicKey::from(&root_public_key)).unwrap();
+        assert_eq!(converted.as_bytes(), root_public_key.as_bytes());
+        assert_eq!(
+            SecretKey::from(&root_secret_key).as_bytes(),
+            secret_key.as_bytes()
+        );
+    }
+
+    #[test]
+    fn test_root_conversion_rejects_other_parameter_sets() {
+        let (_, public_key) = generate_key_pair(ParameterSet::MlKem512, [1u8; 64]);
+        assert_eq!(
+            crate::PublicKey::try_from(&public_key).err(),
+            Some(ParameterSet::MlKem512)
+        );
+    }
+
+    #[test]
+    fn test_from_bytes_checks_length() {
+        for parameter_set in ALL {
+            let size = parameter_set.public_key_size();
+            assert!(PublicKey::from_bytes(parameter_set, &vec![0u8; size]).is_some());
+            assert!(PublicKey::from_bytes(parameter_set, &vec![0u8; size - 1]).is_none());
+
+            let size = parameter_set.private_key_size();
+            assert!(SecretKey::from_bytes(parameter_set, &vec![0u8; size]).is_some());
+            assert!(SecretKey::from_bytes(parameter_set, &vec![0u8; size + 1]).is_none());
+
+            let size = parameter_set.ciphertext_size();
+            assert!(Ciphertext::from_bytes(parameter_set, &vec![0u8; size]).is_some());
+            assert!(Ciphertext::from_bytes(parameter_set, &[]).is_none());
+        }
+    }
+}
@@ -19,6 +19,12 @@
 //! - **Memory safety**: Uses external buffers for input and output
 //! - **Panics**: Panics if anything invalid is detected
 //!
+//! # Parameters
+//!
+//! [`derive`] uses fixed parameters, so that the same password always derives the same key.
+//! Outputs that are stored, such as password verifiers, should use [`DerivedKey`] instead,
+//! which records its [`Params`] and can be upgraded to stronger ones.
+//!
 //! # Example
 //!
 //! ```
@@ -34,7 +40,14 @@
 //! // The derived key can now be used for encryption or authentication
 //! ```
 
-use argon2::{Algorithm, Argon2, ParamsBuilder, Version};
+mod params;
+
+pub use params::{
+    ARGON2_VERSION, Algorithm, DEFAULT_M_COST, DEFAULT_P_COST, DEFAULT_T_COST, DerivedKey,
+    MIN_SALT_SIZE, Params, PasswordKdfError,
+};
+
+use argon2::{Argon2, ParamsBuilder, Version};
 
 /// Derives a cryptographic key from a password using Argon2id.
 ///
@@ -83,7 +96,7 @@ use argon2::{Algorithm, Argon2, ParamsBuilder, Version};
 pub fn derive(password: &[u8], salt: &[u8], output_buffer: &mut [u8]) {
     // Validate salt length (minimum 8 bytes per Argon2 spec)
     assert!(
-        salt.len() >= 8,
+        salt.len() >= MIN_SALT_SIZE,
         "Salt must be at least 8 bytes, got {} bytes",
         salt.len()
     );
@@ -91,13 +104,13 @@ pub fn derive(password: &[u8], salt: &[u8], output_buffer: &mut [u8]) {
     // Parameters optimized for single-core WASM and mobile devices
     // while maintaining high security (128-bit equivalent)
     let params = ParamsBuilder::new()
-        .m_cost(32768) // 32 MiB - Reasonable for mobile devices
-        .t_cost(4) // 4 iterations - Higher to compensate for single thread
-        .p_cost(1) // 1 thread - Single-core for WASM compatibility
+        .m_cost(DEFAULT_M_COST) // 32 MiB - Reasonable for mobile devices
+        .t_cost(DEFAULT_T_COST) // 4 iterations - Higher to compensate for single thread
+        .p_cost(DEFAULT_P_COST) // 1 thread - Single-core for WASM compatibility
         .build()
         .expect("Invalid Argon2 parameters");
 
-    let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, params);
+    let argon2 = Argon2::new(argon2::Algorithm::Argon2id, Version::V0x13, params);
 
     // Perform the key derivation
     argon2
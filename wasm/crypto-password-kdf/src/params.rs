@@ -0,0 +1,317 @@
+//! Self-describing Argon2 parameters, stored next to derived outputs.
+//!
+//! [`derive`](crate::derive) uses fixed parameters: changing them would silently derive other
+//! keys for every existing user. Outputs meant to be stored (e.g. to verify a password) are
+//! instead kept in a [`DerivedKey`] along with the [`Params`] they were derived with, so that
+//! they can be verified after the recommended parameters change, and upgraded to the new ones
+//! once the password is known to be right.
+//!
+//! # Example
+//!
+//! ```
+//! use crypto_password_kdf::{DerivedKey, Params};
+//!
+//! // cheap parameters to keep the example fast
+//! let old_params = Params::with_costs(64, 1, 1, b"per-user-salt-16".to_vec());
+//! let stored = DerivedKey::derive(b"password", old_params, 32).unwrap();
+//! assert!(stored.verify(b"password"));
+//! assert!(!stored.verify(b"wrong password"));
+//!
+//! // later, stronger parameters are recommended
+//! let new_params = Params::with_costs(128, 2, 1, b"per-user-salt-16".to_vec());
+//! if stored.needs_upgrade(&new_params) {
+//!     let upgraded = stored.upgrade(b"password", new_params).unwrap();
+//!     assert!(upgraded.verify(b"password"));
+//! }
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use subtle::ConstantTimeEq;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+/// Memory cost of the default parameters, in KiB (32 MiB).
+pub const DEFAULT_M_COST: u32 = 32768;
+
+/// Number of iterations of the default parameters.
+pub const DEFAULT_T_COST: u32 = 4;
+
+/// Degree of parallelism of the default parameters.
+pub const DEFAULT_P_COST: u32 = 1;
+
+/// Current Argon2 version (1.3).
+pub const ARGON2_VERSION: u32 = 0x13;
+
+/// Minimum salt length in bytes.
+pub const MIN_SALT_SIZE: usize = 8;
+
+/// Error returned when deriving with stored parameters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PasswordKdfError {
+    /// The algorithm version is not supported.
+    UnsupportedVersion,
+    /// The costs are out of range, the salt is too short, or the output length is invalid.
+    InvalidParams,
+    /// The password does not match the stored output.
+    WrongPassword,
+    /// The new parameters are weaker than the current ones.
+    WeakerParams,
+}
+
+impl fmt::Display for PasswordKdfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedVersion => write!(f, "unsupported password KDF version"),
+            Self::InvalidParams => write!(f, "invalid password KDF parameters"),
+            Self::WrongPassword => write!(f, "wrong password"),
+            Self::WeakerParams => write!(f, "password KDF parameters are weaker"),
+        }
+    }
+}
+
+impl std::error::Error for PasswordKdfError {}
+
+/// Password hashing algorithm.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Algorithm {
+    /// Argon2id (RFC 9106).
+    Argon2id,
+}
+
+/// Parameters of a password-based derivation.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Params {
+    /// Hashing algorithm.
+    pub algorithm: Algorithm,
+    /// Version of the algorithm ([`ARGON2_VERSION`]).
+    pub version: u32,
+    /// Memory cost in KiB.
+    pub m_cost: u32,
+    /// Number of iterations.
+    pub t_cost: u32,
+    /// Degree of parallelism.
+    pub p_cost: u32,
+    /// Salt, unique per user.
+    pub salt: Vec<u8>,
+}
+
+impl Params {
+    /// Creates the parameters used by [`derive`](crate::derive), with the given salt.
+    #[must_use]
+    pub fn new(salt: Vec<u8>) -> Self {
+        Self::with_costs(DEFAULT_M_COST, DEFAULT_T_COST, DEFAULT_P_COST, salt)
+    }
+
+    /// Creates Argon2id parameters with the given costs and salt.
+    #[must_use]
+    pub fn with_costs(m_cost: u32, t_cost: u32, p_cost: u32, salt: Vec<u8>) -> Self {
+        Self {
+            algorithm: Algorithm::Argon2id,
+            version: ARGON2_VERSION,
+            m_cost,
+            t_cost,
+            p_cost,
+            salt,
+        }
+    }
+
+    /// Returns `true` if any cost, or the algorithm version, is lower than in `other`.
+    #[must_use]
+    pub fn is_weaker_than(&self, other: &Self) -> bool {
+        self.version < other.version
+            || self.m_cost < other.m_cost
+            || self.t_cost < other.t_cost
+            || self.p_cost < other.p_cost
+    }
+
+    /// Derives `output_buffer.len()` bytes from a password with these parameters.
+    ///
+    /// # Errors
+    ///
+    /// - [`PasswordKdfError::UnsupportedVersion`] if the version is not a known Argon2 version
+    /// - [`PasswordKdfError::InvalidParams`] if the costs, salt or output length are rejected
+    ///   by Argon2
+    pub fn derive(
+        &self,
+        password: &[u8],
+        output_buffer: &mut [u8],
+    ) -> Result<(), PasswordKdfError> {
+        if self.salt.len() < MIN_SALT_SIZE {
+            return Err(PasswordKdfError::InvalidParams);
+        }
+        let version = argon2::Version::try_from(self.version)
+            .map_err(|_| PasswordKdfError::UnsupportedVersion)?;
+        let params = argon2::Params::new(self.m_cost, self.t_cost, self.p_cost, None)
+            .map_err(|_| PasswordKdfError::InvalidParams)?;
+        let algorithm = match self.algorithm {
+            Algorithm::Argon2id => argon2::Algorithm::Argon2id,
+        };
+
+        argon2::Argon2::new(algorithm, version, params)
+            .hash_password_into(password, &self.salt, output_buffer)
+            .map_err(|_| PasswordKdfError::InvalidParams)
+    }
+}
+
+/// An output derived from a password, stored along with its parameters.
+#[derive(Clone, Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
+pub struct DerivedKey {
+    /// Parameters the output was derived with.
+    #[zeroize(skip)]
+    params: Params,
+    /// The derived output.
+    output: Vec<u8>,
+}
+
+impl DerivedKey {
+    /// Derives `output_size` bytes from a password.
+    ///
+    /// # Errors
+    ///
+    /// See [`Params::derive`].
+    pub fn derive(
+        password: &[u8],
+        params: Params,
+        output_size: usize,
+    ) -> Result<Self, PasswordKdfError> {
+        let mut output = vec![0u8; output_size];
+        params.derive(password, &mut output)?;
+        Ok(Self { params, output })
+    }
+
+    /// Returns the parameters the output was derived with.
+    #[must_use]
+    pub const fn params(&self) -> &Params {
+        &self.params
+    }
+
+    /// Returns the derived output.
+    #[must_use]
+    pub fn output(&self) -> &[u8] {
+        &self.output
+    }
+
+    /// Re-derives with the stored parameters, and checks in constant time that the password
+    /// gives the stored output.
+    ///
+    /// Returns `false` if the stored parameters are invalid.
+    #[must_use]
+    pub fn verify(&self, password: &[u8]) -> bool {
+        let mut output = zeroize::Zeroizing::new(vec![0u8; self.output.len()]);
+        self.params.derive(password, &mut output).is_ok() && bool::from(output.ct_eq(&self.output))
+    }
+
+    /// Returns `true` if the output was derived with parameters weaker than `params`.
+    #[must_use]
+    pub fn needs_upgrade(&self, params: &Params) -> bool {
+        self.params.is_weaker_than(params)
+    }
+
+    /// Verifies the password, then derives a new output of the same size with `params`.
+    ///
+    /// # Errors
+    ///
+    /// - [`PasswordKdfError::WeakerParams`] if `params` is weaker than the current parameters
+    /// - [`PasswordKdfError::WrongPassword`] if the password does not match
+    /// - the errors of [`Params::derive`] with the new parameters
+    pub fn upgrade(&self, password: &[u8], params: Params) -> Result<Self, PasswordKdfError> {
+        if params.is_weaker_than(&self.params) {
+            return Err(PasswordKdfError::WeakerParams);
+        }
+        if !self.verify(password) {
+            return Err(PasswordKdfError::WrongPassword);
+        }
+        Self::derive(password, params, self.output.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Cheap parameters to keep the tests fast.
+    fn cheap_params(salt: &[u8]) -> Params {
+        Params::with_costs(64, 1, 1, salt.to_vec())
+    }
+
+    #[test]
+    fn test_default_params_match_derive() {
+        let salt = b"test-salt-16bytes";
+        let mut expected = [0u8; 32];
+        crate::derive(b"password", salt, &mut expected);
+
+        let derived = DerivedKey::derive(b"password", Params::new(salt.to_vec()), 32).unwrap();
+        assert_eq!(derived.output(), expected);
+    }
+
+    #[test]
+    fn test_verify() {
+        let derived =
+            DerivedKey::derive(b"password", cheap_params(b"salt-16-bytes!!!"), 32).unwrap();
+        assert!(derived.verify(b"password"));
+        assert!(!derived.verify(b"Password"));
+        assert!(!derived.verify(b""));
+    }
+
+    #[test]
+    fn test_params_change_output() {
+        let salt = b"salt-16-bytes!!!";
+        let base = DerivedKey::derive(b"password", cheap_params(salt), 32).unwrap();
+        for params in [
+            Params::with_costs(128, 1, 1, salt.to_vec()),
+            Params::with_costs(64, 2, 1, salt.to_vec()),
+            Params::with_costs(64, 1, 2, salt.to_vec()),
+            cheap_params(b"other-salt-16byt"),
+        ] {
+            let derived = DerivedKey::derive(b"password", params, 32).unwrap();
+            assert_ne!(derived.output(), base.output());
+        }
+    }
+
+    #[test]
+    fn test_invalid_params() {
+        let mut params = cheap_params(b"short");
+        assert_eq!(
+            DerivedKey::derive(b"password", params.clone(), 32).err(),
+            Some(PasswordKdfError::InvalidParams)
+        );
+
+        params.salt = b"salt-16-bytes!!!".to_vec();
+        params.version = 0x14;
+        assert_eq!(
+            DerivedKey::derive(b"password", params.clone(), 32).err(),
+            Some(PasswordKdfError::UnsupportedVersion)
+        );
+
+        params.version = 0x13;
+        params.t_cost = 0;
+        assert_eq!(
+            DerivedKey::derive(b"password", params, 32).err(),
+            Some(PasswordKdfError::InvalidParams)
+        );
+    }
+
+    #[test]
+    fn test_upgrade() {
+        let salt = b"salt-16-bytes!!!";
+        let stored = DerivedKey::derive(b"password", cheap_params(salt), 32).unwrap();
+        let stronger = Params::with_costs(128, 2, 1, b"fresh-salt-16byt".to_vec());
+
+        assert!(stored.needs_upgrade(&stronger));
+        assert_eq!(
+            stored.upgrade(b"wrong", stronger.clone()).err(),
+            Some(PasswordKdfError::WrongPassword)
+        );
+
+        let upgraded = stored.upgrade(b"password", stronger.clone()).unwrap();
+        assert_eq!(upgraded.params(), &stronger);
+        assert!(upgraded.verify(b"password"));
+        assert!(!upgraded.needs_upgrade(&stronger));
+
+        // never downgrade
+        assert_eq!(
+            upgraded.upgrade(b"password", cheap_params(salt)).err(),
+            Some(PasswordKdfError::WeakerParams)
+        );
+    }
+}
@@ -106,6 +106,27 @@
 //! If you need error recovery, you can catch the panic using `std::panic::catch_unwind`
 //! (on platforms that support it), but this is generally not recommended for
 //! cryptographic code.
+//!
+//! ## Reproducible Outputs
+//!
+//! With the `seeded` feature, tests can replace the system random source with a seeded
+//! ChaCha20 generator, so that everything built on `fill_buffer` (keys, nonces, signatures,
+//! ciphertexts) is reproducible and can be checked against known-answer test vectors:
+//!
+//! ```rust,ignore
+//! crypto_rng::seed([0u8; crypto_rng::SEED_SIZE]);
+//! // every call to fill_buffer on this thread now draws from the seeded generator
+//! crypto_rng::clear_seed();
+//! ```
+//!
+//! The seed only applies to the calling thread, so tests running in parallel do not interfere.
+//! The feature makes all outputs predictable and must never be enabled in release builds.
+
+#[cfg(feature = "seeded")]
+mod seeded;
+
+#[cfg(feature = "seeded")]
+pub use seeded::{SEED_SIZE, clear_seed, seed, with_seed};
 
 /// Fills a buffer with cryptographically secure random bytes.
 ///
@@ -157,6 +178,11 @@
 /// - **WASM**: Uses `crypto.getRandomValues()` from the Web Crypto API
 /// - **Other platforms**: Falls back to appropriate platform-specific secure RNG
 ///
+/// # Seeded Mode
+///
+/// With the `seeded` feature, if the calling thread was seeded with [`seed`] or [`with_seed`],
+/// the bytes come from the seeded generator instead of the system random source.
+///
 /// # Security
 ///
 /// The random bytes generated are suitable for:
@@ -167,6 +193,10 @@
 /// - Session tokens
 /// - Any other security-critical random data
 pub fn fill_buffer(buffer: &mut [u8]) {
+    #[cfg(feature = "seeded")]
+    if seeded::try_fill_buffer(buffer) {
+        return;
+    }
     getrandom::getrandom(buffer)
         .expect("Failed to generate random bytes: system random source unavailable");
 }
@@ -0,0 +1,152 @@
+//! Seeded random source, for reproducible outputs in tests and test vectors.
+//!
+//! Once [`seed`] is called, [`fill_buffer`](crate::fill_buffer) draws from a ChaCha20
+//! generator seeded with the given bytes instead of the system random source, on the calling
+//! thread only, until [`clear_seed`] is called. The same seed gives the same bytes on every
+//! platform, including WASM, so that keys, nonces and ciphertexts can be compared between the
+//! Rust and JS builds.
+//!
+//! The output is entirely predictable from the seed: this module is only compiled with the
+//! `seeded` feature, which must never be enabled in release builds.
+
+use rand_chacha::ChaCha20Rng;
+use rand_chacha::rand_core::{RngCore, SeedableRng};
+use std::cell::RefCell;
+
+/// Size of a seed in bytes.
+pub const SEED_SIZE: usize = 32;
+
+thread_local! {
+    /// Generator replacing the system random source on this thread, if seeded.
+    static SEEDED_RNG: RefCell<Option<ChaCha20Rng>> = const { RefCell::new(None) };
+}
+
+/// Replaces the system random source with a ChaCha20 generator seeded with `seed`, on the
+/// calling thread.
+///
+/// Seeding again restarts the stream from the new seed.
+pub fn seed(seed: [u8; SEED_SIZE]) {
+    SEEDED_RNG.with(|rng| *rng.borrow_mut() = Some(ChaCha20Rng::from_seed(seed)));
+}
+
+/// Restores the system random source on the calling thread.
+pub fn clear_seed() {
+    SEEDED_RNG.with(|rng| *rng.borrow_mut() = None);
+}
+
+/// Runs `f` with the random source seeded with `seed`, then restores the previous source,
+/// even if `f` panics.
+///
+/// # Example
+///
+/// ```rust
+/// use crypto_rng::{fill_buffer, with_seed};
+///
+/// let draw = || {
+///     let mut bytes = [0u8; 32];
+///     fill_buffer(&mut bytes);
+///     bytes
+/// };
+/// assert_eq!(with_seed([7u8; 32], draw), with_seed([7u8; 32], draw));
+/// ```
+pub fn with_seed<T>(seed: [u8; SEED_SIZE], f: impl FnOnce() -> T) -> T {
+    let previous = SEEDED_RNG.with(|rng| rng.replace(Some(ChaCha20Rng::from_seed(seed))));
+    let _restore = RestoreOnDrop(previous);
+    f()
+}
+
+/// Restores the random source it holds on the calling thread when dropped.
+struct RestoreOnDrop(Option<ChaCha20Rng>);
+
+impl Drop for RestoreOnDrop {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        SEEDED_RNG.with(|rng| *rng.borrow_mut() = previous);
+    }
+}
+
+/// Fills `buffer` from the seeded generator, returning `false` if the thread is not seeded.
+pub(crate) fn try_fill_buffer(buffer: &mut [u8]) -> bool {
+    SEEDED_RNG.with(|rng| match rng.borrow_mut().as_mut() {
+        Some(rng) => {
+            rng.fill_bytes(buffer);
+            true
+        }
+        None => false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fill_buffer;
+
+    fn draw() -> [u8; 32] {
+        let mut buffer = [0u8; 32];
+        fill_buffer(&mut buffer);
+        buffer
+    }
+
+    #[test]
+    fn test_same_seed_same_bytes() {
+        seed([1u8; SEED_SIZE]);
+        let first = (draw(), draw());
+        seed([1u8; SEED_SIZE]);
+        let second = (draw(), draw());
+        clear_seed();
+
+        assert_eq!(first, second);
+        assert_ne!(first.0, first.1);
+    }
+
+    #[test]
+    fn test_different_seeds_different_bytes() {
+        let first = with_seed([1u8; SEED_SIZE], draw);
+        let second = with_seed([2u8; SEED_SIZE], draw);
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn test_chacha20_vector() {
+        // ChaCha20 keystream for an all-zero key and nonce (RFC 7539, section A.1)
+        let keystream = with_seed([0u8; SEED_SIZE], draw);
+        assert_eq!(
+            keystream[..16],
+            [
+                0x76, 0xb8, 0xe0, 0xad, 0xa0, 0xf1, 0x3d, 0x90, 0x40, 0x5d, 0x6a, 0xe5, 0x53, 0x86,
+                0xbd, 0x28
+            ]
+        );
+    }
+
+    #[test]
+    fn test_clear_seed_restores_system_source() {
+        seed([3u8; SEED_SIZE]);
+        let seeded = draw();
+        clear_seed();
+        assert_ne!(draw(), with_seed([3u8; SEED_SIZE], draw));
+        assert_ne!(draw(), seeded);
+    }
+
+    #[test]
+    fn test_with_seed_restores_previous_seed() {
+        seed([4u8; SEED_SIZE]);
+        let inner = with_seed([5u8; SEED_SIZE], draw);
+        let outer = draw();
+        clear_seed();
+
+        assert_eq!(inner, with_seed([5u8; SEED_SIZE], draw));
+        assert_eq!(outer, with_seed([4u8; SEED_SIZE], draw));
+    }
+
+    #[test]
+    fn test_with_seed_restores_previous_seed_on_panic() {
+        seed([6u8; SEED_SIZE]);
+        let result = std::panic::catch_unwind(|| with_seed([7u8; SEED_SIZE], || panic!("in f")));
+        let outer = draw();
+        clear_seed();
+
+        assert!(result.is_err());
+        assert_eq!(outer, with_seed([6u8; SEED_SIZE], draw));
+    }
+}
@@ -0,0 +1,188 @@
+//! Command-line parsing.
+
+use crate::error::CliError;
+use auth::UserId;
+use std::path::PathBuf;
+
+/// Usage text printed by `echo-cli help`.
+pub const USAGE: &str = "\
+Usage: echo-cli [--profile DIR] [--board DIR|URL] <command> [arguments]
+
+Options:
+  --profile DIR   profile directory holding the identity and session state
+                  (default: $ECHO_PROFILE, or ./echo-profile)
+  --board DIR|URL directory of the shared board, or base URL of a board server such as
+                  http://127.0.0.1:3000/api (default: $ECHO_BOARD)
+
+Commands:
+  init [--mnemonic]          create an identity and publish its public keys; with
+                             --mnemonic, generate a mnemonic to use as passphrase
+  whoami                     print the user ID of the identity
+  publish                    publish the public keys of the identity again
+  connect <USER_ID> [TEXT]   request a session with a user, or accept their request;
+                             TEXT is sent along with the announcement
+  peers                      list known peers and the status of their sessions
+  send <USER_ID> <TEXT>      send a message over an active session
+  receive                    read new announcements and messages
+  refresh                    expire stale sessions, send due keep-alive messages and
+                             re-establish broken sessions
+  discard <USER_ID>          forget a peer and its session
+  help                       print this help
+
+The passphrase is read from $ECHO_PASSPHRASE, or from the first line of standard input.
+";
+
+/// A command to run.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Command {
+    Init { mnemonic: bool },
+    Whoami,
+    Publish,
+    Connect { peer: UserId, text: String },
+    Peers,
+    Send { peer: UserId, text: String },
+    Receive,
+    Refresh,
+    Discard { peer: UserId },
+    Help,
+}
+
+/// A parsed command line.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Options {
+    /// Profile directory, if given on the command line
+    pub profile: Option<PathBuf>,
+    /// Board directory or URL, if given on the command line
+    pub board: Option<String>,
+    pub command: Command,
+}
+
+/// Parses the command-line arguments, without the program name.
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Options, CliError> {
+    let mut args = args.into_iter();
+    let mut profile = None;
+    let mut board = None;
+
+    let command = loop {
+        let Some(arg) = args.next() else {
+            break "help".to_string();
+        };
+        match arg.as_str() {
+            "--profile" => profile = Some(PathBuf::from(value_of(&arg, args.next())?)),
+            "--board" => board = Some(value_of(&arg, args.next())?),
+            "-h" | "--help" => break "help".to_string(),
+            _ if arg.starts_with('-') => {
+                return Err(CliError::Usage(format!("unknown option `{arg}`")));
+            }
+            _ => break arg,
+        }
+    };
+
+    let rest: Vec<String> = args.collect();
+    let command = match (command.as_str(), rest.as_slice()) {
+        ("init", []) => Command::Init { mnemonic: false },
+        ("init", [flag]) if flag == "--mnemonic" => Command::Init { mnemonic: true },
+        ("whoami", []) => Command::Whoami,
+        ("publish", []) => Command::Publish,
+        ("connect", [peer, text @ ..]) => Command::Connect {
+            peer: parse_user_id(peer)?,
+            text: text.join(" "),
+        },
+        ("peers", []) => Command::Peers,
+        ("send", [peer, text @ ..]) if !text.is_empty() => Command::Send {
+            peer: parse_user_id(peer)?,
+            text: text.join(" "),
+        },
+        ("receive", []) => Command::Receive,
+        ("refresh", []) => Command::Refresh,
+        ("discard", [peer]) => Command::Discard {
+            peer: parse_user_id(peer)?,
+        },
+        ("help", []) => Command::Help,
+        (
+            "init" | "whoami" | "publish" | "connect" | "peers" | "send" | "receive" | "refresh"
+            | "discard" | "help",
+            _,
+        ) => {
+            return Err(CliError::Usage(format!(
+                "invalid arguments to `{command}`, see `echo-cli help`"
+            )));
+        }
+        _ => return Err(CliError::Usage(format!("unknown command `{command}`"))),
+    };
+
+    Ok(Options {
+        profile,
+        board,
+        command,
+    })
+}
+
+fn value_of(option: &str, value: Option<String>) -> Result<String, CliError> {
+    value.ok_or_else(|| CliError::Usage(format!("`{option}` expects a value")))
+}
+
+fn parse_user_id(text: &str) -> Result<UserId, CliError> {
+    text.parse()
+        .map_err(|error| CliError::Usage(format!("invalid user ID `{text}`: {error}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_line(line: &str) -> Result<Options, CliError> {
+        parse(line.split_whitespace().map(str::to_string))
+    }
+
+    #[test]
+    fn test_parse_options_and_commands() {
+        let options = parse_line("--profile alice --board board init --mnemonic").unwrap();
+        assert_eq!(options.profile, Some(PathBuf::from("alice")));
+        assert_eq!(options.board, Some("board".to_string()));
+        assert_eq!(options.command, Command::Init { mnemonic: true });
+
+        assert_eq!(parse_line("").unwrap().command, Command::Help);
+        assert_eq!(parse_line("--help").unwrap().command, Command::Help);
+        assert_eq!(parse_line("receive").unwrap().command, Command::Receive);
+        assert_eq!(parse_line("receive").unwrap().profile, None);
+    }
+
+    #[test]
+    fn test_parse_peer_commands() {
+        let peer = UserId::from_bytes([5u8; auth::USER_ID_SIZE]);
+
+        let options = parse_line(&format!("send {peer} hello there")).unwrap();
+        assert_eq!(
+            options.command,
+            Command::Send {
+                peer: peer.clone(),
+                text: "hello there".to_string()
+            }
+        );
+        let options = parse_line(&format!("connect {peer}")).unwrap();
+        assert_eq!(
+            options.command,
+            Command::Connect {
+                peer: peer.clone(),
+                text: String::new()
+            }
+        );
+        let options = parse_line(&format!("discard {peer}")).unwrap();
+        assert_eq!(options.command, Command::Discard { peer: peer.clone() });
+
+        assert!(parse_line(&format!("send {peer}")).is_err());
+        assert!(parse_line(&format!("discard {peer} extra")).is_err());
+        assert!(parse_line("connect gossip1invalid").is_err());
+        assert!(parse_line("connect").is_err());
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_command_lines() {
+        assert!(parse_line("--board").is_err());
+        assert!(parse_line("--verbose peers").is_err());
+        assert!(parse_line("peers extra").is_err());
+        assert!(parse_line("init --other").is_err());
+        assert!(parse_line("unknown").is_err());
+    }
+}
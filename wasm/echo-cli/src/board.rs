@@ -0,0 +1,311 @@
+//! Backends storing announcements, messages and public keys.
+//!
+//! The [`Board`] trait mirrors the message protocol used by the application: an append-only
+//! bulletin of announcements read from a counter onwards, a key-value store of messages keyed
+//! by seeker, and a directory of public keys keyed by user ID. Boards are local directories
+//! ([`FileBoard`]) or HTTP servers ([`HttpBoard`](crate::http_board::HttpBoard)).
+
+use auth::UserId;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Error returned by a [`Board`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BoardError {
+    /// The board could not be read or written.
+    Io(String),
+    /// A message is already stored under this seeker.
+    SeekerInUse,
+    /// The board holds an entry that cannot be parsed.
+    Malformed(String),
+}
+
+impl fmt::Display for BoardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "board I/O error: {error}"),
+            Self::SeekerInUse => write!(f, "a message is already stored under this seeker"),
+            Self::Malformed(entry) => write!(f, "malformed board entry: {entry}"),
+        }
+    }
+}
+
+impl std::error::Error for BoardError {}
+
+impl From<io::Error> for BoardError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error.to_string())
+    }
+}
+
+/// A `(seeker, message)` pair read from a board.
+pub type MessageEntry = (Vec<u8>, Vec<u8>);
+
+/// Storage shared by the clients to exchange announcements, messages and public keys.
+pub trait Board {
+    /// Appends an announcement and returns its bulletin counter.
+    fn post_announcement(&mut self, announcement: &[u8]) -> Result<u64, BoardError>;
+
+    /// Returns the `(counter, announcement)` pairs posted after `counter`, in posting order.
+    fn fetch_announcements(&self, counter: u64) -> Result<Vec<(u64, Vec<u8>)>, BoardError>;
+
+    /// Stores a message under its seeker.
+    ///
+    /// Fails with [`BoardError::SeekerInUse`] if the seeker already holds a message.
+    fn post_message(&mut self, seeker: &[u8], message: &[u8]) -> Result<(), BoardError>;
+
+    /// Returns the `(seeker, message)` pairs stored under any of `seekers`.
+    fn fetch_messages(&self, seekers: &[Vec<u8>]) -> Result<Vec<MessageEntry>, BoardError>;
+
+    /// Publishes the serialized public keys of a user, replacing any previous ones.
+    fn post_public_keys(&mut self, user_id: &UserId, public_keys: &[u8]) -> Result<(), BoardError>;
+
+    /// Returns the serialized public keys published by a user, if any.
+    fn fetch_public_keys(&self, user_id: &UserId) -> Result<Option<Vec<u8>>, BoardError>;
+}
+
+impl<B: Board + ?Sized> Board for Box<B> {
+    fn post_announcement(&mut self, announcement: &[u8]) -> Result<u64, BoardError> {
+        (**self).post_announcement(announcement)
+    }
+
+    fn fetch_announcements(&self, counter: u64) -> Result<Vec<(u64, Vec<u8>)>, BoardError> {
+        (**self).fetch_announcements(counter)
+    }
+
+    fn post_message(&mut self, seeker: &[u8], message: &[u8]) -> Result<(), BoardError> {
+        (**self).post_message(seeker, message)
+    }
+
+    fn fetch_messages(&self, seekers: &[Vec<u8>]) -> Result<Vec<MessageEntry>, BoardError> {
+        (**self).fetch_messages(seekers)
+    }
+
+    fn post_public_keys(&mut self, user_id: &UserId, public_keys: &[u8]) -> Result<(), BoardError> {
+        (**self).post_public_keys(user_id, public_keys)
+    }
+
+    fn fetch_public_keys(&self, user_id: &UserId) -> Result<Option<Vec<u8>>, BoardError> {
+        (**self).fetch_public_keys(user_id)
+    }
+}
+
+/// Number of digits of announcement file names, so that they sort by counter
+const COUNTER_DIGITS: usize = 20;
+
+/// Distinguishes the temporary files written by this process
+static TEMPORARY_FILES: AtomicU64 = AtomicU64::new(0);
+
+/// A [`Board`] stored in a local directory, which several clients can share.
+///
+/// ```text
+/// announcements/<counter>   one file per announcement, counters start at 1
+/// messages/<seeker in hex>  one file per message
+/// public_keys/<user ID>     serialized public keys
+/// ```
+///
+/// Entries are written to a temporary file first and then linked in place, so that readers
+/// never see partial entries and concurrent writers never overwrite each other.
+pub struct FileBoard {
+    root: PathBuf,
+}
+
+impl FileBoard {
+    /// Opens the board stored in `root`, creating its directories if needed.
+    pub fn open(root: impl Into<PathBuf>) -> Result<Self, BoardError> {
+        let board = Self { root: root.into() };
+        for directory in ["announcements", "messages", "public_keys", "tmp"] {
+            fs::create_dir_all(board.root.join(directory))?;
+        }
+        Ok(board)
+    }
+
+    /// Writes `contents` to a new temporary file and returns its path.
+    fn write_temporary(&self, contents: &[u8]) -> io::Result<PathBuf> {
+        let index = TEMPORARY_FILES.fetch_add(1, Ordering::Relaxed);
+        let path = self
+            .root
+            .join("tmp")
+            .join(format!("{}-{index}", std::process::id()));
+        fs::write(&path, contents)?;
+        Ok(path)
+    }
+
+    /// Links a complete entry at `path`, failing if an entry already exists there.
+    fn create_entry(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        let temporary = self.write_temporary(contents)?;
+        let result = fs::hard_link(&temporary, path);
+        let _ = fs::remove_file(&temporary);
+        result
+    }
+
+    fn announcement_counters(&self) -> Result<Vec<u64>, BoardError> {
+        let mut counters = Vec::new();
+        for entry in fs::read_dir(self.root.join("announcements"))? {
+            let name = entry?.file_name();
+            let name = name.to_string_lossy();
+            let counter = name
+                .parse()
+                .map_err(|_| BoardError::Malformed(format!("announcements/{name}")))?;
+            counters.push(counter);
+        }
+        counters.sort_unstable();
+        Ok(counters)
+    }
+
+    fn announcement_path(&self, counter: u64) -> PathBuf {
+        self.root
+            .join("announcements")
+            .join(format!("{counter:0COUNTER_DIGITS$}"))
+    }
+
+    fn message_path(&self, seeker: &[u8]) -> PathBuf {
+        self.root.join("messages").join(hex_encode(seeker))
+    }
+
+    fn public_keys_path(&self, user_id: &UserId) -> PathBuf {
+        self.root.join("public_keys").join(user_id.to_string())
+    }
+}
+
+impl Board for FileBoard {
+    fn post_announcement(&mut self, announcement: &[u8]) -> Result<u64, BoardError> {
+        loop {
+            let counter = self.announcement_counters()?.last().copied().unwrap_or(0) + 1;
+            match self.create_entry(&self.announcement_path(counter), announcement) {
+                Ok(()) => return Ok(counter),
+                // another client took this counter in the meantime
+                Err(error) if error.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(error) => return Err(error.into()),
+            }
+        }
+    }
+
+    fn fetch_announcements(&self, counter: u64) -> Result<Vec<(u64, Vec<u8>)>, BoardError> {
+        self.announcement_counters()?
+            .into_iter()
+            .filter(|&posted| posted > counter)
+            .map(|posted| Ok((posted, fs::read(self.announcement_path(posted))?)))
+            .collect()
+    }
+
+    fn post_message(&mut self, seeker: &[u8], message: &[u8]) -> Result<(), BoardError> {
+        match self.create_entry(&self.message_path(seeker), message) {
+            Ok(()) => Ok(()),
+            Err(error) if error.kind() == io::ErrorKind::AlreadyExists => {
+                Err(BoardError::SeekerInUse)
+            }
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    fn fetch_messages(&self, seekers: &[Vec<u8>]) -> Result<Vec<MessageEntry>, BoardError> {
+        let mut messages = Vec::new();
+        for seeker in seekers {
+            match fs::read(self.message_path(seeker)) {
+                Ok(message) => messages.push((seeker.clone(), message)),
+                Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+                Err(error) => return Err(error.into()),
+            }
+        }
+        Ok(messages)
+    }
+
+    fn post_public_keys(&mut self, user_id: &UserId, public_keys: &[u8]) -> Result<(), BoardError> {
+        let temporary = self.write_temporary(public_keys)?;
+        fs::rename(&temporary, self.public_keys_path(user_id))?;
+        Ok(())
+    }
+
+    fn fetch_public_keys(&self, user_id: &UserId) -> Result<Option<Vec<u8>>, BoardError> {
+        match fs::read(self.public_keys_path(user_id)) {
+            Ok(public_keys) => Ok(Some(public_keys)),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+}
+
+/// Encodes bytes as lowercase hexadecimal.
+pub fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Returns an empty directory unique to this test run.
+    pub(crate) fn test_directory(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("echo-cli-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        path
+    }
+
+    #[test]
+    fn test_announcements_read_from_counter() {
+        let root = test_directory("announcements");
+        let mut board = FileBoard::open(&root).unwrap();
+        assert_eq!(board.post_announcement(b"first").unwrap(), 1);
+        assert_eq!(board.post_announcement(b"second").unwrap(), 2);
+
+        // a second handle on the same directory sees the same bulletin
+        let mut other = FileBoard::open(&root).unwrap();
+        assert_eq!(other.post_announcement(b"third").unwrap(), 3);
+
+        let all = board.fetch_announcements(0).unwrap();
+        let contents: Vec<&[u8]> = all.iter().map(|(_, data)| data.as_slice()).collect();
+        assert_eq!(contents, [&b"first"[..], b"second", b"third"]);
+        assert_eq!(
+            board.fetch_announcements(2).unwrap(),
+            [(3, b"third".to_vec())]
+        );
+        assert!(board.fetch_announcements(3).unwrap().is_empty());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_messages_keep_first_write() {
+        let root = test_directory("messages");
+        let mut board = FileBoard::open(&root).unwrap();
+        board.post_message(b"seeker", b"first").unwrap();
+        assert_eq!(
+            board.post_message(b"seeker", b"second"),
+            Err(BoardError::SeekerInUse)
+        );
+
+        let fetched = board
+            .fetch_messages(&[b"missing".to_vec(), b"seeker".to_vec()])
+            .unwrap();
+        assert_eq!(fetched, [(b"seeker".to_vec(), b"first".to_vec())]);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_public_keys_are_replaced() {
+        let root = test_directory("public-keys");
+        let mut board = FileBoard::open(&root).unwrap();
+        let user_id = UserId::from_bytes([7u8; 32]);
+        assert_eq!(board.fetch_public_keys(&user_id).unwrap(), None);
+
+        board.post_public_keys(&user_id, b"old").unwrap();
+        board.post_public_keys(&user_id, b"new").unwrap();
+        assert_eq!(
+            board.fetch_public_keys(&user_id).unwrap(),
+            Some(b"new".to_vec())
+        );
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_hex_encode() {
+        assert_eq!(hex_encode(&[0x00, 0x0f, 0xab, 0xff]), "000fabff");
+        assert_eq!(hex_encode(&[]), "");
+    }
+}
@@ -0,0 +1,382 @@
+//! A user of the protocol, reading and writing a board like the application does.
+
+use crate::board::{Board, BoardError};
+use crate::error::CliError;
+use crate::profile::{Identity, Keys, Profile};
+use auth::{PassphraseSalt, UserId, UserPublicKeys};
+use sessions::{AnnouncementError, SessionError, SessionManager, SessionStatus};
+
+/// Something that happened while reading the board.
+pub enum Event {
+    /// A peer requested a session, which `connect` accepts.
+    SessionRequested { peer: UserId, text: Vec<u8> },
+    /// A peer accepted our session request, or answered it with a new one.
+    SessionActive { peer: UserId, text: Vec<u8> },
+    /// A non-empty message was received over an active session.
+    Message { peer: UserId, text: Vec<u8> },
+    /// An announcement addressed to us was rejected.
+    AnnouncementRejected(AnnouncementError),
+    /// A message read under one of our seekers was rejected, closing its session.
+    MessageRejected(SessionError),
+}
+
+/// An unlocked profile connected to a board.
+pub struct Client<B: Board> {
+    profile: Profile,
+    identity: Identity,
+    keys: Keys,
+    manager: SessionManager,
+    board: B,
+}
+
+impl<B: Board> Client<B> {
+    /// Creates a new identity in `profile` and publishes its public keys on the board.
+    pub fn create(profile: Profile, passphrase: &str, board: B) -> Result<Self, CliError> {
+        let salt = PassphraseSalt::generate();
+        let keys = Keys::derive(passphrase, &salt);
+        let identity = Identity {
+            salt,
+            user_id: keys.public_keys.derive_id(),
+        };
+        profile.create_identity(&identity)?;
+
+        let manager = profile.load_sessions(&keys.state_key)?;
+        let mut client = Self {
+            profile,
+            identity,
+            keys,
+            manager,
+            board,
+        };
+        client.publish()?;
+        client.save()?;
+        Ok(client)
+    }
+
+    /// Unlocks the identity of `profile` and restores its sessions.
+    pub fn open(profile: Profile, passphrase: &str, board: B) -> Result<Self, CliError> {
+        let identity = profile.identity()?;
+        let keys = Keys::derive(passphrase, &identity.salt);
+        if keys.public_keys.derive_id() != identity.user_id {
+            return Err(CliError::WrongPassphrase);
+        }
+        let manager = profile.load_sessions(&keys.state_key)?;
+        Ok(Self {
+            profile,
+            identity,
+            keys,
+            manager,
+            board,
+        })
+    }
+
+    /// Returns the user ID of the identity.
+    pub fn user_id(&self) -> &UserId {
+        &self.identity.user_id
+    }
+
+    /// Publishes the public keys of the identity on the board.
+    pub fn publish(&mut self) -> Result<(), CliError> {
+        self.board
+            .post_public_keys(&self.identity.user_id, &self.keys.public_keys.to_bytes())?;
+        Ok(())
+    }
+
+    /// Requests a session with a peer, or accepts its request, and returns the bulletin
+    /// counter of the announcement.
+    ///
+    /// The keys of known peers are taken from the session manager, the others are fetched
+    /// from the board.
+    pub fn connect(&mut self, peer: &UserId, text: &[u8]) -> Result<u64, CliError> {
+        let peer_keys = match self.manager.peer_public_keys(peer) {
+            Some(peer_keys) => peer_keys,
+            None => self.fetch_public_keys(peer)?,
+        };
+        let announcement = self.manager.establish_outgoing_session(
+            &peer_keys,
+            &self.keys.public_keys,
+            &self.keys.secret_keys,
+            text.to_vec(),
+        );
+        let counter = self.board.post_announcement(&announcement)?;
+        self.save()?;
+        Ok(counter)
+    }
+
+    fn fetch_public_keys(&self, peer: &UserId) -> Result<UserPublicKeys, CliError> {
+        let bytes = self
+            .board
+            .fetch_public_keys(peer)?
+            .ok_or_else(|| CliError::UnknownPeer(peer.clone()))?;
+        let peer_keys = UserPublicKeys::from_bytes(&bytes)
+            .map_err(|_| BoardError::Malformed(format!("public keys of {peer}")))?;
+        // the board is not trusted to return the keys of the right user
+        if &peer_keys.derive_id() != peer {
+            return Err(CliError::UnknownPeer(peer.clone()));
+        }
+        Ok(peer_keys)
+    }
+
+    /// Returns the known peers and the status of their sessions.
+    pub fn peers(&self) -> Vec<(UserId, SessionStatus)> {
+        let mut peers = self.manager.peer_list();
+        peers.sort_unstable_by(|a, b| a.as_bytes().cmp(b.as_bytes()));
+        peers
+            .into_iter()
+            .map(|peer| {
+                let status = self.manager.peer_session_status(&peer);
+                (peer, status)
+            })
+            .collect()
+    }
+
+    /// Sends a message over the active session with a peer.
+    pub fn send(&mut self, peer: &UserId, text: &[u8]) -> Result<(), CliError> {
+        let output = self.manager.send_message(peer, text)?;
+        // the session advanced even if posting fails, so save it first
+        self.save()?;
+        self.board.post_message(&output.seeker, &output.data)?;
+        Ok(())
+    }
+
+    /// Reads the announcements posted since the last call, then the messages under our
+    /// seekers, until no new message arrives.
+    pub fn receive(&mut self) -> Result<Vec<Event>, CliError> {
+        let mut events = Vec::new();
+        self.read_announcements(&mut events)?;
+        self.read_messages(&mut events)?;
+        self.save()?;
+        Ok(events)
+    }
+
+    fn read_announcements(&mut self, events: &mut Vec<Event>) -> Result<(), CliError> {
+        let counter = self.profile.announcement_counter()?;
+        let announcements = self.board.fetch_announcements(counter)?;
+        let Some(last_counter) = announcements.last().map(|(counter, _)| *counter) else {
+            return Ok(());
+        };
+
+        for (_, announcement) in announcements {
+            match self.manager.feed_incoming_announcement(
+                &announcement,
+                &self.keys.public_keys,
+                &self.keys.secret_keys,
+            ) {
+                Ok(result) => {
+                    let peer = result.announcer_public_keys.derive_id();
+                    let text = result.user_data.clone();
+                    match self.manager.peer_session_status(&peer) {
+                        SessionStatus::PeerRequested => {
+                            events.push(Event::SessionRequested { peer, text });
+                        }
+                        _ => events.push(Event::SessionActive { peer, text }),
+                    }
+                }
+                // addressed to someone else, or already processed
+                Err(AnnouncementError::Undecryptable | AnnouncementError::Outdated) => {}
+                Err(error) => events.push(Event::AnnouncementRejected(error)),
+            }
+        }
+
+        // sessions are saved before the counter, so that a crash reads announcements again
+        // rather than skipping them
+        self.save()?;
+        self.profile.set_announcement_counter(last_counter)
+    }
+
+    fn read_messages(&mut self, events: &mut Vec<Event>) -> Result<(), CliError> {
+        loop {
+            let seekers = self.manager.get_message_board_read_keys();
+            let mut received = false;
+            for (seeker, message) in self.board.fetch_messages(&seekers)? {
+                match self.manager.feed_incoming_message_board_read(
+                    &seeker,
+                    &message,
+                    &self.keys.secret_keys,
+                ) {
+                    Ok(output) => {
+                        received = true;
+                        // keep-alive messages are empty
+                        if !output.message.is_empty() {
+                            let peer =
+                                UserId::from_bytes(output.user_id.as_slice().try_into().map_err(
+                                    |_| CliError::Session(SessionError::StateMalformed),
+                                )?);
+                            events.push(Event::Message {
+                                peer,
+                                text: output.message.clone(),
+                            });
+                        }
+                    }
+                    // the seeker moved on with an earlier message of this round
+                    Err(SessionError::UnknownSeeker) => {}
+                    Err(error) => events.push(Event::MessageRejected(error)),
+                }
+            }
+            // reading a message reveals the next seekers of its session
+            if !received {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Expires stale sessions and sends the keep-alive messages that are due, returning the
+    /// peers they were sent to.
+    pub fn refresh(&mut self) -> Result<Vec<UserId>, CliError> {
+        let mut peers = self.manager.refresh();
+        peers.sort_unstable_by(|a, b| a.as_bytes().cmp(b.as_bytes()));
+        let mut sent = Vec::new();
+        for peer in peers {
+            // a saturated session cannot send keep-alive messages until the peer responds
+            match self.send(&peer, &[]) {
+                Ok(()) => sent.push(peer),
+                Err(CliError::Session(_)) => {}
+                Err(error) => return Err(error),
+            }
+        }
+        self.save()?;
+        Ok(sent)
+    }
+
+    /// Posts the announcements that re-establish broken sessions, returning the peers they
+    /// were sent to.
+    pub fn heal(&mut self) -> Result<Vec<UserId>, CliError> {
+        let mut healing = self
+            .manager
+            .heal_sessions(&self.keys.public_keys, &self.keys.secret_keys);
+        healing.sort_unstable_by(|(a, _), (b, _)| a.as_bytes().cmp(b.as_bytes()));
+        let mut sent = Vec::new();
+        for (peer, announcement) in healing {
+            self.board.post_announcement(&announcement)?;
+            sent.push(peer);
+        }
+        self.save()?;
+        Ok(sent)
+    }
+
+    /// Forgets a peer and its session.
+    pub fn discard(&mut self, peer: &UserId) -> Result<(), CliError> {
+        self.manager.peer_discard(peer);
+        self.save()
+    }
+
+    /// Saves the session manager state to the profile.
+    pub fn save(&self) -> Result<(), CliError> {
+        self.profile
+            .save_sessions(&self.manager, &self.keys.state_key)
+    }
+}
+
+/// Returns the name of a session status, as printed by `peers`.
+pub fn status_name(status: &SessionStatus) -> &'static str {
+    match status {
+        SessionStatus::Active => "active",
+        SessionStatus::UnknownPeer => "unknown",
+        SessionStatus::NoSession => "no-session",
+        SessionStatus::PeerRequested => "peer-requested",
+        SessionStatus::SelfRequested => "self-requested",
+        SessionStatus::Killed => "killed",
+        SessionStatus::Saturated => "saturated",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::FileBoard;
+    use crate::board::tests::test_directory;
+
+    fn message_texts(events: &[Event]) -> Vec<(UserId, Vec<u8>)> {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                Event::Message { peer, text } => Some((peer.clone(), text.clone())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_two_clients_exchange_messages() {
+        let root = test_directory("client");
+        let board = || FileBoard::open(root.join("board")).unwrap();
+
+        let alice = Client::create(Profile::new(root.join("alice")), "alice", board()).unwrap();
+        let alice_id = alice.user_id().clone();
+        let bob = Client::create(Profile::new(root.join("bob")), "bob", board()).unwrap();
+        let bob_id = bob.user_id().clone();
+        drop(alice);
+        drop(bob);
+
+        // each command runs with a freshly opened client, like the binary does
+        let alice = || Client::open(Profile::new(root.join("alice")), "alice", board()).unwrap();
+        let bob = || Client::open(Profile::new(root.join("bob")), "bob", board()).unwrap();
+        assert!(matches!(
+            Client::open(Profile::new(root.join("alice")), "wrong", board()),
+            Err(CliError::WrongPassphrase)
+        ));
+
+        alice().connect(&bob_id, b"hi, it's alice").unwrap();
+        let events = bob().receive().unwrap();
+        assert!(matches!(
+            events.as_slice(),
+            [Event::SessionRequested { peer, text }]
+                if *peer == alice_id && text == b"hi, it's alice"
+        ));
+
+        bob().connect(&alice_id, b"").unwrap();
+        let events = alice().receive().unwrap();
+        assert!(matches!(
+            events.as_slice(),
+            [Event::SessionActive { peer, .. }] if *peer == bob_id
+        ));
+        let peers = alice().peers();
+        assert_eq!(peers.len(), 1);
+        assert_eq!(status_name(&peers[0].1), "active");
+
+        alice().send(&bob_id, b"first").unwrap();
+        alice().send(&bob_id, b"second").unwrap();
+        let events = bob().receive().unwrap();
+        assert_eq!(
+            message_texts(&events),
+            [
+                (alice_id.clone(), b"first".to_vec()),
+                (alice_id.clone(), b"second".to_vec())
+            ]
+        );
+        // messages and announcements are only read once
+        assert!(bob().receive().unwrap().is_empty());
+
+        bob().send(&alice_id, b"reply").unwrap();
+        let events = alice().receive().unwrap();
+        assert_eq!(
+            message_texts(&events),
+            [(bob_id.clone(), b"reply".to_vec())]
+        );
+
+        let mut bob = bob();
+        bob.discard(&alice_id).unwrap();
+        assert!(bob.peers().is_empty());
+        assert!(matches!(
+            bob.send(&alice_id, b"gone"),
+            Err(CliError::Session(_))
+        ));
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_connect_requires_published_keys() {
+        let root = test_directory("unknown-peer");
+        let board = FileBoard::open(root.join("board")).unwrap();
+        let mut alice = Client::create(Profile::new(root.join("alice")), "alice", board).unwrap();
+
+        let stranger = UserId::from_bytes([4u8; auth::USER_ID_SIZE]);
+        assert_eq!(
+            alice.connect(&stranger, b""),
+            Err(CliError::UnknownPeer(stranger))
+        );
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
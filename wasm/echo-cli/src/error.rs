@@ -0,0 +1,74 @@
+//! Errors reported by the command-line client.
+
+use crate::board::BoardError;
+use auth::UserId;
+use sessions::SessionError;
+use std::fmt;
+use std::path::PathBuf;
+
+/// Error reported by a command.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CliError {
+    /// The command line is invalid.
+    Usage(String),
+    /// A profile file could not be read or written.
+    Io(String),
+    /// The board backend failed.
+    Board(BoardError),
+    /// The profile directory holds no identity.
+    NoIdentity(PathBuf),
+    /// The profile directory already holds an identity.
+    IdentityExists(PathBuf),
+    /// A profile file cannot be parsed.
+    MalformedProfile(String),
+    /// The passphrase does not unlock the identity of the profile.
+    WrongPassphrase,
+    /// No public keys are published for this user.
+    UnknownPeer(UserId),
+    /// The session manager refused the operation.
+    Session(SessionError),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Usage(error) => write!(f, "{error}"),
+            Self::Io(error) => write!(f, "I/O error: {error}"),
+            Self::Board(error) => write!(f, "{error}"),
+            Self::NoIdentity(path) => write!(
+                f,
+                "no identity in {}, create one with `echo-cli init`",
+                path.display()
+            ),
+            Self::IdentityExists(path) => {
+                write!(f, "an identity already exists in {}", path.display())
+            }
+            Self::MalformedProfile(error) => write!(f, "malformed profile: {error}"),
+            Self::WrongPassphrase => write!(f, "wrong passphrase for this identity"),
+            Self::UnknownPeer(user_id) => {
+                write!(f, "no public keys are published for {user_id}")
+            }
+            Self::Session(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+impl From<std::io::Error> for CliError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error.to_string())
+    }
+}
+
+impl From<BoardError> for CliError {
+    fn from(error: BoardError) -> Self {
+        Self::Board(error)
+    }
+}
+
+impl From<SessionError> for CliError {
+    fn from(error: SessionError) -> Self {
+        Self::Session(error)
+    }
+}
@@ -0,0 +1,202 @@
+//! A [`Board`] behind the REST message protocol of the web application.
+
+use crate::board::{Board, BoardError, MessageEntry};
+use auth::UserId;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use std::time::Duration;
+
+/// Timeout of each request, like the web application
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Deserialize)]
+struct AnnouncementCounter {
+    counter: String,
+}
+
+#[derive(Deserialize)]
+struct KeyValue {
+    key: String,
+    value: String,
+}
+
+#[derive(Deserialize)]
+struct Value {
+    value: String,
+}
+
+/// A [`Board`] served over HTTP, by `echo-server` or by the API used by the web application.
+pub struct HttpBoard {
+    /// URL the endpoints are relative to, such as `http://127.0.0.1:3000/api`
+    base_url: String,
+    agent: ureq::Agent,
+}
+
+impl HttpBoard {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            agent: ureq::AgentBuilder::new().timeout(REQUEST_TIMEOUT).build(),
+        }
+    }
+
+    fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, BoardError> {
+        let response = self
+            .agent
+            .get(&format!("{}{path}", self.base_url))
+            .call()
+            .map_err(request_error)?;
+        response.into_json().map_err(malformed_response)
+    }
+
+    fn post<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: serde_json::Value,
+    ) -> Result<T, BoardError> {
+        let response = self
+            .agent
+            .post(&format!("{}{path}", self.base_url))
+            .send_json(body)
+            .map_err(request_error)?;
+        response.into_json().map_err(malformed_response)
+    }
+}
+
+fn request_error(error: ureq::Error) -> BoardError {
+    match error {
+        ureq::Error::Status(409, _) => BoardError::SeekerInUse,
+        error => BoardError::Io(error.to_string()),
+    }
+}
+
+fn malformed_response(error: std::io::Error) -> BoardError {
+    BoardError::Malformed(format!("response: {error}"))
+}
+
+fn decode(value: &str) -> Result<Vec<u8>, BoardError> {
+    BASE64
+        .decode(value)
+        .map_err(|_| BoardError::Malformed("response holds invalid base64".to_string()))
+}
+
+impl Board for HttpBoard {
+    fn post_announcement(&mut self, announcement: &[u8]) -> Result<u64, BoardError> {
+        let response: AnnouncementCounter =
+            self.post("/bulletin", json!({ "data": BASE64.encode(announcement) }))?;
+        response
+            .counter
+            .parse()
+            .map_err(|_| BoardError::Malformed(format!("bulletin counter `{}`", response.counter)))
+    }
+
+    fn fetch_announcements(&self, counter: u64) -> Result<Vec<(u64, Vec<u8>)>, BoardError> {
+        let announcements: Vec<String> = self.get(&format!("/bulletin?after={counter}"))?;
+        (counter + 1..)
+            .zip(announcements)
+            .map(|(posted, announcement)| Ok((posted, decode(&announcement)?)))
+            .collect()
+    }
+
+    fn post_message(&mut self, seeker: &[u8], message: &[u8]) -> Result<(), BoardError> {
+        let _: serde_json::Value = self.post(
+            "/messages/",
+            json!({ "key": BASE64.encode(seeker), "value": BASE64.encode(message) }),
+        )?;
+        Ok(())
+    }
+
+    fn fetch_messages(&self, seekers: &[Vec<u8>]) -> Result<Vec<MessageEntry>, BoardError> {
+        let seekers: Vec<String> = seekers.iter().map(|seeker| BASE64.encode(seeker)).collect();
+        let messages: Vec<KeyValue> =
+            self.post("/messages/fetch", json!({ "seekers": seekers }))?;
+        messages
+            .into_iter()
+            .map(|message| Ok((decode(&message.key)?, decode(&message.value)?)))
+            .collect()
+    }
+
+    /// The server derives the user ID from the public keys, so `_user_id` is not sent.
+    fn post_public_keys(
+        &mut self,
+        _user_id: &UserId,
+        public_keys: &[u8],
+    ) -> Result<(), BoardError> {
+        let _: Value = self.post("/auth", json!({ "value": BASE64.encode(public_keys) }))?;
+        Ok(())
+    }
+
+    fn fetch_public_keys(&self, user_id: &UserId) -> Result<Option<Vec<u8>>, BoardError> {
+        let response = self
+            .agent
+            .post(&format!("{}/auth/retrieve", self.base_url))
+            .send_json(json!({ "key": BASE64.encode(user_id.as_bytes()) }));
+        match response {
+            Ok(response) => {
+                let response: Value = response.into_json().map_err(malformed_response)?;
+                Ok(Some(decode(&response.value)?))
+            }
+            Err(ureq::Error::Status(404, _)) => Ok(None),
+            Err(error) => Err(request_error(error)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use echo_server::{MemoryStore, Server};
+
+    #[test]
+    fn test_http_board_against_local_server() {
+        let server = Server::bind("127.0.0.1:0", MemoryStore::new())
+            .unwrap()
+            .spawn();
+        let mut board = HttpBoard::new(&server.base_url());
+
+        assert!(board.fetch_announcements(0).unwrap().is_empty());
+        assert_eq!(board.post_announcement(b"first").unwrap(), 1);
+        assert_eq!(board.post_announcement(b"second").unwrap(), 2);
+        assert_eq!(
+            board.fetch_announcements(1).unwrap(),
+            [(2, b"second".to_vec())]
+        );
+
+        board.post_message(b"seeker", b"message").unwrap();
+        assert_eq!(
+            board.post_message(b"seeker", b"other"),
+            Err(BoardError::SeekerInUse)
+        );
+        assert_eq!(
+            board
+                .fetch_messages(&[b"missing".to_vec(), b"seeker".to_vec()])
+                .unwrap(),
+            [(b"seeker".to_vec(), b"message".to_vec())]
+        );
+
+        let root_secret = auth::StaticRootSecret::from_bytes([2u8; auth::STATIC_ROOT_SECRET_SIZE]);
+        let (public_keys, _) = auth::derive_keys_from_static_root_secret(&root_secret);
+        let user_id = public_keys.derive_id();
+        assert_eq!(board.fetch_public_keys(&user_id).unwrap(), None);
+        board
+            .post_public_keys(&user_id, &public_keys.to_bytes())
+            .unwrap();
+        assert_eq!(
+            board.fetch_public_keys(&user_id).unwrap(),
+            Some(public_keys.to_bytes())
+        );
+    }
+
+    #[test]
+    fn test_http_board_reports_unreachable_server() {
+        // nothing listens on the discard port
+        let board = HttpBoard::new("http://127.0.0.1:9/api");
+        assert!(matches!(
+            board.fetch_announcements(0),
+            Err(BoardError::Io(_))
+        ));
+    }
+}
@@ -0,0 +1,171 @@
+//! # echo-cli
+//!
+//! A native command-line client for the Echo protocol, to debug the protocol and script
+//! regression tests without a browser.
+//!
+//! Each run unlocks a profile directory with the passphrase, runs one command against a
+//! board and saves the session state back to the profile. Boards are directories shared by
+//! the clients ([`board::FileBoard`]), or board servers such as `echo-server`
+//! ([`http_board::HttpBoard`]), given by their base URL:
+//!
+//! ```text
+//! $ echo-server &
+//! $ export ECHO_BOARD=http://127.0.0.1:3000/api
+//! ```
+//!
+//! ```text
+//! $ export ECHO_BOARD=/tmp/board
+//! $ ECHO_PASSPHRASE=alice echo-cli --profile alice init
+//! gossip1...alice
+//! $ ECHO_PASSPHRASE=bob echo-cli --profile bob init
+//! gossip1...bob
+//! $ ECHO_PASSPHRASE=alice echo-cli --profile alice connect gossip1...bob hello
+//! $ ECHO_PASSPHRASE=bob echo-cli --profile bob receive
+//! request gossip1...alice hello
+//! $ ECHO_PASSPHRASE=bob echo-cli --profile bob connect gossip1...alice
+//! $ ECHO_PASSPHRASE=alice echo-cli --profile alice receive
+//! active gossip1...bob
+//! $ ECHO_PASSPHRASE=alice echo-cli --profile alice send gossip1...bob how are you?
+//! $ ECHO_PASSPHRASE=bob echo-cli --profile bob receive
+//! message gossip1...alice how are you?
+//! ```
+
+mod args;
+mod board;
+mod client;
+mod error;
+mod http_board;
+mod profile;
+
+use args::{Command, USAGE};
+use board::{Board, FileBoard};
+use client::{Client, Event, status_name};
+use error::CliError;
+use http_board::HttpBoard;
+use profile::Profile;
+use std::io::BufRead;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+/// Profile directory used when neither `--profile` nor `$ECHO_PROFILE` is set
+const DEFAULT_PROFILE: &str = "echo-profile";
+
+/// Number of words of the mnemonics generated by `init --mnemonic`
+const MNEMONIC_WORD_COUNT: usize = 24;
+
+fn main() -> ExitCode {
+    match run(std::env::args().skip(1)) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("echo-cli: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: impl IntoIterator<Item = String>) -> Result<(), CliError> {
+    let options = args::parse(args)?;
+    let profile = Profile::new(
+        options
+            .profile
+            .or_else(|| std::env::var_os("ECHO_PROFILE").map(PathBuf::from))
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_PROFILE)),
+    );
+
+    let board = options.board;
+    let open = |profile| Client::open(profile, &read_passphrase()?, open_board(board.clone())?);
+
+    match options.command {
+        Command::Help => print!("{USAGE}"),
+        Command::Whoami => println!("{}", profile.identity()?.user_id),
+        Command::Init { mnemonic } => {
+            let board = open_board(board)?;
+            let client = if mnemonic {
+                let mnemonic = auth::Mnemonic::generate(MNEMONIC_WORD_COUNT)
+                    .map_err(|error| CliError::Usage(error.to_string()))?;
+                eprintln!("mnemonic (use it as passphrase): {}", mnemonic.phrase());
+                Client::create(profile, mnemonic.phrase(), board)?
+            } else {
+                Client::create(profile, &read_passphrase()?, board)?
+            };
+            println!("{}", client.user_id());
+        }
+        Command::Publish => open(profile)?.publish()?,
+        Command::Connect { peer, text } => {
+            open(profile)?.connect(&peer, text.as_bytes())?;
+        }
+        Command::Peers => {
+            for (peer, status) in open(profile)?.peers() {
+                println!("{peer} {}", status_name(&status));
+            }
+        }
+        Command::Send { peer, text } => open(profile)?.send(&peer, text.as_bytes())?,
+        Command::Receive => {
+            for event in open(profile)?.receive()? {
+                print_event(&event);
+            }
+        }
+        Command::Refresh => {
+            let mut client = open(profile)?;
+            for peer in client.refresh()? {
+                println!("keep-alive {peer}");
+            }
+            for peer in client.heal()? {
+                println!("reconnect {peer}");
+            }
+        }
+        Command::Discard { peer } => open(profile)?.discard(&peer)?,
+    }
+    Ok(())
+}
+
+/// Opens the board given on the command line, or in `$ECHO_BOARD`.
+fn open_board(board: Option<String>) -> Result<Box<dyn Board>, CliError> {
+    let board = board
+        .or_else(|| std::env::var("ECHO_BOARD").ok())
+        .ok_or_else(|| CliError::Usage("no board given, set --board or $ECHO_BOARD".to_string()))?;
+    if board.starts_with("http://") {
+        Ok(Box::new(HttpBoard::new(&board)))
+    } else {
+        Ok(Box::new(FileBoard::open(board)?))
+    }
+}
+
+/// Reads the passphrase from `$ECHO_PASSPHRASE`, or from the first line of standard input.
+fn read_passphrase() -> Result<String, CliError> {
+    let passphrase = match std::env::var("ECHO_PASSPHRASE") {
+        Ok(passphrase) => passphrase,
+        Err(_) => {
+            let mut line = String::new();
+            std::io::stdin().lock().read_line(&mut line)?;
+            line.trim_end_matches(['\r', '\n']).to_string()
+        }
+    };
+    if passphrase.is_empty() {
+        return Err(CliError::Usage("empty passphrase".to_string()));
+    }
+    Ok(passphrase)
+}
+
+/// Prints an event of `receive` on one line: events on standard output, rejections on
+/// standard error.
+fn print_event(event: &Event) {
+    let (kind, peer, text) = match event {
+        Event::SessionRequested { peer, text } => ("request", peer, text),
+        Event::SessionActive { peer, text } => ("active", peer, text),
+        Event::Message { peer, text } => ("message", peer, text),
+        Event::AnnouncementRejected(error) => {
+            eprintln!("echo-cli: rejected an announcement: {error}");
+            return;
+        }
+        Event::MessageRejected(error) => {
+            eprintln!("echo-cli: rejected a message, closing its session: {error}");
+            return;
+        }
+    };
+    if text.is_empty() {
+        println!("{kind} {peer}");
+    } else {
+        println!("{kind} {peer} {}", String::from_utf8_lossy(text));
+    }
+}
@@ -0,0 +1,248 @@
+//! The local profile of a user: identity, session state and board position.
+//!
+//! A profile is a directory holding:
+//!
+//! ```text
+//! identity               passphrase salt and user ID, in text form
+//! sessions               session manager state, encrypted with a key derived from the
+//!                        root secret
+//! announcement_counter   bulletin counter of the last announcement read
+//! ```
+//!
+//! Secret keys are never written: they are derived again from the passphrase on each run.
+
+use crate::error::CliError;
+use auth::{PassphraseSalt, StaticRootSecret, UserId, UserPublicKeys, UserSecretKeys};
+use sessions::{SessionManager, SessionManagerConfig};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Salt of the derivation of the state key from the root secret
+const STATE_KEY_SALT: &[u8] = b"echo-cli.state-key.salt";
+
+/// Info of the derivation of the state key from the root secret
+const STATE_KEY_INFO: &[u8] = b"echo-cli.state-key";
+
+/// Session manager configuration, matching the defaults of the web application.
+pub fn session_config() -> SessionManagerConfig {
+    SessionManagerConfig {
+        max_incoming_announcement_age_millis: 604_800_000,
+        max_incoming_announcement_future_millis: 60_000,
+        max_incoming_message_age_millis: 604_800_000,
+        max_incoming_message_future_millis: 60_000,
+        max_session_inactivity_millis: 604_800_000,
+        keep_alive_interval_millis: 86_400_000,
+        max_session_lag_length: 10_000,
+        max_skipped_messages: 16,
+        max_stored_skipped_messages: 128,
+    }
+}
+
+/// The public part of an identity, stored in the profile.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Identity {
+    /// Salt of the passphrase derivation
+    pub salt: PassphraseSalt,
+    /// User ID derived from the passphrase
+    pub user_id: UserId,
+}
+
+impl Identity {
+    fn to_text(&self) -> String {
+        format!("salt = {}\nuser_id = {}\n", self.salt, self.user_id)
+    }
+
+    fn from_text(text: &str) -> Result<Self, CliError> {
+        let mut salt = None;
+        let mut user_id = None;
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            let malformed = || CliError::MalformedProfile(format!("identity line `{line}`"));
+            let (name, value) = line.split_once('=').ok_or_else(malformed)?;
+            match name.trim() {
+                "salt" => salt = Some(value.trim().parse().map_err(|_| malformed())?),
+                "user_id" => user_id = Some(value.trim().parse().map_err(|_| malformed())?),
+                _ => return Err(malformed()),
+            }
+        }
+        match (salt, user_id) {
+            (Some(salt), Some(user_id)) => Ok(Self { salt, user_id }),
+            _ => Err(CliError::MalformedProfile(
+                "identity is missing its salt or user ID".to_string(),
+            )),
+        }
+    }
+}
+
+/// Keys derived from the passphrase of an identity.
+pub struct Keys {
+    pub public_keys: UserPublicKeys,
+    pub secret_keys: UserSecretKeys,
+    /// Key encrypting the session manager state
+    pub state_key: crypto_aead::Key,
+}
+
+impl Keys {
+    /// Derives the keys of a passphrase, or of a mnemonic typed as passphrase.
+    pub fn derive(passphrase: &str, salt: &PassphraseSalt) -> Self {
+        let root_secret = match passphrase.parse::<auth::Mnemonic>() {
+            Ok(mnemonic) => StaticRootSecret::from_salted_mnemonic(&mnemonic, salt),
+            Err(_) => StaticRootSecret::from_salted_passphrase(passphrase.as_bytes(), salt),
+        };
+        let (public_keys, secret_keys) = auth::derive_keys_from_static_root_secret(&root_secret);
+
+        let mut extract = crypto_kdf::Extract::new(STATE_KEY_SALT);
+        extract.input_item(root_secret.as_slice());
+        let mut state_key = [0u8; crypto_aead::KEY_SIZE];
+        extract.finalize().expand(STATE_KEY_INFO, &mut state_key);
+
+        Self {
+            public_keys,
+            secret_keys,
+            state_key: crypto_aead::Key::from(state_key),
+        }
+    }
+}
+
+/// A profile directory.
+pub struct Profile {
+    root: PathBuf,
+}
+
+impl Profile {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn identity_path(&self) -> PathBuf {
+        self.root.join("identity")
+    }
+
+    fn sessions_path(&self) -> PathBuf {
+        self.root.join("sessions")
+    }
+
+    fn counter_path(&self) -> PathBuf {
+        self.root.join("announcement_counter")
+    }
+
+    /// Stores a new identity, failing if the profile already holds one.
+    pub fn create_identity(&self, identity: &Identity) -> Result<(), CliError> {
+        if self.identity_path().exists() {
+            return Err(CliError::IdentityExists(self.root.clone()));
+        }
+        fs::create_dir_all(&self.root)?;
+        write_atomically(&self.identity_path(), identity.to_text().as_bytes())?;
+        Ok(())
+    }
+
+    /// Reads the identity of the profile.
+    pub fn identity(&self) -> Result<Identity, CliError> {
+        match fs::read_to_string(self.identity_path()) {
+            Ok(text) => Identity::from_text(&text),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                Err(CliError::NoIdentity(self.root.clone()))
+            }
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Reads the session manager state, or starts a new one if none was saved yet.
+    pub fn load_sessions(&self, key: &crypto_aead::Key) -> Result<SessionManager, CliError> {
+        match fs::read(self.sessions_path()) {
+            Ok(blob) => Ok(SessionManager::from_encrypted_blob(&blob, key)?),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                Ok(SessionManager::new(session_config()))
+            }
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Saves the session manager state.
+    pub fn save_sessions(
+        &self,
+        manager: &SessionManager,
+        key: &crypto_aead::Key,
+    ) -> Result<(), CliError> {
+        let blob = manager.to_encrypted_blob(key).ok_or_else(|| {
+            CliError::MalformedProfile("the session state cannot be encrypted".to_string())
+        })?;
+        write_atomically(&self.sessions_path(), &blob)?;
+        Ok(())
+    }
+
+    /// Reads the bulletin counter of the last announcement read, 0 if none was read yet.
+    pub fn announcement_counter(&self) -> Result<u64, CliError> {
+        match fs::read_to_string(self.counter_path()) {
+            Ok(text) => text.trim().parse().map_err(|_| {
+                CliError::MalformedProfile(format!("announcement counter `{}`", text.trim()))
+            }),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(0),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Saves the bulletin counter of the last announcement read.
+    pub fn set_announcement_counter(&self, counter: u64) -> Result<(), CliError> {
+        write_atomically(&self.counter_path(), format!("{counter}\n").as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Replaces a file, so that an interrupted write leaves the previous contents intact.
+fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    fs::write(&temporary, contents)?;
+    fs::rename(&temporary, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::tests::test_directory;
+
+    #[test]
+    fn test_identity_text_roundtrip() {
+        let identity = Identity {
+            salt: PassphraseSalt::from_bytes([3u8; auth::PASSPHRASE_SALT_SIZE]),
+            user_id: UserId::from_bytes([9u8; auth::USER_ID_SIZE]),
+        };
+        let text = identity.to_text();
+        assert!(text.starts_with("salt = gossipsalt1"));
+        assert_eq!(Identity::from_text(&text), Ok(identity));
+
+        assert!(matches!(
+            Identity::from_text("salt = gossipsalt1invalid\n"),
+            Err(CliError::MalformedProfile(_))
+        ));
+        assert!(matches!(
+            Identity::from_text("unknown = 1\n"),
+            Err(CliError::MalformedProfile(_))
+        ));
+    }
+
+    #[test]
+    fn test_profile_files() {
+        let root = test_directory("profile");
+        let profile = Profile::new(&root);
+        assert_eq!(profile.identity(), Err(CliError::NoIdentity(root.clone())));
+        assert_eq!(profile.announcement_counter(), Ok(0));
+
+        let identity = Identity {
+            salt: PassphraseSalt::generate(),
+            user_id: UserId::from_bytes([1u8; auth::USER_ID_SIZE]),
+        };
+        profile.create_identity(&identity).unwrap();
+        assert_eq!(profile.identity(), Ok(identity.clone()));
+        assert_eq!(
+            profile.create_identity(&identity),
+            Err(CliError::IdentityExists(root.clone()))
+        );
+
+        profile.set_announcement_counter(42).unwrap();
+        assert_eq!(profile.announcement_counter(), Ok(42));
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,374 @@
+//! The HTTP endpoints, independent of the transport.
+//!
+//! They follow the REST message protocol of the web application
+//! (`src/api/messageProtocol/rest.ts`): JSON bodies, with binary data in standard base64.
+
+use crate::store::{Store, StoreError};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// Prefix of every endpoint.
+pub const API_PREFIX: &str = "/api";
+
+/// Maximum size of a request body in bytes.
+pub const MAX_BODY_SIZE: usize = 1 << 20;
+
+/// A response to send back: an HTTP status and a JSON body.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Response {
+    pub status: u16,
+    pub body: String,
+}
+
+impl Response {
+    fn json(value: &impl Serialize) -> Self {
+        Self {
+            status: 200,
+            body: serde_json::to_string(value).expect("JSON responses always serialize"),
+        }
+    }
+
+    fn error(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            body: serde_json::json!({ "error": message.into() }).to_string(),
+        }
+    }
+
+    fn from_store_error(error: StoreError) -> Self {
+        match error {
+            StoreError::SeekerInUse => Self::error(409, error.to_string()),
+            StoreError::Backend(_) => Self::error(500, error.to_string()),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct PostAnnouncement {
+    data: String,
+}
+
+#[derive(Serialize)]
+struct AnnouncementCounter {
+    counter: String,
+}
+
+#[derive(Deserialize, Serialize)]
+struct KeyValue {
+    key: String,
+    value: String,
+}
+
+#[derive(Deserialize)]
+struct FetchMessages {
+    seekers: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Key {
+    key: String,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Value {
+    value: String,
+}
+
+/// Handles a request to `url` (path and query) and returns the response.
+pub fn handle(store: &mut dyn Store, method: &str, url: &str, body: &[u8]) -> Response {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let Some(route) = path.strip_prefix(API_PREFIX) else {
+        return Response::error(404, "not found");
+    };
+    let result = match (method, route.trim_end_matches('/')) {
+        ("GET", "/bulletin") => fetch_announcements(store, query),
+        ("POST", "/bulletin") => post_announcement(store, body),
+        ("POST", "/messages") => post_message(store, body),
+        ("POST", "/messages/fetch") => fetch_messages(store, body),
+        ("POST", "/auth") => post_public_keys(store, body),
+        ("POST", "/auth/retrieve") => fetch_public_keys(store, body),
+        (_, "/bulletin" | "/messages" | "/messages/fetch" | "/auth" | "/auth/retrieve") => {
+            Err(Response::error(405, "method not allowed"))
+        }
+        _ => Err(Response::error(404, "not found")),
+    };
+    result.unwrap_or_else(|response| response)
+}
+
+fn parse_body<T: DeserializeOwned>(body: &[u8]) -> Result<T, Response> {
+    serde_json::from_slice(body)
+        .map_err(|error| Response::error(400, format!("invalid request body: {error}")))
+}
+
+fn decode(field: &str, value: &str) -> Result<Vec<u8>, Response> {
+    BASE64
+        .decode(value)
+        .map_err(|_| Response::error(400, format!("`{field}` is not valid base64")))
+}
+
+/// `GET /bulletin[?after=COUNTER]`: announcements, after the first `COUNTER` ones if given.
+fn fetch_announcements(store: &mut dyn Store, query: &str) -> Result<Response, Response> {
+    let mut after = 0;
+    for parameter in query.split('&').filter(|parameter| !parameter.is_empty()) {
+        match parameter.split_once('=') {
+            Some(("after", value)) => {
+                after = value
+                    .parse()
+                    .map_err(|_| Response::error(400, "`after` is not a counter"))?;
+            }
+            _ => return Err(Response::error(400, "unknown query parameter")),
+        }
+    }
+    let announcements = store
+        .announcements_after(after)
+        .map_err(Response::from_store_error)?;
+    let encoded: Vec<String> = announcements
+        .iter()
+        .map(|announcement| BASE64.encode(announcement))
+        .collect();
+    Ok(Response::json(&encoded))
+}
+
+/// `POST /bulletin {data}`: appends an announcement, returns `{counter}`.
+fn post_announcement(store: &mut dyn Store, body: &[u8]) -> Result<Response, Response> {
+    let request: PostAnnouncement = parse_body(body)?;
+    let announcement = decode("data", &request.data)?;
+    let counter = store
+        .post_announcement(&announcement)
+        .map_err(Response::from_store_error)?;
+    Ok(Response::json(&AnnouncementCounter {
+        counter: counter.to_string(),
+    }))
+}
+
+/// `POST /messages/ {key, value}`: stores a message under its seeker.
+fn post_message(store: &mut dyn Store, body: &[u8]) -> Result<Response, Response> {
+    let request: KeyValue = parse_body(body)?;
+    let seeker = decode("key", &request.key)?;
+    let message = decode("value", &request.value)?;
+    store
+        .post_message(&seeker, &message)
+        .map_err(Response::from_store_error)?;
+    Ok(Response::json(&serde_json::json!({})))
+}
+
+/// `POST /messages/fetch {seekers}`: returns the `[{key, value}]` messages under `seekers`.
+fn fetch_messages(store: &mut dyn Store, body: &[u8]) -> Result<Response, Response> {
+    let request: FetchMessages = parse_body(body)?;
+    let seekers = request
+        .seekers
+        .iter()
+        .map(|seeker| decode("seekers", seeker))
+        .collect::<Result<Vec<_>, _>>()?;
+    let messages = store
+        .fetch_messages(&seekers)
+        .map_err(Response::from_store_error)?;
+    let encoded: Vec<KeyValue> = messages
+        .iter()
+        .map(|(seeker, message)| KeyValue {
+            key: BASE64.encode(seeker),
+            value: BASE64.encode(message),
+        })
+        .collect();
+    Ok(Response::json(&encoded))
+}
+
+/// `POST /auth {value}`: stores public keys under the user ID derived from them, returns the
+/// user ID in hex as `{value}`.
+fn post_public_keys(store: &mut dyn Store, body: &[u8]) -> Result<Response, Response> {
+    let request: Value = parse_body(body)?;
+    let public_keys = decode("value", &request.value)?;
+    let user_id = auth::UserPublicKeys::from_bytes(&public_keys)
+        .map_err(|_| Response::error(400, "`value` is not a set of public keys"))?
+        .derive_id();
+    store
+        .post_public_keys(user_id.as_bytes(), &public_keys)
+        .map_err(Response::from_store_error)?;
+    Ok(Response::json(&Value {
+        value: hex_encode(user_id.as_bytes()),
+    }))
+}
+
+/// `POST /auth/retrieve {key}`: returns the public keys of a user ID as `{value}`.
+fn fetch_public_keys(store: &mut dyn Store, body: &[u8]) -> Result<Response, Response> {
+    let request: Key = parse_body(body)?;
+    let user_id = decode("key", &request.key)?;
+    match store
+        .public_keys(&user_id)
+        .map_err(Response::from_store_error)?
+    {
+        Some(public_keys) => Ok(Response::json(&Value {
+            value: BASE64.encode(public_keys),
+        })),
+        None => Err(Response::error(404, "public key not found")),
+    }
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::MemoryStore;
+    use serde_json::{Value as Json, json};
+
+    fn request(store: &mut MemoryStore, method: &str, url: &str, body: Json) -> (u16, Json) {
+        let body = if body.is_null() {
+            Vec::new()
+        } else {
+            body.to_string().into_bytes()
+        };
+        let response = handle(store, method, url, &body);
+        (
+            response.status,
+            serde_json::from_str(&response.body).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_bulletin() {
+        let mut store = MemoryStore::new();
+        let (status, body) = request(&mut store, "GET", "/api/bulletin", Json::Null);
+        assert_eq!((status, body), (200, json!([])));
+
+        let first = BASE64.encode(b"first");
+        let second = BASE64.encode(b"second");
+        let (status, body) = request(
+            &mut store,
+            "POST",
+            "/api/bulletin",
+            json!({ "data": first }),
+        );
+        assert_eq!((status, body), (200, json!({ "counter": "1" })));
+        let (_, body) = request(
+            &mut store,
+            "POST",
+            "/api/bulletin",
+            json!({ "data": second }),
+        );
+        assert_eq!(body, json!({ "counter": "2" }));
+
+        let (_, body) = request(&mut store, "GET", "/api/bulletin", Json::Null);
+        assert_eq!(body, json!([first, second]));
+        let (_, body) = request(&mut store, "GET", "/api/bulletin?after=1", Json::Null);
+        assert_eq!(body, json!([second]));
+
+        let (status, _) = request(&mut store, "GET", "/api/bulletin?after=x", Json::Null);
+        assert_eq!(status, 400);
+        let (status, _) = request(
+            &mut store,
+            "POST",
+            "/api/bulletin",
+            json!({ "data": "not base64!" }),
+        );
+        assert_eq!(status, 400);
+    }
+
+    #[test]
+    fn test_messages() {
+        let mut store = MemoryStore::new();
+        let seeker = BASE64.encode(b"seeker");
+        let message = BASE64.encode(b"message");
+
+        // the web application posts to `/messages/`, with a trailing slash
+        let (status, _) = request(
+            &mut store,
+            "POST",
+            "/api/messages/",
+            json!({ "key": seeker, "value": message }),
+        );
+        assert_eq!(status, 200);
+        // retries of the same message succeed, other messages are refused
+        let (status, _) = request(
+            &mut store,
+            "POST",
+            "/api/messages",
+            json!({ "key": seeker, "value": message }),
+        );
+        assert_eq!(status, 200);
+        let (status, _) = request(
+            &mut store,
+            "POST",
+            "/api/messages/",
+            json!({ "key": seeker, "value": BASE64.encode(b"other") }),
+        );
+        assert_eq!(status, 409);
+
+        let missing = BASE64.encode(b"missing");
+        let (status, body) = request(
+            &mut store,
+            "POST",
+            "/api/messages/fetch",
+            json!({ "seekers": [missing, seeker] }),
+        );
+        assert_eq!(
+            (status, body),
+            (200, json!([{ "key": seeker, "value": message }]))
+        );
+    }
+
+    #[test]
+    fn test_public_keys() {
+        let mut store = MemoryStore::new();
+        let root_secret = auth::StaticRootSecret::from_bytes([1u8; auth::STATIC_ROOT_SECRET_SIZE]);
+        let (public_keys, _) = auth::derive_keys_from_static_root_secret(&root_secret);
+        let user_id = public_keys.derive_id();
+        let encoded_keys = BASE64.encode(public_keys.to_bytes());
+        let encoded_id = BASE64.encode(user_id.as_bytes());
+
+        let (status, _) = request(
+            &mut store,
+            "POST",
+            "/api/auth/retrieve",
+            json!({ "key": encoded_id }),
+        );
+        assert_eq!(status, 404);
+
+        let (status, body) = request(
+            &mut store,
+            "POST",
+            "/api/auth",
+            json!({ "value": encoded_keys }),
+        );
+        let hex = hex_encode(user_id.as_bytes());
+        assert_eq!((status, body), (200, json!({ "value": hex })));
+
+        let (status, body) = request(
+            &mut store,
+            "POST",
+            "/api/auth/retrieve",
+            json!({ "key": encoded_id }),
+        );
+        assert_eq!((status, body), (200, json!({ "value": encoded_keys })));
+
+        let (status, _) = request(
+            &mut store,
+            "POST",
+            "/api/auth",
+            json!({ "value": BASE64.encode(b"garbage") }),
+        );
+        assert_eq!(status, 400);
+    }
+
+    #[test]
+    fn test_unknown_routes() {
+        let mut store = MemoryStore::new();
+        assert_eq!(request(&mut store, "GET", "/bulletin", Json::Null).0, 404);
+        assert_eq!(
+            request(&mut store, "GET", "/api/unknown", Json::Null).0,
+            404
+        );
+        assert_eq!(
+            request(&mut store, "GET", "/api/messages", Json::Null).0,
+            405
+        );
+        assert_eq!(
+            request(&mut store, "POST", "/api/bulletin", json!([])).0,
+            400
+        );
+    }
+}
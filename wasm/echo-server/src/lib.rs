@@ -0,0 +1,47 @@
+//! # echo-server
+//!
+//! A local stand-in for the message and announcement boards, so that the web application,
+//! `echo-cli` and integration tests can run against a fully local stack.
+//!
+//! It serves the endpoints of the REST message protocol of the web application, under
+//! [`API_PREFIX`]:
+//!
+//! | Endpoint                   | Request body  | Response                                  |
+//! |----------------------------|---------------|-------------------------------------------|
+//! | `GET /bulletin[?after=N]`  |               | announcements after the first `N`         |
+//! | `POST /bulletin`           | `{data}`      | `{counter}`, the bulletin counter         |
+//! | `POST /messages/`          | `{key, value}`| `{}`, 409 if the seeker holds another one |
+//! | `POST /messages/fetch`     | `{seekers}`   | `[{key, value}]`                          |
+//! | `POST /auth`               | `{value}`     | `{value}`, the user ID in hex             |
+//! | `POST /auth/retrieve`      | `{key}`       | `{value}`, 404 if unknown                 |
+//!
+//! Binary data is encoded in standard base64. The storage semantics are documented on
+//! [`Store`]; announcements and messages are kept in memory ([`MemoryStore`]) or in a SQLite
+//! database (`SqliteStore`, behind the default `sqlite` feature).
+//!
+//! To point the web application at a local server, set
+//! `VITE_PROTOCOL_API_URL=http://127.0.0.1:3000`.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use echo_server::{MemoryStore, Server};
+//!
+//! let server = Server::bind("127.0.0.1:0", MemoryStore::new())
+//!     .expect("Failed to bind the server")
+//!     .spawn();
+//! println!("listening on {}", server.base_url());
+//! // the server stops when its handle is dropped
+//! ```
+
+mod api;
+mod server;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+mod store;
+
+pub use api::{API_PREFIX, MAX_BODY_SIZE, Response, handle};
+pub use server::{Server, ServerHandle};
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteStore;
+pub use store::{MemoryStore, MessageEntry, Store, StoreError};
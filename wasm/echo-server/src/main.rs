@@ -0,0 +1,64 @@
+//! Runs a local stand-in for the message and announcement boards.
+//!
+//! ```text
+//! echo-server [--listen ADDRESS] [--db PATH]
+//! ```
+//!
+//! Listens on `127.0.0.1:3000` by default, the address the web application uses in
+//! development. Without `--db`, everything is kept in memory and lost when the server stops.
+
+use echo_server::{MemoryStore, Server, Store};
+use std::process::ExitCode;
+
+const USAGE: &str = "Usage: echo-server [--listen ADDRESS] [--db PATH]";
+
+/// Address the server listens on when `--listen` is not given
+const DEFAULT_ADDRESS: &str = "127.0.0.1:3000";
+
+fn main() -> ExitCode {
+    let mut address = DEFAULT_ADDRESS.to_string();
+    let mut database = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), args.next()) {
+            ("--listen", Some(value)) => address = value,
+            ("--db", Some(value)) => database = Some(value),
+            _ => {
+                eprintln!("{USAGE}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    let result = match database {
+        Some(path) => open_database(&path).and_then(|store| serve(&address, store)),
+        None => serve(&address, MemoryStore::new()),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("echo-server: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(feature = "sqlite")]
+fn open_database(path: &str) -> Result<echo_server::SqliteStore, String> {
+    echo_server::SqliteStore::open(path).map_err(|error| error.to_string())
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn open_database(_path: &str) -> Result<MemoryStore, String> {
+    Err("`--db` requires the `sqlite` feature".to_string())
+}
+
+fn serve(address: &str, store: impl Store + 'static) -> Result<(), String> {
+    let server = Server::bind(address, store).map_err(|error| error.to_string())?;
+    eprintln!(
+        "echo-server: listening on http://{}/api",
+        server.local_addr()
+    );
+    server.run();
+    Ok(())
+}
@@ -0,0 +1,194 @@
+//! The HTTP transport of the endpoints.
+
+use crate::api::{self, MAX_BODY_SIZE, Response};
+use crate::store::Store;
+use std::io::{self, Read};
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::JoinHandle;
+
+/// An HTTP server answering requests one at a time from a [`Store`].
+pub struct Server {
+    http: Arc<tiny_http::Server>,
+    stopped: Arc<AtomicBool>,
+    store: Box<dyn Store>,
+}
+
+impl Server {
+    /// Binds the server to `address`. Port 0 picks a free port, see
+    /// [`local_addr`](Self::local_addr).
+    pub fn bind(address: impl ToSocketAddrs, store: impl Store + 'static) -> io::Result<Self> {
+        let http = tiny_http::Server::http(address).map_err(io::Error::other)?;
+        Ok(Self {
+            http: Arc::new(http),
+            stopped: Arc::new(AtomicBool::new(false)),
+            store: Box::new(store),
+        })
+    }
+
+    /// Returns the address the server listens on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.http
+            .server_addr()
+            .to_ip()
+            .expect("the server listens on an IP address")
+    }
+
+    /// Answers requests until the server is stopped through its [`ServerHandle`].
+    pub fn run(mut self) {
+        loop {
+            match self.http.recv() {
+                Ok(request) => self.answer(request),
+                Err(_) if self.stopped.load(Ordering::SeqCst) => return,
+                // a connection failed, the others are unaffected
+                Err(_) => {}
+            }
+        }
+    }
+
+    /// Runs the server on a new thread.
+    pub fn spawn(self) -> ServerHandle {
+        let address = self.local_addr();
+        let http = Arc::clone(&self.http);
+        let stopped = Arc::clone(&self.stopped);
+        let thread = std::thread::spawn(move || self.run());
+        ServerHandle {
+            address,
+            http,
+            stopped,
+            thread: Some(thread),
+        }
+    }
+
+    fn answer(&mut self, mut request: tiny_http::Request) {
+        let response = if *request.method() == tiny_http::Method::Options {
+            // CORS preflight of the web application
+            Response {
+                status: 204,
+                body: String::new(),
+            }
+        } else {
+            let mut body = Vec::new();
+            let read = request
+                .as_reader()
+                .take(MAX_BODY_SIZE as u64 + 1)
+                .read_to_end(&mut body);
+            match read {
+                Err(_) => return,
+                Ok(size) if size > MAX_BODY_SIZE => Response {
+                    status: 413,
+                    body: r#"{"error":"request body too large"}"#.to_string(),
+                },
+                Ok(_) => api::handle(
+                    self.store.as_mut(),
+                    request.method().as_str(),
+                    request.url(),
+                    &body,
+                ),
+            }
+        };
+
+        let mut http_response =
+            tiny_http::Response::from_string(response.body).with_status_code(response.status);
+        for (name, value) in [
+            ("Content-Type", "application/json"),
+            ("Access-Control-Allow-Origin", "*"),
+            ("Access-Control-Allow-Methods", "GET, POST, OPTIONS"),
+            ("Access-Control-Allow-Headers", "Content-Type"),
+        ] {
+            let header = tiny_http::Header::from_bytes(name, value).expect("valid header");
+            http_response = http_response.with_header(header);
+        }
+        // the client may have gone away, which only concerns this request
+        let _ = request.respond(http_response);
+    }
+}
+
+/// A server running on its own thread, stopped when the handle is dropped.
+pub struct ServerHandle {
+    address: SocketAddr,
+    http: Arc<tiny_http::Server>,
+    stopped: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ServerHandle {
+    /// Returns the address the server listens on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Returns the base URL of the endpoints, as configured in the web application.
+    pub fn base_url(&self) -> String {
+        format!("http://{}{}", self.address, api::API_PREFIX)
+    }
+}
+
+impl Drop for ServerHandle {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        self.http.unblock();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::MemoryStore;
+    use std::io::Write;
+    use std::net::TcpStream;
+
+    /// Sends a raw HTTP request and returns the raw response.
+    fn send(address: SocketAddr, method: &str, path: &str, body: &str) -> String {
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(
+            stream,
+            "{method} {path} HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_server_answers_requests() {
+        let server = Server::bind("127.0.0.1:0", MemoryStore::new())
+            .unwrap()
+            .spawn();
+        let address = server.local_addr();
+        assert_eq!(
+            server.base_url(),
+            format!("http://127.0.0.1:{}/api", address.port())
+        );
+
+        let response = send(address, "POST", "/api/bulletin", r#"{"data":"AQI="}"#);
+        assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+        assert!(response.ends_with(r#"{"counter":"1"}"#), "{response}");
+
+        let response = send(address, "GET", "/api/bulletin", "");
+        assert!(response.ends_with(r#"["AQI="]"#), "{response}");
+
+        let response = send(address, "GET", "/api/unknown", "");
+        assert!(response.starts_with("HTTP/1.1 404"), "{response}");
+    }
+
+    #[test]
+    fn test_server_allows_cross_origin_requests() {
+        let server = Server::bind("127.0.0.1:0", MemoryStore::new())
+            .unwrap()
+            .spawn();
+        let response = send(server.local_addr(), "OPTIONS", "/api/messages/fetch", "");
+        assert!(response.starts_with("HTTP/1.1 204"), "{response}");
+        assert!(
+            response.contains("Access-Control-Allow-Origin: *"),
+            "{response}"
+        );
+    }
+}
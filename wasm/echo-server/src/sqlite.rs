@@ -0,0 +1,172 @@
+//! A [`Store`] persisted in a SQLite database.
+
+use crate::store::{MessageEntry, Store, StoreError};
+use rusqlite::{Connection, OptionalExtension, params};
+use std::path::Path;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS announcements (
+    counter INTEGER PRIMARY KEY AUTOINCREMENT,
+    data BLOB NOT NULL
+);
+CREATE TABLE IF NOT EXISTS messages (
+    seeker BLOB PRIMARY KEY,
+    data BLOB NOT NULL
+);
+CREATE TABLE IF NOT EXISTS public_keys (
+    user_id BLOB PRIMARY KEY,
+    data BLOB NOT NULL
+);
+";
+
+/// A [`Store`] persisted in a SQLite database, which survives server restarts.
+pub struct SqliteStore {
+    connection: Connection,
+}
+
+impl SqliteStore {
+    /// Opens the database at `path`, creating it if needed.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, StoreError> {
+        Self::with_connection(Connection::open(path).map_err(backend)?)
+    }
+
+    /// Opens a database kept in memory.
+    pub fn open_in_memory() -> Result<Self, StoreError> {
+        Self::with_connection(Connection::open_in_memory().map_err(backend)?)
+    }
+
+    fn with_connection(connection: Connection) -> Result<Self, StoreError> {
+        connection.execute_batch(SCHEMA).map_err(backend)?;
+        Ok(Self { connection })
+    }
+}
+
+fn backend(error: rusqlite::Error) -> StoreError {
+    StoreError::Backend(error.to_string())
+}
+
+impl Store for SqliteStore {
+    fn post_announcement(&mut self, announcement: &[u8]) -> Result<u64, StoreError> {
+        self.connection
+            .execute(
+                "INSERT INTO announcements (data) VALUES (?1)",
+                params![announcement],
+            )
+            .map_err(backend)?;
+        u64::try_from(self.connection.last_insert_rowid())
+            .map_err(|_| StoreError::Backend("negative bulletin counter".to_string()))
+    }
+
+    fn announcements_after(&self, counter: u64) -> Result<Vec<Vec<u8>>, StoreError> {
+        // counters above i64::MAX cannot have been assigned
+        let Ok(counter) = i64::try_from(counter) else {
+            return Ok(Vec::new());
+        };
+        let mut statement = self
+            .connection
+            .prepare_cached("SELECT data FROM announcements WHERE counter > ?1 ORDER BY counter")
+            .map_err(backend)?;
+        let rows = statement
+            .query_map(params![counter], |row| row.get(0))
+            .map_err(backend)?;
+        rows.collect::<Result<_, _>>().map_err(backend)
+    }
+
+    fn post_message(&mut self, seeker: &[u8], message: &[u8]) -> Result<(), StoreError> {
+        let inserted = self
+            .connection
+            .execute(
+                "INSERT INTO messages (seeker, data) VALUES (?1, ?2) ON CONFLICT DO NOTHING",
+                params![seeker, message],
+            )
+            .map_err(backend)?;
+        if inserted == 1 {
+            return Ok(());
+        }
+        let stored: Vec<u8> = self
+            .connection
+            .query_row(
+                "SELECT data FROM messages WHERE seeker = ?1",
+                params![seeker],
+                |row| row.get(0),
+            )
+            .map_err(backend)?;
+        if stored == message {
+            Ok(())
+        } else {
+            Err(StoreError::SeekerInUse)
+        }
+    }
+
+    fn fetch_messages(&self, seekers: &[Vec<u8>]) -> Result<Vec<MessageEntry>, StoreError> {
+        let mut statement = self
+            .connection
+            .prepare_cached("SELECT data FROM messages WHERE seeker = ?1")
+            .map_err(backend)?;
+        let mut messages = Vec::new();
+        for seeker in seekers {
+            let message: Option<Vec<u8>> = statement
+                .query_row(params![seeker], |row| row.get(0))
+                .optional()
+                .map_err(backend)?;
+            if let Some(message) = message {
+                messages.push((seeker.clone(), message));
+            }
+        }
+        Ok(messages)
+    }
+
+    fn post_public_keys(&mut self, user_id: &[u8], public_keys: &[u8]) -> Result<(), StoreError> {
+        self.connection
+            .execute(
+                "INSERT INTO public_keys (user_id, data) VALUES (?1, ?2)
+                 ON CONFLICT (user_id) DO UPDATE SET data = excluded.data",
+                params![user_id, public_keys],
+            )
+            .map_err(backend)?;
+        Ok(())
+    }
+
+    fn public_keys(&self, user_id: &[u8]) -> Result<Option<Vec<u8>>, StoreError> {
+        self.connection
+            .query_row(
+                "SELECT data FROM public_keys WHERE user_id = ?1",
+                params![user_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(backend)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::check_store;
+
+    #[test]
+    fn test_sqlite_store() {
+        check_store(&mut SqliteStore::open_in_memory().unwrap());
+    }
+
+    #[test]
+    fn test_sqlite_store_survives_reopening() {
+        let path = std::env::temp_dir().join(format!("echo-server-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut store = SqliteStore::open(&path).unwrap();
+        store.post_announcement(b"announcement").unwrap();
+        store.post_message(b"seeker", b"message").unwrap();
+        drop(store);
+
+        let mut store = SqliteStore::open(&path).unwrap();
+        assert_eq!(store.post_announcement(b"next").unwrap(), 2);
+        assert_eq!(
+            store.fetch_messages(&[b"seeker".to_vec()]).unwrap(),
+            [(b"seeker".to_vec(), b"message".to_vec())]
+        );
+        drop(store);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,161 @@
+//! Storage behind the server endpoints.
+
+use std::collections::HashMap;
+use std::fmt;
+
+/// Error returned by a [`Store`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StoreError {
+    /// Another message is already stored under this seeker.
+    SeekerInUse,
+    /// The storage backend failed.
+    Backend(String),
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SeekerInUse => write!(f, "another message is already stored under this seeker"),
+            Self::Backend(error) => write!(f, "storage error: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for StoreError {}
+
+/// A `(seeker, message)` pair.
+pub type MessageEntry = (Vec<u8>, Vec<u8>);
+
+/// Storage of announcements, messages and public keys.
+///
+/// - **Announcements** form an append-only bulletin. Each one gets the next counter, starting
+///   at 1, and they are read from a counter onwards.
+/// - **Messages** are stored under their seeker. A seeker holds a single message: posting the
+///   same message again succeeds, so that clients can retry, but posting another one fails
+///   with [`StoreError::SeekerInUse`].
+/// - **Public keys** are stored under the user ID derived from them, replacing previous ones.
+pub trait Store: Send {
+    /// Appends an announcement and returns its bulletin counter.
+    fn post_announcement(&mut self, announcement: &[u8]) -> Result<u64, StoreError>;
+
+    /// Returns the announcements posted after the first `counter` ones, in posting order.
+    fn announcements_after(&self, counter: u64) -> Result<Vec<Vec<u8>>, StoreError>;
+
+    /// Stores a message under its seeker.
+    fn post_message(&mut self, seeker: &[u8], message: &[u8]) -> Result<(), StoreError>;
+
+    /// Returns the `(seeker, message)` pairs stored under any of `seekers`, in the order of
+    /// `seekers`.
+    fn fetch_messages(&self, seekers: &[Vec<u8>]) -> Result<Vec<MessageEntry>, StoreError>;
+
+    /// Stores the serialized public keys of a user.
+    fn post_public_keys(&mut self, user_id: &[u8], public_keys: &[u8]) -> Result<(), StoreError>;
+
+    /// Returns the serialized public keys of a user, if any.
+    fn public_keys(&self, user_id: &[u8]) -> Result<Option<Vec<u8>>, StoreError>;
+}
+
+/// A [`Store`] kept in memory, lost when the server stops.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    announcements: Vec<Vec<u8>>,
+    messages: HashMap<Vec<u8>, Vec<u8>>,
+    public_keys: HashMap<Vec<u8>, Vec<u8>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Store for MemoryStore {
+    fn post_announcement(&mut self, announcement: &[u8]) -> Result<u64, StoreError> {
+        self.announcements.push(announcement.to_vec());
+        Ok(self.announcements.len() as u64)
+    }
+
+    fn announcements_after(&self, counter: u64) -> Result<Vec<Vec<u8>>, StoreError> {
+        let start = usize::try_from(counter).unwrap_or(usize::MAX);
+        Ok(self.announcements.get(start..).unwrap_or_default().to_vec())
+    }
+
+    fn post_message(&mut self, seeker: &[u8], message: &[u8]) -> Result<(), StoreError> {
+        match self.messages.get(seeker) {
+            Some(stored) if stored == message => Ok(()),
+            Some(_) => Err(StoreError::SeekerInUse),
+            None => {
+                self.messages.insert(seeker.to_vec(), message.to_vec());
+                Ok(())
+            }
+        }
+    }
+
+    fn fetch_messages(&self, seekers: &[Vec<u8>]) -> Result<Vec<MessageEntry>, StoreError> {
+        Ok(seekers
+            .iter()
+            .filter_map(|seeker| {
+                self.messages
+                    .get(seeker)
+                    .map(|message| (seeker.clone(), message.clone()))
+            })
+            .collect())
+    }
+
+    fn post_public_keys(&mut self, user_id: &[u8], public_keys: &[u8]) -> Result<(), StoreError> {
+        self.public_keys
+            .insert(user_id.to_vec(), public_keys.to_vec());
+        Ok(())
+    }
+
+    fn public_keys(&self, user_id: &[u8]) -> Result<Option<Vec<u8>>, StoreError> {
+        Ok(self.public_keys.get(user_id).cloned())
+    }
+}
+
+/// Checks the semantics documented on [`Store`] against an empty store.
+#[cfg(test)]
+pub(crate) fn check_store(store: &mut dyn Store) {
+    assert!(store.announcements_after(0).unwrap().is_empty());
+    assert_eq!(store.post_announcement(b"first").unwrap(), 1);
+    assert_eq!(store.post_announcement(b"second").unwrap(), 2);
+    assert_eq!(
+        store.announcements_after(0).unwrap(),
+        [b"first".to_vec(), b"second".to_vec()]
+    );
+    assert_eq!(store.announcements_after(1).unwrap(), [b"second".to_vec()]);
+    assert!(store.announcements_after(2).unwrap().is_empty());
+    assert!(store.announcements_after(u64::MAX).unwrap().is_empty());
+
+    store.post_message(b"seeker", b"message").unwrap();
+    store.post_message(b"seeker", b"message").unwrap();
+    assert_eq!(
+        store.post_message(b"seeker", b"other"),
+        Err(StoreError::SeekerInUse)
+    );
+    store.post_message(b"another", b"reply").unwrap();
+    assert_eq!(
+        store
+            .fetch_messages(&[b"another".to_vec(), b"missing".to_vec(), b"seeker".to_vec()])
+            .unwrap(),
+        [
+            (b"another".to_vec(), b"reply".to_vec()),
+            (b"seeker".to_vec(), b"message".to_vec()),
+        ]
+    );
+
+    assert_eq!(store.public_keys(b"user").unwrap(), None);
+    store.post_public_keys(b"user", b"old").unwrap();
+    store.post_public_keys(b"user", b"new").unwrap();
+    assert_eq!(store.public_keys(b"user").unwrap(), Some(b"new".to_vec()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_store() {
+        check_store(&mut MemoryStore::new());
+    }
+}
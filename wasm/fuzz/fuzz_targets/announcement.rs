@@ -0,0 +1,40 @@
+//! Fuzzes `IncomingAnnouncementPrecursor::try_from_incoming_announcement_bytes` with
+//! announcements sent to Bob.
+
+#![no_main]
+
+use crypto_agraphon::IncomingAnnouncementPrecursor;
+use gossip_fuzz::{Field, Fixture, mutate};
+use libfuzzer_sys::{fuzz_mutator, fuzz_target};
+
+/// `randomness || KEM ciphertext || encrypted next public key and auth payload`
+const LAYOUT: &[Field] = &[
+    Field::Fixed(32),
+    Field::Fixed(crypto_kem::CIPHERTEXT_SIZE),
+    Field::Rest,
+];
+
+fuzz_target!(|data: &[u8]| {
+    let fixture = Fixture::get();
+    let Ok(precursor) = IncomingAnnouncementPrecursor::try_from_incoming_announcement_bytes(
+        data,
+        &fixture.bob_pk.kem_public_key,
+        &fixture.bob_sk.kem_secret_key,
+    ) else {
+        return;
+    };
+
+    // an accepted announcement can be finalized
+    let _ = precursor.auth_payload();
+    let _ = precursor.auth_key();
+    assert!(
+        precursor
+            .finalize(fixture.alice_pk.kem_public_key.clone())
+            .is_some()
+    );
+});
+
+fuzz_mutator!(|data: &mut [u8], size: usize, max_size: usize, seed: u32| {
+    let fixture = Fixture::get();
+    mutate(data, size, max_size, seed, LAYOUT, &fixture.announcement)
+});
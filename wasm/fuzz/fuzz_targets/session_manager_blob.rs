@@ -0,0 +1,58 @@
+//! Fuzzes `SessionManager::from_encrypted_blob`.
+//!
+//! In wire mode, the input is the encrypted blob. In payload mode, the input is a serialized
+//! session manager, encrypted with the state key before being restored, so that mutations reach
+//! the deserialization. A restored manager must be usable and persist again.
+
+#![no_main]
+
+use gossip_fuzz::{Field, Fixture, Mode, RNG_SEED, mutate_with_mode};
+use libfuzzer_sys::{fuzz_mutator, fuzz_target};
+use sessions::SessionManager;
+
+/// `magic || schema version || nonce || ciphertext`
+const WIRE_LAYOUT: &[Field] = &[
+    Field::Fixed(4),
+    Field::Fixed(2),
+    Field::Fixed(crypto_aead::NONCE_SIZE),
+    Field::Rest,
+];
+
+/// The serialized state has no framing of its own.
+const PAYLOAD_LAYOUT: &[Field] = &[Field::Rest];
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&mode, input)) = data.split_first() else {
+        return;
+    };
+    let fixture = Fixture::get();
+
+    crypto_rng::with_seed(RNG_SEED, || {
+        let blob = match Mode::from_byte(mode) {
+            Mode::Wire => input.to_vec(),
+            Mode::Payload => SessionManager::seal_serialized_state(input, &fixture.state_key),
+        };
+        let Ok(manager) = SessionManager::from_encrypted_blob(&blob, &fixture.state_key) else {
+            return;
+        };
+
+        // a restored manager is usable and persists again
+        for peer_id in manager.peer_list() {
+            let _ = manager.peer_session_status(&peer_id);
+            let _ = manager.peer_public_keys(&peer_id);
+        }
+        let _ = manager.get_message_board_read_keys();
+        let blob = manager
+            .to_encrypted_blob(&fixture.state_key)
+            .expect("restored manager does not serialize");
+        assert!(SessionManager::from_encrypted_blob(&blob, &fixture.state_key).is_ok());
+    });
+});
+
+fuzz_mutator!(|data: &mut [u8], size: usize, max_size: usize, seed: u32| {
+    let fixture = Fixture::get();
+    mutate_with_mode(data, size, max_size, seed, |mode| match mode {
+        Mode::Wire => (WIRE_LAYOUT, fixture.state_blob.clone()),
+        Mode::Payload => (PAYLOAD_LAYOUT, fixture.state.clone()),
+    })
+});
@@ -0,0 +1,54 @@
+//! Fuzzes `Session::try_feed_incoming_message` with messages from the peer.
+//!
+//! In wire mode, the input is the message data, read under the seeker of a genuine message.
+//! In payload mode, the input is a serialized message that Alice encrypts and signs before
+//! sending it, as a malicious peer could. A rejected message must leave Bob's session as it was.
+
+#![no_main]
+
+use gossip_fuzz::{Field, Fixture, Mode, RNG_SEED, mutate_with_mode, serialize};
+use libfuzzer_sys::{fuzz_mutator, fuzz_target};
+
+/// `[seeker public key] [signature] [agraphon message]`, with one-byte length prefixes.
+const WIRE_LAYOUT: &[Field] = &[Field::U8Prefixed, Field::U8Prefixed, Field::Rest];
+
+/// `timestamp || contents`, the contents being prefixed with their length.
+const PAYLOAD_LAYOUT: &[Field] = &[Field::Varint, Field::VarintPrefixed];
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&mode, input)) = data.split_first() else {
+        return;
+    };
+    let fixture = Fixture::get();
+
+    crypto_rng::with_seed(RNG_SEED, || {
+        let (seeker, message) = match Mode::from_byte(mode) {
+            Mode::Wire => (fixture.message.seeker.clone(), input.to_vec()),
+            Mode::Payload => {
+                let output =
+                    Fixture::restore_session(&fixture.alice_session).send_raw_message_bytes(input);
+                (output.seeker.clone(), output.data.clone())
+            }
+        };
+
+        let mut bob_session = Fixture::restore_session(&fixture.bob_session);
+        if bob_session
+            .try_feed_incoming_message(&fixture.bob_sk, &seeker, &message)
+            .is_err()
+        {
+            assert_eq!(
+                serialize(&bob_session),
+                fixture.bob_session,
+                "a rejected message changed the session state"
+            );
+        }
+    });
+});
+
+fuzz_mutator!(|data: &mut [u8], size: usize, max_size: usize, seed: u32| {
+    let fixture = Fixture::get();
+    mutate_with_mode(data, size, max_size, seed, |mode| match mode {
+        Mode::Wire => (WIRE_LAYOUT, fixture.message.data.clone()),
+        Mode::Payload => (PAYLOAD_LAYOUT, fixture.message_payload.clone()),
+    })
+});
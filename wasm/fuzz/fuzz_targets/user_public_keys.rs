@@ -0,0 +1,39 @@
+//! Fuzzes `UserPublicKeys::from_bytes`.
+
+#![no_main]
+
+use auth::UserPublicKeys;
+use gossip_fuzz::{Field, Fixture, mutate};
+use libfuzzer_sys::{fuzz_mutator, fuzz_target};
+
+/// DSA verification key, KEM public key and Massa public key, as bincode byte strings.
+const LAYOUT: &[Field] = &[
+    Field::VarintPrefixed,
+    Field::VarintPrefixed,
+    Field::VarintPrefixed,
+    Field::Rest,
+];
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(keys) = UserPublicKeys::from_bytes(data) else {
+        return;
+    };
+
+    // accepted keys serialize back to bytes that decode to the same keys
+    let bytes = keys.to_bytes();
+    let decoded = UserPublicKeys::from_bytes(&bytes).expect("serialized keys do not decode");
+    assert_eq!(decoded.to_bytes(), bytes);
+    assert_eq!(decoded.derive_id(), keys.derive_id());
+});
+
+fuzz_mutator!(|data: &mut [u8], size: usize, max_size: usize, seed: u32| {
+    let fixture = Fixture::get();
+    mutate(
+        data,
+        size,
+        max_size,
+        seed,
+        LAYOUT,
+        &fixture.bob_pk.to_bytes(),
+    )
+});
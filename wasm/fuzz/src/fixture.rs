@@ -0,0 +1,143 @@
+//! Sessions and samples shared by all fuzz runs of a process.
+
+use crate::serialize;
+use auth::{UserPublicKeys, UserSecretKeys};
+use sessions::{
+    IncomingInitiationRequest, OutgoingInitiationRequest, SendOutgoingMessageOutput, Session,
+    SessionManager, SessionManagerConfig, SkippedMessageLimits, SystemClock,
+};
+use std::sync::OnceLock;
+
+/// Seed of the random source, so that crashes reproduce.
+pub const RNG_SEED: [u8; crypto_rng::SEED_SIZE] = *b"gossip fuzz targets random seed!";
+
+/// Keys, sessions and genuine samples of each input, built once per process.
+///
+/// Alice and Bob have an established session. Sessions are kept serialized, so that each run
+/// starts from a fresh copy of the same state.
+pub struct Fixture {
+    /// Alice's long-term public keys
+    pub alice_pk: UserPublicKeys,
+    /// Bob's long-term public keys
+    pub bob_pk: UserPublicKeys,
+    /// Bob's long-term secret keys
+    pub bob_sk: UserSecretKeys,
+    /// Alice's serialized session with Bob
+    pub alice_session: Vec<u8>,
+    /// Bob's serialized session with Alice
+    pub bob_session: Vec<u8>,
+    /// A genuine message from Alice to Bob
+    pub message: SendOutgoingMessageOutput,
+    /// A genuine serialized message payload
+    pub message_payload: Vec<u8>,
+    /// A genuine agraphon announcement from Alice to Bob
+    pub announcement: Vec<u8>,
+    /// Key of the session manager states
+    pub state_key: crypto_aead::Key,
+    /// A genuine serialized session manager of Alice, with a session with Bob
+    pub state: Vec<u8>,
+    /// The encrypted blob of `state`
+    pub state_blob: Vec<u8>,
+}
+
+/// Configuration of the session managers, as in the tests of the sessions crate.
+fn config() -> SessionManagerConfig {
+    SessionManagerConfig {
+        max_incoming_announcement_age_millis: 60_000,
+        max_incoming_announcement_future_millis: 5_000,
+        max_incoming_message_age_millis: 300_000,
+        max_incoming_message_future_millis: 5_000,
+        max_session_inactivity_millis: 3_600_000,
+        keep_alive_interval_millis: 60_000,
+        max_session_lag_length: 100,
+        max_skipped_messages: 16,
+        max_stored_skipped_messages: 128,
+    }
+}
+
+/// Derives keys from a fixed root secret, skipping the password KDF.
+fn keys(root_secret: u8) -> (UserPublicKeys, UserSecretKeys) {
+    auth::derive_keys_from_static_root_secret(&auth::StaticRootSecret::from_bytes(
+        [root_secret; auth::STATIC_ROOT_SECRET_SIZE],
+    ))
+}
+
+impl Fixture {
+    /// Returns the fixture of this process, building it on first use.
+    pub fn get() -> &'static Self {
+        static FIXTURE: OnceLock<Fixture> = OnceLock::new();
+        FIXTURE.get_or_init(|| crypto_rng::with_seed(RNG_SEED, Self::build))
+    }
+
+    fn build() -> Self {
+        let (alice_pk, alice_sk) = keys(1);
+        let (bob_pk, bob_sk) = keys(2);
+
+        // establish a session between Alice and Bob
+        let limits = SkippedMessageLimits::default();
+        let (alice_announcement, alice_outgoing) =
+            OutgoingInitiationRequest::new(&alice_pk, &alice_sk, &bob_pk, vec![], &SystemClock);
+        let (bob_announcement, bob_outgoing) =
+            OutgoingInitiationRequest::new(&bob_pk, &bob_sk, &alice_pk, vec![], &SystemClock);
+        let (bob_incoming_at_alice, _) =
+            IncomingInitiationRequest::try_from(&bob_announcement, &alice_pk, &alice_sk)
+                .expect("Failed to read Bob's announcement");
+        let (alice_incoming_at_bob, _) =
+            IncomingInitiationRequest::try_from(&alice_announcement, &bob_pk, &bob_sk)
+                .expect("Failed to read Alice's announcement");
+        let alice_session =
+            Session::from_initiation_request_pair(&alice_outgoing, &bob_incoming_at_alice, limits);
+        let bob_session =
+            Session::from_initiation_request_pair(&bob_outgoing, &alice_incoming_at_bob, limits);
+
+        // a genuine message, sent from a copy of Alice's session
+        let message = Self::restore_session(&serialize(&alice_session))
+            .send_outgoing_message(b"hello bob", &SystemClock);
+        let message_payload = serialize(&(0u128, b"hello bob".to_vec()));
+
+        // a genuine agraphon announcement
+        let (announcement, _) =
+            crypto_agraphon::OutgoingAnnouncementPrecursor::new(&bob_pk.kem_public_key)
+                .finalize(b"auth payload");
+
+        // a genuine session manager state
+        let mut state_key_bytes = [0u8; crypto_aead::KEY_SIZE];
+        crypto_rng::fill_buffer(&mut state_key_bytes);
+        let state_key = crypto_aead::Key::from(state_key_bytes);
+        let mut manager = SessionManager::new(config());
+        manager.establish_outgoing_session(&bob_pk, &alice_pk, &alice_sk, vec![]);
+        manager
+            .feed_incoming_announcement(&bob_announcement, &alice_pk, &alice_sk)
+            .expect("Failed to feed Bob's announcement");
+        let state = serialize(&manager);
+        let state_blob = manager
+            .to_encrypted_blob(&state_key)
+            .expect("Failed to encrypt session manager");
+
+        Self {
+            alice_pk,
+            bob_pk,
+            bob_sk,
+            alice_session: serialize(&alice_session),
+            bob_session: serialize(&bob_session),
+            message,
+            message_payload,
+            announcement,
+            state_key,
+            state,
+            state_blob,
+        }
+    }
+
+    /// Deserializes a session serialized with [`serialize`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the bytes are not a serialized session.
+    #[must_use]
+    pub fn restore_session(bytes: &[u8]) -> Session {
+        bincode::serde::decode_from_slice(bytes, bincode::config::standard())
+            .expect("Failed to deserialize session")
+            .0
+    }
+}
@@ -0,0 +1,258 @@
+//! Framing-aware mutation of fuzz inputs.
+
+use crate::Mode;
+
+/// A field of a framed input, as laid out on the wire.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Field {
+    /// A field of a fixed size, such as a KEM ciphertext.
+    Fixed(usize),
+    /// A length byte followed by that many bytes, as in session messages.
+    U8Prefixed,
+    /// A bincode varint length followed by that many bytes, as serialized byte strings.
+    VarintPrefixed,
+    /// A bincode varint integer, such as a timestamp or the length of a sequence.
+    Varint,
+    /// All the remaining bytes.
+    Rest,
+}
+
+/// Integers at the boundaries of the bincode varint encoding.
+const INTERESTING_VARINTS: [u128; 9] = [
+    0,
+    1,
+    250,
+    251,
+    u16::MAX as u128,
+    u16::MAX as u128 + 1,
+    u32::MAX as u128,
+    u64::MAX as u128,
+    u128::MAX,
+];
+
+/// Decodes a bincode varint (standard configuration), returning it and its encoded size.
+fn decode_varint(bytes: &[u8]) -> Option<(u128, usize)> {
+    let (&tag, rest) = bytes.split_first()?;
+    let size = match tag {
+        0..=250 => return Some((u128::from(tag), 1)),
+        251 => 2,
+        252 => 4,
+        253 => 8,
+        254 => 16,
+        _ => return None,
+    };
+    let mut value = [0u8; 16];
+    value[..size].copy_from_slice(rest.get(..size)?);
+    Some((u128::from_le_bytes(value), 1 + size))
+}
+
+/// Encodes an integer as a bincode varint (standard configuration).
+fn encode_varint(value: u128) -> Vec<u8> {
+    let bytes = value.to_le_bytes();
+    let (tag, size) = match value {
+        0..=250 => return vec![value as u8],
+        251..=0xffff => (251, 2),
+        0x1_0000..=0xffff_ffff => (252, 4),
+        0x1_0000_0000..=0xffff_ffff_ffff_ffff => (253, 8),
+        _ => (254, 16),
+    };
+    [&[tag], &bytes[..size]].concat()
+}
+
+/// Splits an input into the contents of its fields, without their length prefixes.
+///
+/// The contents of a [`Field::Varint`] are its encoding. Returns `None` if the input does not
+/// follow the layout.
+fn split<'a>(mut input: &'a [u8], layout: &[Field]) -> Option<Vec<&'a [u8]>> {
+    let mut fields = Vec::with_capacity(layout.len());
+    for field in layout {
+        let (start, len) = match field {
+            Field::Fixed(size) => (0, *size),
+            Field::U8Prefixed => (1, usize::from(*input.first()?)),
+            Field::VarintPrefixed => {
+                let (len, prefix_size) = decode_varint(input)?;
+                (prefix_size, usize::try_from(len).ok()?)
+            }
+            Field::Varint => (0, decode_varint(input)?.1),
+            Field::Rest => (0, input.len()),
+        };
+        fields.push(input.get(start..start.checked_add(len)?)?);
+        input = &input[start + len..];
+    }
+    Some(fields)
+}
+
+/// Assembles field contents into an input, prefixing them with their length.
+///
+/// If `length_override` is set to `(index, len)`, the length prefix of the field at `index`
+/// claims `len` bytes whatever its actual contents.
+fn join(fields: &[Vec<u8>], layout: &[Field], length_override: Option<(usize, usize)>) -> Vec<u8> {
+    let mut input = Vec::new();
+    for (index, (contents, field)) in fields.iter().zip(layout).enumerate() {
+        let len = match length_override {
+            Some((override_index, len)) if override_index == index => len,
+            _ => contents.len(),
+        };
+        match field {
+            Field::U8Prefixed => input.push(len as u8),
+            Field::VarintPrefixed => input.extend(encode_varint(len as u128)),
+            Field::Fixed(_) | Field::Varint | Field::Rest => {}
+        }
+        input.extend_from_slice(contents);
+    }
+    input
+}
+
+/// Copies `input` into the mutation buffer, returning its new size.
+fn write(data: &mut [u8], max_size: usize, input: &[u8]) -> usize {
+    let size = input.len().min(max_size).min(data.len());
+    data[..size].copy_from_slice(&input[..size]);
+    size
+}
+
+/// Mutates a framed input in place and returns its new size, as a libFuzzer custom mutator.
+///
+/// One field is picked from `seed` and either its contents are mutated with the default
+/// mutator, or its length prefix is made inconsistent with its contents. Inputs that do not
+/// follow the layout are replaced with `sample` now and then, and otherwise mutated with the
+/// default mutator.
+pub fn mutate(
+    data: &mut [u8],
+    size: usize,
+    max_size: usize,
+    seed: u32,
+    layout: &[Field],
+    sample: &[u8],
+) -> usize {
+    let Some(fields) = split(&data[..size], layout) else {
+        if seed.is_multiple_of(4) {
+            return write(data, max_size, sample);
+        }
+        return libfuzzer_sys::fuzzer_mutate(data, size, max_size);
+    };
+    let mut fields: Vec<Vec<u8>> = fields.into_iter().map(<[u8]>::to_vec).collect();
+
+    let seed = seed as usize;
+    let index = seed % layout.len();
+    let action = seed / layout.len();
+    let mut length_override = None;
+    match layout[index] {
+        Field::Varint => {
+            let value = INTERESTING_VARINTS[action % INTERESTING_VARINTS.len()];
+            fields[index] = encode_varint(value);
+        }
+        Field::U8Prefixed | Field::VarintPrefixed if action.is_multiple_of(4) => {
+            let len = fields[index].len();
+            let lie = if action.is_multiple_of(8) {
+                len.wrapping_add(1 + action / 8 % 16)
+            } else {
+                len.saturating_sub(1 + action / 8 % 16)
+            };
+            length_override = Some((index, lie));
+        }
+        field => {
+            // give the field all the room the other fields leave
+            let other_size = size - fields[index].len();
+            let field_max_size = max_size.saturating_sub(other_size).max(fields[index].len());
+            if field_max_size == 0 {
+                return libfuzzer_sys::fuzzer_mutate(data, size, max_size);
+            }
+            let contents = &mut fields[index];
+            let contents_size = contents.len();
+            contents.resize(field_max_size, 0);
+            let new_size = libfuzzer_sys::fuzzer_mutate(contents, contents_size, field_max_size);
+            // fixed-size fields keep their size so that the following fields stay in place
+            match field {
+                Field::Fixed(fixed_size) => contents.resize(fixed_size, 0),
+                _ => contents.truncate(new_size),
+            }
+        }
+    }
+
+    let input = join(&fields, layout, length_override);
+    if input.len() > max_size {
+        return libfuzzer_sys::fuzzer_mutate(data, size, max_size);
+    }
+    write(data, max_size, &input)
+}
+
+/// Mutates an input starting with a [`Mode`] byte, with the layout and sample of its mode.
+///
+/// The mode is switched now and then, starting over from the sample of the other mode.
+pub fn mutate_with_mode<'a>(
+    data: &mut [u8],
+    size: usize,
+    max_size: usize,
+    seed: u32,
+    layout_and_sample: impl Fn(Mode) -> (&'a [Field], Vec<u8>),
+) -> usize {
+    if size == 0 || max_size < 2 || seed.is_multiple_of(64) {
+        let mode = Mode::from_byte(data.first().copied().unwrap_or(0).wrapping_add(1));
+        let (_, sample) = layout_and_sample(mode);
+        return write(
+            data,
+            max_size,
+            &[&[mode.to_byte()], sample.as_slice()].concat(),
+        );
+    }
+    let mode = Mode::from_byte(data[0]);
+    let (layout, sample) = layout_and_sample(mode);
+    1 + mutate(
+        &mut data[1..],
+        size - 1,
+        max_size - 1,
+        seed,
+        layout,
+        &sample,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_varint_roundtrip() {
+        for value in INTERESTING_VARINTS {
+            let encoded = encode_varint(value);
+            assert_eq!(decode_varint(&encoded), Some((value, encoded.len())));
+        }
+        assert_eq!(encode_varint(250), [250]);
+        assert_eq!(encode_varint(251), [251, 251, 0]);
+        assert_eq!(decode_varint(&[255]), None);
+        assert_eq!(decode_varint(&[252, 1, 2]), None);
+    }
+
+    #[test]
+    fn test_split_join_roundtrip() {
+        let layout = [
+            Field::Fixed(2),
+            Field::U8Prefixed,
+            Field::VarintPrefixed,
+            Field::Varint,
+            Field::Rest,
+        ];
+        let input = [&[1, 2, 3, 7, 8, 9, 2, 4, 5, 251, 0, 1][..], b"rest"].concat();
+
+        let fields = split(&input, &layout).expect("input follows the layout");
+        assert_eq!(
+            fields,
+            [&[1, 2][..], &[7, 8, 9], &[4, 5], &[251, 0, 1], b"rest"]
+        );
+        let fields: Vec<Vec<u8>> = fields.into_iter().map(<[u8]>::to_vec).collect();
+        assert_eq!(join(&fields, &layout, None), input);
+
+        // the length prefix of a field can lie about its contents
+        let lying = join(&fields, &layout, Some((1, 5)));
+        assert_eq!(lying[2], 5);
+        assert_eq!(lying.len(), input.len());
+    }
+
+    #[test]
+    fn test_split_rejects_truncated_input() {
+        let layout = [Field::U8Prefixed, Field::Rest];
+        assert_eq!(split(&[3, 1, 2], &layout), None);
+        assert_eq!(split(&[], &layout), None);
+        assert_eq!(split(&[1, 2], &[Field::Fixed(3)]), None);
+    }
+}
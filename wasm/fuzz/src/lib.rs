@@ -0,0 +1,61 @@
+//! Shared fixtures and structure-aware mutators of the fuzz targets.
+//!
+//! Every target feeds attacker-controlled bytes to a parser and checks that malformed input
+//! is rejected with an error, never a panic, and never changes the state it is fed to.
+//!
+//! Most of these parsers only reach their interesting code once the outer layers check out:
+//! length prefixes must be consistent, signatures valid, ciphertexts authentic. Two things
+//! help the fuzzer get there:
+//!
+//! - The mutators know the framing of each input (see [`Field`]): they mutate one field at a
+//!   time and rewrite its length prefix, instead of shifting every following field.
+//! - Targets with an encrypted, bincode-serialized payload take a leading mode byte (see
+//!   [`Mode`]). In [`Mode::Payload`], the input is the serialized payload, which the target
+//!   encrypts and signs like a genuine peer would, so that mutations reach the deserialization
+//!   instead of failing authentication.
+
+mod fixture;
+mod layout;
+
+pub use fixture::{Fixture, RNG_SEED};
+pub use layout::{Field, mutate, mutate_with_mode};
+
+/// How a target interprets the bytes following the leading mode byte.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    /// The bytes are fed as they would be read from the network or from storage.
+    Wire,
+    /// The bytes are a serialized payload, encrypted by the target before being fed.
+    Payload,
+}
+
+impl Mode {
+    /// Returns the mode selected by a leading byte.
+    #[must_use]
+    pub const fn from_byte(byte: u8) -> Self {
+        match byte & 1 {
+            0 => Self::Wire,
+            _ => Self::Payload,
+        }
+    }
+
+    /// Returns the leading byte selecting this mode.
+    #[must_use]
+    pub const fn to_byte(self) -> u8 {
+        match self {
+            Self::Wire => 0,
+            Self::Payload => 1,
+        }
+    }
+}
+
+/// Serializes a value the way the sessions crate does, to compare states.
+///
+/// # Panics
+///
+/// Panics if the value cannot be serialized.
+#[must_use]
+pub fn serialize<T: serde::Serialize>(value: &T) -> Vec<u8> {
+    bincode::serde::encode_to_vec(value, bincode::config::standard())
+        .expect("Failed to serialize state")
+}
@@ -28,6 +28,7 @@ pub struct SessionConfig {
 impl SessionConfig {
     /// Creates a new session configuration with the given parameters.
     #[wasm_bindgen(constructor)]
+    #[allow(clippy::too_many_arguments)] // mirrors the JS constructor signature
     pub fn new(
         max_incoming_announcement_age_millis: f64,
         max_incoming_announcement_future_millis: f64,
@@ -36,6 +37,8 @@ impl SessionConfig {
         max_session_inactivity_millis: f64,
         keep_alive_interval_millis: f64,
         max_session_lag_length: u64,
+        max_skipped_messages: u64,
+        max_stored_skipped_messages: u64,
     ) -> Self {
         Self {
             inner: sessions::SessionManagerConfig {
@@ -47,6 +50,8 @@ impl SessionConfig {
                 max_session_inactivity_millis: max_session_inactivity_millis as u128,
                 keep_alive_interval_millis: keep_alive_interval_millis as u128,
                 max_session_lag_length,
+                max_skipped_messages,
+                max_stored_skipped_messages,
             },
         }
     }
@@ -59,6 +64,8 @@ impl SessionConfig {
     /// - Session inactivity: 1 week
     /// - Keep-alive interval: 1 day
     /// - Max lag: 10000 messages
+    /// - Max skipped messages: 16 consecutive messages
+    /// - Max stored skipped messages: 128 messages
     pub fn new_default() -> Self {
         Self {
             inner: sessions::SessionManagerConfig {
@@ -69,6 +76,8 @@ impl SessionConfig {
                 max_session_inactivity_millis: 604_800_000,        // 1 week
                 keep_alive_interval_millis: 86_400_000,            // 1 day
                 max_session_lag_length: 10000,
+                max_skipped_messages: 16,
+                max_stored_skipped_messages: 128,
             },
         }
     }
@@ -87,6 +96,11 @@ impl UserPublicKeys {
         self.inner.derive_id().as_bytes().to_vec()
     }
 
+    /// Derives the user ID and returns its text encoding (`gossip1...`).
+    pub fn derive_id_string(&self) -> String {
+        self.inner.derive_id().to_string()
+    }
+
     /// Gets the DSA verification key bytes.
     #[wasm_bindgen(getter)]
     pub fn dsa_verification_key(&self) -> Vec<u8> {
@@ -119,6 +133,45 @@ impl UserPublicKeys {
     }
 }
 
+/// Safety number of a pair of users, compared out-of-band to verify their public keys.
+#[wasm_bindgen]
+pub struct SafetyNumber {
+    inner: auth::SafetyNumber,
+}
+
+#[wasm_bindgen]
+impl SafetyNumber {
+    /// Computes the safety number of two users' public keys, in any order.
+    #[wasm_bindgen(constructor)]
+    pub fn new(our_pk: &UserPublicKeys, peer_pk: &UserPublicKeys) -> Self {
+        Self {
+            inner: auth::SafetyNumber::new(&our_pk.inner, &peer_pk.inner),
+        }
+    }
+
+    /// Gets the 60 digits of the safety number, without separators.
+    #[wasm_bindgen(getter)]
+    pub fn digits(&self) -> String {
+        self.inner.digits()
+    }
+
+    /// Gets the digits of the safety number grouped by 5, for display.
+    #[wasm_bindgen(getter)]
+    pub fn formatted(&self) -> String {
+        self.inner.to_string()
+    }
+
+    /// Gets the bytes to encode in a QR code.
+    pub fn to_scannable_bytes(&self) -> Vec<u8> {
+        self.inner.to_scannable_bytes()
+    }
+
+    /// Checks bytes scanned from the peer's QR code against this safety number.
+    pub fn matches_scanned(&self, scanned: &[u8]) -> bool {
+        auth::SafetyNumber::from_scannable_bytes(scanned).as_ref() == Some(&self.inner)
+    }
+}
+
 /// User secret keys for signing and decryption.
 #[wasm_bindgen]
 pub struct UserSecretKeys {
@@ -180,10 +233,275 @@ impl UserKeys {
     }
 }
 
+/// Derives the root secret of a passphrase, salted if a salt (`gossipsalt1...`) is given.
+fn root_secret_from_passphrase(
+    passphrase: &str,
+    salt: Option<String>,
+) -> Result<auth::StaticRootSecret, JsValue> {
+    match salt {
+        Some(salt) => {
+            let salt = parse_passphrase_salt(&salt)?;
+            Ok(auth::StaticRootSecret::from_salted_passphrase(
+                passphrase.as_bytes(),
+                &salt,
+            ))
+        }
+        None => Ok(auth::StaticRootSecret::from_passphrase(
+            passphrase.as_bytes(),
+        )),
+    }
+}
+
+/// Parses the text encoding of a passphrase salt.
+fn parse_passphrase_salt(salt: &str) -> Result<auth::PassphraseSalt, JsValue> {
+    salt.parse()
+        .map_err(|e: auth::PassphraseSaltParseError| JsValue::from_str(&e.to_string()))
+}
+
+/// Generates a new random passphrase salt, encoded as text (`gossipsalt1...`).
+///
+/// The salt is not secret, but it is needed to recover the account: it must be kept in the
+/// account backup next to the passphrase or mnemonic.
+#[wasm_bindgen]
+pub fn generate_passphrase_salt() -> String {
+    auth::PassphraseSalt::generate().to_string()
+}
+
 /// Generates user keys from a passphrase using password-based key derivation.
+///
+/// New accounts should pass a salt from `generate_passphrase_salt`. Without a salt, the fixed
+/// salt of older accounts is used.
 #[wasm_bindgen]
-pub fn generate_user_keys(passphrase: &str) -> Result<UserKeys, JsValue> {
-    let root_secret = auth::StaticRootSecret::from_passphrase(passphrase.as_bytes());
+pub fn generate_user_keys(passphrase: &str, salt: Option<String>) -> Result<UserKeys, JsValue> {
+    let root_secret = root_secret_from_passphrase(passphrase, salt)?;
+
+    let (public_keys, secret_keys) = auth::derive_keys_from_static_root_secret(&root_secret);
+
+    Ok(UserKeys {
+        public_keys_bytes: public_keys.to_bytes(),
+        secret_keys_bytes: bincode::serde::encode_to_vec(&secret_keys, bincode::config::standard())
+            .map_err(|e| JsValue::from_str(&format!("Serialization error: {}", e)))?,
+    })
+}
+
+/// Generates a new BIP-39 mnemonic of `word_count` words (12, 15, 18, 21 or 24).
+#[wasm_bindgen]
+pub fn generate_mnemonic(word_count: usize) -> Result<String, JsValue> {
+    auth::Mnemonic::generate(word_count)
+        .map(|mnemonic| mnemonic.phrase().to_string())
+        .map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Checks that a mnemonic typed by a user is made of known words and has a valid checksum.
+#[wasm_bindgen]
+pub fn validate_mnemonic(mnemonic: &str) -> bool {
+    mnemonic.parse::<auth::Mnemonic>().is_ok()
+}
+
+/// Generates user keys from a BIP-39 mnemonic.
+///
+/// Throws if the mnemonic or the salt is invalid. A valid mnemonic gives the same keys as
+/// `generate_user_keys` called with its normalized phrase and the same salt.
+#[wasm_bindgen]
+pub fn generate_user_keys_from_mnemonic(
+    mnemonic: &str,
+    salt: Option<String>,
+) -> Result<UserKeys, JsValue> {
+    let mnemonic: auth::Mnemonic = mnemonic
+        .parse()
+        .map_err(|e: auth::MnemonicError| JsValue::from_str(&e.to_string()))?;
+    let root_secret = match salt {
+        Some(salt) => {
+            auth::StaticRootSecret::from_salted_mnemonic(&mnemonic, &parse_passphrase_salt(&salt)?)
+        }
+        None => auth::StaticRootSecret::from_mnemonic(&mnemonic),
+    };
+
+    let (public_keys, secret_keys) = auth::derive_keys_from_static_root_secret(&root_secret);
+
+    Ok(UserKeys {
+        public_keys_bytes: public_keys.to_bytes(),
+        secret_keys_bytes: bincode::serde::encode_to_vec(&secret_keys, bincode::config::standard())
+            .map_err(|e| JsValue::from_str(&format!("Serialization error: {}", e)))?,
+    })
+}
+
+/// Generates the keys of a later generation from a passphrase, for key rotation.
+///
+/// Generation 0 gives the same keys as `generate_user_keys` with the same salt.
+#[wasm_bindgen]
+pub fn generate_rotated_user_keys(
+    passphrase: &str,
+    generation: u32,
+    salt: Option<String>,
+) -> Result<UserKeys, JsValue> {
+    let root_secret = root_secret_from_passphrase(passphrase, salt)?;
+
+    let (public_keys, secret_keys) =
+        auth::derive_rotated_keys_from_static_root_secret(&root_secret, generation);
+
+    Ok(UserKeys {
+        public_keys_bytes: public_keys.to_bytes(),
+        secret_keys_bytes: bincode::serde::encode_to_vec(&secret_keys, bincode::config::standard())
+            .map_err(|e| JsValue::from_str(&format!("Serialization error: {}", e)))?,
+    })
+}
+
+/// Certificate, signed with a user's old keys, binding their new public keys.
+#[wasm_bindgen]
+pub struct RotationCertificate {
+    inner: auth::RotationCertificate,
+}
+
+#[wasm_bindgen]
+impl RotationCertificate {
+    /// Creates a certificate rotating from `old_pk` to `new_pk`, signed with `old_sk`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(old_pk: &UserPublicKeys, old_sk: &UserSecretKeys, new_pk: &UserPublicKeys) -> Self {
+        Self {
+            inner: auth::RotationCertificate::new(
+                old_pk.inner.clone(),
+                &old_sk.inner,
+                new_pk.inner.clone(),
+            ),
+        }
+    }
+
+    /// Verifies that the certificate is signed by its old keys.
+    pub fn verify(&self) -> bool {
+        self.inner.verify()
+    }
+
+    /// Gets the user ID before the rotation.
+    pub fn old_id(&self) -> Vec<u8> {
+        self.inner.old_id().as_bytes().to_vec()
+    }
+
+    /// Gets the user ID after the rotation.
+    pub fn new_id(&self) -> Vec<u8> {
+        self.inner.new_id().as_bytes().to_vec()
+    }
+
+    /// Gets the public keys after the rotation.
+    pub fn new_public_keys(&self) -> UserPublicKeys {
+        UserPublicKeys {
+            inner: self.inner.new_public_keys().clone(),
+        }
+    }
+
+    /// Serializes the certificate to bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.inner.to_bytes()
+    }
+
+    /// Deserializes a certificate from bytes. The certificate is not verified.
+    pub fn from_bytes(bytes: &[u8]) -> Result<RotationCertificate, JsValue> {
+        let inner = auth::RotationCertificate::from_bytes(bytes)
+            .map_err(|e| JsValue::from_str(&format!("Deserialization error: {}", e)))?;
+        Ok(RotationCertificate { inner })
+    }
+}
+
+/// Certifies the keys derived from a passphrase with `salt` using the keys derived from it
+/// with the fixed salt, to migrate an older account to a salted root secret.
+///
+/// The new keys are given by `generate_user_keys(passphrase, salt)`, and the old ones by
+/// `generate_user_keys(passphrase)`. The certificate is announced to peers like any key
+/// rotation, see `key_rotation_message`.
+#[wasm_bindgen]
+pub fn salt_migration_certificate(
+    passphrase: &str,
+    salt: &str,
+) -> Result<RotationCertificate, JsValue> {
+    let salt = parse_passphrase_salt(salt)?;
+    let (_, _, _, inner) = auth::migrate_to_salted_passphrase(passphrase.as_bytes(), &salt);
+    Ok(RotationCertificate { inner })
+}
+
+/// Builds the message announcing a key rotation to a peer, from serialized certificates
+/// ordered from the oldest to the latest.
+///
+/// The message is sent to peers through `SessionManagerWrapper.send_message`.
+#[wasm_bindgen]
+pub fn key_rotation_message(certificates: js_sys::Array) -> Result<Vec<u8>, JsValue> {
+    let chain = certificates
+        .iter()
+        .map(|bytes| {
+            auth::RotationCertificate::from_bytes(&js_sys::Uint8Array::new(&bytes).to_vec())
+                .map_err(|e| JsValue::from_str(&format!("Deserialization error: {}", e)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(sessions::key_rotation_message(&chain))
+}
+
+/// Checks whether a received message announces a key rotation.
+#[wasm_bindgen]
+pub fn is_key_rotation_message(message: &[u8]) -> bool {
+    sessions::is_key_rotation_message(message)
+}
+
+/// Encodes a 32-byte user ID as text (`gossip1...`).
+#[wasm_bindgen]
+pub fn user_id_to_string(user_id: &[u8]) -> Result<String, JsValue> {
+    let user_id: [u8; 32] = user_id
+        .try_into()
+        .map_err(|_| JsValue::from_str("User ID must be 32 bytes"))?;
+    Ok(auth::UserId::from_bytes(user_id).to_string())
+}
+
+/// Parses the text encoding of a user ID, as typed or pasted by a user, into its 32 bytes.
+///
+/// Fails on typos, truncation, or strings that are not user IDs.
+#[wasm_bindgen]
+pub fn parse_user_id(text: &str) -> Result<Vec<u8>, JsValue> {
+    let user_id: auth::UserId = text
+        .parse()
+        .map_err(|e: auth::UserIdParseError| JsValue::from_str(&e.to_string()))?;
+    Ok(user_id.as_bytes().to_vec())
+}
+
+/// Splits the root secret derived from a passphrase into `share_count` recovery shares, any
+/// `threshold` of which recover the user keys.
+///
+/// Returns one session message per trusted contact, to send with
+/// `SessionManagerWrapper.send_message`. Contacts keep the message and send it back on request.
+/// The shares carry the root secret itself: recovering does not need the passphrase salt.
+#[wasm_bindgen]
+pub fn split_root_secret(
+    passphrase: &str,
+    threshold: u8,
+    share_count: u8,
+    salt: Option<String>,
+) -> Result<js_sys::Array, JsValue> {
+    let root_secret = root_secret_from_passphrase(passphrase, salt)?;
+
+    let shares = auth::split_root_secret(&root_secret, threshold, share_count)
+        .map_err(recovery_error_to_js)?;
+    let array = js_sys::Array::new();
+    for share in &shares {
+        let message = sessions::recovery_share_message(share);
+        array.push(&js_sys::Uint8Array::from(message.as_slice()));
+    }
+    Ok(array)
+}
+
+/// Checks whether a received message carries a recovery share.
+#[wasm_bindgen]
+pub fn is_recovery_share_message(message: &[u8]) -> bool {
+    sessions::is_recovery_share_message(message)
+}
+
+/// Recovers the user keys from recovery share messages sent back by enough trusted contacts.
+#[wasm_bindgen]
+pub fn recover_user_keys(messages: js_sys::Array) -> Result<UserKeys, JsValue> {
+    let shares = messages
+        .iter()
+        .map(|message| {
+            sessions::parse_recovery_share_message(&js_sys::Uint8Array::new(&message).to_vec())
+                .map_err(recovery_error_to_js)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let root_secret = auth::recover_root_secret(&shares).map_err(recovery_error_to_js)?;
 
     let (public_keys, secret_keys) = auth::derive_keys_from_static_root_secret(&root_secret);
 
@@ -364,6 +682,28 @@ pub fn aead_decrypt(
     crypto_aead::decrypt(&key.inner, &nonce.inner, ciphertext, aad)
 }
 
+/// Replaces the random source with a ChaCha20 generator seeded with 32 bytes, so that keys,
+/// nonces and messages can be compared with test vectors generated by the Rust build.
+///
+/// Only available in builds with the `seeded-rng` feature. Every output becomes predictable:
+/// never use in production.
+#[cfg(feature = "seeded-rng")]
+#[wasm_bindgen]
+pub fn seed_rng(seed: &[u8]) -> Result<(), JsValue> {
+    let seed: [u8; crypto_rng::SEED_SIZE] = seed
+        .try_into()
+        .map_err(|_| JsValue::from_str("Seed must be 32 bytes"))?;
+    crypto_rng::seed(seed);
+    Ok(())
+}
+
+/// Restores the system random source after `seed_rng`.
+#[cfg(feature = "seeded-rng")]
+#[wasm_bindgen]
+pub fn clear_rng_seed() {
+    crypto_rng::clear_seed();
+}
+
 /// Session status indicating the state of a peer session.
 #[wasm_bindgen]
 pub enum SessionStatus {
@@ -463,7 +803,174 @@ impl ReceiveMessageOutput {
     }
 }
 
+/// A change to the encrypted state of one peer, to apply to per-peer storage.
+#[wasm_bindgen]
+pub struct PeerStateUpdate {
+    peer_id: Vec<u8>,
+    encrypted_state: Option<Vec<u8>>,
+}
+
+#[wasm_bindgen]
+impl PeerStateUpdate {
+    /// Gets the peer's user id (32 bytes).
+    #[wasm_bindgen(getter)]
+    pub fn peer_id(&self) -> Vec<u8> {
+        self.peer_id.clone()
+    }
+
+    /// Gets the new encrypted state of the peer, or `undefined` if the peer
+    /// was discarded and its stored state must be deleted.
+    #[wasm_bindgen(getter)]
+    pub fn encrypted_state(&self) -> Option<Vec<u8>> {
+        self.encrypted_state.clone()
+    }
+}
+
+/// An announcement re-establishing a broken session with a peer.
+#[wasm_bindgen]
+pub struct HealingAnnouncement {
+    peer_id: Vec<u8>,
+    announcement: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl HealingAnnouncement {
+    /// Gets the peer's user id (32 bytes).
+    #[wasm_bindgen(getter)]
+    pub fn peer_id(&self) -> Vec<u8> {
+        self.peer_id.clone()
+    }
+
+    /// Gets the announcement bytes to publish to the blockchain.
+    #[wasm_bindgen(getter)]
+    pub fn announcement(&self) -> Vec<u8> {
+        self.announcement.clone()
+    }
+}
+
+/// Write-only storage collecting the peer state updates of one persist,
+/// so that JavaScript can apply them to an asynchronous store.
+#[derive(Default)]
+struct PeerStateUpdates(Vec<PeerStateUpdate>);
+
+impl sessions::SessionStorage for PeerStateUpdates {
+    type Error = std::convert::Infallible;
+
+    fn write_peer(
+        &mut self,
+        peer_id: &auth::UserId,
+        encrypted_state: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.0.push(PeerStateUpdate {
+            peer_id: peer_id.as_bytes().to_vec(),
+            encrypted_state: Some(encrypted_state.to_vec()),
+        });
+        Ok(())
+    }
+
+    fn delete_peer(&mut self, peer_id: &auth::UserId) -> Result<(), Self::Error> {
+        self.0.push(PeerStateUpdate {
+            peer_id: peer_id.as_bytes().to_vec(),
+            encrypted_state: None,
+        });
+        Ok(())
+    }
+
+    fn read_peers(&self) -> Result<Vec<(auth::UserId, Vec<u8>)>, Self::Error> {
+        Ok(Vec::new())
+    }
+}
+
+/// Builds a JS `Error` with a stable `code` property so that the application
+/// can tell failures apart without parsing error messages.
+fn js_error_with_code(code: &str, message: &str) -> JsValue {
+    let error = js_sys::Error::new(message);
+    let _ = js_sys::Reflect::set(&error, &JsValue::from_str("code"), &JsValue::from_str(code));
+    error.into()
+}
+
+/// Converts an announcement error into a JS error.
+///
+/// Codes: `ANNOUNCEMENT_MALFORMED`, `ANNOUNCEMENT_UNDECRYPTABLE`, `ANNOUNCEMENT_INVALID_AUTH`,
+/// `ANNOUNCEMENT_TOO_OLD`, `ANNOUNCEMENT_TOO_FAR_IN_FUTURE`, `ANNOUNCEMENT_OUTDATED`,
+/// `ANNOUNCEMENT_UNSUPPORTED_VERSION`.
+fn announcement_error_to_js(error: sessions::AnnouncementError) -> JsValue {
+    let code = match error {
+        sessions::AnnouncementError::Malformed => "ANNOUNCEMENT_MALFORMED",
+        sessions::AnnouncementError::Undecryptable => "ANNOUNCEMENT_UNDECRYPTABLE",
+        sessions::AnnouncementError::InvalidAuthentication => "ANNOUNCEMENT_INVALID_AUTH",
+        sessions::AnnouncementError::TooOld => "ANNOUNCEMENT_TOO_OLD",
+        sessions::AnnouncementError::TooFarInFuture => "ANNOUNCEMENT_TOO_FAR_IN_FUTURE",
+        sessions::AnnouncementError::Outdated => "ANNOUNCEMENT_OUTDATED",
+        sessions::AnnouncementError::UnsupportedProtocolVersion => {
+            "ANNOUNCEMENT_UNSUPPORTED_VERSION"
+        }
+    };
+    js_error_with_code(code, &error.to_string())
+}
+
+/// Converts a session error into a JS error.
+///
+/// Codes: `NO_ACTIVE_SESSION`, `SESSION_SATURATED`, `UNKNOWN_SEEKER`, `MESSAGE_MALFORMED`,
+/// `MESSAGE_INVALID_SIGNATURE`, `MESSAGE_UNDECRYPTABLE`, `MESSAGE_TOO_OLD`,
+/// `MESSAGE_TOO_FAR_IN_FUTURE`, `STATE_UNDECRYPTABLE`, `STATE_MALFORMED`,
+/// `STATE_UNSUPPORTED_VERSION`.
+fn session_error_to_js(error: sessions::SessionError) -> JsValue {
+    let code = match error {
+        sessions::SessionError::NoActiveSession => "NO_ACTIVE_SESSION",
+        sessions::SessionError::Saturated => "SESSION_SATURATED",
+        sessions::SessionError::UnknownSeeker => "UNKNOWN_SEEKER",
+        sessions::SessionError::MalformedMessage => "MESSAGE_MALFORMED",
+        sessions::SessionError::InvalidSignature => "MESSAGE_INVALID_SIGNATURE",
+        sessions::SessionError::DecryptionFailed => "MESSAGE_UNDECRYPTABLE",
+        sessions::SessionError::MessageTooOld => "MESSAGE_TOO_OLD",
+        sessions::SessionError::MessageTooFarInFuture => "MESSAGE_TOO_FAR_IN_FUTURE",
+        sessions::SessionError::StateUndecryptable => "STATE_UNDECRYPTABLE",
+        sessions::SessionError::StateMalformed => "STATE_MALFORMED",
+        sessions::SessionError::UnsupportedStateVersion => "STATE_UNSUPPORTED_VERSION",
+    };
+    js_error_with_code(code, &error.to_string())
+}
+
+/// Converts a key rotation error into a JS error.
+///
+/// Codes: `ROTATION_NOT_ROTATION_MESSAGE`, `ROTATION_MALFORMED`, `ROTATION_UNKNOWN_PEER`,
+/// `ROTATION_INVALID_CERTIFICATE`, `ROTATION_IDENTITY_IN_USE`.
+fn key_rotation_error_to_js(error: sessions::KeyRotationError) -> JsValue {
+    let code = match error {
+        sessions::KeyRotationError::NotKeyRotationMessage => "ROTATION_NOT_ROTATION_MESSAGE",
+        sessions::KeyRotationError::MalformedMessage => "ROTATION_MALFORMED",
+        sessions::KeyRotationError::UnknownPeer => "ROTATION_UNKNOWN_PEER",
+        sessions::KeyRotationError::InvalidCertificate => "ROTATION_INVALID_CERTIFICATE",
+        sessions::KeyRotationError::IdentityInUse => "ROTATION_IDENTITY_IN_USE",
+    };
+    js_error_with_code(code, &error.to_string())
+}
+
+/// Converts a recovery error into a JS error.
+///
+/// Codes: `RECOVERY_INVALID_THRESHOLD`, `RECOVERY_SHARE_MALFORMED`,
+/// `RECOVERY_SHARE_UNSUPPORTED_VERSION`, `RECOVERY_SHARE_CORRUPTED`, `RECOVERY_NOT_ENOUGH_SHARES`,
+/// `RECOVERY_MISMATCHED_SHARES`, `RECOVERY_INVALID_SECRET`.
+fn recovery_error_to_js(error: auth::RecoveryError) -> JsValue {
+    let code = match error {
+        auth::RecoveryError::InvalidThreshold => "RECOVERY_INVALID_THRESHOLD",
+        auth::RecoveryError::InvalidLength | auth::RecoveryError::Malformed => {
+            "RECOVERY_SHARE_MALFORMED"
+        }
+        auth::RecoveryError::UnsupportedVersion => "RECOVERY_SHARE_UNSUPPORTED_VERSION",
+        auth::RecoveryError::ChecksumMismatch => "RECOVERY_SHARE_CORRUPTED",
+        auth::RecoveryError::NotEnoughShares => "RECOVERY_NOT_ENOUGH_SHARES",
+        auth::RecoveryError::MismatchedShares => "RECOVERY_MISMATCHED_SHARES",
+        auth::RecoveryError::InvalidSecret => "RECOVERY_INVALID_SECRET",
+    };
+    js_error_with_code(code, &error.to_string())
+}
+
 /// Session manager wrapper for WebAssembly.
+///
+/// Failing methods throw a JS `Error` whose `code` property identifies the failure
+/// (e.g. `ANNOUNCEMENT_TOO_OLD` or `SESSION_SATURATED`).
 #[wasm_bindgen]
 pub struct SessionManagerWrapper {
     inner: sessions::SessionManager,
@@ -480,12 +987,16 @@ impl SessionManagerWrapper {
     }
 
     /// Deserializes a session manager from an encrypted blob.
+    ///
+    /// Throws with code `STATE_UNDECRYPTABLE` if the key is wrong or the blob is corrupted,
+    /// `STATE_UNSUPPORTED_VERSION` if the blob was written by a newer version,
+    /// or `STATE_MALFORMED` if the decrypted state cannot be parsed.
     pub fn from_encrypted_blob(
         encrypted_blob: &[u8],
         key: &EncryptionKey,
     ) -> Result<SessionManagerWrapper, JsValue> {
         let inner = sessions::SessionManager::from_encrypted_blob(encrypted_blob, &key.inner)
-            .ok_or_else(|| JsValue::from_str("Failed to decrypt session manager"))?;
+            .map_err(session_error_to_js)?;
         Ok(Self { inner })
     }
 
@@ -496,6 +1007,62 @@ impl SessionManagerWrapper {
             .ok_or_else(|| JsValue::from_str("Failed to encrypt session manager"))
     }
 
+    /// Restores a session manager from per-peer states produced by `take_peer_state_updates`.
+    ///
+    /// `peer_states` is an array of `[peerId, encryptedState]` pairs of `Uint8Array`s.
+    ///
+    /// Throws with code `STATE_UNDECRYPTABLE` if a state cannot be decrypted (wrong key,
+    /// corrupted state or state stored under another peer id), or `STATE_MALFORMED`
+    /// if a decrypted state cannot be parsed.
+    pub fn from_peer_states(
+        config: SessionConfig,
+        peer_states: js_sys::Array,
+        key: &EncryptionKey,
+    ) -> Result<SessionManagerWrapper, JsValue> {
+        let mut storage = sessions::MemoryStorage::new();
+        for entry in peer_states.iter() {
+            let pair = js_sys::Array::from(&entry);
+            let peer_id: [u8; 32] = js_sys::Uint8Array::new(&pair.get(0))
+                .to_vec()
+                .try_into()
+                .map_err(|_| JsValue::from_str("Peer ID must be 32 bytes"))?;
+            let encrypted_state = js_sys::Uint8Array::new(&pair.get(1)).to_vec();
+            let Ok(()) = sessions::SessionStorage::write_peer(
+                &mut storage,
+                &auth::UserId::from_bytes(peer_id),
+                &encrypted_state,
+            );
+        }
+
+        let inner = sessions::SessionManager::restore_from(config.inner, &storage, &key.inner)
+            .map_err(|error| match error {
+                sessions::StorageError::Backend(never) => match never {},
+                sessions::StorageError::State(error) => session_error_to_js(error),
+            })?;
+        Ok(Self { inner })
+    }
+
+    /// Encrypts the state of each peer that changed since the previous call.
+    ///
+    /// Returns an array of `PeerStateUpdate`s to write to storage, keyed by peer id.
+    /// Updates without an encrypted state are for discarded peers and must be deleted.
+    /// A manager created with `from_encrypted_blob` returns all its peers on the first call,
+    /// which migrates whole-blob persistence to per-peer storage.
+    pub fn take_peer_state_updates(&mut self, key: &EncryptionKey) -> js_sys::Array {
+        let mut updates = PeerStateUpdates::default();
+        let Ok(_) = self.inner.persist_to(&mut updates, &key.inner);
+        let array = js_sys::Array::new();
+        for update in updates.0 {
+            array.push(&JsValue::from(update));
+        }
+        array
+    }
+
+    /// Returns `true` if some peer state changed since the last `take_peer_state_updates`.
+    pub fn has_unpersisted_changes(&self) -> bool {
+        self.inner.has_unpersisted_changes()
+    }
+
     /// Establishes an outgoing session with a peer.
     ///
     /// # Parameters
@@ -551,7 +1118,8 @@ impl SessionManagerWrapper {
     /// - The timestamp when the announcement was created (milliseconds since Unix epoch)
     /// - The user data embedded in the announcement
     ///
-    /// Returns `None` if the announcement is invalid or too old.
+    /// Throws if the announcement is rejected. Announcements addressed to someone else
+    /// throw with code `ANNOUNCEMENT_UNDECRYPTABLE`.
     ///
     /// # Security Warning
     ///
@@ -570,10 +1138,11 @@ impl SessionManagerWrapper {
         announcement_bytes: &[u8],
         our_pk: &UserPublicKeys,
         our_sk: &UserSecretKeys,
-    ) -> Option<AnnouncementResult> {
+    ) -> Result<AnnouncementResult, JsValue> {
         self.inner
             .feed_incoming_announcement(announcement_bytes, &our_pk.inner, &our_sk.inner)
             .map(|result| AnnouncementResult { inner: result })
+            .map_err(announcement_error_to_js)
     }
 
     /// Gets the list of message board seekers to monitor.
@@ -588,11 +1157,13 @@ impl SessionManagerWrapper {
     }
 
     /// Sends a message to a peer.
+    ///
+    /// Throws with code `NO_ACTIVE_SESSION` or `SESSION_SATURATED` if the message cannot be sent.
     pub fn send_message(
         &mut self,
         peer_id: &[u8],
         message_contents: &[u8],
-    ) -> Result<Option<SendMessageOutput>, JsValue> {
+    ) -> Result<SendMessageOutput, JsValue> {
         if peer_id.len() != 32 {
             return Err(JsValue::from_str("Peer ID must be 32 bytes"));
         }
@@ -600,22 +1171,27 @@ impl SessionManagerWrapper {
         peer_id_arr.copy_from_slice(peer_id);
         let peer_id = auth::UserId::from_bytes(peer_id_arr);
 
-        Ok(self
-            .inner
+        self.inner
             .send_message(&peer_id, message_contents)
             .map(|output| SendMessageOutput {
                 seeker: output.seeker.clone(),
                 data: output.data.clone(),
-            }))
+            })
+            .map_err(session_error_to_js)
     }
 
     /// Processes an incoming message from the message board.
+    ///
+    /// Throws if the message is rejected. Apart from `UNKNOWN_SEEKER`, `MESSAGE_TOO_OLD`
+    /// and `MESSAGE_TOO_FAR_IN_FUTURE`, a rejection closes the session the message
+    /// was addressed to. A message rejected for its timestamp is not received, and can
+    /// be fed again once its timestamp is within bounds.
     pub fn feed_incoming_message_board_read(
         &mut self,
         seeker: &[u8],
         ciphertext: &[u8],
         our_sk: &UserSecretKeys,
-    ) -> Option<ReceiveMessageOutput> {
+    ) -> Result<ReceiveMessageOutput, JsValue> {
         self.inner
             .feed_incoming_message_board_read(seeker, ciphertext, &our_sk.inner)
             .map(|output| {
@@ -632,6 +1208,32 @@ impl SessionManagerWrapper {
                     user_id: output.user_id.clone(),
                 }
             })
+            .map_err(session_error_to_js)
+    }
+
+    /// Processes a key rotation message received from a peer, moving the peer's sessions to
+    /// their new keys. Returns the new peer ID.
+    pub fn feed_key_rotation_message(&mut self, message: &[u8]) -> Result<Vec<u8>, JsValue> {
+        let chain =
+            sessions::parse_key_rotation_message(message).map_err(key_rotation_error_to_js)?;
+        self.inner
+            .feed_key_rotation(&chain)
+            .map(|peer_id| peer_id.as_bytes().to_vec())
+            .map_err(key_rotation_error_to_js)
+    }
+
+    /// Sets our secret keys from before our last rotations, as serialized `UserSecretKeys`,
+    /// to decrypt messages peers sent before learning our new keys.
+    pub fn set_previous_secret_keys(&mut self, keys: js_sys::Array) -> Result<(), JsValue> {
+        let keys = keys
+            .iter()
+            .map(|bytes| {
+                UserSecretKeys::from_bytes(&js_sys::Uint8Array::new(&bytes).to_vec())
+                    .map(|keys| keys.inner)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        self.inner.set_previous_secret_keys(keys);
+        Ok(())
     }
 
     /// Gets the list of all peer IDs.
@@ -678,6 +1280,56 @@ impl SessionManagerWrapper {
         Ok(())
     }
 
+    /// Gets the safety number of a peer, or `undefined` if the peer's keys are not known yet.
+    pub fn peer_safety_number(
+        &self,
+        peer_id: &[u8],
+        our_pk: &UserPublicKeys,
+    ) -> Result<Option<SafetyNumber>, JsValue> {
+        if peer_id.len() != 32 {
+            return Err(JsValue::from_str("Peer ID must be 32 bytes"));
+        }
+        let mut peer_id_arr = [0u8; 32];
+        peer_id_arr.copy_from_slice(peer_id);
+        let peer_id = auth::UserId::from_bytes(peer_id_arr);
+
+        Ok(self
+            .inner
+            .peer_safety_number(&peer_id, &our_pk.inner)
+            .map(|inner| SafetyNumber { inner }))
+    }
+
+    /// Records that the keys of a peer were verified out-of-band.
+    /// Returns false if the peer is unknown.
+    pub fn mark_peer_verified(&mut self, peer_pk: &UserPublicKeys) -> bool {
+        self.inner.mark_peer_verified(&peer_pk.inner)
+    }
+
+    /// Clears the verification of a peer.
+    pub fn mark_peer_unverified(&mut self, peer_id: &[u8]) -> Result<(), JsValue> {
+        if peer_id.len() != 32 {
+            return Err(JsValue::from_str("Peer ID must be 32 bytes"));
+        }
+        let mut peer_id_arr = [0u8; 32];
+        peer_id_arr.copy_from_slice(peer_id);
+        let peer_id = auth::UserId::from_bytes(peer_id_arr);
+
+        self.inner.mark_peer_unverified(&peer_id);
+        Ok(())
+    }
+
+    /// Checks whether the keys of a peer were verified and have not changed since.
+    pub fn is_peer_verified(&self, peer_id: &[u8]) -> Result<bool, JsValue> {
+        if peer_id.len() != 32 {
+            return Err(JsValue::from_str("Peer ID must be 32 bytes"));
+        }
+        let mut peer_id_arr = [0u8; 32];
+        peer_id_arr.copy_from_slice(peer_id);
+        let peer_id = auth::UserId::from_bytes(peer_id_arr);
+
+        Ok(self.inner.is_peer_verified(&peer_id))
+    }
+
     /// Refreshes sessions and returns peer IDs that need keep-alive messages.
     pub fn refresh(&mut self) -> js_sys::Array {
         let peers = self.inner.refresh();
@@ -688,4 +1340,22 @@ impl SessionManagerWrapper {
         }
         array
     }
+
+    /// Returns the announcements re-establishing broken sessions that are due,
+    /// as an array of `HealingAnnouncement`. Call it periodically, next to `refresh`,
+    /// and publish the announcements to the blockchain.
+    pub fn heal_sessions(
+        &mut self,
+        our_pk: &UserPublicKeys,
+        our_sk: &UserSecretKeys,
+    ) -> js_sys::Array {
+        let array = js_sys::Array::new();
+        for (peer_id, announcement) in self.inner.heal_sessions(&our_pk.inner, &our_sk.inner) {
+            array.push(&JsValue::from(HealingAnnouncement {
+                peer_id: peer_id.as_bytes().to_vec(),
+                announcement,
+            }));
+        }
+        array
+    }
 }
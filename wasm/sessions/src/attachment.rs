@@ -0,0 +1,353 @@
+//! Encrypted attachments.
+//!
+//! Attachments are too large to be sent through sessions, even fragmented. Instead, an
+//! attachment is encrypted under a fresh random key with the streaming AEAD of `crypto-aead`,
+//! and the ciphertext is stored off-board (any untrusted storage). Only a small
+//! [`AttachmentDescriptor`] is sent through the session, carrying the key, the hash of the
+//! ciphertext to locate and check it, and the size of the attachment.
+//!
+//! # Integrity
+//!
+//! The descriptor is authenticated by the session it is sent through. The downloaded ciphertext
+//! is checked against the hash in the descriptor before decryption, and each chunk is
+//! authenticated by the streaming AEAD, which also detects reordered or truncated chunks.
+//!
+//! # Wire format
+//!
+//! Descriptors are session messages starting with [`ATTACHMENT_MESSAGE_PREFIX`], followed by the
+//! serialized descriptor. Like group messages and fragments, they cannot be confused with text
+//! messages. Use [`is_attachment`] to route incoming messages.
+//!
+//! The ciphertext is the concatenation of the encrypted chunks, each chunk being
+//! `chunk_size` bytes of the attachment followed by a
+//! [`STREAM_CHUNK_OVERHEAD`](crypto_aead::STREAM_CHUNK_OVERHEAD) bytes tag. The last chunk may be
+//! shorter, and an empty attachment is a single empty chunk.
+
+use crate::error::AttachmentError;
+use crypto_aead::{
+    STREAM_CHUNK_OVERHEAD, STREAM_NONCE_PREFIX_SIZE, StreamDecryptor, StreamEncryptor,
+};
+use serde::{Deserialize, Serialize};
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+/// Prefix of the session messages that carry an attachment descriptor.
+///
+/// `0xFF` never appears in UTF-8 text.
+pub const ATTACHMENT_MESSAGE_PREFIX: [u8; 4] = [0xFF, b'A', b'T', b'T'];
+
+/// Default number of attachment bytes per encrypted chunk.
+pub const DEFAULT_ATTACHMENT_CHUNK_SIZE: u32 = 64 * 1024;
+
+/// Size of the ciphertext hash carried by descriptors.
+pub const ATTACHMENT_HASH_SIZE: usize = 32;
+
+/// Returns `true` if a message received through a session is an attachment descriptor,
+/// and should be parsed with [`AttachmentDescriptor::from_bytes`].
+pub fn is_attachment(message: &[u8]) -> bool {
+    message.starts_with(&ATTACHMENT_MESSAGE_PREFIX)
+}
+
+/// Describes an encrypted attachment stored off-board.
+///
+/// Holds the key of the attachment: it must only be sent through a session.
+#[derive(Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
+pub struct AttachmentDescriptor {
+    /// Key of the streaming AEAD ([`crypto_aead::KEY_SIZE`] bytes)
+    key: Vec<u8>,
+    nonce_prefix: [u8; STREAM_NONCE_PREFIX_SIZE],
+    /// Number of attachment bytes per chunk
+    chunk_size: u32,
+    /// Size of the attachment in bytes
+    size: u64,
+    /// Hash of the whole ciphertext
+    ciphertext_hash: [u8; ATTACHMENT_HASH_SIZE],
+}
+
+fn ciphertext_hash(ciphertext: &[u8]) -> [u8; ATTACHMENT_HASH_SIZE] {
+    *massa_hash::Hash::compute_from(ciphertext).to_bytes()
+}
+
+/// Returns the size of the ciphertext of an attachment,
+/// or `None` if it doesn't fit in a `u64`.
+fn ciphertext_size(size: u64, chunk_size: u32) -> Option<u64> {
+    let chunk_count = size.div_ceil(u64::from(chunk_size)).max(1);
+    chunk_count
+        .checked_mul(STREAM_CHUNK_OVERHEAD as u64)?
+        .checked_add(size)
+}
+
+/// Encrypts an attachment under a fresh random key.
+///
+/// Returns the descriptor to send through a session, and the ciphertext to store off-board.
+///
+/// # Panics
+///
+/// Panics if `chunk_size` is 0.
+pub fn encrypt_attachment(contents: &[u8], chunk_size: u32) -> (AttachmentDescriptor, Vec<u8>) {
+    assert!(chunk_size > 0, "Attachment chunk size must not be 0");
+
+    // generate key and nonce prefix
+    let mut key_bytes = Zeroizing::new([0u8; crypto_aead::KEY_SIZE]);
+    crypto_rng::fill_buffer(key_bytes.as_mut());
+    let key = crypto_aead::Key::from(*key_bytes);
+    let mut nonce_prefix = [0u8; STREAM_NONCE_PREFIX_SIZE];
+    crypto_rng::fill_buffer(&mut nonce_prefix);
+
+    // encrypt chunk by chunk
+    let chunk_size_usize = usize::try_from(chunk_size).unwrap_or(usize::MAX);
+    let chunks: Vec<&[u8]> = if contents.is_empty() {
+        vec![contents]
+    } else {
+        contents.chunks(chunk_size_usize).collect()
+    };
+    let (last_chunk, chunks) = chunks.split_last().expect("At least one chunk");
+    let mut encryptor = StreamEncryptor::new(&key, nonce_prefix);
+    let mut ciphertext =
+        Vec::with_capacity(contents.len() + (chunks.len() + 1) * STREAM_CHUNK_OVERHEAD);
+    for chunk in chunks {
+        ciphertext.extend(encryptor.encrypt_next(chunk, b""));
+    }
+    ciphertext.extend(encryptor.encrypt_last(last_chunk, b""));
+
+    let descriptor = AttachmentDescriptor {
+        key: key_bytes.to_vec(),
+        nonce_prefix,
+        chunk_size,
+        size: contents.len() as u64,
+        ciphertext_hash: ciphertext_hash(&ciphertext),
+    };
+    (descriptor, ciphertext)
+}
+
+impl AttachmentDescriptor {
+    /// Returns the size of the attachment in bytes.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Returns the size of the ciphertext in bytes.
+    pub fn ciphertext_size(&self) -> u64 {
+        ciphertext_size(self.size, self.chunk_size).expect("Checked when parsed")
+    }
+
+    /// Returns the hash of the ciphertext, which can be used to locate it in storage.
+    pub fn ciphertext_hash(&self) -> &[u8; ATTACHMENT_HASH_SIZE] {
+        &self.ciphertext_hash
+    }
+
+    /// Serializes the descriptor into a message to send through a session.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let serialized = Zeroizing::new(
+            bincode::serde::encode_to_vec(self, bincode::config::standard())
+                .expect("Failed to serialize attachment descriptor"),
+        );
+        [ATTACHMENT_MESSAGE_PREFIX.as_slice(), &serialized].concat()
+    }
+
+    /// Parses a descriptor received through a session.
+    ///
+    /// # Errors
+    ///
+    /// * [`AttachmentError::NotAttachment`] - If the message is not an attachment descriptor
+    /// * [`AttachmentError::MalformedDescriptor`] - If the descriptor cannot be parsed or is invalid
+    pub fn from_bytes(message: &[u8]) -> Result<Self, AttachmentError> {
+        let serialized = message
+            .strip_prefix(ATTACHMENT_MESSAGE_PREFIX.as_slice())
+            .ok_or(AttachmentError::NotAttachment)?;
+        let (descriptor, read): (Self, usize) =
+            bincode::serde::decode_from_slice(serialized, bincode::config::standard())
+                .map_err(|_| AttachmentError::MalformedDescriptor)?;
+        if read != serialized.len()
+            || descriptor.key.len() != crypto_aead::KEY_SIZE
+            || descriptor.chunk_size == 0
+            || ciphertext_size(descriptor.size, descriptor.chunk_size).is_none()
+        {
+            return Err(AttachmentError::MalformedDescriptor);
+        }
+        Ok(descriptor)
+    }
+
+    /// Checks and decrypts the ciphertext of the attachment.
+    ///
+    /// # Errors
+    ///
+    /// * [`AttachmentError::CiphertextMismatch`] - If the ciphertext doesn't match the size and
+    ///   hash of the descriptor (wrong or corrupted download)
+    /// * [`AttachmentError::DecryptionFailed`] - If the ciphertext matches the descriptor but
+    ///   cannot be decrypted with it (the sender produced an invalid descriptor)
+    pub fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>, AttachmentError> {
+        // check the ciphertext before decrypting it
+        if ciphertext.len() as u64 != self.ciphertext_size()
+            || ciphertext_hash(ciphertext) != self.ciphertext_hash
+        {
+            return Err(AttachmentError::CiphertextMismatch);
+        }
+
+        // decrypt chunk by chunk
+        let key_bytes: [u8; crypto_aead::KEY_SIZE] =
+            self.key.as_slice().try_into().expect("Checked when parsed");
+        let key = crypto_aead::Key::from(key_bytes);
+        let encrypted_chunk_size = usize::try_from(self.chunk_size)
+            .unwrap_or(usize::MAX)
+            .saturating_add(STREAM_CHUNK_OVERHEAD);
+        let encrypted_chunks: Vec<&[u8]> = ciphertext.chunks(encrypted_chunk_size).collect();
+        let (last_chunk, chunks) = encrypted_chunks
+            .split_last()
+            .ok_or(AttachmentError::DecryptionFailed)?;
+        let mut decryptor = StreamDecryptor::new(&key, self.nonce_prefix);
+        let mut contents = Vec::with_capacity(usize::try_from(self.size).unwrap_or(0));
+        for chunk in chunks {
+            let decrypted = Zeroizing::new(
+                decryptor
+                    .decrypt_next(chunk, b"")
+                    .ok_or(AttachmentError::DecryptionFailed)?,
+            );
+            contents.extend_from_slice(&decrypted);
+        }
+        let decrypted = Zeroizing::new(
+            decryptor
+                .decrypt_last(last_chunk, b"")
+                .ok_or(AttachmentError::DecryptionFailed)?,
+        );
+        contents.extend_from_slice(&decrypted);
+        Ok(contents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attachment_roundtrip() {
+        for size in [0, 1, 99, 100, 101, 350] {
+            let contents: Vec<u8> = (0..size).map(|i| i as u8).collect();
+            let (descriptor, ciphertext) = encrypt_attachment(&contents, 100);
+
+            assert_eq!(descriptor.size(), size as u64);
+            assert_eq!(descriptor.ciphertext_size(), ciphertext.len() as u64);
+            assert_eq!(descriptor.decrypt(&ciphertext).unwrap(), contents);
+        }
+    }
+
+    #[test]
+    fn test_descriptor_message_roundtrip() {
+        let (descriptor, ciphertext) =
+            encrypt_attachment(b"picture", DEFAULT_ATTACHMENT_CHUNK_SIZE);
+
+        let message = descriptor.to_bytes();
+        assert!(is_attachment(&message));
+        let received = AttachmentDescriptor::from_bytes(&message).unwrap();
+        assert_eq!(received.size(), 7);
+        assert_eq!(received.ciphertext_hash(), descriptor.ciphertext_hash());
+        assert_eq!(received.decrypt(&ciphertext).unwrap(), b"picture");
+    }
+
+    #[test]
+    fn test_fresh_key_per_attachment() {
+        let (descriptor1, ciphertext1) = encrypt_attachment(b"same", 100);
+        let (descriptor2, ciphertext2) = encrypt_attachment(b"same", 100);
+        assert_ne!(ciphertext1, ciphertext2);
+        assert_eq!(
+            descriptor1.decrypt(&ciphertext2).err(),
+            Some(AttachmentError::CiphertextMismatch)
+        );
+        assert_eq!(
+            descriptor2.decrypt(&ciphertext1).err(),
+            Some(AttachmentError::CiphertextMismatch)
+        );
+    }
+
+    #[test]
+    fn test_tampered_ciphertext_rejected() {
+        let contents = [5u8; 250];
+        let (descriptor, ciphertext) = encrypt_attachment(&contents, 100);
+
+        // modified
+        let mut tampered = ciphertext.clone();
+        tampered[120] ^= 1;
+        assert_eq!(
+            descriptor.decrypt(&tampered).err(),
+            Some(AttachmentError::CiphertextMismatch)
+        );
+
+        // truncated at a chunk boundary
+        let truncated = &ciphertext[..2 * (100 + STREAM_CHUNK_OVERHEAD)];
+        assert_eq!(
+            descriptor.decrypt(truncated).err(),
+            Some(AttachmentError::CiphertextMismatch)
+        );
+    }
+
+    #[test]
+    fn test_invalid_descriptor_fails_decryption() {
+        let contents = [5u8; 250];
+        let (mut descriptor, ciphertext) = encrypt_attachment(&contents, 100);
+
+        // the ciphertext matches the hash, but the key is wrong
+        descriptor.key = vec![0u8; crypto_aead::KEY_SIZE];
+        assert_eq!(
+            descriptor.decrypt(&ciphertext).err(),
+            Some(AttachmentError::DecryptionFailed)
+        );
+    }
+
+    #[test]
+    fn test_text_message_is_not_attachment() {
+        assert!(!is_attachment(b"hello"));
+        assert_eq!(
+            AttachmentDescriptor::from_bytes(b"hello").err(),
+            Some(AttachmentError::NotAttachment)
+        );
+    }
+
+    #[test]
+    fn test_malformed_descriptor_rejected() {
+        let (descriptor, _) = encrypt_attachment(b"file", 100);
+        let message = descriptor.to_bytes();
+
+        // truncated
+        assert_eq!(
+            AttachmentDescriptor::from_bytes(&message[..message.len() - 1]).err(),
+            Some(AttachmentError::MalformedDescriptor)
+        );
+
+        // trailing bytes
+        let mut extended = message.clone();
+        extended.push(0);
+        assert_eq!(
+            AttachmentDescriptor::from_bytes(&extended).err(),
+            Some(AttachmentError::MalformedDescriptor)
+        );
+
+        // invalid fields
+        for invalid in [
+            AttachmentDescriptor {
+                key: vec![1u8; 32],
+                nonce_prefix: [0u8; STREAM_NONCE_PREFIX_SIZE],
+                chunk_size: 100,
+                size: 4,
+                ciphertext_hash: [0u8; ATTACHMENT_HASH_SIZE],
+            },
+            AttachmentDescriptor {
+                key: vec![1u8; crypto_aead::KEY_SIZE],
+                nonce_prefix: [0u8; STREAM_NONCE_PREFIX_SIZE],
+                chunk_size: 0,
+                size: 4,
+                ciphertext_hash: [0u8; ATTACHMENT_HASH_SIZE],
+            },
+            AttachmentDescriptor {
+                key: vec![1u8; crypto_aead::KEY_SIZE],
+                nonce_prefix: [0u8; STREAM_NONCE_PREFIX_SIZE],
+                chunk_size: 1,
+                size: u64::MAX,
+                ciphertext_hash: [0u8; ATTACHMENT_HASH_SIZE],
+            },
+        ] {
+            assert_eq!(
+                AttachmentDescriptor::from_bytes(&invalid.to_bytes()).err(),
+                Some(AttachmentError::MalformedDescriptor)
+            );
+        }
+    }
+}
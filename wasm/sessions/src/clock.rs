@@ -0,0 +1,89 @@
+//! Time sources used by sessions.
+//!
+//! Every timestamp check (announcement and message age, session inactivity, keep-alive)
+//! reads the current time through a [`Clock`]. [`SystemClock`] is used by default, and
+//! [`ManualClock`] allows tests and simulations to control time explicitly.
+
+use std::sync::{Arc, Mutex};
+
+/// A source of the current Unix time in milliseconds.
+pub trait Clock: Send + Sync {
+    /// Returns the current Unix timestamp in milliseconds.
+    fn now_millis(&self) -> u128;
+}
+
+/// Clock of states restored from storage, which does not persist it.
+pub(crate) fn default_clock() -> Box<dyn Clock> {
+    Box::new(SystemClock)
+}
+
+/// The system wall clock.
+///
+/// Uses JS `Date` on wasm32 and `std::time` on native platforms.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_millis(&self) -> u128 {
+        crate::utils::timestamp_millis()
+    }
+}
+
+/// A clock that never reads earlier than `not_before_millis`, so that a timestamp taken from it
+/// comes after a previous one even if the underlying clock did not move, or moved back.
+pub(crate) struct NotBefore<'a> {
+    pub(crate) clock: &'a dyn Clock,
+    pub(crate) not_before_millis: u128,
+}
+
+impl Clock for NotBefore<'_> {
+    fn now_millis(&self) -> u128 {
+        self.clock.now_millis().max(self.not_before_millis)
+    }
+}
+
+/// A clock that only moves when told to.
+///
+/// Clones share the same time, so a clone can be handed to a [`SessionManager`](crate::SessionManager)
+/// and driven from the outside.
+///
+/// # Example
+///
+/// ```
+/// use sessions::{Clock, ManualClock};
+///
+/// let clock = ManualClock::new(1_000);
+/// let handle = clock.clone();
+/// handle.advance(500);
+/// assert_eq!(clock.now_millis(), 1_500);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ManualClock {
+    now_millis: Arc<Mutex<u128>>,
+}
+
+impl ManualClock {
+    /// Creates a clock set to `now_millis`.
+    pub fn new(now_millis: u128) -> Self {
+        Self {
+            now_millis: Arc::new(Mutex::new(now_millis)),
+        }
+    }
+
+    /// Sets the current time to `now_millis`.
+    pub fn set(&self, now_millis: u128) {
+        *self.now_millis.lock().expect("Clock lock poisoned") = now_millis;
+    }
+
+    /// Moves the current time forward by `millis`.
+    pub fn advance(&self, millis: u128) {
+        let mut now_millis = self.now_millis.lock().expect("Clock lock poisoned");
+        *now_millis = now_millis.saturating_add(millis);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now_millis(&self) -> u128 {
+        *self.now_millis.lock().expect("Clock lock poisoned")
+    }
+}
@@ -0,0 +1,281 @@
+//! Error types for session establishment and messaging.
+//!
+//! Failures are split into two kinds:
+//!
+//! - **Diagnosable** failures, which only depend on public data or on our own configuration
+//!   (an expired timestamp, an unknown seeker, a bad seeker signature...). Reporting them
+//!   precisely is safe and helps the application explain what happened.
+//! - **Opaque** failures, which are decryption failures. A wrong recipient, a tampered
+//!   ciphertext, a wrong key or a replayed message are all reported with the same variant
+//!   ([`AnnouncementError::Undecryptable`], [`SessionError::DecryptionFailed`],
+//!   [`SessionError::StateUndecryptable`]) so that error reporting cannot be used as an oracle.
+
+use crypto_agraphon::AgraphonError;
+use std::fmt;
+
+/// Error returned when an incoming announcement is rejected.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnnouncementError {
+    /// The announcement is truncated or its decrypted contents cannot be parsed.
+    Malformed,
+    /// The announcement cannot be decrypted with our keys.
+    ///
+    /// Opaque: this covers announcements addressed to someone else as well as corrupted ones.
+    Undecryptable,
+    /// The announcer's signature over the announcement contents is invalid.
+    InvalidAuthentication,
+    /// The announcement timestamp is older than `max_incoming_announcement_age_millis`.
+    TooOld,
+    /// The announcement timestamp is further in the future than
+    /// `max_incoming_announcement_future_millis`.
+    TooFarInFuture,
+    /// An announcement from the same peer that is at least as recent was already processed.
+    Outdated,
+    /// The announcer runs a session protocol version we cannot establish a session with.
+    UnsupportedProtocolVersion,
+}
+
+impl fmt::Display for AnnouncementError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Malformed => write!(f, "malformed announcement"),
+            Self::Undecryptable => write!(f, "announcement cannot be decrypted"),
+            Self::InvalidAuthentication => write!(f, "invalid announcement authentication"),
+            Self::TooOld => write!(f, "announcement is too old"),
+            Self::TooFarInFuture => write!(f, "announcement is too far in the future"),
+            Self::Outdated => write!(f, "a more recent announcement was already received"),
+            Self::UnsupportedProtocolVersion => {
+                write!(
+                    f,
+                    "announcement uses an unsupported session protocol version"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for AnnouncementError {}
+
+impl From<AgraphonError> for AnnouncementError {
+    fn from(error: AgraphonError) -> Self {
+        match error {
+            AgraphonError::Malformed => Self::Malformed,
+            AgraphonError::DecryptionFailed | AgraphonError::VersionMismatch => Self::Undecryptable,
+        }
+    }
+}
+
+/// Error returned by session messaging and session state persistence.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SessionError {
+    /// There is no active session with the peer.
+    NoActiveSession,
+    /// The session has reached `max_session_lag_length` unacknowledged outgoing messages.
+    Saturated,
+    /// No active session expects a message under this seeker.
+    UnknownSeeker,
+    /// The message is truncated or its decrypted contents cannot be parsed.
+    MalformedMessage,
+    /// The message is not signed by the seeker keypair it was posted under.
+    InvalidSignature,
+    /// The message cannot be decrypted with the session keys.
+    ///
+    /// Opaque: this covers corrupted messages, replays and messages beyond
+    /// the skipped-message limits.
+    DecryptionFailed,
+    /// The message timestamp is older than `max_incoming_message_age_millis`.
+    MessageTooOld,
+    /// The message timestamp is further in the future than `max_incoming_message_future_millis`.
+    MessageTooFarInFuture,
+    /// The encrypted session state cannot be decrypted.
+    ///
+    /// Opaque: this covers a wrong key as well as a truncated or corrupted blob.
+    StateUndecryptable,
+    /// The encrypted session state was decrypted but its contents cannot be parsed.
+    StateMalformed,
+    /// The session state was written with a newer schema version than this one supports.
+    UnsupportedStateVersion,
+}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoActiveSession => write!(f, "no active session with this peer"),
+            Self::Saturated => write!(f, "too many unacknowledged messages"),
+            Self::UnknownSeeker => write!(f, "no session expects a message under this seeker"),
+            Self::MalformedMessage => write!(f, "malformed message"),
+            Self::InvalidSignature => write!(f, "invalid message signature"),
+            Self::DecryptionFailed => write!(f, "message cannot be decrypted"),
+            Self::MessageTooOld => write!(f, "message is too old"),
+            Self::MessageTooFarInFuture => write!(f, "message is too far in the future"),
+            Self::StateUndecryptable => write!(f, "session state cannot be decrypted"),
+            Self::StateMalformed => write!(f, "malformed session state"),
+            Self::UnsupportedStateVersion => {
+                write!(f, "session state was written by a newer version")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SessionError {}
+
+impl From<AgraphonError> for SessionError {
+    fn from(error: AgraphonError) -> Self {
+        match error {
+            AgraphonError::Malformed => Self::MalformedMessage,
+            AgraphonError::DecryptionFailed | AgraphonError::VersionMismatch => {
+                Self::DecryptionFailed
+            }
+        }
+    }
+}
+
+/// Error returned by group membership and group messaging.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GroupError {
+    /// The message is not a group message.
+    ///
+    /// It was sent through the pairwise session for the application itself.
+    NotGroupMessage,
+    /// The group message cannot be parsed.
+    MalformedMessage,
+    /// We are not a member of this group.
+    UnknownGroup,
+    /// The sender or the targeted user is not a member of the group.
+    NotAMember,
+    /// A membership update that is at least as recent was already applied.
+    OutdatedMembership,
+    /// The membership update skips too many epochs ahead of ours,
+    /// or our epoch cannot be incremented anymore.
+    InvalidEpoch,
+}
+
+impl fmt::Display for GroupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotGroupMessage => write!(f, "not a group message"),
+            Self::MalformedMessage => write!(f, "malformed group message"),
+            Self::UnknownGroup => write!(f, "unknown group"),
+            Self::NotAMember => write!(f, "user is not a member of the group"),
+            Self::OutdatedMembership => {
+                write!(f, "a more recent membership update was already applied")
+            }
+            Self::InvalidEpoch => write!(f, "invalid membership epoch"),
+        }
+    }
+}
+
+impl std::error::Error for GroupError {}
+
+/// Error returned when reassembling a fragmented message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FragmentError {
+    /// The message is not a fragment.
+    NotFragment,
+    /// The fragment cannot be parsed, or its index or fragment count is invalid.
+    MalformedFragment,
+    /// The fragment contradicts the fragments already received for the same message
+    /// (different fragment count or payload hash, or duplicate index). The message is dropped.
+    InconsistentFragment,
+    /// The reassembled message would exceed `max_message_size`. The message is dropped.
+    MessageTooLarge,
+    /// The reassembled message does not match the payload hash carried by its fragments.
+    IntegrityCheckFailed,
+}
+
+impl fmt::Display for FragmentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotFragment => write!(f, "not a message fragment"),
+            Self::MalformedFragment => write!(f, "malformed message fragment"),
+            Self::InconsistentFragment => {
+                write!(
+                    f,
+                    "fragment inconsistent with the fragments already received"
+                )
+            }
+            Self::MessageTooLarge => write!(f, "fragmented message is too large"),
+            Self::IntegrityCheckFailed => write!(f, "reassembled message failed integrity check"),
+        }
+    }
+}
+
+impl std::error::Error for FragmentError {}
+
+/// Error returned when handling an encrypted attachment.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AttachmentError {
+    /// The message is not an attachment descriptor.
+    NotAttachment,
+    /// The descriptor cannot be parsed, or its key, chunk size or size is invalid.
+    MalformedDescriptor,
+    /// The ciphertext does not match the size and hash of the descriptor.
+    CiphertextMismatch,
+    /// The ciphertext matches the descriptor but cannot be decrypted with it.
+    DecryptionFailed,
+}
+
+impl fmt::Display for AttachmentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotAttachment => write!(f, "not an attachment descriptor"),
+            Self::MalformedDescriptor => write!(f, "malformed attachment descriptor"),
+            Self::CiphertextMismatch => {
+                write!(f, "attachment ciphertext does not match its descriptor")
+            }
+            Self::DecryptionFailed => write!(f, "attachment decryption failed"),
+        }
+    }
+}
+
+impl std::error::Error for AttachmentError {}
+
+/// Error returned when following a peer to its rotated keys.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyRotationError {
+    /// The message is not a key rotation message.
+    NotKeyRotationMessage,
+    /// The key rotation message cannot be parsed.
+    MalformedMessage,
+    /// No certificate of the chain rotates away from the keys of a known peer.
+    UnknownPeer,
+    /// A certificate is not signed by the keys it rotates away from, or the chain is broken.
+    InvalidCertificate,
+    /// The rotated keys are those of another known peer.
+    IdentityInUse,
+}
+
+impl fmt::Display for KeyRotationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotKeyRotationMessage => write!(f, "not a key rotation message"),
+            Self::MalformedMessage => write!(f, "malformed key rotation message"),
+            Self::UnknownPeer => write!(f, "key rotation of an unknown peer"),
+            Self::InvalidCertificate => write!(f, "invalid key rotation certificate"),
+            Self::IdentityInUse => write!(f, "rotated keys belong to another peer"),
+        }
+    }
+}
+
+impl std::error::Error for KeyRotationError {}
+
+/// Error returned when persisting to or restoring from a [`SessionStorage`](crate::SessionStorage).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StorageError<E> {
+    /// The storage backend failed.
+    Backend(E),
+    /// A stored peer state cannot be restored
+    /// ([`SessionError::StateUndecryptable`] or [`SessionError::StateMalformed`]).
+    State(SessionError),
+}
+
+impl<E: fmt::Display> fmt::Display for StorageError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Backend(error) => write!(f, "storage backend error: {error}"),
+            Self::State(error) => error.fmt(f),
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for StorageError<E> {}
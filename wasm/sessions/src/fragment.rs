@@ -0,0 +1,772 @@
+//! Fragmentation of large messages.
+//!
+//! Every session message is posted as a single message board entry, and carries the ratchet
+//! overhead (two ML-KEM ciphertexts). Large payloads would therefore become single entries
+//! too large for the board. [`send_fragmented_message`] splits a payload into fragments of at
+//! most [`FragmentConfig::max_fragment_size`] bytes, sent as consecutive session messages, and
+//! a [`FragmentReassembler`] rebuilds the payload on the receiving side.
+//!
+//! # Integrity
+//!
+//! Each fragment is authenticated by the session it is sent through, and fragments are only
+//! reassembled with fragments from the same sender. Every fragment also carries the hash of
+//! the whole payload, which is checked after reassembly, so that fragments of different
+//! messages cannot be mixed up.
+//!
+//! # Expiry
+//!
+//! Fragments may arrive in any order. An incomplete message is dropped when its missing
+//! fragments don't arrive within [`FragmentConfig::max_reassembly_age_millis`], or when too many
+//! other messages are incomplete.
+//!
+//! # Wire format
+//!
+//! Fragments are session messages starting with [`FRAGMENT_MESSAGE_PREFIX`], followed by the
+//! serialized fragment. Like group messages, they cannot be confused with text messages.
+//! Use [`is_fragment`] to route incoming messages.
+
+use crate::{
+    clock::{Clock, SystemClock, default_clock},
+    error::{FragmentError, SessionError},
+    session::{FeedIncomingMessageOutput, SendOutgoingMessageOutput},
+    session_manager::SessionManager,
+    state_format::{self, StateKind},
+};
+use auth::UserId;
+use serde::{Deserialize, Serialize};
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+/// Prefix of the session messages that carry a fragment.
+///
+/// `0xFF` never appears in UTF-8 text.
+pub const FRAGMENT_MESSAGE_PREFIX: [u8; 4] = [0xFF, b'F', b'R', b'G'];
+
+/// Size of the random identifier shared by the fragments of a message.
+const MESSAGE_ID_SIZE: usize = 16;
+
+/// Size of the payload hash carried by fragments.
+const PAYLOAD_HASH_SIZE: usize = 32;
+
+/// Context binding an encrypted reassembler state to its use.
+const FRAGMENT_STATE_CONTEXT: &[u8] = b"fragments";
+
+/// Configuration of message fragmentation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FragmentConfig {
+    /// The maximum number of payload bytes per fragment
+    pub max_fragment_size: u64,
+    /// The maximum size of a reassembled message in bytes
+    pub max_message_size: u64,
+    /// The maximum time to wait for the missing fragments of a message, in milliseconds
+    pub max_reassembly_age_millis: u128,
+    /// The maximum number of incomplete messages kept at once.
+    /// When exceeded, the oldest incomplete message is dropped.
+    pub max_pending_messages: u64,
+}
+
+impl Default for FragmentConfig {
+    fn default() -> Self {
+        Self {
+            max_fragment_size: 8 * 1024,
+            max_message_size: 4 * 1024 * 1024,
+            max_reassembly_age_millis: 600_000,
+            max_pending_messages: 32,
+        }
+    }
+}
+
+/// Returns `true` if a message received through a session is a fragment,
+/// and should be fed to [`FragmentReassembler::feed_incoming_message`].
+pub fn is_fragment(message: &[u8]) -> bool {
+    message.starts_with(&FRAGMENT_MESSAGE_PREFIX)
+}
+
+/// A part of a fragmented message, serialized after [`FRAGMENT_MESSAGE_PREFIX`].
+#[derive(Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
+struct Fragment {
+    /// Random identifier shared by the fragments of a message
+    message_id: [u8; MESSAGE_ID_SIZE],
+    /// Position of the fragment in the message
+    index: u32,
+    /// Number of fragments of the message
+    count: u32,
+    /// Hash of the whole message
+    payload_hash: [u8; PAYLOAD_HASH_SIZE],
+    data: Vec<u8>,
+}
+
+impl Fragment {
+    fn to_bytes(&self) -> Vec<u8> {
+        let serialized = Zeroizing::new(
+            bincode::serde::encode_to_vec(self, bincode::config::standard())
+                .expect("Failed to serialize fragment"),
+        );
+        [FRAGMENT_MESSAGE_PREFIX.as_slice(), &serialized].concat()
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, FragmentError> {
+        let serialized = bytes
+            .strip_prefix(FRAGMENT_MESSAGE_PREFIX.as_slice())
+            .ok_or(FragmentError::NotFragment)?;
+        let (fragment, read): (Self, usize) =
+            bincode::serde::decode_from_slice(serialized, bincode::config::standard())
+                .map_err(|_| FragmentError::MalformedFragment)?;
+        if read != serialized.len() || fragment.count == 0 || fragment.index >= fragment.count {
+            return Err(FragmentError::MalformedFragment);
+        }
+        Ok(fragment)
+    }
+}
+
+fn payload_hash(message: &[u8]) -> [u8; PAYLOAD_HASH_SIZE] {
+    *massa_hash::Hash::compute_from(message).to_bytes()
+}
+
+/// Splits a message into serialized fragments of at most `max_fragment_size` payload bytes.
+/// An empty message is sent as a single empty fragment.
+fn split_message(message: &[u8], max_fragment_size: u64) -> Vec<Vec<u8>> {
+    let max_fragment_size = usize::try_from(max_fragment_size)
+        .unwrap_or(usize::MAX)
+        .max(1);
+    let chunks: Vec<&[u8]> = if message.is_empty() {
+        vec![message]
+    } else {
+        message.chunks(max_fragment_size).collect()
+    };
+
+    let mut message_id = [0u8; MESSAGE_ID_SIZE];
+    crypto_rng::fill_buffer(&mut message_id);
+    let payload_hash = payload_hash(message);
+    let count = u32::try_from(chunks.len()).expect("Message has too many fragments");
+
+    (0..count)
+        .zip(chunks)
+        .map(|(index, chunk)| {
+            Fragment {
+                message_id,
+                index,
+                count,
+                payload_hash,
+                data: chunk.to_vec(),
+            }
+            .to_bytes()
+        })
+        .collect()
+}
+
+/// Sends a message to a peer as fragments of at most `config.max_fragment_size` bytes.
+///
+/// # Returns
+///
+/// The seeker and data to post on the message board for each fragment, in order.
+///
+/// # Errors
+///
+/// - [`SessionError::NoActiveSession`] if there's no active session with the peer
+/// - [`SessionError::Saturated`] if the session cannot take all the fragments without
+///   exceeding `max_session_lag_length`. Nothing is sent in that case.
+pub fn send_fragmented_message(
+    session_manager: &mut SessionManager,
+    peer_id: &UserId,
+    message: &[u8],
+    config: &FragmentConfig,
+) -> Result<Vec<SendOutgoingMessageOutput>, SessionError> {
+    let fragments = split_message(message, config.max_fragment_size);
+
+    // make sure all fragments can be sent, so that the peer does not wait for missing ones
+    if fragments.len() as u64 > session_manager.send_capacity(peer_id)? {
+        return Err(SessionError::Saturated);
+    }
+
+    let outputs = fragments
+        .iter()
+        .map(|fragment| session_manager.send_message(peer_id, fragment))
+        .collect();
+    for mut fragment in fragments {
+        fragment.zeroize();
+    }
+    outputs
+}
+
+/// A message rebuilt from its fragments.
+#[derive(Zeroize, ZeroizeOnDrop)]
+pub struct ReassembledMessage {
+    /// User who sent the message
+    pub sender: UserId,
+    /// Timestamp of the last received fragment (milliseconds since Unix epoch)
+    pub timestamp: u128,
+    /// Message contents
+    pub message: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
+struct ReceivedFragment {
+    index: u32,
+    data: Vec<u8>,
+}
+
+/// A message of which some fragments were received.
+#[derive(Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
+struct PendingMessage {
+    sender: UserId,
+    message_id: [u8; MESSAGE_ID_SIZE],
+    count: u32,
+    payload_hash: [u8; PAYLOAD_HASH_SIZE],
+    fragments: Vec<ReceivedFragment>,
+    /// Total size of the received fragments
+    received_size: u64,
+    /// When the first fragment was received
+    first_received_millis: u128,
+}
+
+impl PendingMessage {
+    fn add(&mut self, fragment: &Fragment, max_message_size: u64) -> Result<(), FragmentError> {
+        if fragment.count != self.count
+            || fragment.payload_hash != self.payload_hash
+            || self.fragments.iter().any(|f| f.index == fragment.index)
+        {
+            return Err(FragmentError::InconsistentFragment);
+        }
+        self.received_size = self
+            .received_size
+            .saturating_add(fragment.data.len() as u64);
+        if self.received_size > max_message_size {
+            return Err(FragmentError::MessageTooLarge);
+        }
+        self.fragments.push(ReceivedFragment {
+            index: fragment.index,
+            data: fragment.data.clone(),
+        });
+        Ok(())
+    }
+
+    fn is_complete(&self) -> bool {
+        self.fragments.len() as u64 == u64::from(self.count)
+    }
+
+    fn reassemble(&mut self) -> Result<Vec<u8>, FragmentError> {
+        self.fragments.sort_by_key(|fragment| fragment.index);
+        let mut message = Vec::with_capacity(self.received_size as usize);
+        for fragment in &self.fragments {
+            message.extend_from_slice(&fragment.data);
+        }
+        if payload_hash(&message) != self.payload_hash {
+            message.zeroize();
+            return Err(FragmentError::IntegrityCheckFailed);
+        }
+        Ok(message)
+    }
+}
+
+/// Rebuilds fragmented messages from their fragments.
+#[derive(Serialize, Deserialize)]
+pub struct FragmentReassembler {
+    config: FragmentConfig,
+    /// Incomplete messages, oldest first
+    pending: Vec<PendingMessage>,
+
+    #[serde(skip, default = "default_clock")]
+    clock: Box<dyn Clock>,
+}
+
+impl Zeroize for FragmentReassembler {
+    fn zeroize(&mut self) {
+        self.pending.clear();
+    }
+}
+
+impl ZeroizeOnDrop for FragmentReassembler {}
+
+impl FragmentReassembler {
+    pub fn new(config: FragmentConfig) -> Self {
+        Self::with_clock(config, SystemClock)
+    }
+
+    /// Creates a reassembler that reads the current time from `clock`, to expire
+    /// incomplete messages.
+    pub fn with_clock(config: FragmentConfig, clock: impl Clock + 'static) -> Self {
+        Self {
+            config,
+            pending: Vec::new(),
+            clock: Box::new(clock),
+        }
+    }
+
+    /// Replaces the clock used to expire incomplete messages.
+    pub fn set_clock(&mut self, clock: impl Clock + 'static) {
+        self.clock = Box::new(clock);
+    }
+
+    /// Restores a reassembler from a blob created by [`to_encrypted_blob`](Self::to_encrypted_blob).
+    ///
+    /// The restored reassembler uses [`SystemClock`], see [`set_clock`](Self::set_clock).
+    ///
+    /// # Errors
+    ///
+    /// * [`SessionError::StateUndecryptable`] - If the key is wrong or the blob is corrupted
+    /// * [`SessionError::UnsupportedStateVersion`] - If the blob was written by a newer version
+    /// * [`SessionError::StateMalformed`] - If the decrypted state cannot be parsed
+    pub fn from_encrypted_blob(
+        encrypted_blob: &[u8],
+        key: &crypto_aead::Key,
+    ) -> Result<Self, SessionError> {
+        // decrypt
+        let decrypted_blob = state_format::open(
+            encrypted_blob,
+            key,
+            FRAGMENT_STATE_CONTEXT,
+            StateKind::FragmentReassembler,
+        )?;
+
+        // deserialize
+        let (reassembler, _) =
+            bincode::serde::decode_from_slice(&decrypted_blob, bincode::config::standard())
+                .map_err(|_| SessionError::StateMalformed)?;
+        Ok(reassembler)
+    }
+
+    /// Serializes and encrypts the reassembler, including incomplete messages,
+    /// into a versioned blob.
+    pub fn to_encrypted_blob(&self, key: &crypto_aead::Key) -> Option<Vec<u8>> {
+        // serialize
+        let serialized_blob =
+            Zeroizing::new(bincode::serde::encode_to_vec(self, bincode::config::standard()).ok()?);
+
+        // encrypt
+        Some(state_format::seal(
+            &serialized_blob,
+            key,
+            FRAGMENT_STATE_CONTEXT,
+        ))
+    }
+
+    /// Returns the number of incomplete messages.
+    pub fn pending_count(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Drops the incomplete messages older than `max_reassembly_age_millis`.
+    ///
+    /// Called automatically when a fragment is received.
+    ///
+    /// # Returns
+    ///
+    /// The number of dropped messages.
+    pub fn drop_expired(&mut self) -> usize {
+        let oldest_timestamp = self
+            .clock
+            .now_millis()
+            .saturating_sub(self.config.max_reassembly_age_millis);
+        let pending_count = self.pending.len();
+        self.pending
+            .retain(|pending| pending.first_received_millis >= oldest_timestamp);
+        pending_count - self.pending.len()
+    }
+
+    /// Processes a fragment received through a session, as returned by
+    /// [`SessionManager::feed_incoming_message_board_read`].
+    ///
+    /// # Returns
+    ///
+    /// The reassembled message if this was its last missing fragment, `None` otherwise.
+    ///
+    /// # Errors
+    ///
+    /// - [`FragmentError::NotFragment`] if the message is not a fragment, see [`is_fragment`]
+    /// - [`FragmentError::MalformedFragment`] if the fragment cannot be parsed
+    /// - [`FragmentError::InconsistentFragment`], [`FragmentError::MessageTooLarge`] or
+    ///   [`FragmentError::IntegrityCheckFailed`] if the message cannot be reassembled.
+    ///   Its fragments are dropped.
+    pub fn feed_incoming_message(
+        &mut self,
+        received: &FeedIncomingMessageOutput,
+    ) -> Result<Option<ReassembledMessage>, FragmentError> {
+        let fragment = Fragment::from_bytes(&received.message)?;
+        let sender = <[u8; auth::USER_ID_SIZE]>::try_from(received.user_id.as_slice())
+            .map(UserId::from_bytes)
+            .map_err(|_| FragmentError::MalformedFragment)?;
+        if u64::from(fragment.count) > self.config.max_message_size.max(1) {
+            return Err(FragmentError::MessageTooLarge);
+        }
+
+        self.drop_expired();
+
+        // find the message, or start a new one
+        let position = match self.pending.iter().position(|pending| {
+            pending.sender == sender && pending.message_id == fragment.message_id
+        }) {
+            Some(position) => position,
+            None => {
+                if self.pending.len() as u64 >= self.config.max_pending_messages.max(1) {
+                    self.pending.remove(0);
+                }
+                self.pending.push(PendingMessage {
+                    sender: sender.clone(),
+                    message_id: fragment.message_id,
+                    count: fragment.count,
+                    payload_hash: fragment.payload_hash,
+                    fragments: Vec::new(),
+                    received_size: 0,
+                    first_received_millis: self.clock.now_millis(),
+                });
+                self.pending.len() - 1
+            }
+        };
+
+        // add the fragment
+        let pending = &mut self.pending[position];
+        if let Err(error) = pending.add(&fragment, self.config.max_message_size) {
+            self.pending.remove(position);
+            return Err(error);
+        }
+        if !pending.is_complete() {
+            return Ok(None);
+        }
+
+        // reassemble
+        let message = self.pending.remove(position).reassemble()?;
+        Ok(Some(ReassembledMessage {
+            sender,
+            timestamp: received.timestamp,
+            message,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{clock::ManualClock, session_manager::SessionManagerConfig};
+
+    const T0: u128 = 1_700_000_000_000;
+
+    fn test_config() -> FragmentConfig {
+        FragmentConfig {
+            max_fragment_size: 10,
+            max_message_size: 100,
+            max_reassembly_age_millis: 60_000,
+            max_pending_messages: 2,
+        }
+    }
+
+    /// Wraps a fragment as if it was received from `sender` through a session
+    fn received(sender: &UserId, fragment: &[u8]) -> FeedIncomingMessageOutput {
+        FeedIncomingMessageOutput {
+            timestamp: T0,
+            message: fragment.to_vec(),
+            newly_acknowledged_self_seekers: vec![],
+            user_id: sender.as_bytes().to_vec(),
+        }
+    }
+
+    fn feed_all(
+        reassembler: &mut FragmentReassembler,
+        sender: &UserId,
+        fragments: &[Vec<u8>],
+    ) -> Vec<Result<Option<ReassembledMessage>, FragmentError>> {
+        fragments
+            .iter()
+            .map(|fragment| reassembler.feed_incoming_message(&received(sender, fragment)))
+            .collect()
+    }
+
+    #[test]
+    fn test_reassemble_out_of_order() {
+        let sender = UserId::from_bytes([1; auth::USER_ID_SIZE]);
+        let mut reassembler = FragmentReassembler::with_clock(test_config(), ManualClock::new(T0));
+        let message: Vec<u8> = (0..45).collect();
+
+        let mut fragments = split_message(&message, 10);
+        assert_eq!(fragments.len(), 5);
+        assert!(fragments.iter().all(|fragment| is_fragment(fragment)));
+        fragments.reverse();
+
+        let (last, others) = fragments.split_last().unwrap();
+        for result in feed_all(&mut reassembler, &sender, others) {
+            assert!(matches!(result, Ok(None)));
+        }
+        assert_eq!(reassembler.pending_count(), 1);
+
+        let reassembled = reassembler
+            .feed_incoming_message(&received(&sender, last))
+            .unwrap()
+            .unwrap();
+        assert_eq!(reassembled.message, message);
+        assert_eq!(reassembled.sender, sender);
+        assert_eq!(reassembler.pending_count(), 0);
+    }
+
+    #[test]
+    fn test_fragment_sizes() {
+        let sender = UserId::from_bytes([1; auth::USER_ID_SIZE]);
+        let mut reassembler = FragmentReassembler::with_clock(test_config(), ManualClock::new(T0));
+
+        for (size, count) in [(0, 1), (1, 1), (10, 1), (11, 2), (100, 10)] {
+            let message = vec![7u8; size];
+            let fragments = split_message(&message, 10);
+            assert_eq!(fragments.len(), count);
+            let reassembled = feed_all(&mut reassembler, &sender, &fragments)
+                .pop()
+                .unwrap()
+                .unwrap()
+                .unwrap();
+            assert_eq!(reassembled.message, message);
+        }
+    }
+
+    #[test]
+    fn test_integrity_check() {
+        let sender = UserId::from_bytes([1; auth::USER_ID_SIZE]);
+        let mut reassembler = FragmentReassembler::with_clock(test_config(), ManualClock::new(T0));
+
+        // consistent fragments announcing the hash of another payload
+        let fragments: Vec<Vec<u8>> = (0..2)
+            .map(|index| {
+                Fragment {
+                    message_id: [3; MESSAGE_ID_SIZE],
+                    index,
+                    count: 2,
+                    payload_hash: payload_hash(b"something else"),
+                    data: vec![index as u8; 5],
+                }
+                .to_bytes()
+            })
+            .collect();
+        let results = feed_all(&mut reassembler, &sender, &fragments);
+        assert!(matches!(results[0], Ok(None)));
+        assert!(matches!(
+            results[1],
+            Err(FragmentError::IntegrityCheckFailed)
+        ));
+        assert_eq!(reassembler.pending_count(), 0);
+    }
+
+    #[test]
+    fn test_inconsistent_fragments_rejected() {
+        let sender = UserId::from_bytes([1; auth::USER_ID_SIZE]);
+        let mut reassembler = FragmentReassembler::with_clock(test_config(), ManualClock::new(T0));
+        let fragment = |index, count| {
+            Fragment {
+                message_id: [3; MESSAGE_ID_SIZE],
+                index,
+                count,
+                payload_hash: [4; PAYLOAD_HASH_SIZE],
+                data: vec![1, 2, 3],
+            }
+            .to_bytes()
+        };
+
+        // different fragment count
+        let results = feed_all(&mut reassembler, &sender, &[fragment(0, 3), fragment(1, 4)]);
+        assert!(matches!(
+            results[1],
+            Err(FragmentError::InconsistentFragment)
+        ));
+        assert_eq!(reassembler.pending_count(), 0);
+
+        // duplicate index
+        let results = feed_all(&mut reassembler, &sender, &[fragment(0, 3), fragment(0, 3)]);
+        assert!(matches!(
+            results[1],
+            Err(FragmentError::InconsistentFragment)
+        ));
+        assert_eq!(reassembler.pending_count(), 0);
+
+        // index out of range
+        assert!(matches!(
+            reassembler.feed_incoming_message(&received(&sender, &fragment(3, 3))),
+            Err(FragmentError::MalformedFragment)
+        ));
+
+        // not a fragment
+        assert!(matches!(
+            reassembler.feed_incoming_message(&received(&sender, b"hello")),
+            Err(FragmentError::NotFragment)
+        ));
+    }
+
+    #[test]
+    fn test_message_too_large() {
+        let sender = UserId::from_bytes([1; auth::USER_ID_SIZE]);
+        let mut reassembler = FragmentReassembler::with_clock(test_config(), ManualClock::new(T0));
+
+        let fragments = split_message(&[0u8; 101], 10);
+        let results = feed_all(&mut reassembler, &sender, &fragments);
+        assert!(matches!(
+            results.last().unwrap(),
+            Err(FragmentError::MessageTooLarge)
+        ));
+        assert_eq!(reassembler.pending_count(), 0);
+
+        // too many fragments to possibly fit
+        let fragments = split_message(&[0u8; 101], 1);
+        assert!(matches!(
+            reassembler.feed_incoming_message(&received(&sender, &fragments[0])),
+            Err(FragmentError::MessageTooLarge)
+        ));
+    }
+
+    #[test]
+    fn test_fragments_of_other_sender_not_mixed() {
+        let alice = UserId::from_bytes([1; auth::USER_ID_SIZE]);
+        let mallory = UserId::from_bytes([2; auth::USER_ID_SIZE]);
+        let mut reassembler = FragmentReassembler::with_clock(test_config(), ManualClock::new(T0));
+
+        let fragments = split_message(&[5u8; 20], 10);
+        assert!(matches!(
+            reassembler.feed_incoming_message(&received(&alice, &fragments[0])),
+            Ok(None)
+        ));
+        // Mallory replays the second fragment: it starts a separate message
+        assert!(matches!(
+            reassembler.feed_incoming_message(&received(&mallory, &fragments[1])),
+            Ok(None)
+        ));
+        assert_eq!(reassembler.pending_count(), 2);
+
+        let reassembled = reassembler
+            .feed_incoming_message(&received(&alice, &fragments[1]))
+            .unwrap()
+            .unwrap();
+        assert_eq!(reassembled.sender, alice);
+    }
+
+    #[test]
+    fn test_incomplete_messages_expire() {
+        let sender = UserId::from_bytes([1; auth::USER_ID_SIZE]);
+        let clock = ManualClock::new(T0);
+        let mut reassembler = FragmentReassembler::with_clock(test_config(), clock.clone());
+
+        let fragments = split_message(&[5u8; 20], 10);
+        reassembler
+            .feed_incoming_message(&received(&sender, &fragments[0]))
+            .unwrap();
+
+        clock.advance(60_000);
+        assert_eq!(reassembler.drop_expired(), 0);
+        clock.advance(1);
+        assert_eq!(reassembler.drop_expired(), 1);
+
+        // the last fragment now starts a new incomplete message
+        assert!(matches!(
+            reassembler.feed_incoming_message(&received(&sender, &fragments[1])),
+            Ok(None)
+        ));
+    }
+
+    #[test]
+    fn test_oldest_incomplete_message_dropped() {
+        let sender = UserId::from_bytes([1; auth::USER_ID_SIZE]);
+        let mut reassembler = FragmentReassembler::with_clock(test_config(), ManualClock::new(T0));
+
+        // max 2 incomplete messages
+        let messages: Vec<Vec<Vec<u8>>> = (0..3).map(|_| split_message(&[5u8; 20], 10)).collect();
+        for fragments in &messages {
+            reassembler
+                .feed_incoming_message(&received(&sender, &fragments[0]))
+                .unwrap();
+        }
+        assert_eq!(reassembler.pending_count(), 2);
+
+        // the first message was dropped, the last ones can still complete
+        assert!(matches!(
+            reassembler.feed_incoming_message(&received(&sender, &messages[0][1])),
+            Ok(None)
+        ));
+        assert!(matches!(
+            reassembler.feed_incoming_message(&received(&sender, &messages[2][1])),
+            Ok(Some(_))
+        ));
+    }
+
+    #[test]
+    fn test_reassembler_persistence() {
+        let sender = UserId::from_bytes([1; auth::USER_ID_SIZE]);
+        let mut reassembler = FragmentReassembler::with_clock(test_config(), ManualClock::new(T0));
+        let fragments = split_message(&[5u8; 20], 10);
+        reassembler
+            .feed_incoming_message(&received(&sender, &fragments[0]))
+            .unwrap();
+
+        let mut key_bytes = [0u8; crypto_aead::KEY_SIZE];
+        crypto_rng::fill_buffer(&mut key_bytes);
+        let key = crypto_aead::Key::from(key_bytes);
+        let blob = reassembler.to_encrypted_blob(&key).unwrap();
+
+        let mut restored = FragmentReassembler::from_encrypted_blob(&blob, &key).unwrap();
+        restored.set_clock(ManualClock::new(T0));
+        assert_eq!(restored.pending_count(), 1);
+        let reassembled = restored
+            .feed_incoming_message(&received(&sender, &fragments[1]))
+            .unwrap()
+            .unwrap();
+        assert_eq!(reassembled.message, vec![5u8; 20]);
+    }
+
+    #[test]
+    fn test_fragmented_message_through_sessions() {
+        let config = || SessionManagerConfig {
+            max_incoming_announcement_age_millis: 60_000,
+            max_incoming_announcement_future_millis: 5_000,
+            max_incoming_message_age_millis: 300_000,
+            max_incoming_message_future_millis: 5_000,
+            max_session_inactivity_millis: 3_600_000,
+            keep_alive_interval_millis: 60_000,
+            max_session_lag_length: 20,
+            max_skipped_messages: 16,
+            max_stored_skipped_messages: 128,
+        };
+        let keypair = || {
+            let mut passphrase = [0u8; 32];
+            crypto_rng::fill_buffer(&mut passphrase);
+            let root_secret = auth::StaticRootSecret::from_passphrase(&passphrase);
+            auth::derive_keys_from_static_root_secret(&root_secret)
+        };
+        let (alice_pk, alice_sk) = keypair();
+        let (bob_pk, bob_sk) = keypair();
+        let mut alice_manager = SessionManager::new(config());
+        let mut bob_manager = SessionManager::new(config());
+        let alice_announcement =
+            alice_manager.establish_outgoing_session(&bob_pk, &alice_pk, &alice_sk, vec![]);
+        let bob_announcement =
+            bob_manager.establish_outgoing_session(&alice_pk, &bob_pk, &bob_sk, vec![]);
+        alice_manager
+            .feed_incoming_announcement(&bob_announcement, &alice_pk, &alice_sk)
+            .unwrap();
+        bob_manager
+            .feed_incoming_announcement(&alice_announcement, &bob_pk, &bob_sk)
+            .unwrap();
+        let bob_id = bob_pk.derive_id();
+
+        let fragment_config = test_config();
+        let message: Vec<u8> = (0..80).collect();
+        let outputs =
+            send_fragmented_message(&mut alice_manager, &bob_id, &message, &fragment_config)
+                .unwrap();
+        assert_eq!(outputs.len(), 8);
+
+        // a message that doesn't fit in the session is not sent at all
+        let capacity = alice_manager.send_capacity(&bob_id).unwrap();
+        let too_large = vec![0u8; (capacity as usize + 1) * 10];
+        assert_eq!(
+            send_fragmented_message(&mut alice_manager, &bob_id, &too_large, &fragment_config)
+                .err(),
+            Some(SessionError::Saturated)
+        );
+        assert_eq!(alice_manager.send_capacity(&bob_id), Ok(capacity));
+
+        // Bob reads the fragments from the board in reverse order
+        let mut reassembler = FragmentReassembler::new(fragment_config);
+        let mut reassembled = None;
+        for output in outputs.iter().rev() {
+            let received = bob_manager
+                .feed_incoming_message_board_read(&output.seeker, &output.data, &bob_sk)
+                .unwrap();
+            assert!(is_fragment(&received.message));
+            reassembled = reassembler.feed_incoming_message(&received).unwrap();
+        }
+        let reassembled = reassembled.unwrap();
+        assert_eq!(reassembled.message, message);
+        assert_eq!(reassembled.sender, alice_pk.derive_id());
+    }
+}
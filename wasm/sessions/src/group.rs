@@ -0,0 +1,1212 @@
+//! Group messaging over pairwise sessions.
+//!
+//! Groups are built on the pairwise sessions of a [`SessionManager`]: every group message is
+//! encrypted separately for each member and sent through the session with that member
+//! (pairwise fan-out). Group traffic therefore keeps the guarantees of pairwise sessions:
+//! it is posted on the message board under per-session seekers, authenticated by the
+//! session it arrives through, and ratcheted like any other message. The cost is one
+//! message board write per member, which suits small groups.
+//!
+//! # Membership
+//!
+//! Each group is identified by a random [`GroupId`]. Any member can add or remove members,
+//! or leave. A membership change is sent to all previous and new members as the complete new
+//! member list, tagged with an epoch incremented on each change. An update is applied if its
+//! epoch is higher than the current one, by at most [`MAX_MEMBERSHIP_EPOCH_JUMP`]. When two
+//! members change the membership concurrently, both updates have the same epoch and the one from
+//! the member with the lowest user ID wins, so that the members receiving both updates converge
+//! on the same list.
+//!
+//! # Wire format
+//!
+//! Group messages are pairwise session messages starting with [`GROUP_MESSAGE_PREFIX`],
+//! followed by the serialized group payload. The first byte of the prefix never appears in
+//! UTF-8 text, so group messages cannot be confused with text messages sent through the same
+//! sessions. Use [`is_group_message`] to route incoming messages.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use sessions::{GroupEvent, GroupManager, SessionManager, is_group_message};
+//! # fn example(
+//! #     session_manager: &mut SessionManager,
+//! #     our_id: auth::UserId,
+//! #     bob_id: auth::UserId,
+//! #     carol_id: auth::UserId,
+//! #     our_sk: &auth::UserSecretKeys,
+//! #     seeker: &[u8],
+//! #     data: &[u8],
+//! # ) {
+//! let mut group_manager = GroupManager::new(our_id);
+//!
+//! // Create a group with peers we have active sessions with
+//! let (group_id, fan_out) = group_manager.create_group(session_manager, &[bob_id, carol_id]);
+//! for (_recipient, output) in &fan_out.outputs {
+//!     // Post output.data to the message board at seeker output.seeker
+//! }
+//!
+//! // Send a message to the group
+//! let fan_out = group_manager
+//!     .send_message(session_manager, &group_id, b"Hello group!")
+//!     .expect("unknown group");
+//!
+//! // Route incoming messages
+//! if let Ok(received) = session_manager.feed_incoming_message_board_read(seeker, data, our_sk) {
+//!     if is_group_message(&received.message) {
+//!         if let Ok(GroupEvent::Message(message)) =
+//!             group_manager.feed_incoming_message(&received)
+//!         {
+//!             println!("{}", String::from_utf8_lossy(&message.message));
+//!         }
+//!     }
+//! }
+//! # }
+//! ```
+
+use crate::{
+    error::{GroupError, SessionError},
+    session::{FeedIncomingMessageOutput, SendOutgoingMessageOutput},
+    session_manager::SessionManager,
+    state_format::{self, StateKind},
+};
+use auth::UserId;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+/// Size of a group ID in bytes.
+pub const GROUP_ID_SIZE: usize = 32;
+
+/// Prefix of the pairwise session messages that carry group traffic.
+///
+/// `0xFF` never appears in UTF-8 text.
+pub const GROUP_MESSAGE_PREFIX: [u8; 4] = [0xFF, b'G', b'R', b'P'];
+
+/// Maximum number of epochs a received membership update can be ahead of ours.
+///
+/// Updates from different members can arrive out of order, so an update may skip the epochs
+/// of the ones still in flight. Bounding the jump prevents a member from announcing an epoch
+/// so high that no later change can supersede it.
+pub const MAX_MEMBERSHIP_EPOCH_JUMP: u64 = 16;
+
+/// Context binding an encrypted group manager state to its use.
+const GROUP_STATE_CONTEXT: &[u8] = b"groups";
+
+/// Random identifier of a group.
+#[derive(Debug, Clone, Hash, Zeroize, ZeroizeOnDrop, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GroupId([u8; GROUP_ID_SIZE]);
+
+impl GroupId {
+    /// Generates a random group ID.
+    pub fn generate() -> Self {
+        let mut bytes = [0u8; GROUP_ID_SIZE];
+        crypto_rng::fill_buffer(&mut bytes);
+        Self(bytes)
+    }
+
+    /// Returns the group ID as a byte slice.
+    #[must_use]
+    pub const fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Creates a `GroupId` from a byte array.
+    #[must_use]
+    pub const fn from_bytes(bytes: [u8; GROUP_ID_SIZE]) -> Self {
+        Self(bytes)
+    }
+}
+
+/// Returns `true` if a message received through a pairwise session carries group traffic,
+/// and should be fed to [`GroupManager::feed_incoming_message`].
+pub fn is_group_message(message: &[u8]) -> bool {
+    message.starts_with(&GROUP_MESSAGE_PREFIX)
+}
+
+/// Group traffic, serialized after [`GROUP_MESSAGE_PREFIX`].
+#[derive(Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
+enum GroupPayload {
+    /// The complete member list after a membership change
+    Membership {
+        group_id: GroupId,
+        epoch: u64,
+        members: Vec<UserId>,
+    },
+    /// A message to the group
+    Message { group_id: GroupId, message: Vec<u8> },
+}
+
+impl GroupPayload {
+    fn to_bytes(&self) -> Vec<u8> {
+        let serialized = Zeroizing::new(
+            bincode::serde::encode_to_vec(self, bincode::config::standard())
+                .expect("Failed to serialize group message"),
+        );
+        [GROUP_MESSAGE_PREFIX.as_slice(), &serialized].concat()
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, GroupError> {
+        let serialized = bytes
+            .strip_prefix(GROUP_MESSAGE_PREFIX.as_slice())
+            .ok_or(GroupError::NotGroupMessage)?;
+        let (payload, read) =
+            bincode::serde::decode_from_slice(serialized, bincode::config::standard())
+                .map_err(|_| GroupError::MalformedMessage)?;
+        if read != serialized.len() {
+            return Err(GroupError::MalformedMessage);
+        }
+        Ok(payload)
+    }
+}
+
+/// A message received in a group.
+#[derive(Zeroize, ZeroizeOnDrop)]
+pub struct GroupMessage {
+    /// Group the message was sent to
+    pub group_id: GroupId,
+    /// Member who sent the message
+    pub sender: UserId,
+    /// Message timestamp (milliseconds since Unix epoch)
+    pub timestamp: u128,
+    /// Message contents
+    pub message: Vec<u8>,
+}
+
+/// Outcome of a group message received from a member.
+pub enum GroupEvent {
+    /// We were added to a group we were not a member of.
+    Joined {
+        group_id: GroupId,
+        /// Member who added us
+        by: UserId,
+        /// All members of the group, including us
+        members: Vec<UserId>,
+    },
+    /// The members of a group changed.
+    MembersChanged {
+        group_id: GroupId,
+        /// Member who made the change. A member leaving removes themselves.
+        by: UserId,
+        added: Vec<UserId>,
+        removed: Vec<UserId>,
+    },
+    /// We were removed from a group, which is forgotten.
+    Removed {
+        group_id: GroupId,
+        /// Member who removed us
+        by: UserId,
+    },
+    /// A message was sent to the group.
+    Message(GroupMessage),
+}
+
+/// Pairwise messages to post on the message board for a group operation.
+#[derive(Zeroize, ZeroizeOnDrop)]
+pub struct GroupFanOut {
+    /// Message board writes, one per reachable recipient
+    #[zeroize(skip)]
+    pub outputs: Vec<(UserId, SendOutgoingMessageOutput)>,
+    /// Recipients that could not be reached through their session.
+    /// The payload can be sent to them later with [`SessionManager::send_message`].
+    #[zeroize(skip)]
+    pub failures: Vec<(UserId, SessionError)>,
+    /// Group message sent through each pairwise session
+    pub payload: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
+struct GroupInfo {
+    /// All members, including us
+    members: Vec<UserId>,
+    /// Number of membership changes
+    epoch: u64,
+    /// Member who made the latest membership change
+    epoch_author: UserId,
+}
+
+impl GroupInfo {
+    fn is_member(&self, user_id: &UserId) -> bool {
+        self.members.contains(user_id)
+    }
+
+    /// Whether a membership update made by `author` at `epoch` replaces the current membership.
+    /// Concurrent updates have the same epoch: the one from the lowest user ID wins.
+    fn is_superseded_by(&self, epoch: u64, author: &UserId) -> bool {
+        epoch > self.epoch
+            || (epoch == self.epoch && author.as_bytes() < self.epoch_author.as_bytes())
+    }
+
+    /// Whether a membership update at `epoch` skips more epochs than a member can have missed.
+    fn is_too_far_ahead(&self, epoch: u64) -> bool {
+        epoch.saturating_sub(self.epoch) > MAX_MEMBERSHIP_EPOCH_JUMP
+    }
+}
+
+/// Manages the groups we are a member of.
+///
+/// Group operations send messages through the pairwise sessions of a [`SessionManager`],
+/// which must have an active session with each member for them to be reached.
+#[derive(Serialize, Deserialize)]
+pub struct GroupManager {
+    our_id: UserId,
+    groups: HashMap<GroupId, Box<GroupInfo>>,
+}
+
+impl Zeroize for GroupManager {
+    fn zeroize(&mut self) {
+        self.groups.clear();
+        self.our_id.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for GroupManager {}
+
+impl GroupManager {
+    /// Creates a group manager for the user `our_id`, with no groups.
+    pub fn new(our_id: UserId) -> Self {
+        Self {
+            our_id,
+            groups: HashMap::new(),
+        }
+    }
+
+    /// Restores a group manager from a blob created by [`to_encrypted_blob`](Self::to_encrypted_blob).
+    ///
+    /// # Errors
+    ///
+    /// * [`SessionError::StateUndecryptable`] - If the key is wrong or the blob is corrupted
+    /// * [`SessionError::UnsupportedStateVersion`] - If the blob was written by a newer version
+    /// * [`SessionError::StateMalformed`] - If the decrypted state cannot be parsed
+    pub fn from_encrypted_blob(
+        encrypted_blob: &[u8],
+        key: &crypto_aead::Key,
+    ) -> Result<Self, SessionError> {
+        // decrypt
+        let decrypted_blob = state_format::open(
+            encrypted_blob,
+            key,
+            GROUP_STATE_CONTEXT,
+            StateKind::GroupManager,
+        )?;
+
+        // deserialize
+        let (group_manager, _) =
+            bincode::serde::decode_from_slice(&decrypted_blob, bincode::config::standard())
+                .map_err(|_| SessionError::StateMalformed)?;
+        Ok(group_manager)
+    }
+
+    /// Serializes and encrypts the group manager into a versioned blob.
+    pub fn to_encrypted_blob(&self, key: &crypto_aead::Key) -> Option<Vec<u8>> {
+        // serialize
+        let serialized_blob =
+            Zeroizing::new(bincode::serde::encode_to_vec(self, bincode::config::standard()).ok()?);
+
+        // encrypt
+        Some(state_format::seal(
+            &serialized_blob,
+            key,
+            GROUP_STATE_CONTEXT,
+        ))
+    }
+
+    pub fn group_list(&self) -> Vec<GroupId> {
+        self.groups.keys().cloned().collect()
+    }
+
+    /// Returns the members of a group, including us, or `None` if we are not a member.
+    pub fn group_members(&self, group_id: &GroupId) -> Option<Vec<UserId>> {
+        self.groups.get(group_id).map(|group| group.members.clone())
+    }
+
+    /// Creates a group with `members` and invites them.
+    ///
+    /// # Returns
+    ///
+    /// The ID of the new group, and the invitations to post on the message board.
+    pub fn create_group(
+        &mut self,
+        session_manager: &mut SessionManager,
+        members: &[UserId],
+    ) -> (GroupId, GroupFanOut) {
+        let group_id = GroupId::generate();
+        let mut group_members = vec![self.our_id.clone()];
+        add_members(&mut group_members, members);
+        let payload = GroupPayload::Membership {
+            group_id: group_id.clone(),
+            epoch: 0,
+            members: group_members.clone(),
+        };
+        let fan_out = fan_out(session_manager, &self.our_id, &group_members, &payload);
+        self.groups.insert(
+            group_id.clone(),
+            Box::new(GroupInfo {
+                members: group_members,
+                epoch: 0,
+                epoch_author: self.our_id.clone(),
+            }),
+        );
+        (group_id, fan_out)
+    }
+
+    /// Adds members to a group. Users that are already members are ignored.
+    ///
+    /// # Errors
+    ///
+    /// - [`GroupError::UnknownGroup`] if we are not a member of the group
+    /// - [`GroupError::InvalidEpoch`] if the group has reached the last epoch
+    pub fn add_members(
+        &mut self,
+        session_manager: &mut SessionManager,
+        group_id: &GroupId,
+        members: &[UserId],
+    ) -> Result<GroupFanOut, GroupError> {
+        self.change_membership(session_manager, group_id, |group_members| {
+            add_members(group_members, members);
+        })
+    }
+
+    /// Removes members from a group. Removed members are notified.
+    ///
+    /// # Errors
+    ///
+    /// - [`GroupError::UnknownGroup`] if we are not a member of the group
+    /// - [`GroupError::NotAMember`] if one of the users is not a member of the group
+    /// - [`GroupError::InvalidEpoch`] if the group has reached the last epoch
+    pub fn remove_members(
+        &mut self,
+        session_manager: &mut SessionManager,
+        group_id: &GroupId,
+        members: &[UserId],
+    ) -> Result<GroupFanOut, GroupError> {
+        let group = self.groups.get(group_id).ok_or(GroupError::UnknownGroup)?;
+        if !members.iter().all(|member| group.is_member(member)) {
+            return Err(GroupError::NotAMember);
+        }
+        self.change_membership(session_manager, group_id, |group_members| {
+            group_members.retain(|member| !members.contains(member));
+        })
+    }
+
+    /// Leaves a group, which is forgotten. The other members are notified.
+    ///
+    /// # Errors
+    ///
+    /// - [`GroupError::UnknownGroup`] if we are not a member of the group
+    /// - [`GroupError::InvalidEpoch`] if the group has reached the last epoch
+    pub fn leave_group(
+        &mut self,
+        session_manager: &mut SessionManager,
+        group_id: &GroupId,
+    ) -> Result<GroupFanOut, GroupError> {
+        let our_id = self.our_id.clone();
+        self.change_membership(session_manager, group_id, |group_members| {
+            group_members.retain(|member| *member != our_id);
+        })
+    }
+
+    /// Sends a message to all other members of a group.
+    ///
+    /// # Errors
+    ///
+    /// - [`GroupError::UnknownGroup`] if we are not a member of the group
+    pub fn send_message(
+        &self,
+        session_manager: &mut SessionManager,
+        group_id: &GroupId,
+        message: &[u8],
+    ) -> Result<GroupFanOut, GroupError> {
+        let group = self.groups.get(group_id).ok_or(GroupError::UnknownGroup)?;
+        let payload = GroupPayload::Message {
+            group_id: group_id.clone(),
+            message: message.to_vec(),
+        };
+        Ok(fan_out(
+            session_manager,
+            &self.our_id,
+            &group.members,
+            &payload,
+        ))
+    }
+
+    /// Processes a group message received through a pairwise session, as returned by
+    /// [`SessionManager::feed_incoming_message_board_read`].
+    ///
+    /// # Errors
+    ///
+    /// - [`GroupError::NotGroupMessage`] if the message is not a group message,
+    ///   see [`is_group_message`]
+    /// - [`GroupError::MalformedMessage`] if the group message cannot be parsed
+    /// - [`GroupError::UnknownGroup`] if we are not a member of the group
+    /// - [`GroupError::NotAMember`] if the sender is not a member of the group
+    /// - [`GroupError::OutdatedMembership`] if a more recent membership update was applied
+    /// - [`GroupError::InvalidEpoch`] if a membership update skips more than
+    ///   [`MAX_MEMBERSHIP_EPOCH_JUMP`] epochs
+    pub fn feed_incoming_message(
+        &mut self,
+        received: &FeedIncomingMessageOutput,
+    ) -> Result<GroupEvent, GroupError> {
+        let payload = GroupPayload::from_bytes(&received.message)?;
+        let sender = <[u8; auth::USER_ID_SIZE]>::try_from(received.user_id.as_slice())
+            .map(UserId::from_bytes)
+            .map_err(|_| GroupError::MalformedMessage)?;
+
+        match &payload {
+            GroupPayload::Membership {
+                group_id,
+                epoch,
+                members,
+            } => self.apply_membership(sender, group_id, *epoch, members),
+            GroupPayload::Message { group_id, message } => {
+                let group = self.groups.get(group_id).ok_or(GroupError::UnknownGroup)?;
+                if !group.is_member(&sender) {
+                    return Err(GroupError::NotAMember);
+                }
+                Ok(GroupEvent::Message(GroupMessage {
+                    group_id: group_id.clone(),
+                    sender,
+                    timestamp: received.timestamp,
+                    message: message.clone(),
+                }))
+            }
+        }
+    }
+
+    /// Applies a local membership change and notifies the previous and new members.
+    fn change_membership(
+        &mut self,
+        session_manager: &mut SessionManager,
+        group_id: &GroupId,
+        change: impl FnOnce(&mut Vec<UserId>),
+    ) -> Result<GroupFanOut, GroupError> {
+        let group = self
+            .groups
+            .get_mut(group_id)
+            .ok_or(GroupError::UnknownGroup)?;
+
+        // apply the change
+        let epoch = group.epoch.checked_add(1).ok_or(GroupError::InvalidEpoch)?;
+        let mut recipients = group.members.clone();
+        change(&mut group.members);
+        group.epoch = epoch;
+        group.epoch_author = self.our_id.clone();
+        let payload = GroupPayload::Membership {
+            group_id: group_id.clone(),
+            epoch: group.epoch,
+            members: group.members.clone(),
+        };
+
+        // notify removed members as well, so that they learn they were removed
+        add_members(&mut recipients, &group.members);
+
+        // forget the group if we left it
+        if !group.is_member(&self.our_id) {
+            self.groups.remove(group_id);
+        }
+
+        Ok(fan_out(
+            session_manager,
+            &self.our_id,
+            &recipients,
+            &payload,
+        ))
+    }
+
+    /// Applies a membership update received from `sender`.
+    fn apply_membership(
+        &mut self,
+        sender: UserId,
+        group_id: &GroupId,
+        epoch: u64,
+        members: &[UserId],
+    ) -> Result<GroupEvent, GroupError> {
+        let mut new_members = Vec::with_capacity(members.len());
+        add_members(&mut new_members, members);
+
+        // a group we don't know: we are being invited
+        let Some(group) = self.groups.get_mut(group_id) else {
+            if !new_members.contains(&self.our_id) {
+                return Err(GroupError::UnknownGroup);
+            }
+            if !new_members.contains(&sender) {
+                return Err(GroupError::NotAMember);
+            }
+            self.groups.insert(
+                group_id.clone(),
+                Box::new(GroupInfo {
+                    members: new_members.clone(),
+                    epoch,
+                    epoch_author: sender.clone(),
+                }),
+            );
+            return Ok(GroupEvent::Joined {
+                group_id: group_id.clone(),
+                by: sender,
+                members: new_members,
+            });
+        };
+
+        // check the update
+        if !group.is_member(&sender) {
+            return Err(GroupError::NotAMember);
+        }
+        if !group.is_superseded_by(epoch, &sender) {
+            return Err(GroupError::OutdatedMembership);
+        }
+        if group.is_too_far_ahead(epoch) {
+            return Err(GroupError::InvalidEpoch);
+        }
+
+        // we were removed
+        if !new_members.contains(&self.our_id) {
+            self.groups.remove(group_id);
+            return Ok(GroupEvent::Removed {
+                group_id: group_id.clone(),
+                by: sender,
+            });
+        }
+
+        // apply the update
+        let added = new_members
+            .iter()
+            .filter(|member| !group.is_member(member))
+            .cloned()
+            .collect();
+        let removed = group
+            .members
+            .iter()
+            .filter(|member| !new_members.contains(member))
+            .cloned()
+            .collect();
+        group.members = new_members;
+        group.epoch = epoch;
+        group.epoch_author = sender.clone();
+        Ok(GroupEvent::MembersChanged {
+            group_id: group_id.clone(),
+            by: sender,
+            added,
+            removed,
+        })
+    }
+}
+
+/// Appends the users of `new_members` that are not in `members` yet.
+fn add_members(members: &mut Vec<UserId>, new_members: &[UserId]) {
+    for new_member in new_members {
+        if !members.contains(new_member) {
+            members.push(new_member.clone());
+        }
+    }
+}
+
+/// Sends a group payload to each recipient except us, through their pairwise session.
+fn fan_out(
+    session_manager: &mut SessionManager,
+    our_id: &UserId,
+    recipients: &[UserId],
+    payload: &GroupPayload,
+) -> GroupFanOut {
+    let payload = payload.to_bytes();
+    let mut outputs = Vec::new();
+    let mut failures = Vec::new();
+    for recipient in recipients.iter().filter(|recipient| *recipient != our_id) {
+        match session_manager.send_message(recipient, &payload) {
+            Ok(output) => outputs.push((recipient.clone(), output)),
+            Err(error) => failures.push((recipient.clone(), error)),
+        }
+    }
+    GroupFanOut {
+        outputs,
+        failures,
+        payload,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session_manager::SessionManagerConfig;
+
+    fn create_test_config() -> SessionManagerConfig {
+        SessionManagerConfig {
+            max_incoming_announcement_age_millis: 60_000,
+            max_incoming_announcement_future_millis: 5_000,
+            max_incoming_message_age_millis: 300_000,
+            max_incoming_message_future_millis: 5_000,
+            max_session_inactivity_millis: 3_600_000,
+            keep_alive_interval_millis: 60_000,
+            max_session_lag_length: 100,
+            max_skipped_messages: 16,
+            max_stored_skipped_messages: 128,
+        }
+    }
+
+    /// A simulated user with their sessions and groups
+    struct Member {
+        pk: auth::UserPublicKeys,
+        sk: auth::UserSecretKeys,
+        id: UserId,
+        session_manager: SessionManager,
+        group_manager: GroupManager,
+    }
+
+    impl Member {
+        fn new() -> Self {
+            let mut passphrase = [0u8; 32];
+            crypto_rng::fill_buffer(&mut passphrase);
+            let root_secret = auth::StaticRootSecret::from_passphrase(&passphrase);
+            let (pk, sk) = auth::derive_keys_from_static_root_secret(&root_secret);
+            let id = pk.derive_id();
+            Self {
+                pk,
+                sk,
+                id: id.clone(),
+                session_manager: SessionManager::new(create_test_config()),
+                group_manager: GroupManager::new(id),
+            }
+        }
+    }
+
+    /// Establishes pairwise sessions between all members.
+    fn connect_all(members: &mut [Member]) {
+        for i in 0..members.len() {
+            for j in (i + 1)..members.len() {
+                let (left, right) = members.split_at_mut(j);
+                let (a, b) = (&mut left[i], &mut right[0]);
+                let a_announcement =
+                    a.session_manager
+                        .establish_outgoing_session(&b.pk, &a.pk, &a.sk, vec![]);
+                let b_announcement =
+                    b.session_manager
+                        .establish_outgoing_session(&a.pk, &b.pk, &b.sk, vec![]);
+                a.session_manager
+                    .feed_incoming_announcement(&b_announcement, &a.pk, &a.sk)
+                    .expect("Failed to process announcement");
+                b.session_manager
+                    .feed_incoming_announcement(&a_announcement, &b.pk, &b.sk)
+                    .expect("Failed to process announcement");
+            }
+        }
+    }
+
+    /// A simulated message board: (seeker, data) posts
+    #[derive(Default)]
+    struct Board {
+        posts: Vec<(Vec<u8>, Vec<u8>)>,
+    }
+
+    impl Board {
+        fn post(&mut self, fan_out: &GroupFanOut) {
+            for (_recipient, output) in &fan_out.outputs {
+                self.posts
+                    .push((output.seeker.clone(), output.data.clone()));
+            }
+        }
+
+        /// Reads the posts under the seekers the member monitors, like a client polling the board,
+        /// and returns the results of feeding them to the group manager.
+        fn read(&mut self, member: &mut Member) -> Vec<Result<GroupEvent, GroupError>> {
+            let mut events = Vec::new();
+            loop {
+                let seekers = member.session_manager.get_message_board_read_keys();
+                let Some(index) = self
+                    .posts
+                    .iter()
+                    .position(|(seeker, _)| seekers.contains(seeker))
+                else {
+                    return events;
+                };
+                let (seeker, data) = self.posts.remove(index);
+                let received = member
+                    .session_manager
+                    .feed_incoming_message_board_read(&seeker, &data, &member.sk)
+                    .expect("Failed to read message");
+                assert!(is_group_message(&received.message));
+                events.push(member.group_manager.feed_incoming_message(&received));
+            }
+        }
+
+        /// Like [`read`](Self::read), expecting all group messages to be accepted.
+        fn deliver(&mut self, member: &mut Member) -> Vec<GroupEvent> {
+            self.read(member)
+                .into_iter()
+                .map(|event| event.expect("Failed to process group message"))
+                .collect()
+        }
+    }
+
+    fn sorted(mut members: Vec<UserId>) -> Vec<UserId> {
+        members.sort_by(|a, b| a.as_bytes().cmp(b.as_bytes()));
+        members
+    }
+
+    fn assert_message(event: &GroupEvent, group_id: &GroupId, sender: &UserId, message: &[u8]) {
+        let GroupEvent::Message(received) = event else {
+            panic!("Expected a group message");
+        };
+        assert_eq!(&received.group_id, group_id);
+        assert_eq!(&received.sender, sender);
+        assert_eq!(received.message, message);
+    }
+
+    #[test]
+    fn test_group_simulation() {
+        let mut members: Vec<Member> = (0..4).map(|_| Member::new()).collect();
+        connect_all(&mut members);
+        let [alice, bob, carol, dave] = &mut members[..] else {
+            unreachable!()
+        };
+        let mut board = Board::default();
+
+        // Alice creates a group with Bob and Carol
+        let (group_id, fan_out) = alice.group_manager.create_group(
+            &mut alice.session_manager,
+            &[bob.id.clone(), carol.id.clone()],
+        );
+        assert_eq!(fan_out.outputs.len(), 2);
+        assert!(fan_out.failures.is_empty());
+        board.post(&fan_out);
+        let expected_members = sorted(vec![alice.id.clone(), bob.id.clone(), carol.id.clone()]);
+        for member in [&mut *bob, &mut *carol] {
+            let events = board.deliver(member);
+            assert_eq!(events.len(), 1);
+            let GroupEvent::Joined {
+                group_id: joined_id,
+                by,
+                members: joined_members,
+            } = &events[0]
+            else {
+                panic!("Expected a join");
+            };
+            assert_eq!(joined_id, &group_id);
+            assert_eq!(by, &alice.id);
+            assert_eq!(sorted(joined_members.clone()), expected_members);
+        }
+        assert!(board.deliver(dave).is_empty());
+
+        // Bob writes to the group
+        let fan_out = bob
+            .group_manager
+            .send_message(&mut bob.session_manager, &group_id, b"hi from bob")
+            .unwrap();
+        board.post(&fan_out);
+        for member in [&mut *alice, &mut *carol] {
+            let events = board.deliver(member);
+            assert_eq!(events.len(), 1);
+            assert_message(&events[0], &group_id, &bob.id, b"hi from bob");
+        }
+
+        // Alice adds Dave
+        let fan_out = alice
+            .group_manager
+            .add_members(
+                &mut alice.session_manager,
+                &group_id,
+                std::slice::from_ref(&dave.id),
+            )
+            .unwrap();
+        assert_eq!(fan_out.outputs.len(), 3);
+        board.post(&fan_out);
+        for member in [&mut *bob, &mut *carol] {
+            let events = board.deliver(member);
+            let GroupEvent::MembersChanged {
+                by, added, removed, ..
+            } = &events[0]
+            else {
+                panic!("Expected a membership change");
+            };
+            assert_eq!(by, &alice.id);
+            assert_eq!(added, &vec![dave.id.clone()]);
+            assert!(removed.is_empty());
+        }
+        let events = board.deliver(dave);
+        assert!(matches!(&events[0], GroupEvent::Joined { members, .. } if members.len() == 4));
+
+        // Dave's message reaches everyone
+        let fan_out = dave
+            .group_manager
+            .send_message(&mut dave.session_manager, &group_id, b"hi from dave")
+            .unwrap();
+        board.post(&fan_out);
+        for member in [&mut *alice, &mut *bob, &mut *carol] {
+            let events = board.deliver(member);
+            assert_eq!(events.len(), 1);
+            assert_message(&events[0], &group_id, &dave.id, b"hi from dave");
+        }
+
+        // Carol leaves
+        let fan_out = carol
+            .group_manager
+            .leave_group(&mut carol.session_manager, &group_id)
+            .unwrap();
+        board.post(&fan_out);
+        assert!(carol.group_manager.group_list().is_empty());
+        for member in [&mut *alice, &mut *bob, &mut *dave] {
+            let events = board.deliver(member);
+            let GroupEvent::MembersChanged {
+                by, added, removed, ..
+            } = &events[0]
+            else {
+                panic!("Expected a membership change");
+            };
+            assert_eq!(by, &carol.id);
+            assert!(added.is_empty());
+            assert_eq!(removed, &vec![carol.id.clone()]);
+        }
+
+        // Alice removes Dave, who is notified
+        let fan_out = alice
+            .group_manager
+            .remove_members(
+                &mut alice.session_manager,
+                &group_id,
+                std::slice::from_ref(&dave.id),
+            )
+            .unwrap();
+        board.post(&fan_out);
+        board.deliver(bob);
+        let events = board.deliver(dave);
+        assert!(matches!(&events[0], GroupEvent::Removed { by, .. } if by == &alice.id));
+        assert!(dave.group_manager.group_list().is_empty());
+        assert_eq!(
+            dave.group_manager
+                .send_message(&mut dave.session_manager, &group_id, b"still here?")
+                .err(),
+            Some(GroupError::UnknownGroup)
+        );
+
+        // Only Bob receives Alice's next message
+        let fan_out = alice
+            .group_manager
+            .send_message(&mut alice.session_manager, &group_id, b"just us")
+            .unwrap();
+        assert_eq!(fan_out.outputs.len(), 1);
+        board.post(&fan_out);
+        assert!(board.deliver(carol).is_empty());
+        assert!(board.deliver(dave).is_empty());
+        let events = board.deliver(bob);
+        assert_message(&events[0], &group_id, &alice.id, b"just us");
+
+        // Everyone agrees on the members
+        let expected_members = sorted(vec![alice.id.clone(), bob.id.clone()]);
+        for member in [&*alice, &*bob] {
+            assert_eq!(
+                sorted(member.group_manager.group_members(&group_id).unwrap()),
+                expected_members
+            );
+        }
+    }
+
+    #[test]
+    fn test_unreachable_members_reported() {
+        let mut members: Vec<Member> = (0..2).map(|_| Member::new()).collect();
+        connect_all(&mut members);
+        let [alice, bob] = &mut members[..] else {
+            unreachable!()
+        };
+        let stranger = Member::new();
+
+        let (_group_id, fan_out) = alice.group_manager.create_group(
+            &mut alice.session_manager,
+            &[bob.id.clone(), stranger.id.clone()],
+        );
+        assert_eq!(fan_out.outputs.len(), 1);
+        assert_eq!(fan_out.outputs[0].0, bob.id);
+        assert_eq!(
+            fan_out.failures,
+            vec![(stranger.id.clone(), SessionError::NoActiveSession)]
+        );
+        assert!(is_group_message(&fan_out.payload));
+    }
+
+    #[test]
+    fn test_non_member_rejected() {
+        let mut members: Vec<Member> = (0..3).map(|_| Member::new()).collect();
+        connect_all(&mut members);
+        let [alice, bob, eve] = &mut members[..] else {
+            unreachable!()
+        };
+        let mut board = Board::default();
+
+        let (group_id, fan_out) = alice
+            .group_manager
+            .create_group(&mut alice.session_manager, std::slice::from_ref(&bob.id));
+        board.post(&fan_out);
+        board.deliver(bob);
+
+        // Eve knows the group ID but is not a member
+        let payload = GroupPayload::Message {
+            group_id: group_id.clone(),
+            message: b"let me in".to_vec(),
+        };
+        let output = eve
+            .session_manager
+            .send_message(&bob.id, &payload.to_bytes())
+            .unwrap();
+        let received = bob
+            .session_manager
+            .feed_incoming_message_board_read(&output.seeker, &output.data, &bob.sk)
+            .unwrap();
+        assert_eq!(
+            bob.group_manager.feed_incoming_message(&received).err(),
+            Some(GroupError::NotAMember)
+        );
+
+        // Nor can she change the members
+        let payload = GroupPayload::Membership {
+            group_id,
+            epoch: 10,
+            members: vec![bob.id.clone(), eve.id.clone()],
+        };
+        let output = eve
+            .session_manager
+            .send_message(&bob.id, &payload.to_bytes())
+            .unwrap();
+        let received = bob
+            .session_manager
+            .feed_incoming_message_board_read(&output.seeker, &output.data, &bob.sk)
+            .unwrap();
+        assert_eq!(
+            bob.group_manager.feed_incoming_message(&received).err(),
+            Some(GroupError::NotAMember)
+        );
+    }
+
+    #[test]
+    fn test_plain_message_is_not_group_message() {
+        let mut members: Vec<Member> = (0..2).map(|_| Member::new()).collect();
+        connect_all(&mut members);
+        let [alice, bob] = &mut members[..] else {
+            unreachable!()
+        };
+
+        let output = alice
+            .session_manager
+            .send_message(&bob.id, "\u{FF}GRP looks like a prefix".as_bytes())
+            .unwrap();
+        let received = bob
+            .session_manager
+            .feed_incoming_message_board_read(&output.seeker, &output.data, &bob.sk)
+            .unwrap();
+        assert!(!is_group_message(&received.message));
+        assert_eq!(
+            bob.group_manager.feed_incoming_message(&received).err(),
+            Some(GroupError::NotGroupMessage)
+        );
+    }
+
+    #[test]
+    fn test_concurrent_membership_changes_converge() {
+        let mut members: Vec<Member> = (0..4).map(|_| Member::new()).collect();
+        connect_all(&mut members);
+        let [alice, bob, carol, dave] = &mut members[..] else {
+            unreachable!()
+        };
+        let mut board = Board::default();
+
+        let (group_id, fan_out) = alice.group_manager.create_group(
+            &mut alice.session_manager,
+            &[bob.id.clone(), carol.id.clone()],
+        );
+        board.post(&fan_out);
+        board.deliver(bob);
+        board.deliver(carol);
+
+        // Alice adds Dave while Bob removes Carol
+        let alice_fan_out = alice
+            .group_manager
+            .add_members(
+                &mut alice.session_manager,
+                &group_id,
+                std::slice::from_ref(&dave.id),
+            )
+            .unwrap();
+        let bob_fan_out = bob
+            .group_manager
+            .remove_members(
+                &mut bob.session_manager,
+                &group_id,
+                std::slice::from_ref(&carol.id),
+            )
+            .unwrap();
+        board.post(&alice_fan_out);
+        board.post(&bob_fan_out);
+        board.read(alice);
+        board.read(bob);
+
+        // Both changes have the same epoch: the one from the lowest user ID wins
+        let winner = if alice.id.as_bytes() < bob.id.as_bytes() {
+            vec![
+                alice.id.clone(),
+                bob.id.clone(),
+                carol.id.clone(),
+                dave.id.clone(),
+            ]
+        } else {
+            vec![alice.id.clone(), bob.id.clone()]
+        };
+        for member in [&*alice, &*bob] {
+            assert_eq!(
+                sorted(member.group_manager.group_members(&group_id).unwrap()),
+                sorted(winner.clone())
+            );
+        }
+    }
+
+    #[test]
+    fn test_outdated_membership_rejected() {
+        let mut members: Vec<Member> = (0..3).map(|_| Member::new()).collect();
+        connect_all(&mut members);
+        let [alice, bob, carol] = &mut members[..] else {
+            unreachable!()
+        };
+        let mut board = Board::default();
+
+        let (group_id, fan_out) = alice
+            .group_manager
+            .create_group(&mut alice.session_manager, std::slice::from_ref(&bob.id));
+        board.post(&fan_out);
+        board.deliver(bob);
+        let fan_out = alice
+            .group_manager
+            .add_members(
+                &mut alice.session_manager,
+                &group_id,
+                std::slice::from_ref(&carol.id),
+            )
+            .unwrap();
+        board.post(&fan_out);
+        board.deliver(bob);
+
+        // Replaying the initial membership through a fresh message is rejected
+        let payload = GroupPayload::Membership {
+            group_id,
+            epoch: 0,
+            members: vec![alice.id.clone(), bob.id.clone()],
+        };
+        let output = alice
+            .session_manager
+            .send_message(&bob.id, &payload.to_bytes())
+            .unwrap();
+        let received = bob
+            .session_manager
+            .feed_incoming_message_board_read(&output.seeker, &output.data, &bob.sk)
+            .unwrap();
+        assert_eq!(
+            bob.group_manager.feed_incoming_message(&received).err(),
+            Some(GroupError::OutdatedMembership)
+        );
+    }
+
+    #[test]
+    fn test_membership_epoch_jump_bounded() {
+        let mut members: Vec<Member> = (0..3).map(|_| Member::new()).collect();
+        connect_all(&mut members);
+        let [alice, bob, carol] = &mut members[..] else {
+            unreachable!()
+        };
+        let mut board = Board::default();
+
+        let (group_id, fan_out) = alice
+            .group_manager
+            .create_group(&mut alice.session_manager, std::slice::from_ref(&bob.id));
+        board.post(&fan_out);
+        board.deliver(bob);
+
+        // Alice cannot freeze the membership with an epoch too far ahead
+        let payload = GroupPayload::Membership {
+            group_id: group_id.clone(),
+            epoch: u64::MAX,
+            members: vec![alice.id.clone(), bob.id.clone()],
+        };
+        let output = alice
+            .session_manager
+            .send_message(&bob.id, &payload.to_bytes())
+            .unwrap();
+        let received = bob
+            .session_manager
+            .feed_incoming_message_board_read(&output.seeker, &output.data, &bob.sk)
+            .unwrap();
+        assert_eq!(
+            bob.group_manager.feed_incoming_message(&received).err(),
+            Some(GroupError::InvalidEpoch)
+        );
+
+        // Bob can still change the membership, and everyone follows
+        let fan_out = bob
+            .group_manager
+            .add_members(
+                &mut bob.session_manager,
+                &group_id,
+                std::slice::from_ref(&carol.id),
+            )
+            .unwrap();
+        board.post(&fan_out);
+        board.deliver(alice);
+        board.deliver(carol);
+        for member in [&*alice, &*bob, &*carol] {
+            assert_eq!(
+                member.group_manager.group_members(&group_id).unwrap().len(),
+                3
+            );
+        }
+
+        // A group joined at the last epoch cannot change anymore, instead of wrapping around
+        let group_id = GroupId::generate();
+        let payload = GroupPayload::Membership {
+            group_id: group_id.clone(),
+            epoch: u64::MAX,
+            members: vec![alice.id.clone(), bob.id.clone()],
+        };
+        let output = alice
+            .session_manager
+            .send_message(&bob.id, &payload.to_bytes())
+            .unwrap();
+        let received = bob
+            .session_manager
+            .feed_incoming_message_board_read(&output.seeker, &output.data, &bob.sk)
+            .unwrap();
+        assert!(matches!(
+            bob.group_manager.feed_incoming_message(&received),
+            Ok(GroupEvent::Joined { .. })
+        ));
+        assert_eq!(
+            bob.group_manager
+                .leave_group(&mut bob.session_manager, &group_id)
+                .err(),
+            Some(GroupError::InvalidEpoch)
+        );
+    }
+
+    #[test]
+    fn test_group_manager_persistence() {
+        let mut members: Vec<Member> = (0..2).map(|_| Member::new()).collect();
+        connect_all(&mut members);
+        let [alice, bob] = &mut members[..] else {
+            unreachable!()
+        };
+
+        let (group_id, _fan_out) = alice
+            .group_manager
+            .create_group(&mut alice.session_manager, std::slice::from_ref(&bob.id));
+
+        let mut key_bytes = [0u8; crypto_aead::KEY_SIZE];
+        crypto_rng::fill_buffer(&mut key_bytes);
+        let key = crypto_aead::Key::from(key_bytes);
+        let blob = alice.group_manager.to_encrypted_blob(&key).unwrap();
+
+        let restored = GroupManager::from_encrypted_blob(&blob, &key).unwrap();
+        assert_eq!(restored.group_list(), vec![group_id.clone()]);
+        assert_eq!(
+            restored.group_members(&group_id),
+            alice.group_manager.group_members(&group_id)
+        );
+
+        // A session manager blob is not a group manager blob
+        let session_blob = alice.session_manager.to_encrypted_blob(&key).unwrap();
+        assert_eq!(
+            GroupManager::from_encrypted_blob(&session_blob, &key).err(),
+            Some(SessionError::StateUndecryptable)
+        );
+    }
+}
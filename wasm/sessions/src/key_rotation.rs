@@ -0,0 +1,373 @@
+//! Key rotation over pairwise sessions.
+//!
+//! A user who rotates their long-term keys signs an [`auth::RotationCertificate`] with their old
+//! keys, and sends the chain of their certificates to each peer through the existing sessions.
+//! Peers feed it to [`SessionManager::feed_key_rotation`], which moves the sessions over to the
+//! new identity.
+//!
+//! Until a peer processes the rotation, it keeps encrypting messages to the old keys: the
+//! rotating user sets them with [`SessionManager::set_previous_secret_keys`] so that these
+//! messages remain readable.
+//!
+//! # Wire format
+//!
+//! Key rotation messages are session messages starting with [`KEY_ROTATION_MESSAGE_PREFIX`],
+//! followed by the serialized chain of certificates. Like group messages, they cannot be
+//! confused with text messages. Use [`is_key_rotation_message`] to route incoming messages.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use sessions::{SessionManager, is_key_rotation_message, parse_key_rotation_message};
+//! # fn example(
+//! #     session_manager: &mut SessionManager,
+//! #     received_message: &[u8],
+//! # ) {
+//! if is_key_rotation_message(received_message) {
+//!     if let Ok(chain) = parse_key_rotation_message(received_message) {
+//!         if let Ok(new_peer_id) = session_manager.feed_key_rotation(&chain) {
+//!             // the peer is now known as new_peer_id
+//!         }
+//!     }
+//! }
+//! # }
+//! ```
+
+use crate::error::KeyRotationError;
+
+/// Prefix of the session messages that carry a chain of key rotation certificates.
+///
+/// `0xFF` never appears in UTF-8 text.
+pub const KEY_ROTATION_MESSAGE_PREFIX: [u8; 4] = [0xFF, b'R', b'O', b'T'];
+
+/// Returns `true` if a message received through a session is a key rotation message,
+/// and should be parsed with [`parse_key_rotation_message`].
+pub fn is_key_rotation_message(message: &[u8]) -> bool {
+    message.starts_with(&KEY_ROTATION_MESSAGE_PREFIX)
+}
+
+/// Serializes a chain of rotation certificates, oldest first, into a session message
+/// to send to each peer with [`SessionManager::send_message`](crate::SessionManager::send_message).
+pub fn key_rotation_message(chain: &[auth::RotationCertificate]) -> Vec<u8> {
+    let serialized = bincode::serde::encode_to_vec(chain, bincode::config::standard())
+        .expect("Failed to serialize key rotation chain");
+    [KEY_ROTATION_MESSAGE_PREFIX.as_slice(), &serialized].concat()
+}
+
+/// Parses a key rotation message. The certificates are verified by
+/// [`SessionManager::feed_key_rotation`](crate::SessionManager::feed_key_rotation).
+///
+/// # Errors
+///
+/// - [`KeyRotationError::NotKeyRotationMessage`] if the message does not start with
+///   [`KEY_ROTATION_MESSAGE_PREFIX`]
+/// - [`KeyRotationError::MalformedMessage`] if the chain cannot be parsed
+pub fn parse_key_rotation_message(
+    message: &[u8],
+) -> Result<Vec<auth::RotationCertificate>, KeyRotationError> {
+    let serialized = message
+        .strip_prefix(KEY_ROTATION_MESSAGE_PREFIX.as_slice())
+        .ok_or(KeyRotationError::NotKeyRotationMessage)?;
+    let (chain, read): (Vec<auth::RotationCertificate>, usize) =
+        bincode::serde::decode_from_slice(serialized, bincode::config::standard())
+            .map_err(|_| KeyRotationError::MalformedMessage)?;
+    if read != serialized.len() {
+        return Err(KeyRotationError::MalformedMessage);
+    }
+    Ok(chain)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        error::SessionError,
+        session_manager::{SessionManager, SessionManagerConfig, SessionStatus},
+        storage::MemoryStorage,
+    };
+    use auth::UserId;
+    use std::collections::HashSet;
+
+    fn create_test_config() -> SessionManagerConfig {
+        SessionManagerConfig {
+            max_incoming_announcement_age_millis: 60_000,
+            max_incoming_announcement_future_millis: 5_000,
+            max_incoming_message_age_millis: 300_000,
+            max_incoming_message_future_millis: 5_000,
+            max_session_inactivity_millis: 3_600_000,
+            keep_alive_interval_millis: 60_000,
+            max_session_lag_length: 100,
+            max_skipped_messages: 16,
+            max_stored_skipped_messages: 128,
+        }
+    }
+
+    /// A simulated user with their keys and sessions
+    struct User {
+        root_secret: auth::StaticRootSecret,
+        pk: auth::UserPublicKeys,
+        sk: auth::UserSecretKeys,
+        session_manager: SessionManager,
+    }
+
+    impl User {
+        fn new() -> Self {
+            let mut root_secret = [0u8; auth::STATIC_ROOT_SECRET_SIZE];
+            crypto_rng::fill_buffer(&mut root_secret);
+            let root_secret = auth::StaticRootSecret::from_bytes(root_secret);
+            let (pk, sk) = auth::derive_keys_from_static_root_secret(&root_secret);
+            Self {
+                root_secret,
+                pk,
+                sk,
+                session_manager: SessionManager::new(create_test_config()),
+            }
+        }
+
+        fn id(&self) -> UserId {
+            self.pk.derive_id()
+        }
+
+        /// Moves to a generation of keys, keeping the previous secret keys to read the
+        /// messages that peers send before following the rotation.
+        fn rotate(&mut self, generation: u32) -> auth::RotationCertificate {
+            let (new_pk, new_sk) =
+                auth::derive_rotated_keys_from_static_root_secret(&self.root_secret, generation);
+            let old_pk = std::mem::replace(&mut self.pk, new_pk.clone());
+            let old_sk = std::mem::replace(&mut self.sk, new_sk);
+            let certificate = auth::RotationCertificate::new(old_pk, &old_sk, new_pk);
+            self.session_manager.set_previous_secret_keys(vec![old_sk]);
+            certificate
+        }
+
+        /// Sends a message to `peer_id` and feeds it to `to`, returning what `to` received.
+        fn send(
+            &mut self,
+            peer_id: &UserId,
+            to: &mut User,
+            message: &[u8],
+        ) -> Result<Vec<u8>, SessionError> {
+            let output = self
+                .session_manager
+                .send_message(peer_id, message)
+                .expect("Failed to send message");
+            to.session_manager
+                .feed_incoming_message_board_read(&output.seeker, &output.data, &to.sk)
+                .map(|received| received.message.clone())
+        }
+    }
+
+    fn connect(a: &mut User, b: &mut User) {
+        let a_announcement =
+            a.session_manager
+                .establish_outgoing_session(&b.pk, &a.pk, &a.sk, vec![]);
+        let b_announcement =
+            b.session_manager
+                .establish_outgoing_session(&a.pk, &b.pk, &b.sk, vec![]);
+        a.session_manager
+            .feed_incoming_announcement(&b_announcement, &a.pk, &a.sk)
+            .expect("Failed to process announcement");
+        b.session_manager
+            .feed_incoming_announcement(&a_announcement, &b.pk, &b.sk)
+            .expect("Failed to process announcement");
+    }
+
+    #[test]
+    fn test_message_roundtrip() {
+        let alice = User::new();
+        let (new_pk, _) = auth::derive_rotated_keys_from_static_root_secret(&alice.root_secret, 1);
+        let chain = [auth::RotationCertificate::new(
+            alice.pk.clone(),
+            &alice.sk,
+            new_pk.clone(),
+        )];
+
+        let message = key_rotation_message(&chain);
+        assert!(is_key_rotation_message(&message));
+        assert!(!is_key_rotation_message(b"hello"));
+
+        let parsed = parse_key_rotation_message(&message).unwrap();
+        assert_eq!(parsed.len(), 1);
+        assert!(parsed[0].verify());
+        assert_eq!(parsed[0].new_id(), new_pk.derive_id());
+
+        assert_eq!(
+            parse_key_rotation_message(b"hello").err(),
+            Some(KeyRotationError::NotKeyRotationMessage)
+        );
+        assert_eq!(
+            parse_key_rotation_message(&message[..message.len() - 1]).err(),
+            Some(KeyRotationError::MalformedMessage)
+        );
+        assert_eq!(
+            parse_key_rotation_message(&[message.as_slice(), &[0]].concat()).err(),
+            Some(KeyRotationError::MalformedMessage)
+        );
+    }
+
+    #[test]
+    fn test_session_follows_rotation() {
+        let mut alice = User::new();
+        let mut bob = User::new();
+        connect(&mut alice, &mut bob);
+        let alice_old_id = alice.id();
+        assert!(bob.session_manager.mark_peer_verified(&alice.pk));
+
+        // Alice rotates and tells Bob through their session
+        let rotation = key_rotation_message(&[alice.rotate(1)]);
+        let alice_new_id = alice.id();
+        let received = alice.send(&bob.id(), &mut bob, &rotation).unwrap();
+
+        // Bob sends a message before following: Alice reads it with her previous keys
+        assert_eq!(
+            bob.send(&alice_old_id, &mut alice, b"before").unwrap(),
+            b"before"
+        );
+
+        // Bob follows Alice to her new ID, keeping the session and the verification
+        let chain = parse_key_rotation_message(&received).unwrap();
+        assert_eq!(
+            bob.session_manager.feed_key_rotation(&chain),
+            Ok(alice_new_id.clone())
+        );
+        assert_eq!(bob.session_manager.peer_list(), vec![alice_new_id.clone()]);
+        assert!(matches!(
+            bob.session_manager.peer_session_status(&alice_new_id),
+            SessionStatus::Active
+        ));
+        assert!(matches!(
+            bob.session_manager.peer_session_status(&alice_old_id),
+            SessionStatus::UnknownPeer
+        ));
+        assert!(bob.session_manager.is_peer_verified(&alice_new_id));
+        assert_eq!(
+            bob.session_manager
+                .peer_public_keys(&alice_new_id)
+                .map(|pk| pk.derive_id()),
+            Some(alice_new_id.clone())
+        );
+
+        // Bob's messages are now encrypted to Alice's new keys
+        alice.session_manager.set_previous_secret_keys(Vec::new());
+        assert_eq!(
+            bob.send(&alice_new_id, &mut alice, b"after").unwrap(),
+            b"after"
+        );
+
+        // Alice's messages are attributed to her new ID
+        let output = alice
+            .session_manager
+            .send_message(&bob.id(), b"hi")
+            .unwrap();
+        let received = bob
+            .session_manager
+            .feed_incoming_message_board_read(&output.seeker, &output.data, &bob.sk)
+            .unwrap();
+        assert_eq!(received.user_id, alice_new_id.as_bytes());
+
+        // the rotation was already followed
+        assert_eq!(
+            bob.session_manager.feed_key_rotation(&chain),
+            Err(KeyRotationError::UnknownPeer)
+        );
+    }
+
+    #[test]
+    fn test_messages_to_previous_keys_need_them() {
+        let mut alice = User::new();
+        let mut bob = User::new();
+        connect(&mut alice, &mut bob);
+        let alice_old_id = alice.id();
+
+        // Alice rotates but forgets her previous keys
+        let _ = alice.rotate(1);
+        alice.session_manager.set_previous_secret_keys(Vec::new());
+        assert_eq!(
+            bob.send(&alice_old_id, &mut alice, b"lost"),
+            Err(SessionError::DecryptionFailed)
+        );
+    }
+
+    #[test]
+    fn test_rotation_chain_catches_up() {
+        let mut alice = User::new();
+        let mut bob = User::new();
+        connect(&mut alice, &mut bob);
+
+        // Bob missed two rotations: Alice sends the whole chain
+        let chain = [alice.rotate(1), alice.rotate(2)];
+        assert_eq!(
+            bob.session_manager.feed_key_rotation(&chain),
+            Ok(alice.id())
+        );
+        assert_eq!(bob.session_manager.peer_list(), vec![alice.id()]);
+    }
+
+    #[test]
+    fn test_invalid_rotation_rejected() {
+        let mut alice = User::new();
+        let mut bob = User::new();
+        let mut carol = User::new();
+        let mallory = User::new();
+        connect(&mut alice, &mut bob);
+        connect(&mut bob, &mut carol);
+        let peers: HashSet<UserId> = bob.session_manager.peer_list().into_iter().collect();
+
+        // not signed by the old keys
+        let forged =
+            auth::RotationCertificate::new(alice.pk.clone(), &mallory.sk, mallory.pk.clone());
+        assert_eq!(
+            bob.session_manager.feed_key_rotation(&[forged]),
+            Err(KeyRotationError::InvalidCertificate)
+        );
+
+        // unknown peer
+        let (new_pk, _) =
+            auth::derive_rotated_keys_from_static_root_secret(&mallory.root_secret, 1);
+        let unknown = auth::RotationCertificate::new(mallory.pk.clone(), &mallory.sk, new_pk);
+        assert_eq!(
+            bob.session_manager.feed_key_rotation(&[unknown]),
+            Err(KeyRotationError::UnknownPeer)
+        );
+        assert_eq!(
+            bob.session_manager.feed_key_rotation(&[]),
+            Err(KeyRotationError::UnknownPeer)
+        );
+
+        // rotation onto the keys of another peer
+        let onto_carol =
+            auth::RotationCertificate::new(alice.pk.clone(), &alice.sk, carol.pk.clone());
+        assert_eq!(
+            bob.session_manager.feed_key_rotation(&[onto_carol]),
+            Err(KeyRotationError::IdentityInUse)
+        );
+
+        // nothing changed
+        let peers_after: HashSet<UserId> = bob.session_manager.peer_list().into_iter().collect();
+        assert_eq!(peers_after, peers);
+    }
+
+    #[test]
+    fn test_rotation_persisted() {
+        let mut alice = User::new();
+        let mut bob = User::new();
+        connect(&mut alice, &mut bob);
+        let mut key_bytes = [0u8; crypto_aead::KEY_SIZE];
+        crypto_rng::fill_buffer(&mut key_bytes);
+        let key = crypto_aead::Key::from(key_bytes);
+        let mut storage = MemoryStorage::new();
+        bob.session_manager.persist_to(&mut storage, &key).unwrap();
+
+        let certificate = alice.rotate(1);
+        bob.session_manager
+            .feed_key_rotation(&[certificate])
+            .unwrap();
+        assert_eq!(bob.session_manager.persist_to(&mut storage, &key), Ok(2));
+
+        // the state under the old ID was removed
+        let restored = SessionManager::restore_from(create_test_config(), &storage, &key).unwrap();
+        assert_eq!(restored.peer_list(), vec![alice.id()]);
+        assert!(storage.get(&alice.id()).is_some());
+        assert_eq!(storage.len(), 1);
+    }
+}
@@ -17,6 +17,7 @@
 //! - **Sealed seekers**: Message board lookups don't reveal sender/receiver identities
 //! - **Session lifecycle management**: Automatic expiry, keep-alive, and lag control
 //! - **Concurrent sessions**: Manage multiple peer sessions simultaneously
+//! - **Group messaging**: Groups with membership changes, over pairwise sessions
 //!
 //! # Security
 //!
@@ -55,9 +56,11 @@
 //!
 //! - **Clock Synchronization**: The timestamp validation relies on reasonably synchronized clocks.
 //!   Configure `max_incoming_announcement_future_millis` and `max_incoming_message_future_millis`
-//!   based on expected clock drift.
+//!   based on expected clock drift. The current time is read through a [`Clock`], which defaults
+//!   to [`SystemClock`] and can be replaced with [`SessionManager::with_clock`].
 //! - **Denial of Service**: The `max_session_lag_length` configuration prevents memory exhaustion
-//!   from unacknowledged messages.
+//!   from unacknowledged messages, and `max_skipped_messages` / `max_stored_skipped_messages`
+//!   bound the state kept for messages delivered out of order.
 //! - **Session Expiry**: Configure `max_session_inactivity_millis` appropriately to balance security
 //!   (shorter = less time for attacks) vs usability (longer = fewer re-establishments).
 //! - **Key Material**: All sensitive key material uses `zeroize` to clear memory on drop.
@@ -110,6 +113,8 @@
 //!     max_session_inactivity_millis: 3_600_000,           // 1 hour
 //!     keep_alive_interval_millis: 60_000,                 // 1 minute
 //!     max_session_lag_length: 100,                        // max unacknowledged messages
+//!     max_skipped_messages: 16,                           // max consecutive missing messages
+//!     max_stored_skipped_messages: 128,                   // max missing messages still readable
 //! };
 //!
 //! let mut session_manager = SessionManager::new(config);
@@ -134,7 +139,7 @@
 //! loop {
 //!     // 1. Process incoming announcements from the blockchain
 //!     for announcement_bytes in fetch_new_announcements_fn() {
-//!         if let Some(result) = session_manager.feed_incoming_announcement(
+//!         if let Ok(result) = session_manager.feed_incoming_announcement(
 //!             &announcement_bytes,
 //!             &our_pk,
 //!             &our_sk
@@ -152,7 +157,7 @@
 //!     // 3. Check for incoming messages using those seekers
 //!     for seeker in seekers {
 //!         if let Some(message_bytes) = blockchain_read_fn(&seeker) {
-//!             if let Some(msg_output) = session_manager.feed_incoming_message_board_read(
+//!             if let Ok(msg_output) = session_manager.feed_incoming_message_board_read(
 //!                 &seeker,
 //!                 &message_bytes,
 //!                 &our_sk
@@ -171,7 +176,7 @@
 //!     // 4. Send outgoing messages
 //!     let peer_id: UserId = peer_pk.derive_id();
 //!     let message_contents = b"Hello, peer!";
-//!     if let Some(output) = session_manager.send_message(&peer_id, message_contents) {
+//!     if let Ok(output) = session_manager.send_message(&peer_id, message_contents) {
 //!         // Publish to blockchain message board
 //!         blockchain_write_fn(&output.seeker, &output.data);
 //!     }
@@ -180,12 +185,17 @@
 //!     let keep_alive_peers = session_manager.refresh();
 //!     for peer_id in keep_alive_peers {
 //!         let keep_alive_msg = b"";  // Empty message for keep-alive
-//!         if let Some(output) = session_manager.send_message(&peer_id, keep_alive_msg) {
+//!         if let Ok(output) = session_manager.send_message(&peer_id, keep_alive_msg) {
 //!             blockchain_write_fn(&output.seeker, &output.data);
 //!         }
 //!     }
 //!
-//!     // 6. Check session statuses
+//!     // 6. Re-establish broken sessions
+//!     for (_peer_id, _announcement) in session_manager.heal_sessions(&our_pk, &our_sk) {
+//!         // Publish `announcement` to the blockchain announcement board
+//!     }
+//!
+//!     // 7. Check session statuses
 //!     for peer_id in session_manager.peer_list() {
 //!         match session_manager.peer_session_status(&peer_id) {
 //!             SessionStatus::Active => { /* Session is healthy */ },
@@ -213,15 +223,107 @@
 //! 2. **Handshake**: When both peers have sent announcements, `feed_incoming_announcement()` creates the session
 //! 3. **Active Communication**: Use `send_message()` and `feed_incoming_message_board_read()` to exchange messages
 //! 4. **Keep-Alive**: Call `refresh()` periodically and send keep-alive messages to prevent expiry
-//! 5. **Termination**: Sessions expire after `max_session_inactivity_millis` of inactivity, or can be manually
+//! 5. **Healing**: Call `heal_sessions()` periodically and publish the announcements it returns, to
+//!    re-establish the sessions closed by a rejected message or lost by the peer
+//! 6. **Termination**: Sessions expire after `max_session_inactivity_millis` of inactivity, or can be manually
 //!    closed with `peer_discard()`
+//!
+//! ## Group Messaging
+//!
+//! A [`GroupManager`] runs groups on top of the pairwise sessions: group messages and membership
+//! changes are sent to each member through their session, so they use the same seekers and the
+//! same message board as pairwise messages. Messages received from a session are routed to the
+//! group manager when [`is_group_message`] returns `true`.
+//!
+//! ## Large Messages
+//!
+//! Each message is posted as a single message board entry. Payloads too large for one entry
+//! can be sent with [`send_fragmented_message`], which splits them into fragments sent as
+//! consecutive messages, and rebuilt by a [`FragmentReassembler`]. Messages received from a
+//! session are routed to the reassembler when [`is_fragment`] returns `true`.
+//!
+//! Files and other bulky content are sent as attachments instead: [`encrypt_attachment`]
+//! encrypts them under a fresh key into a ciphertext to store off-board, and returns an
+//! [`AttachmentDescriptor`] carrying the key, the hash of the ciphertext and the size. Only the
+//! descriptor is sent through the session, and [`is_attachment`] identifies it on reception.
+//!
+//! ## Key Rotation
+//!
+//! A peer that rotates its long-term keys changes its user ID. It sends the chain of its
+//! [`auth::RotationCertificate`]s through its sessions with [`key_rotation_message`], and
+//! receivers identify it with [`is_key_rotation_message`] and feed the parsed chain to
+//! `feed_key_rotation()`, which moves the peer and its session to the new ID. The rotating user
+//! keeps reading messages encrypted to its old keys with `set_previous_secret_keys()`.
+//!
+//! ## Social Recovery
+//!
+//! The shares of a root secret split with [`auth::split_root_secret`] are handed to trusted
+//! contacts through their sessions with [`recovery_share_message`], and identified on reception
+//! with [`is_recovery_share_message`].
+//!
+//! ## Persistence
+//!
+//! The whole session manager can be saved with `to_encrypted_blob()` and restored with
+//! `from_encrypted_blob()`. To avoid re-encrypting every peer after each change, the state can
+//! instead be saved per peer into a [`SessionStorage`] backend: `persist_to()` only writes the
+//! peers that changed since the previous call, and `restore_from()` loads them back. A manager
+//! restored from a whole blob considers all its peers changed, so the first `persist_to()`
+//! migrates it to per-peer storage.
+//!
+//! Saved states start with a magic number and a schema version. States written by an older
+//! version of this crate are upgraded when restored, while states from a newer version are
+//! rejected with [`SessionError::UnsupportedStateVersion`].
+//!
+//! ## Error Handling
+//!
+//! Rejected announcements are reported as [`AnnouncementError`], and messaging or state restoration
+//! failures as [`SessionError`]. Failures that only depend on public data (timestamps, seekers,
+//! signatures) are reported precisely, while all decryption failures map to a single opaque variant
+//! so that the reason cannot be probed.
 
+mod attachment;
+mod clock;
+mod error;
+mod fragment;
+mod group;
+mod key_rotation;
+mod recovery;
 mod session;
 mod session_manager;
+mod state_format;
+mod storage;
 mod utils;
 
-pub use session::{FeedIncomingMessageOutput, SendOutgoingMessageOutput};
+pub use attachment::{
+    ATTACHMENT_HASH_SIZE, ATTACHMENT_MESSAGE_PREFIX, AttachmentDescriptor,
+    DEFAULT_ATTACHMENT_CHUNK_SIZE, encrypt_attachment, is_attachment,
+};
+pub use clock::{Clock, ManualClock, SystemClock};
+pub use error::{
+    AnnouncementError, AttachmentError, FragmentError, GroupError, KeyRotationError, SessionError,
+    StorageError,
+};
+pub use fragment::{
+    FRAGMENT_MESSAGE_PREFIX, FragmentConfig, FragmentReassembler, ReassembledMessage, is_fragment,
+    send_fragmented_message,
+};
+pub use group::{
+    GROUP_ID_SIZE, GROUP_MESSAGE_PREFIX, GroupEvent, GroupFanOut, GroupId, GroupManager,
+    GroupMessage, MAX_MEMBERSHIP_EPOCH_JUMP, is_group_message,
+};
+pub use key_rotation::{
+    KEY_ROTATION_MESSAGE_PREFIX, is_key_rotation_message, key_rotation_message,
+    parse_key_rotation_message,
+};
+pub use recovery::{
+    RECOVERY_SHARE_MESSAGE_PREFIX, is_recovery_share_message, parse_recovery_share_message,
+    recovery_share_message,
+};
+pub use session::{DecryptedIncomingMessage, FeedIncomingMessageOutput, SendOutgoingMessageOutput};
 pub use session::{IncomingInitiationRequest, OutgoingInitiationRequest, Session};
 pub use session_manager::{
     AnnouncementResult, SessionManager, SessionManagerConfig, SessionStatus,
 };
+pub use storage::{MemoryStorage, SessionStorage};
+
+pub use crypto_agraphon::SkippedMessageLimits;
@@ -0,0 +1,82 @@
+//! Handing recovery shares to trusted contacts over pairwise sessions.
+//!
+//! A user who splits their root secret with [`auth::split_root_secret`] sends one
+//! [`auth::RecoveryShare`] to each trusted contact through their session. The contact stores it,
+//! and sends it back when the user, having lost their passphrase, asks for it from a new device.
+//!
+//! # Wire format
+//!
+//! Recovery share messages are session messages starting with
+//! [`RECOVERY_SHARE_MESSAGE_PREFIX`], followed by the serialized share. Like group messages,
+//! they cannot be confused with text messages. Use [`is_recovery_share_message`] to route
+//! incoming messages.
+
+/// Prefix of the session messages that carry a recovery share.
+///
+/// `0xFF` never appears in UTF-8 text.
+pub const RECOVERY_SHARE_MESSAGE_PREFIX: [u8; 4] = [0xFF, b'R', b'C', b'V'];
+
+/// Returns `true` if a message received through a session is a recovery share message,
+/// and should be parsed with [`parse_recovery_share_message`].
+pub fn is_recovery_share_message(message: &[u8]) -> bool {
+    message.starts_with(&RECOVERY_SHARE_MESSAGE_PREFIX)
+}
+
+/// Serializes a recovery share into a session message to send to a trusted contact with
+/// [`SessionManager::send_message`](crate::SessionManager::send_message).
+pub fn recovery_share_message(share: &auth::RecoveryShare) -> Vec<u8> {
+    [
+        RECOVERY_SHARE_MESSAGE_PREFIX.as_slice(),
+        share.to_bytes().as_slice(),
+    ]
+    .concat()
+}
+
+/// Parses a recovery share message, verifying the checksum of the share.
+///
+/// # Errors
+///
+/// Returns [`auth::RecoveryError::Malformed`] if the message does not start with
+/// [`RECOVERY_SHARE_MESSAGE_PREFIX`], or the error of [`auth::RecoveryShare::from_bytes`].
+pub fn parse_recovery_share_message(
+    message: &[u8],
+) -> Result<auth::RecoveryShare, auth::RecoveryError> {
+    let serialized = message
+        .strip_prefix(RECOVERY_SHARE_MESSAGE_PREFIX.as_slice())
+        .ok_or(auth::RecoveryError::Malformed)?;
+    auth::RecoveryShare::from_bytes(serialized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message_roundtrip() {
+        let root_secret = auth::StaticRootSecret::from_bytes([3u8; auth::STATIC_ROOT_SECRET_SIZE]);
+        let shares = auth::split_root_secret(&root_secret, 2, 3).unwrap();
+
+        let messages = shares
+            .iter()
+            .map(recovery_share_message)
+            .collect::<Vec<_>>();
+        assert!(messages.iter().all(|m| is_recovery_share_message(m)));
+        assert!(!is_recovery_share_message(b"hello"));
+
+        let received = messages[1..]
+            .iter()
+            .map(|m| parse_recovery_share_message(m).unwrap())
+            .collect::<Vec<_>>();
+        let recovered = auth::recover_root_secret(&received).unwrap();
+        assert_eq!(recovered.as_slice(), root_secret.as_slice());
+
+        assert_eq!(
+            parse_recovery_share_message(b"hello").err(),
+            Some(auth::RecoveryError::Malformed)
+        );
+        assert_eq!(
+            parse_recovery_share_message(&messages[0][..messages[0].len() - 1]).err(),
+            Some(auth::RecoveryError::InvalidLength)
+        );
+    }
+}
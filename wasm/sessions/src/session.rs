@@ -6,7 +6,7 @@
 //! - **End-to-end encryption**: Messages are encrypted using the Agraphon double-ratchet protocol
 //! - **Forward secrecy**: Past messages remain secure even if current keys are compromised
 //! - **Post-compromise security**: Security is restored after key compromise
-//! - **Message ordering**: Lag detection and acknowledgment tracking
+//! - **Message ordering**: Lag detection, acknowledgment tracking and out-of-order delivery
 //! - **Seeker-based addressing**: Messages are identified by hashed Massa public keys derived from ephemeral keypairs
 //!
 //! # Protocol Flow
@@ -25,9 +25,16 @@
 //!
 //! During session initialization, each party generates a random 32-byte seeker seed and includes
 //! it in their announcement. When establishing the session, both parties use a KDF to deterministically
-//! derive initial seeker keypairs by combining both seeds (in appropriate order). This ensures both
-//! parties can independently compute each other's initial seeker keys, while maintaining forward secrecy
-//! through the message-level seeker ratchet.
+//! derive a seeker chain for each side by combining both seeds (in appropriate order). Each step of a
+//! chain derives the seeker keypair of a message along with the next chain key, so both parties can
+//! independently compute the seeker keys of each other's messages, while a chain key does not reveal
+//! the seekers of the messages before it.
+//!
+//! The recipient keeps the seeker keypairs of the sender's next `max_skip + 1` messages
+//! (see [`SkippedMessageLimits`]). A recipient that misses some messages can therefore still look up
+//! and decrypt the following ones, and the missing messages remain readable if they show up later.
+//!
+//! Known-answer vectors for the seeker derivation are in `test_vectors/seekers.json`.
 //!
 //! # Example
 //!
@@ -37,11 +44,13 @@
 //! # let (alice_pk, alice_sk) = derive_keys_from_static_root_secret(&root_secret_a);
 //! # let root_secret_b = StaticRootSecret::from_passphrase(b"bob");
 //! # let (bob_pk, bob_sk) = derive_keys_from_static_root_secret(&root_secret_b);
-//! use sessions::{OutgoingInitiationRequest, IncomingInitiationRequest, Session};
+//! use sessions::{
+//!     IncomingInitiationRequest, OutgoingInitiationRequest, Session, SkippedMessageLimits, SystemClock,
+//! };
 //!
 //! // Alice creates an outgoing announcement
 //! let (alice_announcement_bytes, alice_outgoing) =
-//!     OutgoingInitiationRequest::new(&alice_pk, &alice_sk, &bob_pk, vec![]);
+//!     OutgoingInitiationRequest::new(&alice_pk, &alice_sk, &bob_pk, vec![], &SystemClock);
 //!
 //! // Bob receives it and parses it
 //! let (alice_incoming_at_bob, _user_data) =
@@ -50,28 +59,34 @@
 //!
 //! // Bob creates his own announcement and both establish sessions
 //! let (bob_announcement_bytes, bob_outgoing) =
-//!     OutgoingInitiationRequest::new(&bob_pk, &bob_sk, &alice_pk, vec![]);
+//!     OutgoingInitiationRequest::new(&bob_pk, &bob_sk, &alice_pk, vec![], &SystemClock);
 //! let (bob_incoming_at_alice, _user_data) =
 //!     IncomingInitiationRequest::try_from(&bob_announcement_bytes, &alice_pk, &alice_sk).unwrap();
 //!
+//! let limits = SkippedMessageLimits::default();
 //! let mut alice_session =
-//!     Session::from_initiation_request_pair(&alice_outgoing, &bob_incoming_at_alice);
+//!     Session::from_initiation_request_pair(&alice_outgoing, &bob_incoming_at_alice, limits);
 //! let mut bob_session =
-//!     Session::from_initiation_request_pair(&bob_outgoing, &alice_incoming_at_bob);
+//!     Session::from_initiation_request_pair(&bob_outgoing, &alice_incoming_at_bob, limits);
 //!
 //! // Alice sends a message
-//! let output = alice_session.send_outgoing_message(b"Hello Bob!");
+//! let output = alice_session.send_outgoing_message(b"Hello Bob!", &SystemClock);
 //!
 //! // Bob retrieves it and decrypts
-//! let seeker = alice_session.next_peer_message_seeker();
 //! let received = bob_session
-//!     .try_feed_incoming_message(&bob_sk, &seeker, &output.data)
+//!     .try_feed_incoming_message(&bob_sk, &output.seeker, &output.data)
 //!     .expect("Failed to decrypt");
 //!
 //! assert_eq!(received.message, b"Hello Bob!");
 //! ```
 
+use crate::{
+    clock::Clock,
+    error::{AnnouncementError, SessionError},
+};
+use crypto_agraphon::SkippedMessageLimits;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
 use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
 
 /// Database key suffix for message seekers.
@@ -79,17 +94,38 @@ use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
 /// where hash_bytes is the massa_hash of the seeker's public key.
 const MESSAGE_SEEKER_DB_KEY: &[u8] = &[1u8];
 
+/// Version of the session protocol announced in [`SessionInitPayload`].
+///
+/// Both peers of a session must run the same version. Announcements without a version
+/// come from version 0, whose messages carry a single next seeker keypair and cannot be
+/// delivered out of order. Version 1 brought out-of-order delivery: messages carry the
+/// seeker keypairs of the sender's next messages and are encrypted with per-message chain keys.
+/// Version 2 re-seeds the chain keys from each peer message, and derives the seeker keypairs
+/// from a seeker chain instead of carrying them in messages.
+pub(crate) const SESSION_PROTOCOL_VERSION: u16 = 2;
+
 /// Session initialization payload embedded in announcements.
 ///
 /// This is serialized, encrypted in an auth blob, and included in the announcement.
 /// It contains the random seed used to derive the initial seeker keypair through KDF,
-/// and the message timestamp.
+/// the message timestamp and the session protocol version of the announcer.
 #[derive(Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
 pub(crate) struct SessionInitPayload {
     /// Random 32-byte seed used to derive the initial seeker keypair via KDF
     pub(crate) seeker_seed: [u8; 32],
     /// Unix timestamp in milliseconds when this payload was created
     pub(crate) unix_timestamp_millis: u128,
+    /// Session protocol version of the announcer (see [`SESSION_PROTOCOL_VERSION`])
+    pub(crate) protocol_version: u16,
+}
+
+/// Session initialization payload of protocol version 0, which had no version field.
+///
+/// Only decoded to tell announcements of outdated peers apart from malformed ones.
+#[derive(Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
+pub(crate) struct SessionInitPayloadV0 {
+    pub(crate) seeker_seed: [u8; 32],
+    pub(crate) unix_timestamp_millis: u128,
 }
 
 /// Auth payload embedded in announcements.
@@ -109,9 +145,6 @@ pub(crate) struct AuthPayload {
 pub(crate) struct Message {
     /// Timestamp when the message was created (milliseconds since Unix epoch)
     pub timestamp: u128,
-    /// Next Massa keypair for future seeker generation (part of the ratchet)
-    #[zeroize(skip)]
-    pub seeker_massa_keypair_next: massa_signature::KeyPair,
     /// Actual message contents provided by the user
     pub contents: Vec<u8>,
 }
@@ -147,6 +180,26 @@ pub struct FeedIncomingMessageOutput {
     pub user_id: Vec<u8>,
 }
 
+/// Incoming message read by [`Session::try_decrypt_incoming_message`].
+///
+/// The session state only changes once the message is accepted with
+/// [`Session::accept_incoming_message`], so that the caller can check its timestamp first.
+/// Its fields zeroize themselves on drop.
+pub struct DecryptedIncomingMessage {
+    /// Index of the message among the messages of the peer
+    message_index: u64,
+    message: Message,
+    agraphon_message: crypto_agraphon::DecryptedIncomingMessage,
+}
+
+impl DecryptedIncomingMessage {
+    /// Returns the timestamp of the message (milliseconds since Unix epoch).
+    #[must_use]
+    pub fn timestamp(&self) -> u128 {
+        self.message.timestamp
+    }
+}
+
 /// Incoming session initiation request from a peer.
 ///
 /// Created by parsing announcement bytes received from the peer.
@@ -175,12 +228,19 @@ impl IncomingInitiationRequest {
     /// # Returns
     ///
     /// A tuple containing the incoming initiation request and the user data from the announcement.
-    /// If the parsing fails, the function returns `None`.
+    ///
+    /// # Errors
+    ///
+    /// - [`AnnouncementError::Malformed`] if the announcement or its contents cannot be parsed
+    /// - [`AnnouncementError::Undecryptable`] if the announcement cannot be decrypted with our keys
+    /// - [`AnnouncementError::InvalidAuthentication`] if the announcer's signature is invalid
+    /// - [`AnnouncementError::UnsupportedProtocolVersion`] if the announcer runs another
+    ///   session protocol version
     pub fn try_from(
         bytes: &[u8],
         our_pk: &auth::UserPublicKeys,
         our_sk: &auth::UserSecretKeys,
-    ) -> Option<(Self, Vec<u8>)> {
+    ) -> Result<(Self, Vec<u8>), AnnouncementError> {
         // parse announcement precursor
         let incoming_announcement_precursor =
             crypto_agraphon::IncomingAnnouncementPrecursor::try_from_incoming_announcement_bytes(
@@ -196,27 +256,42 @@ impl IncomingInitiationRequest {
         // deserialize announcement contents
         let auth_payload: AuthPayload =
             bincode::serde::decode_from_slice(auth_payload, bincode::config::standard())
-                .ok()?
+                .map_err(|_| AnnouncementError::Malformed)?
                 .0;
 
         // verify auth blob
         if !auth_payload.auth_blob.verify(auth_key) {
-            return None;
+            return Err(AnnouncementError::InvalidAuthentication);
         }
 
         // deserialize inner data
-        let init_payload: SessionInitPayload = bincode::serde::decode_from_slice(
-            auth_payload.auth_blob.public_payload(),
-            bincode::config::standard(),
-        )
-        .ok()?
-        .0;
+        let public_payload = auth_payload.auth_blob.public_payload();
+        let init_payload: SessionInitPayload =
+            match bincode::serde::decode_from_slice(public_payload, bincode::config::standard()) {
+                Ok((init_payload, _)) => init_payload,
+                Err(_) => {
+                    // version 0 payloads have no version field
+                    return Err(
+                        match bincode::serde::decode_from_slice::<SessionInitPayloadV0, _>(
+                            public_payload,
+                            bincode::config::standard(),
+                        ) {
+                            Ok(_) => AnnouncementError::UnsupportedProtocolVersion,
+                            Err(_) => AnnouncementError::Malformed,
+                        },
+                    );
+                }
+            };
+        if init_payload.protocol_version != SESSION_PROTOCOL_VERSION {
+            return Err(AnnouncementError::UnsupportedProtocolVersion);
+        }
 
         // finalize agraphon announcement
         let agraphon_announcement = incoming_announcement_precursor
-            .finalize(auth_payload.auth_blob.public_keys().kem_public_key.clone())?;
+            .finalize(auth_payload.auth_blob.public_keys().kem_public_key.clone())
+            .ok_or(AnnouncementError::InvalidAuthentication)?;
 
-        Some((
+        Ok((
             Self {
                 agraphon_announcement: agraphon_announcement.clone(),
                 origin_public_keys: auth_payload.auth_blob.public_keys().clone(),
@@ -242,14 +317,18 @@ pub struct OutgoingInitiationRequest {
 }
 
 impl OutgoingInitiationRequest {
+    /// Creates an announcement for `peer_pk`, timestamped with the current time of `clock`.
+    ///
+    /// Returns the announcement bytes to publish and the request to keep until the peer answers.
     pub fn new(
         our_pk: &auth::UserPublicKeys,
         our_sk: &auth::UserSecretKeys,
         peer_pk: &auth::UserPublicKeys,
         user_data: Vec<u8>,
+        clock: &dyn Clock,
     ) -> (Vec<u8>, Self) {
         // get current timestamp
-        let timestamp_millis = crate::utils::timestamp_millis();
+        let timestamp_millis = clock.now_millis();
 
         // prepare agraphon outgoing announcement precursor
         let agraphon_announcement_precursor =
@@ -270,6 +349,7 @@ impl OutgoingInitiationRequest {
         let session_init_payload = SessionInitPayload {
             seeker_seed,
             unix_timestamp_millis: timestamp_millis,
+            protocol_version: SESSION_PROTOCOL_VERSION,
         };
         let session_init_payload_bytes =
             bincode::serde::encode_to_vec(&session_init_payload, bincode::config::standard())
@@ -311,23 +391,139 @@ impl OutgoingInitiationRequest {
 /// security through the Agraphon double-ratchet protocol. Messages are addressed using seekers
 /// derived from ephemeral Massa keypairs.
 ///
-/// The initial seeker keypairs are deterministically derived from the random seeds exchanged
-/// during session initialization using a KDF. Each subsequent message includes the next seeker
-/// keypair in the ratchet, ensuring forward secrecy.
+/// The seeker keypairs are deterministically derived from the random seeds exchanged
+/// during session initialization through a one-way seeker chain for each side, ensuring
+/// forward secrecy while tolerating messages that are missing or delivered out of order.
 #[derive(Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
 pub struct Session {
     /// Agraphon protocol instance handling encryption and ratcheting
     agraphon_instance: crypto_agraphon::Agraphon,
     /// Peer's long-term public keys
     peer_public_keys: auth::UserPublicKeys,
-    /// Current Massa keypair for the next message we expect to receive from the peer
+    /// Massa keypairs of the peer messages we can receive and have not received yet,
+    /// indexed by the position of the message in the peer's sequence
+    #[zeroize(skip)] // TODO: add zeroization to massa keypair
+    peer_seeker_massa_keypairs: BTreeMap<u64, massa_signature::KeyPair>,
+    /// Seeker chain of the peer messages following the ones in `peer_seeker_massa_keypairs`
+    peer_seeker_chain: SeekerChain,
+    /// Index of the peer message following the newest one received
+    peer_next_index: u64,
+    /// Seeker chain of our next messages
+    self_seeker_chain: SeekerChain,
+    /// Limits on the state kept for peer messages delivered out of order
     #[zeroize(skip)]
+    skipped_message_limits: SkippedMessageLimits,
+}
+
+/// One-way chain deriving the seeker keypairs of the successive messages of a party.
+///
+/// The chain starts from the seeds of both peers, and each step derives the seeker keypair
+/// of a message along with the next chain key. The seeker keypairs of the messages before
+/// the current one cannot be recovered from the chain.
+///
+/// The first chain key is `KDF(sender_seed || receiver_seed, "session.seeker.chain")`, and each
+/// step derives `KDF(k_seeker, "session.seeker.key")` as the 32-byte secret of a Massa Ed25519
+/// keypair (33 bytes: version byte + secret) and `KDF(k_seeker, "session.seeker.chain")` as the
+/// next chain key.
+#[derive(Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
+struct SeekerChain {
+    /// Index of the message whose seeker keypair is derived next
+    index: u64,
+    /// Chain key of the message at `index`
+    k_seeker: [u8; 32],
+}
+
+impl SeekerChain {
+    /// Starts the seeker chain of the messages of a party from its seed and its peer's seed.
+    fn new(sender_seed: &[u8; 32], receiver_seed: &[u8; 32]) -> Self {
+        let mut kdf = crypto_kdf::Extract::new(b"session.seeker.kdf.salt---------");
+        kdf.input_item(sender_seed.as_slice());
+        kdf.input_item(receiver_seed.as_slice());
+        let mut k_seeker = [0u8; 32];
+        kdf.finalize()
+            .expand(b"session.seeker.chain", &mut k_seeker);
+        Self { index: 0, k_seeker }
+    }
+
+    /// Derives the seeker keypair of the next message and steps the chain forward.
+    ///
+    /// Returns the index of the message in the sequence of its sender along with its keypair.
+    fn next_keypair(&mut self) -> (u64, massa_signature::KeyPair) {
+        let mut kdf = crypto_kdf::Extract::new(b"session.seeker.chain.salt-------");
+        kdf.input_item(self.k_seeker.as_slice());
+        let expander = kdf.finalize();
+
+        // Massa keypair format: [version_byte, 32_secret_key_bytes]
+        let mut seeker_key = Zeroizing::new([0u8; 33]);
+        expander.expand(b"session.seeker.key", &mut seeker_key[1..]);
+        expander.expand(b"session.seeker.chain", &mut self.k_seeker);
+        let keypair = massa_signature::KeyPair::from_bytes(seeker_key.as_slice())
+            .expect("Failed to generate seeker keypair");
+
+        let index = self.index;
+        self.index += 1;
+        (index, keypair)
+    }
+}
+
+/// `Session` of schema version 0, before out-of-order delivery, which only knew
+/// the seeker keypair of the next message of each side
+#[derive(Deserialize)]
+pub(crate) struct SessionV0 {
+    agraphon_instance: crypto_agraphon::AgraphonV0,
+    peer_public_keys: auth::UserPublicKeys,
     peer_seeker_massa_keypair: massa_signature::KeyPair,
-    /// Current Massa keypair for the next message we will send to the peer
-    #[zeroize(skip)]
     self_seeker_massa_keypair: massa_signature::KeyPair,
 }
 
+/// `Session` of schema versions 1 to 4, whose messages carried the seeker keypairs of the
+/// following messages of their sender.
+///
+/// Its sessions cannot be carried over to the current protocol: it is only kept so that older
+/// states can be upgraded through it.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct SessionV4 {
+    agraphon_instance: crypto_agraphon::AgraphonV1,
+    peer_public_keys: auth::UserPublicKeys,
+    peer_seeker_massa_keypairs: BTreeMap<u64, massa_signature::KeyPair>,
+    peer_next_index: u64,
+    self_seeker_massa_keypairs: VecDeque<massa_signature::KeyPair>,
+    skipped_message_limits: SkippedMessageLimits,
+}
+
+impl SessionV4 {
+    /// Upgrades a session saved before out-of-order delivery was introduced.
+    ///
+    /// The next message of each side keeps its seeker keypair. Our following messages get
+    /// random seeker keypairs, which were announced to the peer with our next message.
+    pub(crate) fn from_v0(legacy: SessionV0, skipped_message_limits: SkippedMessageLimits) -> Self {
+        let seeker_window = skipped_message_limits.max_skip.saturating_add(1);
+        let self_seeker_massa_keypairs = std::iter::once(legacy.self_seeker_massa_keypair)
+            .chain(std::iter::repeat_with(|| {
+                massa_signature::KeyPair::generate(0).expect("Failed to generate seeker keypair")
+            }))
+            .take(seeker_window as usize)
+            .collect();
+
+        Self {
+            agraphon_instance: crypto_agraphon::AgraphonV1::from_v0(
+                &legacy.agraphon_instance,
+                skipped_message_limits,
+            ),
+            peer_public_keys: legacy.peer_public_keys,
+            peer_seeker_massa_keypairs: BTreeMap::from([(0, legacy.peer_seeker_massa_keypair)]),
+            peer_next_index: 0,
+            self_seeker_massa_keypairs,
+            skipped_message_limits,
+        }
+    }
+
+    /// Returns the long-term public keys of the peer.
+    pub(crate) fn peer_public_keys(&self) -> &auth::UserPublicKeys {
+        &self.peer_public_keys
+    }
+}
+
 impl Session {
     /// Creates a new session from a pair of initiation requests.
     ///
@@ -337,18 +533,19 @@ impl Session {
     ///
     /// # Seeker Key Derivation
     ///
-    /// The initial seeker keypairs are derived using a key derivation function (KDF) that
-    /// combines both parties' random seeds:
-    /// - Peer's seeker key: `KDF(peer_seed || our_seed, "session.seeker.key")`
-    /// - Our seeker key: `KDF(our_seed || peer_seed, "session.seeker.key")`
+    /// The seeker keypairs are derived from a seeker chain for each party, which starts from
+    /// a key derivation function (KDF) combining both parties' random seeds:
+    /// - Peer's seeker chain: `KDF(peer_seed || our_seed, "session.seeker.chain")`
+    /// - Our seeker chain: `KDF(our_seed || peer_seed, "session.seeker.chain")`
     ///
     /// The order of inputs ensures each party derives different keys for sending vs receiving.
-    /// The keys are formatted as Massa Ed25519 keypairs (33 bytes: version byte + 32-byte secret).
+    /// The keypairs of the first `max_skip + 1` peer messages are derived upfront.
     ///
     /// # Arguments
     ///
     /// * `outgoing_initiation_request` - Our announcement that was sent to the peer
     /// * `incoming_initiation_request` - The peer's announcement that we received
+    /// * `skipped_message_limits` - Limits on the state kept for peer messages delivered out of order
     ///
     /// # Example
     ///
@@ -357,59 +554,65 @@ impl Session {
     /// # use sessions::*;
     /// # let (alice_pk, alice_sk) = derive_keys_from_static_root_secret(&StaticRootSecret::from_passphrase(b"alice"));
     /// # let (bob_pk, bob_sk) = derive_keys_from_static_root_secret(&StaticRootSecret::from_passphrase(b"bob"));
-    /// let (announcement_bytes, outgoing) = OutgoingInitiationRequest::new(&alice_pk, &alice_sk, &bob_pk, vec![]);
-    /// # let (bob_announcement, _) = OutgoingInitiationRequest::new(&bob_pk, &bob_sk, &alice_pk, vec![]);
+    /// let (announcement_bytes, outgoing) = OutgoingInitiationRequest::new(&alice_pk, &alice_sk, &bob_pk, vec![], &SystemClock);
+    /// # let (bob_announcement, _) = OutgoingInitiationRequest::new(&bob_pk, &bob_sk, &alice_pk, vec![], &SystemClock);
     /// # let (incoming, _user_data) = IncomingInitiationRequest::try_from(&bob_announcement, &alice_pk, &alice_sk).unwrap();
-    /// let session = Session::from_initiation_request_pair(&outgoing, &incoming);
+    /// let session =
+    ///     Session::from_initiation_request_pair(&outgoing, &incoming, SkippedMessageLimits::default());
     /// ```
     pub fn from_initiation_request_pair(
         outgoing_initiation_request: &OutgoingInitiationRequest,
         incoming_initiation_request: &IncomingInitiationRequest,
+        skipped_message_limits: SkippedMessageLimits,
     ) -> Self {
         // create agraphon instance
         let agraphon_instance = crypto_agraphon::Agraphon::from_announcement_pair(
             &outgoing_initiation_request.agraphon_announcement,
             &incoming_initiation_request.agraphon_announcement,
+            skipped_message_limits,
         );
 
-        // Derive peer's initial seeker keypair from [peer_seed, our_seed]
-        // This produces the key the peer will use to send their first message to us
-        let peer_seeker_massa_keypair = {
-            let mut kdf = crypto_kdf::Extract::new(b"session.seeker.kdf.salt---------");
-            kdf.input_item(incoming_initiation_request.seeker_seed.as_slice());
-            kdf.input_item(outgoing_initiation_request.seeker_seed.as_slice());
-            let expander = kdf.finalize();
-            // Massa keypair format: [version_byte, 32_secret_key_bytes]
-            let mut seeker_key = [0u8; 33];
-            expander.expand(b"session.seeker.key", &mut seeker_key[1..]);
-            massa_signature::KeyPair::from_bytes(seeker_key.as_slice())
-                .expect("Failed to generate peer seeker keypair")
-        };
+        // Start the peer's seeker chain from [peer_seed, our_seed]
+        // It derives the keys the peer will use to send their messages to us
+        let peer_seeker_chain = SeekerChain::new(
+            &incoming_initiation_request.seeker_seed,
+            &outgoing_initiation_request.seeker_seed,
+        );
 
-        // Derive our initial seeker keypair from [our_seed, peer_seed]
-        // This produces the key we will use to send our first message to the peer
-        let self_seeker_massa_keypair = {
-            let mut kdf = crypto_kdf::Extract::new(b"session.seeker.kdf.salt---------");
-            kdf.input_item(outgoing_initiation_request.seeker_seed.as_slice());
-            kdf.input_item(incoming_initiation_request.seeker_seed.as_slice());
-            let expander = kdf.finalize();
-            // Massa keypair format: [version_byte, 32_secret_key_bytes]
-            let mut seeker_key = [0u8; 33];
-            expander.expand(b"session.seeker.key", &mut seeker_key[1..]);
-            massa_signature::KeyPair::from_bytes(seeker_key.as_slice())
-                .expect("Failed to generate self seeker keypair")
-        };
+        // Start our seeker chain from [our_seed, peer_seed]
+        // It derives the keys we will use to send our messages to the peer
+        let self_seeker_chain = SeekerChain::new(
+            &outgoing_initiation_request.seeker_seed,
+            &incoming_initiation_request.seeker_seed,
+        );
 
         // create session
-        Self {
+        let mut session = Self {
             agraphon_instance,
             peer_public_keys: incoming_initiation_request.origin_public_keys.clone(),
-            peer_seeker_massa_keypair,
-            self_seeker_massa_keypair,
+            peer_seeker_massa_keypairs: BTreeMap::new(),
+            peer_seeker_chain,
+            peer_next_index: 0,
+            self_seeker_chain,
+            skipped_message_limits,
+        };
+        session.derive_peer_seeker_keypairs();
+        session
+    }
+
+    /// Derives the seeker keypairs of the peer messages that can be received,
+    /// up to `max_skip` messages past the next one.
+    fn derive_peer_seeker_keypairs(&mut self) {
+        let max_index = self
+            .peer_next_index
+            .saturating_add(self.skipped_message_limits.max_skip);
+        while self.peer_seeker_chain.index <= max_index {
+            let (index, keypair) = self.peer_seeker_chain.next_keypair();
+            self.peer_seeker_massa_keypairs.insert(index, keypair);
         }
     }
 
-    fn compute_seeker(seeker_public_key: &massa_signature::PublicKey) -> Vec<u8> {
+    pub(crate) fn compute_seeker(seeker_public_key: &massa_signature::PublicKey) -> Vec<u8> {
         // Hash the public key bytes to get a fixed-size identifier
         let public_key_bytes = seeker_public_key.to_bytes();
         let hash = massa_hash::Hash::compute_from(&public_key_bytes);
@@ -430,45 +633,39 @@ impl Session {
     /// Sends an outgoing message on the session and returns the seeker and encrypted message data.
     ///
     /// This method:
-    /// 1. Generates a new random seeker keypair for the next message
+    /// 1. Derives the seeker keypair of this message from our seeker chain,
+    ///    stepping the chain forward
     /// 2. Uses the current seeker keypair to create the message seeker (address)
     /// 3. Encrypts the message using the Agraphon protocol
     /// 4. Signs the encrypted message with the current seeker keypair
-    /// 5. Updates the session's seeker keypair for the next message
     ///
-    /// The peer must have the corresponding seeker to decrypt the message. The message
-    /// includes the next seeker keypair, maintaining the forward-ratcheting property.
+    /// The peer must have the corresponding seeker to decrypt the message. The peer derives
+    /// the seekers of our next messages from the same chain, which lets it read them even if
+    /// some messages go missing.
     ///
     /// # Arguments
     ///
     /// * `message` - The plaintext message bytes to send
+    /// * `clock` - The clock used to timestamp the message
     ///
     /// # Returns
     ///
     /// A [`SendOutgoingMessageOutput`] containing the seeker (database key) and encrypted data
     /// that should be posted to the message board.
-    pub fn send_outgoing_message(&mut self, message: &[u8]) -> SendOutgoingMessageOutput {
+    pub fn send_outgoing_message(
+        &mut self,
+        message: &[u8],
+        clock: &dyn Clock,
+    ) -> SendOutgoingMessageOutput {
         // get timestamp
-        let timestamp = crate::utils::timestamp_millis();
-
-        // generate seeker for next message on our side
-        let mut seeker_keypair =
-            massa_signature::KeyPair::generate(0).expect("Failed to generate seeker keypair");
+        let timestamp = clock.now_millis();
 
-        // flip with the current seeker
-        std::mem::swap(&mut seeker_keypair, &mut self.self_seeker_massa_keypair);
-        // seeker_keypair is now the "current" seeker
-
-        // compute ephemeral seeker public key
-        let seeker_public_key = seeker_keypair.get_public_key();
-
-        // assemble seeker datastore key
-        let seeker = Self::compute_seeker(&seeker_public_key);
+        // derive the seeker of this message
+        let (_, seeker_keypair) = self.self_seeker_chain.next_keypair();
 
         // create message
         let msg = Message {
             timestamp,
-            seeker_massa_keypair_next: self.self_seeker_massa_keypair.clone(),
             contents: message.to_vec(),
         };
 
@@ -478,10 +675,36 @@ impl Session {
                 .expect("Failed to serialize message"),
         );
 
+        self.seal_message_bytes(timestamp, &seeker_keypair, &msg_bytes)
+    }
+
+    /// Sends arbitrary bytes in place of a serialized message, as a malicious peer could.
+    ///
+    /// Only built for tests and fuzzing, to exercise the parsing of authenticated messages.
+    #[cfg(any(test, fuzzing))]
+    #[doc(hidden)]
+    pub fn send_raw_message_bytes(&mut self, msg_bytes: &[u8]) -> SendOutgoingMessageOutput {
+        let (_, seeker_keypair) = self.self_seeker_chain.next_keypair();
+        self.seal_message_bytes(0, &seeker_keypair, msg_bytes)
+    }
+
+    /// Encrypts serialized message bytes and signs them with the seeker keypair of the message.
+    fn seal_message_bytes(
+        &mut self,
+        timestamp: u128,
+        seeker_keypair: &massa_signature::KeyPair,
+        msg_bytes: &[u8],
+    ) -> SendOutgoingMessageOutput {
+        // compute ephemeral seeker public key
+        let seeker_public_key = seeker_keypair.get_public_key();
+
+        // assemble seeker datastore key
+        let seeker = Self::compute_seeker(&seeker_public_key);
+
         // feed agraphon
         let agraphon_message_bytes = self.agraphon_instance.send_outgoing_message(
             &seeker,
-            &msg_bytes,
+            msg_bytes,
             &self.peer_public_keys.kem_public_key,
         );
 
@@ -524,15 +747,52 @@ impl Session {
     /// is derived from the peer's current seeker keypair by hashing its public key.
     ///
     /// After successfully receiving a message via [`try_feed_incoming_message`](Self::try_feed_incoming_message),
-    /// this seeker will be updated to point to the subsequent message.
+    /// this seeker will be updated to point to the message following the newest one received.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the seeker keypair of the next peer message is unknown. This should never happen
+    /// as the seeker keypairs of the following peer messages are derived as messages are accepted.
     pub fn next_peer_message_seeker(&self) -> Vec<u8> {
-        Self::compute_seeker(&self.peer_seeker_massa_keypair.get_public_key())
+        let keypair = self
+            .peer_seeker_massa_keypairs
+            .get(&self.peer_next_index)
+            .expect("Next peer seeker keypair unexpectedly missing");
+        Self::compute_seeker(&keypair.get_public_key())
+    }
+
+    /// Returns the seekers (database keys) of all the peer messages that can currently be received.
+    ///
+    /// This includes the next message from the peer, the messages following it within the
+    /// `max_skip` limit, and the earlier messages that were skipped over and are still expected.
+    /// Any of them can be fed to [`try_feed_incoming_message`](Self::try_feed_incoming_message)
+    /// in any order.
+    pub fn peer_message_seekers(&self) -> Vec<Vec<u8>> {
+        self.readable_peer_seeker_keypairs()
+            .map(|(_, keypair)| Self::compute_seeker(&keypair.get_public_key()))
+            .collect()
+    }
+
+    /// Returns the known seeker keypairs of the peer messages that can currently be received,
+    /// along with the index of each message in the peer's sequence.
+    fn readable_peer_seeker_keypairs(
+        &self,
+    ) -> impl Iterator<Item = (u64, &massa_signature::KeyPair)> {
+        let max_index = self
+            .peer_next_index
+            .saturating_add(self.skipped_message_limits.max_skip);
+        self.peer_seeker_massa_keypairs
+            .range(..=max_index)
+            .map(|(index, keypair)| (*index, keypair))
     }
 
     /// Attempts to decrypt and process an incoming message from the peer.
     ///
     /// This method verifies the message signature, decrypts the content, and updates
-    /// the session state including the peer's seeker keypair for the next message.
+    /// the session state including the peer's seeker keypairs for the next messages.
+    /// A message that cannot be read leaves the session state untouched.
+    /// Messages can be fed in any order as long as their seeker is one of
+    /// [`peer_message_seekers`](Self::peer_message_seekers). Each message is only accepted once.
     ///
     /// # Arguments
     ///
@@ -542,36 +802,81 @@ impl Session {
     ///
     /// # Returns
     ///
-    /// - `Some(FeedIncomingMessageOutput)` if decryption succeeds, containing the plaintext
-    ///   message and list of acknowledged seekers and the user id of the peer that sent the message
-    /// - `None` if the message is invalid, cannot be decrypted, or has an invalid signature
+    /// `Ok(FeedIncomingMessageOutput)` if decryption succeeds, containing the plaintext
+    /// message and list of acknowledged seekers and the user id of the peer that sent the message.
+    ///
+    /// # Errors
+    ///
+    /// - [`SessionError::UnknownSeeker`] if the session does not expect a message under this seeker
+    /// - [`SessionError::MalformedMessage`] if the message or its contents cannot be parsed
+    /// - [`SessionError::InvalidSignature`] if the message is not signed by its seeker keypair
+    /// - [`SessionError::DecryptionFailed`] if the message cannot be decrypted
     pub fn try_feed_incoming_message(
         &mut self,
         self_static_sk: &auth::UserSecretKeys,
         seeker: &[u8],
         message: &[u8],
-    ) -> Option<FeedIncomingMessageOutput> {
+    ) -> Result<FeedIncomingMessageOutput, SessionError> {
+        let decrypted = self.try_decrypt_incoming_message(self_static_sk, seeker, message)?;
+        self.accept_incoming_message(decrypted)
+    }
+
+    /// Reads an incoming message from the peer without changing the session state.
+    ///
+    /// This is the first half of [`try_feed_incoming_message`](Self::try_feed_incoming_message):
+    /// the message signature is verified and its content decrypted. The message is only
+    /// received once passed to [`accept_incoming_message`](Self::accept_incoming_message).
+    ///
+    /// # Errors
+    ///
+    /// - [`SessionError::UnknownSeeker`] if the session does not expect a message under this seeker
+    /// - [`SessionError::MalformedMessage`] if the message or its contents cannot be parsed
+    /// - [`SessionError::InvalidSignature`] if the message is not signed by its seeker keypair
+    /// - [`SessionError::DecryptionFailed`] if the message cannot be decrypted
+    pub fn try_decrypt_incoming_message(
+        &self,
+        self_static_sk: &auth::UserSecretKeys,
+        seeker: &[u8],
+        message: &[u8],
+    ) -> Result<DecryptedIncomingMessage, SessionError> {
+        // find which of the expected peer messages the seeker designates
+        let message_index = self
+            .readable_peer_seeker_keypairs()
+            .find(|(_, keypair)| Self::compute_seeker(&keypair.get_public_key()) == seeker)
+            .map(|(index, _)| index)
+            .ok_or(SessionError::UnknownSeeker)?;
+
         // decompose seeker
-        let hash_len = *seeker.first()? as usize;
-        let hash_bytes = seeker.get(1..1 + hash_len)?;
+        let hash_len = *seeker.first().ok_or(SessionError::UnknownSeeker)? as usize;
+        let hash_bytes = seeker
+            .get(1..1 + hash_len)
+            .ok_or(SessionError::UnknownSeeker)?;
         if seeker.get(1 + hash_len..) != Some(MESSAGE_SEEKER_DB_KEY) {
-            return None;
+            return Err(SessionError::UnknownSeeker);
         }
 
         // decompose the data
-        let seeker_public_key_len = *message.first()? as usize;
-        let seeker_public_key_bytes = message.get(1..1 + seeker_public_key_len)?;
-        let seeker_public_key =
-            massa_signature::PublicKey::from_bytes(seeker_public_key_bytes).ok()?;
+        let seeker_public_key_len =
+            *message.first().ok_or(SessionError::MalformedMessage)? as usize;
+        let seeker_public_key_bytes = message
+            .get(1..1 + seeker_public_key_len)
+            .ok_or(SessionError::MalformedMessage)?;
+        let seeker_public_key = massa_signature::PublicKey::from_bytes(seeker_public_key_bytes)
+            .map_err(|_| SessionError::MalformedMessage)?;
 
         let signature_offset = 1 + seeker_public_key_len;
-        let signature_len = *message.get(signature_offset)? as usize;
-        let signature_bytes =
-            message.get(signature_offset + 1..signature_offset + 1 + signature_len)?;
-        let signature = massa_signature::Signature::from_bytes(signature_bytes).ok()?;
+        let signature_len = *message
+            .get(signature_offset)
+            .ok_or(SessionError::MalformedMessage)? as usize;
+        let signature_bytes = message
+            .get(signature_offset + 1..signature_offset + 1 + signature_len)
+            .ok_or(SessionError::MalformedMessage)?;
+        let signature = massa_signature::Signature::from_bytes(signature_bytes)
+            .map_err(|_| SessionError::MalformedMessage)?;
 
         let message_bytes = message
-            .get(signature_offset + 1 + signature_len..)?
+            .get(signature_offset + 1 + signature_len..)
+            .ok_or(SessionError::MalformedMessage)?
             .to_vec();
 
         // check that the hash derives from the seeker public key by recomputing it
@@ -580,7 +885,7 @@ impl Session {
         let expected_hash_bytes = expected_hash.to_bytes();
 
         if hash_bytes != expected_hash_bytes.as_slice() {
-            return None;
+            return Err(SessionError::InvalidSignature);
         }
 
         // check that the signature is valid
@@ -590,29 +895,77 @@ impl Session {
             .verify_signature(&hash_to_verify, &signature)
             .is_err()
         {
-            return None;
+            return Err(SessionError::InvalidSignature);
         }
 
-        // try to read message from agraphon
-        let agraphon_result = self
+        // try to read message from agraphon, without changing its state
+        let decrypted = self
             .agraphon_instance
-            .try_feed_incoming_message(&self_static_sk.kem_secret_key, &message_bytes)?;
+            .try_decrypt_incoming_message(&self_static_sk.kem_secret_key, &message_bytes)?;
 
         // deserialize the message
         let message: Message = bincode::serde::decode_from_slice(
-            &agraphon_result.message_bytes,
+            decrypted.message_bytes(),
             bincode::config::standard(),
         )
-        .ok()?
+        .map_err(|_| SessionError::MalformedMessage)?
         .0;
 
-        // update peer seeker keypair for next message
-        self.peer_seeker_massa_keypair = message.seeker_massa_keypair_next.clone();
+        Ok(DecryptedIncomingMessage {
+            message_index,
+            message,
+            agraphon_message: decrypted,
+        })
+    }
+
+    /// Receives a message read by [`try_decrypt_incoming_message`](Self::try_decrypt_incoming_message),
+    /// updating the session state including the peer's seeker keypairs for the next messages.
+    ///
+    /// # Errors
+    ///
+    /// - [`SessionError::UnknownSeeker`] if the message was already received in the meantime
+    /// - [`SessionError::DecryptionFailed`] if the message no longer fits the session state
+    pub fn accept_incoming_message(
+        &mut self,
+        decrypted: DecryptedIncomingMessage,
+    ) -> Result<FeedIncomingMessageOutput, SessionError> {
+        let DecryptedIncomingMessage {
+            message_index,
+            message,
+            agraphon_message,
+        } = decrypted;
+        if !self.peer_seeker_massa_keypairs.contains_key(&message_index) {
+            return Err(SessionError::UnknownSeeker);
+        }
+
+        // the message is accepted: apply the ratchet step
+        let agraphon_result = self
+            .agraphon_instance
+            .accept_incoming_message(agraphon_message)?;
+
+        // this message was received: forget its seeker
+        self.peer_seeker_massa_keypairs.remove(&message_index);
+        if message_index >= self.peer_next_index {
+            self.peer_next_index = message_index.saturating_add(1);
+        }
+
+        // derive the seeker keypairs of the following peer messages that can now be received
+        self.derive_peer_seeker_keypairs();
+
+        // forget the oldest skipped peer messages beyond the limit, as the agraphon instance does
+        while self
+            .peer_seeker_massa_keypairs
+            .range(..self.peer_next_index)
+            .count() as u64
+            > self.skipped_message_limits.max_stored
+        {
+            self.peer_seeker_massa_keypairs.pop_first();
+        }
 
         // get user id of the peer that sent the message
         let user_id = self.peer_public_keys.derive_id();
 
-        Some(FeedIncomingMessageOutput {
+        Ok(FeedIncomingMessageOutput {
             timestamp: message.timestamp,
             message: message.contents.clone(),
             newly_acknowledged_self_seekers: agraphon_result
@@ -622,6 +975,18 @@ impl Session {
         })
     }
 
+    /// Returns the long-term public keys of the peer.
+    pub fn peer_public_keys(&self) -> &auth::UserPublicKeys {
+        &self.peer_public_keys
+    }
+
+    /// Replaces the long-term public keys of the peer after a verified key rotation.
+    ///
+    /// The ratchet state is kept: the following messages are encapsulated to the new KEM key.
+    pub(crate) fn set_peer_public_keys(&mut self, peer_public_keys: auth::UserPublicKeys) {
+        self.peer_public_keys = peer_public_keys;
+    }
+
     /// Returns the number of unacknowledged messages sent by this session.
     ///
     /// The lag length increases when you send messages and decreases when the peer
@@ -635,6 +1000,7 @@ impl Session {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::clock::{ManualClock, SystemClock};
 
     /// Helper function to generate a random keypair for testing
     fn generate_test_keypair() -> (auth::UserPublicKeys, auth::UserSecretKeys) {
@@ -649,8 +1015,6 @@ mod tests {
     fn create_test_message(contents: &[u8]) -> Message {
         Message {
             timestamp: crate::utils::timestamp_millis(),
-            seeker_massa_keypair_next: massa_signature::KeyPair::generate(0)
-                .expect("Failed to generate placeholder keypair"),
             contents: contents.to_vec(),
         }
     }
@@ -665,7 +1029,7 @@ mod tests {
         let (peer_pk, _peer_sk) = generate_test_keypair();
 
         let (announcement_bytes, outgoing_req) =
-            OutgoingInitiationRequest::new(&our_pk, &our_sk, &peer_pk, vec![]);
+            OutgoingInitiationRequest::new(&our_pk, &our_sk, &peer_pk, vec![], &SystemClock);
 
         assert!(!announcement_bytes.is_empty());
         assert!(outgoing_req.timestamp_millis > 0);
@@ -680,13 +1044,13 @@ mod tests {
 
         // Create an outgoing request from peer's perspective
         let (announcement_bytes, _) =
-            OutgoingInitiationRequest::new(&peer_pk, &peer_sk, &our_pk, vec![]);
+            OutgoingInitiationRequest::new(&peer_pk, &peer_sk, &our_pk, vec![], &SystemClock);
 
         // Parse it as incoming from our perspective
         let incoming_req =
             IncomingInitiationRequest::try_from(&announcement_bytes, &our_pk, &our_sk);
 
-        assert!(incoming_req.is_some());
+        assert!(incoming_req.is_ok());
         let (incoming_req, user_data) = incoming_req.unwrap();
         assert!(user_data.is_empty());
         assert!(incoming_req.timestamp_millis > 0);
@@ -696,6 +1060,45 @@ mod tests {
         );
     }
 
+    /// Tests that announcements and messages are timestamped with the injected clock,
+    /// and that the peer sees the sender's timestamps
+    #[test]
+    fn test_timestamps_come_from_clock() {
+        let (alice_pk, alice_sk) = generate_test_keypair();
+        let (bob_pk, bob_sk) = generate_test_keypair();
+        let alice_clock = ManualClock::new(1_000_000);
+        let bob_clock = ManualClock::new(5_000_000);
+
+        let (alice_announcement, alice_outgoing) =
+            OutgoingInitiationRequest::new(&alice_pk, &alice_sk, &bob_pk, vec![], &alice_clock);
+        let (bob_announcement, bob_outgoing) =
+            OutgoingInitiationRequest::new(&bob_pk, &bob_sk, &alice_pk, vec![], &bob_clock);
+        assert_eq!(alice_outgoing.timestamp_millis, 1_000_000);
+        assert_eq!(bob_outgoing.timestamp_millis, 5_000_000);
+
+        let (bob_incoming_at_alice, _) =
+            IncomingInitiationRequest::try_from(&bob_announcement, &alice_pk, &alice_sk).unwrap();
+        let (alice_incoming_at_bob, _) =
+            IncomingInitiationRequest::try_from(&alice_announcement, &bob_pk, &bob_sk).unwrap();
+        assert_eq!(bob_incoming_at_alice.timestamp_millis, 5_000_000);
+        assert_eq!(alice_incoming_at_bob.timestamp_millis, 1_000_000);
+
+        let limits = SkippedMessageLimits::default();
+        let mut alice_session =
+            Session::from_initiation_request_pair(&alice_outgoing, &bob_incoming_at_alice, limits);
+        let mut bob_session =
+            Session::from_initiation_request_pair(&bob_outgoing, &alice_incoming_at_bob, limits);
+
+        alice_clock.advance(250);
+        let output = alice_session.send_outgoing_message(b"hello", &alice_clock);
+        assert_eq!(output.timestamp, 1_000_250);
+
+        let received = bob_session
+            .try_feed_incoming_message(&bob_sk, &output.seeker, &output.data)
+            .expect("Failed to decrypt");
+        assert_eq!(received.timestamp, 1_000_250);
+    }
+
     /// Tests that parsing an announcement intended for a different recipient fails
     #[test]
     fn test_incoming_initiation_request_wrong_recipient() {
@@ -705,13 +1108,13 @@ mod tests {
 
         // Create an announcement for wrong_pk
         let (announcement_bytes, _) =
-            OutgoingInitiationRequest::new(&peer_pk, &peer_sk, &wrong_pk, vec![]);
+            OutgoingInitiationRequest::new(&peer_pk, &peer_sk, &wrong_pk, vec![], &SystemClock);
 
         // Try to parse with our keys - should fail
         let incoming_req =
             IncomingInitiationRequest::try_from(&announcement_bytes, &our_pk, &our_sk);
 
-        assert!(incoming_req.is_none());
+        assert_eq!(incoming_req.err(), Some(AnnouncementError::Undecryptable));
     }
 
     /// Tests that parsing invalid announcement data fails gracefully
@@ -721,7 +1124,89 @@ mod tests {
         let invalid_bytes = b"not a valid announcement";
 
         let incoming_req = IncomingInitiationRequest::try_from(invalid_bytes, &our_pk, &our_sk);
-        assert!(incoming_req.is_none());
+        assert_eq!(incoming_req.err(), Some(AnnouncementError::Malformed));
+    }
+
+    /// Builds a signed announcement for `peer_pk` carrying an arbitrary session init payload
+    fn announcement_with_init_payload(
+        our_pk: &auth::UserPublicKeys,
+        our_sk: &auth::UserSecretKeys,
+        peer_pk: &auth::UserPublicKeys,
+        init_payload_bytes: Vec<u8>,
+    ) -> Vec<u8> {
+        let precursor =
+            crypto_agraphon::OutgoingAnnouncementPrecursor::new(&peer_pk.kem_public_key);
+        let auth_payload = AuthPayload {
+            auth_blob: auth::AuthBlob::new(
+                our_pk.clone(),
+                our_sk,
+                init_payload_bytes,
+                precursor.auth_key(),
+            ),
+            user_data: vec![],
+        };
+        let auth_payload_bytes =
+            bincode::serde::encode_to_vec(&auth_payload, bincode::config::standard()).unwrap();
+        precursor.finalize(&auth_payload_bytes).0
+    }
+
+    /// Tests that announcements of another session protocol version are rejected as such,
+    /// and that unparsable init payloads are still reported as malformed
+    #[test]
+    fn test_incoming_initiation_request_protocol_version() {
+        let (our_pk, our_sk) = generate_test_keypair();
+        let (peer_pk, peer_sk) = generate_test_keypair();
+
+        // version 0 payloads have no version field
+        let v0_payload = bincode::serde::encode_to_vec(
+            SessionInitPayloadV0 {
+                seeker_seed: [7u8; 32],
+                unix_timestamp_millis: crate::utils::timestamp_millis(),
+            },
+            bincode::config::standard(),
+        )
+        .unwrap();
+        let announcement = announcement_with_init_payload(&peer_pk, &peer_sk, &our_pk, v0_payload);
+        assert_eq!(
+            IncomingInitiationRequest::try_from(&announcement, &our_pk, &our_sk).err(),
+            Some(AnnouncementError::UnsupportedProtocolVersion)
+        );
+
+        let future_payload = bincode::serde::encode_to_vec(
+            SessionInitPayload {
+                seeker_seed: [7u8; 32],
+                unix_timestamp_millis: crate::utils::timestamp_millis(),
+                protocol_version: SESSION_PROTOCOL_VERSION + 1,
+            },
+            bincode::config::standard(),
+        )
+        .unwrap();
+        let announcement =
+            announcement_with_init_payload(&peer_pk, &peer_sk, &our_pk, future_payload);
+        assert_eq!(
+            IncomingInitiationRequest::try_from(&announcement, &our_pk, &our_sk).err(),
+            Some(AnnouncementError::UnsupportedProtocolVersion)
+        );
+
+        let announcement =
+            announcement_with_init_payload(&peer_pk, &peer_sk, &our_pk, b"garbage".to_vec());
+        assert_eq!(
+            IncomingInitiationRequest::try_from(&announcement, &our_pk, &our_sk).err(),
+            Some(AnnouncementError::Malformed)
+        );
+
+        // while announcements of our version are accepted
+        let payload = bincode::serde::encode_to_vec(
+            SessionInitPayload {
+                seeker_seed: [7u8; 32],
+                unix_timestamp_millis: crate::utils::timestamp_millis(),
+                protocol_version: SESSION_PROTOCOL_VERSION,
+            },
+            bincode::config::standard(),
+        )
+        .unwrap();
+        let announcement = announcement_with_init_payload(&peer_pk, &peer_sk, &our_pk, payload);
+        assert!(IncomingInitiationRequest::try_from(&announcement, &our_pk, &our_sk).is_ok());
     }
 
     /// Tests that sessions can be created from a pair of initiation requests.
@@ -733,11 +1218,11 @@ mod tests {
 
         // Alice creates outgoing request to Bob
         let (alice_announcement, alice_outgoing) =
-            OutgoingInitiationRequest::new(&alice_pk, &alice_sk, &bob_pk, vec![]);
+            OutgoingInitiationRequest::new(&alice_pk, &alice_sk, &bob_pk, vec![], &SystemClock);
 
         // Bob creates outgoing request to Alice
         let (bob_announcement, bob_outgoing) =
-            OutgoingInitiationRequest::new(&bob_pk, &bob_sk, &alice_pk, vec![]);
+            OutgoingInitiationRequest::new(&bob_pk, &bob_sk, &alice_pk, vec![], &SystemClock);
 
         // Alice receives Bob's announcement
         let (bob_incoming_at_alice, _) =
@@ -750,10 +1235,16 @@ mod tests {
                 .expect("Failed to parse Alice's announcement at Bob");
 
         // Both create sessions
-        let _alice_session =
-            Session::from_initiation_request_pair(&alice_outgoing, &bob_incoming_at_alice);
-        let _bob_session =
-            Session::from_initiation_request_pair(&bob_outgoing, &alice_incoming_at_bob);
+        let _alice_session = Session::from_initiation_request_pair(
+            &alice_outgoing,
+            &bob_incoming_at_alice,
+            SkippedMessageLimits::default(),
+        );
+        let _bob_session = Session::from_initiation_request_pair(
+            &bob_outgoing,
+            &alice_incoming_at_bob,
+            SkippedMessageLimits::default(),
+        );
 
         // Sessions created successfully (KeyPairs are generated randomly, so we can't compare them)
     }
@@ -767,23 +1258,29 @@ mod tests {
         // Establish sessions
         let _timestamp = crate::utils::timestamp_millis();
         let (alice_announcement, alice_outgoing) =
-            OutgoingInitiationRequest::new(&alice_pk, &alice_sk, &bob_pk, vec![]);
+            OutgoingInitiationRequest::new(&alice_pk, &alice_sk, &bob_pk, vec![], &SystemClock);
         let (bob_announcement, bob_outgoing) =
-            OutgoingInitiationRequest::new(&bob_pk, &bob_sk, &alice_pk, vec![]);
+            OutgoingInitiationRequest::new(&bob_pk, &bob_sk, &alice_pk, vec![], &SystemClock);
 
         let (bob_incoming_at_alice, _) =
             IncomingInitiationRequest::try_from(&bob_announcement, &alice_pk, &alice_sk).unwrap();
         let (alice_incoming_at_bob, _) =
             IncomingInitiationRequest::try_from(&alice_announcement, &bob_pk, &bob_sk).unwrap();
 
-        let mut alice_session =
-            Session::from_initiation_request_pair(&alice_outgoing, &bob_incoming_at_alice);
-        let mut bob_session =
-            Session::from_initiation_request_pair(&bob_outgoing, &alice_incoming_at_bob);
+        let mut alice_session = Session::from_initiation_request_pair(
+            &alice_outgoing,
+            &bob_incoming_at_alice,
+            SkippedMessageLimits::default(),
+        );
+        let mut bob_session = Session::from_initiation_request_pair(
+            &bob_outgoing,
+            &alice_incoming_at_bob,
+            SkippedMessageLimits::default(),
+        );
 
         // Alice sends a message to Bob
         let message = create_test_message(b"Hello Bob!");
-        let send_output = alice_session.send_outgoing_message(&message.contents);
+        let send_output = alice_session.send_outgoing_message(&message.contents, &SystemClock);
 
         assert!(!send_output.seeker.is_empty());
         assert!(!send_output.data.is_empty());
@@ -811,23 +1308,29 @@ mod tests {
         // Establish sessions
         let _timestamp = crate::utils::timestamp_millis();
         let (alice_announcement, alice_outgoing) =
-            OutgoingInitiationRequest::new(&alice_pk, &alice_sk, &bob_pk, vec![]);
+            OutgoingInitiationRequest::new(&alice_pk, &alice_sk, &bob_pk, vec![], &SystemClock);
         let (bob_announcement, bob_outgoing) =
-            OutgoingInitiationRequest::new(&bob_pk, &bob_sk, &alice_pk, vec![]);
+            OutgoingInitiationRequest::new(&bob_pk, &bob_sk, &alice_pk, vec![], &SystemClock);
 
         let (bob_incoming_at_alice, _) =
             IncomingInitiationRequest::try_from(&bob_announcement, &alice_pk, &alice_sk).unwrap();
         let (alice_incoming_at_bob, _) =
             IncomingInitiationRequest::try_from(&alice_announcement, &bob_pk, &bob_sk).unwrap();
 
-        let mut alice_session =
-            Session::from_initiation_request_pair(&alice_outgoing, &bob_incoming_at_alice);
-        let mut bob_session =
-            Session::from_initiation_request_pair(&bob_outgoing, &alice_incoming_at_bob);
+        let mut alice_session = Session::from_initiation_request_pair(
+            &alice_outgoing,
+            &bob_incoming_at_alice,
+            SkippedMessageLimits::default(),
+        );
+        let mut bob_session = Session::from_initiation_request_pair(
+            &bob_outgoing,
+            &alice_incoming_at_bob,
+            SkippedMessageLimits::default(),
+        );
 
         // Alice -> Bob
         let msg1 = create_test_message(b"Hello Bob!");
-        let output1 = alice_session.send_outgoing_message(&msg1.contents);
+        let output1 = alice_session.send_outgoing_message(&msg1.contents, &SystemClock);
         let received1 = bob_session
             .try_feed_incoming_message(&bob_sk, &output1.seeker, &output1.data)
             .unwrap();
@@ -837,7 +1340,7 @@ mod tests {
 
         // Bob -> Alice
         let msg2 = create_test_message(b"Hi Alice!");
-        let output2 = bob_session.send_outgoing_message(&msg2.contents);
+        let output2 = bob_session.send_outgoing_message(&msg2.contents, &SystemClock);
         let received2 = alice_session
             .try_feed_incoming_message(&alice_sk, &output2.seeker, &output2.data)
             .unwrap();
@@ -847,7 +1350,7 @@ mod tests {
 
         // Alice -> Bob (second message)
         let msg3 = create_test_message(b"How are you?");
-        let output3 = alice_session.send_outgoing_message(&msg3.contents);
+        let output3 = alice_session.send_outgoing_message(&msg3.contents, &SystemClock);
         let received3 = bob_session
             .try_feed_incoming_message(&bob_sk, &output3.seeker, &output3.data)
             .unwrap();
@@ -866,39 +1369,45 @@ mod tests {
         // Establish session between Alice and Bob
         let _timestamp = crate::utils::timestamp_millis();
         let (alice_announcement, alice_outgoing) =
-            OutgoingInitiationRequest::new(&alice_pk, &alice_sk, &bob_pk, vec![]);
+            OutgoingInitiationRequest::new(&alice_pk, &alice_sk, &bob_pk, vec![], &SystemClock);
         let (bob_announcement, _bob_outgoing) =
-            OutgoingInitiationRequest::new(&bob_pk, &bob_sk, &alice_pk, vec![]);
+            OutgoingInitiationRequest::new(&bob_pk, &bob_sk, &alice_pk, vec![], &SystemClock);
 
         let (bob_incoming_at_alice, _) =
             IncomingInitiationRequest::try_from(&bob_announcement, &alice_pk, &alice_sk).unwrap();
         let (_alice_incoming_at_bob, _) =
             IncomingInitiationRequest::try_from(&alice_announcement, &bob_pk, &bob_sk).unwrap();
 
-        let mut alice_session =
-            Session::from_initiation_request_pair(&alice_outgoing, &bob_incoming_at_alice);
+        let mut alice_session = Session::from_initiation_request_pair(
+            &alice_outgoing,
+            &bob_incoming_at_alice,
+            SkippedMessageLimits::default(),
+        );
 
         // Eve establishes her own session with Alice
         let (eve_announcement, eve_outgoing) =
-            OutgoingInitiationRequest::new(&eve_pk, &eve_sk, &alice_pk, vec![]);
+            OutgoingInitiationRequest::new(&eve_pk, &eve_sk, &alice_pk, vec![], &SystemClock);
         let (eve_incoming_at_alice, _) =
             IncomingInitiationRequest::try_from(&eve_announcement, &alice_pk, &alice_sk).unwrap();
         let (alice_to_eve_announcement, _alice_to_eve_outgoing) =
-            OutgoingInitiationRequest::new(&alice_pk, &alice_sk, &eve_pk, vec![]);
+            OutgoingInitiationRequest::new(&alice_pk, &alice_sk, &eve_pk, vec![], &SystemClock);
         let (_alice_incoming_at_eve, _) =
             IncomingInitiationRequest::try_from(&alice_to_eve_announcement, &eve_pk, &eve_sk)
                 .unwrap();
-        let mut eve_session =
-            Session::from_initiation_request_pair(&eve_outgoing, &eve_incoming_at_alice);
+        let mut eve_session = Session::from_initiation_request_pair(
+            &eve_outgoing,
+            &eve_incoming_at_alice,
+            SkippedMessageLimits::default(),
+        );
 
         // Alice sends message to Bob
         let message = create_test_message(b"Secret message for Bob");
-        let send_output = alice_session.send_outgoing_message(&message.contents);
+        let send_output = alice_session.send_outgoing_message(&message.contents, &SystemClock);
 
         // Eve tries to decrypt (should fail)
         let eve_attempt =
             eve_session.try_feed_incoming_message(&eve_sk, &send_output.seeker, &send_output.data);
-        assert!(eve_attempt.is_none());
+        assert_eq!(eve_attempt.err(), Some(SessionError::UnknownSeeker));
     }
 
     /// Tests that seekers are constructed correctly and have the expected structure
@@ -908,16 +1417,19 @@ mod tests {
         let (bob_pk, bob_sk) = generate_test_keypair();
 
         let (alice_announcement, alice_outgoing) =
-            OutgoingInitiationRequest::new(&alice_pk, &alice_sk, &bob_pk, vec![]);
+            OutgoingInitiationRequest::new(&alice_pk, &alice_sk, &bob_pk, vec![], &SystemClock);
         let (bob_announcement, _bob_outgoing) =
-            OutgoingInitiationRequest::new(&bob_pk, &bob_sk, &alice_pk, vec![]);
+            OutgoingInitiationRequest::new(&bob_pk, &bob_sk, &alice_pk, vec![], &SystemClock);
 
         let (bob_incoming_at_alice, _) =
             IncomingInitiationRequest::try_from(&bob_announcement, &alice_pk, &alice_sk).unwrap();
         let (_alice_incoming_at_bob, _) =
             IncomingInitiationRequest::try_from(&alice_announcement, &bob_pk, &bob_sk).unwrap();
-        let alice_session =
-            Session::from_initiation_request_pair(&alice_outgoing, &bob_incoming_at_alice);
+        let alice_session = Session::from_initiation_request_pair(
+            &alice_outgoing,
+            &bob_incoming_at_alice,
+            SkippedMessageLimits::default(),
+        );
 
         // Get seeker for next peer message
         let peer_seeker = alice_session.next_peer_message_seeker();
@@ -935,26 +1447,29 @@ mod tests {
         let (bob_pk, bob_sk) = generate_test_keypair();
 
         let (alice_announcement, alice_outgoing) =
-            OutgoingInitiationRequest::new(&alice_pk, &alice_sk, &bob_pk, vec![]);
+            OutgoingInitiationRequest::new(&alice_pk, &alice_sk, &bob_pk, vec![], &SystemClock);
         let (bob_announcement, _bob_outgoing) =
-            OutgoingInitiationRequest::new(&bob_pk, &bob_sk, &alice_pk, vec![]);
+            OutgoingInitiationRequest::new(&bob_pk, &bob_sk, &alice_pk, vec![], &SystemClock);
 
         let (bob_incoming_at_alice, _) =
             IncomingInitiationRequest::try_from(&bob_announcement, &alice_pk, &alice_sk).unwrap();
         let (_alice_incoming_at_bob, _) =
             IncomingInitiationRequest::try_from(&alice_announcement, &bob_pk, &bob_sk).unwrap();
-        let mut alice_session =
-            Session::from_initiation_request_pair(&alice_outgoing, &bob_incoming_at_alice);
+        let mut alice_session = Session::from_initiation_request_pair(
+            &alice_outgoing,
+            &bob_incoming_at_alice,
+            SkippedMessageLimits::default(),
+        );
 
         // Get initial lag
         let initial_lag = alice_session.lag_length();
 
         // Send messages (lag increases without acknowledgments)
-        alice_session.send_outgoing_message(&create_test_message(b"msg1").contents);
+        alice_session.send_outgoing_message(&create_test_message(b"msg1").contents, &SystemClock);
         let lag1 = alice_session.lag_length();
         assert!(lag1 > initial_lag);
 
-        alice_session.send_outgoing_message(&create_test_message(b"msg2").contents);
+        alice_session.send_outgoing_message(&create_test_message(b"msg2").contents, &SystemClock);
         let lag2 = alice_session.lag_length();
         assert!(lag2 > lag1);
     }
@@ -968,25 +1483,31 @@ mod tests {
         // Establish sessions
         let _timestamp = crate::utils::timestamp_millis();
         let (alice_announcement, alice_outgoing) =
-            OutgoingInitiationRequest::new(&alice_pk, &alice_sk, &bob_pk, vec![]);
+            OutgoingInitiationRequest::new(&alice_pk, &alice_sk, &bob_pk, vec![], &SystemClock);
         let (bob_announcement, bob_outgoing) =
-            OutgoingInitiationRequest::new(&bob_pk, &bob_sk, &alice_pk, vec![]);
+            OutgoingInitiationRequest::new(&bob_pk, &bob_sk, &alice_pk, vec![], &SystemClock);
 
         let (bob_incoming_at_alice, _) =
             IncomingInitiationRequest::try_from(&bob_announcement, &alice_pk, &alice_sk).unwrap();
         let (alice_incoming_at_bob, _) =
             IncomingInitiationRequest::try_from(&alice_announcement, &bob_pk, &bob_sk).unwrap();
 
-        let mut alice_session =
-            Session::from_initiation_request_pair(&alice_outgoing, &bob_incoming_at_alice);
-        let mut bob_session =
-            Session::from_initiation_request_pair(&bob_outgoing, &alice_incoming_at_bob);
+        let mut alice_session = Session::from_initiation_request_pair(
+            &alice_outgoing,
+            &bob_incoming_at_alice,
+            SkippedMessageLimits::default(),
+        );
+        let mut bob_session = Session::from_initiation_request_pair(
+            &bob_outgoing,
+            &alice_incoming_at_bob,
+            SkippedMessageLimits::default(),
+        );
 
         // Alice sends multiple messages
         let msg1 = create_test_message(b"msg1");
-        let output1 = alice_session.send_outgoing_message(&msg1.contents);
+        let output1 = alice_session.send_outgoing_message(&msg1.contents, &SystemClock);
         let msg2 = create_test_message(b"msg2");
-        let _output2 = alice_session.send_outgoing_message(&msg2.contents);
+        let _output2 = alice_session.send_outgoing_message(&msg2.contents, &SystemClock);
 
         // Bob receives first message
         let received1 = bob_session
@@ -998,7 +1519,7 @@ mod tests {
 
         // Bob sends a reply (which acknowledges Alice's messages)
         let reply = create_test_message(b"reply");
-        let reply_output = bob_session.send_outgoing_message(&reply.contents);
+        let reply_output = bob_session.send_outgoing_message(&reply.contents, &SystemClock);
 
         // Alice receives Bob's reply
         let received_reply = alice_session
@@ -1021,23 +1542,30 @@ mod tests {
         // Establish sessions
         let _timestamp = crate::utils::timestamp_millis();
         let (alice_announcement, alice_outgoing) =
-            OutgoingInitiationRequest::new(&alice_pk, &alice_sk, &bob_pk, vec![]);
+            OutgoingInitiationRequest::new(&alice_pk, &alice_sk, &bob_pk, vec![], &SystemClock);
         let (bob_announcement, bob_outgoing) =
-            OutgoingInitiationRequest::new(&bob_pk, &bob_sk, &alice_pk, vec![]);
+            OutgoingInitiationRequest::new(&bob_pk, &bob_sk, &alice_pk, vec![], &SystemClock);
 
         let (bob_incoming_at_alice, _) =
             IncomingInitiationRequest::try_from(&bob_announcement, &alice_pk, &alice_sk).unwrap();
         let (alice_incoming_at_bob, _) =
             IncomingInitiationRequest::try_from(&alice_announcement, &bob_pk, &bob_sk).unwrap();
 
-        let mut alice_session =
-            Session::from_initiation_request_pair(&alice_outgoing, &bob_incoming_at_alice);
-        let mut bob_session =
-            Session::from_initiation_request_pair(&bob_outgoing, &alice_incoming_at_bob);
+        let mut alice_session = Session::from_initiation_request_pair(
+            &alice_outgoing,
+            &bob_incoming_at_alice,
+            SkippedMessageLimits::default(),
+        );
+        let mut bob_session = Session::from_initiation_request_pair(
+            &bob_outgoing,
+            &alice_incoming_at_bob,
+            SkippedMessageLimits::default(),
+        );
 
         // Send empty message (for keep-alive)
         let empty_message = create_test_message(b"");
-        let send_output = alice_session.send_outgoing_message(&empty_message.contents);
+        let send_output =
+            alice_session.send_outgoing_message(&empty_message.contents, &SystemClock);
 
         let receive_output = bob_session
             .try_feed_incoming_message(&bob_sk, &send_output.seeker, &send_output.data)
@@ -1057,24 +1585,31 @@ mod tests {
         // Establish sessions
         let _timestamp = crate::utils::timestamp_millis();
         let (alice_announcement, alice_outgoing) =
-            OutgoingInitiationRequest::new(&alice_pk, &alice_sk, &bob_pk, vec![]);
+            OutgoingInitiationRequest::new(&alice_pk, &alice_sk, &bob_pk, vec![], &SystemClock);
         let (bob_announcement, bob_outgoing) =
-            OutgoingInitiationRequest::new(&bob_pk, &bob_sk, &alice_pk, vec![]);
+            OutgoingInitiationRequest::new(&bob_pk, &bob_sk, &alice_pk, vec![], &SystemClock);
 
         let (bob_incoming_at_alice, _) =
             IncomingInitiationRequest::try_from(&bob_announcement, &alice_pk, &alice_sk).unwrap();
         let (alice_incoming_at_bob, _) =
             IncomingInitiationRequest::try_from(&alice_announcement, &bob_pk, &bob_sk).unwrap();
 
-        let mut alice_session =
-            Session::from_initiation_request_pair(&alice_outgoing, &bob_incoming_at_alice);
-        let mut bob_session =
-            Session::from_initiation_request_pair(&bob_outgoing, &alice_incoming_at_bob);
+        let mut alice_session = Session::from_initiation_request_pair(
+            &alice_outgoing,
+            &bob_incoming_at_alice,
+            SkippedMessageLimits::default(),
+        );
+        let mut bob_session = Session::from_initiation_request_pair(
+            &bob_outgoing,
+            &alice_incoming_at_bob,
+            SkippedMessageLimits::default(),
+        );
 
         // Send large message (10KB)
         let large_content = vec![42u8; 10_000];
         let large_message = create_test_message(&large_content);
-        let send_output = alice_session.send_outgoing_message(&large_message.contents);
+        let send_output =
+            alice_session.send_outgoing_message(&large_message.contents, &SystemClock);
 
         let receive_output = bob_session
             .try_feed_incoming_message(&bob_sk, &send_output.seeker, &send_output.data)
@@ -1085,6 +1620,303 @@ mod tests {
         assert_eq!(receive_output.user_id, alice_id.as_bytes().to_vec());
     }
 
+    /// Helper function to establish a pair of sessions between two fresh users
+    fn establish_test_sessions(
+        limits: SkippedMessageLimits,
+    ) -> (Session, Session, auth::UserSecretKeys, auth::UserSecretKeys) {
+        let (alice_pk, alice_sk) = generate_test_keypair();
+        let (bob_pk, bob_sk) = generate_test_keypair();
+
+        let (alice_announcement, alice_outgoing) =
+            OutgoingInitiationRequest::new(&alice_pk, &alice_sk, &bob_pk, vec![], &SystemClock);
+        let (bob_announcement, bob_outgoing) =
+            OutgoingInitiationRequest::new(&bob_pk, &bob_sk, &alice_pk, vec![], &SystemClock);
+
+        let (bob_incoming_at_alice, _) =
+            IncomingInitiationRequest::try_from(&bob_announcement, &alice_pk, &alice_sk).unwrap();
+        let (alice_incoming_at_bob, _) =
+            IncomingInitiationRequest::try_from(&alice_announcement, &bob_pk, &bob_sk).unwrap();
+
+        let alice_session =
+            Session::from_initiation_request_pair(&alice_outgoing, &bob_incoming_at_alice, limits);
+        let bob_session =
+            Session::from_initiation_request_pair(&bob_outgoing, &alice_incoming_at_bob, limits);
+
+        (alice_session, bob_session, alice_sk, bob_sk)
+    }
+
+    /// Tests that messages can be looked up and decrypted in any order
+    #[test]
+    fn test_session_out_of_order_messages() {
+        let (mut alice_session, mut bob_session, _alice_sk, bob_sk) =
+            establish_test_sessions(SkippedMessageLimits::default());
+
+        let outputs: Vec<SendOutgoingMessageOutput> = (0..3u8)
+            .map(|i| alice_session.send_outgoing_message(&[i], &SystemClock))
+            .collect();
+
+        // Bob can look up all three messages before receiving any of them
+        let expected_seekers = bob_session.peer_message_seekers();
+        for output in &outputs {
+            assert!(expected_seekers.contains(&output.seeker));
+        }
+        assert_eq!(bob_session.next_peer_message_seeker(), outputs[0].seeker);
+
+        // Bob receives them in the order 3, 1, 2
+        for i in [2, 0, 1] {
+            let received = bob_session
+                .try_feed_incoming_message(&bob_sk, &outputs[i].seeker, &outputs[i].data)
+                .expect("Failed to decrypt out-of-order message");
+            assert_eq!(received.message, vec![i as u8]);
+        }
+
+        // received seekers are no longer expected
+        let expected_seekers = bob_session.peer_message_seekers();
+        for output in &outputs {
+            assert!(!expected_seekers.contains(&output.seeker));
+        }
+    }
+
+    /// Tests that a missing message does not prevent reading the following ones,
+    /// and that it can still be read when it shows up
+    #[test]
+    fn test_session_gap_in_messages() {
+        let (mut alice_session, mut bob_session, alice_sk, bob_sk) =
+            establish_test_sessions(SkippedMessageLimits::default());
+
+        let output1 = alice_session.send_outgoing_message(b"msg1", &SystemClock);
+        let output2 = alice_session.send_outgoing_message(b"msg2", &SystemClock);
+        let output3 = alice_session.send_outgoing_message(b"msg3", &SystemClock);
+
+        bob_session
+            .try_feed_incoming_message(&bob_sk, &output1.seeker, &output1.data)
+            .expect("Failed to decrypt msg1");
+        bob_session
+            .try_feed_incoming_message(&bob_sk, &output3.seeker, &output3.data)
+            .expect("Failed to decrypt msg3 over the gap");
+        assert_eq!(
+            bob_session.next_peer_message_seeker(),
+            alice_session
+                .send_outgoing_message(b"msg4", &SystemClock)
+                .seeker
+        );
+
+        // the conversation goes on while msg2 is missing
+        let reply = bob_session.send_outgoing_message(b"reply", &SystemClock);
+        alice_session
+            .try_feed_incoming_message(&alice_sk, &reply.seeker, &reply.data)
+            .expect("Failed to decrypt reply");
+
+        let received2 = bob_session
+            .try_feed_incoming_message(&bob_sk, &output2.seeker, &output2.data)
+            .expect("Failed to decrypt late msg2");
+        assert_eq!(received2.message, b"msg2");
+    }
+
+    /// Tests that the same message cannot be received twice
+    #[test]
+    fn test_session_duplicate_message_rejected() {
+        let (mut alice_session, mut bob_session, _alice_sk, bob_sk) =
+            establish_test_sessions(SkippedMessageLimits::default());
+
+        let output1 = alice_session.send_outgoing_message(b"msg1", &SystemClock);
+        let output2 = alice_session.send_outgoing_message(b"msg2", &SystemClock);
+
+        bob_session
+            .try_feed_incoming_message(&bob_sk, &output2.seeker, &output2.data)
+            .expect("Failed to decrypt msg2");
+        bob_session
+            .try_feed_incoming_message(&bob_sk, &output1.seeker, &output1.data)
+            .expect("Failed to decrypt msg1");
+
+        assert_eq!(
+            bob_session
+                .try_feed_incoming_message(&bob_sk, &output1.seeker, &output1.data)
+                .err(),
+            Some(SessionError::UnknownSeeker)
+        );
+        assert_eq!(
+            bob_session
+                .try_feed_incoming_message(&bob_sk, &output2.seeker, &output2.data)
+                .err(),
+            Some(SessionError::UnknownSeeker)
+        );
+    }
+
+    /// Tests that messages too far ahead of the next expected one are not readable
+    #[test]
+    fn test_session_skip_limit() {
+        let limits = SkippedMessageLimits {
+            max_skip: 1,
+            max_stored: 8,
+        };
+        let (mut alice_session, mut bob_session, _alice_sk, bob_sk) =
+            establish_test_sessions(limits);
+
+        let output1 = alice_session.send_outgoing_message(b"msg1", &SystemClock);
+        let output2 = alice_session.send_outgoing_message(b"msg2", &SystemClock);
+        let output3 = alice_session.send_outgoing_message(b"msg3", &SystemClock);
+
+        let expected_seekers = bob_session.peer_message_seekers();
+        assert!(expected_seekers.contains(&output1.seeker));
+        assert!(expected_seekers.contains(&output2.seeker));
+        assert!(!expected_seekers.contains(&output3.seeker));
+        assert_eq!(
+            bob_session
+                .try_feed_incoming_message(&bob_sk, &output3.seeker, &output3.data)
+                .err(),
+            Some(SessionError::UnknownSeeker)
+        );
+
+        // once msg2 is received, msg3 becomes readable
+        bob_session
+            .try_feed_incoming_message(&bob_sk, &output2.seeker, &output2.data)
+            .expect("Failed to decrypt msg2");
+        bob_session
+            .try_feed_incoming_message(&bob_sk, &output3.seeker, &output3.data)
+            .expect("Failed to decrypt msg3");
+        bob_session
+            .try_feed_incoming_message(&bob_sk, &output1.seeker, &output1.data)
+            .expect("Failed to decrypt late msg1");
+    }
+
+    /// Tests that failures are reported with a distinct error and leave the session usable
+    #[test]
+    fn test_session_tampered_message_errors() {
+        let (mut alice_session, mut bob_session, _alice_sk, bob_sk) =
+            establish_test_sessions(SkippedMessageLimits::default());
+
+        let output = alice_session.send_outgoing_message(b"msg", &SystemClock);
+
+        // tampering with the encrypted part breaks the seeker signature
+        let mut tampered = output.data.clone();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+        assert_eq!(
+            bob_session
+                .try_feed_incoming_message(&bob_sk, &output.seeker, &tampered)
+                .err(),
+            Some(SessionError::InvalidSignature)
+        );
+
+        // truncated data cannot be parsed
+        assert_eq!(
+            bob_session
+                .try_feed_incoming_message(&bob_sk, &output.seeker, &output.data[..1])
+                .err(),
+            Some(SessionError::MalformedMessage)
+        );
+
+        // the genuine message is still accepted
+        bob_session
+            .try_feed_incoming_message(&bob_sk, &output.seeker, &output.data)
+            .expect("Failed to decrypt msg");
+    }
+
+    /// Tests that an authenticated message whose payload cannot be parsed is rejected
+    /// without changing the session state
+    #[test]
+    fn test_session_malformed_payload_leaves_state_unchanged() {
+        let (mut alice_session, mut bob_session, _alice_sk, bob_sk) =
+            establish_test_sessions(SkippedMessageLimits::default());
+        let serialize = |session: &Session| {
+            bincode::serde::encode_to_vec(session, bincode::config::standard()).unwrap()
+        };
+
+        let malformed = alice_session.send_raw_message_bytes(b"not a message");
+        let state_before = serialize(&bob_session);
+        assert_eq!(
+            bob_session
+                .try_feed_incoming_message(&bob_sk, &malformed.seeker, &malformed.data)
+                .err(),
+            Some(SessionError::MalformedMessage)
+        );
+        assert_eq!(serialize(&bob_session), state_before);
+
+        // the following genuine message is still accepted
+        let output = alice_session.send_outgoing_message(b"msg", &SystemClock);
+        let received = bob_session
+            .try_feed_incoming_message(&bob_sk, &output.seeker, &output.data)
+            .expect("Failed to decrypt msg");
+        assert_eq!(received.message, b"msg");
+    }
+
+    /// Tests that reading a message only changes the session state once it is accepted
+    #[test]
+    fn test_session_decrypt_then_accept() {
+        let (mut alice_session, mut bob_session, _alice_sk, bob_sk) =
+            establish_test_sessions(SkippedMessageLimits::default());
+        let serialize = |session: &Session| {
+            bincode::serde::encode_to_vec(session, bincode::config::standard()).unwrap()
+        };
+
+        let output = alice_session.send_outgoing_message(b"msg", &SystemClock);
+        let state_before = serialize(&bob_session);
+        let decrypted = bob_session
+            .try_decrypt_incoming_message(&bob_sk, &output.seeker, &output.data)
+            .expect("Failed to decrypt msg");
+        assert_eq!(serialize(&bob_session), state_before);
+        drop(decrypted);
+
+        // the message was not received: it can be read again and accepted
+        let decrypted = bob_session
+            .try_decrypt_incoming_message(&bob_sk, &output.seeker, &output.data)
+            .expect("Failed to decrypt msg");
+        let duplicate = bob_session
+            .try_decrypt_incoming_message(&bob_sk, &output.seeker, &output.data)
+            .expect("Failed to decrypt msg");
+        let received = bob_session
+            .accept_incoming_message(decrypted)
+            .expect("Failed to accept msg");
+        assert_eq!(received.message, b"msg");
+
+        // a copy read before the message was accepted is not accepted twice
+        assert_eq!(
+            bob_session.accept_incoming_message(duplicate).err(),
+            Some(SessionError::UnknownSeeker)
+        );
+    }
+
+    /// Checks the seeker chain derivation against the known-answer vectors
+    #[test]
+    fn test_seeker_vectors() {
+        fn from_hex(hex: &str) -> Vec<u8> {
+            (0..hex.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).expect("invalid hex"))
+                .collect()
+        }
+
+        let vectors: serde_json::Value =
+            serde_json::from_str(include_str!("../test_vectors/seekers.json"))
+                .expect("test vectors are valid JSON");
+        let seed = |name: &str| -> [u8; 32] {
+            from_hex(vectors[name].as_str().expect("seed is a string"))
+                .try_into()
+                .expect("seed is 32 bytes")
+        };
+        let expected = vectors["seekers"].as_array().expect("seekers is an array");
+
+        let mut chain = SeekerChain::new(&seed("sender_seed"), &seed("receiver_seed"));
+        for vector in expected {
+            let (index, keypair) = chain.next_keypair();
+            assert_eq!(Some(index), vector["index"].as_u64());
+            let public_key = keypair.get_public_key();
+            assert_eq!(
+                public_key.to_bytes().to_vec(),
+                from_hex(
+                    vector["public_key"]
+                        .as_str()
+                        .expect("public key is a string")
+                )
+            );
+            assert_eq!(
+                Session::compute_seeker(&public_key),
+                from_hex(vector["seeker"].as_str().expect("seeker is a string"))
+            );
+        }
+    }
+
     // test_seeker_prefix_uniqueness removed - seekers now use randomly generated Massa keypairs,
     // so uniqueness is guaranteed by cryptographic randomness rather than prefixes
 }
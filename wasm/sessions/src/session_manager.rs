@@ -6,6 +6,7 @@
 //! - **Session lifecycle**: Creating, tracking, and expiring sessions
 //! - **Message board coordination**: Computing seekers for message lookup
 //! - **Keep-alive**: Automatically refreshing idle sessions
+//! - **Healing**: Re-establishing broken sessions with backoff
 //! - **State persistence**: Serialization for encrypted storage
 //!
 //! # Architecture
@@ -31,6 +32,8 @@
 //!     max_session_inactivity_millis: 3_600_000,
 //!     keep_alive_interval_millis: 60_000,
 //!     max_session_lag_length: 100,
+//!     max_skipped_messages: 16,
+//!     max_stored_skipped_messages: 128,
 //! };
 //! let mut manager = SessionManager::new(config);
 //!
@@ -51,7 +54,7 @@
 //!
 //! // Send a message
 //! let peer_id = peer_pk.derive_id();
-//! if let Some(output) = manager.send_message(&peer_id, b"Hello!") {
+//! if let Ok(output) = manager.send_message(&peer_id, b"Hello!") {
 //!     // Post output.data to message board at seeker output.seeker
 //! }
 //!
@@ -61,7 +64,7 @@
 //!
 //! // Process incoming message
 //! # let (seeker, data): (Vec<u8>, Vec<u8>) = (vec![], vec![]);
-//! if let Some(received) = manager.feed_incoming_message_board_read(
+//! if let Ok(received) = manager.feed_incoming_message_board_read(
 //!     &seeker,
 //!     &data,
 //!     &our_sk
@@ -77,7 +80,21 @@
 //! - **Outgoing**: We initiated but haven't received peer's announcement yet
 //! - **Incoming**: Peer initiated but we haven't established the session yet
 //! - **Saturated**: Session is active but has too much unacknowledged lag
-//! - **Killed**: Session was terminated due to an error
+//! - **Killed**: Session was terminated due to an error, until `heal_sessions` re-establishes it
+//!
+//! # Session Convergence
+//!
+//! Both peers may announce at the same time, and announce again before reading the answer of
+//! the other. Whatever happens, the session with a peer is built from the latest announcement
+//! of each side: our latest outgoing one and the latest incoming one. Our announcements to a
+//! peer have strictly increasing timestamps, and incoming announcements that are not newer
+//! than the latest one are rejected, so both sides agree on which announcement of a side is
+//! the latest, in whatever order they read them. Once each side has read the latest
+//! announcement of the other before it expired, both have built the same session. Messages
+//! sent in the meantime over a session that the peer never builds are lost.
+//!
+//! A session replaced by a new one keeps reading the messages of the peer until the peer uses
+//! the new one, so that the messages it sent before switching are not lost.
 //!
 //! # Message Board Integration
 //!
@@ -86,6 +103,7 @@
 //! 2. Messages are posted to a message board with seekers as keys
 //! 3. Recipients monitor specific seekers to find their messages
 //! 4. Seekers are hashes of ephemeral Massa public keys
+//! 5. Seekers of upcoming messages are known in advance, so messages can be read in any order
 //!
 //! This design allows for:
 //! - Privacy: Seekers don't reveal sender/recipient
@@ -93,17 +111,25 @@
 //! - Unlinkability: Each message uses a fresh seeker
 
 use crate::{
+    clock::{Clock, NotBefore, SystemClock, default_clock},
+    error::{AnnouncementError, KeyRotationError, SessionError, StorageError},
     session::{
         FeedIncomingMessageOutput, IncomingInitiationRequest, OutgoingInitiationRequest,
-        SendOutgoingMessageOutput, Session,
+        SendOutgoingMessageOutput, Session, SessionV0, SessionV4,
     },
-    utils::timestamp_millis,
+    state_format::{self, StateKind},
+    storage::SessionStorage,
 };
 use auth::UserId;
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use crypto_agraphon::SkippedMessageLimits;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use std::collections::{HashMap, HashSet};
 use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
 
+/// Number of announcements sent to heal a broken session before giving up,
+/// see [`SessionManager::heal_sessions`]
+const MAX_HEALING_ATTEMPTS: u32 = 5;
+
 /// Result from processing an incoming announcement.
 ///
 /// Contains the announcer's public keys, the timestamp of the announcement,
@@ -155,6 +181,24 @@ pub struct SessionManagerConfig {
 
     /// The maximum lag length of a session before sending more messages is blocked
     pub max_session_lag_length: u64,
+
+    /// The maximum number of consecutive peer messages that can be missing
+    /// when receiving a later message from that peer
+    pub max_skipped_messages: u64,
+    /// The maximum number of missing peer messages per session that remain readable
+    /// if they arrive later. A missing message is also given up once the peer has
+    /// acknowledged this many more of our messages
+    pub max_stored_skipped_messages: u64,
+}
+
+impl SessionManagerConfig {
+    /// Limits on the state kept by sessions for messages delivered out of order
+    fn skipped_message_limits(&self) -> SkippedMessageLimits {
+        SkippedMessageLimits {
+            max_skip: self.max_skipped_messages,
+            max_stored: self.max_stored_skipped_messages,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
@@ -164,22 +208,441 @@ struct SessionInfo {
     last_outgoing_message_timestamp: u128,
 }
 
+impl SessionInfo {
+    /// Returns `true` if the session expects a message of the peer under `seeker`
+    fn expects_seeker(&self, seeker: &[u8]) -> bool {
+        self.session
+            .peer_message_seekers()
+            .iter()
+            .any(|peer_seeker| peer_seeker == seeker)
+    }
+}
+
+/// Re-establishment of a broken session, see [`SessionManager::heal_sessions`]
+#[derive(Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
+struct Healing {
+    /// Keys to announce to, kept since the announcements they come from expire
+    peer_public_keys: auth::UserPublicKeys,
+    /// Number of healing announcements sent so far
+    attempts: u32,
+    /// Time from which the next healing announcement can be sent
+    next_attempt_millis: u128,
+}
+
+impl Healing {
+    fn new(peer_public_keys: &auth::UserPublicKeys, timestamp_millis: u128) -> Self {
+        Self {
+            peer_public_keys: peer_public_keys.clone(),
+            attempts: 0,
+            next_attempt_millis: timestamp_millis,
+        }
+    }
+}
+
 #[derive(Default, Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
 struct PeerInfo {
     active_session: Option<SessionInfo>,
     latest_incoming_init_request: Option<IncomingInitiationRequest>,
     latest_outgoing_init_request: Option<OutgoingInitiationRequest>,
+    /// Fingerprint of the peer keys verified out-of-band, if any
+    verified_key_fingerprint: Option<[u8; auth::KEY_FINGERPRINT_SIZE]>,
+    /// Healing of a broken session, until the peer is heard from again
+    healing: Option<Healing>,
+    /// Session replaced by the active one, still reading the messages the peer sent on it
+    /// until the peer uses the active one
+    replaced_session: Option<SessionInfo>,
+}
+
+impl PeerInfo {
+    /// Makes `session_info` the active session.
+    ///
+    /// The replaced session is kept until the peer uses the new one. If a replaced session is
+    /// already kept, the active one was never used by the peer, and is dropped instead.
+    fn replace_session(&mut self, session_info: SessionInfo) {
+        let replaced_session = self.active_session.replace(session_info);
+        if self.replaced_session.is_none() {
+            self.replaced_session = replaced_session;
+        }
+    }
+
+    /// The active session, or the replaced one if `replaced`
+    fn session_mut(&mut self, replaced: bool) -> Option<&mut SessionInfo> {
+        if replaced {
+            self.replaced_session.as_mut()
+        } else {
+            self.active_session.as_mut()
+        }
+    }
+
+    /// Returns `true` if the latest announcement of the peer is not answered by one of ours
+    fn has_unanswered_announcement(&self) -> bool {
+        self.latest_incoming_init_request.is_some() && self.latest_outgoing_init_request.is_none()
+    }
+
+    /// Latest known long-term public keys of the peer
+    fn public_keys(&self) -> Option<&auth::UserPublicKeys> {
+        self.latest_incoming_init_request
+            .as_ref()
+            .map(|request| &request.origin_public_keys)
+            .or_else(|| {
+                self.active_session
+                    .as_ref()
+                    .map(|session_info| session_info.session.peer_public_keys())
+            })
+    }
+
+    /// Clears the verification if the peer keys are not the verified ones anymore.
+    ///
+    /// Returns `true` if the verification was cleared.
+    fn clear_verification_if_keys_changed(&mut self, peer_pk: &auth::UserPublicKeys) -> bool {
+        match self.verified_key_fingerprint {
+            Some(fingerprint) if fingerprint != auth::key_fingerprint(peer_pk) => {
+                self.verified_key_fingerprint = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Moves the peer to its rotated keys, keeping its session, pending announcements, healing
+    /// and verification.
+    fn rotate_public_keys(&mut self, old_pk: &auth::UserPublicKeys, new_pk: &auth::UserPublicKeys) {
+        for session_info in [&mut self.active_session, &mut self.replaced_session]
+            .into_iter()
+            .flatten()
+        {
+            session_info.session.set_peer_public_keys(new_pk.clone());
+        }
+        if let Some(request) = &mut self.latest_incoming_init_request {
+            request.origin_public_keys = new_pk.clone();
+        }
+        if let Some(healing) = &mut self.healing {
+            healing.peer_public_keys = new_pk.clone();
+        }
+
+        // the verified keys signed the rotation, so the new keys are verified too
+        if self.verified_key_fingerprint == Some(auth::key_fingerprint(old_pk)) {
+            self.verified_key_fingerprint = Some(auth::key_fingerprint(new_pk));
+        }
+    }
+}
+
+/// `SessionManagerConfig` of schema version 0, before out-of-order delivery
+#[derive(Deserialize)]
+struct SessionManagerConfigV0 {
+    max_incoming_announcement_age_millis: u128,
+    max_incoming_announcement_future_millis: u128,
+    max_incoming_message_age_millis: u128,
+    max_incoming_message_future_millis: u128,
+    max_session_inactivity_millis: u128,
+    keep_alive_interval_millis: u128,
+    max_session_lag_length: u64,
+}
+
+impl From<SessionManagerConfigV0> for SessionManagerConfig {
+    fn from(config: SessionManagerConfigV0) -> Self {
+        let skipped_message_limits = SkippedMessageLimits::default();
+        Self {
+            max_incoming_announcement_age_millis: config.max_incoming_announcement_age_millis,
+            max_incoming_announcement_future_millis: config.max_incoming_announcement_future_millis,
+            max_incoming_message_age_millis: config.max_incoming_message_age_millis,
+            max_incoming_message_future_millis: config.max_incoming_message_future_millis,
+            max_session_inactivity_millis: config.max_session_inactivity_millis,
+            keep_alive_interval_millis: config.keep_alive_interval_millis,
+            max_session_lag_length: config.max_session_lag_length,
+            max_skipped_messages: skipped_message_limits.max_skip,
+            max_stored_skipped_messages: skipped_message_limits.max_stored,
+        }
+    }
+}
+
+/// `SessionInfo` of schema version 0, before out-of-order delivery
+#[derive(Deserialize)]
+struct SessionInfoV0 {
+    session: SessionV0,
+    last_incoming_message_timestamp: u128,
+    last_outgoing_message_timestamp: u128,
+}
+
+/// `PeerInfo` of schema version 0, before out-of-order delivery
+#[derive(Deserialize)]
+struct PeerInfoV0 {
+    active_session: Option<SessionInfoV0>,
+    latest_incoming_init_request: Option<IncomingInitiationRequest>,
+    latest_outgoing_init_request: Option<OutgoingInitiationRequest>,
+}
+
+/// Serialized layout of a `SessionManager` of schema version 0
+#[derive(Deserialize)]
+struct SessionManagerStateV0 {
+    config: SessionManagerConfigV0,
+    peers: HashMap<UserId, Box<PeerInfoV0>>,
+}
+
+/// Upgrades a serialized `SessionManager` from schema version 0 to 1, which introduced
+/// the out-of-order delivery of messages along with the envelope.
+///
+/// The new limits of the configuration take their default values, and active sessions
+/// are upgraded with [`SessionV4::from_v0`].
+pub(crate) fn migrate_v0_to_v1(
+    kind: StateKind,
+    state: Zeroizing<Vec<u8>>,
+) -> Result<Zeroizing<Vec<u8>>, SessionError> {
+    // only whole session managers were saved before versioning
+    if kind != StateKind::SessionManager {
+        return Ok(state);
+    }
+
+    let (session_manager, _): (SessionManagerStateV0, usize) =
+        bincode::serde::decode_from_slice(&state, bincode::config::standard())
+            .map_err(|_| SessionError::StateMalformed)?;
+    let config = SessionManagerConfig::from(session_manager.config);
+    let skipped_message_limits = config.skipped_message_limits();
+    let upgraded_session_manager = SessionManagerState {
+        config,
+        peers: session_manager
+            .peers
+            .into_iter()
+            .map(|(peer_id, peer_info)| {
+                let PeerInfoV0 {
+                    active_session,
+                    latest_incoming_init_request,
+                    latest_outgoing_init_request,
+                } = *peer_info;
+                let peer_info = PeerInfoV1 {
+                    active_session: active_session.map(|session_info| SessionInfoV4 {
+                        session: SessionV4::from_v0(session_info.session, skipped_message_limits),
+                        last_incoming_message_timestamp: session_info
+                            .last_incoming_message_timestamp,
+                        last_outgoing_message_timestamp: session_info
+                            .last_outgoing_message_timestamp,
+                    }),
+                    latest_incoming_init_request,
+                    latest_outgoing_init_request,
+                };
+                (peer_id, Box::new(peer_info))
+            })
+            .collect(),
+    };
+    bincode::serde::encode_to_vec(&upgraded_session_manager, bincode::config::standard())
+        .map(Zeroizing::new)
+        .map_err(|_| SessionError::StateMalformed)
+}
+
+/// `SessionInfo` of schema versions 1 to 4, whose sessions used the session protocol version 1
+#[derive(Serialize, Deserialize)]
+struct SessionInfoV4 {
+    session: SessionV4,
+    last_incoming_message_timestamp: u128,
+    last_outgoing_message_timestamp: u128,
+}
+
+/// `PeerInfo` of schema version 1, before peer verification was recorded
+#[derive(Serialize, Deserialize)]
+struct PeerInfoV1 {
+    active_session: Option<SessionInfoV4>,
+    latest_incoming_init_request: Option<IncomingInitiationRequest>,
+    latest_outgoing_init_request: Option<OutgoingInitiationRequest>,
+}
+
+/// `PeerInfo` of schema version 2, before the healing of broken sessions was recorded
+#[derive(Serialize, Deserialize)]
+struct PeerInfoV2 {
+    active_session: Option<SessionInfoV4>,
+    latest_incoming_init_request: Option<IncomingInitiationRequest>,
+    latest_outgoing_init_request: Option<OutgoingInitiationRequest>,
+    verified_key_fingerprint: Option<[u8; auth::KEY_FINGERPRINT_SIZE]>,
+}
+
+impl From<PeerInfoV1> for PeerInfoV2 {
+    fn from(peer_info: PeerInfoV1) -> Self {
+        Self {
+            active_session: peer_info.active_session,
+            latest_incoming_init_request: peer_info.latest_incoming_init_request,
+            latest_outgoing_init_request: peer_info.latest_outgoing_init_request,
+            verified_key_fingerprint: None,
+        }
+    }
+}
+
+/// `PeerInfo` of schema version 3, before replaced sessions were kept
+#[derive(Serialize, Deserialize)]
+struct PeerInfoV3 {
+    active_session: Option<SessionInfoV4>,
+    latest_incoming_init_request: Option<IncomingInitiationRequest>,
+    latest_outgoing_init_request: Option<OutgoingInitiationRequest>,
+    verified_key_fingerprint: Option<[u8; auth::KEY_FINGERPRINT_SIZE]>,
+    healing: Option<Healing>,
+}
+
+impl From<PeerInfoV2> for PeerInfoV3 {
+    fn from(peer_info: PeerInfoV2) -> Self {
+        Self {
+            active_session: peer_info.active_session,
+            latest_incoming_init_request: peer_info.latest_incoming_init_request,
+            latest_outgoing_init_request: peer_info.latest_outgoing_init_request,
+            verified_key_fingerprint: peer_info.verified_key_fingerprint,
+            healing: None,
+        }
+    }
+}
+
+/// `PeerInfo` of schema version 4, whose sessions used the session protocol version 1
+#[derive(Serialize, Deserialize)]
+struct PeerInfoV4 {
+    active_session: Option<SessionInfoV4>,
+    latest_incoming_init_request: Option<IncomingInitiationRequest>,
+    latest_outgoing_init_request: Option<OutgoingInitiationRequest>,
+    verified_key_fingerprint: Option<[u8; auth::KEY_FINGERPRINT_SIZE]>,
+    healing: Option<Healing>,
+    replaced_session: Option<SessionInfoV4>,
+}
+
+impl From<PeerInfoV3> for PeerInfoV4 {
+    fn from(peer_info: PeerInfoV3) -> Self {
+        Self {
+            active_session: peer_info.active_session,
+            latest_incoming_init_request: peer_info.latest_incoming_init_request,
+            latest_outgoing_init_request: peer_info.latest_outgoing_init_request,
+            verified_key_fingerprint: peer_info.verified_key_fingerprint,
+            healing: peer_info.healing,
+            replaced_session: None,
+        }
+    }
+}
+
+impl From<PeerInfoV4> for PeerInfo {
+    fn from(peer_info: PeerInfoV4) -> Self {
+        // sessions and pending announcements of the previous protocol version cannot be
+        // carried over: the session is healed with the latest known keys of the peer
+        let peer_public_keys = peer_info
+            .active_session
+            .as_ref()
+            .map(|session_info| session_info.session.peer_public_keys())
+            .or(peer_info
+                .latest_incoming_init_request
+                .as_ref()
+                .map(|request| &request.origin_public_keys));
+        let healing = peer_info
+            .healing
+            .or_else(|| peer_public_keys.map(|peer_public_keys| Healing::new(peer_public_keys, 0)));
+        Self {
+            active_session: None,
+            latest_incoming_init_request: None,
+            latest_outgoing_init_request: None,
+            verified_key_fingerprint: peer_info.verified_key_fingerprint,
+            healing,
+            replaced_session: None,
+        }
+    }
+}
+
+/// Serialized layout of a `SessionManager` whose peers are stored as `P`
+#[derive(Serialize, Deserialize)]
+struct SessionManagerState<P> {
+    config: SessionManagerConfig,
+    peers: HashMap<UserId, Box<P>>,
+}
+
+/// Upgrades the peers of a serialized `SessionManager` or `PeerInfo` from the layout `Old`
+/// to the layout `New`.
+fn upgrade_peers<Old, New>(
+    kind: StateKind,
+    state: Zeroizing<Vec<u8>>,
+) -> Result<Zeroizing<Vec<u8>>, SessionError>
+where
+    Old: DeserializeOwned,
+    New: Serialize + From<Old>,
+{
+    let upgraded_state = match kind {
+        StateKind::SessionManager => {
+            let (session_manager, _): (SessionManagerState<Old>, usize) =
+                bincode::serde::decode_from_slice(&state, bincode::config::standard())
+                    .map_err(|_| SessionError::StateMalformed)?;
+            let upgraded_session_manager = SessionManagerState {
+                config: session_manager.config,
+                peers: session_manager
+                    .peers
+                    .into_iter()
+                    .map(|(peer_id, peer_info)| (peer_id, Box::new(New::from(*peer_info))))
+                    .collect(),
+            };
+            bincode::serde::encode_to_vec(&upgraded_session_manager, bincode::config::standard())
+        }
+        StateKind::Peer => {
+            let (peer_info, _): (Old, usize) =
+                bincode::serde::decode_from_slice(&state, bincode::config::standard())
+                    .map_err(|_| SessionError::StateMalformed)?;
+            bincode::serde::encode_to_vec(New::from(peer_info), bincode::config::standard())
+        }
+        StateKind::GroupManager | StateKind::FragmentReassembler => return Ok(state),
+    };
+    upgraded_state
+        .map(Zeroizing::new)
+        .map_err(|_| SessionError::StateMalformed)
+}
+
+/// Upgrades a serialized `SessionManager` or `PeerInfo` from schema version 1 to 2,
+/// which records the verification of peers. No peer is verified after the upgrade.
+pub(crate) fn migrate_v1_to_v2(
+    kind: StateKind,
+    state: Zeroizing<Vec<u8>>,
+) -> Result<Zeroizing<Vec<u8>>, SessionError> {
+    upgrade_peers::<PeerInfoV1, PeerInfoV2>(kind, state)
+}
+
+/// Upgrades a serialized `SessionManager` or `PeerInfo` from schema version 2 to 3,
+/// which records the healing of broken sessions. No session is being healed after the upgrade.
+pub(crate) fn migrate_v2_to_v3(
+    kind: StateKind,
+    state: Zeroizing<Vec<u8>>,
+) -> Result<Zeroizing<Vec<u8>>, SessionError> {
+    upgrade_peers::<PeerInfoV2, PeerInfoV3>(kind, state)
+}
+
+/// Upgrades a serialized `SessionManager` or `PeerInfo` from schema version 3 to 4,
+/// which keeps replaced sessions. No session is replaced after the upgrade.
+pub(crate) fn migrate_v3_to_v4(
+    kind: StateKind,
+    state: Zeroizing<Vec<u8>>,
+) -> Result<Zeroizing<Vec<u8>>, SessionError> {
+    upgrade_peers::<PeerInfoV3, PeerInfoV4>(kind, state)
+}
+
+/// Upgrades a serialized `SessionManager` or `PeerInfo` from schema version 4 to 5,
+/// which moves to the session protocol version 2.
+///
+/// Sessions of the previous protocol version cannot be carried over, since their seeker
+/// keypairs were not derived from a chain. They are dropped along with the pending
+/// announcements, and healed by [`SessionManager::heal_sessions`], which announces anew
+/// to every peer that had a session.
+pub(crate) fn migrate_v4_to_v5(
+    kind: StateKind,
+    state: Zeroizing<Vec<u8>>,
+) -> Result<Zeroizing<Vec<u8>>, SessionError> {
+    upgrade_peers::<PeerInfoV4, PeerInfo>(kind, state)
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct SessionManager {
     config: SessionManagerConfig,
     peers: HashMap<UserId, Box<PeerInfo>>,
+    /// Source of the current time. Not persisted: restored managers use the system clock.
+    #[serde(skip, default = "default_clock")]
+    clock: Box<dyn Clock>,
+    /// Peers whose state changed since the last [`persist_to`](Self::persist_to).
+    #[serde(skip)]
+    dirty_peers: HashSet<UserId>,
+    /// Our secret keys from before our key rotations. Not persisted, like the clock.
+    #[serde(skip)]
+    previous_secret_keys: Vec<auth::UserSecretKeys>,
 }
 
 impl Zeroize for SessionManager {
     fn zeroize(&mut self) {
         self.peers.clear();
+        self.dirty_peers.clear();
+        self.previous_secret_keys.clear();
         self.config.zeroize();
     }
 }
@@ -188,40 +651,78 @@ impl ZeroizeOnDrop for SessionManager {}
 
 impl SessionManager {
     pub fn new(config: SessionManagerConfig) -> Self {
+        Self::with_clock(config, SystemClock)
+    }
+
+    /// Creates a session manager that reads the current time from `clock`.
+    ///
+    /// All timestamp checks (announcement and message age, session inactivity, keep-alive)
+    /// and the timestamps of our own announcements and messages use this clock.
+    pub fn with_clock(config: SessionManagerConfig, clock: impl Clock + 'static) -> Self {
         Self {
             config,
             peers: HashMap::new(),
+            clock: Box::new(clock),
+            dirty_peers: HashSet::new(),
+            previous_secret_keys: Vec::new(),
         }
     }
 
+    /// Replaces the clock used by this session manager.
+    ///
+    /// The clock is not part of the persisted state, so a manager restored with
+    /// [`from_encrypted_blob`](Self::from_encrypted_blob) uses [`SystemClock`] until this is called.
+    pub fn set_clock(&mut self, clock: impl Clock + 'static) {
+        self.clock = Box::new(clock);
+    }
+
+    /// Sets our secret keys from before our key rotations, newest first.
+    ///
+    /// After we rotate our keys, peers keep encrypting messages to our previous keys until they
+    /// process our [`auth::RotationCertificate`]. Incoming messages that cannot be decrypted with
+    /// our current secret keys are retried with these ones.
+    ///
+    /// Like the clock, they are not part of the persisted state and must be set again after
+    /// restoring the manager.
+    pub fn set_previous_secret_keys(&mut self, previous_secret_keys: Vec<auth::UserSecretKeys>) {
+        self.previous_secret_keys = previous_secret_keys;
+    }
+
     /// Deserializes a `SessionManager` from an encrypted blob.
     ///
     /// This method decrypts and deserializes a previously encrypted session manager state,
     /// allowing for secure persistence and restoration of session state. The encrypted blob
     /// must have been created using [`to_encrypted_blob`](Self::to_encrypted_blob) with the
-    /// same encryption key.
+    /// same encryption key. Blobs written by older versions are upgraded to the current format.
     ///
     /// # Arguments
     ///
     /// * `encrypted_blob` - The encrypted binary data containing the serialized session manager.
-    ///   The blob format is: `[nonce (12 bytes) || encrypted_data || auth_tag (16 bytes)]`
-    /// * `key` - The AES-256-GCM encryption key used to decrypt the blob. Must be the same key
+    ///   The blob format is:
+    ///   `[magic (4 bytes) || schema version (2 bytes) || nonce (16 bytes) || ciphertext]`.
+    ///   Blobs written before versioning have no magic and schema version.
+    /// * `key` - The AES-256-SIV encryption key used to decrypt the blob. Must be the same key
     ///   that was used to create the encrypted blob.
     ///
     /// # Returns
     ///
-    /// * `Some(SessionManager)` - If decryption and deserialization succeed
-    /// * `None` - If:
-    ///   - The blob is too short to contain a valid nonce
-    ///   - Decryption fails (wrong key, corrupted data, or failed authentication)
-    ///   - Deserialization fails (incompatible format or corrupted data)
+    /// The restored `SessionManager` if decryption and deserialization succeed.
+    /// It uses [`SystemClock`], see [`set_clock`](Self::set_clock). All its peers are
+    /// considered changed, so that [`persist_to`](Self::persist_to) writes them all.
+    ///
+    /// # Errors
+    ///
+    /// * [`SessionError::StateUndecryptable`] - If the blob is too short to contain a valid nonce,
+    ///   or decryption fails (wrong key, corrupted data, or failed authentication)
+    /// * [`SessionError::UnsupportedStateVersion`] - If the blob was written by a newer version
+    /// * [`SessionError::StateMalformed`] - If deserialization fails (incompatible format)
     ///
     /// # Security
     ///
-    /// - Uses AES-256-GCM for authenticated encryption, ensuring both confidentiality and integrity
+    /// - Uses AES-256-SIV for authenticated encryption, ensuring both confidentiality and integrity
     /// - The nonce is prepended to the ciphertext and is unique per encryption
     /// - All sensitive data is zeroized from memory when dropped
-    /// - Returns `None` on any error to avoid leaking information through error messages
+    /// - A wrong key and a corrupted blob are reported with the same error, as they cannot be told apart
     ///
     /// # Example
     ///
@@ -239,58 +740,136 @@ impl SessionManager {
     /// // Later, restore from encrypted blob
     /// let restored_manager = SessionManager::from_encrypted_blob(&encrypted_blob, &key).unwrap();
     /// ```
-    pub fn from_encrypted_blob(encrypted_blob: &[u8], key: &crypto_aead::Key) -> Option<Self> {
-        // read nonce
-        let nonce = {
-            let nonce_bytes: [u8; crypto_aead::NONCE_SIZE] = encrypted_blob
-                .get(..crypto_aead::NONCE_SIZE)?
-                .try_into()
-                .ok()?;
-            crypto_aead::Nonce::from(nonce_bytes)
-        };
-
-        // get ciphertext (everything after the nonce)
-        let ciphertext = encrypted_blob.get(crypto_aead::NONCE_SIZE..)?;
-
-        // decrypt
-        let decrypted_blob = Zeroizing::new(crypto_aead::decrypt(key, &nonce, ciphertext, b"")?);
+    pub fn from_encrypted_blob(
+        encrypted_blob: &[u8],
+        key: &crypto_aead::Key,
+    ) -> Result<Self, SessionError> {
+        // decrypt, falling back to the unversioned format of blobs written before versioning
+        let decrypted_blob =
+            state_format::open(encrypted_blob, key, b"", StateKind::SessionManager).or_else(
+                |error| match state_format::open_unversioned(encrypted_blob, key) {
+                    Err(SessionError::StateUndecryptable) => Err(error),
+                    unversioned => unversioned,
+                },
+            )?;
 
         // deserialize
-        let session_manager: Self =
+        let mut session_manager: Self =
             bincode::serde::decode_from_slice(&decrypted_blob, bincode::config::standard())
-                .ok()?
+                .map_err(|_| SessionError::StateMalformed)?
                 .0;
 
+        // nothing has been written to per-peer storage yet
+        session_manager.dirty_peers = session_manager.peers.keys().cloned().collect();
+
         // return
-        Some(session_manager)
+        Ok(session_manager)
     }
 
+    /// Serializes and encrypts the session manager into a versioned blob that can be restored
+    /// with [`from_encrypted_blob`](Self::from_encrypted_blob).
     pub fn to_encrypted_blob(&self, key: &crypto_aead::Key) -> Option<Vec<u8>> {
-        // generate nonce
-        let nonce = {
-            let mut nonce_bytes = [0u8; crypto_aead::NONCE_SIZE];
-            crypto_rng::fill_buffer(&mut nonce_bytes);
-            crypto_aead::Nonce::from(nonce_bytes)
-        };
-
         // serialize
         let serialized_blob =
             Zeroizing::new(bincode::serde::encode_to_vec(self, bincode::config::standard()).ok()?);
 
         // encrypt
-        let encrypted_blob =
-            Zeroizing::new(crypto_aead::encrypt(key, &nonce, &serialized_blob, b""));
+        Some(state_format::seal(&serialized_blob, key, b""))
+    }
+
+    /// Encrypts a serialized session manager into a blob, as
+    /// [`to_encrypted_blob`](Self::to_encrypted_blob) does.
+    ///
+    /// Only built for fuzzing, to exercise the deserialization of arbitrary states.
+    #[cfg(fuzzing)]
+    #[doc(hidden)]
+    pub fn seal_serialized_state(serialized_state: &[u8], key: &crypto_aead::Key) -> Vec<u8> {
+        state_format::seal(serialized_state, key, b"")
+    }
+
+    /// Restores a `SessionManager` from per-peer state written by [`persist_to`](Self::persist_to).
+    ///
+    /// The configuration is not part of the per-peer state and must be provided. The restored
+    /// manager uses [`SystemClock`], see [`set_clock`](Self::set_clock).
+    ///
+    /// # Errors
+    ///
+    /// * [`StorageError::Backend`] - If the peers cannot be read from the storage
+    /// * [`StorageError::State`] - If the state of a peer cannot be restored: see
+    ///   [`from_encrypted_blob`](Self::from_encrypted_blob) for the possible errors.
+    ///   A state stored under the wrong peer ID cannot be decrypted.
+    pub fn restore_from<S: SessionStorage>(
+        config: SessionManagerConfig,
+        storage: &S,
+        key: &crypto_aead::Key,
+    ) -> Result<Self, StorageError<S::Error>> {
+        let mut session_manager = Self::new(config);
+        for (peer_id, encrypted_state) in storage.read_peers().map_err(StorageError::Backend)? {
+            // decrypt, authenticating the peer ID
+            let decrypted_state =
+                state_format::open(&encrypted_state, key, peer_id.as_bytes(), StateKind::Peer)
+                    .map_err(StorageError::State)?;
+
+            // deserialize
+            let peer_info: PeerInfo =
+                bincode::serde::decode_from_slice(&decrypted_state, bincode::config::standard())
+                    .map_err(|_| StorageError::State(SessionError::StateMalformed))?
+                    .0;
+
+            session_manager.peers.insert(peer_id, Box::new(peer_info));
+        }
+        Ok(session_manager)
+    }
 
-        // combine nonce and encrypted blob
-        let combined_blob = [nonce.as_bytes().as_slice(), &encrypted_blob].concat();
+    /// Writes the state of the peers that changed since the previous call to `storage`,
+    /// and removes the discarded ones.
+    ///
+    /// Each peer state is encrypted separately with `key` and bound to its peer ID.
+    ///
+    /// # Returns
+    ///
+    /// The number of peers written or removed.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first backend error. The peers that were not persisted yet are kept
+    /// as changed, so that the next call retries them.
+    pub fn persist_to<S: SessionStorage>(
+        &mut self,
+        storage: &mut S,
+        key: &crypto_aead::Key,
+    ) -> Result<usize, S::Error> {
+        let dirty_peers: Vec<UserId> = self.dirty_peers.iter().cloned().collect();
+        for peer_id in &dirty_peers {
+            match self.peers.get(peer_id) {
+                Some(peer_info) => {
+                    // serialize
+                    let serialized_state = Zeroizing::new(
+                        bincode::serde::encode_to_vec(peer_info, bincode::config::standard())
+                            .expect("Failed to serialize peer state"),
+                    );
+
+                    // encrypt, binding the state to the peer ID
+                    let encrypted_state =
+                        state_format::seal(&serialized_state, key, peer_id.as_bytes());
+                    storage.write_peer(peer_id, &encrypted_state)?;
+                }
+                None => storage.delete_peer(peer_id)?,
+            }
+            self.dirty_peers.remove(peer_id);
+        }
+        Ok(dirty_peers.len())
+    }
 
-        Some(combined_blob)
+    /// Returns `true` if some peer state changed since the last [`persist_to`](Self::persist_to).
+    pub fn has_unpersisted_changes(&self) -> bool {
+        !self.dirty_peers.is_empty()
     }
 
     /// Returns the peer IDs that need a keep-alive message
     pub fn refresh(&mut self) -> Vec<UserId> {
         // check for expired announcements and sessions
-        let timestamp_now = timestamp_millis();
+        let timestamp_now = self.clock.now_millis();
         let oldest_message_timestamp =
             timestamp_now.saturating_sub(self.config.max_session_inactivity_millis);
         let keep_alive_timestamp =
@@ -303,18 +882,31 @@ impl SessionManager {
             if let Some(active_session) = &mut peer_info.active_session {
                 if active_session.last_incoming_message_timestamp < oldest_message_timestamp {
                     peer_info.active_session = None;
+                    self.dirty_peers.insert(peer_id.clone());
                 }
             }
+            if peer_info
+                .replaced_session
+                .as_ref()
+                .is_some_and(|replaced_session| {
+                    replaced_session.last_incoming_message_timestamp < oldest_message_timestamp
+                })
+            {
+                peer_info.replaced_session = None;
+                self.dirty_peers.insert(peer_id.clone());
+            }
 
             // announcement expiry
             if let Some(latest_incoming_init_request) = &peer_info.latest_incoming_init_request {
                 if latest_incoming_init_request.timestamp_millis < oldest_announcement_timestamp {
                     peer_info.latest_incoming_init_request = None;
+                    self.dirty_peers.insert(peer_id.clone());
                 }
             }
             if let Some(latest_outgoing_init_request) = &peer_info.latest_outgoing_init_request {
                 if latest_outgoing_init_request.timestamp_millis < oldest_announcement_timestamp {
                     peer_info.latest_outgoing_init_request = None;
+                    self.dirty_peers.insert(peer_id.clone());
                 }
             }
 
@@ -330,11 +922,89 @@ impl SessionManager {
         keep_alive_needed
     }
 
+    /// Produces the announcements that re-establish broken sessions, for the peers that are due.
+    ///
+    /// A session is broken when [`feed_incoming_message_board_read`](Self::feed_incoming_message_board_read)
+    /// rejects one of its messages, or when the peer announces again while we have no session
+    /// request of our own pending: the peer lost the session, so we answer it. Our session stays
+    /// active until the answer replaces it, and then keeps reading the messages the peer sent on
+    /// it until the peer uses the new session. Healing announcements are addressed to the keys
+    /// the session was established with, and carry no user data.
+    ///
+    /// Only broken sessions are healed. A session that expired for inactivity was closed by
+    /// both peers alike, since neither heard from the other: re-establishing it is up to the
+    /// application, which knows which contacts are still wanted. A saturated session is not
+    /// broken: it is still open and waits for the peer to acknowledge our messages.
+    ///
+    /// Answers are sent right away. Otherwise the first announcement is sent right away, and
+    /// each next one once the previous one expired (`max_incoming_announcement_age_millis`),
+    /// waiting twice as long each time, up to 5 announcements. Healing stops once a message
+    /// is received from the peer, so a session that breaks again before that keeps the backoff.
+    ///
+    /// When both peers heal at the same time, each one builds the new session from the
+    /// announcement of the other when it arrives, as for any session request: the backoff
+    /// keeps them from announcing again in the meantime, so they end up on the same session.
+    ///
+    /// Call it periodically, next to [`refresh`](Self::refresh).
+    ///
+    /// # Returns
+    ///
+    /// `(peer ID, announcement bytes)` pairs. The announcements must be published to the
+    /// announcement board like the ones of
+    /// [`establish_outgoing_session`](Self::establish_outgoing_session).
+    pub fn heal_sessions(
+        &mut self,
+        our_pk: &auth::UserPublicKeys,
+        our_sk: &auth::UserSecretKeys,
+    ) -> Vec<(UserId, Vec<u8>)> {
+        let timestamp_now = self.clock.now_millis();
+        let base_delay = self.config.max_incoming_announcement_age_millis;
+        let mut due_peers = Vec::new();
+        for (peer_id, peer_info) in self.peers.iter_mut() {
+            // a new session was established, and waits for the peer to use it
+            if peer_info.active_session.is_some() && !peer_info.has_unanswered_announcement() {
+                continue;
+            }
+            let Some(healing) = &mut peer_info.healing else {
+                continue;
+            };
+            if timestamp_now < healing.next_attempt_millis {
+                continue;
+            }
+
+            // give up once the last announcement went unanswered
+            if healing.attempts >= MAX_HEALING_ATTEMPTS {
+                peer_info.healing = None;
+                self.dirty_peers.insert(peer_id.clone());
+                continue;
+            }
+
+            let backoff = 1u128.checked_shl(healing.attempts).unwrap_or(u128::MAX);
+            healing.next_attempt_millis =
+                timestamp_now.saturating_add(base_delay.saturating_mul(backoff));
+            healing.attempts += 1;
+            due_peers.push((peer_id.clone(), healing.peer_public_keys.clone()));
+        }
+
+        // announce, which also marks the peers as changed
+        due_peers
+            .into_iter()
+            .map(|(peer_id, peer_pk)| {
+                let announcement_bytes =
+                    self.establish_outgoing_session(&peer_pk, our_pk, our_sk, Vec::new());
+                (peer_id, announcement_bytes)
+            })
+            .collect()
+    }
+
     /// Feeds an incoming announcement into the session manager.
     ///
     /// Processes an announcement received from the peer, extracting their public keys
     /// and any user data they included. If both peers have sent announcements, this
-    /// will automatically establish a bidirectional session.
+    /// will automatically establish a bidirectional session. If the peer announces again
+    /// while we have no session request pending, the peer lost its session:
+    /// [`heal_sessions`](Self::heal_sessions) answers right away. Our session stays active
+    /// until then.
     ///
     /// # Arguments
     ///
@@ -349,10 +1019,14 @@ impl SessionManager {
     /// - The timestamp when the announcement was created
     /// - The user data embedded in the announcement (can be empty)
     ///
-    /// Returns `None` if:
-    /// - The announcement is malformed or cannot be decrypted
-    /// - The announcement is too old or too far in the future
-    /// - The announcement is older than a previously received announcement from the same peer
+    /// # Errors
+    ///
+    /// - The errors of [`IncomingInitiationRequest::try_from`] if the announcement cannot be
+    ///   decrypted, parsed or authenticated
+    /// - [`AnnouncementError::TooOld`] / [`AnnouncementError::TooFarInFuture`] if the announcement
+    ///   timestamp is out of the configured bounds
+    /// - [`AnnouncementError::Outdated`] if the announcement is not newer than a previously
+    ///   received announcement from the same peer
     ///
     /// # Security Warning
     ///
@@ -370,7 +1044,7 @@ impl SessionManager {
     /// # Example
     ///
     /// ```ignore
-    /// if let Some(result) = manager.feed_incoming_announcement(
+    /// if let Ok(result) = manager.feed_incoming_announcement(
     ///     &announcement_bytes,
     ///     &our_pk,
     ///     &our_sk
@@ -385,34 +1059,35 @@ impl SessionManager {
         announcement_bytes: &[u8],
         our_pk: &auth::UserPublicKeys,
         our_sk: &auth::UserSecretKeys,
-    ) -> Option<AnnouncementResult> {
+    ) -> Result<AnnouncementResult, AnnouncementError> {
         // try to parse as incoming initiation request
         let (incoming_initiation_request, user_data) =
             IncomingInitiationRequest::try_from(announcement_bytes, our_pk, our_sk)?;
 
         // check if it is not too old or too much in the future
-        let cur_timestamp = timestamp_millis();
+        let cur_timestamp = self.clock.now_millis();
         if incoming_initiation_request.timestamp_millis
             < cur_timestamp.saturating_sub(self.config.max_incoming_announcement_age_millis)
         {
-            return None;
+            return Err(AnnouncementError::TooOld);
         }
         if incoming_initiation_request.timestamp_millis
             > cur_timestamp.saturating_add(self.config.max_incoming_announcement_future_millis)
         {
-            return None;
+            return Err(AnnouncementError::TooFarInFuture);
         }
 
         // compute peer ID
         let peer_id = incoming_initiation_request.origin_public_keys.derive_id();
 
-        // make sure that it is newer than the latest incoming initiation request we processed, otherwise ignore
+        // make sure that it is newer than the latest incoming initiation request we processed, otherwise ignore.
+        // The peer pairs its latest announcement with ours in the same way, see "Session Convergence"
         if let Some(peer_info) = self.peers.get(&peer_id) {
             if let Some(latest_incoming_init_request) = &peer_info.latest_incoming_init_request {
                 if incoming_initiation_request.timestamp_millis
                     <= latest_incoming_init_request.timestamp_millis
                 {
-                    return None;
+                    return Err(AnnouncementError::Outdated);
                 }
             }
         }
@@ -424,12 +1099,26 @@ impl SessionManager {
                 let new_session = Session::from_initiation_request_pair(
                     latest_outgoing_init_request,
                     &incoming_initiation_request,
+                    self.config.skipped_message_limits(),
                 );
-                peer_info.active_session = Some(SessionInfo {
+                let last_outgoing_message_timestamp = latest_outgoing_init_request.timestamp_millis;
+                peer_info.replace_session(SessionInfo {
                     session: new_session,
                     last_incoming_message_timestamp: incoming_initiation_request.timestamp_millis,
-                    last_outgoing_message_timestamp: latest_outgoing_init_request.timestamp_millis,
+                    last_outgoing_message_timestamp,
+                });
+            } else if peer_info.active_session.is_some() || peer_info.healing.is_some() {
+                // the peer started over while we had nothing pending: it lost our session.
+                // Answer right away, unless a healing in progress is backing off: it keeps its
+                // backoff but announces to the latest keys. Our session stays active until
+                // the answer replaces it.
+                let healing = peer_info.healing.get_or_insert_with(|| {
+                    Healing::new(
+                        &incoming_initiation_request.origin_public_keys,
+                        cur_timestamp,
+                    )
                 });
+                healing.peer_public_keys = incoming_initiation_request.origin_public_keys.clone();
             }
         }
 
@@ -437,9 +1126,11 @@ impl SessionManager {
         let announcer_public_keys = incoming_initiation_request.origin_public_keys.clone();
         let timestamp_millis = incoming_initiation_request.timestamp_millis;
         let peer_info = self.peers.entry(peer_id.clone()).or_default();
+        peer_info.clear_verification_if_keys_changed(&announcer_public_keys);
         peer_info.latest_incoming_init_request = Some(incoming_initiation_request);
+        self.dirty_peers.insert(peer_id);
 
-        Some(AnnouncementResult {
+        Ok(AnnouncementResult {
             announcer_public_keys,
             timestamp_millis,
             user_data,
@@ -484,6 +1175,8 @@ impl SessionManager {
     ///   session will be established immediately
     /// - If we haven't received their announcement yet, the session enters the
     ///   "SelfRequested" state and waits for the peer's announcement
+    /// - The announcement is timestamped after our previous pending one, if any, so that it
+    ///   replaces it on both sides (see "Session Convergence" in the module documentation)
     ///
     /// # Example
     ///
@@ -508,9 +1201,19 @@ impl SessionManager {
         // get peer ID
         let peer_id = peer_pk.derive_id();
 
-        // create outgoing initiation request
+        // create outgoing initiation request, newer than the previous one even within the same
+        // millisecond, so that the peer does not reject it as outdated and pairs it as we do
+        let not_before_millis = self
+            .peers
+            .get(&peer_id)
+            .and_then(|peer_info| peer_info.latest_outgoing_init_request.as_ref())
+            .map_or(0, |previous| previous.timestamp_millis.saturating_add(1));
+        let clock = NotBefore {
+            clock: self.clock.as_ref(),
+            not_before_millis,
+        };
         let (announcement_bytes, outgoing_initiation_request) =
-            OutgoingInitiationRequest::new(our_pk, our_sk, peer_pk, user_data);
+            OutgoingInitiationRequest::new(our_pk, our_sk, peer_pk, user_data, &clock);
 
         // check if we already have an incoming announcement from this peer
         if let Some(peer_info) = self.peers.get_mut(&peer_id) {
@@ -519,10 +1222,12 @@ impl SessionManager {
                 let new_session = Session::from_initiation_request_pair(
                     &outgoing_initiation_request,
                     latest_incoming_init_request,
+                    self.config.skipped_message_limits(),
                 );
-                peer_info.active_session = Some(SessionInfo {
+                let last_incoming_message_timestamp = latest_incoming_init_request.timestamp_millis;
+                peer_info.replace_session(SessionInfo {
                     session: new_session,
-                    last_incoming_message_timestamp: latest_incoming_init_request.timestamp_millis,
+                    last_incoming_message_timestamp,
                     last_outgoing_message_timestamp: outgoing_initiation_request.timestamp_millis,
                 });
             }
@@ -530,12 +1235,16 @@ impl SessionManager {
 
         // update the latest outgoing initiation request
         let peer_info = self.peers.entry(peer_id.clone()).or_default();
+        peer_info.clear_verification_if_keys_changed(peer_pk);
         peer_info.latest_outgoing_init_request = Some(outgoing_initiation_request);
+        self.dirty_peers.insert(peer_id);
         announcement_bytes
     }
 
     pub fn peer_discard(&mut self, peer_id: &UserId) {
-        self.peers.remove(peer_id);
+        if self.peers.remove(peer_id).is_some() {
+            self.dirty_peers.insert(peer_id.clone());
+        }
     }
 
     pub fn peer_session_status(&self, peer_id: &UserId) -> SessionStatus {
@@ -568,87 +1277,274 @@ impl SessionManager {
         self.peers.keys().cloned().collect()
     }
 
+    /// Returns the latest known long-term public keys of a peer.
+    ///
+    /// They are known once the peer has sent us an announcement. Returns `None` for unknown
+    /// peers, and for peers that only have our own session request pending.
+    pub fn peer_public_keys(&self, peer_id: &UserId) -> Option<auth::UserPublicKeys> {
+        self.peers.get(peer_id)?.public_keys().cloned()
+    }
+
+    /// Computes the safety number of a peer, to be compared out-of-band with the one
+    /// displayed by the peer before calling [`mark_peer_verified`](Self::mark_peer_verified).
+    ///
+    /// Returns `None` if the public keys of the peer are not known,
+    /// see [`peer_public_keys`](Self::peer_public_keys).
+    pub fn peer_safety_number(
+        &self,
+        peer_id: &UserId,
+        our_pk: &auth::UserPublicKeys,
+    ) -> Option<auth::SafetyNumber> {
+        let peer_info = self.peers.get(peer_id)?;
+        Some(auth::SafetyNumber::new(our_pk, peer_info.public_keys()?))
+    }
+
+    /// Records that the keys of a peer were verified out-of-band, typically by comparing
+    /// [`auth::SafetyNumber`]s.
+    ///
+    /// The verification is bound to `peer_pk`: it is cleared automatically when the peer
+    /// presents other keys.
+    ///
+    /// Returns `false`, and records nothing, if the peer is unknown or if `peer_pk` are not
+    /// its latest known keys.
+    pub fn mark_peer_verified(&mut self, peer_pk: &auth::UserPublicKeys) -> bool {
+        let peer_id = peer_pk.derive_id();
+        let Some(peer_info) = self.peers.get_mut(&peer_id) else {
+            return false;
+        };
+        let fingerprint = auth::key_fingerprint(peer_pk);
+        if let Some(known_keys) = peer_info.public_keys() {
+            if auth::key_fingerprint(known_keys) != fingerprint {
+                return false;
+            }
+        }
+        if peer_info.verified_key_fingerprint != Some(fingerprint) {
+            peer_info.verified_key_fingerprint = Some(fingerprint);
+            self.dirty_peers.insert(peer_id);
+        }
+        true
+    }
+
+    /// Clears the verification of a peer.
+    pub fn mark_peer_unverified(&mut self, peer_id: &UserId) {
+        if let Some(peer_info) = self.peers.get_mut(peer_id) {
+            if peer_info.verified_key_fingerprint.take().is_some() {
+                self.dirty_peers.insert(peer_id.clone());
+            }
+        }
+    }
+
+    /// Returns `true` if the keys of the peer were verified and have not changed since.
+    pub fn is_peer_verified(&self, peer_id: &UserId) -> bool {
+        self.peers
+            .get(peer_id)
+            .is_some_and(|peer_info| peer_info.verified_key_fingerprint.is_some())
+    }
+
+    /// Follows a peer to its rotated keys, after verifying the chain of
+    /// [`auth::RotationCertificate`]s it sent.
+    ///
+    /// The chain may start before the keys we know the peer by: the certificates before the one
+    /// rotating away from a known peer are skipped. The peer is then moved to its new ID along
+    /// with its session, pending announcements and verification. The session keeps its ratchet
+    /// state, and our following messages are encrypted to the new keys. The verification is kept,
+    /// since the verified keys signed the rotation.
+    ///
+    /// # Returns
+    ///
+    /// The new ID of the peer.
+    ///
+    /// # Errors
+    ///
+    /// - [`KeyRotationError::UnknownPeer`] if no certificate rotates away from a known peer,
+    ///   including when the rotation was already followed
+    /// - [`KeyRotationError::InvalidCertificate`] if a certificate from the known peer onwards is
+    ///   invalid, or if the chain is broken
+    /// - [`KeyRotationError::IdentityInUse`] if the rotated keys are those of another known peer
+    pub fn feed_key_rotation(
+        &mut self,
+        chain: &[auth::RotationCertificate],
+    ) -> Result<UserId, KeyRotationError> {
+        // find the rotation away from the keys of a known peer
+        let start = chain
+            .iter()
+            .position(|certificate| self.peers.contains_key(&certificate.old_id()))
+            .ok_or(KeyRotationError::UnknownPeer)?;
+        let chain = &chain[start..];
+
+        // verify the chain from there
+        let old_pk = chain[0].old_public_keys();
+        let new_pk = auth::verify_rotation_chain(old_pk, chain)
+            .ok_or(KeyRotationError::InvalidCertificate)?;
+        let old_id = old_pk.derive_id();
+        let new_id = new_pk.derive_id();
+        if self.peers.contains_key(&new_id) {
+            return Err(KeyRotationError::IdentityInUse);
+        }
+
+        // move the peer to its new ID
+        let mut peer_info = self
+            .peers
+            .remove(&old_id)
+            .expect("Rotated peer unexpectedly missing");
+        peer_info.rotate_public_keys(old_pk, new_pk);
+        self.peers.insert(new_id.clone(), peer_info);
+        self.dirty_peers.insert(old_id);
+        self.dirty_peers.insert(new_id.clone());
+        Ok(new_id)
+    }
+
     pub fn get_message_board_read_keys(&self) -> Vec<Vec<u8>> {
         let mut message_board_seekers = Vec::new();
         for (_peer_id, peer_info) in self.peers.iter() {
-            if let Some(active_session) = &peer_info.active_session {
-                message_board_seekers.push(active_session.session.next_peer_message_seeker());
+            for session_info in [&peer_info.active_session, &peer_info.replaced_session]
+                .into_iter()
+                .flatten()
+            {
+                message_board_seekers.extend(session_info.session.peer_message_seekers());
             }
         }
         message_board_seekers
     }
 
+    /// Feeds a message into the active session of the peer, or into its replaced session
+    /// if `replaced`.
+    ///
     /// returns (message id, message)
     fn inner_feed_incoming_msg(
         &mut self,
         peer_id: &UserId,
+        replaced: bool,
         seeker: &[u8],
         bytes: &[u8],
         our_sk: &auth::UserSecretKeys,
-    ) -> Option<FeedIncomingMessageOutput> {
+    ) -> Result<FeedIncomingMessageOutput, SessionError> {
+        let cur_timestamp = self.clock.now_millis();
+
         // try to decode message
-        let mut msg = None;
+        let mut msg = Err(SessionError::NoActiveSession);
         if let Some(peer_info) = self.peers.get_mut(peer_id) {
-            if let Some(active_session) = &mut peer_info.active_session {
-                msg = active_session
+            if let Some(session_info) = peer_info.session_mut(replaced) {
+                let mut decrypted = session_info
                     .session
-                    .try_feed_incoming_message(our_sk, seeker, bytes);
+                    .try_decrypt_incoming_message(our_sk, seeker, bytes);
+
+                // the peer may not have processed our key rotation yet.
+                // A failed decryption leaves the session untouched, so it can be retried.
+                for previous_sk in &self.previous_secret_keys {
+                    if decrypted.as_ref().err() != Some(&SessionError::DecryptionFailed) {
+                        break;
+                    }
+                    decrypted = session_info.session.try_decrypt_incoming_message(
+                        previous_sk,
+                        seeker,
+                        bytes,
+                    );
+                }
+
+                // check message timestamp (past, future) before the session receives it,
+                // so that a rejected message is not consumed
+                msg = decrypted.and_then(|decrypted| {
+                    if decrypted.timestamp()
+                        < cur_timestamp.saturating_sub(self.config.max_incoming_message_age_millis)
+                    {
+                        return Err(SessionError::MessageTooOld);
+                    }
+                    if decrypted.timestamp()
+                        > cur_timestamp
+                            .saturating_add(self.config.max_incoming_message_future_millis)
+                    {
+                        return Err(SessionError::MessageTooFarInFuture);
+                    }
+                    session_info.session.accept_incoming_message(decrypted)
+                });
             }
         }
         let msg = msg?;
 
-        // check message timestamp (past, future)
-        let cur_timestamp = timestamp_millis();
-        if msg.timestamp < cur_timestamp.saturating_sub(self.config.max_incoming_message_age_millis)
-        {
-            return None;
-        }
-        if msg.timestamp
-            > cur_timestamp.saturating_add(self.config.max_incoming_message_future_millis)
-        {
-            return None;
-        }
-
-        // check if the message timestamp is consistent with the latest one,
-        // and update the last incoming message timestamp
+        // update the last incoming message timestamp.
+        // Messages may be delivered out of order, so an older timestamp is not an inconsistency:
+        // replays are already rejected by the session since each message is only accepted once.
         if let Some(peer_info) = self.peers.get_mut(peer_id) {
-            if let Some(active_session) = &mut peer_info.active_session {
-                if msg.timestamp < active_session.last_incoming_message_timestamp {
-                    return None;
-                }
-                active_session.last_incoming_message_timestamp = msg.timestamp;
+            if let Some(session_info) = peer_info.session_mut(replaced) {
+                session_info.last_incoming_message_timestamp = session_info
+                    .last_incoming_message_timestamp
+                    .max(msg.timestamp);
+            }
+
+            // the peer is heard from over the active session: it is healed,
+            // and does not use the replaced session anymore
+            if !replaced {
+                peer_info.healing = None;
+                peer_info.replaced_session = None;
             }
         }
 
         // return the message
-        Some(msg)
+        Ok(msg)
     }
 
+    /// Feeds a message read from the message board under one of the seekers returned by
+    /// [`get_message_board_read_keys`](Self::get_message_board_read_keys).
+    ///
+    /// # Errors
+    ///
+    /// - [`SessionError::UnknownSeeker`] if no session expects a message under this seeker.
+    ///   Sessions are left untouched.
+    /// - [`SessionError::MessageTooOld`] or [`SessionError::MessageTooFarInFuture`] if the message
+    ///   was read but its timestamp is out of bounds. The session stays open and does not
+    ///   receive the message, so it can still be fed once its timestamp is within bounds.
+    /// - Any other [`SessionError`] if the message cannot be read by the session it was addressed
+    ///   to. That session has a problem and is closed. An active session is then healed by
+    ///   [`heal_sessions`](Self::heal_sessions), a replaced one is only dropped.
     pub fn feed_incoming_message_board_read(
         &mut self,
         seeker: &[u8],
         bytes: &[u8],
         our_sk: &auth::UserSecretKeys,
-    ) -> Option<FeedIncomingMessageOutput> {
-        // find the peer that has the seeker
-        let mut peer_id = None;
+    ) -> Result<FeedIncomingMessageOutput, SessionError> {
+        // find the peer that has the seeker, and whether it is for its replaced session
+        let mut found = None;
         for (p_id, peer_info) in self.peers.iter() {
-            if let Some(active_session) = &peer_info.active_session {
-                if active_session.session.next_peer_message_seeker() == seeker {
-                    peer_id = Some(p_id.clone());
-                    break;
-                }
+            let expects_seeker = |session_info: &Option<SessionInfo>| {
+                session_info
+                    .as_ref()
+                    .is_some_and(|session_info| session_info.expects_seeker(seeker))
+            };
+            if expects_seeker(&peer_info.active_session) {
+                found = Some((p_id.clone(), false));
+                break;
+            }
+            if expects_seeker(&peer_info.replaced_session) {
+                found = Some((p_id.clone(), true));
+                break;
             }
         }
-        let peer_id = peer_id?;
+        let (peer_id, replaced) = found.ok_or(SessionError::UnknownSeeker)?;
 
         // feed the message into the session
-        let msg = self.inner_feed_incoming_msg(&peer_id, seeker, bytes, our_sk);
-
-        // if the message is None here, it means the session has a problem: close it
-        if msg.is_none() {
+        let msg = self.inner_feed_incoming_msg(&peer_id, replaced, seeker, bytes, our_sk);
+        self.dirty_peers.insert(peer_id.clone());
+
+        // if the message cannot be read here, it means the session has a problem: close it and heal it.
+        // A healing in progress keeps its backoff.
+        // A timestamp out of bounds only rejects the message, the session read it fine.
+        if matches!(
+            msg,
+            Err(SessionError::MalformedMessage
+                | SessionError::InvalidSignature
+                | SessionError::DecryptionFailed)
+        ) {
+            let timestamp_now = self.clock.now_millis();
             if let Some(peer_info) = self.peers.get_mut(&peer_id) {
-                peer_info.active_session = None;
+                if replaced {
+                    // the active session is not affected
+                    peer_info.replaced_session = None;
+                } else if let Some(session_info) = peer_info.active_session.take() {
+                    peer_info.healing.get_or_insert_with(|| {
+                        Healing::new(session_info.session.peer_public_keys(), timestamp_now)
+                    });
+                }
             }
         }
 
@@ -656,40 +1552,64 @@ impl SessionManager {
         msg
     }
 
+    /// Returns how many messages can be sent to a peer before its session is saturated.
+    pub(crate) fn send_capacity(&self, peer_id: &UserId) -> Result<u64, SessionError> {
+        let active_session = self
+            .peers
+            .get(peer_id)
+            .and_then(|peer_info| peer_info.active_session.as_ref())
+            .ok_or(SessionError::NoActiveSession)?;
+        Ok(self
+            .config
+            .max_session_lag_length
+            .saturating_sub(active_session.session.lag_length()))
+    }
+
     /// Sends a message to a peer through their active session.
     ///
     /// # Returns
     ///
-    /// - `Some(SendOutgoingMessageOutput)` if the message was successfully prepared for sending
-    /// - `None` if there's no active session with the peer or if the session lag exceeds the configured maximum
+    /// The seeker and data to post on the message board.
+    ///
+    /// # Errors
+    ///
+    /// - [`SessionError::NoActiveSession`] if there's no active session with the peer
+    /// - [`SessionError::Saturated`] if the session lag exceeds the configured maximum
     ///
     /// # Behavior
     ///
     /// This method will check the session lag length before sending. If the number of unacknowledged
-    /// messages exceeds `max_session_lag_length`, it will return `None` to prevent overwhelming the peer.
+    /// messages exceeds `max_session_lag_length`, it will return an error to prevent overwhelming the peer.
     pub fn send_message(
         &mut self,
         peer_id: &UserId,
         message: &[u8],
-    ) -> Option<SendOutgoingMessageOutput> {
+    ) -> Result<SendOutgoingMessageOutput, SessionError> {
         // get the session and send
         if let Some(peer_info) = self.peers.get_mut(peer_id) {
             if let Some(active_session) = &mut peer_info.active_session {
                 if active_session.session.lag_length() >= self.config.max_session_lag_length {
-                    return None;
+                    return Err(SessionError::Saturated);
                 }
-                let send_result = active_session.session.send_outgoing_message(message);
+                let send_result = active_session
+                    .session
+                    .send_outgoing_message(message, self.clock.as_ref());
                 active_session.last_outgoing_message_timestamp = send_result.timestamp;
-                return Some(send_result);
+                self.dirty_peers.insert(peer_id.clone());
+                return Ok(send_result);
             }
         }
-        None
+        Err(SessionError::NoActiveSession)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{clock::ManualClock, storage::MemoryStorage};
+
+    /// Arbitrary starting time for tests driven by a [`ManualClock`]
+    const T0: u128 = 1_700_000_000_000;
 
     fn generate_test_keypair() -> (auth::UserPublicKeys, auth::UserSecretKeys) {
         // Generate a random passphrase for testing
@@ -708,6 +1628,8 @@ mod tests {
             max_session_inactivity_millis: 3_600_000,
             keep_alive_interval_millis: 60_000,
             max_session_lag_length: 100,
+            max_skipped_messages: 16,
+            max_stored_skipped_messages: 128,
         }
     }
 
@@ -741,8 +1663,12 @@ mod tests {
             bob_manager.establish_outgoing_session(&alice_pk, &bob_pk, &bob_sk, vec![]);
 
         // Feed announcements
-        bob_manager.feed_incoming_announcement(&alice_announcement, &bob_pk, &bob_sk);
-        alice_manager.feed_incoming_announcement(&bob_announcement, &alice_pk, &alice_sk);
+        bob_manager
+            .feed_incoming_announcement(&alice_announcement, &bob_pk, &bob_sk)
+            .expect("Failed to process announcement");
+        alice_manager
+            .feed_incoming_announcement(&bob_announcement, &alice_pk, &alice_sk)
+            .expect("Failed to process announcement");
 
         // Check session status
         let alice_id = alice_pk.derive_id();
@@ -838,7 +1764,9 @@ mod tests {
             peer_manager.establish_outgoing_session(&our_pk, &peer_pk, &peer_sk, vec![]);
 
         // We receive peer's announcement
-        manager.feed_incoming_announcement(&peer_announcement, &our_pk, &our_sk);
+        manager
+            .feed_incoming_announcement(&peer_announcement, &our_pk, &our_sk)
+            .expect("Failed to process announcement");
 
         assert!(matches!(
             manager.peer_session_status(&peer_id),
@@ -875,8 +1803,12 @@ mod tests {
         let bob_announcement =
             bob_manager.establish_outgoing_session(&alice_pk, &bob_pk, &bob_sk, vec![]);
 
-        bob_manager.feed_incoming_announcement(&alice_announcement, &bob_pk, &bob_sk);
-        alice_manager.feed_incoming_announcement(&bob_announcement, &alice_pk, &alice_sk);
+        bob_manager
+            .feed_incoming_announcement(&alice_announcement, &bob_pk, &bob_sk)
+            .expect("Failed to process announcement");
+        alice_manager
+            .feed_incoming_announcement(&bob_announcement, &alice_pk, &alice_sk)
+            .expect("Failed to process announcement");
 
         let bob_id = bob_pk.derive_id();
         let alice_id = alice_pk.derive_id();
@@ -923,14 +1855,26 @@ mod tests {
             charlie_manager.establish_outgoing_session(&alice_pk, &charlie_pk, &charlie_sk, vec![]);
 
         // Complete handshakes
-        bob_manager.feed_incoming_announcement(&alice_to_bob, &bob_pk, &bob_sk);
-        charlie_manager.feed_incoming_announcement(&alice_to_charlie, &charlie_pk, &charlie_sk);
-        alice_manager.feed_incoming_announcement(&bob_to_alice, &alice_pk, &alice_sk);
-        alice_manager.feed_incoming_announcement(&charlie_to_alice, &alice_pk, &alice_sk);
-
-        // Alice should have 2 read keys (one for Bob, one for Charlie)
+        bob_manager
+            .feed_incoming_announcement(&alice_to_bob, &bob_pk, &bob_sk)
+            .expect("Failed to process announcement");
+        charlie_manager
+            .feed_incoming_announcement(&alice_to_charlie, &charlie_pk, &charlie_sk)
+            .expect("Failed to process announcement");
+        alice_manager
+            .feed_incoming_announcement(&bob_to_alice, &alice_pk, &alice_sk)
+            .expect("Failed to process announcement");
+        alice_manager
+            .feed_incoming_announcement(&charlie_to_alice, &alice_pk, &alice_sk)
+            .expect("Failed to process announcement");
+
+        // Alice watches the upcoming message window of both Bob and Charlie
+        let config = create_test_config();
         let read_keys = alice_manager.get_message_board_read_keys();
-        assert_eq!(read_keys.len(), 2);
+        assert_eq!(
+            read_keys.len() as u64,
+            2 * (config.max_skipped_messages + 1)
+        );
     }
 
     #[test]
@@ -945,7 +1889,7 @@ mod tests {
         let message = create_test_message(b"test");
         let result = manager.send_message(&peer_id, &message);
 
-        assert!(result.is_none());
+        assert_eq!(result.err(), Some(SessionError::NoActiveSession));
     }
 
     #[test]
@@ -963,8 +1907,12 @@ mod tests {
         let bob_announcement =
             bob_manager.establish_outgoing_session(&alice_pk, &bob_pk, &bob_sk, vec![]);
 
-        bob_manager.feed_incoming_announcement(&alice_announcement, &bob_pk, &bob_sk);
-        alice_manager.feed_incoming_announcement(&bob_announcement, &alice_pk, &alice_sk);
+        bob_manager
+            .feed_incoming_announcement(&alice_announcement, &bob_pk, &bob_sk)
+            .expect("Failed to process announcement");
+        alice_manager
+            .feed_incoming_announcement(&bob_announcement, &alice_pk, &alice_sk)
+            .expect("Failed to process announcement");
 
         let bob_id = bob_pk.derive_id();
         let alice_id = alice_pk.derive_id();
@@ -1003,7 +1951,12 @@ mod tests {
             peer_manager.establish_outgoing_session(&other_pk, &peer_pk, &peer_sk, vec![]);
 
         // Try to feed with our keys (should be ignored)
-        manager.feed_incoming_announcement(&announcement, &our_pk, &our_sk);
+        assert_eq!(
+            manager
+                .feed_incoming_announcement(&announcement, &our_pk, &our_sk)
+                .err(),
+            Some(AnnouncementError::Undecryptable)
+        );
 
         // No peer should be added
         assert_eq!(manager.peer_list().len(), 0);
@@ -1018,7 +1971,12 @@ mod tests {
         let garbage = b"this is not a valid announcement";
 
         // Should be ignored without crashing
-        manager.feed_incoming_announcement(garbage, &our_pk, &our_sk);
+        assert_eq!(
+            manager
+                .feed_incoming_announcement(garbage, &our_pk, &our_sk)
+                .err(),
+            Some(AnnouncementError::Malformed)
+        );
 
         assert_eq!(manager.peer_list().len(), 0);
     }
@@ -1028,8 +1986,9 @@ mod tests {
         let mut config = create_test_config();
         config.max_incoming_announcement_age_millis = 1000; // 1 second
 
-        let mut alice_manager = SessionManager::new(config);
-        let mut bob_manager = SessionManager::new(create_test_config());
+        let clock = ManualClock::new(T0);
+        let mut alice_manager = SessionManager::with_clock(config, clock.clone());
+        let mut bob_manager = SessionManager::with_clock(create_test_config(), clock.clone());
 
         let (alice_pk, alice_sk) = generate_test_keypair();
         let (bob_pk, bob_sk) = generate_test_keypair();
@@ -1038,11 +1997,16 @@ mod tests {
         let bob_announcement =
             bob_manager.establish_outgoing_session(&alice_pk, &bob_pk, &bob_sk, vec![]);
 
-        // Wait for announcement to become too old
-        std::thread::sleep(std::time::Duration::from_millis(1100));
+        // Let the announcement become too old
+        clock.advance(1100);
 
         // Alice tries to process old announcement
-        alice_manager.feed_incoming_announcement(&bob_announcement, &alice_pk, &alice_sk);
+        assert_eq!(
+            alice_manager
+                .feed_incoming_announcement(&bob_announcement, &alice_pk, &alice_sk)
+                .err(),
+            Some(AnnouncementError::TooOld)
+        );
 
         // Should not create peer entry or should be in appropriate state
         let bob_id = bob_pk.derive_id();
@@ -1076,18 +2040,22 @@ mod tests {
         let bob_announcement =
             bob_manager.establish_outgoing_session(&alice_pk, &bob_pk, &bob_sk, vec![]);
 
-        bob_manager.feed_incoming_announcement(&alice_announcement, &bob_pk, &bob_sk);
-        alice_manager.feed_incoming_announcement(&bob_announcement, &alice_pk, &alice_sk);
+        bob_manager
+            .feed_incoming_announcement(&alice_announcement, &bob_pk, &bob_sk)
+            .expect("Failed to process announcement");
+        alice_manager
+            .feed_incoming_announcement(&bob_announcement, &alice_pk, &alice_sk)
+            .expect("Failed to process announcement");
 
         let bob_id = bob_pk.derive_id();
 
         // Send messages until saturated
         let msg1 = create_test_message(b"msg1");
-        alice_manager.send_message(&bob_id, &msg1);
+        let _ = alice_manager.send_message(&bob_id, &msg1);
         let msg2 = create_test_message(b"msg2");
-        alice_manager.send_message(&bob_id, &msg2);
+        let _ = alice_manager.send_message(&bob_id, &msg2);
         let msg3 = create_test_message(b"msg3");
-        alice_manager.send_message(&bob_id, &msg3);
+        let _ = alice_manager.send_message(&bob_id, &msg3);
 
         // Check if saturated
         let status = alice_manager.peer_session_status(&bob_id);
@@ -1096,7 +2064,10 @@ mod tests {
         // Try to send another message (should fail)
         let msg4 = create_test_message(b"msg4");
         let result = alice_manager.send_message(&bob_id, &msg4);
-        assert!(result.is_none());
+        assert_eq!(result.err(), Some(SessionError::Saturated));
+
+        // A saturated session is still open, so it is not healed
+        assert!(alice_manager.heal_sessions(&alice_pk, &alice_sk).is_empty());
     }
 
     #[test]
@@ -1109,7 +2080,7 @@ mod tests {
         let message_bytes = b"some encrypted data";
 
         let result = manager.feed_incoming_message_board_read(wrong_seeker, message_bytes, &our_sk);
-        assert!(result.is_none());
+        assert_eq!(result.err(), Some(SessionError::UnknownSeeker));
     }
 
     #[test]
@@ -1133,8 +2104,12 @@ mod tests {
             bob_manager.establish_outgoing_session(&alice_pk, &bob_pk, &bob_sk, vec![]);
 
         // Alice receives both (newer should be kept)
-        alice_manager.feed_incoming_announcement(&bob_announcement1, &alice_pk, &alice_sk);
-        alice_manager.feed_incoming_announcement(&bob_announcement2, &alice_pk, &alice_sk);
+        alice_manager
+            .feed_incoming_announcement(&bob_announcement1, &alice_pk, &alice_sk)
+            .expect("Failed to process announcement");
+        alice_manager
+            .feed_incoming_announcement(&bob_announcement2, &alice_pk, &alice_sk)
+            .expect("Failed to process announcement");
 
         let bob_id = bob_pk.derive_id();
         assert!(matches!(
@@ -1166,10 +2141,12 @@ mod tests {
             bob_manager.establish_outgoing_session(&alice_pk, &bob_pk, &bob_sk, vec![]);
 
         // Alice receives newer first
-        alice_manager.feed_incoming_announcement(&bob_announcement2, &alice_pk, &alice_sk);
+        alice_manager
+            .feed_incoming_announcement(&bob_announcement2, &alice_pk, &alice_sk)
+            .expect("Failed to process announcement");
 
         // Then receives older (should be ignored)
-        alice_manager.feed_incoming_announcement(&bob_announcement1, &alice_pk, &alice_sk);
+        let _ = alice_manager.feed_incoming_announcement(&bob_announcement1, &alice_pk, &alice_sk);
 
         // Should still have the peer
         let _bob_id = bob_pk.derive_id();
@@ -1191,8 +2168,12 @@ mod tests {
         let bob_announcement =
             bob_manager.establish_outgoing_session(&alice_pk, &bob_pk, &bob_sk, vec![]);
 
-        bob_manager.feed_incoming_announcement(&alice_announcement, &bob_pk, &bob_sk);
-        alice_manager.feed_incoming_announcement(&bob_announcement, &alice_pk, &alice_sk);
+        bob_manager
+            .feed_incoming_announcement(&alice_announcement, &bob_pk, &bob_sk)
+            .expect("Failed to process announcement");
+        alice_manager
+            .feed_incoming_announcement(&bob_announcement, &alice_pk, &alice_sk)
+            .expect("Failed to process announcement");
 
         let bob_id = bob_pk.derive_id();
         assert!(matches!(
@@ -1216,8 +2197,12 @@ mod tests {
         let bob_announcement =
             bob_manager.establish_outgoing_session(&alice_pk, &bob_pk, &bob_sk, vec![]);
 
-        bob_manager.feed_incoming_announcement(&alice_announcement, &bob_pk, &bob_sk);
-        alice_manager.feed_incoming_announcement(&bob_announcement, &alice_pk, &alice_sk);
+        bob_manager
+            .feed_incoming_announcement(&alice_announcement, &bob_pk, &bob_sk)
+            .expect("Failed to process announcement");
+        alice_manager
+            .feed_incoming_announcement(&bob_announcement, &alice_pk, &alice_sk)
+            .expect("Failed to process announcement");
 
         let bob_id = bob_pk.derive_id();
         let alice_id = alice_pk.derive_id();
@@ -1247,6 +2232,84 @@ mod tests {
         assert!(!received_reply.newly_acknowledged_self_seekers.is_empty());
     }
 
+    #[test]
+    fn test_out_of_order_messages_keep_session_active() {
+        let config = create_test_config();
+        let mut alice_manager = SessionManager::new(config);
+        let mut bob_manager = SessionManager::new(create_test_config());
+
+        let (alice_pk, alice_sk) = generate_test_keypair();
+        let (bob_pk, bob_sk) = generate_test_keypair();
+
+        // Establish sessions
+        let alice_announcement =
+            alice_manager.establish_outgoing_session(&bob_pk, &alice_pk, &alice_sk, vec![]);
+        let bob_announcement =
+            bob_manager.establish_outgoing_session(&alice_pk, &bob_pk, &bob_sk, vec![]);
+
+        bob_manager
+            .feed_incoming_announcement(&alice_announcement, &bob_pk, &bob_sk)
+            .expect("Failed to process announcement");
+        alice_manager
+            .feed_incoming_announcement(&bob_announcement, &alice_pk, &alice_sk)
+            .expect("Failed to process announcement");
+
+        let bob_id = bob_pk.derive_id();
+        let alice_id = alice_pk.derive_id();
+
+        // Alice sends three messages
+        let output1 = alice_manager
+            .send_message(&bob_id, &create_test_message(b"msg1"))
+            .unwrap();
+        let output2 = alice_manager
+            .send_message(&bob_id, &create_test_message(b"msg2"))
+            .unwrap();
+        let output3 = alice_manager
+            .send_message(&bob_id, &create_test_message(b"msg3"))
+            .unwrap();
+
+        // All of them are watched by Bob
+        let bob_seekers = bob_manager.get_message_board_read_keys();
+        assert!(bob_seekers.contains(&output1.seeker));
+        assert!(bob_seekers.contains(&output2.seeker));
+        assert!(bob_seekers.contains(&output3.seeker));
+
+        // Bob receives them out of order
+        let received3 = bob_manager
+            .feed_incoming_message_board_read(&output3.seeker, &output3.data, &bob_sk)
+            .expect("Failed to receive msg3");
+        assert_eq!(received3.message.as_slice(), b"msg3");
+        let received1 = bob_manager
+            .feed_incoming_message_board_read(&output1.seeker, &output1.data, &bob_sk)
+            .expect("Failed to receive msg1");
+        assert_eq!(received1.message.as_slice(), b"msg1");
+        let received2 = bob_manager
+            .feed_incoming_message_board_read(&output2.seeker, &output2.data, &bob_sk)
+            .expect("Failed to receive msg2");
+        assert_eq!(received2.message.as_slice(), b"msg2");
+
+        // Replaying a message fails but does not close the session
+        assert_eq!(
+            bob_manager
+                .feed_incoming_message_board_read(&output2.seeker, &output2.data, &bob_sk)
+                .err(),
+            Some(SessionError::UnknownSeeker)
+        );
+        assert!(matches!(
+            bob_manager.peer_session_status(&alice_id),
+            SessionStatus::Active
+        ));
+
+        // The conversation continues normally
+        let reply_output = bob_manager
+            .send_message(&alice_id, &create_test_message(b"reply"))
+            .unwrap();
+        let received_reply = alice_manager
+            .feed_incoming_message_board_read(&reply_output.seeker, &reply_output.data, &alice_sk)
+            .expect("Failed to receive reply");
+        assert_eq!(received_reply.message.as_slice(), b"reply");
+    }
+
     #[test]
     fn test_corrupted_message_closes_session() {
         let config = create_test_config();
@@ -1262,22 +2325,33 @@ mod tests {
         let bob_announcement =
             bob_manager.establish_outgoing_session(&alice_pk, &bob_pk, &bob_sk, vec![]);
 
-        bob_manager.feed_incoming_announcement(&alice_announcement, &bob_pk, &bob_sk);
-        alice_manager.feed_incoming_announcement(&bob_announcement, &alice_pk, &alice_sk);
+        bob_manager
+            .feed_incoming_announcement(&alice_announcement, &bob_pk, &bob_sk)
+            .expect("Failed to process announcement");
+        alice_manager
+            .feed_incoming_announcement(&bob_announcement, &alice_pk, &alice_sk)
+            .expect("Failed to process announcement");
 
         let _bob_id = bob_pk.derive_id();
 
         // Get Bob's expected seeker
         let bob_seekers = bob_manager.get_message_board_read_keys();
-        assert_eq!(bob_seekers.len(), 1);
+        assert_eq!(
+            bob_seekers.len() as u64,
+            create_test_config().max_skipped_messages + 1
+        );
         let bob_seeker = &bob_seekers[0];
 
         // Feed corrupted message
         let corrupted = b"corrupted message data";
         let result = bob_manager.feed_incoming_message_board_read(bob_seeker, corrupted, &bob_sk);
 
-        // Should return None and close session
-        assert!(result.is_none());
+        // Should be rejected and close the session
+        assert_eq!(result.err(), Some(SessionError::MalformedMessage));
+        assert!(matches!(
+            bob_manager.peer_session_status(&alice_pk.derive_id()),
+            SessionStatus::Killed
+        ));
     }
 
     #[test]
@@ -1296,7 +2370,9 @@ mod tests {
         let mut charlie_manager = SessionManager::new(create_test_config());
         let charlie_announcement =
             charlie_manager.establish_outgoing_session(&alice_pk, &charlie_pk, &charlie_sk, vec![]);
-        alice_manager.feed_incoming_announcement(&charlie_announcement, &alice_pk, &alice_sk);
+        alice_manager
+            .feed_incoming_announcement(&charlie_announcement, &alice_pk, &alice_sk)
+            .expect("Failed to process announcement");
 
         // Alice should have 2 peers
         let peer_list = alice_manager.peer_list();
@@ -1419,7 +2495,11 @@ mod tests {
 
         // Try to decrypt with key2 (should fail)
         let result = SessionManager::from_encrypted_blob(&encrypted_blob, &key2);
-        assert!(result.is_none(), "Decryption with wrong key should fail");
+        assert_eq!(
+            result.err(),
+            Some(SessionError::StateUndecryptable),
+            "Decryption with wrong key should fail"
+        );
     }
 
     #[test]
@@ -1487,7 +2567,7 @@ mod tests {
         let mut bob_manager = SessionManager::new(create_test_config());
         let result = bob_manager.feed_incoming_announcement(&alice_announcement, &bob_pk, &bob_sk);
 
-        assert!(result.is_some());
+        assert!(result.is_ok());
         let result = result.unwrap();
 
         // Verify the user data matches
@@ -1517,7 +2597,7 @@ mod tests {
         let mut bob_manager = SessionManager::new(create_test_config());
         let result = bob_manager.feed_incoming_announcement(&alice_announcement, &bob_pk, &bob_sk);
 
-        assert!(result.is_some());
+        assert!(result.is_ok());
         let result = result.unwrap();
 
         // Verify the user data is empty
@@ -1552,7 +2632,7 @@ mod tests {
         let mut bob_manager = SessionManager::new(create_test_config());
         let result = bob_manager.feed_incoming_announcement(&alice_announcement, &bob_pk, &bob_sk);
 
-        assert!(result.is_some());
+        assert!(result.is_ok());
         let result = result.unwrap();
 
         // Verify the user data matches
@@ -1597,13 +2677,13 @@ mod tests {
         // Bob receives Alice's announcement
         let bob_result =
             bob_manager.feed_incoming_announcement(&alice_announcement, &bob_pk, &bob_sk);
-        assert!(bob_result.is_some());
+        assert!(bob_result.is_ok());
         assert_eq!(bob_result.as_ref().unwrap().user_data, alice_user_data);
 
         // Alice receives Bob's announcement
         let alice_result =
             alice_manager.feed_incoming_announcement(&bob_announcement, &alice_pk, &alice_sk);
-        assert!(alice_result.is_some());
+        assert!(alice_result.is_ok());
         assert_eq!(alice_result.as_ref().unwrap().user_data, bob_user_data);
 
         // Verify sessions are established
@@ -1642,7 +2722,7 @@ mod tests {
         let mut bob_manager = SessionManager::new(create_test_config());
         let result = bob_manager.feed_incoming_announcement(&alice_announcement, &bob_pk, &bob_sk);
 
-        assert!(result.is_some());
+        assert!(result.is_ok());
         let result = result.unwrap();
 
         // Verify the user data matches
@@ -1675,10 +2755,14 @@ mod tests {
             bob_manager.establish_outgoing_session(&alice_pk, &bob_pk, &bob_sk, vec![]);
 
         // Alice receives Bob's announcement B
-        alice_manager.feed_incoming_announcement(&announcement_b, &alice_pk, &alice_sk);
+        alice_manager
+            .feed_incoming_announcement(&announcement_b, &alice_pk, &alice_sk)
+            .expect("Failed to process announcement");
 
         // Bob receives Alice's announcement A
-        bob_manager.feed_incoming_announcement(&announcement_a, &bob_pk, &bob_sk);
+        bob_manager
+            .feed_incoming_announcement(&announcement_a, &bob_pk, &bob_sk)
+            .expect("Failed to process announcement");
 
         // Verify both have active sessions
         assert!(
@@ -1726,7 +2810,9 @@ mod tests {
             alice_manager.establish_outgoing_session(&bob_pk, &alice_pk, &alice_sk, vec![]);
 
         // Bob receives announcement C
-        bob_manager.feed_incoming_announcement(&announcement_c, &bob_pk, &bob_sk);
+        bob_manager
+            .feed_incoming_announcement(&announcement_c, &bob_pk, &bob_sk)
+            .expect("Failed to process announcement");
 
         // Verify both still have active sessions
         assert!(
@@ -1774,7 +2860,9 @@ mod tests {
             bob_manager.establish_outgoing_session(&alice_pk, &bob_pk, &bob_sk, vec![]);
 
         // Alice receives announcement D
-        alice_manager.feed_incoming_announcement(&announcement_d, &alice_pk, &alice_sk);
+        alice_manager
+            .feed_incoming_announcement(&announcement_d, &alice_pk, &alice_sk)
+            .expect("Failed to process announcement");
 
         // Verify both still have active sessions
         assert!(
@@ -1817,4 +2905,1365 @@ mod tests {
             .expect("Alice should receive message on newest session");
         assert_eq!(received6.message.as_slice(), b"Hi Alice from D-C session!");
     }
+
+    /// Helper to establish an active session between Alice and Bob at `T0`,
+    /// with each manager reading the time from its own clock
+    fn establish_session_with_clocks(
+        alice_clock: &ManualClock,
+        bob_clock: &ManualClock,
+    ) -> (
+        SessionManager,
+        auth::UserPublicKeys,
+        auth::UserSecretKeys,
+        SessionManager,
+        auth::UserPublicKeys,
+        auth::UserSecretKeys,
+    ) {
+        alice_clock.set(T0);
+        bob_clock.set(T0);
+        let mut alice_manager =
+            SessionManager::with_clock(create_test_config(), alice_clock.clone());
+        let mut bob_manager = SessionManager::with_clock(create_test_config(), bob_clock.clone());
+
+        let (alice_pk, alice_sk) = generate_test_keypair();
+        let (bob_pk, bob_sk) = generate_test_keypair();
+
+        let alice_announcement =
+            alice_manager.establish_outgoing_session(&bob_pk, &alice_pk, &alice_sk, vec![]);
+        let bob_announcement =
+            bob_manager.establish_outgoing_session(&alice_pk, &bob_pk, &bob_sk, vec![]);
+        alice_manager
+            .feed_incoming_announcement(&bob_announcement, &alice_pk, &alice_sk)
+            .expect("Failed to process announcement");
+        bob_manager
+            .feed_incoming_announcement(&alice_announcement, &bob_pk, &bob_sk)
+            .expect("Failed to process announcement");
+
+        (
+            alice_manager,
+            alice_pk,
+            alice_sk,
+            bob_manager,
+            bob_pk,
+            bob_sk,
+        )
+    }
+
+    #[test]
+    fn test_announcement_age_bounds() {
+        // max age 60s, max future 5s
+        let alice_clock = ManualClock::new(T0);
+        let bob_clock = ManualClock::new(T0);
+        let mut alice_manager =
+            SessionManager::with_clock(create_test_config(), alice_clock.clone());
+        let mut bob_manager = SessionManager::with_clock(create_test_config(), bob_clock.clone());
+
+        let (alice_pk, alice_sk) = generate_test_keypair();
+        let (bob_pk, bob_sk) = generate_test_keypair();
+
+        // Announcement created at T0
+        let old_announcement =
+            bob_manager.establish_outgoing_session(&alice_pk, &bob_pk, &bob_sk, vec![]);
+
+        // One millisecond past the maximum age: rejected
+        alice_clock.set(T0 + 60_001);
+        assert_eq!(
+            alice_manager
+                .feed_incoming_announcement(&old_announcement, &alice_pk, &alice_sk)
+                .err(),
+            Some(AnnouncementError::TooOld)
+        );
+
+        // Exactly the maximum age: accepted
+        alice_clock.set(T0 + 60_000);
+        let result = alice_manager
+            .feed_incoming_announcement(&old_announcement, &alice_pk, &alice_sk)
+            .expect("Announcement at the maximum age should be accepted");
+        assert_eq!(result.timestamp_millis, T0);
+
+        // Bob's clock runs ahead of Alice's
+        bob_clock.set(T0 + 100_000);
+        let future_announcement =
+            bob_manager.establish_outgoing_session(&alice_pk, &bob_pk, &bob_sk, vec![]);
+
+        // One millisecond further in the future than allowed: rejected
+        alice_clock.set(T0 + 100_000 - 5_001);
+        assert_eq!(
+            alice_manager
+                .feed_incoming_announcement(&future_announcement, &alice_pk, &alice_sk)
+                .err(),
+            Some(AnnouncementError::TooFarInFuture)
+        );
+
+        // Exactly the maximum future skew: accepted
+        alice_clock.set(T0 + 100_000 - 5_000);
+        let result = alice_manager
+            .feed_incoming_announcement(&future_announcement, &alice_pk, &alice_sk)
+            .expect("Announcement at the maximum future skew should be accepted");
+        assert_eq!(result.timestamp_millis, T0 + 100_000);
+    }
+
+    #[test]
+    fn test_announcement_in_same_millisecond_replaces_previous_one() {
+        let clock = ManualClock::new(T0);
+        let mut alice_manager = SessionManager::with_clock(create_test_config(), clock.clone());
+        let mut bob_manager = SessionManager::with_clock(create_test_config(), clock.clone());
+
+        let (alice_pk, alice_sk) = generate_test_keypair();
+        let (bob_pk, bob_sk) = generate_test_keypair();
+        let alice_id = alice_pk.derive_id();
+        let bob_id = bob_pk.derive_id();
+
+        // Alice requests a session twice within the same millisecond
+        let first = alice_manager.establish_outgoing_session(&bob_pk, &alice_pk, &alice_sk, vec![]);
+        let second =
+            alice_manager.establish_outgoing_session(&bob_pk, &alice_pk, &alice_sk, vec![]);
+
+        // The second one is the latest for Bob too, even when read first
+        let result = bob_manager
+            .feed_incoming_announcement(&second, &bob_pk, &bob_sk)
+            .expect("Failed to process announcement");
+        assert_eq!(result.timestamp_millis, T0 + 1);
+        assert_eq!(
+            bob_manager
+                .feed_incoming_announcement(&first, &bob_pk, &bob_sk)
+                .err(),
+            Some(AnnouncementError::Outdated)
+        );
+
+        // So the answer of Bob pairs with the request that Alice kept
+        let answer = bob_manager.establish_outgoing_session(&alice_pk, &bob_pk, &bob_sk, vec![]);
+        alice_manager
+            .feed_incoming_announcement(&answer, &alice_pk, &alice_sk)
+            .expect("Failed to process announcement");
+        let output = alice_manager.send_message(&bob_id, b"paired").unwrap();
+        let received = bob_manager
+            .feed_incoming_message_board_read(&output.seeker, &output.data, &bob_sk)
+            .expect("Bob should receive message on the same session");
+        assert_eq!(received.message.as_slice(), b"paired");
+        assert!(matches!(
+            bob_manager.peer_session_status(&alice_id),
+            SessionStatus::Active
+        ));
+    }
+
+    #[test]
+    fn test_refresh_expires_announcements() {
+        // max announcement age 60s
+        let clock = ManualClock::new(T0);
+        let mut alice_manager = SessionManager::with_clock(create_test_config(), clock.clone());
+        let mut bob_manager = SessionManager::with_clock(create_test_config(), clock.clone());
+
+        let (alice_pk, alice_sk) = generate_test_keypair();
+        let (bob_pk, bob_sk) = generate_test_keypair();
+        let (charlie_pk, _charlie_sk) = generate_test_keypair();
+        let bob_id = bob_pk.derive_id();
+        let charlie_id = charlie_pk.derive_id();
+
+        // Bob requests a session with Alice, Alice requests a session with Charlie
+        let bob_announcement =
+            bob_manager.establish_outgoing_session(&alice_pk, &bob_pk, &bob_sk, vec![]);
+        alice_manager
+            .feed_incoming_announcement(&bob_announcement, &alice_pk, &alice_sk)
+            .expect("Failed to process announcement");
+        alice_manager.establish_outgoing_session(&charlie_pk, &alice_pk, &alice_sk, vec![]);
+
+        // Requests are kept up to the maximum announcement age
+        clock.set(T0 + 60_000);
+        assert!(alice_manager.refresh().is_empty());
+        assert!(matches!(
+            alice_manager.peer_session_status(&bob_id),
+            SessionStatus::PeerRequested
+        ));
+        assert!(matches!(
+            alice_manager.peer_session_status(&charlie_id),
+            SessionStatus::SelfRequested
+        ));
+
+        // And dropped right after
+        clock.advance(1);
+        assert!(alice_manager.refresh().is_empty());
+        assert!(matches!(
+            alice_manager.peer_session_status(&bob_id),
+            SessionStatus::NoSession
+        ));
+        assert!(matches!(
+            alice_manager.peer_session_status(&charlie_id),
+            SessionStatus::NoSession
+        ));
+    }
+
+    #[test]
+    fn test_refresh_expires_inactive_session() {
+        // max inactivity 1h
+        let alice_clock = ManualClock::new(T0);
+        let bob_clock = ManualClock::new(T0);
+        let (mut alice_manager, alice_pk, alice_sk, _bob_manager, bob_pk, _bob_sk) =
+            establish_session_with_clocks(&alice_clock, &bob_clock);
+        let bob_id = bob_pk.derive_id();
+
+        // The session survives up to the maximum inactivity
+        alice_clock.set(T0 + 3_600_000);
+        alice_manager.refresh();
+        assert!(matches!(
+            alice_manager.peer_session_status(&bob_id),
+            SessionStatus::Active
+        ));
+
+        // And is closed right after. The announcements have expired as well.
+        alice_clock.advance(1);
+        assert!(alice_manager.refresh().is_empty());
+        assert!(matches!(
+            alice_manager.peer_session_status(&bob_id),
+            SessionStatus::NoSession
+        ));
+        assert_eq!(
+            alice_manager.send_message(&bob_id, b"too late").err(),
+            Some(SessionError::NoActiveSession)
+        );
+
+        // An expired session is not broken, so it is not healed
+        assert!(alice_manager.heal_sessions(&alice_pk, &alice_sk).is_empty());
+    }
+
+    #[test]
+    fn test_incoming_message_extends_session() {
+        // max inactivity 1h
+        let alice_clock = ManualClock::new(T0);
+        let bob_clock = ManualClock::new(T0);
+        let (mut alice_manager, alice_pk, alice_sk, mut bob_manager, bob_pk, _bob_sk) =
+            establish_session_with_clocks(&alice_clock, &bob_clock);
+        let alice_id = alice_pk.derive_id();
+        let bob_id = bob_pk.derive_id();
+
+        // Bob writes to Alice after 50 minutes
+        alice_clock.set(T0 + 3_000_000);
+        bob_clock.set(T0 + 3_000_000);
+        let output = bob_manager
+            .send_message(&alice_id, b"still here")
+            .expect("Failed to send message");
+        alice_manager
+            .feed_incoming_message_board_read(&output.seeker, &output.data, &alice_sk)
+            .expect("Failed to receive message");
+
+        // The session now lasts for an hour after that message
+        alice_clock.set(T0 + 3_000_000 + 3_600_000);
+        alice_manager.refresh();
+        assert!(matches!(
+            alice_manager.peer_session_status(&bob_id),
+            SessionStatus::Active
+        ));
+
+        alice_clock.advance(1);
+        alice_manager.refresh();
+        assert!(matches!(
+            alice_manager.peer_session_status(&bob_id),
+            SessionStatus::NoSession
+        ));
+    }
+
+    #[test]
+    fn test_refresh_keep_alive() {
+        // keep-alive interval 1 minute
+        let alice_clock = ManualClock::new(T0);
+        let bob_clock = ManualClock::new(T0);
+        let (mut alice_manager, _alice_pk, _alice_sk, _bob_manager, bob_pk, _bob_sk) =
+            establish_session_with_clocks(&alice_clock, &bob_clock);
+        let bob_id = bob_pk.derive_id();
+
+        // Our announcement counts as the last outgoing message
+        assert!(alice_manager.refresh().is_empty());
+        alice_clock.set(T0 + 60_000);
+        assert!(alice_manager.refresh().is_empty());
+
+        // Keep-alive needed once the interval has elapsed
+        alice_clock.advance(1);
+        let keep_alive_peers = alice_manager.refresh();
+        assert_eq!(keep_alive_peers.len(), 1);
+        assert_eq!(keep_alive_peers[0], bob_id);
+
+        // Sending a message resets the interval
+        alice_manager
+            .send_message(&bob_id, b"")
+            .expect("Failed to send keep-alive");
+        assert!(alice_manager.refresh().is_empty());
+        alice_clock.advance(60_000);
+        assert!(alice_manager.refresh().is_empty());
+        alice_clock.advance(1);
+        assert_eq!(alice_manager.refresh().len(), 1);
+    }
+
+    #[test]
+    fn test_message_too_old_keeps_session() {
+        // max message age 5 minutes
+        let alice_clock = ManualClock::new(T0);
+        let bob_clock = ManualClock::new(T0);
+        let (mut alice_manager, alice_pk, alice_sk, mut bob_manager, bob_pk, _bob_sk) =
+            establish_session_with_clocks(&alice_clock, &bob_clock);
+        let alice_id = alice_pk.derive_id();
+        let bob_id = bob_pk.derive_id();
+
+        let old_output = bob_manager
+            .send_message(&alice_id, b"old")
+            .expect("Failed to send message");
+        let recent_output = bob_manager
+            .send_message(&alice_id, b"recent")
+            .expect("Failed to send message");
+
+        // Exactly the maximum age: accepted
+        alice_clock.set(T0 + 300_000);
+        alice_manager
+            .feed_incoming_message_board_read(&old_output.seeker, &old_output.data, &alice_sk)
+            .expect("Message at the maximum age should be accepted");
+
+        // One millisecond older: rejected, but the session stays open
+        alice_clock.advance(1);
+        assert_eq!(
+            alice_manager
+                .feed_incoming_message_board_read(
+                    &recent_output.seeker,
+                    &recent_output.data,
+                    &alice_sk
+                )
+                .err(),
+            Some(SessionError::MessageTooOld)
+        );
+        assert!(matches!(
+            alice_manager.peer_session_status(&bob_id),
+            SessionStatus::Active
+        ));
+
+        // The rejected message was not received by the session
+        assert!(
+            alice_manager
+                .get_message_board_read_keys()
+                .contains(&recent_output.seeker)
+        );
+
+        // Later messages are still read over the session
+        bob_clock.set(T0 + 300_001);
+        let output = bob_manager
+            .send_message(&alice_id, b"on time")
+            .expect("Failed to send message");
+        let received = alice_manager
+            .feed_incoming_message_board_read(&output.seeker, &output.data, &alice_sk)
+            .expect("Session should stay open after a message out of time bounds");
+        assert_eq!(received.message, b"on time");
+    }
+
+    #[test]
+    fn test_message_too_far_in_future_keeps_session() {
+        // max message future skew 5 seconds
+        let alice_clock = ManualClock::new(T0);
+        let bob_clock = ManualClock::new(T0);
+        let (mut alice_manager, alice_pk, alice_sk, mut bob_manager, bob_pk, _bob_sk) =
+            establish_session_with_clocks(&alice_clock, &bob_clock);
+        let alice_id = alice_pk.derive_id();
+        let bob_id = bob_pk.derive_id();
+
+        // Exactly the maximum future skew: accepted
+        bob_clock.set(T0 + 5_000);
+        let output = bob_manager
+            .send_message(&alice_id, b"slightly ahead")
+            .expect("Failed to send message");
+        alice_manager
+            .feed_incoming_message_board_read(&output.seeker, &output.data, &alice_sk)
+            .expect("Message at the maximum future skew should be accepted");
+
+        // One millisecond further: rejected, but the session stays open
+        bob_clock.advance(1);
+        let output = bob_manager
+            .send_message(&alice_id, b"too far ahead")
+            .expect("Failed to send message");
+        assert_eq!(
+            alice_manager
+                .feed_incoming_message_board_read(&output.seeker, &output.data, &alice_sk)
+                .err(),
+            Some(SessionError::MessageTooFarInFuture)
+        );
+        assert!(matches!(
+            alice_manager.peer_session_status(&bob_id),
+            SessionStatus::Active
+        ));
+
+        // The rejected message was not received by the session: it is read once within bounds
+        alice_clock.set(T0 + 5_001);
+        let received = alice_manager
+            .feed_incoming_message_board_read(&output.seeker, &output.data, &alice_sk)
+            .expect("Message should be read once its timestamp is within bounds");
+        assert_eq!(received.message, b"too far ahead");
+
+        // Later messages are still read over the session
+        let output = bob_manager
+            .send_message(&alice_id, b"on time")
+            .expect("Failed to send message");
+        let received = alice_manager
+            .feed_incoming_message_board_read(&output.seeker, &output.data, &alice_sk)
+            .expect("Session should stay open after a message out of time bounds");
+        assert_eq!(received.message, b"on time");
+    }
+
+    /// Expires the announcements of an active session, so that only the session itself
+    /// remains, then kills it with a corrupted message
+    fn kill_session_after_announcements_expired(
+        manager: &mut SessionManager,
+        clock: &ManualClock,
+        our_sk: &auth::UserSecretKeys,
+    ) {
+        clock.set(T0 + 120_000);
+        manager.refresh();
+        let seeker = manager.get_message_board_read_keys()[0].clone();
+        assert!(
+            manager
+                .feed_incoming_message_board_read(&seeker, b"corrupted", our_sk)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_killed_session_heals() {
+        let alice_clock = ManualClock::new(T0);
+        let bob_clock = ManualClock::new(T0);
+        let (mut alice_manager, alice_pk, alice_sk, mut bob_manager, bob_pk, bob_sk) =
+            establish_session_with_clocks(&alice_clock, &bob_clock);
+        let alice_id = alice_pk.derive_id();
+        let bob_id = bob_pk.derive_id();
+
+        // Nothing to heal while the session works
+        assert!(bob_manager.heal_sessions(&bob_pk, &bob_sk).is_empty());
+
+        kill_session_after_announcements_expired(&mut bob_manager, &bob_clock, &bob_sk);
+        assert!(matches!(
+            bob_manager.peer_session_status(&alice_id),
+            SessionStatus::NoSession
+        ));
+
+        // Bob announces to the keys of the broken session, although the announcements expired
+        let healing = bob_manager.heal_sessions(&bob_pk, &bob_sk);
+        assert_eq!(healing.len(), 1);
+        assert_eq!(healing[0].0, alice_id);
+        assert!(matches!(
+            bob_manager.peer_session_status(&alice_id),
+            SessionStatus::SelfRequested
+        ));
+
+        // Alice still has the session and nothing pending: she learns that Bob lost it,
+        // but keeps the session until she answers
+        alice_clock.set(T0 + 120_000);
+        alice_manager.refresh();
+        alice_manager
+            .feed_incoming_announcement(&healing[0].1, &alice_pk, &alice_sk)
+            .expect("Failed to process announcement");
+        assert!(matches!(
+            alice_manager.peer_session_status(&bob_id),
+            SessionStatus::Active
+        ));
+
+        // And answers right away, which establishes the new session on both sides
+        let answer = alice_manager.heal_sessions(&alice_pk, &alice_sk);
+        assert_eq!(answer.len(), 1);
+        assert_eq!(answer[0].0, bob_id);
+        assert!(alice_manager.heal_sessions(&alice_pk, &alice_sk).is_empty());
+        bob_manager
+            .feed_incoming_announcement(&answer[0].1, &bob_pk, &bob_sk)
+            .expect("Failed to process announcement");
+        assert!(matches!(
+            alice_manager.peer_session_status(&bob_id),
+            SessionStatus::Active
+        ));
+        assert!(matches!(
+            bob_manager.peer_session_status(&alice_id),
+            SessionStatus::Active
+        ));
+
+        // The new session works both ways, which ends the healing
+        let output = alice_manager.send_message(&bob_id, b"healed").unwrap();
+        let received = bob_manager
+            .feed_incoming_message_board_read(&output.seeker, &output.data, &bob_sk)
+            .expect("Bob should receive message on healed session");
+        assert_eq!(received.message.as_slice(), b"healed");
+        let output = bob_manager.send_message(&alice_id, b"indeed").unwrap();
+        let received = alice_manager
+            .feed_incoming_message_board_read(&output.seeker, &output.data, &alice_sk)
+            .expect("Alice should receive message on healed session");
+        assert_eq!(received.message.as_slice(), b"indeed");
+        assert!(alice_manager.peers[&bob_id].healing.is_none());
+        assert!(bob_manager.peers[&alice_id].healing.is_none());
+    }
+
+    #[test]
+    fn test_replaced_session_kept_until_new_one_used() {
+        let alice_clock = ManualClock::new(T0);
+        let bob_clock = ManualClock::new(T0);
+        let (mut alice_manager, alice_pk, alice_sk, mut bob_manager, bob_pk, bob_sk) =
+            establish_session_with_clocks(&alice_clock, &bob_clock);
+        let alice_id = alice_pk.derive_id();
+        let bob_id = bob_pk.derive_id();
+
+        // Once the announcements expired, Bob sends a message, then announces again
+        // although his session works
+        alice_clock.set(T0 + 120_000);
+        bob_clock.set(T0 + 120_000);
+        alice_manager.refresh();
+        bob_manager.refresh();
+        let in_flight = bob_manager.send_message(&alice_id, b"in flight").unwrap();
+        let announcement =
+            bob_manager.establish_outgoing_session(&alice_pk, &bob_pk, &bob_sk, vec![]);
+
+        // Alice keeps her session, and answers
+        alice_manager
+            .feed_incoming_announcement(&announcement, &alice_pk, &alice_sk)
+            .expect("Failed to process announcement");
+        assert!(matches!(
+            alice_manager.peer_session_status(&bob_id),
+            SessionStatus::Active
+        ));
+        let old_seekers = alice_manager.get_message_board_read_keys();
+        let answer = alice_manager.heal_sessions(&alice_pk, &alice_sk);
+        assert_eq!(answer.len(), 1);
+        bob_manager
+            .feed_incoming_announcement(&answer[0].1, &bob_pk, &bob_sk)
+            .expect("Failed to process announcement");
+
+        // The replaced session still reads the message sent before the new session
+        let read_keys = alice_manager.get_message_board_read_keys();
+        assert!(old_seekers.iter().all(|seeker| read_keys.contains(seeker)));
+        let received = alice_manager
+            .feed_incoming_message_board_read(&in_flight.seeker, &in_flight.data, &alice_sk)
+            .expect("Alice should receive message on replaced session");
+        assert_eq!(received.message.as_slice(), b"in flight");
+
+        // Until Bob uses the new session
+        let output = bob_manager.send_message(&alice_id, b"new session").unwrap();
+        let received = alice_manager
+            .feed_incoming_message_board_read(&output.seeker, &output.data, &alice_sk)
+            .expect("Alice should receive message on new session");
+        assert_eq!(received.message.as_slice(), b"new session");
+        assert!(alice_manager.peers[&bob_id].replaced_session.is_none());
+        let read_keys = alice_manager.get_message_board_read_keys();
+        assert!(old_seekers.iter().all(|seeker| !read_keys.contains(seeker)));
+    }
+
+    #[test]
+    fn test_healing_backs_off() {
+        // max announcement age 60s
+        let alice_clock = ManualClock::new(T0);
+        let bob_clock = ManualClock::new(T0);
+        let (_alice_manager, alice_pk, _alice_sk, mut bob_manager, bob_pk, bob_sk) =
+            establish_session_with_clocks(&alice_clock, &bob_clock);
+        let alice_id = alice_pk.derive_id();
+
+        kill_session_after_announcements_expired(&mut bob_manager, &bob_clock, &bob_sk);
+        let mut next_attempt = T0 + 120_000;
+        assert_eq!(bob_manager.heal_sessions(&bob_pk, &bob_sk).len(), 1);
+
+        // The backoff survives a restart
+        let key = crypto_aead::Key::from([7u8; crypto_aead::KEY_SIZE]);
+        let blob = bob_manager.to_encrypted_blob(&key).unwrap();
+        let mut bob_manager = SessionManager::from_encrypted_blob(&blob, &key).unwrap();
+        bob_manager.set_clock(bob_clock.clone());
+
+        // Alice never answers: Bob announces again once his announcement expired,
+        // waiting twice as long each time
+        for delay in [60_000, 120_000, 240_000, 480_000] {
+            next_attempt += delay;
+            bob_clock.set(next_attempt - 1);
+            assert!(bob_manager.heal_sessions(&bob_pk, &bob_sk).is_empty());
+            bob_clock.set(next_attempt);
+            assert_eq!(bob_manager.heal_sessions(&bob_pk, &bob_sk).len(), 1);
+        }
+
+        // Then gives up once the last announcement went unanswered
+        bob_clock.set(next_attempt + 960_000);
+        assert!(bob_manager.heal_sessions(&bob_pk, &bob_sk).is_empty());
+        assert!(bob_manager.peers[&alice_id].healing.is_none());
+        bob_clock.advance(10_000_000);
+        assert!(bob_manager.heal_sessions(&bob_pk, &bob_sk).is_empty());
+    }
+
+    #[test]
+    fn test_peer_announcement_keeps_healing_backoff() {
+        // max announcement age 60s
+        let alice_clock = ManualClock::new(T0);
+        let bob_clock = ManualClock::new(T0);
+        let (mut alice_manager, alice_pk, alice_sk, mut bob_manager, bob_pk, bob_sk) =
+            establish_session_with_clocks(&alice_clock, &bob_clock);
+        let alice_id = alice_pk.derive_id();
+
+        kill_session_after_announcements_expired(&mut bob_manager, &bob_clock, &bob_sk);
+        assert_eq!(bob_manager.heal_sessions(&bob_pk, &bob_sk).len(), 1);
+
+        // Bob's healing announcement expires unanswered
+        bob_clock.set(T0 + 180_001);
+        bob_manager.refresh();
+        assert!(
+            bob_manager.peers[&alice_id]
+                .latest_outgoing_init_request
+                .is_none()
+        );
+
+        // Then Alice starts over
+        alice_clock.set(T0 + 180_001);
+        let announcement =
+            alice_manager.establish_outgoing_session(&bob_pk, &alice_pk, &alice_sk, vec![]);
+        bob_manager
+            .feed_incoming_announcement(&announcement, &bob_pk, &bob_sk)
+            .expect("Failed to feed Alice's announcement");
+
+        // Bob answers within the backoff of the healing in progress
+        let healing = bob_manager.peers[&alice_id]
+            .healing
+            .as_ref()
+            .expect("Healing should be in progress");
+        assert_eq!(healing.attempts, 1);
+        assert_eq!(healing.next_attempt_millis, T0 + 180_000);
+        assert_eq!(bob_manager.heal_sessions(&bob_pk, &bob_sk).len(), 1);
+        assert_eq!(
+            bob_manager.peers[&alice_id]
+                .healing
+                .as_ref()
+                .unwrap()
+                .attempts,
+            2
+        );
+    }
+
+    #[test]
+    fn test_simultaneous_healing_converges() {
+        let alice_clock = ManualClock::new(T0);
+        let bob_clock = ManualClock::new(T0);
+        let (mut alice_manager, alice_pk, alice_sk, mut bob_manager, bob_pk, bob_sk) =
+            establish_session_with_clocks(&alice_clock, &bob_clock);
+        let alice_id = alice_pk.derive_id();
+        let bob_id = bob_pk.derive_id();
+
+        // Both sessions break, and both peers announce at the same time
+        kill_session_after_announcements_expired(&mut alice_manager, &alice_clock, &alice_sk);
+        kill_session_after_announcements_expired(&mut bob_manager, &bob_clock, &bob_sk);
+        let alice_healing = alice_manager.heal_sessions(&alice_pk, &alice_sk);
+        let bob_healing = bob_manager.heal_sessions(&bob_pk, &bob_sk);
+        assert_eq!(alice_healing.len(), 1);
+        assert_eq!(bob_healing.len(), 1);
+
+        // Each one builds the session from the announcement of the other
+        alice_manager
+            .feed_incoming_announcement(&bob_healing[0].1, &alice_pk, &alice_sk)
+            .expect("Failed to process announcement");
+        bob_manager
+            .feed_incoming_announcement(&alice_healing[0].1, &bob_pk, &bob_sk)
+            .expect("Failed to process announcement");
+        assert!(matches!(
+            alice_manager.peer_session_status(&bob_id),
+            SessionStatus::Active
+        ));
+        assert!(matches!(
+            bob_manager.peer_session_status(&alice_id),
+            SessionStatus::Active
+        ));
+
+        // Without announcing again, even once the backoff elapsed
+        alice_clock.advance(60_000);
+        bob_clock.advance(60_000);
+        assert!(alice_manager.heal_sessions(&alice_pk, &alice_sk).is_empty());
+        assert!(bob_manager.heal_sessions(&bob_pk, &bob_sk).is_empty());
+
+        // And it is the same session
+        let output = alice_manager
+            .send_message(&bob_id, b"same session")
+            .unwrap();
+        let received = bob_manager
+            .feed_incoming_message_board_read(&output.seeker, &output.data, &bob_sk)
+            .expect("Bob should receive message on healed session");
+        assert_eq!(received.message.as_slice(), b"same session");
+        let output = bob_manager
+            .send_message(&alice_id, b"same session")
+            .unwrap();
+        let received = alice_manager
+            .feed_incoming_message_board_read(&output.seeker, &output.data, &alice_sk)
+            .expect("Alice should receive message on healed session");
+        assert_eq!(received.message.as_slice(), b"same session");
+    }
+
+    #[test]
+    fn test_persist_only_changed_peers() {
+        let clock = ManualClock::new(T0);
+        let (mut alice_manager, alice_pk, alice_sk, mut bob_manager, bob_pk, _bob_sk) =
+            establish_session_with_clocks(&clock, &clock);
+        let alice_id = alice_pk.derive_id();
+        let bob_id = bob_pk.derive_id();
+        let (charlie_pk, _charlie_sk) = generate_test_keypair();
+        alice_manager.establish_outgoing_session(&charlie_pk, &alice_pk, &alice_sk, vec![]);
+
+        let key = generate_test_key();
+        let mut storage = MemoryStorage::new();
+
+        // First persist writes both peers
+        assert!(alice_manager.has_unpersisted_changes());
+        assert_eq!(alice_manager.persist_to(&mut storage, &key), Ok(2));
+        assert_eq!(storage.len(), 2);
+        assert!(!alice_manager.has_unpersisted_changes());
+
+        // Nothing changed: nothing is written
+        assert_eq!(alice_manager.persist_to(&mut storage, &key), Ok(0));
+
+        // Receiving a message only changes Bob's state
+        let charlie_state = storage.get(&charlie_pk.derive_id()).unwrap().to_vec();
+        let output = bob_manager
+            .send_message(&alice_id, b"hello")
+            .expect("Failed to send message");
+        alice_manager
+            .feed_incoming_message_board_read(&output.seeker, &output.data, &alice_sk)
+            .expect("Failed to receive message");
+        assert_eq!(alice_manager.persist_to(&mut storage, &key), Ok(1));
+        assert_eq!(
+            storage.get(&charlie_pk.derive_id()),
+            Some(charlie_state.as_slice())
+        );
+
+        // A rejected seeker does not change anything
+        assert_eq!(
+            alice_manager
+                .feed_incoming_message_board_read(b"unknown", b"data", &alice_sk)
+                .err(),
+            Some(SessionError::UnknownSeeker)
+        );
+        assert!(!alice_manager.has_unpersisted_changes());
+
+        // Sending a message changes Bob's state
+        alice_manager
+            .send_message(&bob_id, b"hi")
+            .expect("Failed to send message");
+        assert_eq!(alice_manager.persist_to(&mut storage, &key), Ok(1));
+    }
+
+    #[test]
+    fn test_restore_from_storage() {
+        let clock = ManualClock::new(T0);
+        let (mut alice_manager, alice_pk, alice_sk, mut bob_manager, bob_pk, bob_sk) =
+            establish_session_with_clocks(&clock, &clock);
+        let alice_id = alice_pk.derive_id();
+        let bob_id = bob_pk.derive_id();
+
+        let key = generate_test_key();
+        let mut storage = MemoryStorage::new();
+        alice_manager
+            .persist_to(&mut storage, &key)
+            .expect("Failed to persist");
+
+        // Restore and keep exchanging messages
+        let mut restored_manager =
+            SessionManager::restore_from(create_test_config(), &storage, &key)
+                .expect("Failed to restore");
+        restored_manager.set_clock(clock.clone());
+        assert!(!restored_manager.has_unpersisted_changes());
+        assert!(matches!(
+            restored_manager.peer_session_status(&bob_id),
+            SessionStatus::Active
+        ));
+
+        let output = restored_manager
+            .send_message(&bob_id, b"after restore")
+            .expect("Failed to send message");
+        let received = bob_manager
+            .feed_incoming_message_board_read(&output.seeker, &output.data, &bob_sk)
+            .expect("Failed to receive message");
+        assert_eq!(received.message.as_slice(), b"after restore");
+
+        let output = bob_manager
+            .send_message(&alice_id, b"reply")
+            .expect("Failed to send message");
+        let received = restored_manager
+            .feed_incoming_message_board_read(&output.seeker, &output.data, &alice_sk)
+            .expect("Failed to receive message");
+        assert_eq!(received.message.as_slice(), b"reply");
+    }
+
+    #[test]
+    fn test_persist_discarded_peer() {
+        let clock = ManualClock::new(T0);
+        let (mut alice_manager, _alice_pk, _alice_sk, _bob_manager, bob_pk, _bob_sk) =
+            establish_session_with_clocks(&clock, &clock);
+        let bob_id = bob_pk.derive_id();
+
+        let key = generate_test_key();
+        let mut storage = MemoryStorage::new();
+        alice_manager
+            .persist_to(&mut storage, &key)
+            .expect("Failed to persist");
+        assert_eq!(storage.len(), 1);
+
+        alice_manager.peer_discard(&bob_id);
+        assert_eq!(alice_manager.persist_to(&mut storage, &key), Ok(1));
+        assert!(storage.is_empty());
+
+        // Discarding an unknown peer does not change anything
+        alice_manager.peer_discard(&bob_id);
+        assert!(!alice_manager.has_unpersisted_changes());
+    }
+
+    #[test]
+    fn test_refresh_expiry_is_persisted() {
+        let clock = ManualClock::new(T0);
+        let (mut alice_manager, _alice_pk, _alice_sk, _bob_manager, bob_pk, _bob_sk) =
+            establish_session_with_clocks(&clock, &clock);
+        let bob_id = bob_pk.derive_id();
+
+        let key = generate_test_key();
+        let mut storage = MemoryStorage::new();
+        alice_manager
+            .persist_to(&mut storage, &key)
+            .expect("Failed to persist");
+
+        // Nothing expires yet
+        alice_manager.refresh();
+        assert!(!alice_manager.has_unpersisted_changes());
+
+        // The announcements expire
+        clock.advance(60_001);
+        alice_manager.refresh();
+        assert_eq!(alice_manager.persist_to(&mut storage, &key), Ok(1));
+
+        // Then the session
+        clock.advance(3_600_000);
+        alice_manager.refresh();
+        assert_eq!(alice_manager.persist_to(&mut storage, &key), Ok(1));
+
+        let restored_manager = SessionManager::restore_from(create_test_config(), &storage, &key)
+            .expect("Failed to restore");
+        assert!(matches!(
+            restored_manager.peer_session_status(&bob_id),
+            SessionStatus::NoSession
+        ));
+    }
+
+    #[test]
+    fn test_import_whole_blob_into_storage() {
+        let clock = ManualClock::new(T0);
+        let (alice_manager, _alice_pk, _alice_sk, _bob_manager, bob_pk, _bob_sk) =
+            establish_session_with_clocks(&clock, &clock);
+        let bob_id = bob_pk.derive_id();
+
+        // State saved in the whole-blob format
+        let key = generate_test_key();
+        let encrypted_blob = alice_manager
+            .to_encrypted_blob(&key)
+            .expect("Encryption should succeed");
+
+        // Importing it writes every peer on the first persist
+        let mut imported_manager = SessionManager::from_encrypted_blob(&encrypted_blob, &key)
+            .expect("Decryption should succeed");
+        assert!(imported_manager.has_unpersisted_changes());
+        let mut storage = MemoryStorage::new();
+        assert_eq!(imported_manager.persist_to(&mut storage, &key), Ok(1));
+
+        let restored_manager = SessionManager::restore_from(create_test_config(), &storage, &key)
+            .expect("Failed to restore");
+        assert!(matches!(
+            restored_manager.peer_session_status(&bob_id),
+            SessionStatus::Active
+        ));
+    }
+
+    #[test]
+    fn test_restore_rejects_invalid_peer_state() {
+        let mut manager = SessionManager::new(create_test_config());
+        let (our_pk, our_sk) = generate_test_keypair();
+        let (bob_pk, _bob_sk) = generate_test_keypair();
+        let (charlie_pk, _charlie_sk) = generate_test_keypair();
+        manager.establish_outgoing_session(&bob_pk, &our_pk, &our_sk, vec![]);
+        manager.establish_outgoing_session(&charlie_pk, &our_pk, &our_sk, vec![]);
+
+        let key = generate_test_key();
+        let mut storage = MemoryStorage::new();
+        manager
+            .persist_to(&mut storage, &key)
+            .expect("Failed to persist");
+
+        // Wrong key
+        assert_eq!(
+            SessionManager::restore_from(create_test_config(), &storage, &generate_test_key())
+                .err(),
+            Some(StorageError::State(SessionError::StateUndecryptable))
+        );
+
+        // Bob's state stored under Charlie's ID
+        let bob_state = storage.get(&bob_pk.derive_id()).unwrap().to_vec();
+        storage
+            .write_peer(&charlie_pk.derive_id(), &bob_state)
+            .unwrap();
+        assert_eq!(
+            SessionManager::restore_from(create_test_config(), &storage, &key).err(),
+            Some(StorageError::State(SessionError::StateUndecryptable))
+        );
+
+        // Truncated state
+        storage
+            .write_peer(&charlie_pk.derive_id(), &bob_state[..4])
+            .unwrap();
+        assert_eq!(
+            SessionManager::restore_from(create_test_config(), &storage, &key).err(),
+            Some(StorageError::State(SessionError::StateUndecryptable))
+        );
+    }
+
+    #[test]
+    fn test_peer_verification() {
+        let mut alice_manager = SessionManager::new(create_test_config());
+        let mut bob_manager = SessionManager::new(create_test_config());
+        let (alice_pk, alice_sk) = generate_test_keypair();
+        let (bob_pk, bob_sk) = generate_test_keypair();
+        let (charlie_pk, _) = generate_test_keypair();
+        let alice_id = alice_pk.derive_id();
+        let bob_id = bob_pk.derive_id();
+
+        // Bob's keys are unknown to Alice until she gets his announcement
+        alice_manager.establish_outgoing_session(&bob_pk, &alice_pk, &alice_sk, vec![]);
+        assert!(alice_manager.peer_public_keys(&bob_id).is_none());
+        assert!(
+            alice_manager
+                .peer_safety_number(&bob_id, &alice_pk)
+                .is_none()
+        );
+
+        let bob_announcement =
+            bob_manager.establish_outgoing_session(&alice_pk, &bob_pk, &bob_sk, vec![]);
+        alice_manager
+            .feed_incoming_announcement(&bob_announcement, &alice_pk, &alice_sk)
+            .unwrap();
+        assert_eq!(
+            alice_manager.peer_public_keys(&bob_id).unwrap().derive_id(),
+            bob_id
+        );
+        assert!(bob_manager.peer_public_keys(&alice_id).is_none());
+
+        // Both sides display the same safety number
+        assert_eq!(
+            alice_manager.peer_safety_number(&bob_id, &alice_pk),
+            Some(auth::SafetyNumber::new(&bob_pk, &alice_pk))
+        );
+
+        // Verification
+        assert!(!alice_manager.is_peer_verified(&bob_id));
+        alice_manager
+            .persist_to(&mut MemoryStorage::new(), &generate_test_key())
+            .unwrap();
+        assert!(alice_manager.mark_peer_verified(&bob_pk));
+        assert!(alice_manager.is_peer_verified(&bob_id));
+        assert!(alice_manager.has_unpersisted_changes());
+
+        // Unknown peers cannot be verified
+        assert!(!alice_manager.mark_peer_verified(&charlie_pk));
+        assert!(!alice_manager.is_peer_verified(&charlie_pk.derive_id()));
+
+        // Unverification
+        alice_manager.mark_peer_unverified(&bob_id);
+        assert!(!alice_manager.is_peer_verified(&bob_id));
+    }
+
+    #[test]
+    fn test_peer_verification_survives_persistence() {
+        let mut manager = SessionManager::new(create_test_config());
+        let (our_pk, our_sk) = generate_test_keypair();
+        let (peer_pk, _) = generate_test_keypair();
+        let peer_id = peer_pk.derive_id();
+        let key = generate_test_key();
+
+        manager.establish_outgoing_session(&peer_pk, &our_pk, &our_sk, vec![]);
+        assert!(manager.mark_peer_verified(&peer_pk));
+
+        let blob = manager.to_encrypted_blob(&key).unwrap();
+        let restored = SessionManager::from_encrypted_blob(&blob, &key).unwrap();
+        assert!(restored.is_peer_verified(&peer_id));
+
+        let mut storage = MemoryStorage::new();
+        manager.persist_to(&mut storage, &key).unwrap();
+        let restored = SessionManager::restore_from(create_test_config(), &storage, &key).unwrap();
+        assert!(restored.is_peer_verified(&peer_id));
+    }
+
+    #[test]
+    fn test_peer_verification_cleared_on_key_change() {
+        let clock = ManualClock::new(T0);
+        let mut alice_manager = SessionManager::with_clock(create_test_config(), clock.clone());
+        let mut bob_manager = SessionManager::with_clock(create_test_config(), clock.clone());
+        let (alice_pk, alice_sk) = generate_test_keypair();
+        let (bob_pk, bob_sk) = generate_test_keypair();
+        let bob_id = bob_pk.derive_id();
+
+        let bob_announcement =
+            bob_manager.establish_outgoing_session(&alice_pk, &bob_pk, &bob_sk, vec![]);
+        alice_manager
+            .feed_incoming_announcement(&bob_announcement, &alice_pk, &alice_sk)
+            .unwrap();
+        assert!(alice_manager.mark_peer_verified(&bob_pk));
+
+        // Announcements with the verified keys keep the verification
+        clock.advance(1000);
+        let bob_announcement =
+            bob_manager.establish_outgoing_session(&alice_pk, &bob_pk, &bob_sk, vec![]);
+        alice_manager
+            .feed_incoming_announcement(&bob_announcement, &alice_pk, &alice_sk)
+            .unwrap();
+        assert!(alice_manager.is_peer_verified(&bob_id));
+
+        // Simulate a verification of other keys than the ones Bob now presents
+        alice_manager
+            .peers
+            .get_mut(&bob_id)
+            .unwrap()
+            .verified_key_fingerprint = Some([0x33; auth::KEY_FINGERPRINT_SIZE]);
+        clock.advance(1000);
+        let bob_announcement =
+            bob_manager.establish_outgoing_session(&alice_pk, &bob_pk, &bob_sk, vec![]);
+        alice_manager
+            .feed_incoming_announcement(&bob_announcement, &alice_pk, &alice_sk)
+            .unwrap();
+        assert!(!alice_manager.is_peer_verified(&bob_id));
+    }
+
+    /// Key the golden files in `testdata/` are encrypted with
+    const GOLDEN_KEY: [u8; crypto_aead::KEY_SIZE] = [0x42; crypto_aead::KEY_SIZE];
+
+    /// ID of the peer stored in the golden files in `testdata/`
+    const GOLDEN_PEER_ID: [u8; auth::USER_ID_SIZE] = [0x11; auth::USER_ID_SIZE];
+
+    fn read_golden(name: &str) -> Vec<u8> {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("testdata")
+            .join(name);
+        std::fs::read(&path).unwrap_or_else(|e| panic!("failed to read {}: {e}", path.display()))
+    }
+
+    /// Checks the contents of the golden session manager, and that it serializes back to
+    /// exactly `serialized_state`, so that any layout change is caught.
+    fn check_golden_session_manager(manager: &SessionManager, serialized_state: &[u8]) {
+        assert_eq!(manager.config.max_incoming_message_age_millis, 300_000);
+        assert_eq!(manager.config.max_stored_skipped_messages, 128);
+
+        let peer_id = UserId::from_bytes(GOLDEN_PEER_ID);
+        assert_eq!(manager.peer_list(), vec![peer_id.clone()]);
+
+        let reserialized =
+            bincode::serde::encode_to_vec(manager, bincode::config::standard()).unwrap();
+        assert_eq!(reserialized, serialized_state);
+    }
+
+    /// Checks that the session request of the golden peer is still pending
+    fn check_golden_request(manager: &SessionManager) {
+        let peer_id = UserId::from_bytes(GOLDEN_PEER_ID);
+        assert!(matches!(
+            manager.peer_session_status(&peer_id),
+            SessionStatus::SelfRequested
+        ));
+        let request = manager.peers[&peer_id]
+            .latest_outgoing_init_request
+            .as_ref()
+            .unwrap();
+        assert_eq!(request.timestamp_millis, T0);
+    }
+
+    /// Checks that the session request of the golden peer, written before the session protocol
+    /// version 2, was dropped since the peer cannot answer it anymore. Our request did not give
+    /// us the keys of the peer, so there is nothing to heal.
+    fn check_golden_request_dropped(manager: &SessionManager) {
+        let peer_id = UserId::from_bytes(GOLDEN_PEER_ID);
+        assert!(matches!(
+            manager.peer_session_status(&peer_id),
+            SessionStatus::NoSession
+        ));
+        assert!(manager.peers[&peer_id].healing.is_none());
+    }
+
+    #[test]
+    fn test_golden_session_manager_v0() {
+        // Blob written before versioning and out-of-order delivery, with an active session
+        let blob = read_golden("session_manager_v0.bin");
+        let key = crypto_aead::Key::from(GOLDEN_KEY);
+
+        let mut manager = SessionManager::from_encrypted_blob(&blob, &key).unwrap();
+        let default_limits = SkippedMessageLimits::default();
+        assert_eq!(manager.config.max_incoming_message_age_millis, 300_000);
+        assert_eq!(manager.config.max_skipped_messages, default_limits.max_skip);
+        assert_eq!(
+            manager.config.max_stored_skipped_messages,
+            default_limits.max_stored
+        );
+
+        // the session cannot be carried over to the session protocol version 2: it is dropped,
+        // along with the announcements it was established from, and healed
+        let peer_id = UserId::from_bytes(GOLDEN_PEER_ID);
+        assert_eq!(manager.peer_list(), vec![peer_id.clone()]);
+        assert!(matches!(
+            manager.peer_session_status(&peer_id),
+            SessionStatus::NoSession
+        ));
+        let healing = manager.peers[&peer_id].healing.as_ref().unwrap();
+        assert_eq!(healing.attempts, 0);
+        assert!(manager.get_message_board_read_keys().is_empty());
+        assert_eq!(
+            manager.send_message(&peer_id, b"hello").err(),
+            Some(SessionError::NoActiveSession)
+        );
+
+        // saving it again writes the current version
+        let saved_blob = manager.to_encrypted_blob(&key).unwrap();
+        assert_eq!(&saved_blob[..4], &state_format::STATE_MAGIC);
+        assert_eq!(
+            &saved_blob[4..6],
+            &state_format::STATE_SCHEMA_VERSION.to_le_bytes()
+        );
+        let restored = SessionManager::from_encrypted_blob(&saved_blob, &key).unwrap();
+        assert_eq!(
+            bincode::serde::encode_to_vec(&restored, bincode::config::standard()).unwrap(),
+            bincode::serde::encode_to_vec(&manager, bincode::config::standard()).unwrap()
+        );
+
+        // the peer is announced to anew right away
+        let (our_pk, our_sk) = generate_test_keypair();
+        let announcements = manager.heal_sessions(&our_pk, &our_sk);
+        assert_eq!(announcements.len(), 1);
+        assert_eq!(announcements[0].0, peer_id);
+    }
+
+    #[test]
+    fn test_golden_session_manager_v1() {
+        let blob = read_golden("session_manager_v1.bin");
+        let key = crypto_aead::Key::from(GOLDEN_KEY);
+
+        let manager = SessionManager::from_encrypted_blob(&blob, &key).unwrap();
+        let serialized_state =
+            state_format::open(&blob, &key, b"", StateKind::SessionManager).unwrap();
+        check_golden_session_manager(&manager, &serialized_state);
+        check_golden_request_dropped(&manager);
+    }
+
+    #[test]
+    fn test_golden_peer_state_v1() {
+        let blob = read_golden("peer_state_v1.bin");
+        let key = crypto_aead::Key::from(GOLDEN_KEY);
+        let peer_id = UserId::from_bytes(GOLDEN_PEER_ID);
+
+        let mut storage = MemoryStorage::new();
+        storage.write_peer(&peer_id, &blob).unwrap();
+        let manager = SessionManager::restore_from(create_test_config(), &storage, &key).unwrap();
+        check_golden_request_dropped(&manager);
+
+        let serialized_state =
+            state_format::open(&blob, &key, peer_id.as_bytes(), StateKind::Peer).unwrap();
+        let reserialized =
+            bincode::serde::encode_to_vec(&manager.peers[&peer_id], bincode::config::standard())
+                .unwrap();
+        assert_eq!(reserialized, *serialized_state);
+
+        // A peer state is not a session manager blob
+        assert_eq!(
+            SessionManager::from_encrypted_blob(&blob, &key).err(),
+            Some(SessionError::StateUndecryptable)
+        );
+    }
+
+    #[test]
+    fn test_golden_session_manager_v2() {
+        let blob = read_golden("session_manager_v2.bin");
+        let key = crypto_aead::Key::from(GOLDEN_KEY);
+
+        let manager = SessionManager::from_encrypted_blob(&blob, &key).unwrap();
+        let serialized_state =
+            state_format::open(&blob, &key, b"", StateKind::SessionManager).unwrap();
+        check_golden_session_manager(&manager, &serialized_state);
+        check_golden_request_dropped(&manager);
+
+        let peer_id = UserId::from_bytes(GOLDEN_PEER_ID);
+        assert!(manager.is_peer_verified(&peer_id));
+        assert_eq!(
+            manager.peers[&peer_id].verified_key_fingerprint,
+            Some([0x33; auth::KEY_FINGERPRINT_SIZE])
+        );
+    }
+
+    #[test]
+    fn test_golden_peer_state_v2() {
+        let blob = read_golden("peer_state_v2.bin");
+        let key = crypto_aead::Key::from(GOLDEN_KEY);
+        let peer_id = UserId::from_bytes(GOLDEN_PEER_ID);
+
+        let mut storage = MemoryStorage::new();
+        storage.write_peer(&peer_id, &blob).unwrap();
+        let manager = SessionManager::restore_from(create_test_config(), &storage, &key).unwrap();
+        check_golden_request_dropped(&manager);
+        assert!(manager.is_peer_verified(&peer_id));
+
+        let serialized_state =
+            state_format::open(&blob, &key, peer_id.as_bytes(), StateKind::Peer).unwrap();
+        let reserialized =
+            bincode::serde::encode_to_vec(&manager.peers[&peer_id], bincode::config::standard())
+                .unwrap();
+        assert_eq!(reserialized, *serialized_state);
+    }
+
+    #[test]
+    fn test_golden_session_manager_v3() {
+        let blob = read_golden("session_manager_v3.bin");
+        let key = crypto_aead::Key::from(GOLDEN_KEY);
+
+        let manager = SessionManager::from_encrypted_blob(&blob, &key).unwrap();
+        let serialized_state =
+            state_format::open(&blob, &key, b"", StateKind::SessionManager).unwrap();
+        check_golden_session_manager(&manager, &serialized_state);
+        check_golden_request_dropped(&manager);
+
+        let peer_id = UserId::from_bytes(GOLDEN_PEER_ID);
+        assert!(manager.is_peer_verified(&peer_id));
+        assert!(manager.peers[&peer_id].healing.is_none());
+    }
+
+    #[test]
+    fn test_golden_peer_state_v3() {
+        let blob = read_golden("peer_state_v3.bin");
+        let key = crypto_aead::Key::from(GOLDEN_KEY);
+        let peer_id = UserId::from_bytes(GOLDEN_PEER_ID);
+
+        let mut storage = MemoryStorage::new();
+        storage.write_peer(&peer_id, &blob).unwrap();
+        let manager = SessionManager::restore_from(create_test_config(), &storage, &key).unwrap();
+        check_golden_request_dropped(&manager);
+        assert!(manager.is_peer_verified(&peer_id));
+        assert!(manager.peers[&peer_id].healing.is_none());
+
+        let serialized_state =
+            state_format::open(&blob, &key, peer_id.as_bytes(), StateKind::Peer).unwrap();
+        let reserialized =
+            bincode::serde::encode_to_vec(&manager.peers[&peer_id], bincode::config::standard())
+                .unwrap();
+        assert_eq!(reserialized, *serialized_state);
+    }
+
+    #[test]
+    fn test_golden_session_manager_v4() {
+        let blob = read_golden("session_manager_v4.bin");
+        let key = crypto_aead::Key::from(GOLDEN_KEY);
+
+        let manager = SessionManager::from_encrypted_blob(&blob, &key).unwrap();
+        let serialized_state =
+            state_format::open(&blob, &key, b"", StateKind::SessionManager).unwrap();
+        check_golden_session_manager(&manager, &serialized_state);
+        check_golden_request_dropped(&manager);
+
+        let peer_id = UserId::from_bytes(GOLDEN_PEER_ID);
+        assert!(manager.is_peer_verified(&peer_id));
+        assert!(manager.peers[&peer_id].healing.is_none());
+        assert!(manager.peers[&peer_id].replaced_session.is_none());
+    }
+
+    #[test]
+    fn test_golden_peer_state_v4() {
+        let blob = read_golden("peer_state_v4.bin");
+        let key = crypto_aead::Key::from(GOLDEN_KEY);
+        let peer_id = UserId::from_bytes(GOLDEN_PEER_ID);
+
+        let mut storage = MemoryStorage::new();
+        storage.write_peer(&peer_id, &blob).unwrap();
+        let manager = SessionManager::restore_from(create_test_config(), &storage, &key).unwrap();
+        check_golden_request_dropped(&manager);
+        assert!(manager.is_peer_verified(&peer_id));
+        assert!(manager.peers[&peer_id].replaced_session.is_none());
+
+        let serialized_state =
+            state_format::open(&blob, &key, peer_id.as_bytes(), StateKind::Peer).unwrap();
+        let reserialized =
+            bincode::serde::encode_to_vec(&manager.peers[&peer_id], bincode::config::standard())
+                .unwrap();
+        assert_eq!(reserialized, *serialized_state);
+    }
+
+    #[test]
+    fn test_golden_session_manager_v5() {
+        let blob = read_golden("session_manager_v5.bin");
+        let key = crypto_aead::Key::from(GOLDEN_KEY);
+
+        let manager = SessionManager::from_encrypted_blob(&blob, &key).unwrap();
+        let serialized_state =
+            state_format::open(&blob, &key, b"", StateKind::SessionManager).unwrap();
+        check_golden_session_manager(&manager, &serialized_state);
+        check_golden_request(&manager);
+
+        let peer_id = UserId::from_bytes(GOLDEN_PEER_ID);
+        assert!(manager.is_peer_verified(&peer_id));
+        assert!(manager.peers[&peer_id].healing.is_none());
+        assert!(manager.peers[&peer_id].replaced_session.is_none());
+    }
+
+    #[test]
+    fn test_golden_peer_state_v5() {
+        let blob = read_golden("peer_state_v5.bin");
+        let key = crypto_aead::Key::from(GOLDEN_KEY);
+        let peer_id = UserId::from_bytes(GOLDEN_PEER_ID);
+
+        let mut storage = MemoryStorage::new();
+        storage.write_peer(&peer_id, &blob).unwrap();
+        let manager = SessionManager::restore_from(create_test_config(), &storage, &key).unwrap();
+        check_golden_request(&manager);
+        assert!(manager.is_peer_verified(&peer_id));
+
+        let serialized_state =
+            state_format::open(&blob, &key, peer_id.as_bytes(), StateKind::Peer).unwrap();
+        let reserialized =
+            bincode::serde::encode_to_vec(&manager.peers[&peer_id], bincode::config::standard())
+                .unwrap();
+        assert_eq!(reserialized, *serialized_state);
+    }
+
+    #[test]
+    fn test_golden_states_v1_are_unverified() {
+        let key = crypto_aead::Key::from(GOLDEN_KEY);
+        let peer_id = UserId::from_bytes(GOLDEN_PEER_ID);
+
+        let blob = read_golden("session_manager_v1.bin");
+        let manager = SessionManager::from_encrypted_blob(&blob, &key).unwrap();
+        assert!(!manager.is_peer_verified(&peer_id));
+
+        let mut storage = MemoryStorage::new();
+        storage
+            .write_peer(&peer_id, &read_golden("peer_state_v1.bin"))
+            .unwrap();
+        let manager = SessionManager::restore_from(create_test_config(), &storage, &key).unwrap();
+        assert!(!manager.is_peer_verified(&peer_id));
+    }
+
+    #[test]
+    fn test_golden_agraphon_v1() {
+        // Ratchet state of an active session, unencrypted
+        let serialized_state = read_golden("agraphon_v1.bin");
+        let (agraphon, _): (crypto_agraphon::AgraphonV1, usize) =
+            bincode::serde::decode_from_slice(&serialized_state, bincode::config::standard())
+                .unwrap();
+        let reserialized =
+            bincode::serde::encode_to_vec(&agraphon, bincode::config::standard()).unwrap();
+        assert_eq!(reserialized, serialized_state);
+    }
+
+    #[test]
+    fn test_golden_agraphon_v5() {
+        // Ratchet state of an active session with a missing peer message, unencrypted
+        let serialized_state = read_golden("agraphon_v5.bin");
+        let (agraphon, _): (crypto_agraphon::Agraphon, usize) =
+            bincode::serde::decode_from_slice(&serialized_state, bincode::config::standard())
+                .unwrap();
+        assert_eq!(agraphon.skipped_message_count(), 1);
+        let reserialized =
+            bincode::serde::encode_to_vec(&agraphon, bincode::config::standard()).unwrap();
+        assert_eq!(reserialized, serialized_state);
+    }
+
+    #[test]
+    fn test_blob_from_newer_version_rejected() {
+        let mut blob = read_golden("session_manager_v1.bin");
+        let key = crypto_aead::Key::from(GOLDEN_KEY);
+        blob[4..6].copy_from_slice(&(state_format::STATE_SCHEMA_VERSION + 1).to_le_bytes());
+
+        assert_eq!(
+            SessionManager::from_encrypted_blob(&blob, &key).err(),
+            Some(SessionError::UnsupportedStateVersion)
+        );
+    }
 }
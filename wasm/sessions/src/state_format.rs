@@ -0,0 +1,280 @@
+//! Versioned envelope for persisted session state.
+//!
+//! Persisted state is encrypted as:
+//!
+//! ```text
+//! [magic (4 bytes) || schema version (u16 LE) || nonce (16 bytes) || ciphertext]
+//! ```
+//!
+//! The header is authenticated as associated data, together with a context that binds
+//! the state to its use (e.g. the peer ID of a per-peer state).
+//!
+//! The schema version identifies the layout of the serialized structs (`SessionManager`,
+//! `PeerInfo`, `Session`, `Agraphon`, `GroupManager`, `FragmentReassembler`...). Any change
+//! to that layout must bump [`STATE_SCHEMA_VERSION`] and register a migration in
+//! [`MIGRATIONS`] that upgrades the serialized state of the previous version, so that saved
+//! states remain readable.
+//!
+//! Version 0 is the unversioned `[nonce || ciphertext]` format of whole session manager
+//! blobs written before the envelope was introduced.
+//!
+//! Version 1 introduced the envelope along with the out-of-order delivery of messages,
+//! which changed the layout of `SessionManagerConfig`, `Session` and `Agraphon`.
+//!
+//! Version 2 records the out-of-band verification of each peer in `PeerInfo`.
+//!
+//! Version 3 records the healing of broken sessions in `PeerInfo`.
+//!
+//! Version 4 keeps the session replaced by the active one in `PeerInfo`.
+//!
+//! Version 5 moved to the session protocol version 2, which changed the layout of `Session`
+//! and `Agraphon`. Sessions of the previous protocol are dropped and healed.
+
+use crate::{error::SessionError, session_manager};
+use zeroize::Zeroizing;
+
+/// Magic number identifying a versioned session state.
+pub(crate) const STATE_MAGIC: [u8; 4] = *b"GSSM";
+
+/// Current schema version of the serialized state.
+pub(crate) const STATE_SCHEMA_VERSION: u16 = 5;
+
+/// Size of the `[magic || schema version]` header.
+const HEADER_SIZE: usize = STATE_MAGIC.len() + 2;
+
+/// Kind of serialized state, for migrations that need to tell them apart.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum StateKind {
+    /// A whole `SessionManager`
+    SessionManager,
+    /// The `PeerInfo` of a single peer
+    Peer,
+    /// A whole `GroupManager`
+    GroupManager,
+    /// A whole `FragmentReassembler`
+    FragmentReassembler,
+}
+
+/// Upgrades a serialized state by one schema version.
+type Migration = fn(StateKind, Zeroizing<Vec<u8>>) -> Result<Zeroizing<Vec<u8>>, SessionError>;
+
+/// Migrations indexed by the schema version they upgrade from:
+/// `MIGRATIONS[v]` upgrades a state of version `v` to version `v + 1`.
+const MIGRATIONS: [Migration; STATE_SCHEMA_VERSION as usize] = [
+    session_manager::migrate_v0_to_v1,
+    session_manager::migrate_v1_to_v2,
+    session_manager::migrate_v2_to_v3,
+    session_manager::migrate_v3_to_v4,
+    session_manager::migrate_v4_to_v5,
+];
+
+/// Upgrades a serialized state of schema version `version` to the current version.
+fn migrate(
+    kind: StateKind,
+    mut state: Zeroizing<Vec<u8>>,
+    version: u16,
+) -> Result<Zeroizing<Vec<u8>>, SessionError> {
+    for migration in &MIGRATIONS[usize::from(version)..] {
+        state = migration(kind, state)?;
+    }
+    Ok(state)
+}
+
+fn header(version: u16) -> [u8; HEADER_SIZE] {
+    let mut header = [0u8; HEADER_SIZE];
+    header[..STATE_MAGIC.len()].copy_from_slice(&STATE_MAGIC);
+    header[STATE_MAGIC.len()..].copy_from_slice(&version.to_le_bytes());
+    header
+}
+
+/// Encrypts a serialized state of the current schema version.
+pub(crate) fn seal(serialized_state: &[u8], key: &crypto_aead::Key, context: &[u8]) -> Vec<u8> {
+    // generate nonce
+    let nonce = {
+        let mut nonce_bytes = [0u8; crypto_aead::NONCE_SIZE];
+        crypto_rng::fill_buffer(&mut nonce_bytes);
+        crypto_aead::Nonce::from(nonce_bytes)
+    };
+
+    // encrypt, authenticating the header and the context
+    let header = header(STATE_SCHEMA_VERSION);
+    let aad = [header.as_slice(), context].concat();
+    let encrypted_state = Zeroizing::new(crypto_aead::encrypt(key, &nonce, serialized_state, &aad));
+
+    // assemble the envelope
+    [
+        header.as_slice(),
+        nonce.as_bytes().as_slice(),
+        &encrypted_state,
+    ]
+    .concat()
+}
+
+/// Decrypts a versioned state and upgrades it to the current schema version.
+///
+/// # Errors
+///
+/// * [`SessionError::StateUndecryptable`] - If the envelope is truncated, has no magic number,
+///   or cannot be decrypted (wrong key, wrong context, corrupted data)
+/// * [`SessionError::UnsupportedStateVersion`] - If the state was written with a newer schema
+/// * The errors of the migrations
+pub(crate) fn open(
+    encrypted_state: &[u8],
+    key: &crypto_aead::Key,
+    context: &[u8],
+    kind: StateKind,
+) -> Result<Zeroizing<Vec<u8>>, SessionError> {
+    // read header
+    let (header, sealed_state) = encrypted_state
+        .split_at_checked(HEADER_SIZE)
+        .ok_or(SessionError::StateUndecryptable)?;
+    let (magic, version) = header.split_at(STATE_MAGIC.len());
+    if magic != STATE_MAGIC {
+        return Err(SessionError::StateUndecryptable);
+    }
+    let version = u16::from_le_bytes([version[0], version[1]]);
+    if version == 0 || version > STATE_SCHEMA_VERSION {
+        return Err(SessionError::UnsupportedStateVersion);
+    }
+
+    // decrypt
+    let aad = [header, context].concat();
+    let serialized_state = decrypt(sealed_state, key, &aad)?;
+
+    // upgrade
+    migrate(kind, serialized_state, version)
+}
+
+/// Decrypts a whole session manager blob written before versioning (schema version 0)
+/// and upgrades it to the current schema version.
+pub(crate) fn open_unversioned(
+    encrypted_state: &[u8],
+    key: &crypto_aead::Key,
+) -> Result<Zeroizing<Vec<u8>>, SessionError> {
+    let serialized_state = decrypt(encrypted_state, key, b"")?;
+    migrate(StateKind::SessionManager, serialized_state, 0)
+}
+
+/// Decrypts `[nonce || ciphertext]`.
+fn decrypt(
+    sealed_state: &[u8],
+    key: &crypto_aead::Key,
+    aad: &[u8],
+) -> Result<Zeroizing<Vec<u8>>, SessionError> {
+    // read nonce
+    let (nonce_bytes, ciphertext) = sealed_state
+        .split_at_checked(crypto_aead::NONCE_SIZE)
+        .ok_or(SessionError::StateUndecryptable)?;
+    let nonce_bytes: [u8; crypto_aead::NONCE_SIZE] = nonce_bytes
+        .try_into()
+        .map_err(|_| SessionError::StateUndecryptable)?;
+    let nonce = crypto_aead::Nonce::from(nonce_bytes);
+
+    // decrypt
+    crypto_aead::decrypt(key, &nonce, ciphertext, aad)
+        .map(Zeroizing::new)
+        .ok_or(SessionError::StateUndecryptable)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generate_test_key() -> crypto_aead::Key {
+        let mut key_bytes = [0u8; crypto_aead::KEY_SIZE];
+        crypto_rng::fill_buffer(&mut key_bytes);
+        crypto_aead::Key::from(key_bytes)
+    }
+
+    #[test]
+    fn test_seal_open_roundtrip() {
+        let key = generate_test_key();
+        let sealed = seal(b"state", &key, b"context");
+
+        assert_eq!(&sealed[..HEADER_SIZE], &header(STATE_SCHEMA_VERSION));
+        let opened = open(&sealed, &key, b"context", StateKind::Peer).unwrap();
+        assert_eq!(opened.as_slice(), b"state");
+    }
+
+    #[test]
+    fn test_open_checks_context_and_key() {
+        let key = generate_test_key();
+        let sealed = seal(b"state", &key, b"context");
+
+        assert_eq!(
+            open(&sealed, &key, b"other context", StateKind::Peer),
+            Err(SessionError::StateUndecryptable)
+        );
+        assert_eq!(
+            open(&sealed, &generate_test_key(), b"context", StateKind::Peer),
+            Err(SessionError::StateUndecryptable)
+        );
+    }
+
+    #[test]
+    fn test_open_checks_header() {
+        let key = generate_test_key();
+        let sealed = seal(b"state", &key, b"");
+
+        // truncated
+        assert_eq!(
+            open(
+                &sealed[..HEADER_SIZE - 1],
+                &key,
+                b"",
+                StateKind::SessionManager
+            ),
+            Err(SessionError::StateUndecryptable)
+        );
+
+        // no magic number
+        let mut tampered = sealed.clone();
+        tampered[0] ^= 1;
+        assert_eq!(
+            open(&tampered, &key, b"", StateKind::SessionManager),
+            Err(SessionError::StateUndecryptable)
+        );
+
+        // unknown versions
+        for version in [0, STATE_SCHEMA_VERSION + 1] {
+            let mut tampered = sealed.clone();
+            tampered[..HEADER_SIZE].copy_from_slice(&header(version));
+            assert_eq!(
+                open(&tampered, &key, b"", StateKind::SessionManager),
+                Err(SessionError::UnsupportedStateVersion)
+            );
+        }
+    }
+
+    fn seal_unversioned(serialized_state: &[u8], key: &crypto_aead::Key) -> Vec<u8> {
+        let nonce = crypto_aead::Nonce::from([7u8; crypto_aead::NONCE_SIZE]);
+        [
+            nonce.as_bytes().as_slice(),
+            &crypto_aead::encrypt(key, &nonce, serialized_state, b""),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn test_open_unversioned() {
+        let key = generate_test_key();
+        // a session manager of version 0 with a zeroed config and no peers
+        let legacy_state = [0u8; 8];
+        let legacy = seal_unversioned(&legacy_state, &key);
+
+        let opened = open_unversioned(&legacy, &key).unwrap();
+        let migrated = migrate(
+            StateKind::SessionManager,
+            Zeroizing::new(legacy_state.to_vec()),
+            0,
+        )
+        .unwrap();
+        assert_eq!(opened, migrated);
+
+        // the state is decoded by the migrations
+        assert_eq!(
+            open_unversioned(&seal_unversioned(b"state", &key), &key),
+            Err(SessionError::StateMalformed)
+        );
+    }
+}
@@ -0,0 +1,78 @@
+//! Per-peer persistence of session manager state.
+//!
+//! Instead of re-encrypting the whole session manager after every change, the state of
+//! each peer is encrypted separately and written to a [`SessionStorage`] backend. The
+//! session manager tracks which peers changed, so that only those are written by
+//! [`SessionManager::persist_to`](crate::SessionManager::persist_to).
+
+use auth::UserId;
+use std::{collections::HashMap, convert::Infallible};
+
+/// A backend storing the encrypted state of each peer.
+///
+/// Entries are opaque encrypted blobs keyed by peer ID. They are bound to their peer ID,
+/// so a backend that mixes up entries causes a restore error rather than a corrupted state.
+pub trait SessionStorage {
+    /// Error returned by the backend.
+    type Error;
+
+    /// Stores the encrypted state of a peer, replacing any previous state.
+    fn write_peer(&mut self, peer_id: &UserId, encrypted_state: &[u8]) -> Result<(), Self::Error>;
+
+    /// Removes the stored state of a peer. Removing an absent peer is not an error.
+    fn delete_peer(&mut self, peer_id: &UserId) -> Result<(), Self::Error>;
+
+    /// Returns the encrypted states of all stored peers.
+    fn read_peers(&self) -> Result<Vec<(UserId, Vec<u8>)>, Self::Error>;
+}
+
+/// An in-memory [`SessionStorage`].
+///
+/// Useful for tests, and as a staging area when the actual storage is asynchronous.
+#[derive(Default)]
+pub struct MemoryStorage {
+    peers: HashMap<UserId, Vec<u8>>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the stored encrypted state of a peer.
+    pub fn get(&self, peer_id: &UserId) -> Option<&[u8]> {
+        self.peers.get(peer_id).map(Vec::as_slice)
+    }
+
+    /// Returns the number of stored peers.
+    pub fn len(&self) -> usize {
+        self.peers.len()
+    }
+
+    /// Returns `true` if no peer is stored.
+    pub fn is_empty(&self) -> bool {
+        self.peers.is_empty()
+    }
+}
+
+impl SessionStorage for MemoryStorage {
+    type Error = Infallible;
+
+    fn write_peer(&mut self, peer_id: &UserId, encrypted_state: &[u8]) -> Result<(), Self::Error> {
+        self.peers.insert(peer_id.clone(), encrypted_state.to_vec());
+        Ok(())
+    }
+
+    fn delete_peer(&mut self, peer_id: &UserId) -> Result<(), Self::Error> {
+        self.peers.remove(peer_id);
+        Ok(())
+    }
+
+    fn read_peers(&self) -> Result<Vec<(UserId, Vec<u8>)>, Self::Error> {
+        Ok(self
+            .peers
+            .iter()
+            .map(|(peer_id, encrypted_state)| (peer_id.clone(), encrypted_state.clone()))
+            .collect())
+    }
+}
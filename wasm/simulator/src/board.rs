@@ -0,0 +1,153 @@
+//! In-memory versions of the boards the clients read and write.
+//!
+//! They mimic the storage API used by the application: announcements are appended to a
+//! bulletin and read from a counter onwards, and messages are stored in a key-value store
+//! under their seeker.
+
+use std::collections::HashMap;
+
+/// An append-only bulletin of announcements.
+#[derive(Debug, Default)]
+pub struct AnnouncementBoard {
+    announcements: Vec<Vec<u8>>,
+}
+
+impl AnnouncementBoard {
+    /// Creates an empty bulletin.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends an announcement and returns its bulletin counter.
+    pub fn post(&mut self, announcement: Vec<u8>) -> usize {
+        self.announcements.push(announcement);
+        self.announcements.len()
+    }
+
+    /// Returns the announcements posted after the first `counter` ones.
+    pub fn read_from(&self, counter: usize) -> &[Vec<u8>] {
+        self.announcements.get(counter..).unwrap_or_default()
+    }
+
+    /// Returns the number of announcements posted so far.
+    pub fn len(&self) -> usize {
+        self.announcements.len()
+    }
+
+    /// Returns `true` if no announcement was posted yet.
+    pub fn is_empty(&self) -> bool {
+        self.announcements.is_empty()
+    }
+}
+
+/// A key-value store of messages, keyed by seeker.
+///
+/// Each seeker holds a single message, which is never overwritten. Messages are returned in
+/// the order they were posted.
+#[derive(Debug, Default)]
+pub struct MessageBoard {
+    /// Messages and their posting order, by seeker
+    messages: HashMap<Vec<u8>, (u64, Vec<u8>)>,
+    /// Number of messages posted so far
+    posted: u64,
+}
+
+impl MessageBoard {
+    /// Creates an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores a message under its seeker.
+    ///
+    /// Returns `false`, leaving the store unchanged, if the seeker already holds a message.
+    pub fn post(&mut self, seeker: Vec<u8>, data: Vec<u8>) -> bool {
+        if self.messages.contains_key(&seeker) {
+            return false;
+        }
+        self.messages.insert(seeker, (self.posted, data));
+        self.posted += 1;
+        true
+    }
+
+    /// Returns the message stored under a seeker, if any.
+    pub fn read(&self, seeker: &[u8]) -> Option<&[u8]> {
+        self.messages.get(seeker).map(|(_, data)| data.as_slice())
+    }
+
+    /// Returns the `(seeker, message)` pairs stored under any of `seekers`, in posting order.
+    pub fn fetch(&self, seekers: &[Vec<u8>]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let mut found: Vec<_> = seekers
+            .iter()
+            .filter_map(|seeker| {
+                self.messages
+                    .get(seeker)
+                    .map(|(order, data)| (*order, seeker.clone(), data.clone()))
+            })
+            .collect();
+        found.sort_unstable_by_key(|(order, _, _)| *order);
+        found.dedup_by_key(|(order, _, _)| *order);
+        found
+            .into_iter()
+            .map(|(_, seeker, data)| (seeker, data))
+            .collect()
+    }
+
+    /// Returns the number of stored messages.
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    /// Returns `true` if no message is stored.
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_announcement_board_reads_from_counter() {
+        let mut board = AnnouncementBoard::new();
+        assert!(board.is_empty());
+        assert_eq!(board.post(b"first".to_vec()), 1);
+        assert_eq!(board.post(b"second".to_vec()), 2);
+
+        assert_eq!(board.read_from(0), [b"first".to_vec(), b"second".to_vec()]);
+        assert_eq!(board.read_from(1), [b"second".to_vec()]);
+        assert!(board.read_from(2).is_empty());
+        assert!(board.read_from(10).is_empty());
+    }
+
+    #[test]
+    fn test_message_board_keeps_first_message() {
+        let mut board = MessageBoard::new();
+        assert!(board.post(b"seeker".to_vec(), b"first".to_vec()));
+        assert!(!board.post(b"seeker".to_vec(), b"second".to_vec()));
+        assert_eq!(board.read(b"seeker"), Some(&b"first"[..]));
+        assert_eq!(board.read(b"other"), None);
+        assert_eq!(board.len(), 1);
+    }
+
+    #[test]
+    fn test_message_board_fetches_in_posting_order() {
+        let mut board = MessageBoard::new();
+        board.post(b"b".to_vec(), b"1".to_vec());
+        board.post(b"a".to_vec(), b"2".to_vec());
+        board.post(b"c".to_vec(), b"3".to_vec());
+
+        let seekers = [b"c".to_vec(), b"a".to_vec(), b"b".to_vec(), b"a".to_vec()];
+        let fetched = board.fetch(&seekers);
+        assert_eq!(
+            fetched,
+            [
+                (b"b".to_vec(), b"1".to_vec()),
+                (b"a".to_vec(), b"2".to_vec()),
+                (b"c".to_vec(), b"3".to_vec()),
+            ]
+        );
+        assert!(board.fetch(&[b"missing".to_vec()]).is_empty());
+    }
+}
@@ -0,0 +1,44 @@
+//! Faults injected between the clients and the boards.
+
+/// Faults injected by a [`Simulation`](crate::Simulation).
+///
+/// Drops, delays and duplicates apply to every post and poll. Restarts and clock skew are
+/// only drawn by [`run_random`](crate::Simulation::run_random), scripted scenarios apply them
+/// explicitly.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Faults {
+    /// Probability that a posted message is lost before reaching the message board.
+    ///
+    /// Announcements are never lost: the session manager does not send them again, so a lost
+    /// announcement leaves its sender waiting for good.
+    pub drop_rate: f64,
+    /// Probability, at each poll of a client, that it reads again one of the posts addressed to
+    /// it that already reached the boards.
+    pub duplicate_rate: f64,
+    /// Maximum delay before a post reaches the boards. Each post is delayed independently, so
+    /// posts can be read in another order than they were sent.
+    pub max_delay_millis: u128,
+    /// Probability, at each step of a random run, that a client restarts from its encrypted
+    /// session manager state.
+    pub restart_rate: f64,
+    /// Maximum offset of the clock of a client from the simulation time, in either direction.
+    /// A new offset is drawn for each client that restarts during a random run.
+    pub max_clock_skew_millis: u128,
+}
+
+impl Faults {
+    /// No faults: posts reach the boards right away and clients never restart.
+    pub const NONE: Self = Self {
+        drop_rate: 0.0,
+        duplicate_rate: 0.0,
+        max_delay_millis: 0,
+        restart_rate: 0.0,
+        max_clock_skew_millis: 0,
+    };
+}
+
+impl Default for Faults {
+    fn default() -> Self {
+        Self::NONE
+    }
+}
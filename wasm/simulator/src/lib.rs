@@ -0,0 +1,66 @@
+//! # simulator
+//!
+//! An in-memory message board simulator, to test many [`sessions::SessionManager`]s talking to
+//! each other end to end.
+//!
+//! ## Boards
+//!
+//! [`AnnouncementBoard`] and [`MessageBoard`] mimic the storage used by the application: an
+//! append-only bulletin of announcements read from a counter onwards, and a key-value store of
+//! messages keyed by seeker.
+//!
+//! ## Simulations
+//!
+//! A [`Simulation`] runs clients that each own a session manager, keys and a clock, and that
+//! read and write the boards like the application does. Scenarios can be scripted (connect,
+//! send, deliver posts in a chosen order, drop or replay them, restart a client, skew its clock)
+//! or randomized with [`Simulation::run_random`], which injects the configured [`Faults`]:
+//!
+//! - **Drops**: messages lost before reaching the board
+//! - **Reordering**: posts delayed independently of each other
+//! - **Duplicates**: posts read again by their recipient
+//! - **Clock skew**: clients whose clocks are offset from each other
+//! - **Restarts**: clients restored from their `to_encrypted_blob()` state
+//!
+//! Every message a client accepts is checked against what its peer sent, and
+//! [`Simulation::check_agreement`] checks, once all posts are read, that the clients agree on
+//! their sessions and that no message was lost apart from the dropped ones. The random choices
+//! of a simulation derive from its seed, so a failing seed replays the same schedule.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use simulator::{Faults, Simulation};
+//!
+//! let mut simulation = Simulation::new(42);
+//! let alice = simulation.add_client("alice");
+//! let bob = simulation.add_client("bob");
+//!
+//! simulation.connect(alice, bob);
+//! simulation.settle();
+//! simulation.send(alice, bob, b"hello bob").expect("Failed to send message");
+//! simulation.step();
+//! assert_eq!(simulation.inbox(bob), [(alice, b"hello bob".to_vec())]);
+//!
+//! simulation.set_faults(Faults {
+//!     drop_rate: 0.05,
+//!     duplicate_rate: 0.1,
+//!     max_delay_millis: 2_000,
+//!     restart_rate: 0.05,
+//!     max_clock_skew_millis: 1_000,
+//! });
+//! simulation.run_random(20);
+//! assert_eq!(simulation.check_agreement(), Ok(()));
+//! ```
+
+mod board;
+mod faults;
+mod simulation;
+mod violation;
+
+pub use board::{AnnouncementBoard, MessageBoard};
+pub use faults::Faults;
+pub use simulation::{
+    ClientId, PostId, PostInfo, PostKind, START_MILLIS, Simulation, default_session_config,
+};
+pub use violation::Violation;
@@ -0,0 +1,1071 @@
+//! Clients exchanging announcements and messages through simulated boards.
+
+use crate::board::{AnnouncementBoard, MessageBoard};
+use crate::faults::Faults;
+use crate::violation::Violation;
+use auth::{UserId, UserPublicKeys, UserSecretKeys};
+use rand_chacha::ChaCha8Rng;
+use rand_chacha::rand_core::{RngCore, SeedableRng};
+use sessions::{
+    AnnouncementError, ManualClock, SessionError, SessionManager, SessionManagerConfig,
+    SessionStatus,
+};
+use std::collections::{BTreeMap, HashMap};
+
+/// Unix time at which simulations start, in milliseconds
+pub const START_MILLIS: u128 = 1_700_000_000_000;
+
+/// Maximum time elapsed between two steps of a random run, in milliseconds
+const MAX_RANDOM_STEP_MILLIS: u128 = 5_000;
+
+/// Maximum number of delivery rounds of [`Simulation::settle`]
+const MAX_SETTLE_ROUNDS: usize = 64;
+
+/// Contents of the messages sent by [`Simulation::check_agreement`] over each session
+const PROBE_MESSAGE: &[u8] = b"agreement probe";
+
+/// Session manager configuration used by [`Simulation::new`].
+pub fn default_session_config() -> SessionManagerConfig {
+    SessionManagerConfig {
+        max_incoming_announcement_age_millis: 60_000,
+        max_incoming_announcement_future_millis: 5_000,
+        max_incoming_message_age_millis: 300_000,
+        max_incoming_message_future_millis: 5_000,
+        max_session_inactivity_millis: 3_600_000,
+        keep_alive_interval_millis: 60_000,
+        max_session_lag_length: 100,
+        max_skipped_messages: 16,
+        max_stored_skipped_messages: 128,
+    }
+}
+
+/// Identifies a client of a simulation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ClientId(usize);
+
+/// Identifies a post, in the order posts were made.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PostId(usize);
+
+/// What a post carries.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PostKind {
+    /// An announcement, posted to the announcement board
+    Announcement,
+    /// A session message, posted to the message board
+    Message,
+}
+
+/// Public description of a post.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PostInfo {
+    pub id: PostId,
+    pub kind: PostKind,
+    /// Client that made the post
+    pub from: ClientId,
+    /// Client the post is addressed to
+    pub to: ClientId,
+}
+
+/// Where a post stands between its sender and the boards.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PostState {
+    /// The post reaches the boards at the given simulation time
+    InFlight { visible_at: u128 },
+    /// The post was lost
+    Dropped,
+    /// The post is on the boards
+    Landed,
+}
+
+enum Payload {
+    Announcement(Vec<u8>),
+    Message {
+        seeker: Vec<u8>,
+        data: Vec<u8>,
+        /// Plaintext, to check what the recipient reads
+        contents: Vec<u8>,
+    },
+}
+
+struct Post {
+    info: PostInfo,
+    payload: Payload,
+    state: PostState,
+    /// Whether the recipient accepted the message
+    received: bool,
+}
+
+struct Client {
+    name: String,
+    public_keys: UserPublicKeys,
+    secret_keys: UserSecretKeys,
+    manager: SessionManager,
+    clock: ManualClock,
+    clock_skew_millis: i128,
+    /// Key of the encrypted state the client restarts from
+    state_key: crypto_aead::Key,
+    /// Number of announcements of the board already read
+    announcement_counter: usize,
+    /// Non-empty messages accepted by the client, with their sender
+    inbox: Vec<(ClientId, Vec<u8>)>,
+}
+
+/// Deterministic source of the simulation choices, independent of the cryptographic random
+/// source so that a seed always replays the same schedule.
+struct Dice(ChaCha8Rng);
+
+impl Dice {
+    /// Returns a number below `bound`, or 0 if `bound` is 0.
+    fn below(&mut self, bound: u128) -> u128 {
+        if bound == 0 {
+            return 0;
+        }
+        let value = (u128::from(self.0.next_u64()) << 64) | u128::from(self.0.next_u64());
+        value % bound
+    }
+
+    /// Returns `true` with the given probability.
+    fn chance(&mut self, probability: f64) -> bool {
+        let draw = (self.0.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+        draw < probability
+    }
+
+    /// Picks one of `count` items, which must not be 0.
+    fn pick(&mut self, count: usize) -> usize {
+        self.below(count as u128) as usize
+    }
+}
+
+/// Clients running their own [`SessionManager`] and talking through shared in-memory boards.
+///
+/// Each client has its own keys, clock and encrypted state. Posts go through a network layer
+/// that injects the configured [`Faults`] before they reach the boards, and every message a
+/// client accepts is checked against what its peer actually sent. Clients behave like the
+/// application: they answer every session request, send the keep-alive messages requested
+/// by [`SessionManager::refresh`], and the announcements of [`SessionManager::heal_sessions`].
+///
+/// Time only moves with [`advance`](Self::advance) and [`settle`](Self::settle).
+pub struct Simulation {
+    session_config: fn() -> SessionManagerConfig,
+    faults: Faults,
+    dice: Dice,
+    now_millis: u128,
+    clients: Vec<Client>,
+    clients_by_user_id: HashMap<Vec<u8>, ClientId>,
+    announcement_board: AnnouncementBoard,
+    message_board: MessageBoard,
+    posts: Vec<Post>,
+    /// Post of each announcement of the board
+    announcement_posts: Vec<PostId>,
+    /// Post of each message, by seeker
+    message_posts: HashMap<Vec<u8>, PostId>,
+    /// Violations detected while running
+    violations: Vec<Violation>,
+    /// Number of messages sent by random runs
+    random_messages: u64,
+}
+
+impl Simulation {
+    /// Creates a simulation without faults, whose random choices derive from `seed`.
+    pub fn new(seed: u64) -> Self {
+        Self::with_session_config(seed, default_session_config)
+    }
+
+    /// Creates a simulation whose clients use the configuration returned by `session_config`.
+    pub fn with_session_config(seed: u64, session_config: fn() -> SessionManagerConfig) -> Self {
+        Self {
+            session_config,
+            faults: Faults::NONE,
+            dice: Dice(ChaCha8Rng::seed_from_u64(seed)),
+            now_millis: START_MILLIS,
+            clients: Vec::new(),
+            clients_by_user_id: HashMap::new(),
+            announcement_board: AnnouncementBoard::new(),
+            message_board: MessageBoard::new(),
+            posts: Vec::new(),
+            announcement_posts: Vec::new(),
+            message_posts: HashMap::new(),
+            violations: Vec::new(),
+            random_messages: 0,
+        }
+    }
+
+    /// Sets the faults injected from now on.
+    pub fn set_faults(&mut self, faults: Faults) {
+        self.faults = faults;
+    }
+
+    /// Adds a client with its own keys and an empty session manager.
+    ///
+    /// The keys derive from the position of the client, so they are the same in every run.
+    pub fn add_client(&mut self, name: &str) -> ClientId {
+        let id = ClientId(self.clients.len());
+        let mut root_secret = [0u8; auth::STATIC_ROOT_SECRET_SIZE];
+        root_secret[..8].copy_from_slice(&(id.0 as u64).to_le_bytes());
+        let (public_keys, secret_keys) = auth::derive_keys_from_static_root_secret(
+            &auth::StaticRootSecret::from_bytes(root_secret),
+        );
+
+        let mut state_key = [0u8; crypto_aead::KEY_SIZE];
+        crypto_rng::fill_buffer(&mut state_key);
+        let clock = ManualClock::new(self.now_millis);
+
+        self.clients_by_user_id
+            .insert(public_keys.derive_id().as_bytes().to_vec(), id);
+        self.clients.push(Client {
+            name: name.to_string(),
+            public_keys,
+            secret_keys,
+            manager: SessionManager::with_clock((self.session_config)(), clock.clone()),
+            clock,
+            clock_skew_millis: 0,
+            state_key: crypto_aead::Key::from(state_key),
+            announcement_counter: 0,
+            inbox: Vec::new(),
+        });
+        id
+    }
+
+    /// Returns the name a client was added with.
+    pub fn name(&self, client: ClientId) -> &str {
+        &self.clients[client.0].name
+    }
+
+    /// Returns the user ID of a client.
+    pub fn user_id(&self, client: ClientId) -> UserId {
+        self.clients[client.0].public_keys.derive_id()
+    }
+
+    /// Returns the session manager of a client.
+    pub fn manager(&self, client: ClientId) -> &SessionManager {
+        &self.clients[client.0].manager
+    }
+
+    /// Returns the status of the session of `client` with `peer`, as seen by `client`.
+    pub fn status(&self, client: ClientId, peer: ClientId) -> SessionStatus {
+        self.manager(client)
+            .peer_session_status(&self.user_id(peer))
+    }
+
+    /// Returns the non-empty messages accepted by a client so far, with their sender.
+    ///
+    /// Keep-alive messages are empty, so they are not listed.
+    pub fn inbox(&self, client: ClientId) -> &[(ClientId, Vec<u8>)] {
+        &self.clients[client.0].inbox
+    }
+
+    /// Returns the current simulation time, which clients see through their clock skew.
+    pub fn now_millis(&self) -> u128 {
+        self.now_millis
+    }
+
+    pub fn announcement_board(&self) -> &AnnouncementBoard {
+        &self.announcement_board
+    }
+
+    pub fn message_board(&self) -> &MessageBoard {
+        &self.message_board
+    }
+
+    /// Returns the violations detected so far.
+    ///
+    /// They are detected as the clients read the boards. [`check_agreement`](Self::check_agreement)
+    /// adds the ones that can only be checked once the simulation is settled.
+    pub fn violations(&self) -> &[Violation] {
+        &self.violations
+    }
+
+    /// Makes `from` request a session with `to`, and returns the post of its announcement.
+    pub fn connect(&mut self, from: ClientId, to: ClientId) -> PostId {
+        let peer_pk = self.clients[to.0].public_keys.clone();
+        let client = &mut self.clients[from.0];
+        let announcement = client.manager.establish_outgoing_session(
+            &peer_pk,
+            &client.public_keys,
+            &client.secret_keys,
+            Vec::new(),
+        );
+        self.post(from, to, Payload::Announcement(announcement))
+    }
+
+    /// Sends a message from `from` to `to`, and returns its post.
+    ///
+    /// # Errors
+    ///
+    /// The errors of [`SessionManager::send_message`]: nothing is posted.
+    pub fn send(
+        &mut self,
+        from: ClientId,
+        to: ClientId,
+        contents: &[u8],
+    ) -> Result<PostId, SessionError> {
+        let peer_id = self.user_id(to);
+        let output = self.clients[from.0]
+            .manager
+            .send_message(&peer_id, contents)?;
+        let id = self.post(
+            from,
+            to,
+            Payload::Message {
+                seeker: output.seeker.clone(),
+                data: output.data.clone(),
+                contents: contents.to_vec(),
+            },
+        );
+        self.message_posts.insert(output.seeker.clone(), id);
+        Ok(id)
+    }
+
+    /// Offsets the clock of a client from the simulation time.
+    pub fn set_clock_skew(&mut self, client: ClientId, skew_millis: i128) {
+        self.clients[client.0].clock_skew_millis = skew_millis;
+        self.sync_clocks();
+    }
+
+    /// Restarts a client: its session manager is replaced with the one restored from its
+    /// encrypted blob.
+    pub fn restart(&mut self, client: ClientId) {
+        let client_state = &mut self.clients[client.0];
+        let blob = client_state
+            .manager
+            .to_encrypted_blob(&client_state.state_key)
+            .expect("Failed to serialize session manager");
+        match SessionManager::from_encrypted_blob(&blob, &client_state.state_key) {
+            Ok(mut manager) => {
+                manager.set_clock(client_state.clock.clone());
+                client_state.manager = manager;
+            }
+            Err(error) => self.violations.push(Violation::RestartFailed {
+                client: client_state.name.clone(),
+                error,
+            }),
+        }
+    }
+
+    /// Returns the posts that did not reach the boards yet, in the order they will.
+    pub fn in_flight(&self) -> Vec<PostInfo> {
+        self.in_flight_posts()
+            .into_iter()
+            .map(|(_, id)| self.posts[id.0].info)
+            .collect()
+    }
+
+    /// Makes a post in flight reach the boards now, whatever its delay.
+    ///
+    /// Returns `false` if the post is not in flight.
+    pub fn deliver(&mut self, post: PostId) -> bool {
+        if !matches!(self.posts[post.0].state, PostState::InFlight { .. }) {
+            return false;
+        }
+        self.land(post);
+        true
+    }
+
+    /// Loses a post in flight.
+    ///
+    /// Returns `false` if the post is not in flight.
+    pub fn drop_post(&mut self, post: PostId) -> bool {
+        let state = &mut self.posts[post.0].state;
+        if !matches!(state, PostState::InFlight { .. }) {
+            return false;
+        }
+        *state = PostState::Dropped;
+        true
+    }
+
+    /// Makes the recipient of a post that reached the boards read it again.
+    ///
+    /// Returns `false` if the post is not on the boards.
+    pub fn replay(&mut self, post: PostId) -> bool {
+        if self.posts[post.0].state != PostState::Landed {
+            return false;
+        }
+        let info = self.posts[post.0].info;
+        match &self.posts[post.0].payload {
+            Payload::Announcement(announcement) => {
+                let announcement = announcement.clone();
+                self.read_announcement(info.to, &announcement, info, true);
+            }
+            Payload::Message { seeker, data, .. } => {
+                let (seeker, data) = (seeker.clone(), data.clone());
+                self.read_message(info.to, &seeker, &data);
+            }
+        }
+        true
+    }
+
+    /// Makes a client read the boards like the application does: new announcements, then
+    /// messages under its seekers, then keep-alive messages and healing announcements if needed.
+    pub fn poll(&mut self, client: ClientId) {
+        self.read_announcements(client);
+        self.read_messages(client);
+
+        if self.dice.chance(self.faults.duplicate_rate) {
+            let landed: Vec<PostId> = self
+                .posts
+                .iter()
+                .filter(|post| post.info.to == client && post.state == PostState::Landed)
+                .map(|post| post.info.id)
+                .collect();
+            if !landed.is_empty() {
+                let post = landed[self.dice.pick(landed.len())];
+                self.replay(post);
+            }
+        }
+
+        let mut keep_alive_peers = self.clients[client.0].manager.refresh();
+        keep_alive_peers.sort_unstable_by(|a, b| a.as_bytes().cmp(b.as_bytes()));
+        for peer_id in keep_alive_peers {
+            if let Some(&peer) = self.clients_by_user_id.get(peer_id.as_bytes()) {
+                // a saturated session cannot send keep-alive messages until the peer responds
+                let _ = self.send(client, peer, &[]);
+            }
+        }
+
+        let client_state = &mut self.clients[client.0];
+        let mut healing = client_state
+            .manager
+            .heal_sessions(&client_state.public_keys, &client_state.secret_keys);
+        healing.sort_unstable_by(|(a, _), (b, _)| a.as_bytes().cmp(b.as_bytes()));
+        for (peer_id, announcement) in healing {
+            if let Some(&peer) = self.clients_by_user_id.get(peer_id.as_bytes()) {
+                self.post(client, peer, Payload::Announcement(announcement));
+            }
+        }
+    }
+
+    /// Makes the posts due by now reach the boards, then polls every client.
+    pub fn step(&mut self) {
+        for (visible_at, post) in self.in_flight_posts() {
+            if visible_at > self.now_millis {
+                break;
+            }
+            self.land(post);
+        }
+        for index in 0..self.clients.len() {
+            self.poll(ClientId(index));
+        }
+    }
+
+    /// Moves time forward by `millis`, then runs a [`step`](Self::step).
+    pub fn advance(&mut self, millis: u128) {
+        self.now_millis += millis;
+        self.sync_clocks();
+        self.step();
+    }
+
+    /// Runs steps without faults until every post reached the boards and was read, moving time
+    /// forward to the arrival of the last post in flight.
+    pub fn settle(&mut self) {
+        let faults = std::mem::replace(&mut self.faults, Faults::NONE);
+        for _ in 0..MAX_SETTLE_ROUNDS {
+            if let Some((visible_at, _)) = self.in_flight_posts().last() {
+                self.now_millis = self.now_millis.max(*visible_at);
+                self.sync_clocks();
+            }
+            self.step();
+            if self.in_flight_posts().is_empty() {
+                break;
+            }
+        }
+        self.faults = faults;
+    }
+
+    /// Runs `steps` random steps.
+    ///
+    /// At each step, a random client requests a session with a random peer if neither has one,
+    /// or sends it a message if it can. Then a random client may restart with a new clock skew,
+    /// and time moves forward by up to 5 seconds.
+    pub fn run_random(&mut self, steps: usize) {
+        let client_count = self.clients.len();
+        if client_count < 2 {
+            return;
+        }
+        for _ in 0..steps {
+            let from = ClientId(self.dice.pick(client_count));
+            let to = ClientId((from.0 + 1 + self.dice.pick(client_count - 1)) % client_count);
+            let from_status = self.status(from, to);
+            let to_status = self.status(to, from);
+            if is_unrelated(&from_status) && is_unrelated(&to_status) {
+                self.connect(from, to);
+            } else if matches!(from_status, SessionStatus::Active) {
+                self.random_messages += 1;
+                let contents = format!("message {}", self.random_messages);
+                // only fails if the session is saturated
+                let _ = self.send(from, to, contents.as_bytes());
+            }
+
+            if self.dice.chance(self.faults.restart_rate) {
+                let client = ClientId(self.dice.pick(client_count));
+                self.restart(client);
+                let max_skew = self.faults.max_clock_skew_millis;
+                let skew = self.dice.below(2 * max_skew + 1) as i128 - max_skew as i128;
+                self.set_clock_skew(client, skew);
+            }
+
+            let millis = self.dice.below(MAX_RANDOM_STEP_MILLIS + 1);
+            self.advance(millis);
+        }
+    }
+
+    /// Settles the simulation and checks that the clients agree on their sessions.
+    ///
+    /// Once settled, any two clients must either both have a session with each other or both
+    /// have none. A message is then sent each way over every session, and every message that
+    /// was not dropped must have been received exactly once. The probe messages show up in the
+    /// inboxes.
+    ///
+    /// # Errors
+    ///
+    /// The violations detected so far, followed by the ones found by this check.
+    pub fn check_agreement(&mut self) -> Result<(), Vec<Violation>> {
+        let faults = std::mem::replace(&mut self.faults, Faults::NONE);
+        self.settle();
+
+        let mut violations = Vec::new();
+        let mut session_pairs = Vec::new();
+        for a in 0..self.clients.len() {
+            for b in a + 1..self.clients.len() {
+                let (a, b) = (ClientId(a), ClientId(b));
+                let a_status = self.status(a, b);
+                let b_status = self.status(b, a);
+                if has_session(&a_status) && has_session(&b_status) {
+                    session_pairs.push((a, b));
+                } else if !(is_unrelated(&a_status) && is_unrelated(&b_status)) {
+                    violations.push(Violation::StatusMismatch {
+                        client: self.name(a).to_string(),
+                        peer: self.name(b).to_string(),
+                        client_status: status_name(&a_status),
+                        peer_status: status_name(&b_status),
+                    });
+                }
+            }
+        }
+
+        // a saturated side can send once it has read the probe of its peer
+        let mut refused = Vec::new();
+        for &(a, b) in &session_pairs {
+            for (from, to) in [(a, b), (b, a)] {
+                if self.send(from, to, PROBE_MESSAGE).is_err() {
+                    refused.push((from, to));
+                }
+            }
+        }
+        self.settle();
+        for (from, to) in refused {
+            if let Err(error) = self.send(from, to, PROBE_MESSAGE) {
+                violations.push(Violation::SendRefused {
+                    client: self.name(from).to_string(),
+                    peer: self.name(to).to_string(),
+                    error,
+                });
+            }
+        }
+        self.settle();
+
+        let mut undelivered: BTreeMap<(ClientId, ClientId), usize> = BTreeMap::new();
+        for post in &self.posts {
+            if post.info.kind == PostKind::Message
+                && post.state == PostState::Landed
+                && !post.received
+            {
+                *undelivered
+                    .entry((post.info.to, post.info.from))
+                    .or_default() += 1;
+            }
+        }
+        for ((client, peer), count) in undelivered {
+            violations.push(Violation::Undelivered {
+                client: self.name(client).to_string(),
+                peer: self.name(peer).to_string(),
+                count,
+            });
+        }
+
+        self.faults = faults;
+        let mut all_violations = self.violations.clone();
+        all_violations.extend(violations);
+        if all_violations.is_empty() {
+            Ok(())
+        } else {
+            Err(all_violations)
+        }
+    }
+
+    /// Sends a post through the network, which may drop or delay it.
+    fn post(&mut self, from: ClientId, to: ClientId, payload: Payload) -> PostId {
+        let kind = match payload {
+            Payload::Announcement(_) => PostKind::Announcement,
+            Payload::Message { .. } => PostKind::Message,
+        };
+        let id = PostId(self.posts.len());
+        let state = if kind == PostKind::Message && self.dice.chance(self.faults.drop_rate) {
+            PostState::Dropped
+        } else {
+            let delay = self.dice.below(self.faults.max_delay_millis + 1);
+            PostState::InFlight {
+                visible_at: self.now_millis + delay,
+            }
+        };
+        self.posts.push(Post {
+            info: PostInfo { id, kind, from, to },
+            payload,
+            state,
+            received: false,
+        });
+        id
+    }
+
+    /// Returns the posts in flight with their arrival time, in order of arrival.
+    fn in_flight_posts(&self) -> Vec<(u128, PostId)> {
+        let mut in_flight: Vec<_> = self
+            .posts
+            .iter()
+            .filter_map(|post| match post.state {
+                PostState::InFlight { visible_at } => Some((visible_at, post.info.id)),
+                PostState::Dropped | PostState::Landed => None,
+            })
+            .collect();
+        in_flight.sort_unstable();
+        in_flight
+    }
+
+    /// Puts a post on its board.
+    fn land(&mut self, id: PostId) {
+        let post = &mut self.posts[id.0];
+        post.state = PostState::Landed;
+        match &post.payload {
+            Payload::Announcement(announcement) => {
+                self.announcement_board.post(announcement.clone());
+                self.announcement_posts.push(id);
+            }
+            Payload::Message { seeker, data, .. } => {
+                if !self.message_board.post(seeker.clone(), data.clone()) {
+                    self.violations.push(Violation::SeekerReused {
+                        client: self.clients[post.info.from.0].name.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    /// Sets the clock of every client to the simulation time, offset by its skew.
+    fn sync_clocks(&self) {
+        for client in &self.clients {
+            client.clock.set(
+                self.now_millis
+                    .saturating_add_signed(client.clock_skew_millis),
+            );
+        }
+    }
+
+    fn read_announcements(&mut self, client: ClientId) {
+        let counter = self.clients[client.0].announcement_counter;
+        let announcements = self.announcement_board.read_from(counter).to_vec();
+        self.clients[client.0].announcement_counter = counter + announcements.len();
+        for (offset, announcement) in announcements.iter().enumerate() {
+            let info = self.posts[self.announcement_posts[counter + offset].0].info;
+            self.read_announcement(client, announcement, info, false);
+        }
+    }
+
+    /// Feeds an announcement to a client, which answers session requests.
+    ///
+    /// `replayed` announcements were already read, so their rejection is expected.
+    fn read_announcement(
+        &mut self,
+        client: ClientId,
+        announcement: &[u8],
+        info: PostInfo,
+        replayed: bool,
+    ) {
+        let addressed = info.to == client;
+        let client_state = &mut self.clients[client.0];
+        let result = client_state.manager.feed_incoming_announcement(
+            announcement,
+            &client_state.public_keys,
+            &client_state.secret_keys,
+        );
+        match result {
+            Ok(_) if !addressed => self.violations.push(Violation::UnexpectedAnnouncement {
+                client: client_state.name.clone(),
+            }),
+            Ok(_) => {
+                if matches!(self.status(client, info.from), SessionStatus::PeerRequested) {
+                    self.connect(client, info.from);
+                }
+            }
+            Err(_) if !addressed || replayed => {}
+            // announcements from the same peer can be read out of order
+            Err(AnnouncementError::Outdated) => {}
+            Err(error) => self.violations.push(Violation::AnnouncementRejected {
+                client: client_state.name.clone(),
+                peer: self.clients[info.from.0].name.clone(),
+                error,
+            }),
+        }
+    }
+
+    /// Feeds the messages found under the seekers of a client, until none is accepted.
+    fn read_messages(&mut self, client: ClientId) {
+        loop {
+            let seekers = self.clients[client.0].manager.get_message_board_read_keys();
+            let mut accepted = false;
+            for (seeker, data) in self.message_board.fetch(&seekers) {
+                accepted |= self.read_message(client, &seeker, &data);
+            }
+            if !accepted {
+                break;
+            }
+        }
+    }
+
+    /// Feeds a message to a client, and returns `true` if it was accepted.
+    fn read_message(&mut self, client: ClientId, seeker: &[u8], data: &[u8]) -> bool {
+        let client_state = &mut self.clients[client.0];
+        let result = client_state.manager.feed_incoming_message_board_read(
+            seeker,
+            data,
+            &client_state.secret_keys,
+        );
+        let post = self.message_posts.get(seeker).copied();
+
+        let output = match result {
+            Ok(output) => output,
+            // already read, or not for this client
+            Err(SessionError::UnknownSeeker) => return false,
+            Err(error) => {
+                let violation = Violation::MessageRejected {
+                    client: self.name(client).to_string(),
+                    peer: post.map_or_else(
+                        || "unknown".to_string(),
+                        |post| self.name(self.posts[post.0].info.from).to_string(),
+                    ),
+                    error,
+                };
+                self.violations.push(violation);
+                return false;
+            }
+        };
+
+        // the message must be the one its peer posted under this seeker, read for the first time
+        let sender = self.clients_by_user_id.get(&output.user_id).copied();
+        let genuine = post.is_some_and(|post| {
+            let post = &self.posts[post.0];
+            let contents_match = matches!(
+                &post.payload,
+                Payload::Message { contents, .. } if *contents == output.message
+            );
+            post.info.to == client
+                && Some(post.info.from) == sender
+                && contents_match
+                && !post.received
+        });
+        let Some(post) = post.filter(|_| genuine) else {
+            let violation = Violation::UnexpectedMessage {
+                client: self.name(client).to_string(),
+                peer: sender
+                    .map_or_else(|| "unknown".to_string(), |peer| self.name(peer).to_string()),
+            };
+            self.violations.push(violation);
+            return true;
+        };
+
+        let post = &mut self.posts[post.0];
+        post.received = true;
+        if !output.message.is_empty() {
+            self.clients[client.0]
+                .inbox
+                .push((post.info.from, output.message.clone()));
+        }
+        true
+    }
+}
+
+/// Returns `true` if the status is an established session.
+fn has_session(status: &SessionStatus) -> bool {
+    matches!(status, SessionStatus::Active | SessionStatus::Saturated)
+}
+
+/// Returns `true` if the status shows no session and no pending request.
+fn is_unrelated(status: &SessionStatus) -> bool {
+    matches!(
+        status,
+        SessionStatus::UnknownPeer | SessionStatus::NoSession
+    )
+}
+
+fn status_name(status: &SessionStatus) -> &'static str {
+    match status {
+        SessionStatus::Active => "active",
+        SessionStatus::UnknownPeer => "unknown peer",
+        SessionStatus::NoSession => "no session",
+        SessionStatus::PeerRequested => "requested by the peer",
+        SessionStatus::SelfRequested => "requested by us",
+        SessionStatus::Killed => "killed",
+        SessionStatus::Saturated => "saturated",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn two_clients() -> (Simulation, ClientId, ClientId) {
+        let mut simulation = Simulation::new(0);
+        let alice = simulation.add_client("alice");
+        let bob = simulation.add_client("bob");
+        simulation.connect(alice, bob);
+        simulation.settle();
+        (simulation, alice, bob)
+    }
+
+    fn inbox_contents(simulation: &Simulation, client: ClientId) -> Vec<&[u8]> {
+        simulation
+            .inbox(client)
+            .iter()
+            .map(|(_, contents)| contents.as_slice())
+            .collect()
+    }
+
+    #[test]
+    fn test_session_request_is_answered() {
+        let (mut simulation, alice, bob) = two_clients();
+        assert!(matches!(
+            simulation.status(alice, bob),
+            SessionStatus::Active
+        ));
+        assert!(matches!(
+            simulation.status(bob, alice),
+            SessionStatus::Active
+        ));
+        assert_eq!(simulation.announcement_board().len(), 2);
+
+        simulation.send(alice, bob, b"hello bob").unwrap();
+        simulation.send(bob, alice, b"hello alice").unwrap();
+        simulation.step();
+        assert_eq!(simulation.inbox(bob), [(alice, b"hello bob".to_vec())]);
+        assert_eq!(simulation.inbox(alice), [(bob, b"hello alice".to_vec())]);
+        assert_eq!(simulation.check_agreement(), Ok(()));
+    }
+
+    #[test]
+    fn test_reordered_and_replayed_messages_are_read_once() {
+        let (mut simulation, alice, bob) = two_clients();
+        let posts: Vec<PostId> = [b"one", b"two", b"six"]
+            .iter()
+            .map(|contents| simulation.send(alice, bob, *contents).unwrap())
+            .collect();
+        assert_eq!(simulation.in_flight().len(), 3);
+
+        for &post in posts.iter().rev() {
+            assert!(simulation.deliver(post));
+            simulation.poll(bob);
+        }
+        for &post in &posts {
+            assert!(simulation.replay(post));
+        }
+        assert_eq!(
+            inbox_contents(&simulation, bob),
+            [&b"six"[..], b"two", b"one"]
+        );
+        assert!(simulation.violations().is_empty());
+        assert_eq!(simulation.check_agreement(), Ok(()));
+    }
+
+    #[test]
+    fn test_dropped_message_is_skipped() {
+        let (mut simulation, alice, bob) = two_clients();
+        let first = simulation.send(alice, bob, b"first").unwrap();
+        let lost = simulation.send(alice, bob, b"lost").unwrap();
+        let last = simulation.send(alice, bob, b"last").unwrap();
+        assert!(simulation.drop_post(lost));
+        assert!(!simulation.deliver(lost));
+
+        simulation.deliver(first);
+        simulation.deliver(last);
+        simulation.poll(bob);
+        assert_eq!(inbox_contents(&simulation, bob), [&b"first"[..], b"last"]);
+        assert_eq!(simulation.check_agreement(), Ok(()));
+    }
+
+    #[test]
+    fn test_restarted_clients_resume_their_sessions() {
+        let (mut simulation, alice, bob) = two_clients();
+        simulation.send(alice, bob, b"before restart").unwrap();
+        simulation.restart(bob);
+        simulation.step();
+        simulation.restart(alice);
+        simulation.send(bob, alice, b"after restart").unwrap();
+        simulation.advance(1_000);
+
+        assert_eq!(inbox_contents(&simulation, bob), [&b"before restart"[..]]);
+        assert_eq!(inbox_contents(&simulation, alice), [&b"after restart"[..]]);
+        assert_eq!(simulation.check_agreement(), Ok(()));
+    }
+
+    #[test]
+    fn test_keep_alive_messages_are_sent() {
+        let (mut simulation, alice, bob) = two_clients();
+        let posts_before = simulation.posts.len();
+        simulation.advance(61_000);
+        simulation.settle();
+
+        // both sides sent a keep-alive, which is not listed in the inboxes
+        assert_eq!(simulation.posts.len(), posts_before + 2);
+        assert!(simulation.inbox(alice).is_empty());
+        assert!(simulation.inbox(bob).is_empty());
+        assert_eq!(simulation.check_agreement(), Ok(()));
+    }
+
+    #[test]
+    fn test_clock_skew_beyond_tolerance_is_detected() {
+        let (mut simulation, alice, bob) = two_clients();
+        simulation.set_clock_skew(alice, 10_000);
+        simulation.send(alice, bob, b"from the future").unwrap();
+        simulation.step();
+
+        let rejected = Violation::MessageRejected {
+            client: "bob".to_string(),
+            peer: "alice".to_string(),
+            error: SessionError::MessageTooFarInFuture,
+        };
+        assert_eq!(simulation.violations(), std::slice::from_ref(&rejected));
+
+        // the session stays open on both sides
+        simulation.settle();
+        assert!(matches!(
+            simulation.status(alice, bob),
+            SessionStatus::Active
+        ));
+        assert!(matches!(
+            simulation.status(bob, alice),
+            SessionStatus::Active
+        ));
+        simulation.send(bob, alice, b"still open").unwrap();
+        simulation.settle();
+        assert!(inbox_contents(&simulation, alice).contains(&b"still open".as_slice()));
+
+        // but the message from the future is not delivered while it is ahead of Bob's clock
+        let violations = simulation.check_agreement().unwrap_err();
+        assert_eq!(violations[0], rejected);
+        assert!(
+            violations
+                .iter()
+                .any(|violation| matches!(violation, Violation::Undelivered { .. }))
+        );
+
+        // it was not consumed by the session, so it is read once within bounds
+        simulation.advance(10_000);
+        simulation.settle();
+        assert!(inbox_contents(&simulation, bob).contains(&b"from the future".as_slice()));
+    }
+
+    #[test]
+    fn test_simultaneous_requests_agree() {
+        let mut simulation = Simulation::new(0);
+        let alice = simulation.add_client("alice");
+        let bob = simulation.add_client("bob");
+        simulation.connect(alice, bob);
+        simulation.connect(bob, alice);
+        simulation.settle();
+
+        // each request answers the other
+        assert_eq!(simulation.announcement_board().len(), 2);
+        assert!(matches!(
+            simulation.status(alice, bob),
+            SessionStatus::Active
+        ));
+        assert!(matches!(
+            simulation.status(bob, alice),
+            SessionStatus::Active
+        ));
+        assert_eq!(simulation.check_agreement(), Ok(()));
+    }
+
+    #[test]
+    fn test_repeated_requests_agree_in_any_reading_order() {
+        for reversed in [false, true] {
+            let mut simulation = Simulation::new(0);
+            let alice = simulation.add_client("alice");
+            let bob = simulation.add_client("bob");
+
+            // within the same millisecond
+            let mut requests = [
+                simulation.connect(alice, bob),
+                simulation.connect(alice, bob),
+            ];
+            if reversed {
+                requests.reverse();
+            }
+            for post in requests {
+                assert!(simulation.deliver(post));
+            }
+            simulation.poll(bob);
+
+            assert_eq!(simulation.check_agreement(), Ok(()), "reversed {reversed}");
+        }
+    }
+
+    #[test]
+    fn test_request_repeated_before_reading_the_answer_agrees() {
+        let mut simulation = Simulation::new(0);
+        let alice = simulation.add_client("alice");
+        let bob = simulation.add_client("bob");
+        simulation.connect(alice, bob);
+        let bob_request = simulation.connect(bob, alice);
+
+        // Alice reads the request of Bob, then requests again before Bob read her first one
+        assert!(simulation.deliver(bob_request));
+        simulation.poll(alice);
+        simulation.connect(alice, bob);
+        simulation.settle();
+
+        assert_eq!(simulation.check_agreement(), Ok(()));
+    }
+
+    #[test]
+    fn test_random_simultaneous_requests_agree() {
+        for seed in 0..8 {
+            let mut simulation = Simulation::new(seed);
+            let alice = simulation.add_client("alice");
+            let bob = simulation.add_client("bob");
+            simulation.set_faults(Faults {
+                duplicate_rate: 0.2,
+                max_delay_millis: 3_000,
+                ..Faults::NONE
+            });
+            for _ in 0..20 {
+                for (from, to) in [(alice, bob), (bob, alice)] {
+                    if simulation.dice.chance(0.5) {
+                        simulation.connect(from, to);
+                    }
+                }
+                // often within the same millisecond
+                let millis = 500 * simulation.dice.below(3);
+                simulation.advance(millis);
+            }
+            assert_eq!(simulation.check_agreement(), Ok(()), "seed {seed}");
+        }
+    }
+
+    #[test]
+    fn test_random_runs_stay_in_agreement() {
+        for seed in 0..4 {
+            let mut simulation = Simulation::new(seed);
+            for name in ["alice", "bob", "carol", "dave"] {
+                simulation.add_client(name);
+            }
+            simulation.set_faults(Faults {
+                drop_rate: 0.05,
+                duplicate_rate: 0.2,
+                max_delay_millis: 3_000,
+                restart_rate: 0.05,
+                max_clock_skew_millis: 1_000,
+            });
+            simulation.run_random(150);
+            assert_eq!(simulation.check_agreement(), Ok(()), "seed {seed}");
+        }
+    }
+}
@@ -0,0 +1,111 @@
+//! Disagreements detected between simulated clients.
+
+use sessions::{AnnouncementError, SessionError};
+use std::fmt;
+
+/// A broken expectation detected while running a simulation.
+///
+/// Clients are designated by the names they were added with.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Violation {
+    /// An announcement addressed to the client was rejected for another reason than being
+    /// outdated.
+    AnnouncementRejected {
+        client: String,
+        peer: String,
+        error: AnnouncementError,
+    },
+    /// An announcement that was not addressed to the client was accepted.
+    UnexpectedAnnouncement { client: String },
+    /// A message read under one of the client's seekers was rejected.
+    ///
+    /// Its session is closed, unless only the message timestamp was out of bounds.
+    MessageRejected {
+        client: String,
+        peer: String,
+        error: SessionError,
+    },
+    /// A message was accepted although it was not sent by this peer to the client, or was
+    /// already received.
+    UnexpectedMessage { client: String, peer: String },
+    /// A message was posted under a seeker that already holds one.
+    SeekerReused { client: String },
+    /// The session manager could not be restored from its encrypted blob.
+    RestartFailed { client: String, error: SessionError },
+    /// Once settled, the client and the peer disagree on the state of their session.
+    StatusMismatch {
+        client: String,
+        peer: String,
+        client_status: &'static str,
+        peer_status: &'static str,
+    },
+    /// A message could not be sent over a session both sides consider active.
+    SendRefused {
+        client: String,
+        peer: String,
+        error: SessionError,
+    },
+    /// Messages that were not dropped were still not received once settled.
+    Undelivered {
+        client: String,
+        peer: String,
+        count: usize,
+    },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AnnouncementRejected {
+                client,
+                peer,
+                error,
+            } => write!(f, "{client} rejected the announcement of {peer}: {error}"),
+            Self::UnexpectedAnnouncement { client } => {
+                write!(
+                    f,
+                    "{client} accepted an announcement addressed to someone else"
+                )
+            }
+            Self::MessageRejected {
+                client,
+                peer,
+                error,
+            } => write!(f, "{client} rejected a message from {peer}: {error}"),
+            Self::UnexpectedMessage { client, peer } => {
+                write!(
+                    f,
+                    "{client} accepted a message {peer} did not send it, or accepted it twice"
+                )
+            }
+            Self::SeekerReused { client } => {
+                write!(f, "{client} posted a message under a seeker already in use")
+            }
+            Self::RestartFailed { client, error } => {
+                write!(f, "{client} could not restore its session manager: {error}")
+            }
+            Self::StatusMismatch {
+                client,
+                peer,
+                client_status,
+                peer_status,
+            } => write!(
+                f,
+                "{client} sees its session with {peer} as {client_status}, \
+                 while {peer} sees it as {peer_status}"
+            ),
+            Self::SendRefused {
+                client,
+                peer,
+                error,
+            } => write!(f, "{client} could not send a message to {peer}: {error}"),
+            Self::Undelivered {
+                client,
+                peer,
+                count,
+            } => write!(f, "{count} messages from {peer} never reached {client}"),
+        }
+    }
+}
+
+impl std::error::Error for Violation {}